@@ -0,0 +1,189 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use crate::flamegraph::FlameGraph;
+
+/// Change of the sizes of a path between two flamegraphs.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PathGrowth {
+    /// Keys from the root of the flamegraph, e.g. type and field names.
+    pub path: Vec<String>,
+    /// Size of the path excluding its children, before and after.
+    pub self_before: usize,
+    pub self_after: usize,
+    /// Size of the path including its children, before and after.
+    pub total_before: usize,
+    pub total_after: usize,
+}
+
+impl PathGrowth {
+    pub fn self_growth(&self) -> isize {
+        self.self_after as isize - self.self_before as isize
+    }
+
+    pub fn total_growth(&self) -> isize {
+        self.total_after as isize - self.total_before as isize
+    }
+}
+
+/// Comparison of two flamegraphs, e.g. of the same process at different times.
+///
+/// # Example
+///
+/// ```
+/// use allocative::FlameGraphBuilder;
+/// use allocative::FlameGraphDiff;
+///
+/// let mut before = FlameGraphBuilder::default();
+/// before.visit_root(&vec![1u32; 10]);
+/// let mut after = FlameGraphBuilder::default();
+/// after.visit_root(&vec![1u32; 20]);
+///
+/// let diff = FlameGraphDiff::new(before.finish().tree(), after.finish().tree());
+/// let differential_flamegraph_src = diff.flamegraph();
+/// let largest_growth = &diff.growth()[0];
+/// ```
+#[derive(Debug)]
+pub struct FlameGraphDiff {
+    flamegraph: String,
+    growth: Vec<PathGrowth>,
+}
+
+impl FlameGraphDiff {
+    pub fn new(before: &FlameGraph, after: &FlameGraph) -> FlameGraphDiff {
+        let mut flamegraph = String::new();
+        let mut growth = Vec::new();
+        diff(
+            Some(before),
+            Some(after),
+            &mut Vec::new(),
+            &mut flamegraph,
+            &mut growth,
+        );
+        growth.sort_by_key(|x| (Reverse(x.self_growth()), Reverse(x.total_growth())));
+        FlameGraphDiff { flamegraph, growth }
+    }
+
+    /// Differential flamegraph source, with the sizes before and after for each stack,
+    /// can be fed to `flamegraph.pl` or `inferno`.
+    pub fn flamegraph(&self) -> String {
+        self.flamegraph.clone()
+    }
+
+    /// Paths whose size changed, largest growth of size excluding children first.
+    pub fn growth(&self) -> &[PathGrowth] {
+        &self.growth
+    }
+}
+
+/// Compare the children of two nodes, returning the total sizes of the nodes.
+fn diff<'a>(
+    before: Option<&'a FlameGraph>,
+    after: Option<&'a FlameGraph>,
+    stack: &mut Vec<&'a str>,
+    flamegraph: &mut String,
+    growth: &mut Vec<PathGrowth>,
+) -> (usize, usize) {
+    let keys: BTreeSet<&str> = before
+        .into_iter()
+        .chain(after)
+        .flat_map(|x| x.children().map(|(k, _)| k))
+        .collect();
+
+    let self_before = before.map_or(0, FlameGraph::self_size);
+    let self_after = after.map_or(0, FlameGraph::self_size);
+    if !stack.is_empty() && (self_before != 0 || self_after != 0) {
+        writeln!(
+            flamegraph,
+            "{} {} {}",
+            stack.join(";"),
+            self_before,
+            self_after
+        )
+        .unwrap();
+    }
+
+    let mut total_before = self_before;
+    let mut total_after = self_after;
+    for key in keys {
+        stack.push(key);
+        let (b, a) = diff(
+            before.and_then(|x| x.child(key)),
+            after.and_then(|x| x.child(key)),
+            stack,
+            flamegraph,
+            growth,
+        );
+        stack.pop().unwrap();
+        total_before += b;
+        total_after += a;
+    }
+
+    if !stack.is_empty() && (self_before != self_after || total_before != total_after) {
+        growth.push(PathGrowth {
+            path: stack.iter().map(|x| (*x).to_owned()).collect(),
+            self_before,
+            self_after,
+            total_before,
+            total_after,
+        });
+    }
+    (total_before, total_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::FlameGraphDiff;
+    use crate::flamegraph::FlameGraph;
+
+    fn flamegraph(stacks: &[(&[&str], usize)]) -> FlameGraph {
+        let mut res = FlameGraph::default();
+        for &(stack, size) in stacks {
+            res.add_stack(stack, size);
+        }
+        res
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = flamegraph(&[(&["a"], 10), (&["a", "b"], 5), (&["c"], 7)]);
+        let after = flamegraph(&[(&["a"], 10), (&["a", "b"], 25), (&["d", "e"], 3)]);
+        let diff = FlameGraphDiff::new(&before, &after);
+
+        assert_eq!(
+            "\
+            a 10 10\n\
+            a;b 5 25\n\
+            c 7 0\n\
+            d;e 0 3\n\
+            ",
+            diff.flamegraph()
+        );
+
+        let growth = diff
+            .growth()
+            .iter()
+            .map(|x| (x.path.join(";"), x.self_growth(), x.total_growth()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("a;b".to_owned(), 20, 20),
+                ("d;e".to_owned(), 3, 3),
+                ("a".to_owned(), 0, 20),
+                ("d".to_owned(), 0, 3),
+                ("c".to_owned(), -7, -7),
+            ],
+            growth
+        );
+    }
+}
@@ -10,6 +10,8 @@
 use std::cell::Ref;
 use std::cell::RefCell;
 use std::cell::RefMut;
+use std::collections::hash_map;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Write as _;
@@ -27,6 +29,7 @@ use crate::Allocative;
 pub struct FlameGraphOutput {
     flamegraph: String,
     warnings: String,
+    tree: FlameGraph,
 }
 
 impl FlameGraphOutput {
@@ -39,6 +42,136 @@ impl FlameGraphOutput {
     pub fn warnings(&self) -> String {
         self.warnings.clone()
     }
+
+    /// The flamegraph as a tree, which can be serialized or compared with another one.
+    pub fn tree(&self) -> &FlameGraph {
+        &self.tree
+    }
+}
+
+/// A finished flamegraph: a tree keyed by type and field names, where each node has the size
+/// of the memory attributed to it, excluding its children.
+///
+/// With the `serde` feature, it serializes to a list of stacks with their sizes, like the
+/// flamegraph source.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct FlameGraph {
+    self_size: usize,
+    children: BTreeMap<String, FlameGraph>,
+}
+
+impl FlameGraph {
+    /// Size of this node, excluding its children.
+    pub fn self_size(&self) -> usize {
+        self.self_size
+    }
+
+    /// Size of this node including its children, i.e. the memory retained by this path.
+    pub fn total_size(&self) -> usize {
+        self.self_size
+            + self
+                .children
+                .values()
+                .map(FlameGraph::total_size)
+                .sum::<usize>()
+    }
+
+    pub fn child(&self, key: &str) -> Option<&FlameGraph> {
+        self.children.get(key)
+    }
+
+    /// Child nodes, sorted by key.
+    pub fn children(&self) -> impl Iterator<Item = (&str, &FlameGraph)> {
+        self.children.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Add `size` to the node at the end of `stack`, creating it if needed.
+    pub(crate) fn add_stack(&mut self, stack: &[impl AsRef<str>], size: usize) {
+        match stack.split_first() {
+            None => self.self_size += size,
+            Some((first, rest)) => self
+                .children
+                .entry(first.as_ref().to_owned())
+                .or_default()
+                .add_stack(rest, size),
+        }
+    }
+
+    /// Call `f` with the stack and the self size of every node except the root.
+    pub(crate) fn visit_stacks<'a>(
+        &'a self,
+        stack: &mut Vec<&'a str>,
+        f: &mut impl FnMut(&[&'a str], usize),
+    ) {
+        for (key, child) in &self.children {
+            stack.push(key);
+            f(stack, child.self_size);
+            child.visit_stacks(stack, f);
+            stack.pop().unwrap();
+        }
+    }
+
+    /// Flamegraph source, same as [`FlameGraphOutput::flamegraph`].
+    pub fn write_flame_graph(&self) -> String {
+        let mut w = String::new();
+        self.visit_stacks(&mut Vec::new(), &mut |stack, size| {
+            if size > 0 {
+                writeln!(w, "{} {}", stack.join(";"), size).unwrap();
+            }
+        });
+        w
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct FlameGraphStack<S> {
+    stack: Vec<S>,
+    size: usize,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FlameGraph {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // A flat list rather than a nested structure, so that deep trees don't hit recursion
+        // limits of deserializers.
+        let mut stacks = Vec::new();
+        self.visit_stacks(&mut Vec::new(), &mut |stack, size| {
+            if size > 0 {
+                stacks.push(FlameGraphStack {
+                    stack: stack.to_vec(),
+                    size,
+                });
+            }
+        });
+        serializer.collect_seq(stacks)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FlameGraph {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stacks = Vec::<FlameGraphStack<String>>::deserialize(deserializer)?;
+        let mut res = FlameGraph::default();
+        for stack in stacks {
+            res.add_stack(&stack.stack, stack.size);
+        }
+        Ok(res)
+    }
+}
+
+/// How the data behind shared pointers (e.g. `Arc`), which may be reachable from several
+/// places, is attributed in the flamegraph.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum SharedAttribution {
+    /// The data is placed at the root of the flamegraph, and the pointers only account for
+    /// their own size.
+    #[default]
+    Root,
+    /// The data is placed under the first pointer visited to it.
+    FirstVisitor,
+    /// The data is split evenly between all the pointers visited to it.
+    Proportional,
 }
 
 #[derive(Default, Eq, PartialEq, Clone, Debug)]
@@ -49,7 +182,8 @@ struct TreeData {
     /// Size excluding children. This value is output to flamegraph for given stack.
     /// Can be negative if nodes provides sizes incorrectly.
     rem_size: isize,
-    /// Whether this node is `Box` something.
+    /// Whether the children of this node are out of line, e.g. for `Box` something,
+    /// so they are not included in `size`.
     unique: bool,
     /// Child nodes.
     children: HashMap<Key, Tree>,
@@ -120,6 +254,60 @@ impl Tree {
         self.write_flame_graph(&[], &mut s, &mut warnings);
         (s, warnings)
     }
+
+    fn to_tree(&self, is_root: bool) -> FlameGraph {
+        let borrow = self.borrow();
+        FlameGraph {
+            // Like in the flamegraph source, the root and negative sizes are ignored.
+            self_size: if is_root {
+                0
+            } else {
+                borrow.rem_size.max(0) as usize
+            },
+            children: borrow
+                .children
+                .iter()
+                .map(|(key, child)| ((**key).to_owned(), child.to_tree(false)))
+                .collect(),
+        }
+    }
+
+    fn deep_clone(&self) -> Tree {
+        let borrow = self.borrow();
+        Tree(Rc::new(RefCell::new(TreeData {
+            size: borrow.size,
+            rem_size: borrow.rem_size,
+            unique: borrow.unique,
+            children: borrow
+                .children
+                .iter()
+                .map(|(key, child)| (key.clone(), child.deep_clone()))
+                .collect(),
+        })))
+    }
+
+    /// Add share `i` out of `n` of the sizes of `other` to this tree.
+    fn add_share(&self, other: &Tree, i: usize, n: usize) {
+        let other = other.borrow();
+        {
+            let mut this = self.borrow_mut();
+            // Spread the remainder over the first shares, so the shares add up to the size.
+            this.size += other.size / n + usize::from(i < other.size % n);
+            this.unique |= other.unique;
+        }
+        for (key, child) in &other.children {
+            self.child(key.clone()).add_share(child, i, n);
+        }
+    }
+}
+
+/// Data behind a shared pointer, attributed proportionally.
+#[derive(Debug)]
+struct SharedData {
+    /// Tree of the data, with an empty root.
+    tree: Tree,
+    /// The pointer nodes which visited the data.
+    visitors: Vec<Tree>,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -174,12 +362,19 @@ impl TreeStack {
 /// [inferno]: https://github.com/jonhoo/inferno
 #[derive(Debug)]
 pub struct FlameGraphBuilder {
+    /// How to attribute the data behind shared pointers.
+    shared_attribution: SharedAttribution,
     /// Visited shared pointers.
     visited_shared: HashSet<*const ()>,
+    /// Data behind shared pointers, with `SharedAttribution::Proportional`.
+    shared_data: HashMap<*const (), SharedData>,
+    /// Shared pointers in the order their data was visited completely,
+    /// with `SharedAttribution::Proportional`.
+    completed_shared: Vec<*const ()>,
     /// Current node we are processing in `Visitor`.
     current: TreeStack,
-    /// Previous stack when entering shared pointer.
-    shared: Vec<TreeStack>,
+    /// Previous stack when entering shared pointer, and the pointer.
+    shared: Vec<(TreeStack, *const ())>,
     /// Data root.
     root: Tree,
     /// Is root visitor created?
@@ -188,9 +383,19 @@ pub struct FlameGraphBuilder {
 
 impl Default for FlameGraphBuilder {
     fn default() -> FlameGraphBuilder {
+        FlameGraphBuilder::with_shared_attribution(SharedAttribution::default())
+    }
+}
+
+impl FlameGraphBuilder {
+    /// Create a builder which attributes the data behind shared pointers as specified.
+    pub fn with_shared_attribution(shared_attribution: SharedAttribution) -> FlameGraphBuilder {
         let root = Tree::default();
         FlameGraphBuilder {
+            shared_attribution,
             visited_shared: HashSet::new(),
+            shared_data: HashMap::new(),
+            completed_shared: Vec::new(),
             current: TreeStack {
                 stack: Vec::new(),
                 tree: root.clone(),
@@ -200,9 +405,7 @@ impl Default for FlameGraphBuilder {
             entered_root_visitor: false,
         }
     }
-}
 
-impl FlameGraphBuilder {
     pub fn root_visitor(&mut self) -> Visitor {
         assert!(!self.entered_root_visitor);
         self.entered_root_visitor = true;
@@ -227,20 +430,23 @@ impl FlameGraphBuilder {
         }
     }
 
-    fn finish_impl(self) -> Tree {
+    fn finish_impl(mut self) -> Tree {
         assert!(self.shared.is_empty());
         assert!(self.current.stack.is_empty());
         assert!(!self.entered_root_visitor);
+        self.attribute_shared();
         Self::update_sizes(self.root.clone());
         self.root
     }
 
     /// Finish building the flamegraph.
     pub fn finish(self) -> FlameGraphOutput {
-        let (flamegraph, warnings) = self.finish_impl().to_flame_graph();
+        let root = self.finish_impl();
+        let (flamegraph, warnings) = root.to_flame_graph();
         FlameGraphOutput {
             flamegraph,
             warnings,
+            tree: root.to_tree(true),
         }
     }
 
@@ -249,6 +455,25 @@ impl FlameGraphBuilder {
         self.finish().flamegraph
     }
 
+    /// Copy the data behind shared pointers to the pointers which visited it, with
+    /// `SharedAttribution::Proportional`.
+    fn attribute_shared(&mut self) {
+        // Data is complete after all the data it points to, so copying in this order copies
+        // nested shared data along with its container.
+        for ptr in mem::take(&mut self.completed_shared) {
+            let data = self
+                .shared_data
+                .remove(&ptr)
+                .expect("completed shared data must be recorded");
+            // Snapshot the data, in case it is visited from within itself.
+            let tree = data.tree.deep_clone();
+            let n = data.visitors.len();
+            for (i, visitor) in data.visitors.iter().enumerate() {
+                visitor.add_share(&tree, i, n);
+            }
+        }
+    }
+
     fn update_sizes(tree: Tree) {
         for child in tree.children() {
             Self::update_sizes(child);
@@ -270,9 +495,12 @@ impl FlameGraphBuilder {
 
         let up = self.current.up();
         if !up {
-            if let Some(mut shared) = self.shared.pop() {
+            if let Some((mut shared, ptr)) = self.shared.pop() {
                 assert!(shared.up());
                 self.current = shared;
+                if self.shared_attribution == SharedAttribution::Proportional {
+                    self.completed_shared.push(ptr);
+                }
             } else {
                 self.entered_root_visitor = false;
             }
@@ -299,21 +527,54 @@ impl VisitorImpl for FlameGraphBuilder {
         &mut self,
         name: Key,
         size: usize,
-        _ptr: *const (),
+        ptr: *const (),
         _parent: NodeKind,
     ) -> bool {
         self.current.down(name);
         self.current.tree.borrow_mut().size += size;
 
-        if !self.visited_shared.insert(_ptr) {
-            self.exit_impl();
-            return false;
-        }
+        let tree = match self.shared_attribution {
+            SharedAttribution::Root => {
+                if !self.visited_shared.insert(ptr) {
+                    self.exit_impl();
+                    return false;
+                }
+                self.root.clone()
+            }
+            SharedAttribution::FirstVisitor => {
+                self.current.tree.borrow_mut().unique = true;
+                if !self.visited_shared.insert(ptr) {
+                    self.exit_impl();
+                    return false;
+                }
+                // Descend from the pointer, like for unique pointers.
+                return true;
+            }
+            SharedAttribution::Proportional => {
+                self.current.tree.borrow_mut().unique = true;
+                let visitor = self.current.tree.clone();
+                match self.shared_data.entry(ptr) {
+                    hash_map::Entry::Occupied(mut e) => {
+                        e.get_mut().visitors.push(visitor);
+                        self.exit_impl();
+                        return false;
+                    }
+                    hash_map::Entry::Vacant(e) => {
+                        let tree = Tree::default();
+                        e.insert(SharedData {
+                            tree: tree.clone(),
+                            visitors: vec![visitor],
+                        });
+                        tree
+                    }
+                }
+            }
+        };
 
-        self.shared.push(mem::take(&mut self.current));
+        self.shared.push((mem::take(&mut self.current), ptr));
         self.current = TreeStack {
             stack: Vec::new(),
-            tree: self.root.clone(),
+            tree,
         };
         true
     }
@@ -339,6 +600,7 @@ impl VisitorImpl for FlameGraphBuilder {
 mod tests {
 
     use crate::flamegraph::FlameGraphBuilder;
+    use crate::flamegraph::SharedAttribution;
     use crate::flamegraph::Tree;
     use crate::key::Key;
 
@@ -432,6 +694,119 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_shared_first_visitor() {
+        let p = 10;
+
+        let mut fg = FlameGraphBuilder::with_shared_attribution(SharedAttribution::FirstVisitor);
+        let mut visitor = fg.root_visitor();
+
+        for _ in 0..2 {
+            let mut s = visitor.enter(Key::new("Struct"), 10);
+            s.visit_simple(Key::new("a"), 3);
+            {
+                let sh = s.enter_shared(Key::new("p"), 6, &p as *const i32 as *const ());
+                if let Some(mut sh) = sh {
+                    sh.visit_simple(Key::new("Shared"), 13);
+                    sh.exit();
+                }
+            }
+            s.exit();
+        }
+
+        visitor.exit();
+
+        let tree = fg.finish_impl();
+
+        assert_eq!(
+            "\
+            Struct 2\n\
+            Struct;a 6\n\
+            Struct;p 12\n\
+            Struct;p;Shared 13\n\
+        ",
+            tree.to_flame_graph().0,
+            "{:#?}",
+            tree,
+        );
+    }
+
+    #[test]
+    fn test_shared_proportional() {
+        let p = 10;
+        let q = 20;
+
+        let mut fg = FlameGraphBuilder::with_shared_attribution(SharedAttribution::Proportional);
+        let mut visitor = fg.root_visitor();
+
+        for name in ["A", "B"] {
+            let sh = visitor.enter_shared(Key::new(name), 8, &p as *const i32 as *const ());
+            if let Some(mut sh) = sh {
+                sh.visit_simple(Key::new("Shared"), 13);
+                // Nested shared data, also visited from the root.
+                let inner = sh.enter_shared(Key::new("q"), 8, &q as *const i32 as *const ());
+                if let Some(mut inner) = inner {
+                    inner.visit_simple(Key::new("Inner"), 4);
+                    inner.exit();
+                }
+                sh.exit();
+            }
+        }
+        assert!(
+            visitor
+                .enter_shared(Key::new("C"), 8, &q as *const i32 as *const ())
+                .is_none()
+        );
+
+        visitor.exit();
+
+        let tree = fg.finish_impl();
+
+        assert_eq!(
+            "\
+            A 8\n\
+            A;Shared 7\n\
+            A;q 4\n\
+            A;q;Inner 1\n\
+            B 8\n\
+            B;Shared 6\n\
+            B;q 4\n\
+            B;q;Inner 1\n\
+            C 8\n\
+            C;Inner 2\n\
+        ",
+            tree.to_flame_graph().0,
+            "{:#?}",
+            tree,
+        );
+    }
+
+    #[test]
+    fn test_tree() {
+        let mut fg = FlameGraphBuilder::default();
+        let mut visitor = fg.root_visitor();
+        let mut s = visitor.enter(Key::new("Struct"), 10);
+        s.visit_simple(Key::new("a"), 3);
+        let mut un = s.enter_unique(Key::new("p"), 6);
+        un.visit_simple(Key::new("x"), 13);
+        un.exit();
+        s.exit();
+        visitor.exit();
+
+        let output = fg.finish();
+        let tree = output.tree();
+        assert_eq!(output.flamegraph(), tree.write_flame_graph());
+        assert_eq!(23, tree.total_size());
+        let s = tree.child("Struct").unwrap();
+        assert_eq!(1, s.self_size());
+        assert_eq!(23, s.total_size());
+        assert_eq!(
+            vec!["a", "p"],
+            s.children().map(|(k, _)| k).collect::<Vec<_>>()
+        );
+        assert_eq!(19, s.child("p").unwrap().total_size());
+    }
+
     #[test]
     fn test_inline_children_too_large() {
         let mut fg = FlameGraphBuilder::default();
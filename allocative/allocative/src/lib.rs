@@ -14,7 +14,8 @@
 //!
 //! An object implementing [`Allocative`] trait is introspectable, and this crate
 //! provides two utilities to work with such objects:
-//! * [`FlameGraphBuilder`] to build a flame graph of object tree
+//! * [`FlameGraphBuilder`] to build a flame graph of object tree,
+//!    and [`FlameGraphDiff`] to compare two of them
 //! * [`size_of_unique_allocated_data`] provides estimation
 //!    of how much allocated memory the value holds
 //!
@@ -46,6 +47,7 @@
 #![deny(rustdoc::broken_intra_doc_links)]
 
 mod allocative_trait;
+mod diff;
 mod flamegraph;
 mod global_root;
 mod impls;
@@ -59,7 +61,12 @@ pub use allocative_derive::root;
 pub use allocative_derive::Allocative;
 
 pub use crate::allocative_trait::Allocative;
+pub use crate::diff::FlameGraphDiff;
+pub use crate::diff::PathGrowth;
+pub use crate::flamegraph::FlameGraph;
 pub use crate::flamegraph::FlameGraphBuilder;
+pub use crate::flamegraph::FlameGraphOutput;
+pub use crate::flamegraph::SharedAttribution;
 pub use crate::global_root::register_root;
 pub use crate::key::Key;
 pub use crate::size_of::size_of_unique_allocated_data;
@@ -0,0 +1,23 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The subset of Bazel's Build Event Protocol that Buck2 produces, and the `PublishBuildEvent`
+//! service used to upload it.
+
+pub mod build_event_stream {
+    tonic::include_proto!("build_event_stream");
+
+    /// The type URL of a `BuildEvent` packed into a `google.protobuf.Any`, as expected by
+    /// `PublishBuildEvent` implementations.
+    pub const BUILD_EVENT_TYPE_URL: &str = "type.googleapis.com/build_event_stream.BuildEvent";
+}
+
+pub mod google_devtools_build_v1 {
+    tonic::include_proto!("google.devtools.build.v1");
+}
@@ -110,6 +110,7 @@ impl ClientCommandContext {
         Ok(ClientContext {
             config_overrides: config_opts.config_overrides(arg_matches)?,
             target_platform: config_opts.target_platforms.clone().unwrap_or_default(),
+            modifiers: config_opts.modifiers.clone(),
             host_platform: match config_opts.host_platform_override() {
                 HostPlatformOverride::Default => GrpcHostPlatformOverride::DefaultPlatform,
                 HostPlatformOverride::Linux => GrpcHostPlatformOverride::Linux,
@@ -158,6 +159,7 @@ impl ClientCommandContext {
                 .to_owned(),
             config_overrides: Default::default(),
             target_platform: Default::default(),
+            modifiers: Vec::new(),
             host_platform: Default::default(),
             host_arch: Default::default(),
             oncall: Default::default(),
@@ -160,6 +160,14 @@ pub struct CommonBuildConfigurationOptions {
     )]
     pub target_platforms: Option<String>,
 
+    #[clap(
+        long = "modifier",
+        help = "Constraint value to apply on top of each target's platform (can be repeated)",
+        number_of_values = 1,
+        value_name = "CONSTRAINT"
+    )]
+    pub modifiers: Vec<String>,
+
     #[clap(long, ignore_case = true, value_name = "HOST", arg_enum)]
     fake_host: Option<HostPlatformOverride>,
 
@@ -273,6 +281,7 @@ impl CommonBuildConfigurationOptions {
             config_values: vec![],
             config_files: vec![],
             target_platforms: None,
+            modifiers: vec![],
             fake_host: None,
             fake_arch: None,
             oncall: None,
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::time::Duration;
+
 use tokio::io::AsyncReadExt;
 
 use crate::stdin::Stdin;
@@ -119,16 +121,46 @@ mod interactive_terminal {
 
 use interactive_terminal::InteractiveTerminal;
 
+const ESC: u8 = 0x1b;
+
+/// How long to wait for the rest of an escape sequence before treating `ESC` as a key on its own.
+/// Terminals send the whole sequence at once, so this only needs to cover scheduling delays.
+const ESCAPE_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// A keypress read from the console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleKey {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+}
+
+impl ConsoleKey {
+    /// Decode the final byte of a `ESC [` (CSI) sequence, ignoring any parameters. Sequences we
+    /// don't know about are reported as `Escape`, which is never harmful to the interactive console.
+    fn from_csi(c: u8) -> Self {
+        match c {
+            b'A' => ConsoleKey::Up,
+            b'B' => ConsoleKey::Down,
+            b'C' => ConsoleKey::Right,
+            b'D' => ConsoleKey::Left,
+            _ => ConsoleKey::Escape,
+        }
+    }
+}
+
 #[async_trait::async_trait]
 pub trait ConsoleInteraction: Send + Sync {
-    async fn char(&mut self) -> anyhow::Result<char>;
+    async fn key(&mut self) -> anyhow::Result<ConsoleKey>;
 }
 
-#[async_trait::async_trait]
-impl<'a> ConsoleInteraction for ConsoleInteractionStream<'a> {
-    async fn char(&mut self) -> anyhow::Result<char> {
+impl<'a> ConsoleInteractionStream<'a> {
+    async fn byte(&mut self) -> anyhow::Result<u8> {
         match self.stdin.read_u8().await {
-            Ok(c) => Ok(c.into()),
+            Ok(c) => Ok(c),
             // NOTE: An EOF here would be reported as "unexpected" because we asked for a u8.
             Err(e)
                 if e.kind() == std::io::ErrorKind::UnexpectedEof
@@ -141,11 +173,40 @@ impl<'a> ConsoleInteraction for ConsoleInteractionStream<'a> {
     }
 }
 
+#[async_trait::async_trait]
+impl<'a> ConsoleInteraction for ConsoleInteractionStream<'a> {
+    async fn key(&mut self) -> anyhow::Result<ConsoleKey> {
+        let c = self.byte().await?;
+        if c != ESC {
+            return Ok(ConsoleKey::Char(c.into()));
+        }
+
+        let next = match tokio::time::timeout(ESCAPE_SEQUENCE_TIMEOUT, self.byte()).await {
+            Ok(c) => c?,
+            Err(_) => return Ok(ConsoleKey::Escape),
+        };
+        if next != b'[' {
+            return Ok(ConsoleKey::Escape);
+        }
+
+        // Parameter and intermediate bytes (e.g. the `1;5` in `ESC [ 1 ; 5 A`) are skipped, the
+        // sequence ends with a final byte in `0x40..=0x7E`.
+        loop {
+            let c = self.byte().await?;
+            match c {
+                0x40..=0x7E => return Ok(ConsoleKey::from_csi(c)),
+                0x20..=0x3F => {}
+                _ => return Ok(ConsoleKey::Escape),
+            }
+        }
+    }
+}
+
 pub struct NoopConsoleInteraction;
 
 #[async_trait::async_trait]
 impl ConsoleInteraction for NoopConsoleInteraction {
-    async fn char(&mut self) -> anyhow::Result<char> {
+    async fn key(&mut self) -> anyhow::Result<ConsoleKey> {
         futures::future::pending().await
     }
 }
@@ -29,6 +29,7 @@ use crate::client_cpu_tracker::ClientCpuTracker;
 use crate::command_outcome::CommandOutcome;
 use crate::console_interaction_stream::ConsoleInteraction;
 use crate::console_interaction_stream::ConsoleInteractionStream;
+use crate::console_interaction_stream::ConsoleKey;
 use crate::console_interaction_stream::NoopConsoleInteraction;
 use crate::file_tailer::FileTailer;
 use crate::file_tailer::StdoutOrStderr;
@@ -172,8 +173,8 @@ impl EventsCtx {
                     Some(event) = tailers.stream.recv() => {
                         self.dispatch_tailer_event(event).await?;
                     }
-                    c = console_interaction.char() => {
-                        self.handle_console_interaction(c?).await?;
+                    key = console_interaction.key() => {
+                        self.handle_console_interaction(key?).await?;
                     }
                     tick = self.ticker.tick() => {
                         self.tick(&tick).await?;
@@ -329,8 +330,8 @@ impl EventsCtx {
             .await
     }
 
-    async fn handle_console_interaction(&mut self, c: char) -> anyhow::Result<()> {
-        self.handle_subscribers(|subscriber| subscriber.handle_console_interaction(c))
+    async fn handle_console_interaction(&mut self, key: ConsoleKey) -> anyhow::Result<()> {
+        self.handle_subscribers(|subscriber| subscriber.handle_console_interaction(key))
             .await
     }
 
@@ -27,7 +27,7 @@ impl StreamValue {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Copy)]
 pub enum StreamValueRef<'a> {
     Result(&'a CommandResult),
     Event(&'a buck2_data::BuckEvent),
@@ -354,6 +354,7 @@ pub(crate) fn format_test_result(
         status,
         duration,
         details,
+        attempt,
         ..
     } = test_result;
     let status = TestStatus::try_from(*status)?;
@@ -376,8 +377,13 @@ pub(crate) fn format_test_result(
         TestStatus::UNKNOWN => Span::new_styled("? Unknown".to_owned().cyan()),
         TestStatus::RERUN => Span::new_styled("↻ Rerun".to_owned().cyan()),
         TestStatus::LISTING_FAILED => Span::new_styled("⚠ Listing failed".to_owned().red()),
+        TestStatus::FLAKY => Span::new_styled("⚑ Flaky".to_owned().yellow()),
     }?;
     let mut base = Line::from_iter([prefix, Span::new_unstyled(format!(": {}", name,))?]);
+    if *attempt > 1 {
+        base.0
+            .push(Span::new_unstyled(format!(" (attempt {})", attempt))?);
+    }
     if let Some(duration) = duration {
         if let Ok(duration) = Duration::try_from(duration.clone()) {
             base.0.push(Span::new_unstyled(format!(
@@ -18,6 +18,8 @@ use futures::StreamExt;
 use gazebo::prelude::VecExt;
 
 use crate::client_ctx::ClientCommandContext;
+use crate::subscribers::event_log::index::index_path;
+use crate::subscribers::event_log::is_event_log_name;
 use crate::subscribers::event_log::Encoding;
 use crate::subscribers::event_log::EventLogErrors;
 
@@ -47,6 +49,9 @@ pub(crate) async fn remove_old_logs(logdir: &AbsNormPath) {
         futures::stream::iter(logfiles.into_iter().rev().skip(N_LOGS_RETAINED - 1))
             .then(async move |file| {
                 // The oldest logs might be open from another concurrent build, so suppress error.
+                tokio::fs::remove_file(index_path(file.as_path()))
+                    .await
+                    .ok();
                 tokio::fs::remove_file(file).await.ok()
             })
             .collect::<Vec<_>>()
@@ -57,7 +62,10 @@ pub(crate) async fn remove_old_logs(logdir: &AbsNormPath) {
 /// List logs in logdir, ordered from oldest to newest.
 pub fn get_local_logs(logdir: &AbsNormPath) -> anyhow::Result<Vec<AbsNormPathBuf>> {
     let dir = fs_util::read_dir(logdir)?;
-    let mut logfiles = dir.filter_map(Result::ok).collect::<Vec<_>>();
+    let mut logfiles = dir
+        .filter_map(Result::ok)
+        .filter(|entry| is_event_log_name(&entry.file_name().to_string_lossy()))
+        .collect::<Vec<_>>();
     logfiles.sort_by_cached_key(|file| {
         // Return Unix epoch if unable to get creation time.
         if let Ok(metadata) = file.metadata() {
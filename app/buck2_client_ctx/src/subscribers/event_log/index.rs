@@ -0,0 +1,608 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! An index of an event log, written next to it, so that we can answer questions about a command
+//! without reading its whole log.
+//!
+//! Indexed logs are written in blocks, each compressed independently, so that reading can start at
+//! the beginning of any block. The index records where blocks start, which blocks mention which
+//! targets, actions and spans, and a summary of the command.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use cli_proto::command_result;
+use cli_proto::CommandResult;
+use gazebo::variants::VariantName;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::stream_value::StreamValueRef;
+use crate::what_ran::WhatRanRelevantAction;
+
+/// The extension added to the name of an event log to get the name of its index.
+const INDEX_EXTENSION: &str = ".idx";
+
+/// Changed when the format of the index changes. Indexes with another version are ignored.
+const INDEX_VERSION: u32 = 2;
+
+/// How many bytes of events, before compression, we write in a block. A query reads at least one
+/// block, so this is a tradeoff between the size of the index and how much we read to answer
+/// queries.
+const BLOCK_SIZE: u64 = 1 << 20;
+
+/// What a command did, recorded in the index of its event log.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct IndexSummary {
+    pub trace_id: Option<String>,
+    pub command: Option<String>,
+    pub start_time: Option<SystemTime>,
+    pub end_time: Option<SystemTime>,
+    /// Whether the command succeeded, if it finished.
+    pub success: Option<bool>,
+    pub events: u64,
+    pub spans: u64,
+    pub loads: u64,
+    pub analyses: u64,
+    pub actions: u64,
+    pub failed_actions: u64,
+    pub cached_actions: u64,
+}
+
+impl IndexSummary {
+    pub fn duration(&self) -> Option<Duration> {
+        self.end_time?.duration_since(self.start_time?).ok()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Block {
+    /// Where the block starts in the log file.
+    pub(crate) offset: u64,
+    /// The smallest and largest span ids of the events in this block and of their parents.
+    pub(crate) span_ids: Option<(u64, u64)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventLogIndex {
+    version: u32,
+    pub summary: IndexSummary,
+    pub(crate) blocks: Vec<Block>,
+    /// The blocks with events about each target, by unconfigured label.
+    pub(crate) targets: BTreeMap<String, Vec<u32>>,
+    /// The blocks with events about each action, by action key.
+    pub(crate) action_keys: BTreeMap<String, Vec<u32>>,
+    /// The blocks with events about running commands, see `is_execution`.
+    pub(crate) executions: Vec<u32>,
+    /// The spans that were still open when the log ended, and the block that starts each of them.
+    pub(crate) open_spans: BTreeMap<u64, u32>,
+}
+
+impl EventLogIndex {
+    /// Reads the index of the event log at `log`. Returns `None` if the log has no index, or if it
+    /// was written by a version of Buck2 with another index format.
+    pub(crate) async fn read(log: &Path) -> anyhow::Result<Option<EventLogIndex>> {
+        let path = index_path(log);
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Error reading event log index `{}`", path.display())
+                });
+            }
+        };
+        let index: EventLogIndex = serde_json::from_slice(&data)
+            .with_context(|| format!("Invalid event log index `{}`", path.display()))?;
+        Ok(if index.version == INDEX_VERSION {
+            Some(index)
+        } else {
+            None
+        })
+    }
+
+    pub(crate) async fn write(&self, log: &Path) -> anyhow::Result<()> {
+        let path = index_path(log);
+        tokio::fs::write(&path, serde_json::to_vec(self)?)
+            .await
+            .with_context(|| format!("Error writing event log index `{}`", path.display()))
+    }
+}
+
+/// The path of the index of the event log at `log`.
+pub(crate) fn index_path(log: &Path) -> PathBuf {
+    let mut path = log.as_os_str().to_owned();
+    path.push(INDEX_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Builds the index of an event log as it is written.
+pub(crate) struct EventLogIndexer {
+    index: EventLogIndex,
+    /// The bytes written in the current block so far.
+    block_size: u64,
+    max_block_size: u64,
+}
+
+impl EventLogIndexer {
+    pub(crate) fn new() -> Self {
+        Self::with_block_size(BLOCK_SIZE)
+    }
+
+    pub(crate) fn with_block_size(max_block_size: u64) -> Self {
+        Self {
+            index: EventLogIndex {
+                version: INDEX_VERSION,
+                summary: IndexSummary::default(),
+                blocks: vec![Block {
+                    offset: 0,
+                    span_ids: None,
+                }],
+                targets: BTreeMap::new(),
+                action_keys: BTreeMap::new(),
+                executions: Vec::new(),
+                open_spans: BTreeMap::new(),
+            },
+            block_size: 0,
+            max_block_size,
+        }
+    }
+
+    /// Records a value written to the current block.
+    pub(crate) fn record(&mut self, value: StreamValueRef<'_>) {
+        match value {
+            StreamValueRef::Event(event) => self.record_event(event),
+            StreamValueRef::Result(result) => self.record_result(result),
+        }
+    }
+
+    fn record_event(&mut self, event: &buck2_data::BuckEvent) {
+        let block = self.index.blocks.len() - 1;
+        let summary = &mut self.index.summary;
+
+        summary.events += 1;
+        if summary.trace_id.is_none() {
+            summary.trace_id = Some(event.trace_id.clone());
+        }
+        if let Some(timestamp) = event
+            .timestamp
+            .clone()
+            .and_then(|t| SystemTime::try_from(t).ok())
+        {
+            summary.start_time.get_or_insert(timestamp);
+            summary.end_time = Some(timestamp);
+        }
+        summary_of_event(summary, event);
+
+        match &event.data {
+            Some(buck2_data::buck_event::Data::SpanStart(..)) => {
+                self.index.open_spans.insert(event.span_id, block as u32);
+            }
+            Some(buck2_data::buck_event::Data::SpanEnd(..)) => {
+                self.index.open_spans.remove(&event.span_id);
+            }
+            _ => {}
+        }
+
+        for span_id in [event.span_id, event.parent_id] {
+            if span_id == 0 {
+                continue;
+            }
+            let span_ids = &mut self.index.blocks[block].span_ids;
+            *span_ids = Some(match *span_ids {
+                Some((min, max)) => (min.min(span_id), max.max(span_id)),
+                None => (span_id, span_id),
+            });
+        }
+
+        let keys = EventKeys::of(event);
+        if let Some(target) = keys.target {
+            add_block(&mut self.index.targets, target, block);
+        }
+        if let Some(action_key) = keys.action_key {
+            add_block(&mut self.index.action_keys, action_key.to_owned(), block);
+        }
+        if is_execution(event) && self.index.executions.last() != Some(&(block as u32)) {
+            self.index.executions.push(block as u32);
+        }
+    }
+
+    fn record_result(&mut self, result: &CommandResult) {
+        // Commands that fail early might have no end event.
+        if self.index.summary.success.is_none() {
+            self.index.summary.success = Some(!matches!(
+                result.result,
+                Some(command_result::Result::Error(..))
+            ));
+        }
+    }
+
+    /// Records that `len` bytes were written to the current block. Returns whether the block is
+    /// full, in which case the caller should start another one.
+    pub(crate) fn record_bytes(&mut self, len: usize) -> bool {
+        self.block_size += len as u64;
+        self.block_size >= self.max_block_size
+    }
+
+    /// Starts a new block, which starts at `offset` in the log file.
+    pub(crate) fn start_block(&mut self, offset: u64) {
+        self.index.blocks.push(Block {
+            offset,
+            span_ids: None,
+        });
+        self.block_size = 0;
+    }
+
+    pub(crate) fn finish(self) -> EventLogIndex {
+        self.index
+    }
+}
+
+fn summary_of_event(summary: &mut IndexSummary, event: &buck2_data::BuckEvent) {
+    use buck2_data::buck_event::Data;
+
+    match &event.data {
+        Some(Data::SpanStart(start)) => {
+            use buck2_data::span_start_event::Data;
+
+            summary.spans += 1;
+            match &start.data {
+                Some(Data::Command(command)) => {
+                    summary.command = command
+                        .data
+                        .as_ref()
+                        .map(|data| data.variant_name().to_lowercase());
+                }
+                Some(Data::Load(..)) => summary.loads += 1,
+                Some(Data::Analysis(..)) => summary.analyses += 1,
+                Some(Data::ActionExecution(..)) => summary.actions += 1,
+                _ => {}
+            }
+        }
+        Some(Data::SpanEnd(end)) => {
+            use buck2_data::span_end_event::Data;
+
+            match &end.data {
+                Some(Data::Command(command)) => summary.success = Some(command.is_success),
+                Some(Data::ActionExecution(action)) => {
+                    if action.failed {
+                        summary.failed_actions += 1;
+                    }
+                    if action.execution_kind == buck2_data::ActionExecutionKind::ActionCache as i32
+                    {
+                        summary.cached_actions += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+fn add_block(map: &mut BTreeMap<String, Vec<u32>>, key: String, block: usize) {
+    let blocks = map.entry(key).or_default();
+    if blocks.last() != Some(&(block as u32)) {
+        blocks.push(block as u32);
+    }
+}
+
+/// Whether an event is about running a command: the start of an action or a test, or of one of
+/// their executor stages. These are the events that `buck2 log what-ran` looks at.
+fn is_execution(event: &buck2_data::BuckEvent) -> bool {
+    use buck2_data::buck_event::Data;
+
+    match &event.data {
+        Some(data @ Data::SpanStart(start)) => {
+            matches!(
+                start.data,
+                Some(buck2_data::span_start_event::Data::ExecutorStage(..))
+            ) || WhatRanRelevantAction::from_buck_data(data).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// The target and action that an event is about, if any.
+struct EventKeys<'a> {
+    /// The unconfigured label of the target.
+    target: Option<String>,
+    action_key: Option<&'a str>,
+}
+
+impl<'a> EventKeys<'a> {
+    fn of(event: &'a buck2_data::BuckEvent) -> Self {
+        use buck2_data::buck_event::Data;
+
+        let (target, action_key) = match &event.data {
+            Some(Data::SpanStart(start)) => {
+                use buck2_data::span_start_event::Data;
+
+                match &start.data {
+                    Some(Data::Analysis(analysis)) => (analysis.target.as_ref(), None),
+                    Some(Data::ActionExecution(action)) => {
+                        action_target_and_key(action.key.as_ref())
+                    }
+                    Some(Data::FinalMaterialization(materialization)) => action_target_and_key(
+                        materialization
+                            .artifact
+                            .as_ref()
+                            .and_then(|a| a.key.as_ref()),
+                    ),
+                    _ => (None, None),
+                }
+            }
+            Some(Data::SpanEnd(end)) => {
+                use buck2_data::span_end_event::Data;
+
+                match &end.data {
+                    Some(Data::Analysis(analysis)) => (analysis.target.as_ref(), None),
+                    Some(Data::ActionExecution(action)) => {
+                        action_target_and_key(action.key.as_ref())
+                    }
+                    _ => (None, None),
+                }
+            }
+            _ => (None, None),
+        };
+
+        Self {
+            target: target
+                .and_then(|target| target.label.as_ref())
+                .map(|label| format!("{}:{}", label.package, label.name)),
+            action_key,
+        }
+    }
+}
+
+fn action_target_and_key(
+    key: Option<&buck2_data::ActionKey>,
+) -> (Option<&buck2_data::ConfiguredTargetLabel>, Option<&str>) {
+    let key = match key {
+        Some(key) => key,
+        None => return (None, None),
+    };
+    let target = match &key.owner {
+        Some(buck2_data::action_key::Owner::TargetLabel(target))
+        | Some(buck2_data::action_key::Owner::TestTargetLabel(target)) => Some(target),
+        _ => None,
+    };
+    (target, Some(key.key.as_str()).filter(|key| !key.is_empty()))
+}
+
+/// Selects events of a log. Each criterion that is set must match.
+#[derive(Clone, Debug, Default)]
+pub struct EventLogQuery {
+    /// Events of this span, or whose parent is this span.
+    pub span_id: Option<u64>,
+    /// Events about this target, by unconfigured label.
+    pub target: Option<String>,
+    /// Events about this action, by action key.
+    pub action_key: Option<String>,
+    /// Only events about running commands.
+    pub executions: bool,
+}
+
+impl EventLogQuery {
+    pub fn is_empty(&self) -> bool {
+        self.span_id.is_none()
+            && self.target.is_none()
+            && self.action_key.is_none()
+            && !self.executions
+    }
+
+    pub(crate) fn matches(&self, event: &buck2_data::BuckEvent) -> bool {
+        if let Some(span_id) = self.span_id {
+            if event.span_id != span_id && event.parent_id != span_id {
+                return false;
+            }
+        }
+        if self.target.is_some() || self.action_key.is_some() {
+            let keys = EventKeys::of(event);
+            if self.target.is_some() && keys.target != self.target {
+                return false;
+            }
+            if self.action_key.is_some() && keys.action_key != self.action_key.as_deref() {
+                return false;
+            }
+        }
+        if self.executions && !is_execution(event) {
+            return false;
+        }
+        true
+    }
+
+    /// The blocks that might have events matching this query, in order.
+    pub(crate) fn blocks(&self, index: &EventLogIndex) -> Vec<usize> {
+        let keyed = |map: &BTreeMap<String, Vec<u32>>, key: &str| {
+            map.get(key).map_or_else(Vec::new, |blocks| {
+                blocks.iter().map(|b| *b as usize).collect()
+            })
+        };
+
+        let mut candidates = (0..index.blocks.len()).collect::<Vec<_>>();
+        if let Some(span_id) = self.span_id {
+            candidates.retain(|block| {
+                index.blocks[*block]
+                    .span_ids
+                    .map_or(false, |(min, max)| min <= span_id && span_id <= max)
+            });
+        }
+        if let Some(target) = &self.target {
+            let blocks = keyed(&index.targets, target);
+            candidates.retain(|block| blocks.binary_search(block).is_ok());
+        }
+        if let Some(action_key) = &self.action_key {
+            let blocks = keyed(&index.action_keys, action_key);
+            candidates.retain(|block| blocks.binary_search(block).is_ok());
+        }
+        if self.executions {
+            candidates.retain(|block| index.executions.binary_search(&(*block as u32)).is_ok());
+        }
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn event(span_id: u64, data: buck2_data::buck_event::Data) -> buck2_data::BuckEvent {
+        buck2_data::BuckEvent {
+            timestamp: Some(SystemTime::now().into()),
+            trace_id: "trace".to_owned(),
+            span_id,
+            parent_id: 0,
+            data: Some(data),
+        }
+    }
+
+    fn action(span_id: u64, name: &str, key: &str) -> buck2_data::BuckEvent {
+        event(
+            span_id,
+            buck2_data::SpanStartEvent {
+                data: Some(
+                    buck2_data::ActionExecutionStart {
+                        key: Some(buck2_data::ActionKey {
+                            owner: Some(buck2_data::action_key::Owner::TargetLabel(
+                                buck2_data::ConfiguredTargetLabel {
+                                    label: Some(buck2_data::TargetLabel {
+                                        package: "root//foo".to_owned(),
+                                        name: name.to_owned(),
+                                    }),
+                                    configuration: None,
+                                    execution_configuration: None,
+                                },
+                            )),
+                            key: key.to_owned(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        )
+    }
+
+    #[test]
+    fn test_index_and_query() {
+        let mut indexer = EventLogIndexer::new();
+        indexer.record(StreamValueRef::Event(&event(
+            1,
+            buck2_data::SpanStartEvent {
+                data: Some(
+                    buck2_data::CommandStart {
+                        metadata: HashMap::new(),
+                        data: Some(buck2_data::BuildCommandStart {}.into()),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        )));
+        indexer.record(StreamValueRef::Event(&action(2, "bar", "bar_key")));
+        assert!(indexer.record_bytes(BLOCK_SIZE as usize));
+        indexer.start_block(100);
+        indexer.record(StreamValueRef::Event(&action(3, "baz", "baz_key")));
+        indexer.record(StreamValueRef::Event(&event(
+            2,
+            buck2_data::SpanEndEvent::default().into(),
+        )));
+        indexer.record(StreamValueRef::Result(&CommandResult {
+            result: Some(command_result::Result::Error(Default::default())),
+        }));
+        let index = indexer.finish();
+
+        assert_eq!(index.summary.command.as_deref(), Some("build"));
+        assert_eq!(index.summary.trace_id.as_deref(), Some("trace"));
+        assert_eq!(index.summary.events, 4);
+        assert_eq!(index.summary.spans, 3);
+        assert_eq!(index.summary.actions, 2);
+        assert_eq!(index.summary.success, Some(false));
+        assert_eq!(index.blocks.len(), 2);
+        assert_eq!(index.blocks[1].offset, 100);
+        assert_eq!(index.blocks[0].span_ids, Some((1, 2)));
+        assert_eq!(index.executions, vec![0, 1]);
+        assert_eq!(
+            index
+                .open_spans
+                .iter()
+                .map(|(s, b)| (*s, *b))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (3, 1)]
+        );
+
+        let query = |query: EventLogQuery| query.blocks(&index);
+        assert_eq!(query(EventLogQuery::default()), vec![0, 1]);
+        assert_eq!(
+            query(EventLogQuery {
+                target: Some("root//foo:baz".to_owned()),
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            query(EventLogQuery {
+                action_key: Some("bar_key".to_owned()),
+                ..Default::default()
+            }),
+            vec![0]
+        );
+        assert_eq!(
+            query(EventLogQuery {
+                span_id: Some(3),
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            query(EventLogQuery {
+                target: Some("root//foo:qux".to_owned()),
+                ..Default::default()
+            }),
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            query(EventLogQuery {
+                executions: true,
+                ..Default::default()
+            }),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let event = action(3, "baz", "baz_key");
+        assert!(
+            EventLogQuery {
+                target: Some("root//foo:baz".to_owned()),
+                action_key: Some("baz_key".to_owned()),
+                span_id: Some(3),
+                executions: true,
+            }
+            .matches(&event)
+        );
+        assert!(
+            !EventLogQuery {
+                target: Some("root//foo:bar".to_owned()),
+                ..Default::default()
+            }
+            .matches(&event)
+        );
+    }
+}
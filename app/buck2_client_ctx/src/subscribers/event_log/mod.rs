@@ -10,9 +10,12 @@
 #![allow(clippy::needless_return)] // FIXME?
 
 pub mod file_names;
+pub mod index;
 pub mod upload;
 
+use std::collections::HashSet;
 use std::io::Cursor;
+use std::io::SeekFrom;
 use std::mem;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -26,6 +29,7 @@ use async_compression::tokio::write::ZstdEncoder;
 use async_trait::async_trait;
 use buck2_core::env_helper::EnvHelper;
 use buck2_core::fs::async_fs_util;
+use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::abs_path::AbsPathBuf;
 use buck2_core::fs::working_dir::WorkingDir;
@@ -36,6 +40,7 @@ use buck2_events::BuckEvent;
 use bytes::BytesMut;
 use cli_proto::*;
 use futures::future::Future;
+use futures::stream::BoxStream;
 use futures::stream::Stream;
 use futures::stream::TryStreamExt;
 use futures::FutureExt;
@@ -46,9 +51,12 @@ use prost::Message;
 use serde::Deserialize;
 use serde::Serialize;
 use thiserror::Error;
+use tokio::fs::File;
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncBufReadExt;
 use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWrite;
 use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader;
@@ -61,6 +69,10 @@ use crate::stream_value::StreamValue;
 use crate::stream_value::StreamValueRef;
 use crate::subscribers::event_log::file_names::get_logfile_name;
 use crate::subscribers::event_log::file_names::remove_old_logs;
+use crate::subscribers::event_log::index::EventLogIndex;
+use crate::subscribers::event_log::index::EventLogIndexer;
+use crate::subscribers::event_log::index::EventLogQuery;
+use crate::subscribers::event_log::index::IndexSummary;
 use crate::subscribers::event_log::upload::log_upload;
 use crate::subscribers::event_log::upload::LogUploadError;
 use crate::subscribers::subscriber::EventSubscriber;
@@ -77,6 +89,8 @@ enum EventLogErrors {
     EndOfFile(String),
     #[error("No event log available for {idx}th last command (have latest {num_logfiles})")]
     RecentIndexOutOfBounds { idx: usize, num_logfiles: usize },
+    #[error("Cannot rewrite event log `{0}` in place, write it to another path")]
+    RewriteInPlace(String),
 }
 
 #[derive(Copy, Clone, Dupe, Debug)]
@@ -141,9 +155,72 @@ const KNOWN_ENCODINGS: &[Encoding] = &[
     Encoding::PROTO_ZSTD,
 ];
 
-type EventLogWriter = Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>;
+/// Whether `name` is the name of an event log, as opposed to e.g. its index.
+pub(crate) fn is_event_log_name(name: &str) -> bool {
+    KNOWN_ENCODINGS
+        .iter()
+        .flat_map(|encoding| encoding.extensions)
+        .any(|extension| name.ends_with(extension))
+}
+
 type EventLogReader = Box<dyn AsyncRead + Send + Sync + Unpin + 'static>;
 
+/// An event log being written. Compressed logs can be written in blocks that are compressed
+/// independently, so that reading can start at the beginning of any block.
+enum EventLogWriter {
+    Uncompressed(File),
+    Gzip(GzipEncoder<File>),
+    Zstd(ZstdEncoder<File>),
+}
+
+impl EventLogWriter {
+    fn new(file: File, compression: Compression) -> Self {
+        match compression {
+            Compression::None => Self::Uncompressed(file),
+            Compression::Gzip => Self::Gzip(GzipEncoder::with_quality(
+                file,
+                async_compression::Level::Fastest,
+            )),
+            Compression::Zstd => Self::Zstd(ZstdEncoder::with_quality(
+                file,
+                async_compression::Level::Default,
+            )),
+        }
+    }
+
+    fn writer(&mut self) -> &mut (dyn AsyncWrite + Send + Sync + Unpin) {
+        match self {
+            Self::Uncompressed(file) => file,
+            Self::Gzip(encoder) => encoder,
+            Self::Zstd(encoder) => encoder,
+        }
+    }
+
+    /// Ends the current block and starts a new one. Returns the offset of the new block in the
+    /// file.
+    async fn start_block(&mut self) -> anyhow::Result<u64> {
+        // Shutting down an encoder finishes its stream, and only flushes the file, so we can write
+        // the next block to it.
+        let (file, compression) = match self {
+            Self::Uncompressed(file) => {
+                file.flush().await?;
+                return Ok(file.metadata().await?.len());
+            }
+            Self::Gzip(encoder) => {
+                encoder.shutdown().await?;
+                (encoder.get_ref().try_clone().await?, Compression::Gzip)
+            }
+            Self::Zstd(encoder) => {
+                encoder.shutdown().await?;
+                (encoder.get_ref().try_clone().await?, Compression::Zstd)
+            }
+        };
+        let offset = file.metadata().await?.len();
+        *self = Self::new(file, compression);
+        Ok(offset)
+    }
+}
+
 #[derive(Error, Debug)]
 enum EventLogInferenceError {
     #[error("Event log at path {} has no filename", .0.display())]
@@ -226,11 +303,7 @@ impl EventLogPathBuf {
         let invocation = serde_json::from_str::<Invocation>(&header)
             .with_context(|| format!("Invalid header: {}", header.trim_end()))?;
 
-        let events = LinesStream::new(log_lines).map(|line| {
-            let line = line.context("Error reading next line")?;
-            serde_json::from_str::<StreamValue>(&line)
-                .with_context(|| format!("Invalid line: {}", line.trim_end()))
-        });
+        let events = LinesStream::new(log_lines).map(parse_json_line);
 
         Ok((invocation, events.boxed()))
     }
@@ -261,13 +334,7 @@ impl EventLogPathBuf {
                 Frame::Invocation(_) => {
                     Err(anyhow::anyhow!("Expected StreamValue, found Invocation"))
                 }
-                Frame::Value(val) => match val.progress {
-                    Some(command_progress::Progress::Event(event)) => Ok(StreamValue::Event(event)),
-                    Some(command_progress::Progress::Result(result)) => {
-                        Ok(StreamValue::Result(result))
-                    }
-                    None => return Err(anyhow::anyhow!("Event type not recognized")),
-                },
+                Frame::Value(val) => stream_value(val),
             }
         });
 
@@ -292,13 +359,200 @@ impl EventLogPathBuf {
         );
 
         let file = async_fs_util::open(&self.path).await?;
-        let file = match self.encoding.compression {
-            Compression::None => box file as EventLogReader,
-            Compression::Gzip => box GzipDecoder::new(BufReader::new(file)) as EventLogReader,
-            Compression::Zstd => box ZstdDecoder::new(BufReader::new(file)) as EventLogReader,
+        Ok(self.decompress(box file))
+    }
+
+    /// Decompresses `reader`, which may consist of multiple blocks compressed independently.
+    fn decompress(&self, reader: EventLogReader) -> EventLogReader {
+        match self.encoding.compression {
+            Compression::None => reader,
+            Compression::Gzip => {
+                let mut decoder = GzipDecoder::new(BufReader::new(reader));
+                decoder.multiple_members(true);
+                box decoder
+            }
+            Compression::Zstd => {
+                let mut decoder = ZstdDecoder::new(BufReader::new(reader));
+                decoder.multiple_members(true);
+                box decoder
+            }
+        }
+    }
+
+    /// Reads the index of this log, if it has one.
+    pub async fn read_index(&self) -> anyhow::Result<Option<EventLogIndex>> {
+        EventLogIndex::read(&self.path).await
+    }
+
+    async fn open_block(
+        &self,
+        index: &EventLogIndex,
+        block: usize,
+    ) -> anyhow::Result<EventLogReader> {
+        let start = index.blocks[block].offset;
+        let mut file = async_fs_util::open(&self.path).await?;
+        file.seek(SeekFrom::Start(start))
+            .await
+            .with_context(|| format!("Error seeking in `{}`", self.path.display()))?;
+        let reader = match index.blocks.get(block + 1) {
+            Some(next) => box file.take(next.offset - start) as EventLogReader,
+            None => box file as EventLogReader,
+        };
+        Ok(self.decompress(reader))
+    }
+
+    /// The values in a block of this log.
+    async fn unpack_block(
+        &self,
+        index: &EventLogIndex,
+        block: usize,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<StreamValue>>> {
+        let reader = self.open_block(index, block).await?;
+        // The first block starts with the invocation.
+        let first = block == 0;
+
+        Ok(match self.encoding.mode {
+            LogMode::Json => LinesStream::new(BufReader::new(reader).lines())
+                .skip(if first { 1 } else { 0 })
+                .map(parse_json_line)
+                .boxed(),
+            LogMode::Protobuf => FramedRead::new(
+                reader,
+                EventLogDecoder {
+                    saw_invocation: !first,
+                },
+            )
+            .try_filter_map(|frame| {
+                futures::future::ready(match frame {
+                    Frame::Invocation(_) => Ok(None),
+                    Frame::Value(val) => stream_value(val).map(Some),
+                })
+            })
+            .boxed(),
+        })
+    }
+
+    /// The values in `blocks` of this log, in order.
+    fn unpack_blocks(
+        &self,
+        index: EventLogIndex,
+        blocks: Vec<usize>,
+    ) -> BoxStream<'static, anyhow::Result<StreamValue>> {
+        let log = self.clone();
+        let index = Arc::new(index);
+        futures::stream::iter(blocks)
+            .then(move |block| {
+                let log = log.clone();
+                let index = index.dupe();
+                async move { log.unpack_block(&index, block).await }
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    /// The start events of the spans that were still open when this log ended, according to its
+    /// index. Parents are started before their children.
+    pub fn open_spans(
+        &self,
+        index: EventLogIndex,
+    ) -> BoxStream<'static, anyhow::Result<buck2_data::BuckEvent>> {
+        let mut blocks = index
+            .open_spans
+            .values()
+            .map(|block| *block as usize)
+            .collect::<Vec<_>>();
+        blocks.sort_unstable();
+        blocks.dedup();
+        let open_spans = index.open_spans.keys().copied().collect::<HashSet<_>>();
+
+        self.unpack_blocks(index, blocks)
+            .try_filter_map(move |value| {
+                futures::future::ready(Ok(match value {
+                    StreamValue::Event(event)
+                        if open_spans.contains(&event.span_id)
+                            && matches!(
+                                event.data,
+                                Some(buck2_data::buck_event::Data::SpanStart(..))
+                            ) =>
+                    {
+                        Some(event)
+                    }
+                    _ => None,
+                }))
+            })
+            .boxed()
+    }
+
+    /// The events of this log that match `query`. If the log has an index, we only read the blocks
+    /// that might have matching events.
+    pub async fn query(
+        &self,
+        query: EventLogQuery,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<buck2_data::BuckEvent>>> {
+        let values = match self.read_index().await? {
+            Some(index) if !query.is_empty() => {
+                let blocks = query.blocks(&index);
+                self.unpack_blocks(index, blocks)
+            }
+            _ => match self.encoding.mode {
+                LogMode::Json => self.unpack_stream_json().await?.1,
+                LogMode::Protobuf => self.unpack_stream_protobuf().await?.1,
+            },
         };
 
-        Ok(file)
+        Ok(values
+            .try_filter_map(move |value| {
+                futures::future::ready(Ok(match value {
+                    StreamValue::Event(event) if query.matches(&event) => Some(event),
+                    _ => None,
+                }))
+            })
+            .boxed())
+    }
+
+    /// The summary of the command that this log is for, from the index of the log if it has one,
+    /// otherwise from the whole log.
+    pub async fn read_summary(&self) -> anyhow::Result<IndexSummary> {
+        if let Some(index) = self.read_index().await? {
+            return Ok(index.summary);
+        }
+
+        let (_invocation, mut events) = self.unpack_stream().await?;
+        let mut indexer = EventLogIndexer::new();
+        while let Some(value) = events.try_next().await? {
+            indexer.record(value.as_ref());
+        }
+        Ok(indexer.finish().summary)
+    }
+
+    /// Rewrites this log to `output`, in blocks, and writes an index next to it. The encoding of
+    /// the output is inferred from its extension. Any existing log at `output` is replaced.
+    pub async fn write_indexed(&self, output: AbsPathBuf) -> anyhow::Result<()> {
+        let output = EventLogPathBuf::infer(output)?;
+        if fs_util::try_exists(&output.path)?
+            && fs_util::canonicalize(&output.path)? == fs_util::canonicalize(&self.path)?
+        {
+            return Err(anyhow::anyhow!(EventLogErrors::RewriteInPlace(
+                self.path.display().to_string()
+            )));
+        }
+        // We append to the log as we write it, so start from an empty file, and don't leave an
+        // index of the previous log around if we fail.
+        fs_util::remove_all(&output.path)?;
+        fs_util::remove_all(index::index_path(&output.path))?;
+
+        let trace_id = self.get_summary().await?.trace_id;
+        let (invocation, mut events) = self.unpack_stream().await?;
+
+        let mut log_file = open_event_log_for_writing(output, trace_id, true).await?;
+        let mut buf = Vec::new();
+        log_file.write_values(&[invocation], &mut buf).await?;
+        while let Some(value) = events.try_next().await? {
+            let value = value.as_ref();
+            log_file.index(value);
+            log_file.write_values(&[value], &mut buf).await?;
+        }
+        log_file.shutdown().await
     }
 
     pub async fn get_summary(&self) -> anyhow::Result<EventLogSummary> {
@@ -333,6 +587,70 @@ struct NamedEventLogWriter {
     path: EventLogPathBuf,
     file: EventLogWriter,
     trace_id: TraceId,
+    /// Set if we write an index of this log.
+    index: Option<EventLogIndexer>,
+}
+
+impl NamedEventLogWriter {
+    fn index(&mut self, value: StreamValueRef<'_>) {
+        if let Some(index) = &mut self.index {
+            index.record(value);
+        }
+    }
+
+    /// Writes `values`, using `buf` to serialize them.
+    async fn write_values<'a, T, I>(&mut self, values: I, buf: &mut Vec<u8>) -> anyhow::Result<()>
+    where
+        T: SerializeForLog + 'a,
+        I: IntoIterator<Item = &'a T>,
+    {
+        buf.clear();
+
+        for value in values {
+            match self.path.encoding.mode {
+                LogMode::Json => {
+                    value.serialize_to_json(buf)?;
+                    buf.push(b'\n');
+                }
+                LogMode::Protobuf => value.serialize_to_protobuf_length_delimited(buf)?,
+            };
+        }
+
+        self.file
+            .writer()
+            .write_all(buf)
+            .await
+            .context("Failed to write event")?;
+
+        if let Some(index) = &mut self.index {
+            if index.record_bytes(buf.len()) {
+                let offset = self
+                    .file
+                    .start_block()
+                    .await
+                    .context("Failed to start event log block")?;
+                index.start_block(offset);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the log, and writes its index if we have one. The index is only an optimization,
+    /// so failing to write it is not an error.
+    async fn shutdown(&mut self) -> anyhow::Result<()> {
+        self.file.writer().shutdown().await?;
+        if let Some(index) = self.index.take() {
+            if let Err(e) = index.finish().write(&self.path.path).await {
+                tracing::warn!(
+                    "Error writing index of event log `{}`: {:#}",
+                    self.path.path.display(),
+                    e
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 enum LogFileState {
@@ -409,24 +727,7 @@ impl EventLog {
         match &mut self.state {
             LogFileState::Opened(files) => {
                 for f in files.iter_mut() {
-                    self.buf.clear();
-
-                    for event in events.clone() {
-                        match f.path.encoding.mode {
-                            LogMode::Json => {
-                                event.serialize_to_json(&mut self.buf)?;
-                                self.buf.push(b'\n');
-                            }
-                            LogMode::Protobuf => {
-                                event.serialize_to_protobuf_length_delimited(&mut self.buf)?
-                            }
-                        };
-                    }
-
-                    f.file
-                        .write_all(&self.buf)
-                        .await
-                        .context("Failed to write event")?;
+                    f.write_values(events.clone(), &mut self.buf).await?;
 
                     if self.buf.len() > 1_000_000 {
                         // Make sure we don't keep too much memory if encountered one large event.
@@ -451,6 +752,16 @@ impl EventLog {
         }
     }
 
+    fn index(&mut self, values: &[StreamValueRef<'_>]) {
+        if let LogFileState::Opened(files) = &mut self.state {
+            for file in files {
+                for value in values {
+                    file.index(*value);
+                }
+            }
+        }
+    }
+
     async fn ensure_log_files_opened(&mut self, event: &BuckEvent) -> anyhow::Result<()> {
         let (logdir, maybe_extra_path) = match &self.state {
             LogFileState::Unopened(logdir, extra_path) => (logdir, extra_path),
@@ -485,7 +796,13 @@ impl EventLog {
                 .join(get_logfile_name(event, encoding, &self.command_name)?),
             encoding,
         };
-        let mut log_files = vec![open_event_log_for_writing(path, event.trace_id()?).await?];
+        // Indexed logs are written in independently compressed blocks, which tools that don't
+        // expect multi-frame logs might not read past the first block of, so indexing is opt-in.
+        // `buck2 log compact` can index a log after the fact.
+        static EVENT_LOG_INDEX: EnvHelper<bool> = EnvHelper::new("BUCK2_EVENT_LOG_INDEX");
+        let index = EVENT_LOG_INDEX.get_copied()?.unwrap_or_default();
+
+        let mut log_files = vec![open_event_log_for_writing(path, event.trace_id()?, index).await?];
 
         // Also open the user's log file, if any as provided, with no encoding.
         if let Some(extra_path) = maybe_extra_path {
@@ -498,6 +815,7 @@ impl EventLog {
                         },
                     ),
                     event.trace_id()?,
+                    false,
                 )
                 .await?,
             );
@@ -522,7 +840,7 @@ impl EventLog {
 
         async move {
             for file in log_files.iter_mut() {
-                file.file.shutdown().await?;
+                file.shutdown().await?;
             }
 
             let log_file_to_upload = match log_files.first() {
@@ -572,6 +890,7 @@ impl Drop for EventLog {
 async fn open_event_log_for_writing(
     path: EventLogPathBuf,
     trace_id: TraceId,
+    index: bool,
 ) -> anyhow::Result<NamedEventLogWriter> {
     let file = OpenOptions::new()
         .create(true)
@@ -585,20 +904,15 @@ async fn open_event_log_for_writing(
             )
         })?;
 
-    let file = match path.encoding.compression {
-        Compression::None => box file as EventLogWriter,
-        Compression::Gzip => {
-            box GzipEncoder::with_quality(file, async_compression::Level::Fastest) as EventLogWriter
-        }
-        Compression::Zstd => {
-            box ZstdEncoder::with_quality(file, async_compression::Level::Default) as EventLogWriter
-        }
-    };
-
     Ok(NamedEventLogWriter {
+        file: EventLogWriter::new(file, path.encoding.compression),
         path,
-        file,
         trace_id,
+        index: if index {
+            Some(EventLogIndexer::new())
+        } else {
+            None
+        },
     })
 }
 
@@ -609,7 +923,10 @@ impl EventSubscriber for EventLog {
         let mut first = true;
         for event in events {
             if let buck_event::Data::Instant(instant) = event.data() {
-                if let Some(instant_event::Data::RawOutput(_)) = instant.data.as_ref() {
+                if let Some(
+                    instant_event::Data::RawOutput(_) | instant_event::Data::CommandStderr(_),
+                ) = instant.data.as_ref()
+                {
                     continue;
                 }
             }
@@ -624,6 +941,7 @@ impl EventSubscriber for EventLog {
             return Ok(());
         }
 
+        self.index(&event_refs);
         self.write_ln(&event_refs).await
     }
 
@@ -645,6 +963,7 @@ impl EventSubscriber for EventLog {
 
         let event = StreamValueRef::Result(result);
 
+        self.index(&[event]);
         self.write_ln(&[event]).await
     }
 
@@ -657,7 +976,7 @@ impl EventSubscriber for EventLog {
         };
 
         for file in log_files {
-            file.file.flush().await.with_context(|| {
+            file.file.writer().flush().await.with_context(|| {
                 format!("Error flushing log file at {}", file.path.path.display())
             })?;
         }
@@ -713,6 +1032,20 @@ impl<'a> SerializeForLog for StreamValueRef<'a> {
     }
 }
 
+fn parse_json_line(line: std::io::Result<String>) -> anyhow::Result<StreamValue> {
+    let line = line.context("Error reading next line")?;
+    serde_json::from_str::<StreamValue>(&line)
+        .with_context(|| format!("Invalid line: {}", line.trim_end()))
+}
+
+fn stream_value(progress: cli_proto::CommandProgress) -> anyhow::Result<StreamValue> {
+    match progress.progress {
+        Some(command_progress::Progress::Event(event)) => Ok(StreamValue::Event(event)),
+        Some(command_progress::Progress::Result(result)) => Ok(StreamValue::Result(result)),
+        None => Err(anyhow::anyhow!("Event type not recognized")),
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 enum Frame {
     Invocation(buck2_data::Invocation),
@@ -783,7 +1116,7 @@ mod tests {
         async fn new_test_event_log(log: EventLogPathBuf) -> anyhow::Result<Self> {
             Ok(Self {
                 state: LogFileState::Opened(vec![
-                    open_event_log_for_writing(log, TraceId::new()).await?,
+                    open_event_log_for_writing(log, TraceId::new(), false).await?,
                 ]),
                 sanitized_argv: vec!["buck2".to_owned()],
                 async_cleanup_context: None,
@@ -942,6 +1275,116 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_indexed_log_gzip() -> anyhow::Result<()> {
+        test_indexed_log(Encoding::PROTO_GZIP).await
+    }
+
+    #[tokio::test]
+    async fn test_indexed_log_zstd() -> anyhow::Result<()> {
+        test_indexed_log(Encoding::PROTO_ZSTD).await
+    }
+
+    #[tokio::test]
+    async fn test_indexed_log_json() -> anyhow::Result<()> {
+        test_indexed_log(Encoding::JSON_GZIP).await
+    }
+
+    async fn test_indexed_log(encoding: Encoding) -> anyhow::Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let log = EventLogPathBuf {
+            path: AbsPathBuf::try_from(tmp_dir.path().join("log")).unwrap(),
+            encoding,
+        };
+
+        // Put every write in its own block.
+        let mut writer = open_event_log_for_writing(log.clone(), TraceId::new(), false).await?;
+        writer.index = Some(EventLogIndexer::with_block_size(1));
+
+        let mut buf = Vec::new();
+        let invocation = Invocation {
+            command_line_args: vec!["buck2".to_owned()],
+            working_dir: "/".to_owned(),
+        };
+        writer.write_values(&[invocation], &mut buf).await?;
+        let events = (0..10).map(|_| make_event()).collect::<Vec<_>>();
+        for event in &events {
+            let value = StreamValueRef::Event(event.event());
+            writer.index(value);
+            writer.write_values(&[value], &mut buf).await?;
+        }
+        writer.shutdown().await?;
+
+        let index = log.read_index().await?.expect("No index");
+        assert_eq!(index.blocks.len(), 12);
+        assert_eq!(index.summary.events, 10);
+        assert_eq!(log.read_summary().await?, index.summary);
+
+        // The log can be read as a whole.
+        let (_invocation, values) = log.unpack_stream().await?;
+        assert_eq!(values.try_collect::<Vec<_>>().await?.len(), 10);
+
+        // Nothing ends these spans, so they were all still open when the log ended.
+        let open_spans = log
+            .open_spans(index.clone())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(open_spans.len(), 10);
+
+        // Or just the block that we need.
+        let expected = buck2_data::BuckEvent::from(events[5].clone());
+        let query = EventLogQuery {
+            span_id: Some(expected.span_id),
+            ..Default::default()
+        };
+        assert_eq!(query.blocks(&index), vec![6]);
+        let found = log.query(query).await?.try_collect::<Vec<_>>().await?;
+        assert_eq!(found, vec![expected]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_indexed() -> anyhow::Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let log = EventLogPathBuf {
+            path: AbsPathBuf::try_from(tmp_dir.path().join("log.pb.zst")).unwrap(),
+            encoding: Encoding::PROTO_ZSTD,
+        };
+
+        let mut event_log = EventLog::new_test_event_log(log.clone()).await?;
+        event_log.log_invocation().await?;
+        for event in (0..3).map(|_| make_event()) {
+            event_log
+                .write_ln(&[StreamValueRef::Event(event.event())])
+                .await?;
+        }
+        event_log.exit().await?;
+
+        // Compacting twice to the same path replaces the output rather than appending to it.
+        let output = AbsPathBuf::try_from(tmp_dir.path().join("compacted.json-lines.gz")).unwrap();
+        log.write_indexed(output.clone()).await?;
+        log.write_indexed(output.clone()).await?;
+        let compacted = EventLogPathBuf::infer(output)?;
+        let (_invocation, values) = compacted.unpack_stream().await?;
+        assert_eq!(values.try_collect::<Vec<_>>().await?.len(), 3);
+        assert_eq!(
+            compacted
+                .read_index()
+                .await?
+                .expect("No index")
+                .summary
+                .events,
+            3
+        );
+
+        assert!(log.write_indexed(log.path.clone()).await.is_err());
+        let (_invocation, values) = log.unpack_stream().await?;
+        assert_eq!(values.try_collect::<Vec<_>>().await?.len(), 3);
+
+        Ok(())
+    }
+
     #[test]
     fn test_stream_value_serialize_to_protobuf_length_delimited() {
         let event = make_event();
@@ -165,6 +165,16 @@ impl RePanel {
         Ok(r)
     }
 
+    /// Everything we know, regardless of whether the detailed view is enabled.
+    pub(crate) fn render_all(&self) -> anyhow::Result<Vec<Line>> {
+        let mut lines = Vec::new();
+        if let Some(header) = self.render_header(DrawMode::Normal) {
+            lines.push(Line::unstyled(&header)?);
+        }
+        lines.extend(self.render_detailed()?);
+        Ok(lines)
+    }
+
     pub(crate) fn render(&self, draw_mode: DrawMode) -> anyhow::Result<Vec<Line>> {
         let header = match self.render_header(draw_mode) {
             Some(header) => header,
@@ -20,6 +20,8 @@ use buck2_events::BuckEvent;
 use gazebo::prelude::*;
 use thiserror::Error;
 
+use crate::console_interaction_stream::ConsoleKey;
+
 /// Information about tick timing.
 #[derive(Debug, Clone, Dupe)]
 pub struct Tick {
@@ -97,7 +99,7 @@ pub trait EventSubscriber: Send {
     async fn handle_tailer_stderr(&mut self, _stderr: &str) -> anyhow::Result<()> {
         Ok(())
     }
-    async fn handle_console_interaction(&mut self, _c: char) -> anyhow::Result<()> {
+    async fn handle_console_interaction(&mut self, _key: ConsoleKey) -> anyhow::Result<()> {
         Ok(())
     }
     async fn handle_events(&mut self, _event: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
@@ -22,6 +22,7 @@ use buck2_data::TestRunStart;
 use buck2_events::BuckEvent;
 use cli_proto::CommandResult;
 
+use crate::console_interaction_stream::ConsoleKey;
 use crate::subscribers::subscriber::EventSubscriber;
 use crate::subscribers::subscriber::Tick;
 use crate::subscribers::subscriber::VisitorError;
@@ -39,7 +40,7 @@ pub trait UnpackingEventSubscriber: Send {
     async fn handle_stderr(&mut self, _stderr: &str) -> anyhow::Result<()> {
         Ok(())
     }
-    async fn handle_console_interaction(&mut self, _c: char) -> anyhow::Result<()> {
+    async fn handle_console_interaction(&mut self, _key: ConsoleKey) -> anyhow::Result<()> {
         Ok(())
     }
     async fn handle_event(&mut self, event: &Arc<BuckEvent>) -> anyhow::Result<()> {
@@ -250,6 +251,12 @@ pub trait UnpackingEventSubscriber: Send {
             buck2_data::instant_event::Data::TargetPatterns(tag) => {
                 self.handle_resolved_target_patterns(tag)
             }
+            buck2_data::instant_event::Data::TestShardAssignment(assignment) => {
+                self.handle_test_shard_assignment(assignment, event)
+            }
+            buck2_data::instant_event::Data::CommandStderr(stderr) => {
+                self.handle_command_stderr(stderr, event)
+            }
         }
         .await
     }
@@ -429,6 +436,20 @@ pub trait UnpackingEventSubscriber: Send {
     ) -> anyhow::Result<()> {
         Ok(())
     }
+    async fn handle_test_shard_assignment(
+        &mut self,
+        _assignment: &buck2_data::TestShardAssignment,
+        _event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn handle_command_stderr(
+        &mut self,
+        _stderr: &buck2_data::CommandStderr,
+        _event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
     async fn handle_test_discovery_start(
         &mut self,
         _test_info: &TestDiscoveryStart,
@@ -647,8 +668,8 @@ impl<U: UnpackingEventSubscriber> EventSubscriber for UnpackingEventSubscriberAs
         self.0.handle_stderr(stderr).await
     }
 
-    async fn handle_console_interaction(&mut self, c: char) -> anyhow::Result<()> {
-        self.0.handle_console_interaction(c).await
+    async fn handle_console_interaction(&mut self, key: ConsoleKey) -> anyhow::Result<()> {
+        self.0.handle_console_interaction(key).await
     }
 
     async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
@@ -8,14 +8,18 @@
  */
 
 use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::fs;
 use std::io::Write;
 use std::iter;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
 use buck2_data::CommandExecutionDetails;
+use buck2_events::span::SpanId;
 use buck2_events::trace::TraceId;
 use buck2_events::BuckEvent;
 use gazebo::prelude::*;
@@ -40,6 +44,7 @@ use superconsole::Span;
 use superconsole::State;
 pub(crate) use superconsole::SuperConsole;
 
+use crate::console_interaction_stream::ConsoleKey;
 use crate::subscribers::display;
 use crate::subscribers::display::TargetDisplayOptions;
 use crate::subscribers::io::IoHeader;
@@ -53,6 +58,9 @@ use crate::subscribers::superconsole::debug_events::DebugEventsState;
 use crate::subscribers::superconsole::dice::DiceComponent;
 use crate::subscribers::superconsole::dice::DiceState;
 use crate::subscribers::superconsole::re::ReHeader;
+use crate::subscribers::superconsole::selected_action::move_selection;
+use crate::subscribers::superconsole::selected_action::SelectedActionComponent;
+use crate::subscribers::superconsole::selected_action::StderrTails;
 use crate::subscribers::superconsole::test::TestState;
 use crate::subscribers::superconsole::timed_list::Cutoffs;
 use crate::subscribers::superconsole::timed_list::TimedList;
@@ -66,6 +74,7 @@ mod common;
 pub(crate) mod debug_events;
 pub(crate) mod dice;
 mod re;
+mod selected_action;
 pub mod test;
 pub mod timed_list;
 
@@ -117,6 +126,10 @@ impl TimeSpeed {
 pub(crate) struct TimedListState {
     /// Two lines for root events with single child event.
     pub(crate) two_lines: bool,
+    /// The root picked with the arrow keys, if any.
+    pub(crate) selected: Option<SpanId>,
+    /// Show the details of the selected root below the list.
+    pub(crate) expanded: bool,
 }
 
 pub(crate) struct SuperConsoleState {
@@ -130,6 +143,9 @@ pub(crate) struct SuperConsoleState {
     /// This contains the SpanTracker, which is why it's part of the SuperConsoleState.
     simple_console: SimpleConsole,
     timed_list: TimedListState,
+    stderr_tails: StderrTails,
+    /// Stop redrawing, so the user can read the console without it changing under them.
+    paused: bool,
 }
 
 #[derive(Default)]
@@ -155,6 +171,7 @@ impl StatefulSuperConsole {
         components.push(box DiceComponent);
         components.push(box CommandsComponent);
         components.push(box TimedList::new(MAX_EVENTS, CUTOFFS, header));
+        components.push(box SelectedActionComponent);
         let root = box Split::new(components, Direction::Vertical, SplitKind::Adaptive);
         // bound all components to our recommended grapheme-width
         box Bounded::new(root, Some(SUPERCONSOLE_WIDTH), None)
@@ -222,6 +239,8 @@ impl StatefulSuperConsole {
                 debug_events: DebugEventsState::new(config.enable_debug_events),
                 commands_state: CommandsComponentState { enabled: false },
                 timed_list: TimedListState::default(),
+                stderr_tails: StderrTails::default(),
+                paused: false,
             },
             super_console: Some(super_console),
             verbosity,
@@ -270,6 +289,7 @@ impl SuperConsoleState {
             &self.debug_events,
             &self.commands_state,
             &self.timed_list,
+            &self.stderr_tails,
         ]
     }
 }
@@ -290,6 +310,50 @@ impl StatefulSuperConsole {
         self.handle_stderr(&format!("{what}: {on_off}, press `{key}` to revert"))
             .await
     }
+
+    fn select(&mut self, offset: isize) {
+        let timed_list = &mut self.state.timed_list;
+        timed_list.selected = move_selection(
+            timed_list.selected,
+            self.state.simple_console.spans(),
+            offset,
+        );
+    }
+
+    /// Write the DICE and RE state, along with what is currently running, to a file so that
+    /// a stuck build can be investigated without killing it.
+    fn dump_state(&self) -> anyhow::Result<PathBuf> {
+        let mut out = String::new();
+
+        writeln!(out, "Running:")?;
+        for root in self.state.simple_console.spans().iter_roots() {
+            let info = root.info();
+            writeln!(
+                out,
+                "  {} ({:.1}s)",
+                display::display_event(&info.event, TargetDisplayOptions::for_log())?,
+                info.start.elapsed().as_secs_f64()
+            )?;
+        }
+
+        writeln!(out, "\nDICE key states (started, finished):")?;
+        for (k, v) in self.state.dice_state.key_states() {
+            writeln!(out, "  {}: {}, {}", k, v.started, v.finished)?;
+        }
+
+        writeln!(out, "\nRE:")?;
+        for line in self.state.simple_console.re_panel().render_all()? {
+            writeln!(out, "  {}", line.to_unstyled())?;
+        }
+
+        let trace_id = match &self.state.session_info.trace_id {
+            Some(trace_id) => trace_id.to_string(),
+            None => "unknown".to_owned(),
+        };
+        let path = std::env::temp_dir().join(format!("buck2-console-state-{}.txt", trace_id));
+        fs::write(&path, out).with_context(|| format!("Writing `{}`", path.display()))?;
+        Ok(path)
+    }
 }
 
 // TODO(brasselsprouts): after deprecating filetailers, simplify these code paths
@@ -302,6 +366,11 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
                     .await
                     .with_context(|| display::InvalidBuckEvent(event.clone()))?;
                 self.state.simple_console.update_span_tracker(event)?;
+                if let (buck2_data::buck_event::Data::SpanEnd(_), Some(span_id)) =
+                    (event.data(), event.span_id())
+                {
+                    self.state.stderr_tails.remove(span_id);
+                }
             }
             None => {
                 self.state.simple_console.handle_event(event).await?;
@@ -349,8 +418,49 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         self.state.simple_console.handle_output(raw_output).await
     }
 
-    async fn handle_console_interaction(&mut self, c: char) -> anyhow::Result<()> {
-        if c == 'd' {
+    async fn handle_console_interaction(&mut self, key: ConsoleKey) -> anyhow::Result<()> {
+        let c = match key {
+            ConsoleKey::Char(c) => c,
+            ConsoleKey::Up => {
+                self.select(-1);
+                return Ok(());
+            }
+            ConsoleKey::Down => {
+                self.select(1);
+                return Ok(());
+            }
+            ConsoleKey::Right => {
+                self.state.timed_list.expanded = self.state.timed_list.selected.is_some();
+                return Ok(());
+            }
+            ConsoleKey::Left | ConsoleKey::Escape => {
+                // Back out one level at a time: first collapse, then deselect.
+                let timed_list = &mut self.state.timed_list;
+                if timed_list.expanded {
+                    timed_list.expanded = false;
+                } else {
+                    timed_list.selected = None;
+                }
+                return Ok(());
+            }
+        };
+
+        if c == '\n' {
+            self.state.timed_list.expanded = self.state.timed_list.selected.is_some();
+        } else if c == 'p' {
+            self.toggle("Paused display", 'p', |s| &mut s.state.paused)
+                .await?;
+            // Render once more so the message above is shown before we stop drawing.
+            if let Some(super_console) = &mut self.super_console {
+                super_console.render(&self.state.state())?;
+            }
+        } else if c == 'w' {
+            let msg = match self.dump_state() {
+                Ok(path) => format!("Wrote DICE and RE state to `{}`", path.display()),
+                Err(e) => format!("Failed to write DICE and RE state: {:#}", e),
+            };
+            self.handle_stderr(&msg).await?;
+        } else if c == 'd' {
             self.toggle("DICE component", 'd', |s| &mut s.state.dice_state.enabled)
                 .await?;
         } else if c == 'e' {
@@ -382,6 +492,12 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
                 `2` = toggle two lines mode\n\
                 `r` = toggle detailed RE\n\
                 `i` = toggle I/O counters\n\
+                `c` = toggle commands\n\
+                up/down = select an action\n\
+                right/enter = show details of the selected action\n\
+                left/esc = hide details, then clear the selection\n\
+                `p` = pause or unpause the display\n\
+                `w` = write DICE and RE state to a file\n\
                 `h` = show this help",
             )
             .await?;
@@ -431,6 +547,7 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
 
     async fn tick(&mut self, tick: &Tick) -> anyhow::Result<()> {
         match &mut self.super_console {
+            Some(_) if self.state.paused => Ok(()),
             Some(super_console) => {
                 self.state.current_tick = tick.dupe();
                 super_console.render(&self.state.state())
@@ -595,6 +712,17 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         Ok(())
     }
 
+    async fn handle_command_stderr(
+        &mut self,
+        stderr: &buck2_data::CommandStderr,
+        event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        if let Some(span_id) = event.parent_id() {
+            self.state.stderr_tails.append(span_id, &stderr.stderr);
+        }
+        Ok(())
+    }
+
     async fn handle_dice_snapshot(
         &mut self,
         update: &buck2_data::DiceStateSnapshot,
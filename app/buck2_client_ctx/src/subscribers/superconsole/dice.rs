@@ -32,6 +32,10 @@ impl DiceState {
             self.key_states.insert(k.clone(), v.clone());
         }
     }
+
+    pub(crate) fn key_states(&self) -> &BTreeMap<String, DiceKeyState> {
+        &self.key_states
+    }
 }
 
 #[derive(Debug)]
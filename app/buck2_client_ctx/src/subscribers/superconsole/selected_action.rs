@@ -0,0 +1,332 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Drill-down into the action selected in the timed list with the arrow keys.
+//!
+//! The expanded view shows what the span tracker knows about a running action: its stages,
+//! command line and environment, along with the tail of the stderr the daemon streams while a
+//! local command runs.
+
+use std::collections::HashMap;
+
+use buck2_events::span::SpanId;
+use superconsole::style::Attribute;
+use superconsole::style::ContentStyle;
+use superconsole::style::StyledContent;
+use superconsole::Component;
+use superconsole::Dimensions;
+use superconsole::DrawMode;
+use superconsole::Line;
+use superconsole::Lines;
+use superconsole::Span;
+use superconsole::State;
+
+use crate::subscribers::display;
+use crate::subscribers::display::TargetDisplayOptions;
+use crate::subscribers::span_tracker::BuckEventSpanHandle;
+use crate::subscribers::span_tracker::BuckEventSpanTracker;
+use crate::subscribers::superconsole::TimeSpeed;
+use crate::subscribers::superconsole::TimedListState;
+
+/// Move the selection `offset` roots up (negative) or down (positive) the timed list.
+///
+/// Selecting follows the span rather than its position, so the selection stays on the same
+/// action as others finish. If the selected span has finished, we start again from the top.
+pub(crate) fn move_selection(
+    selected: Option<SpanId>,
+    spans: &BuckEventSpanTracker,
+    offset: isize,
+) -> Option<SpanId> {
+    let roots: Vec<SpanId> = spans
+        .iter_roots()
+        .filter_map(|root| root.info().event.span_id())
+        .collect();
+    if roots.is_empty() {
+        return None;
+    }
+    let current = selected.and_then(|selected| roots.iter().position(|x| *x == selected));
+    let index = match current {
+        Some(current) => (current as isize + offset).clamp(0, roots.len() as isize - 1) as usize,
+        None => 0,
+    };
+    Some(roots[index])
+}
+
+/// Find the root for the selected span, if it is still running.
+pub(crate) fn find_selected<'a>(
+    spans: &'a BuckEventSpanTracker,
+    selected: Option<SpanId>,
+) -> Option<BuckEventSpanHandle<'a>> {
+    let selected = selected?;
+    spans
+        .iter_roots()
+        .find(|root| root.info().event.span_id() == Some(selected))
+}
+
+/// The style used to mark the selected row.
+pub(crate) fn highlight(line: &mut Line) {
+    for span in &mut line.0 {
+        span.stylization.attributes.set(Attribute::Reverse);
+    }
+}
+
+/// How many lines of stderr to keep for each running command.
+const STDERR_TAIL_LINES: usize = 10;
+
+/// The last lines of stderr of running commands, by the span of the executor stage running them.
+#[derive(Default)]
+pub(crate) struct StderrTails {
+    tails: HashMap<SpanId, String>,
+}
+
+impl StderrTails {
+    pub(crate) fn append(&mut self, span: SpanId, stderr: &str) {
+        let tail = self.tails.entry(span).or_default();
+        tail.push_str(stderr);
+        let cut = tail
+            .trim_end_matches('\n')
+            .rmatch_indices('\n')
+            .nth(STDERR_TAIL_LINES - 1)
+            .map(|(pos, _)| pos + 1);
+        if let Some(cut) = cut {
+            tail.drain(..cut);
+        }
+    }
+
+    /// Forget the stderr of a span once it has finished.
+    pub(crate) fn remove(&mut self, span: SpanId) {
+        self.tails.remove(&span);
+    }
+
+    fn get(&self, span: SpanId) -> Option<&str> {
+        self.tails.get(&span).map(|tail| tail.as_str())
+    }
+}
+
+/// Everything we know about how a span is being executed, gathered from its descendants.
+#[derive(Default)]
+struct ExecutionDetails<'a> {
+    command: Option<buck2_data::LocalCommand>,
+    re_action_digest: Option<String>,
+    stderr_tail: Option<&'a str>,
+}
+
+impl<'a> ExecutionDetails<'a> {
+    fn collect(&mut self, span: &BuckEventSpanHandle, stderr_tails: &'a StderrTails) {
+        use buck2_data::executor_stage_start::Stage;
+
+        let event = &span.info().event;
+        if let buck2_data::buck_event::Data::SpanStart(start) = event.data() {
+            if let Some(buck2_data::span_start_event::Data::ExecutorStage(executor_stage)) =
+                &start.data
+            {
+                match &executor_stage.stage {
+                    Some(Stage::Local(buck2_data::LocalStage {
+                        stage: Some(buck2_data::local_stage::Stage::Execute(execute)),
+                        ..
+                    })) => self.command = execute.command.clone(),
+                    Some(Stage::Re(buck2_data::ReStage {
+                        stage: Some(buck2_data::re_stage::Stage::Upload(upload)),
+                        ..
+                    })) => self.command = upload.command.clone(),
+                    Some(Stage::Re(buck2_data::ReStage {
+                        stage: Some(buck2_data::re_stage::Stage::Execute(execute)),
+                        ..
+                    })) => self.re_action_digest = Some(execute.action_digest.clone()),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(tail) = event.span_id().and_then(|id| stderr_tails.get(id)) {
+            self.stderr_tail = Some(tail);
+        }
+
+        for child in span.children() {
+            self.collect(&child, stderr_tails);
+        }
+    }
+}
+
+/// Shows the full details of the selected action when it is expanded: what it is doing now,
+/// the command line and environment it is running with, and what it last wrote to stderr.
+#[derive(Debug)]
+pub(crate) struct SelectedActionComponent;
+
+impl SelectedActionComponent {
+    fn draw_children(
+        span: &BuckEventSpanHandle,
+        indent: usize,
+        time_speed: f64,
+        lines: &mut Lines,
+    ) -> anyhow::Result<()> {
+        for child in span.children() {
+            let info = child.info();
+            lines.push(Line::sanitized(&format!(
+                "{:indent$}{} ({})",
+                "",
+                display::display_event(&info.event, TargetDisplayOptions::for_console())?,
+                display::duration_as_secs_elapsed(info.start.elapsed(), time_speed),
+            )));
+            Self::draw_children(&child, indent + 2, time_speed, lines)?;
+        }
+        Ok(())
+    }
+}
+
+impl Component for SelectedActionComponent {
+    fn draw_unchecked(
+        &self,
+        state: &State,
+        _dimensions: Dimensions,
+        mode: DrawMode,
+    ) -> anyhow::Result<Lines> {
+        let timed_list = state.get::<TimedListState>()?;
+        if !timed_list.expanded || matches!(mode, DrawMode::Final) {
+            return Ok(vec![]);
+        }
+        let spans = state.get::<BuckEventSpanTracker>()?;
+        let time_speed = state.get::<TimeSpeed>()?.speed();
+
+        let root = match find_selected(spans, timed_list.selected) {
+            Some(root) => root,
+            None => {
+                return Ok(vec![Line::unstyled(
+                    "Selected action has finished, use the arrow keys to select another",
+                )?]);
+            }
+        };
+
+        let info = root.info();
+        let bold = ContentStyle {
+            attributes: Attribute::Bold.into(),
+            ..Default::default()
+        };
+        let mut lines = vec![Line::from_iter([Span::new_styled_lossy(
+            StyledContent::new(
+                bold,
+                format!(
+                    "{} ({})",
+                    display::display_event(&info.event, TargetDisplayOptions::for_console())?,
+                    display::duration_as_secs_elapsed(info.start.elapsed(), time_speed),
+                ),
+            ),
+        )])];
+        Self::draw_children(&root, 2, time_speed, &mut lines)?;
+
+        let mut details = ExecutionDetails::default();
+        details.collect(&root, state.get::<StderrTails>()?);
+        if let Some(digest) = &details.re_action_digest {
+            lines.push(Line::sanitized(&format!("RE action digest: {}", digest)));
+        }
+        if let Some(command) = &details.command {
+            lines.push(Line::sanitized(&format!(
+                "Command: {}",
+                shlex::join(command.argv.iter().map(|x| x.as_str()))
+            )));
+            if !command.env.is_empty() {
+                lines.push(Line::unstyled("Environment:")?);
+                for entry in &command.env {
+                    lines.push(Line::sanitized(&format!("  {}={}", entry.key, entry.value)));
+                }
+            }
+        }
+        if let Some(stderr) = details.stderr_tail {
+            lines.push(Line::unstyled("Stderr:")?);
+            for line in stderr.lines() {
+                lines.push(Line::sanitized(&format!("  {}", line)));
+            }
+        }
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::sync::Arc;
+    use std::time::Instant;
+    use std::time::SystemTime;
+
+    use buck2_data::FakeStart;
+    use buck2_data::SpanStartEvent;
+    use buck2_events::trace::TraceId;
+    use buck2_events::BuckEvent;
+
+    use super::*;
+
+    fn fake_span(name: &str) -> Arc<BuckEvent> {
+        Arc::new(BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            Some(SpanId::new()),
+            None,
+            buck2_data::buck_event::Data::SpanStart(SpanStartEvent {
+                data: Some(buck2_data::span_start_event::Data::Fake(FakeStart {
+                    caramba: name.to_owned(),
+                })),
+            }),
+        ))
+    }
+
+    #[test]
+    fn test_move_selection() -> anyhow::Result<()> {
+        let mut spans = BuckEventSpanTracker::new();
+        assert_eq!(move_selection(None, &spans, 1), None);
+
+        let e1 = fake_span("e1");
+        let e2 = fake_span("e2");
+        spans.start_at(&e1, Instant::now())?;
+        spans.start_at(&e2, Instant::now())?;
+        let e1 = e1.span_id();
+        let e2 = e2.span_id();
+
+        assert_eq!(move_selection(None, &spans, 1), e1);
+        assert_eq!(move_selection(e1, &spans, 1), e2);
+        assert_eq!(move_selection(e2, &spans, 1), e2);
+        assert_eq!(move_selection(e2, &spans, -1), e1);
+        assert_eq!(move_selection(e1, &spans, -1), e1);
+        // A selection which is no longer running starts again from the top.
+        assert_eq!(move_selection(Some(SpanId::new()), &spans, 1), e1);
+
+        assert_eq!(
+            find_selected(&spans, e2).map(|root| root.info().event.span_id()),
+            Some(e2)
+        );
+        assert!(find_selected(&spans, None).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stderr_tails() {
+        let mut tails = StderrTails::default();
+        let span = SpanId::new();
+        assert_eq!(tails.get(span), None);
+
+        tails.append(span, "line 0\nli");
+        tails.append(span, "ne 1\n");
+        assert_eq!(tails.get(span), Some("line 0\nline 1\n"));
+
+        for i in 2..15 {
+            tails.append(span, &format!("line {}\n", i));
+        }
+        let expected: String = (5..15).map(|i| format!("line {}\n", i)).collect();
+        assert_eq!(tails.get(span), Some(expected.as_str()));
+
+        // A partial line is kept along with the last complete ones.
+        tails.append(span, "partial");
+        let expected: String = (6..15)
+            .map(|i| format!("line {}\n", i))
+            .chain(iter::once("partial".to_owned()))
+            .collect();
+        assert_eq!(tails.get(span), Some(expected.as_str()));
+
+        tails.remove(span);
+        assert_eq!(tails.get(span), None);
+    }
+}
@@ -36,6 +36,7 @@ pub(crate) struct TestState {
     pub unknown: u64,
     pub listing_success: u64,
     pub listing_failed: u64,
+    pub flaky: u64,
 }
 
 impl TestState {
@@ -52,6 +53,7 @@ impl TestState {
             TestStatus::RERUN => &mut self.retry,
             TestStatus::LISTING_SUCCESS => &mut self.listing_success,
             TestStatus::LISTING_FAILED => &mut self.listing_failed,
+            TestStatus::FLAKY => &mut self.flaky,
         };
         *counter += 1;
 
@@ -111,6 +113,17 @@ impl Component for TestCounterComponent {
             .to_span()?,
         );
         spans.push(". ".try_into()?);
+        if test_state.flaky > 0 {
+            spans.push(
+                StylizedCount {
+                    label: "Flaky",
+                    count: test_state.flaky,
+                    color: Color::Yellow,
+                }
+                .to_span()?,
+            );
+            spans.push(". ".try_into()?);
+        }
         spans.push(
             StylizedCount {
                 label: "Fail",
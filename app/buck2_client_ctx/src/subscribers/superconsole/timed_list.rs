@@ -182,6 +182,7 @@ impl Component for TimedListBodyInner {
         let spans = state.get::<BuckEventSpanTracker>()?;
 
         let time_speed = state.get::<TimeSpeed>()?;
+        let selected = state.get::<TimedListState>()?.selected;
 
         let mut roots = spans.iter_roots();
 
@@ -190,7 +191,12 @@ impl Component for TimedListBodyInner {
         let mut first_not_rendered = None;
 
         for root in &mut roots {
-            let rows = self.draw_root(&root, state)?;
+            let mut rows = self.draw_root(&root, state)?;
+            if selected.is_some() && root.info().event.span_id() == selected {
+                if let Some(row) = rows.first_mut() {
+                    row.highlight();
+                }
+            }
 
             if builder.len() + rows.len() >= self.max_size {
                 first_not_rendered = Some(root);
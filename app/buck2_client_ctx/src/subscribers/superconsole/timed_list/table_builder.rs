@@ -23,6 +23,7 @@ use superconsole::State;
 use crate::subscribers::display;
 use crate::subscribers::display::TargetDisplayOptions;
 use crate::subscribers::span_tracker::BuckEventSpanInfo;
+use crate::subscribers::superconsole::selected_action;
 use crate::subscribers::superconsole::timed_list::Cutoffs;
 
 #[derive(Debug)]
@@ -129,6 +130,12 @@ impl Row {
         let time = Line::from_iter([Span::new_styled(styled_for_delay(time, age, cutoffs))?]);
         Ok(Row { event: line, time })
     }
+
+    /// Mark this row as the one selected in the console.
+    pub(crate) fn highlight(&mut self) {
+        selected_action::highlight(&mut self.event);
+        selected_action::highlight(&mut self.time);
+    }
 }
 
 /// This component echoes the `Lines` that have been stored in it.
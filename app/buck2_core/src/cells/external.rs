@@ -0,0 +1,149 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! External cells are cells whose sources live outside of the project: in an archive, or in a
+//! git repository. They are pinned to a revision, and materialized in the buck-out directory of
+//! the daemon before they are first read. Once materialized, they are used like any other cell.
+
+use allocative::Allocative;
+use derive_more::Display;
+
+use crate::fs::paths::forward_rel_path::ForwardRelativePath;
+use crate::fs::project::ProjectRelativePath;
+use crate::fs::project::ProjectRelativePathBuf;
+
+/// Where external cells are materialized, relative to the buck-out directory.
+const EXTERNAL_CELLS_DIR: &str = "external_cells";
+
+/// Where the sources of an external cell come from.
+#[derive(Clone, Debug, Display, Hash, PartialEq, Eq, Allocative)]
+pub enum ExternalCellOrigin {
+    /// An archive, verified against its sha256.
+    #[display(fmt = "archive `{}`", url)]
+    Archive {
+        /// `http://`, `https://` or `file://` URL of the archive.
+        url: String,
+        /// Hex-encoded sha256 of the archive.
+        sha256: String,
+        /// A directory in the archive that is the root of the cell, if it is not the root of
+        /// the archive.
+        strip_prefix: Option<String>,
+    },
+    /// A git repository, checked out at a commit.
+    #[display(fmt = "git repository `{}` at `{}`", origin, commit)]
+    Git {
+        /// The repository to clone from.
+        origin: String,
+        /// The full hash of the commit to check out.
+        commit: String,
+    },
+}
+
+impl ExternalCellOrigin {
+    /// The revision that the cell is pinned to. Two origins with the same pin have the same
+    /// contents.
+    pub fn pin(&self) -> &str {
+        match self {
+            Self::Archive { sha256, .. } => sha256,
+            Self::Git { commit, .. } => commit,
+        }
+    }
+}
+
+/// An external cell, and where its sources are materialized.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Allocative)]
+pub struct ExternalCell {
+    origin: ExternalCellOrigin,
+    materialization_path: ProjectRelativePathBuf,
+}
+
+impl ExternalCell {
+    /// The external cell `name` with `origin`, materialized in `buck_out_dir`. The directory it is
+    /// materialized to is named after its pin, so that changing the pin materializes it again.
+    pub fn new(
+        name: &str,
+        origin: ExternalCellOrigin,
+        buck_out_dir: &ProjectRelativePath,
+    ) -> anyhow::Result<Self> {
+        let materialization_path = buck_out_dir
+            .join(ForwardRelativePath::unchecked_new(EXTERNAL_CELLS_DIR))
+            .join(ForwardRelativePath::new(name)?)
+            .join(ForwardRelativePath::new(origin.pin())?);
+        Ok(Self {
+            origin,
+            materialization_path,
+        })
+    }
+
+    pub fn origin(&self) -> &ExternalCellOrigin {
+        &self.origin
+    }
+
+    /// Where the sources of the cell are materialized.
+    pub fn materialization_path(&self) -> &ProjectRelativePath {
+        &self.materialization_path
+    }
+
+    /// The root of the cell.
+    pub fn cell_root(&self) -> anyhow::Result<ProjectRelativePathBuf> {
+        Ok(match &self.origin {
+            ExternalCellOrigin::Archive {
+                strip_prefix: Some(prefix),
+                ..
+            } => self
+                .materialization_path
+                .join(ForwardRelativePath::new(prefix)?),
+            _ => self.materialization_path.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_root() -> anyhow::Result<()> {
+        let buck_out = ProjectRelativePath::unchecked_new("buck-out/v2");
+
+        let archive = ExternalCell::new(
+            "zlib",
+            ExternalCellOrigin::Archive {
+                url: "https://example.com/zlib.tar.gz".to_owned(),
+                sha256: "ab".repeat(32),
+                strip_prefix: Some("zlib-1.2.13".to_owned()),
+            },
+            buck_out,
+        )?;
+        assert_eq!(
+            format!("buck-out/v2/external_cells/zlib/{}", "ab".repeat(32)),
+            archive.materialization_path().as_str()
+        );
+        assert_eq!(
+            format!(
+                "buck-out/v2/external_cells/zlib/{}/zlib-1.2.13",
+                "ab".repeat(32)
+            ),
+            archive.cell_root()?.as_str()
+        );
+
+        let git_origin = ExternalCellOrigin::Git {
+            origin: "/repos/fmt".to_owned(),
+            commit: "c".repeat(40),
+        };
+        let git = ExternalCell::new("fmt", git_origin.clone(), buck_out)?;
+        assert_eq!(
+            git.materialization_path().as_str(),
+            git.cell_root()?.as_str()
+        );
+        assert!(ExternalCell::new("../fmt", git_origin, buck_out).is_err());
+
+        Ok(())
+    }
+}
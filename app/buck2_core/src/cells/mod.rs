@@ -130,6 +130,7 @@
 pub mod build_file_cell;
 pub mod cell_path;
 pub mod cell_root_path;
+pub mod external;
 pub mod paths;
 
 use std::borrow::Borrow;
@@ -152,6 +153,7 @@ use thiserror::Error;
 use crate::cells::cell_path::CellPath;
 use crate::cells::cell_root_path::CellRootPath;
 use crate::cells::cell_root_path::CellRootPathBuf;
+use crate::cells::external::ExternalCell;
 use crate::fs::paths::abs_norm_path::AbsNormPath;
 use crate::fs::paths::abs_norm_path::AbsNormPathBuf;
 use crate::fs::paths::file_name::FileNameBuf;
@@ -289,6 +291,9 @@ struct CellData {
     #[derivative(Debug = "ignore")]
     /// the aliases of this specific cell
     aliases: CellAliasResolver,
+    /// where the sources of this cell come from, if it is an external cell. Its pin is part of
+    /// the identity of the cell, so that changing it invalidates everything read from the cell.
+    external: Option<ExternalCell>,
 }
 
 impl CellInstance {
@@ -297,12 +302,14 @@ impl CellInstance {
         path: CellRootPathBuf,
         buildfiles: Vec<FileNameBuf>,
         aliases: CellAliasResolver,
+        external: Option<ExternalCell>,
     ) -> CellInstance {
         CellInstance(Arc::new(CellData {
             name,
             path,
             buildfiles,
             aliases,
+            external,
         }))
     }
 
@@ -324,6 +331,11 @@ impl CellInstance {
     pub fn cell_alias_resolver(&self) -> &CellAliasResolver {
         &self.0.aliases
     }
+
+    /// Get where the sources of the cell come from, if it is an external cell.
+    pub fn external(&self) -> Option<&ExternalCell> {
+        self.0.external.as_ref()
+    }
 }
 
 /// Resolves 'CellName's into 'CellInstance's.
@@ -509,6 +521,8 @@ struct CellAggregatorInfo {
     /// The build file name in this if it's been set. If it hasn't we'll use the
     /// default `["BUCK.v2", "BUCK"]` when building the resolver.
     buildfiles: Option<Vec<FileNameBuf>>,
+    /// Where the sources of the cell come from, if it is an external cell.
+    external: Option<ExternalCell>,
 }
 
 impl CellsAggregator {
@@ -557,6 +571,11 @@ impl CellsAggregator {
         cell_info.buildfiles = Some(buildfiles);
     }
 
+    /// Marks the cell at `cell_root` as an external cell.
+    pub fn set_external(&mut self, cell_root: CellRootPathBuf, external: ExternalCell) {
+        self.cell_info(cell_root).external = Some(external);
+    }
+
     fn get_cell_name_from_path(&self, path: &CellRootPath) -> anyhow::Result<CellName> {
         self.cell_infos
             .get(path)
@@ -598,6 +617,7 @@ impl CellsAggregator {
                         .clone()
                         .unwrap_or_else(default_buildfiles),
                     CellAliasResolver::new(Arc::new(aliases_for_cell))?,
+                    cell_info.external.clone(),
                 ),
             );
             if let Some(old) = old {
@@ -653,6 +673,7 @@ pub mod testing {
                         path.clone(),
                         default_buildfiles(),
                         CellAliasResolver(Arc::new(Default::default())),
+                        None,
                     ),
                 );
 
@@ -676,6 +697,7 @@ pub mod testing {
                         path.clone(),
                         default_buildfiles(),
                         CellAliasResolver(Arc::new(alias.clone())),
+                        None,
                     ),
                 );
                 assert!(prev.is_none());
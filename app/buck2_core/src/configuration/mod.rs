@@ -336,7 +336,7 @@ impl Display for ConfigurationPlatform {
 }
 
 /// A set of values used in configuration-related contexts.
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Allocative)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Allocative)]
 pub struct ConfigurationData {
     // contains the full specification of the platform configuration
     pub constraints: BTreeMap<ConstraintKey, ConstraintValue>,
@@ -52,6 +52,20 @@ impl ForkserverClient {
         req: buck2_forkserver_proto::CommandRequest,
         cancel: C,
     ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
+    where
+        C: Future<Output = ()> + Send + 'static,
+    {
+        self.execute_observing_stderr(req, cancel, |_| {}).await
+    }
+
+    /// Like `execute`, but `on_stderr` is also called with the stderr of the command as it is
+    /// produced.
+    pub async fn execute_observing_stderr<C>(
+        &self,
+        req: buck2_forkserver_proto::CommandRequest,
+        cancel: C,
+        on_stderr: impl FnMut(&[u8]) + Send,
+    ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
     where
         C: Future<Output = ()> + Send + 'static,
     {
@@ -73,6 +87,6 @@ impl ForkserverClient {
             .context("Error dispatching command to Forkserver")?
             .into_inner();
         let stream = decode_event_stream(stream);
-        decode_command_event_stream(stream).await
+        decode_command_event_stream(stream, on_stderr).await
     }
 }
@@ -203,6 +203,7 @@ where
 
 pub(crate) async fn decode_command_event_stream<S>(
     stream: S,
+    mut on_stderr: impl FnMut(&[u8]),
 ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
 where
     S: Stream<Item = anyhow::Result<CommandEvent>>,
@@ -215,7 +216,10 @@ where
     while let Some(event) = stream.try_next().await? {
         match event {
             CommandEvent::Stdout(bytes) => stdout.extend(&bytes),
-            CommandEvent::Stderr(bytes) => stderr.extend(&bytes),
+            CommandEvent::Stderr(bytes) => {
+                on_stderr(&bytes);
+                stderr.extend(&bytes)
+            }
             CommandEvent::Exit(exit) => return Ok((exit, stdout, stderr)),
         }
     }
@@ -229,6 +233,19 @@ pub async fn gather_output<T>(
     cmd: Command,
     cancellation: T,
 ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
+where
+    T: Future<Output = anyhow::Result<GatherOutputStatus>> + Send,
+{
+    gather_output_observing_stderr(cmd, cancellation, |_| {}).await
+}
+
+/// Like `gather_output`, but `on_stderr` is also called with the stderr of the command as it is
+/// produced, so that it can be shown while the command is still running.
+pub async fn gather_output_observing_stderr<T>(
+    cmd: Command,
+    cancellation: T,
+    on_stderr: impl FnMut(&[u8]) + Send,
+) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>
 where
     T: Future<Output = anyhow::Result<GatherOutputStatus>> + Send,
 {
@@ -239,7 +256,7 @@ where
         .context("Failed to start command")?;
 
     let stream = stream_command_events(child, cancellation)?;
-    decode_command_event_stream(stream).await
+    decode_command_event_stream(stream, on_stderr).await
 }
 
 fn kill_process(child: &Child) -> anyhow::Result<()> {
@@ -368,6 +385,25 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_gather_output_observing_stderr() -> anyhow::Result<()> {
+        let mut cmd = background_command("sh");
+        cmd.args(["-c", "echo hello >&2; sleep 0.1; echo world >&2"]);
+
+        let mut observed = Vec::new();
+        let (status, _stdout, stderr) =
+            gather_output_observing_stderr(cmd, futures::future::pending(), |bytes| {
+                observed.extend_from_slice(bytes)
+            })
+            .await?;
+        assert!(matches!(status, GatherOutputStatus::Finished(s) if s.code() == Some(0)));
+        assert_eq!(str::from_utf8(&stderr)?, "hello\nworld\n");
+        assert_eq!(observed, stderr);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_gather_does_not_wait_for_children() -> anyhow::Result<()> {
         // If we wait for sleep, this will time out.
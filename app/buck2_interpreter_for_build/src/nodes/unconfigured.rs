@@ -21,6 +21,7 @@ use buck2_node::attrs::coercion_context::AttrCoercionContext;
 use buck2_node::attrs::inspect_options::AttrInspectOptions;
 use buck2_node::attrs::internal::NAME_ATTRIBUTE_FIELD;
 use buck2_node::attrs::internal::VISIBILITY_ATTRIBUTE_FIELD;
+use buck2_node::attrs::internal::WITHIN_VIEW_ATTRIBUTE_FIELD;
 use buck2_node::attrs::spec::AttributeSpec;
 use buck2_node::attrs::values::AttrValues;
 use buck2_node::call_stack::StarlarkCallStack;
@@ -30,6 +31,7 @@ use buck2_node::rule_type::RuleType;
 use buck2_node::rule_type::StarlarkRuleType;
 use buck2_node::visibility::VisibilityPattern;
 use buck2_node::visibility::VisibilitySpecification;
+use buck2_node::visibility::WithinViewSpecification;
 use gazebo::dupe::Dupe;
 use starlark::eval::CallStack;
 use starlark::eval::ParametersParser;
@@ -92,6 +94,7 @@ impl TargetNodeExt for TargetNode {
                     AttrValues::with_capacity(0),
                     CoercedDepsCollector::new(),
                     VisibilitySpecification::Public,
+                    WithinViewSpecification::Public,
                     None,
                     None,
                 ));
@@ -147,6 +150,16 @@ impl TargetNodeExt for TargetNode {
             visibility = VisibilitySpecification::Public;
         }
 
+        let within_view = match attr_spec.attr_or_none(
+            &attr_values,
+            WITHIN_VIEW_ATTRIBUTE_FIELD,
+            AttrInspectOptions::All,
+        ) {
+            Some(within_view) => parse_within_view(internals.attr_coercion_context(), within_view)
+                .context("When parsing `within_view` attribute")?,
+            None => WithinViewSpecification::Public,
+        };
+
         let label = TargetLabel::new(package.dupe(), target_name);
         let mut deps_cache = CoercedDepsCollector::new();
 
@@ -164,6 +177,7 @@ impl TargetNodeExt for TargetNode {
             attr_values,
             deps_cache,
             visibility,
+            within_view,
             call_stack.map(StarlarkCallStack::new),
             oncall,
         ))
@@ -209,3 +223,20 @@ fn parse_visibility(
         Ok(VisibilitySpecification::VisibleTo(specs))
     }
 }
+
+fn parse_within_view(
+    ctx: &dyn AttrCoercionContext,
+    attr: &CoercedAttr,
+) -> anyhow::Result<WithinViewSpecification> {
+    // `within_view` takes the same patterns as `visibility`, but `None` or an empty list don't
+    // restrict anything.
+    if let CoercedAttr::Literal(AttrLiteral::None) = attr {
+        return Ok(WithinViewSpecification::Public);
+    }
+    match parse_visibility(ctx, attr)? {
+        VisibilitySpecification::Public | VisibilitySpecification::Default => {
+            Ok(WithinViewSpecification::Public)
+        }
+        VisibilitySpecification::VisibleTo(specs) => Ok(WithinViewSpecification::VisibleTo(specs)),
+    }
+}
@@ -0,0 +1,14 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The subset of the OpenTelemetry protocol (OTLP) used to export traces to a collector.
+
+pub mod trace_service {
+    tonic::include_proto!("opentelemetry.proto.collector.trace.v1");
+}
@@ -30,6 +30,8 @@
 //!        | EXPR ' + ' EXPR
 //!        | EXPR ' except ' EXPR
 //!        | EXPR ' - ' EXPR
+//!        | 'let' NAME '=' EXPR 'in' EXPR
+//!        | '$' NAME
 //!
 //! # word is much broader than a normal identifier-like thing would allow since we don't want to require
 //! # quoting targets "@fbcode//some:target" or common regexes ".*" or filenames "Foo.java".
@@ -42,6 +44,9 @@
 //!
 //! FUNCTION_NAME ::= "a-zA-Z_" "a-zA-Z0-9_" *
 //!
+//! # a `$` followed by a word character is still a word, so `$foo/bar` remains a literal.
+//! NAME ::= "a-zA-Z0-9_" +
+//!
 //! ```
 
 #![feature(box_syntax)]
@@ -66,6 +71,8 @@ use nom::character::complete::multispace0;
 use nom::character::complete::multispace1;
 use nom::combinator::all_consuming;
 use nom::combinator::cut;
+use nom::combinator::not;
+use nom::combinator::peek;
 use nom::combinator::recognize;
 use nom::error::context;
 use nom::error::convert_error;
@@ -84,8 +91,6 @@ use thiserror::Error;
 use crate::span::Span;
 use crate::spanned::Spanned;
 
-// TODO(cjhopman): Add `LET WORD = expr IN expr`
-
 // TODO(cjhopman): We should switch to our own error type here. VerboseError doesn't even allow us to construct
 // our own error messages (so, for example, we can't have a good error message for too large integers) and doesn't
 // support propagating anyhow or std errors (and since we can't do a custom message, we can't even capture them as a string).
@@ -123,6 +128,16 @@ pub enum Expr<'a> {
     BinaryOpSequence(Box<SpannedExpr<'a>>, Vec<(BinaryOp, SpannedExpr<'a>)>),
     Set(Vec<Span<'a>>),
     FileSet(Vec<Span<'a>>),
+    /// `let name = value in body`. The value is evaluated once and is visible as `$name` within
+    /// the body only.
+    Let {
+        name: Span<'a>,
+        value: Box<SpannedExpr<'a>>,
+        body: Box<SpannedExpr<'a>>,
+    },
+    /// A reference to a name bound by an enclosing `let` (or a query macro argument), without the
+    /// leading `$`.
+    Variable(Span<'a>),
 }
 
 impl Display for Expr<'_> {
@@ -179,6 +194,10 @@ impl Display for Expr<'_> {
                 }
                 f.write_str(")")?;
             }
+            Expr::Let { name, value, body } => {
+                write!(f, "let {} = {} in {}", name.fragment(), value, body)?;
+            }
+            Expr::Variable(name) => write!(f, "${}", name.fragment())?,
         }
         Ok(())
     }
@@ -274,10 +293,12 @@ fn single_expr<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Spann
     // parse an expression from the beginning of the input and check after if there's a "trailing" infix operator.
     let (input, left_expr) = alt((
         preceded(char('('), cut(terminated(expr, char(')')))),
+        expr_let,
         expr_set,
         expr_fileset,
         expr_function,
         expr_int,
+        expr_variable,
         expr_word,
     ))(input)?;
 
@@ -348,11 +369,47 @@ fn expr_int<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, SpannedE
     })(input)
 }
 
-fn word<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
-    fn non_quoted_word<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
-        recognize(many1(alt((alphanumeric1, is_a("*/@.-_:$#%")))))(input)
-    }
+fn non_quoted_word<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
+    recognize(many1(alt((alphanumeric1, is_a("*/@.-_:$#%")))))(input)
+}
+
+fn name<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
+    recognize(many1(alt((alphanumeric1, tag("_")))))(input)
+}
+
+/// Tries to parse an Expr::Variable. Anything that continues past the name with other word
+/// characters (ex. `$foo/bar`) is left to be parsed as a word.
+fn expr_variable<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, SpannedExpr<'a>, E> {
+    spanned(|input| {
+        let (input, name) =
+            terminated(preceded(char('$'), name), not(peek(non_quoted_word)))(input)?;
+        Ok((input, Expr::Variable(name)))
+    })(input)
+}
 
+/// Tries to parse an Expr::Let. Will fail if it detects an unfinished "let NAME ="
+fn expr_let<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, SpannedExpr<'a>, E> {
+    spanned(|input| {
+        let (input, _) = terminated(tag("let"), multispace1)(input)?;
+        let (input, name) = terminated(name, multispace0)(input)?;
+        let (input, _) = char('=')(input)?;
+        cut(move |input| {
+            let (input, value) = expr(input)?;
+            let (input, _) = terminated(tag("in"), multispace1)(input)?;
+            let (input, body) = expr(input)?;
+            Ok((
+                input,
+                Expr::Let {
+                    name,
+                    value: box value,
+                    body: box body,
+                },
+            ))
+        })(input)
+    })(input)
+}
+
+fn word<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
     alt((
         preceded(
             char('\''),
@@ -499,9 +556,11 @@ mod tests {
                 "a + b",
                 "(a - (b))",
                 "123",
+                "let x = deps(a) in $x + b",
+                "let x=a in let y = $x in f($y, $x)",
             ],
             &[],
-            &["func(", "set(", "(a", "01234"],
+            &["func(", "set(", "(a", "01234", "let x = a", "let x = a in"],
         );
 
         match parse_expr("set(a b c)") {
@@ -536,6 +595,59 @@ mod tests {
             v => panic!("expected '//:tgt', got `{:?}`", v),
         }
 
+        match parse_expr("let x = a in $x ^ b") {
+            Ok(Spanned {
+                value: Expr::Let { body, .. },
+                ..
+            }) => match body.value {
+                Expr::BinaryOpSequence(..) => {}
+                v => panic!("expected let body to be an intersect expr, got `{:?}`", v),
+            },
+            v => panic!("expected let expr, got `{:?}`", v),
+        }
+
+        match parse_expr("$foo/bar") {
+            Ok(Spanned {
+                value: Expr::String("$foo/bar"),
+                ..
+            }) => {}
+            v => panic!("expected '$foo/bar', got `{:?}`", v),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_let() -> anyhow::Result<()> {
+        run_tests(
+            expr_let,
+            &[
+                "let x = a in b",
+                "let x=a in $x",
+                "let x_1 = f(a) in g($x_1)",
+            ],
+            // As long as we don't match "let NAME =", it should be recoverable
+            &[
+                "let",
+                "letx = a in b",
+                "let(x)",
+                "let x in b",
+                "lets x = a in b",
+            ],
+            // An error after the "=" is non-recoverable
+            &["let x =", "let x = a", "let x = a inb", "let x = a in"],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable() -> anyhow::Result<()> {
+        run_tests(
+            expr_variable,
+            &["$x", "$x_1", "$1"],
+            &["x", "$", "$/x", "$x/y", "$x.*"],
+            &[],
+        );
         Ok(())
     }
 
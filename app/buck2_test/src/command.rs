@@ -24,6 +24,7 @@ use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::liveliness_manager::LivelinessGuard;
 use buck2_common::pattern::resolve::ResolvedPattern;
 use buck2_core::cells::CellResolver;
+use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::*;
 use buck2_core::package::Package;
@@ -70,8 +71,12 @@ use crate::executor_launcher::ExecutorLauncher;
 use crate::executor_launcher::OutOfProcessTestExecutor;
 use crate::orchestrator::BuckTestOrchestrator;
 use crate::orchestrator::TestResultOrExitCode;
+use crate::report;
+use crate::report::ReportedTest;
+use crate::session::TestRetries;
 use crate::session::TestSession;
 use crate::session::TestSessionOptions;
+use crate::sharding::TestSharding;
 use crate::translations::build_configured_target_handle;
 
 #[derive(Debug, Serialize)]
@@ -100,16 +105,28 @@ impl TestOutcome {
 struct ExecutorReport {
     exit_code: Option<i32>,
     statuses: TestStatuses,
+    /// All the results, if we were asked to write reports.
+    results: Option<Vec<TestResult>>,
 }
 
 impl ExecutorReport {
-    fn ingest(&mut self, status: &TestResultOrExitCode) {
+    fn new(keep_results: bool) -> Self {
+        Self {
+            results: keep_results.then(Vec::new),
+            ..Default::default()
+        }
+    }
+
+    fn ingest(&mut self, status: TestResultOrExitCode) {
         match status {
             TestResultOrExitCode::TestResult(res) => {
-                self.statuses.ingest(res);
+                self.statuses.ingest(&res);
+                if let Some(results) = &mut self.results {
+                    results.push(res);
+                }
             }
             TestResultOrExitCode::ExitCode(exit_code) => {
-                self.exit_code = Some(*exit_code);
+                self.exit_code = Some(exit_code);
             }
         }
     }
@@ -156,6 +173,7 @@ struct TestStatuses {
     fatals: CounterWithExamples,
     listing_success: CounterWithExamples,
     listing_failed: CounterWithExamples,
+    flaky: CounterWithExamples,
 }
 impl TestStatuses {
     fn ingest(&mut self, result: &TestResult) {
@@ -170,6 +188,7 @@ impl TestStatuses {
             TestStatus::RERUN => {}
             TestStatus::LISTING_SUCCESS => self.listing_success.add(&result.name),
             TestStatus::LISTING_FAILED => self.listing_failed.add(&result.name),
+            TestStatus::FLAKY => self.flaky.add(&result.name),
         }
     }
 }
@@ -223,12 +242,10 @@ async fn test(
 
     // Get the test runner from the config. Note that we use a different key from v1 since the API
     // is completely different, so there is not expectation that the same binary works for both.
+    // Without one, we use the executor built into Buck.
     let test_executor = ctx
         .get_legacy_config_property(cell_resolver.root_cell(), "test", "v2_test_executor")
-        .await?
-        .context("test.v2_test_executor must be set in configuration")?
-        .as_ref()
-        .to_owned();
+        .await?;
 
     let parsed_patterns = parse_patterns_from_cli_args(
         &request.target_patterns,
@@ -241,9 +258,14 @@ async fn test(
     let resolved_pattern =
         resolve_patterns(&parsed_patterns, &cell_resolver, &ctx.file_ops()).await?;
 
-    let launcher: Box<dyn ExecutorLauncher> = box OutOfProcessTestExecutor {
-        name: test_executor,
-        dispatcher: ctx.per_transaction_data().get_dispatcher().dupe(),
+    let dispatcher = ctx.per_transaction_data().get_dispatcher().dupe();
+    let launcher: Box<dyn ExecutorLauncher> = match test_executor {
+        Some(test_executor) => box OutOfProcessTestExecutor {
+            name: test_executor.as_ref().to_owned(),
+            args: Vec::new(),
+            dispatcher,
+        },
+        None => box OutOfProcessTestExecutor::builtin(dispatcher)?,
     };
 
     let options = request
@@ -251,11 +273,23 @@ async fn test(
         .as_ref()
         .context("Missing `options`")?;
 
-    let session = TestSession::new(TestSessionOptions {
+    let session = Arc::new(TestSession::new(TestSessionOptions {
         allow_re: options.allow_re,
         force_use_project_relative_paths: options.force_use_project_relative_paths,
         force_run_from_project_root: options.force_run_from_project_root,
-    });
+        allow_test_cache: options.allow_test_cache,
+        retries: TestRetries {
+            default: options.retries,
+            by_label: options.label_retries.clone(),
+        },
+    }));
+
+    let sharding = request
+        .sharding
+        .as_ref()
+        .map(|sharding| TestSharding::new(sharding.index, sharding.count, sharding.timings.clone()))
+        .transpose()
+        .context("Invalid test sharding")?;
 
     let test_outcome = test_targets(
         &ctx,
@@ -268,12 +302,18 @@ async fn test(
             request.always_exclude,
             request.build_filtered_targets,
         )),
+        sharding,
         &*launcher,
-        session,
+        session.dupe(),
         cell_resolver,
+        request.junit_xml_path.is_some() || request.json_report_path.is_some(),
     )
     .await?;
 
+    if let Some(results) = &test_outcome.executor_report.results {
+        write_reports(request, &session, results)?;
+    }
+
     // TODO(bobyf) remap exit code for buck reserved exit code
     let exit_code = test_outcome.exit_code().context("No exit code available")?;
 
@@ -320,6 +360,13 @@ async fn test(
                 .listing_failed
                 .to_cli_proto_counter(),
         ),
+        flaky: Some(
+            test_outcome
+                .executor_report
+                .statuses
+                .flaky
+                .to_cli_proto_counter(),
+        ),
     };
 
     Ok(TestResponse {
@@ -329,17 +376,44 @@ async fn test(
     })
 }
 
+fn write_reports(
+    request: &TestRequest,
+    session: &TestSession,
+    results: &[TestResult],
+) -> anyhow::Result<()> {
+    let tests = results
+        .iter()
+        .map(|result| {
+            let label = session.get(result.target)?;
+            Ok(ReportedTest {
+                target: label.unconfigured().to_string(),
+                configuration: label.cfg().to_string(),
+                result: result.clone(),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if let Some(path) = &request.junit_xml_path {
+        fs_util::write(path, report::junit_xml(&tests)).context("Error writing JUnit report")?;
+    }
+    if let Some(path) = &request.json_report_path {
+        fs_util::write(path, report::json(&tests)?).context("Error writing JSON report")?;
+    }
+    Ok(())
+}
+
 async fn test_targets(
     ctx: &DiceComputations,
     pattern: ResolvedPattern<ProvidersPattern>,
     global_target_platform: Option<TargetLabel>,
     external_runner_args: Vec<String>,
     label_filtering: Arc<TestLabelFiltering>,
+    sharding: Option<TestSharding>,
     launcher: &dyn ExecutorLauncher,
-    session: TestSession,
+    session: Arc<TestSession>,
     cell_resolver: CellResolver,
+    keep_results: bool,
 ) -> anyhow::Result<TestOutcome> {
-    let session = Arc::new(session);
     let (liveliness_manager, _guard) = LivelinessGuard::create();
 
     let tpx_args = {
@@ -387,6 +461,7 @@ async fn test_targets(
                 let mut driver = TestDriver::new(TestDriverState {
                     ctx: &ctx,
                     label_filtering: &label_filtering,
+                    sharding: &sharding,
                     global_target_platform: &global_target_platform,
                     session: &session,
                     test_executor: &test_executor,
@@ -416,8 +491,8 @@ async fn test_targets(
                 // Wait for the tests to finish running.
 
                 let test_statuses = test_status_receiver
-                    .try_fold(ExecutorReport::default(), |mut acc, result| {
-                        acc.ingest(&result);
+                    .try_fold(ExecutorReport::new(keep_results), |mut acc, result| {
+                        acc.ingest(result);
                         future::ready(Ok(acc))
                     })
                     .await
@@ -485,6 +560,7 @@ enum TestDriverTask {
 pub(crate) struct TestDriverState<'a, 'e> {
     ctx: &'a DiceComputations,
     label_filtering: &'a Arc<TestLabelFiltering>,
+    sharding: &'a Option<TestSharding>,
     global_target_platform: &'a Option<TargetLabel>,
     session: &'a TestSession,
     test_executor: &'a Arc<dyn TestExecutor + 'e>,
@@ -617,6 +693,7 @@ impl<'a, 'e> TestDriver<'a, 'e> {
                     state.test_executor.dupe(),
                     state.session,
                     state.label_filtering.dupe(),
+                    state.sharding.as_ref(),
                     state.cell_resolver,
                 )
                 .await?;
@@ -675,6 +752,7 @@ async fn test_target(
     test_executor: Arc<dyn TestExecutor + '_>,
     session: &TestSession,
     label_filtering: Arc<TestLabelFiltering>,
+    sharding: Option<&TestSharding>,
     cell_resolver: &CellResolver,
 ) -> anyhow::Result<Option<ConfiguredProvidersLabel>> {
     // NOTE: We fail if we hit an incompatible target here. This can happen if we reach an
@@ -682,6 +760,16 @@ async fn test_target(
     // in v1: https://fb.workplace.com/groups/buckeng/posts/8520953297953210
     let frozen_providers = ctx.get_providers(&target).await?.require_compatible()?;
     let providers = frozen_providers.provider_collection();
+
+    if let Some(sharding) = sharding {
+        // Targets that aren't tests don't need a shard, they have nothing to run.
+        if <dyn TestProvider>::from_collection(providers).is_some()
+            && !in_shard(ctx, sharding, &target)
+        {
+            return Ok(None);
+        }
+    }
+
     build_artifacts(ctx, providers, &label_filtering).await?;
 
     let fut = match <dyn TestProvider>::from_collection(providers) {
@@ -705,6 +793,25 @@ async fn test_target(
     fut.await
 }
 
+/// Whether `target` belongs to the shard we are running. The decision is recorded in the event log.
+fn in_shard(
+    ctx: &DiceComputations,
+    sharding: &TestSharding,
+    target: &ConfiguredProvidersLabel,
+) -> bool {
+    let assignment = sharding.assign(target);
+    ctx.per_transaction_data()
+        .get_dispatcher()
+        .instant_event(buck2_data::TestShardAssignment {
+            target: target.unconfigured().to_string(),
+            shard: assignment.shard,
+            shard_index: sharding.index(),
+            shard_count: sharding.count(),
+            strategy: assignment.strategy.as_str().to_owned(),
+        });
+    assignment.shard == sharding.index()
+}
+
 fn skip_run_based_on_labels(
     provider: &dyn TestProvider,
     label_filtering: &TestLabelFiltering,
@@ -55,9 +55,26 @@ pub trait ExecutorLauncher: Send + Sync {
 
 pub struct OutOfProcessTestExecutor {
     pub name: String,
+    /// Arguments passed before the ones Buck adds, e.g. to select a subcommand.
+    pub args: Vec<String>,
     pub dispatcher: EventDispatcher,
 }
 
+impl OutOfProcessTestExecutor {
+    /// The executor built into Buck, which is run as a hidden subcommand of the current binary.
+    pub fn builtin(dispatcher: EventDispatcher) -> anyhow::Result<Self> {
+        let exe = std::env::current_exe().context("Cannot access current_exe")?;
+        Ok(Self {
+            name: exe
+                .to_str()
+                .with_context(|| format!("Path to Buck is not valid UTF-8: {}", exe.display()))?
+                .to_owned(),
+            args: vec!["internal-test-runner".to_owned()],
+            dispatcher,
+        })
+    }
+}
+
 #[async_trait]
 impl ExecutorLauncher for OutOfProcessTestExecutor {
     async fn launch(&self, tpx_args: Vec<String>) -> anyhow::Result<ExecutorLaunch> {
@@ -70,7 +87,7 @@ impl ExecutorLauncher for OutOfProcessTestExecutor {
             let use_tcp = BUCK2_TEST_TPX_USE_TCP.get_copied()?.unwrap_or_default();
             if !use_tcp {
                 return spawn_orchestrator(
-                    crate::unix::executor::spawn(&self.name, tpx_args).await?,
+                    crate::unix::executor::spawn(&self.name, &self.args, tpx_args).await?,
                     self.dispatcher.dupe(),
                 )
                 .await;
@@ -78,7 +95,7 @@ impl ExecutorLauncher for OutOfProcessTestExecutor {
         }
 
         spawn_orchestrator(
-            crate::tcp::executor::spawn(&self.name, tpx_args).await?,
+            crate::tcp::executor::spawn(&self.name, &self.args, tpx_args).await?,
             self.dispatcher.dupe(),
         )
         .await
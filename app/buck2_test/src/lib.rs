@@ -16,8 +16,11 @@ pub mod command;
 pub mod downward_api;
 pub mod executor_launcher;
 pub mod orchestrator;
+pub(crate) mod report;
 pub mod session;
+pub(crate) mod sharding;
 pub(crate) mod tcp;
+pub mod test_cache;
 pub mod translations;
 #[cfg(unix)]
 pub(crate) mod unix;
@@ -9,6 +9,7 @@
 
 //! Implementation of the `TestOrchestrator` from `buck2_test_api`.
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::sync::Arc;
@@ -30,6 +31,7 @@ use buck2_build_api::interpreter::rule_defs::provider::builtin::external_runner_
 use buck2_build_api::interpreter::rule_defs::provider::builtin::external_runner_test_info::TestCommandMember;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::executor_config::CommandExecutorConfig;
+use buck2_common::file_ops::FileDigest;
 use buck2_common::liveliness_manager::LivelinessManager;
 use buck2_core::category::Category;
 use buck2_core::cells::cell_root_path::CellRootPathBuf;
@@ -89,11 +91,13 @@ use buck2_test_api::data::ExternalRunnerSpecValue;
 use buck2_test_api::data::Output;
 use buck2_test_api::data::PrepareForLocalExecutionResult;
 use buck2_test_api::data::TestResult;
+use buck2_test_api::data::TestStatus;
 use buck2_test_api::protocol::TestOrchestrator;
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use dice::DiceTransaction;
 use futures::channel::mpsc::UnboundedSender;
+use futures::Future;
 use gazebo::prelude::*;
 use host_sharing::HostSharingRequirements;
 use indexmap::IndexMap;
@@ -102,6 +106,8 @@ use once_cell::sync::Lazy;
 use uuid::Uuid;
 
 use crate::session::TestSession;
+use crate::test_cache::LocalTestCache;
+use crate::test_cache::CACHEABLE_TEST_LABEL;
 use crate::translations;
 
 static TEST_CATEGORY: Lazy<Category> = Lazy::new(|| Category::try_from("test").unwrap());
@@ -123,9 +129,22 @@ pub struct BuckTestOrchestrator {
     /// identifiers (e.g. Uuid or similar) because each might create some temporary outputs on disk,
     /// so use sequential identifiers for each target.
     identifiers: DashMap<ConfiguredTargetLabel, usize>,
+    /// How the most recent execution of each test went, by target and test name, used to annotate
+    /// the results the executor reports.
+    attempts: DashMap<(ConfiguredTargetHandle, String), TestAttempts>,
     liveliness_manager: Arc<dyn LivelinessManager>,
 }
 
+/// The orchestrator reruns failing tests before handing the result to the executor, so the
+/// executor doesn't know about retries: we keep track of them here instead.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+struct TestAttempts {
+    /// How many times the test was run.
+    count: u32,
+    /// Whether the test failed, but passed when retried.
+    recovered: bool,
+}
+
 impl BuckTestOrchestrator {
     pub async fn new(
         dice: DiceTransaction,
@@ -157,6 +176,7 @@ impl BuckTestOrchestrator {
             results_channel,
             events,
             identifiers: Default::default(),
+            attempts: Default::default(),
         }
     }
 
@@ -195,6 +215,7 @@ impl TestOrchestrator for BuckTestOrchestrator {
     ) -> anyhow::Result<ExecutionResult2> {
         self.liveliness_manager.require_alive().await?;
 
+        let handle = test_target;
         let test_target = self.session.get(test_target)?;
 
         let fs = self.dice.get_artifact_fs().await?;
@@ -207,6 +228,7 @@ impl TestOrchestrator for BuckTestOrchestrator {
                 env,
                 pre_create_dirs,
                 executor_override,
+                TestOutputRoot::Cacheable,
             )
             .await?;
 
@@ -218,6 +240,8 @@ impl TestOrchestrator for BuckTestOrchestrator {
             supports_re,
             declared_outputs,
             executor,
+            cacheable,
+            retries,
         } = test_executable_expanded;
         let execution_request = self
             .create_command_execution_request(
@@ -227,17 +251,60 @@ impl TestOrchestrator for BuckTestOrchestrator {
                 inputs,
                 declared_outputs,
             )
-            .await?;
+            .await?
+            .with_allow_cache_lookup(cacheable)
+            .with_allow_cache_upload(cacheable);
+
+        let cache_key = if cacheable {
+            let digest = executor
+                .action_digest(&execution_request)
+                .context("Error computing test action digest")?;
+            let cached = self
+                .dice
+                .get_blocking_executor()
+                .execute_io_inline(|| LocalTestCache::new(&fs).lookup(&digest))
+                .await?;
+            if let Some(cached) = cached {
+                tracing::debug!(
+                    "Reusing cached result of `{}` for `{}`",
+                    digest,
+                    test_target
+                );
+                return Ok(cached);
+            }
+            Some(digest)
+        } else {
+            None
+        };
+
+        let execution_request = self.with_execution_options(
+            execution_request,
+            Some(host_sharing_requirements),
+            timeout,
+            supports_re,
+        )?;
 
+        let (test_target, metadata, executor, execution_request) =
+            (&test_target, &metadata, &executor, &execution_request);
         let (stdout, stderr, status, timing, outputs) = self
-            .execute_shared(
-                test_target,
+            .execute_with_retries(
+                handle,
                 metadata,
-                Some(host_sharing_requirements),
-                timeout,
-                supports_re,
-                &executor,
-                execution_request,
+                retries,
+                |execution| matches!(execution.2, ExecutionStatus::Finished { exitcode: 0 }),
+                |attempt| async move {
+                    if attempt > 1 {
+                        self.liveliness_manager.require_alive().await?;
+                        tracing::info!(
+                            "Retrying `{}` after attempt {} of {} failed",
+                            test_target,
+                            attempt - 1,
+                            retries + 1
+                        );
+                    }
+                    self.execute_shared(test_target, metadata.clone(), executor, execution_request)
+                        .await
+                },
             )
             .await?;
 
@@ -265,17 +332,39 @@ impl TestOrchestrator for BuckTestOrchestrator {
             .await
             .context("Error materializing test outputs")?;
 
-        Ok(ExecutionResult2 {
+        let result = ExecutionResult2 {
             status,
             stdout,
             stderr,
             outputs,
             start_time: timing.start_time,
             execution_time: timing.execution_time,
-        })
+        };
+
+        if let Some(digest) = cache_key {
+            // Failing to record a result only costs us a rerun later, so it doesn't fail the test.
+            if let Err(e) = self
+                .dice
+                .get_blocking_executor()
+                .execute_io_inline(|| LocalTestCache::new(&fs).store(&digest, &result))
+                .await
+            {
+                tracing::warn!("Error caching the result of `{}`: {:#}", digest, e);
+            }
+        }
+
+        Ok(result)
     }
 
-    async fn report_test_result(&self, r: TestResult) -> anyhow::Result<()> {
+    async fn report_test_result(&self, mut r: TestResult) -> anyhow::Result<()> {
+        // Results of later executions of the same test shouldn't be annotated with this one.
+        if let Some((_, attempts)) = self.attempts.remove(&(r.target, r.name.clone())) {
+            r.attempt = attempts.count;
+            if attempts.recovered && r.status == TestStatus::PASS {
+                r.status = TestStatus::FLAKY;
+            }
+        }
+
         let event = buck2_data::instant_event::Data::TestResult(translations::convert_test_result(
             r.clone(),
         )?);
@@ -341,6 +430,7 @@ impl TestOrchestrator for BuckTestOrchestrator {
                 env,
                 pre_create_dirs,
                 None, // No executor used, so there isn't a executor to override.
+                TestOutputRoot::Unique,
             )
             .await?;
 
@@ -352,6 +442,8 @@ impl TestOrchestrator for BuckTestOrchestrator {
             supports_re: _,
             declared_outputs,
             executor: _,
+            cacheable: _,
+            retries: _,
         } = test_executable_expanded;
 
         let execution_request = self
@@ -385,22 +477,61 @@ impl TestOrchestrator for BuckTestOrchestrator {
 }
 
 impl BuckTestOrchestrator {
-    async fn execute_shared(
+    /// Run a test until it passes, at most `retries + 1` times, and record how many times it ran
+    /// for the tests named in `metadata`. Returns the last execution.
+    async fn execute_with_retries<T, Fut>(
         &self,
-        test_target: ConfiguredProvidersLabel,
-        metadata: DisplayMetadata,
+        handle: ConfiguredTargetHandle,
+        metadata: &DisplayMetadata,
+        retries: u32,
+        passed: impl Fn(&T) -> bool,
+        mut execute: impl FnMut(u32) -> Fut,
+    ) -> anyhow::Result<T>
+    where
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let execution = execute(attempt).await?;
+            let passed = passed(&execution);
+            if passed || attempt > retries {
+                self.record_attempts(handle, metadata, attempt, passed && attempt > 1);
+                return Ok(execution);
+            }
+        }
+    }
+
+    fn record_attempts(
+        &self,
+        handle: ConfiguredTargetHandle,
+        metadata: &DisplayMetadata,
+        count: u32,
+        recovered: bool,
+    ) {
+        // The executor reports results by test name: the test cases it asked us to run, or the
+        // suite if it ran all of them. We can't tell which of several test cases run together
+        // failed, so they all count as recovered.
+        let names = match metadata {
+            DisplayMetadata::Listing(_) => return,
+            DisplayMetadata::Testing { suite, testcases } if testcases.is_empty() => {
+                std::slice::from_ref(suite)
+            }
+            DisplayMetadata::Testing { testcases, .. } => testcases.as_slice(),
+        };
+        for name in names {
+            self.attempts
+                .insert((handle, name.clone()), TestAttempts { count, recovered });
+        }
+    }
+
+    fn with_execution_options(
+        &self,
+        mut request: CommandExecutionRequest,
         host_sharing_requirements: Option<HostSharingRequirements>,
         timeout: Duration,
         supports_re: bool,
-        executor: &CommandExecutor,
-        mut request: CommandExecutionRequest,
-    ) -> anyhow::Result<(
-        ExecutionStream,
-        ExecutionStream,
-        ExecutionStatus,
-        CommandExecutionTimingData,
-        IndexMap<CommandExecutionOutput, ArtifactValue>,
-    )> {
+    ) -> anyhow::Result<CommandExecutionRequest> {
         let mut executor_preference = ExecutorPreference::Default;
 
         if !self.session.options().allow_re {
@@ -421,7 +552,22 @@ impl BuckTestOrchestrator {
         if let Some(requirements) = host_sharing_requirements {
             request = request.with_host_sharing_requirements(requirements);
         }
+        Ok(request)
+    }
 
+    async fn execute_shared(
+        &self,
+        test_target: &ConfiguredProvidersLabel,
+        metadata: DisplayMetadata,
+        executor: &CommandExecutor,
+        request: &CommandExecutionRequest,
+    ) -> anyhow::Result<(
+        ExecutionStream,
+        ExecutionStream,
+        ExecutionStatus,
+        CommandExecutionTimingData,
+        IndexMap<CommandExecutionOutput, ArtifactValue>,
+    )> {
         let manager = CommandExecutionManager::new(
             box MutexClaimManager::new(),
             self.events.dupe(),
@@ -443,7 +589,7 @@ impl BuckTestOrchestrator {
                 identifier: Some(&identifier),
                 action_key: &action_key as _,
             },
-            &request,
+            request,
             manager,
         );
 
@@ -561,6 +707,7 @@ impl BuckTestOrchestrator {
         env: HashMap<String, ArgValue>,
         pre_create_dirs: Vec<DeclaredOutput>,
         executor_override: Option<ExecutorConfigOverride>,
+        output_location: TestOutputRoot,
     ) -> anyhow::Result<ExpandedTestExecutable> {
         // NOTE: get_providers() implicitly calls this already but it's not the end of the world
         // since this will get cached in DICE.
@@ -576,13 +723,6 @@ impl BuckTestOrchestrator {
             .await?
             .require_compatible()?;
 
-        let output_root = self
-            .session
-            .prefix()
-            .join(ForwardRelativePathBuf::unchecked_new(
-                Uuid::new_v4().to_string(),
-            ));
-
         let mut declared_outputs = IndexMap::<BuckOutTestPath, OutputCreationBehavior>::new();
 
         let mut supports_re = true;
@@ -590,6 +730,9 @@ impl BuckTestOrchestrator {
         let cwd;
         let expanded;
         let executor;
+        let cacheable;
+        let retries;
+        let output_root;
 
         {
             let opts = self.session.options();
@@ -599,6 +742,24 @@ impl BuckTestOrchestrator {
                 .get_provider(ExternalRunnerTestInfoCallable::provider_id_t())
                 .context("Test executable only supports ExternalRunnerTestInfo providers")?;
 
+            cacheable = opts.allow_test_cache
+                && test_info
+                    .labels()
+                    .any(|label| label == CACHEABLE_TEST_LABEL);
+            retries = opts.retries.for_labels(test_info.labels());
+
+            output_root = match output_location {
+                TestOutputRoot::Cacheable if cacheable => {
+                    cacheable_output_root(test_target, &cmd, &env, &pre_create_dirs)
+                }
+                _ => self
+                    .session
+                    .prefix()
+                    .join(ForwardRelativePathBuf::unchecked_new(
+                        Uuid::new_v4().to_string(),
+                    )),
+            };
+
             cwd = if test_info.run_from_project_root() || opts.force_run_from_project_root {
                 CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("".to_owned()))
             } else {
@@ -672,6 +833,8 @@ impl BuckTestOrchestrator {
             declared_outputs,
             supports_re,
             executor,
+            cacheable,
+            retries,
         })
     }
 
@@ -864,6 +1027,37 @@ impl<'a> CommandLineBuilder for CommandLineBuilderFormatWrapper<'a> {
     }
 }
 
+/// Where a test writes its outputs, relative to the test outputs directory.
+#[derive(Clone, Copy, Dupe)]
+enum TestOutputRoot {
+    /// A new directory for this run.
+    Unique,
+    /// Cacheable tests write their outputs in the same directory in all runs of the same test,
+    /// since outputs are part of the action digest, see `cacheable_output_root`. Tests that
+    /// aren't cacheable use a new directory.
+    Cacheable,
+}
+
+/// The output directory of a cacheable test. It is named after the test and its command before
+/// expansion, so that it does not depend on the outputs it contains and the test only needs to
+/// be expanded once. Changes to the inputs of the test reuse the directory, but still change the
+/// action digest the test is cached under.
+fn cacheable_output_root(
+    test_target: &ConfiguredProvidersLabel,
+    cmd: &[ArgValue],
+    env: &HashMap<String, ArgValue>,
+    pre_create_dirs: &[DeclaredOutput],
+) -> ForwardRelativePathBuf {
+    let env = env.iter().collect::<BTreeMap<_, _>>();
+    let spec = format!("{} {:?} {:?} {:?}", test_target, cmd, env, pre_create_dirs);
+    let digest = FileDigest::from_bytes_sha1(spec.as_bytes());
+    // The digest is formatted as `hash:size`, and colons don't belong in paths on Windows.
+    ForwardRelativePathBuf::unchecked_new(format!(
+        "cacheable/{}",
+        digest.to_string().replace(':', "_")
+    ))
+}
+
 struct ExpandedTestExecutable {
     cwd: ProjectRelativePathBuf,
     cmd: Vec<String>,
@@ -872,6 +1066,10 @@ struct ExpandedTestExecutable {
     supports_re: bool,
     declared_outputs: IndexMap<BuckOutTestPath, OutputCreationBehavior>,
     executor: CommandExecutor,
+    /// Whether the result may be reused by later runs, see `test_cache`.
+    cacheable: bool,
+    /// How many times to rerun the test if it fails.
+    retries: u32,
 }
 
 fn create_prepare_for_local_execution_result(
@@ -970,7 +1168,6 @@ mod tests {
     use buck2_core::fs::project::ProjectRootTemp;
     use buck2_events::dispatch::EventDispatcher;
     use buck2_test_api::data::testing::ConfiguredTargetHandleExt;
-    use buck2_test_api::data::TestStatus;
     use dice::testing::DiceBuilder;
     use dice::UserComputationData;
     use futures::channel::mpsc;
@@ -979,6 +1176,7 @@ mod tests {
     use futures::stream::TryStreamExt;
 
     use super::*;
+    use crate::session::TestRetries;
 
     fn make() -> anyhow::Result<(
         BuckTestOrchestrator,
@@ -1026,6 +1224,8 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    attempt: 1,
+                    stderr: String::new(),
                 })
                 .await?;
 
@@ -1037,6 +1237,8 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    attempt: 1,
+                    stderr: String::new(),
                 })
                 .await?;
 
@@ -1058,6 +1260,8 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    attempt: 1,
+                    stderr: String::new(),
                 }),
                 TestResultOrExitCode::TestResult(TestResult {
                     target: ConfiguredTargetHandle::testing_new(0),
@@ -1067,6 +1271,8 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    attempt: 1,
+                    stderr: String::new(),
                 }),
                 TestResultOrExitCode::ExitCode(0),
             ]
@@ -1097,4 +1303,99 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn orchestrator_retried_results() -> anyhow::Result<()> {
+        let (orchestrator, channel) = make()?;
+        let handle = ConfiguredTargetHandle::testing_new(0);
+        let retries = TestRetries {
+            default: 0,
+            by_label: HashMap::from([("flaky".to_owned(), 2)]),
+        };
+
+        // Runs the test `name` with a fake executor which fails the first `failures` times, and
+        // returns the attempts it saw along with the final status.
+        let run = |name: &'static str, labels: &'static [&'static str], failures: u32| {
+            let orchestrator = &orchestrator;
+            let retries = retries.for_labels(labels.iter().copied());
+            async move {
+                let mut attempts = Vec::new();
+                let status = orchestrator
+                    .execute_with_retries(
+                        handle,
+                        &DisplayMetadata::Testing {
+                            suite: "suite".to_owned(),
+                            testcases: vec![name.to_owned()],
+                        },
+                        retries,
+                        |status| *status == TestStatus::PASS,
+                        |attempt| {
+                            attempts.push(attempt);
+                            future::ready(anyhow::Ok(if attempt <= failures {
+                                TestStatus::FAIL
+                            } else {
+                                TestStatus::PASS
+                            }))
+                        },
+                    )
+                    .await?;
+                orchestrator
+                    .report_test_result(TestResult {
+                        target: handle,
+                        status,
+                        msg: None,
+                        name: name.to_owned(),
+                        duration: None,
+                        details: String::new(),
+                        attempt: 1,
+                        stderr: String::new(),
+                    })
+                    .await?;
+                anyhow::Ok(attempts)
+            }
+        };
+
+        let jobs = async {
+            // Passes when retried.
+            assert_eq!(run("a", &["flaky"], 1).await?, vec![1, 2]);
+            // Fails without retries.
+            assert_eq!(run("b", &[], 1).await?, vec![1]);
+            // Passes in the same target as a flaky test.
+            assert_eq!(run("c", &[], 0).await?, vec![1]);
+            // Passes straight away when run again.
+            assert_eq!(run("a", &["flaky"], 0).await?, vec![1]);
+            // Fails all the retries.
+            assert_eq!(run("d", &["other", "flaky"], 5).await?, vec![1, 2, 3]);
+            orchestrator.end_of_test_results(0).await?;
+            anyhow::Ok(())
+        };
+
+        let ((), results) = future::try_join(jobs, channel.try_collect::<Vec<_>>()).await?;
+
+        let result = |name: &str, status, attempt| {
+            TestResultOrExitCode::TestResult(TestResult {
+                target: handle,
+                status,
+                msg: None,
+                name: name.to_owned(),
+                duration: None,
+                details: String::new(),
+                attempt,
+                stderr: String::new(),
+            })
+        };
+        assert_eq!(
+            results,
+            vec![
+                result("a", TestStatus::FLAKY, 2),
+                result("b", TestStatus::FAIL, 1),
+                result("c", TestStatus::PASS, 1),
+                result("a", TestStatus::PASS, 1),
+                result("d", TestStatus::FAIL, 3),
+                TestResultOrExitCode::ExitCode(0),
+            ]
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,394 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! JUnit XML and JSON reports of the results of `buck2 test`.
+//!
+//! These are built from the results executors send to the orchestrator, so they look the same
+//! whichever executor ran the tests. Each configured test target becomes a JUnit `<testsuite>`,
+//! and each result reported for it a `<testcase>`.
+
+use std::fmt::Write;
+
+use buck2_test_api::data::TestResult;
+use buck2_test_api::data::TestStatus;
+use indexmap::IndexMap;
+use serde::Serialize;
+
+/// How much of a test's output to include in a report. When it is longer, we keep the end, which
+/// is usually where the interesting part is.
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// A test result, along with the target it came from.
+pub(crate) struct ReportedTest {
+    /// The unconfigured label of the target.
+    pub(crate) target: String,
+    pub(crate) configuration: String,
+    pub(crate) result: TestResult,
+}
+
+impl ReportedTest {
+    /// Listings and results superseded by a rerun aren't tests in their own right.
+    fn is_reported(&self) -> bool {
+        !matches!(
+            self.result.status,
+            TestStatus::LISTING_SUCCESS | TestStatus::RERUN
+        )
+    }
+
+    fn duration_secs(&self) -> Option<f64> {
+        self.result.duration.map(|d| d.as_secs_f64())
+    }
+}
+
+fn status_name(status: &TestStatus) -> &'static str {
+    match status {
+        TestStatus::PASS => "PASS",
+        TestStatus::FAIL => "FAIL",
+        TestStatus::SKIP => "SKIP",
+        TestStatus::OMITTED => "OMITTED",
+        TestStatus::FATAL => "FATAL",
+        TestStatus::TIMEOUT => "TIMEOUT",
+        TestStatus::UNKNOWN => "UNKNOWN",
+        TestStatus::RERUN => "RERUN",
+        TestStatus::LISTING_SUCCESS => "LISTING_SUCCESS",
+        TestStatus::LISTING_FAILED => "LISTING_FAILED",
+        TestStatus::FLAKY => "FLAKY",
+    }
+}
+
+/// How a status is represented in JUnit.
+enum JUnitOutcome {
+    Passed,
+    Failure,
+    Error,
+    Skipped,
+}
+
+impl JUnitOutcome {
+    fn of(status: &TestStatus) -> Self {
+        match status {
+            TestStatus::PASS | TestStatus::FLAKY | TestStatus::LISTING_SUCCESS => Self::Passed,
+            TestStatus::FAIL | TestStatus::TIMEOUT => Self::Failure,
+            TestStatus::FATAL | TestStatus::LISTING_FAILED | TestStatus::UNKNOWN => Self::Error,
+            TestStatus::SKIP | TestStatus::OMITTED | TestStatus::RERUN => Self::Skipped,
+        }
+    }
+}
+
+/// The end of `output`, if it's too long to include in full.
+fn excerpt(output: &str) -> String {
+    if output.len() <= MAX_OUTPUT_BYTES {
+        return output.to_owned();
+    }
+    let mut start = output.len() - MAX_OUTPUT_BYTES;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[{} bytes truncated]\n{}", start, &output[start..])
+}
+
+/// Escape text for use in an XML attribute or element, dropping characters XML can't represent.
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Default)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+    time: f64,
+}
+
+impl Counts {
+    fn add(&mut self, test: &ReportedTest) {
+        self.tests += 1;
+        match JUnitOutcome::of(&test.result.status) {
+            JUnitOutcome::Passed => {}
+            JUnitOutcome::Failure => self.failures += 1,
+            JUnitOutcome::Error => self.errors += 1,
+            JUnitOutcome::Skipped => self.skipped += 1,
+        }
+        self.time += test.duration_secs().unwrap_or_default();
+    }
+
+    fn attributes(&self) -> String {
+        format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+            self.tests, self.failures, self.errors, self.skipped, self.time
+        )
+    }
+}
+
+/// A JUnit XML report, in the dialect understood by Jenkins and GitLab.
+pub(crate) fn junit_xml(tests: &[ReportedTest]) -> String {
+    let mut suites = IndexMap::<(&str, &str), Vec<&ReportedTest>>::new();
+    let mut total = Counts::default();
+    for test in tests.iter().filter(|t| t.is_reported()) {
+        suites
+            .entry((&test.target, &test.configuration))
+            .or_default()
+            .push(test);
+        total.add(test);
+    }
+
+    // Writing to a `String` can't fail.
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(xml, "<testsuites {}>", total.attributes()).unwrap();
+    for ((target, configuration), tests) in suites {
+        let mut counts = Counts::default();
+        tests.iter().for_each(|test| counts.add(test));
+        // The same target may be tested in several configurations, and suites are told apart by
+        // their name.
+        writeln!(
+            xml,
+            "  <testsuite name=\"{} ({})\" {}>",
+            xml_escape(target),
+            xml_escape(configuration),
+            counts.attributes()
+        )
+        .unwrap();
+        writeln!(xml, "    <properties>").unwrap();
+        writeln!(
+            xml,
+            "      <property name=\"configuration\" value=\"{}\"/>",
+            xml_escape(configuration)
+        )
+        .unwrap();
+        writeln!(xml, "    </properties>").unwrap();
+        for test in tests {
+            write_testcase(&mut xml, test);
+        }
+        writeln!(xml, "  </testsuite>").unwrap();
+    }
+    writeln!(xml, "</testsuites>").unwrap();
+    xml
+}
+
+fn write_testcase(xml: &mut String, test: &ReportedTest) {
+    let result = &test.result;
+    write!(
+        xml,
+        "    <testcase name=\"{}\" classname=\"{}\"",
+        xml_escape(&result.name),
+        xml_escape(&test.target)
+    )
+    .unwrap();
+    if let Some(duration) = test.duration_secs() {
+        write!(xml, " time=\"{:.3}\"", duration).unwrap();
+    }
+    writeln!(xml, ">").unwrap();
+
+    if result.status == TestStatus::FLAKY || result.attempt > 1 {
+        writeln!(xml, "      <properties>").unwrap();
+        writeln!(
+            xml,
+            "        <property name=\"attempts\" value=\"{}\"/>",
+            result.attempt
+        )
+        .unwrap();
+        if result.status == TestStatus::FLAKY {
+            writeln!(xml, "        <property name=\"flaky\" value=\"true\"/>").unwrap();
+        }
+        writeln!(xml, "      </properties>").unwrap();
+    }
+
+    let status = status_name(&result.status);
+    let message = xml_escape(result.msg.as_deref().unwrap_or(status));
+    match JUnitOutcome::of(&result.status) {
+        JUnitOutcome::Passed => {}
+        JUnitOutcome::Failure => writeln!(
+            xml,
+            "      <failure type=\"{}\" message=\"{}\"/>",
+            status, message
+        )
+        .unwrap(),
+        JUnitOutcome::Error => writeln!(
+            xml,
+            "      <error type=\"{}\" message=\"{}\"/>",
+            status, message
+        )
+        .unwrap(),
+        JUnitOutcome::Skipped => writeln!(xml, "      <skipped message=\"{}\"/>", message).unwrap(),
+    }
+
+    if !result.details.is_empty() {
+        writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            xml_escape(&excerpt(&result.details))
+        )
+        .unwrap();
+    }
+    if !result.stderr.is_empty() {
+        writeln!(
+            xml,
+            "      <system-err>{}</system-err>",
+            xml_escape(&excerpt(&result.stderr))
+        )
+        .unwrap();
+    }
+    writeln!(xml, "    </testcase>").unwrap();
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    results: Vec<JsonTestResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonTestResult<'a> {
+    target: &'a str,
+    configuration: &'a str,
+    name: &'a str,
+    status: &'static str,
+    duration_secs: Option<f64>,
+    attempt: u32,
+    message: Option<&'a str>,
+    /// The test's stdout, and its stderr unless the executor reported it separately, possibly
+    /// truncated.
+    output: String,
+    /// The test's stderr if the executor reported it separately, possibly truncated.
+    stderr: String,
+}
+
+/// A JSON report, with one entry for each test result.
+pub(crate) fn json(tests: &[ReportedTest]) -> anyhow::Result<String> {
+    let results = tests
+        .iter()
+        .filter(|t| t.is_reported())
+        .map(|test| JsonTestResult {
+            target: &test.target,
+            configuration: &test.configuration,
+            name: &test.result.name,
+            status: status_name(&test.result.status),
+            duration_secs: test.duration_secs(),
+            attempt: test.result.attempt,
+            message: test.result.msg.as_deref(),
+            output: excerpt(&test.result.details),
+            stderr: excerpt(&test.result.stderr),
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&JsonReport { results })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buck2_test_api::data::testing::ConfiguredTargetHandleExt;
+    use buck2_test_api::data::ConfiguredTargetHandle;
+
+    use super::*;
+
+    fn reported(
+        target: &str,
+        name: &str,
+        status: TestStatus,
+        details: &str,
+        stderr: &str,
+    ) -> ReportedTest {
+        ReportedTest {
+            target: target.to_owned(),
+            configuration: "cfg#0123".to_owned(),
+            result: TestResult {
+                target: ConfiguredTargetHandle::testing_new(0),
+                name: name.to_owned(),
+                status,
+                msg: None,
+                duration: Some(Duration::from_millis(1500)),
+                details: details.to_owned(),
+                attempt: 1,
+                stderr: stderr.to_owned(),
+            },
+        }
+    }
+
+    fn tests() -> Vec<ReportedTest> {
+        vec![
+            reported("root//:a", "a", TestStatus::PASS, "", ""),
+            reported(
+                "root//:b",
+                "b1",
+                TestStatus::FAIL,
+                "expected <1> & got 2\u{1b}",
+                "assertion failed",
+            ),
+            reported("root//:b", "b2", TestStatus::SKIP, "", ""),
+            reported("root//:b", "listing", TestStatus::LISTING_SUCCESS, "", ""),
+        ]
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        assert_eq!(
+            junit_xml(&tests()),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="3" failures="1" errors="0" skipped="1" time="4.500">
+  <testsuite name="root//:a (cfg#0123)" tests="1" failures="0" errors="0" skipped="0" time="1.500">
+    <properties>
+      <property name="configuration" value="cfg#0123"/>
+    </properties>
+    <testcase name="a" classname="root//:a" time="1.500">
+    </testcase>
+  </testsuite>
+  <testsuite name="root//:b (cfg#0123)" tests="2" failures="1" errors="0" skipped="1" time="3.000">
+    <properties>
+      <property name="configuration" value="cfg#0123"/>
+    </properties>
+    <testcase name="b1" classname="root//:b" time="1.500">
+      <failure type="FAIL" message="FAIL"/>
+      <system-out>expected &lt;1&gt; &amp; got 2</system-out>
+      <system-err>assertion failed</system-err>
+    </testcase>
+    <testcase name="b2" classname="root//:b" time="1.500">
+      <skipped message="SKIP"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let json: serde_json::Value = serde_json::from_str(&json(&tests())?)?;
+        let results = json["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1]["target"], "root//:b");
+        assert_eq!(results[1]["configuration"], "cfg#0123");
+        assert_eq!(results[1]["status"], "FAIL");
+        assert_eq!(results[1]["duration_secs"], 1.5);
+        assert_eq!(results[1]["stderr"], "assertion failed");
+        Ok(())
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let output = "é".repeat(MAX_OUTPUT_BYTES);
+        let excerpt = excerpt(&output);
+        assert!(excerpt.starts_with(&format!("[{} bytes truncated]\n", MAX_OUTPUT_BYTES)));
+        assert!(excerpt.len() < output.len());
+    }
+}
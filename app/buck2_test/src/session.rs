@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 
@@ -17,14 +18,37 @@ use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_test_api::data::ConfiguredTargetHandle;
 use chrono::Local;
 use dashmap::DashMap;
-use gazebo::prelude::*;
 
-#[derive(Debug, Clone, Copy, Dupe, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct TestSessionOptions {
     /// Whether this session should allow things to run on RE.
     pub allow_re: bool,
     pub force_use_project_relative_paths: bool,
     pub force_run_from_project_root: bool,
+    /// Whether tests that opt in may reuse the result of an identical earlier run.
+    pub allow_test_cache: bool,
+    /// How many times failing tests are rerun before we give up on them.
+    pub retries: TestRetries,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TestRetries {
+    /// Retries for tests that have none of the labels below.
+    pub default: u32,
+    /// Retries for tests with a given label.
+    pub by_label: HashMap<String, u32>,
+}
+
+impl TestRetries {
+    /// The number of retries for a test with these labels. If several labels have a number of
+    /// retries set, the largest one wins.
+    pub fn for_labels<'a>(&self, labels: impl IntoIterator<Item = &'a str>) -> u32 {
+        labels
+            .into_iter()
+            .filter_map(|label| self.by_label.get(label).copied())
+            .max()
+            .unwrap_or(self.default)
+    }
 }
 
 /// The state of a buck2 test command.
@@ -60,8 +84,8 @@ impl TestSession {
         }
     }
 
-    pub fn options(&self) -> TestSessionOptions {
-        self.options
+    pub fn options(&self) -> &TestSessionOptions {
+        &self.options
     }
 
     pub fn prefix(&self) -> &ForwardRelativePath {
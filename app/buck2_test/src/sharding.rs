@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Splitting the tests of a `buck2 test` invocation across machines.
+//!
+//! Every machine is given the same patterns along with its own shard index, and must agree on the
+//! shard of every test without talking to the others. Tests are identified by their unconfigured
+//! label, and assigned either by a stable hash of it, or, if their expected duration is known, so
+//! as to balance the total duration of each shard.
+//!
+//! Sharding is done per test target rather than per test case: the test cases of a target are
+//! only known to the test executor once the target runs, so they all run in the target's shard.
+
+use std::collections::HashMap;
+
+use buck2_common::file_ops::FileDigest;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum TestShardingError {
+    #[error("The shard count must be at least 1")]
+    NoShards,
+    #[error("Shard index {0} is out of range for {1} shards")]
+    IndexOutOfRange(u32, u32),
+    #[error("Invalid duration for `{0}` in the timings: {1}")]
+    InvalidTiming(String, f64),
+}
+
+/// How a test was assigned to its shard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ShardStrategy {
+    /// By a hash of its label.
+    Hash,
+    /// By balancing the expected durations of the tests.
+    Timings,
+}
+
+impl ShardStrategy {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            ShardStrategy::Hash => "hash",
+            ShardStrategy::Timings => "timings",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct ShardAssignment {
+    pub(crate) shard: u32,
+    pub(crate) strategy: ShardStrategy,
+}
+
+#[derive(Debug)]
+pub(crate) struct TestSharding {
+    index: u32,
+    count: u32,
+    /// Shards of the tests listed in the timings, keyed on label.
+    timed: HashMap<String, u32>,
+}
+
+impl TestSharding {
+    /// `timings` are the expected durations of tests in seconds, keyed on their unconfigured label.
+    pub(crate) fn new(
+        index: u32,
+        count: u32,
+        timings: HashMap<String, f64>,
+    ) -> anyhow::Result<Self> {
+        if count == 0 {
+            return Err(TestShardingError::NoShards.into());
+        }
+        if index >= count {
+            return Err(TestShardingError::IndexOutOfRange(index, count).into());
+        }
+
+        let mut timings = timings.into_iter().collect::<Vec<_>>();
+        if let Some((label, duration)) = timings
+            .iter()
+            .find(|(_, duration)| !duration.is_finite() || *duration < 0.0)
+        {
+            return Err(TestShardingError::InvalidTiming(label.clone(), *duration).into());
+        }
+
+        // Longest first, each onto whichever shard has the least to do so far. Ties are broken by
+        // label and then by shard index, so that every machine comes to the same answer.
+        timings.sort_by(|(l1, d1), (l2, d2)| d2.total_cmp(d1).then_with(|| l1.cmp(l2)));
+        let mut loads = vec![0.0f64; count as usize];
+        let mut timed = HashMap::with_capacity(timings.len());
+        for (label, duration) in timings {
+            let (shard, _) = loads
+                .iter()
+                .enumerate()
+                .min_by(|(i1, l1), (i2, l2)| l1.total_cmp(l2).then_with(|| i1.cmp(i2)))
+                .unwrap();
+            loads[shard] += duration;
+            timed.insert(label, shard as u32);
+        }
+
+        Ok(Self {
+            index,
+            count,
+            timed,
+        })
+    }
+
+    pub(crate) fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub(crate) fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub(crate) fn assign(&self, label: &ConfiguredProvidersLabel) -> ShardAssignment {
+        self.assign_name(&label.unconfigured().to_string())
+    }
+
+    fn assign_name(&self, name: &str) -> ShardAssignment {
+        if let Some(shard) = self.timed.get(name) {
+            return ShardAssignment {
+                shard: *shard,
+                strategy: ShardStrategy::Timings,
+            };
+        }
+
+        // Unlike `DefaultHasher`, SHA1 is guaranteed to be the same on every machine and release.
+        let digest = FileDigest::from_bytes_sha1(name.as_bytes());
+        let hash = u64::from_be_bytes(digest.sha1()[..8].try_into().unwrap());
+        ShardAssignment {
+            shard: (hash % self.count as u64) as u32,
+            strategy: ShardStrategy::Hash,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid() {
+        assert!(TestSharding::new(0, 0, HashMap::new()).is_err());
+        assert!(TestSharding::new(3, 3, HashMap::new()).is_err());
+        assert!(
+            TestSharding::new(0, 2, HashMap::from([("root//:t".to_owned(), f64::NAN)])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_hash_is_stable() -> anyhow::Result<()> {
+        let names = (0..100).map(|i| format!("root//pkg:test{}", i));
+        let shards = (0..4)
+            .map(|index| TestSharding::new(index, 4, HashMap::new()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        for name in names {
+            let assigned = shards
+                .iter()
+                .map(|sharding| sharding.assign_name(&name))
+                .collect::<Vec<_>>();
+            // Every shard agrees on where the test goes.
+            assert!(assigned.iter().all(|a| a == &assigned[0]));
+            assert_eq!(assigned[0].strategy, ShardStrategy::Hash);
+            assert!(assigned[0].shard < 4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_timings_are_balanced() -> anyhow::Result<()> {
+        let sharding = TestSharding::new(
+            0,
+            2,
+            HashMap::from([
+                ("root//:a".to_owned(), 10.0),
+                ("root//:b".to_owned(), 6.0),
+                ("root//:c".to_owned(), 4.0),
+                ("root//:d".to_owned(), 1.0),
+            ]),
+        )?;
+
+        let shard = |name: &str| {
+            let assignment = sharding.assign_name(name);
+            assert_eq!(assignment.strategy, ShardStrategy::Timings);
+            assignment.shard
+        };
+        assert_eq!(shard("root//:a"), 0);
+        assert_eq!(shard("root//:b"), 1);
+        assert_eq!(shard("root//:c"), 1);
+        assert_eq!(shard("root//:d"), 0);
+
+        assert_eq!(
+            sharding.assign_name("root//:unknown").strategy,
+            ShardStrategy::Hash
+        );
+        Ok(())
+    }
+}
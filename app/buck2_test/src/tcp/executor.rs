@@ -18,6 +18,7 @@ use tokio::process::Child;
 
 pub(crate) async fn spawn(
     name: &str,
+    args: &[String],
     tpx_args: Vec<String>,
 ) -> anyhow::Result<(Child, TcpStream, TcpStream)> {
     // Use TCPStream via TCPListner with accept to simulate UnixStream.
@@ -29,6 +30,7 @@ pub(crate) async fn spawn(
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .args(args)
         .arg("--executor-addr")
         .arg(executor_addr)
         .arg("--orchestrator-addr")
@@ -0,0 +1,269 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Caching of test results.
+//!
+//! Tests labelled [`CACHEABLE_TEST_LABEL`](CACHEABLE_TEST_LABEL) are keyed on the digest of the
+//! action they run as, which covers their command, environment and inputs. Only those tests are
+//! looked up in and uploaded to the remote action cache, and passing results are also recorded
+//! locally in `buck-out` so that tests which ran locally don't need to run again either. The digests of
+//! the outputs are recorded too, so that a result is not reused once its outputs were overwritten.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use buck2_core::directory::FingerprintedDirectory;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project::ProjectRelativePathBuf;
+use buck2_execute::artifact::fs::ArtifactFs;
+use buck2_execute::directory::ActionDirectoryBuilder;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute_impl::executors::local::build_entry_from_disk;
+use buck2_test_api::data::DeclaredOutput;
+use buck2_test_api::data::ExecutionResult2;
+use buck2_test_api::data::ExecutionStatus;
+use buck2_test_api::data::ExecutionStream;
+use buck2_test_api::data::Output;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Tests with this label may reuse the result of an earlier run of the same action.
+pub const CACHEABLE_TEST_LABEL: &str = "cacheable";
+
+/// Where the local cache lives, relative to buck-out.
+const LOCAL_CACHE_DIR: &str = "test_cache";
+
+/// What we keep of a passing `ExecutionResult2`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CachedTestResult {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    outputs: Vec<CachedOutput>,
+    start_time: SystemTime,
+    execution_time: Duration,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CachedOutput {
+    /// The declared output name.
+    name: String,
+    /// Where the output was written, relative to the project root.
+    path: String,
+    /// The fingerprint of the output when the test passed, see `fingerprint_output`.
+    digest: String,
+}
+
+/// Fingerprint the file, directory or symlink at `path`. Returns `None` if there is nothing there.
+fn fingerprint_output(path: &AbsNormPath) -> anyhow::Result<Option<String>> {
+    let entry = match build_entry_from_disk(path.to_buf())? {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    // Wrap the entry in a directory so that files and directories are fingerprinted alike.
+    let mut builder = ActionDirectoryBuilder::empty();
+    builder.insert(FileNameBuf::unchecked_new("output"), entry)?;
+    Ok(Some(builder.fingerprint().fingerprint().to_string()))
+}
+
+impl CachedTestResult {
+    /// Returns `None` if the result is not worth caching, i.e. the test did not pass.
+    fn new(fs: &ArtifactFs, result: &ExecutionResult2) -> anyhow::Result<Option<Self>> {
+        if result.status != (ExecutionStatus::Finished { exitcode: 0 }) {
+            return Ok(None);
+        }
+        let ExecutionStream::Inline(stdout) = &result.stdout;
+        let ExecutionStream::Inline(stderr) = &result.stderr;
+        let mut outputs = Vec::with_capacity(result.outputs.len());
+        for (declared, output) in &result.outputs {
+            let Output::LocalPath(path) = output;
+            let relative = match fs.fs().relativize(path) {
+                Ok(relative) => relative,
+                Err(_) => return Ok(None),
+            };
+            let digest = match fingerprint_output(path)? {
+                Some(digest) => digest,
+                None => return Ok(None),
+            };
+            outputs.push(CachedOutput {
+                name: declared.name.as_str().to_owned(),
+                path: relative.as_str().to_owned(),
+                digest,
+            });
+        }
+        Ok(Some(Self {
+            stdout: stdout.clone(),
+            stderr: stderr.clone(),
+            outputs,
+            start_time: result.start_time,
+            execution_time: result.execution_time,
+        }))
+    }
+
+    /// Returns `None` if any of the outputs have since been deleted or modified.
+    fn into_execution_result(self, fs: &ArtifactFs) -> anyhow::Result<Option<ExecutionResult2>> {
+        let mut outputs = HashMap::with_capacity(self.outputs.len());
+        for output in self.outputs {
+            let path = fs
+                .fs()
+                .resolve(&ProjectRelativePathBuf::unchecked_new(output.path));
+            if fingerprint_output(&path)?.as_ref() != Some(&output.digest) {
+                return Ok(None);
+            }
+            outputs.insert(
+                DeclaredOutput {
+                    name: ForwardRelativePathBuf::unchecked_new(output.name),
+                },
+                Output::LocalPath(path),
+            );
+        }
+        Ok(Some(ExecutionResult2 {
+            status: ExecutionStatus::Finished { exitcode: 0 },
+            stdout: ExecutionStream::Inline(self.stdout),
+            stderr: ExecutionStream::Inline(self.stderr),
+            outputs,
+            start_time: self.start_time,
+            execution_time: self.execution_time,
+        }))
+    }
+}
+
+/// Passing test results recorded in `buck-out`. Calls do blocking I/O.
+pub(crate) struct LocalTestCache<'a> {
+    fs: &'a ArtifactFs,
+}
+
+impl<'a> LocalTestCache<'a> {
+    pub(crate) fn new(fs: &'a ArtifactFs) -> Self {
+        Self { fs }
+    }
+
+    fn path(&self, digest: &ActionDigest) -> ProjectRelativePathBuf {
+        // The digest is formatted as `hash:size`, and colons don't belong in paths on Windows.
+        self.fs
+            .buck_out_path_resolver()
+            .root()
+            .join(ForwardRelativePathBuf::unchecked_new(format!(
+                "{}/{}",
+                LOCAL_CACHE_DIR,
+                digest.to_string().replace(':', "_")
+            )))
+    }
+
+    pub(crate) fn lookup(&self, digest: &ActionDigest) -> anyhow::Result<Option<ExecutionResult2>> {
+        let path = self.fs.fs().resolve(&self.path(digest));
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(anyhow::Error::from(e))
+                    .with_context(|| format!("Error reading `{}`", path.display()));
+            }
+        };
+        // A result we can't read is treated like one we don't have: it will be overwritten once the
+        // test passes again.
+        match serde_json::from_slice::<CachedTestResult>(&data) {
+            Ok(cached) => cached.into_execution_result(self.fs),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub(crate) fn store(
+        &self,
+        digest: &ActionDigest,
+        result: &ExecutionResult2,
+    ) -> anyhow::Result<()> {
+        let cached = match CachedTestResult::new(self.fs, result)? {
+            Some(cached) => cached,
+            None => return Ok(()),
+        };
+        let path = self.fs.fs().resolve(&self.path(digest));
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Error creating `{}`", dir.display()))?;
+        }
+        std::fs::write(&path, serde_json::to_vec(&cached)?)
+            .with_context(|| format!("Error writing `{}`", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::testing::CellResolverExt;
+    use buck2_core::cells::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_execute::path::buck_out_path::BuckOutPathResolver;
+    use buck2_execute::path::buck_out_path::BuckPathResolver;
+    use gazebo::prelude::*;
+
+    use super::*;
+
+    fn result(exitcode: i32, outputs: HashMap<DeclaredOutput, Output>) -> ExecutionResult2 {
+        ExecutionResult2 {
+            status: ExecutionStatus::Finished { exitcode },
+            stdout: ExecutionStream::Inline(b"stdout".to_vec()),
+            stderr: ExecutionStream::Inline(b"stderr".to_vec()),
+            outputs,
+            start_time: SystemTime::UNIX_EPOCH,
+            execution_time: Duration::from_secs(3),
+        }
+    }
+
+    #[test]
+    fn test_local_cache() -> anyhow::Result<()> {
+        let root = ProjectRootTemp::new()?;
+        let fs = ArtifactFs::new(
+            BuckPathResolver::new(CellResolver::of_names_and_paths(&[(
+                CellName::unchecked_new("cell".to_owned()),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell".to_owned())),
+            )])),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new(
+                "buck-out/v2".to_owned(),
+            )),
+            root.path().dupe(),
+        );
+        let cache = LocalTestCache::new(&fs);
+        let digest = ActionDigest::from_bytes_sha1(b"test");
+
+        // Failures are never cached.
+        cache.store(&digest, &result(1, HashMap::new()))?;
+        assert_eq!(cache.lookup(&digest)?, None);
+
+        let output = fs
+            .fs()
+            .resolve(&ProjectRelativePathBuf::unchecked_new("out.txt".to_owned()));
+        std::fs::write(&output, "")?;
+        let passed = result(
+            0,
+            HashMap::from([(
+                DeclaredOutput {
+                    name: ForwardRelativePathBuf::unchecked_new("out.txt".to_owned()),
+                },
+                Output::LocalPath(output.clone()),
+            )]),
+        );
+        cache.store(&digest, &passed)?;
+        assert_eq!(cache.lookup(&digest)?, Some(passed));
+
+        // Once an output is overwritten, the cached result is no use.
+        std::fs::write(&output, "overwritten")?;
+        assert_eq!(cache.lookup(&digest)?, None);
+
+        // Nor once it is gone.
+        cache.store(&digest, &passed)?;
+        std::fs::remove_file(&output)?;
+        assert_eq!(cache.lookup(&digest)?, None);
+        Ok(())
+    }
+}
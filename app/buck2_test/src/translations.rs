@@ -57,14 +57,19 @@ pub fn convert_test_result(
         status,
         msg,
         duration,
-        details,
+        mut details,
+        attempt,
+        stderr,
         ..
     } = test_result;
+    // The console shows all of the test's output together.
+    details.push_str(&stderr);
     Ok(buck2_data::TestResult {
         name,
         status: status.try_into().context("Invalid `status`")?,
         msg: msg.map(|msg| buck2_data::test_result::OptionalMsg { msg }),
         duration: duration.and_then(|d| d.try_into().ok()),
         details,
+        attempt,
     })
 }
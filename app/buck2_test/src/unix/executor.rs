@@ -18,6 +18,7 @@ use tokio::process::Child;
 
 pub(crate) async fn spawn(
     name: &str,
+    args: &[String],
     tpx_args: Vec<String>,
 ) -> anyhow::Result<(Child, UnixStream, UnixStream)> {
     let (executor_client_async_io, executor_server_async_io) =
@@ -43,6 +44,7 @@ pub(crate) async fn spawn(
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .args(args)
         .arg("--executor-fd")
         .arg(executor_server_fd)
         .arg("--orchestrator-fd")
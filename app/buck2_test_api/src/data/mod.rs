@@ -90,8 +90,13 @@ pub struct TestResult {
     // the duration of the test run
     // TODO(skcd) should this be optional? why doesn't everything have duration
     pub duration: Option<Duration>,
-    // the output of the test execution (combining stdout and stderr)
+    // the output of the test execution (combining stdout and stderr, unless stderr is reported
+    // separately)
     pub details: String,
+    // which run of the test this is the result of, counting from 1
+    pub attempt: u32,
+    // the stderr of the test execution, if it's not part of `details`
+    pub stderr: String,
 }
 
 /// different possible test results
@@ -111,6 +116,8 @@ pub enum TestStatus {
     RERUN,
     LISTING_SUCCESS,
     LISTING_FAILED,
+    // Failed at least once, then passed when retried.
+    FLAKY,
 }
 
 /// The set of information about a test rule that is passed to the test executor
@@ -0,0 +1,48 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::time::Duration;
+
+use anyhow::Context as _;
+
+/// Options for the built-in test executor. These are the arguments Buck passes through to the
+/// executor, i.e. anything after `--` on the `buck2 test` command line.
+#[derive(Debug, Clone, clap::Parser)]
+#[clap(name = "buck2-test-runner")]
+pub struct RunnerConfig {
+    /// Passed by Buck for compatibility with other executors, and ignored.
+    #[clap(long, hidden = true)]
+    buck_test_info: Option<String>,
+
+    /// How long each test may run for, in seconds, before it is killed.
+    #[clap(long, default_value = "600")]
+    timeout: u64,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            buck_test_info: None,
+            timeout: 600,
+        }
+    }
+}
+
+impl RunnerConfig {
+    /// Parse the arguments Buck passes to the executor. As with any command line, the first one
+    /// is the program name.
+    pub fn from_args(args: Vec<String>) -> anyhow::Result<Self> {
+        <Self as clap::Parser>::try_parse_from(args)
+            .context("Invalid arguments for the built-in test executor")
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
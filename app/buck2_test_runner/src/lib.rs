@@ -0,0 +1,30 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A test executor built into Buck, used by `buck2 test` when no external executor is
+//! configured via `test.v2_test_executor`.
+//!
+//! It speaks the same protocol as any other executor (see `buck2_test_api`), but keeps things
+//! simple: every test rule is run once, as a single test, using the command and environment
+//! declared on its `ExternalRunnerTestInfo`. Execution goes through the orchestrator, so tests run
+//! locally or on RE as configured for the target.
+
+mod config;
+mod runner;
+mod service;
+
+pub use config::RunnerConfig;
+pub use runner::run_tests;
+pub use runner::Buck2TestExecutor;
+pub use runner::TestRequest;
+pub use runner::EXIT_CODE_TEST_FAILURES;
+pub use service::run;
+pub use service::run_from_addrs;
+#[cfg(unix)]
+pub use service::run_from_fds;
@@ -0,0 +1,411 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ArgValueContent;
+use buck2_test_api::data::DisplayMetadata;
+use buck2_test_api::data::ExecutionResult2;
+use buck2_test_api::data::ExecutionStatus;
+use buck2_test_api::data::ExecutionStream;
+use buck2_test_api::data::ExternalRunnerSpec;
+use buck2_test_api::data::ExternalRunnerSpecValue;
+use buck2_test_api::data::TestResult;
+use buck2_test_api::data::TestStatus;
+use buck2_test_api::protocol::TestExecutor;
+use buck2_test_api::protocol::TestOrchestrator;
+use gazebo::prelude::*;
+use host_sharing::HostSharingRequirements;
+use host_sharing::WeightClass;
+use tokio::sync::mpsc;
+
+use crate::config::RunnerConfig;
+
+/// The exit code reported when any test did not pass, same as Buck v1.
+pub const EXIT_CODE_TEST_FAILURES: i32 = 32;
+
+/// Tests with this label are reported as skipped without being run.
+const LABEL_DISABLED: &str = "disabled";
+/// Tests with this label must have the host to themselves.
+const LABEL_SERIALIZE: &str = "serialize";
+/// Tests with this label are given more of the host's resources.
+const LABEL_HEAVYWEIGHT: &str = "heavyweight";
+
+/// What Buck has asked of the executor, in the order it asked.
+pub enum TestRequest {
+    Spec(ExternalRunnerSpec),
+    EndOfTestRequests,
+}
+
+/// The `TestExecutor` Buck talks to. It only queues up requests: the tests are run by
+/// [`run_tests`](run_tests), which does the talking back to Buck.
+pub struct Buck2TestExecutor {
+    requests: mpsc::UnboundedSender<TestRequest>,
+}
+
+impl Buck2TestExecutor {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<TestRequest>) {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        (Self { requests }, receiver)
+    }
+
+    fn send(&self, request: TestRequest) -> anyhow::Result<()> {
+        self.requests
+            .send(request)
+            .ok()
+            .context("The built-in test executor has stopped running tests")
+    }
+}
+
+#[async_trait::async_trait]
+impl TestExecutor for Buck2TestExecutor {
+    async fn external_runner_spec(&self, s: ExternalRunnerSpec) -> anyhow::Result<()> {
+        self.send(TestRequest::Spec(s))
+    }
+
+    async fn end_of_test_requests(&self) -> anyhow::Result<()> {
+        self.send(TestRequest::EndOfTestRequests)
+    }
+}
+
+/// Run every test Buck sends until it says there are no more, report each result, and return the
+/// exit code for the test command. Tests run concurrently, Buck decides how many run at a time.
+pub async fn run_tests(
+    orchestrator: Arc<dyn TestOrchestrator>,
+    config: RunnerConfig,
+    mut requests: mpsc::UnboundedReceiver<TestRequest>,
+) -> anyhow::Result<i32> {
+    let config = Arc::new(config);
+    let mut tests = Vec::new();
+
+    while let Some(request) = requests.recv().await {
+        match request {
+            TestRequest::Spec(spec) => {
+                let orchestrator = orchestrator.dupe();
+                let config = config.dupe();
+                tests.push(tokio::spawn(async move {
+                    run_test(&*orchestrator, &config, spec).await
+                }));
+            }
+            TestRequest::EndOfTestRequests => break,
+        }
+    }
+
+    let mut exit_code = 0;
+    for test in tests {
+        let status = test.await.context("Test task panicked")??;
+        if !matches!(status, TestStatus::PASS | TestStatus::SKIP) {
+            exit_code = EXIT_CODE_TEST_FAILURES;
+        }
+    }
+    Ok(exit_code)
+}
+
+/// Run a single test rule as a single test and report its result.
+async fn run_test(
+    orchestrator: &dyn TestOrchestrator,
+    config: &RunnerConfig,
+    spec: ExternalRunnerSpec,
+) -> anyhow::Result<TestStatus> {
+    let has_label = |label: &str| spec.labels.iter().any(|l| l == label);
+
+    let result = if has_label(LABEL_DISABLED) {
+        TestResult {
+            target: spec.target.handle,
+            name: spec.target.name.clone(),
+            status: TestStatus::SKIP,
+            msg: Some(format!("Test is labelled `{}`", LABEL_DISABLED)),
+            duration: None,
+            details: String::new(),
+            attempt: 1,
+            stderr: String::new(),
+        }
+    } else {
+        let host_sharing_requirements = if has_label(LABEL_SERIALIZE) {
+            HostSharingRequirements::ExclusiveAccess
+        } else if has_label(LABEL_HEAVYWEIGHT) {
+            HostSharingRequirements::Shared(WeightClass::Permits(4))
+        } else {
+            HostSharingRequirements::default()
+        };
+
+        let execution = orchestrator
+            .execute2(
+                DisplayMetadata::Testing {
+                    suite: spec.target.name.clone(),
+                    testcases: Vec::new(),
+                },
+                spec.target.handle,
+                spec.command.iter().cloned().map(to_arg_value).collect(),
+                spec.env
+                    .iter()
+                    .map(|(k, v)| (k.clone(), to_arg_value(v.clone())))
+                    .collect(),
+                config.timeout(),
+                host_sharing_requirements,
+                Vec::new(),
+                None,
+            )
+            .await;
+
+        match execution {
+            Ok(execution) => test_result(&spec, execution),
+            Err(e) => TestResult {
+                target: spec.target.handle,
+                name: spec.target.name.clone(),
+                status: TestStatus::FATAL,
+                msg: Some(with_contacts(&spec, format!("{:#}", e))),
+                duration: None,
+                details: String::new(),
+                attempt: 1,
+                stderr: String::new(),
+            },
+        }
+    };
+
+    let status = result.status.dupe();
+    orchestrator
+        .report_test_result(result)
+        .await
+        .with_context(|| format!("Failed to report result for `{}`", spec.target.name))?;
+    Ok(status)
+}
+
+fn to_arg_value(value: ExternalRunnerSpecValue) -> ArgValue {
+    ArgValue {
+        content: ArgValueContent::ExternalRunnerSpecValue(value),
+        format: None,
+    }
+}
+
+fn test_result(spec: &ExternalRunnerSpec, execution: ExecutionResult2) -> TestResult {
+    let ExecutionResult2 {
+        status,
+        stdout,
+        stderr,
+        execution_time,
+        ..
+    } = execution;
+
+    let (status, msg) = match status {
+        ExecutionStatus::Finished { exitcode: 0 } => (TestStatus::PASS, None),
+        ExecutionStatus::Finished { exitcode } => (
+            TestStatus::FAIL,
+            Some(format!("Test exited with code {}", exitcode)),
+        ),
+        ExecutionStatus::TimedOut { duration } => (
+            TestStatus::TIMEOUT,
+            Some(format!(
+                "Test timed out after {:.1}s",
+                duration.as_secs_f64()
+            )),
+        ),
+    };
+
+    let to_string = |stream: ExecutionStream| match stream {
+        ExecutionStream::Inline(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+    };
+
+    TestResult {
+        target: spec.target.handle,
+        name: spec.target.name.clone(),
+        status,
+        msg: msg.map(|msg| with_contacts(spec, msg)),
+        duration: Some(execution_time),
+        details: to_string(stdout),
+        // The orchestrator retries failures itself, and fills in which attempt this was.
+        attempt: 1,
+        stderr: to_string(stderr),
+    }
+}
+
+/// Point whoever reads a failure at the people who own the test.
+fn with_contacts(spec: &ExternalRunnerSpec, msg: String) -> String {
+    let owners: Vec<&str> = spec
+        .oncall
+        .iter()
+        .chain(spec.contacts.iter())
+        .map(|x| x.as_str())
+        .collect();
+    if owners.is_empty() {
+        msg
+    } else {
+        format!("{} (contacts: {})", msg, owners.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_test_api::data::testing::ConfiguredTargetHandleExt;
+    use buck2_test_api::data::ConfiguredTarget;
+    use buck2_test_api::data::ConfiguredTargetHandle;
+    use buck2_test_api::data::DeclaredOutput;
+    use buck2_test_api::data::ExecutorConfigOverride;
+    use buck2_test_api::data::PrepareForLocalExecutionResult;
+
+    use super::*;
+
+    /// Runs commands by treating the first argument as the exit code.
+    #[derive(Default)]
+    struct MockOrchestrator {
+        executed: Mutex<Vec<HostSharingRequirements>>,
+        results: Mutex<Vec<TestResult>>,
+    }
+
+    #[async_trait::async_trait]
+    impl TestOrchestrator for MockOrchestrator {
+        async fn execute2(
+            &self,
+            _ui_prints: DisplayMetadata,
+            _target: ConfiguredTargetHandle,
+            cmd: Vec<ArgValue>,
+            _env: HashMap<String, ArgValue>,
+            _timeout: Duration,
+            host_sharing_requirements: HostSharingRequirements,
+            _pre_create_dirs: Vec<DeclaredOutput>,
+            _executor_override: Option<ExecutorConfigOverride>,
+        ) -> anyhow::Result<ExecutionResult2> {
+            self.executed
+                .lock()
+                .unwrap()
+                .push(host_sharing_requirements);
+            let exitcode = match &cmd[0].content {
+                ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
+                    code,
+                )) => code.parse()?,
+                _ => anyhow::bail!("Unexpected command"),
+            };
+            Ok(ExecutionResult2 {
+                status: ExecutionStatus::Finished { exitcode },
+                stdout: ExecutionStream::Inline(b"out\n".to_vec()),
+                stderr: ExecutionStream::Inline(b"err\n".to_vec()),
+                outputs: HashMap::new(),
+                start_time: SystemTime::now(),
+                execution_time: Duration::from_secs(1),
+            })
+        }
+
+        async fn report_test_result(&self, r: TestResult) -> anyhow::Result<()> {
+            self.results.lock().unwrap().push(r);
+            Ok(())
+        }
+
+        async fn report_tests_discovered(
+            &self,
+            _target: ConfiguredTargetHandle,
+            _suite: String,
+            _name: Vec<String>,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn report_test_session(&self, _session_info: String) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn end_of_test_results(&self, _exit_code: i32) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn prepare_for_local_execution(
+            &self,
+            _ui_prints: DisplayMetadata,
+            _target: ConfiguredTargetHandle,
+            _cmd: Vec<ArgValue>,
+            _env: HashMap<String, ArgValue>,
+            _pre_create_dirs: Vec<DeclaredOutput>,
+        ) -> anyhow::Result<PrepareForLocalExecutionResult> {
+            anyhow::bail!("Not supported")
+        }
+    }
+
+    fn spec(id: u64, exitcode: i32, labels: &[&str]) -> ExternalRunnerSpec {
+        ExternalRunnerSpec {
+            target: ConfiguredTarget {
+                handle: ConfiguredTargetHandle::testing_new(id),
+                name: format!("cell//pkg:test{}", id),
+                cell: "cell".to_owned(),
+                package: "pkg".to_owned(),
+                target: format!("test{}", id),
+                configuration: "<unspecified>".to_owned(),
+                package_project_relative_path: ForwardRelativePathBuf::unchecked_new(
+                    "pkg".to_owned(),
+                ),
+            },
+            test_type: "custom".to_owned(),
+            command: vec![ExternalRunnerSpecValue::Verbatim(exitcode.to_string())],
+            env: HashMap::new(),
+            labels: labels.iter().map(|l| (*l).to_owned()).collect(),
+            contacts: vec!["someone".to_owned()],
+            oncall: None,
+        }
+    }
+
+    async fn run(specs: Vec<ExternalRunnerSpec>) -> anyhow::Result<(i32, Arc<MockOrchestrator>)> {
+        let orchestrator = Arc::new(MockOrchestrator::default());
+        let (executor, requests) = Buck2TestExecutor::new();
+        for spec in specs {
+            executor.external_runner_spec(spec).await?;
+        }
+        executor.end_of_test_requests().await?;
+        let exit_code = run_tests(orchestrator.dupe(), RunnerConfig::default(), requests).await?;
+        Ok((exit_code, orchestrator))
+    }
+
+    #[tokio::test]
+    async fn test_all_pass() -> anyhow::Result<()> {
+        let (exit_code, orchestrator) =
+            run(vec![spec(1, 0, &[]), spec(2, 0, &[LABEL_DISABLED])]).await?;
+        assert_eq!(exit_code, 0);
+
+        let mut results = orchestrator.results.lock().unwrap().clone();
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(results[0].status, TestStatus::PASS);
+        assert_eq!(results[0].details, "out\n");
+        assert_eq!(results[0].stderr, "err\n");
+        assert_eq!(results[0].msg, None);
+        // Disabled tests are never executed.
+        assert_eq!(results[1].status, TestStatus::SKIP);
+        assert_eq!(orchestrator.executed.lock().unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failure() -> anyhow::Result<()> {
+        let (exit_code, orchestrator) =
+            run(vec![spec(1, 0, &[]), spec(2, 3, &[LABEL_SERIALIZE])]).await?;
+        assert_eq!(exit_code, EXIT_CODE_TEST_FAILURES);
+
+        let results = orchestrator.results.lock().unwrap();
+        let failed = results
+            .iter()
+            .find(|r| r.status == TestStatus::FAIL)
+            .unwrap();
+        assert_eq!(
+            failed.msg.as_deref(),
+            Some("Test exited with code 3 (contacts: someone)")
+        );
+        assert!(
+            orchestrator
+                .executed
+                .lock()
+                .unwrap()
+                .contains(&HostSharingRequirements::ExclusiveAccess)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use buck2_grpc::DuplexChannel;
+use buck2_test_api::grpc::spawn_executor_server;
+use buck2_test_api::grpc::TestOrchestratorClient;
+use buck2_test_api::protocol::TestOrchestrator;
+use gazebo::prelude::*;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::net::TcpStream;
+
+use crate::config::RunnerConfig;
+use crate::runner::run_tests;
+use crate::runner::Buck2TestExecutor;
+
+/// Serve the executor on `executor_io` and talk to Buck's orchestrator on `orchestrator_io` until
+/// all the tests have run. `args` are the arguments Buck passed to the executor.
+pub async fn run<E, O>(executor_io: E, orchestrator_io: O, args: Vec<String>) -> anyhow::Result<()>
+where
+    E: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    O: AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static,
+{
+    let config = RunnerConfig::from_args(args)?;
+
+    let orchestrator = TestOrchestratorClient::new(orchestrator_io)
+        .await
+        .context("Failed to create TestOrchestratorClient")?;
+    let orchestrator = Arc::new(orchestrator) as Arc<dyn TestOrchestrator>;
+
+    let (executor, requests) = Buck2TestExecutor::new();
+    let server = {
+        let (read, write) = tokio::io::split(executor_io);
+        spawn_executor_server(DuplexChannel::new(read, write), executor)
+    };
+
+    let exit_code = run_tests(orchestrator.dupe(), config, requests).await?;
+    orchestrator
+        .end_of_test_results(exit_code)
+        .await
+        .context("Failed to report end of test results")?;
+
+    server
+        .shutdown()
+        .await
+        .context("Failed to shut down the executor server")
+}
+
+/// Connect to Buck over the TCP addresses it passed as `--executor-addr` and `--orchestrator-addr`.
+pub async fn run_from_addrs(
+    executor_addr: &str,
+    orchestrator_addr: &str,
+    args: Vec<String>,
+) -> anyhow::Result<()> {
+    let executor_io = TcpStream::connect(executor_addr)
+        .await
+        .with_context(|| format!("Failed to connect to executor address {}", executor_addr))?;
+    let orchestrator_io = TcpStream::connect(orchestrator_addr)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to connect to orchestrator address {}",
+                orchestrator_addr
+            )
+        })?;
+    run(executor_io, orchestrator_io, args).await
+}
+
+/// Use the sockets Buck passed as `--executor-fd` and `--orchestrator-fd`.
+#[cfg(unix)]
+pub async fn run_from_fds(
+    executor_fd: std::os::unix::io::RawFd,
+    orchestrator_fd: std::os::unix::io::RawFd,
+    args: Vec<String>,
+) -> anyhow::Result<()> {
+    use std::os::unix::io::FromRawFd;
+    use std::os::unix::net::UnixStream as StdUnixStream;
+
+    use tokio::net::UnixStream;
+
+    let from_fd = |fd| -> anyhow::Result<UnixStream> {
+        // SAFETY: At worst, we just read (or close) the wrong FD.
+        let io = unsafe { StdUnixStream::from_raw_fd(fd) };
+        io.set_nonblocking(true)?;
+        Ok(UnixStream::from_std(io)?)
+    };
+
+    let executor_io = from_fd(executor_fd).context("Invalid `--executor-fd`")?;
+    let orchestrator_io = from_fd(orchestrator_fd).context("Invalid `--orchestrator-fd`")?;
+    run(executor_io, orchestrator_io, args).await
+}
@@ -42,7 +42,7 @@ enum VisibilityCommandError {
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
 #[clap(
     name = "audit-visibility",
-    about = "Verify the visibility for transitive deps of the specified target(s) on the unconfigured target graph"
+    about = "Verify the visibility and within_view for transitive deps of the specified target(s) on the unconfigured target graph"
 )]
 pub struct AuditVisibilityCommand {
     #[clap(flatten)]
@@ -87,28 +87,7 @@ impl AuditVisibilityCommand {
 
         async_depth_first_postorder_traversal(&lookup, targets.iter_names(), &mut delegate).await?;
 
-        let mut visibility_errors = Vec::new();
-
-        for target in delegate.targets.iter() {
-            for dep in target.deps() {
-                match delegate.targets.get(dep) {
-                    Some(val) => {
-                        if !val.is_visible_to(target.label()) {
-                            visibility_errors.push(VisibilityError::NotVisibleTo(
-                                dep.dupe(),
-                                target.label().dupe(),
-                            ));
-                        }
-                    }
-                    None => {
-                        return Err(anyhow::Error::new(VisibilityCommandError::DepNodeNotFound(
-                            dep.to_string(),
-                            target.label().name().to_string(),
-                        )));
-                    }
-                }
-            }
-        }
+        let visibility_errors = visibility_errors(&delegate.targets)?;
 
         for err in &visibility_errors {
             buck2_client_ctx::eprintln!("{}", err)?;
@@ -123,6 +102,40 @@ impl AuditVisibilityCommand {
     }
 }
 
+/// Check the visibility and within_view of the deps of `targets`, which must contain all of them.
+fn visibility_errors(targets: &TargetSet<TargetNode>) -> anyhow::Result<Vec<VisibilityError>> {
+    let mut visibility_errors = Vec::new();
+
+    for target in targets.iter() {
+        for dep in target.deps() {
+            match targets.get(dep) {
+                Some(val) => {
+                    if !val.is_visible_to(target.label()) {
+                        visibility_errors.push(VisibilityError::NotVisibleTo(
+                            dep.dupe(),
+                            target.label().dupe(),
+                        ));
+                    }
+                    if !target.is_within_view(dep) {
+                        visibility_errors.push(VisibilityError::NotWithinView(
+                            dep.dupe(),
+                            target.label().dupe(),
+                        ));
+                    }
+                }
+                None => {
+                    return Err(anyhow::Error::new(VisibilityCommandError::DepNodeNotFound(
+                        dep.to_string(),
+                        target.label().name().to_string(),
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(visibility_errors)
+}
+
 #[async_trait]
 impl AuditSubcommand for AuditVisibilityCommand {
     async fn server_execute(
@@ -165,3 +178,130 @@ impl AuditSubcommand for AuditVisibilityCommand {
         &self.common_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::package::testing::PackageExt;
+    use buck2_core::package::Package;
+    use buck2_core::pattern::ParsedPattern;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::TargetLabel;
+    use buck2_core::target::TargetName;
+    use buck2_node::attrs::attr::testing::AttributeExt;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::attr_literal::AttrLiteral;
+    use buck2_node::attrs::attr_type::dep::DepAttr;
+    use buck2_node::attrs::attr_type::dep::DepAttrTransition;
+    use buck2_node::attrs::attr_type::dep::DepAttrType;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_node::visibility::VisibilityPattern;
+    use buck2_node::visibility::VisibilitySpecification;
+    use buck2_node::visibility::WithinViewSpecification;
+
+    use super::*;
+
+    fn node(
+        pkg: &Package,
+        name: &str,
+        deps: &[&TargetNode],
+        visibility: VisibilitySpecification,
+        within_view: WithinViewSpecification,
+    ) -> TargetNode {
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::unchecked_new("cell", "foo", "def.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let deps = (
+            "some_deps",
+            Attribute::testing_new(None, AttrType::list(AttrType::dep(Vec::new()))),
+            CoercedAttr::from_literal(AttrLiteral::List(
+                deps.map(|dep| {
+                    CoercedAttr::from_literal(AttrLiteral::Dep(Box::new(DepAttr::new(
+                        DepAttrType::new(Vec::new(), DepAttrTransition::Identity),
+                        ProvidersLabel::new(dep.label().dupe(), ProvidersName::Default),
+                    ))))
+                })
+                .into_boxed_slice(),
+                AttrType::dep(Vec::new()),
+            )),
+        );
+        TargetNode::testing_new_with_visibility(
+            TargetLabel::new(pkg.dupe(), TargetName::unchecked_new(name)),
+            rule_type,
+            vec![deps],
+            visibility,
+            within_view,
+        )
+    }
+
+    fn only_view(pkg: &Package) -> WithinViewSpecification {
+        WithinViewSpecification::VisibleTo(vec![VisibilityPattern(ParsedPattern::Package(
+            pkg.dupe(),
+        ))])
+    }
+
+    #[test]
+    fn test_visibility_errors() -> anyhow::Result<()> {
+        let foo = Package::testing_new("cell", "foo");
+        let bar = Package::testing_new("cell", "bar");
+
+        let public = node(
+            &bar,
+            "public",
+            &[],
+            VisibilitySpecification::Public,
+            WithinViewSpecification::Public,
+        );
+        let private = node(
+            &bar,
+            "private",
+            &[],
+            VisibilitySpecification::Default,
+            WithinViewSpecification::Public,
+        );
+        let allowed = node(
+            &foo,
+            "allowed",
+            &[&public],
+            VisibilitySpecification::Public,
+            only_view(&bar),
+        );
+        let not_visible = node(
+            &foo,
+            "not_visible",
+            &[&private],
+            VisibilitySpecification::Public,
+            only_view(&bar),
+        );
+        let outside_view = node(
+            &foo,
+            "outside_view",
+            &[&public, &allowed],
+            VisibilitySpecification::Public,
+            only_view(&foo),
+        );
+
+        let mut targets = TargetSet::new();
+        for target in [public, private, allowed, not_visible, outside_view] {
+            targets.insert(target);
+        }
+
+        let errors = visibility_errors(&targets)?.map(|e| e.to_string());
+        assert_eq!(
+            vec![
+                "`cell//bar:private` is not visible to `cell//foo:not_visible` (run `buck2 uquery --output-attribute visibility cell//bar:private` to check the visibility)",
+                "`cell//bar:public` is not within the view of `cell//foo:outside_view` (run `buck2 uquery --output-attribute within_view cell//foo:outside_view` to check the view)",
+            ],
+            errors
+        );
+        Ok(())
+    }
+}
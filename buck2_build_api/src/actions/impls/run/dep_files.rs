@@ -8,6 +8,7 @@
  */
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Display;
@@ -15,31 +16,43 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use anyhow::Context as _;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileMetadata;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::category::Category;
+use buck2_core::directory::DirectoryEntry;
 use buck2_core::directory::DirectorySelector;
 use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::env_helper::EnvHelper;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project::ProjectRelativePath;
+use buck2_core::fs::project::ProjectRelativePathBuf;
 use buck2_execute::artifact::fs::ArtifactFs;
+use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::base_deferred_key::BaseDeferredKey;
 use buck2_execute::directory::expand_selector_for_dependencies;
 use buck2_execute::directory::ActionDirectoryBuilder;
+use buck2_execute::directory::ActionDirectoryMember;
 use buck2_execute::directory::ActionImmutableDirectory;
 use buck2_execute::directory::ActionSharedDirectory;
 use buck2_execute::directory::INTERNER;
+use buck2_execute::execute::request::RemoteDepFiles;
 use buck2_execute::execute::target::CommandExecutionTarget;
 use buck2_execute::materialize::materializer::MaterializationError;
 use buck2_execute::materialize::materializer::Materializer;
+use buck2_execute::path::buck_out_path::BuckOutPath;
 use dashmap::DashMap;
 use derive_more::Display;
 use futures::StreamExt;
 use gazebo::prelude::*;
+use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 use parking_lot::MappedMutexGuard;
 use parking_lot::Mutex;
 use parking_lot::MutexGuard;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 use tracing::instrument;
 
@@ -65,12 +78,21 @@ pub fn flush_dep_files() {
     DEP_FILES.clear();
 }
 
+/// Like `flush_dep_files`, but also forget about the dep files persisted by the materializer.
+pub async fn flush_persisted_dep_files(materializer: &dyn Materializer) -> anyhow::Result<()> {
+    flush_dep_files();
+    if let Some(store) = materializer.dep_file_state_store() {
+        store.delete_all().await?;
+    }
+    Ok(())
+}
+
 pub fn get_dep_files(key: &DepFilesKey) -> Option<Arc<DepFileState>> {
     DEP_FILES.get(key).map(|s| s.dupe())
 }
 
 /// A key used to associate a RunAction with a possible previous dep file.
-#[derive(Eq, PartialEq, Hash, Display, Allocative)]
+#[derive(Clone, Eq, PartialEq, Hash, Display, Allocative)]
 #[display(
     fmt = "{} {} {}",
     owner,
@@ -176,6 +198,43 @@ impl DepFileState {
         Ok(Some(dep_files))
     }
 
+    /// Produce the form of this state that is persisted across restarts. This requires the
+    /// fingerprints to have been computed, and only supports actions whose outputs are all files,
+    /// since we don't persist directory trees. Returns None if this state can't be persisted.
+    fn to_persisted(&self, fs: &ArtifactFs) -> anyhow::Result<Option<PersistedDepFileState>> {
+        let fingerprints = match &*self.input_signatures.lock() {
+            DepFileStateInputSignatures::Computed(StoredFingerprints::Digests(fingerprints)) => {
+                PersistedFingerprints::new(fingerprints)
+            }
+            DepFileStateInputSignatures::Computed(StoredFingerprints::Dirs(dirs)) => {
+                PersistedFingerprints::new(&dirs.as_fingerprints())
+            }
+            DepFileStateInputSignatures::Deferred(..) => return Ok(None),
+        };
+
+        let mut outputs = Vec::new();
+        for (path, value) in self.result.iter() {
+            let meta = match (value.entry(), value.deps()) {
+                (DirectoryEntry::Leaf(ActionDirectoryMember::File(meta)), None) => meta,
+                _ => return Ok(None),
+            };
+            outputs.push(PersistedOutput {
+                path: path.path().as_str().to_owned(),
+                hidden_components_count: path.hidden_components_count(),
+                action_key: path.action_key().map(|k| k.to_owned()),
+                digest: digest_to_string(&meta.digest),
+                is_executable: meta.is_executable,
+            });
+        }
+
+        Ok(Some(PersistedDepFileState {
+            cli_digest: self.cli_digest.to_hex(),
+            dep_files: self.declared_dep_files.paths(fs)?,
+            fingerprints,
+            outputs,
+        }))
+    }
+
     fn has_signatures(&self) -> bool {
         match *self.input_signatures.lock() {
             DepFileStateInputSignatures::Computed(..) => true,
@@ -219,6 +278,161 @@ impl DepFileState {
     }
 }
 
+/// The form of a DepFileState that we persist in the materializer's dep file state store, so that
+/// dep files can still be used to skip actions after the daemon restarts. The dep files are outputs
+/// of the action, so their digests are recorded alongside the other outputs.
+#[derive(Serialize, Deserialize)]
+struct PersistedDepFileState {
+    cli_digest: String,
+    /// The path of each dep file, by label.
+    dep_files: BTreeMap<String, String>,
+    fingerprints: PersistedFingerprints,
+    outputs: Vec<PersistedOutput>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedFingerprints {
+    untagged: String,
+    tagged: BTreeMap<String, String>,
+}
+
+impl PersistedFingerprints {
+    fn new(fingerprints: &PartitionedInputs<TrackedFileDigest>) -> Self {
+        Self {
+            untagged: digest_to_string(&fingerprints.untagged),
+            tagged: fingerprints
+                .tagged
+                .iter()
+                .map(|(label, digest)| (label.to_string(), digest_to_string(digest)))
+                .collect(),
+        }
+    }
+
+    fn into_fingerprints(self) -> anyhow::Result<PartitionedInputs<TrackedFileDigest>> {
+        Ok(PartitionedInputs {
+            untagged: digest_from_string(&self.untagged)?,
+            tagged: self
+                .tagged
+                .into_iter()
+                .map(|(label, digest)| anyhow::Ok((Arc::from(label), digest_from_string(&digest)?)))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedOutput {
+    path: String,
+    hidden_components_count: usize,
+    action_key: Option<String>,
+    digest: String,
+    is_executable: bool,
+}
+
+fn digest_to_string(digest: &TrackedFileDigest) -> String {
+    format!("{}:{}", hex::encode(digest.sha1()), digest.size())
+}
+
+fn digest_from_string(digest: &str) -> anyhow::Result<TrackedFileDigest> {
+    Ok(TrackedFileDigest::new(FileDigest::parse_digest_sha1(
+        digest,
+    )?))
+}
+
+impl PersistedDepFileState {
+    /// Produce a DepFileState for the action identified by `key`, which now declares
+    /// `declared_dep_files`. Returns None if the action declares different dep files than the ones
+    /// this state was produced with.
+    fn into_state(
+        self,
+        key: &DepFilesKey,
+        declared_dep_files: &DeclaredDepFiles,
+        fs: &ArtifactFs,
+    ) -> anyhow::Result<Option<DepFileState>> {
+        if declared_dep_files.paths(fs)? != self.dep_files {
+            return Ok(None);
+        }
+
+        let outputs = self
+            .outputs
+            .into_iter()
+            .map(|output| {
+                let path = BuckOutPath::with_hidden_and_action_key(
+                    key.owner.dupe(),
+                    ForwardRelativePathBuf::new(output.path)?,
+                    output.hidden_components_count,
+                    output.action_key.map(Arc::from),
+                );
+                let value = ArtifactValue::file(FileMetadata {
+                    digest: digest_from_string(&output.digest)?,
+                    is_executable: output.is_executable,
+                });
+                anyhow::Ok((path, value))
+            })
+            .collect::<Result<IndexMap<_, _>, _>>()?;
+
+        Ok(Some(DepFileState {
+            cli_digest: ExpandedCommandLineDigest::from_hex(&self.cli_digest)?,
+            input_signatures: Mutex::new(DepFileStateInputSignatures::Computed(
+                StoredFingerprints::Digests(self.fingerprints.into_fingerprints()?),
+            )),
+            declared_dep_files: declared_dep_files.clone(),
+            result: ActionOutputs::new(outputs),
+        }))
+    }
+}
+
+/// Load the state persisted for `key` by the materializer, if any. The state is only used if the
+/// action declares the same dep files it was produced with, and if the materializer still has the
+/// outputs it recorded (which include the dep files, so we know we can read them).
+async fn load_persisted_dep_file_state(
+    key: &DepFilesKey,
+    declared_dep_files: &DeclaredDepFiles,
+    ctx: &dyn ActionExecutionCtx,
+) -> anyhow::Result<Option<Arc<DepFileState>>> {
+    let store = match ctx.materializer().dep_file_state_store() {
+        Some(store) => store,
+        None => return Ok(None),
+    };
+
+    let persisted = match store.get(key.to_string()).await? {
+        Some(persisted) => persisted,
+        None => return Ok(None),
+    };
+
+    let state = serde_json::from_str::<PersistedDepFileState>(&persisted)
+        .map_err(anyhow::Error::from)
+        .and_then(|persisted| persisted.into_state(key, declared_dep_files, ctx.fs()));
+    let state = match state {
+        Ok(Some(state)) => state,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            // This is only a cache, so if we can't read it we just run the action.
+            tracing::debug!(
+                "Ignoring invalid persisted dep file state for {}: {:#}",
+                key,
+                e
+            );
+            return Ok(None);
+        }
+    };
+
+    let fs = ctx.fs();
+    let outputs = state
+        .result
+        .iter()
+        .map(|(path, value)| (fs.buck_out_path_resolver().resolve_gen(path), value.dupe()))
+        .collect();
+    if !ctx.materializer().declare_match(outputs).await?.is_match() {
+        tracing::trace!("Persisted dep file outputs mismatch in materializer");
+        return Ok(None);
+    }
+
+    let state = Arc::new(state);
+    DEP_FILES.insert(key.clone(), state.dupe());
+    Ok(Some(state))
+}
+
 /// The set of dep files declared by a RunAction, matching tags to their labels. We enforce at
 /// creation time that tags and lables are both unique.
 #[derive(Debug, Allocative)]
@@ -256,7 +470,10 @@ pub async fn match_or_clear_dep_file(
 ) -> anyhow::Result<Option<ActionOutputs>> {
     let previous_state = match get_dep_files(key) {
         Some(d) => d.dupe(),
-        None => return Ok(None),
+        None => match load_persisted_dep_file_state(key, declared_dep_files, ctx).await? {
+            Some(d) => d,
+            None => return Ok(None),
+        },
     };
 
     // We first need to check if the same dep files existed before or not. If not, then we
@@ -330,6 +547,9 @@ pub async fn match_or_clear_dep_file(
     tracing::trace!("Dep files are a miss");
 
     DEP_FILES.remove(key);
+    if let Some(store) = ctx.materializer().dep_file_state_store() {
+        store.delete(key.to_string()).await?;
+    }
 
     Ok(None)
 }
@@ -356,7 +576,12 @@ pub async fn populate_dep_files(
         result: result.dupe(),
     };
 
-    if has_no_dep_files || ctx.run_action_knobs().eager_dep_files {
+    // Persisting the state requires its fingerprints, so if we have somewhere to persist it to, we
+    // compute them now rather than on the next lookup, which might only happen after a restart.
+    let dep_file_state_store = ctx.materializer().dep_file_state_store();
+
+    if has_no_dep_files || dep_file_state_store.is_some() || ctx.run_action_knobs().eager_dep_files
+    {
         let dep_files = state
             .read_dep_files(ctx.fs(), ctx.materializer())
             .await?
@@ -369,11 +594,62 @@ pub async fn populate_dep_files(
         ));
     }
 
+    if let Some(store) = dep_file_state_store {
+        match state.to_persisted(ctx.fs())? {
+            Some(persisted) => {
+                store
+                    .insert(key.to_string(), serde_json::to_string(&persisted)?)
+                    .await?
+            }
+            None => store.delete(key.to_string()).await?,
+        }
+    }
+
     DEP_FILES.insert(key, Arc::new(state));
 
     Ok(())
 }
 
+/// Describe this command for the remote dep file cache: its key excludes the tagged inputs, which
+/// are matched against the dep files of previous executions by the executor instead.
+pub(crate) fn make_remote_dep_files(
+    cli_digest: &ExpandedCommandLineDigest,
+    declared_inputs: &PartitionedInputs<Vec<ArtifactGroup>>,
+    declared_dep_files: &DeclaredDepFiles,
+    outputs: &[ProjectRelativePathBuf],
+    ctx: &dyn ActionExecutionCtx,
+) -> anyhow::Result<RemoteDepFiles> {
+    let fs = ctx.fs();
+    let directories = declared_inputs.to_directories(ctx)?.share();
+
+    let mut key = format!(
+        "{} {}",
+        cli_digest.to_hex(),
+        directories.untagged.fingerprint()
+    );
+    for output in outputs {
+        key.push(' ');
+        key.push_str(output.as_str());
+    }
+
+    let dep_files = declared_dep_files
+        .tagged
+        .values()
+        .map(|declared_dep_file| {
+            anyhow::Ok((
+                declared_dep_file.label.dupe(),
+                fs.resolve(declared_dep_file.output.get_path())?,
+            ))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(RemoteDepFiles {
+        key,
+        tagged_inputs: directories.tagged,
+        dep_files,
+    })
+}
+
 /// Inputs partitioned by tag. `D` is the representation of the set of inputs.
 #[derive(Clone, PartialEq, Eq, Allocative)]
 pub struct PartitionedInputs<D> {
@@ -534,7 +810,7 @@ struct DeclaredDepFile {
 }
 
 /// All the dep files declared by a command;
-#[derive(Default, Debug, Allocative)]
+#[derive(Default, Debug, Clone, Allocative)]
 pub struct DeclaredDepFiles {
     tagged: HashMap<ArtifactTag, DeclaredDepFile>,
 }
@@ -544,6 +820,20 @@ impl DeclaredDepFiles {
         self.tagged.is_empty()
     }
 
+    /// The path of each dep file, by label.
+    fn paths(&self, fs: &ArtifactFs) -> anyhow::Result<BTreeMap<String, String>> {
+        self.tagged
+            .values()
+            .map(|declared_dep_file| {
+                let path = fs.resolve(declared_dep_file.output.get_path())?;
+                Ok((
+                    declared_dep_file.label.to_string(),
+                    path.as_str().to_owned(),
+                ))
+            })
+            .collect()
+    }
+
     /// Add dep file to this set.
     fn visit_output(
         &mut self,
@@ -718,18 +1008,52 @@ impl CommandLineArtifactVisitor for DepFilesCommandLineVisitor<'_> {
 
 #[cfg(test)]
 mod test {
+    use buck2_common::executor_config::PathSeparatorKind;
+    use buck2_core::buck_path::BuckPath;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::testing::CellResolverExt;
+    use buck2_core::cells::CellName;
+    use buck2_core::cells::CellResolver;
     use buck2_core::configuration::Configuration;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
     use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::fs::project::ProjectRelativePathBuf;
+    use buck2_core::fs::project::ProjectRoot;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::package::package_relative_path::PackageRelativePathBuf;
     use buck2_core::package::testing::PackageExt;
     use buck2_core::package::Package;
     use buck2_core::target::testing::ConfiguredTargetLabelExt;
     use buck2_core::target::ConfiguredTargetLabel;
     use buck2_core::target::TargetName;
+    use buck2_events::dispatch::EventDispatcher;
+    use buck2_execute::artifact::fs::ExecutorFs;
+    use buck2_execute::artifact::source_artifact::SourceArtifact;
+    use buck2_execute::execute::blocking::testing::DummyBlockingExecutor;
+    use buck2_execute::execute::blocking::BlockingExecutor;
+    use buck2_execute::execute::request::CommandExecutionOutput;
+    use buck2_execute::execute::request::CommandExecutionRequest;
+    use buck2_execute::materialize::materializer::ArtifactNotMaterializedReason;
+    use buck2_execute::materialize::materializer::CasDownloadInfo;
+    use buck2_execute::materialize::materializer::CopiedArtifact;
+    use buck2_execute::materialize::materializer::DeclareMatchOutcome;
+    use buck2_execute::materialize::materializer::DepFileStateStore;
+    use buck2_execute::materialize::materializer::HttpDownloadInfo;
+    use buck2_execute::materialize::materializer::WriteRequest;
+    use buck2_execute::path::buck_out_path::BuckOutPathResolver;
+    use buck2_execute::path::buck_out_path::BuckPathResolver;
+    use buck2_execute::re::manager::ManagedRemoteExecutionClient;
+    use futures::stream;
+    use futures::stream::BoxStream;
     use maplit::hashmap;
 
     use super::*;
     use crate::actions::artifact::build_artifact::BuildArtifact;
     use crate::actions::artifact::testing::BuildArtifactTestingExt;
+    use crate::actions::execute::action_executor::ActionExecutionMetadata;
+    use crate::actions::impls::run::expanded_command_line::ExpandedCommandLine;
+    use crate::actions::impls::run::knobs::RunActionKnobs;
+    use crate::artifact_groups::ArtifactGroupValues;
     use crate::deferred::types::testing::DeferredIdExt;
     use crate::deferred::types::DeferredId;
 
@@ -793,4 +1117,453 @@ mod test {
         assert!(!decl2.declares_same_dep_files(&decl3));
         assert!(!decl3.declares_same_dep_files(&decl4));
     }
+
+    fn artifact_fs() -> ArtifactFs {
+        artifact_fs_at(ProjectRoot::new(
+            AbsNormPathBuf::try_from(std::env::current_dir().unwrap()).unwrap(),
+        ))
+    }
+
+    fn artifact_fs_at(project_root: ProjectRoot) -> ArtifactFs {
+        ArtifactFs::new(
+            BuckPathResolver::new(CellResolver::of_names_and_paths(&[(
+                CellName::unchecked_new("cell".into()),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+            )])),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck_out".into())),
+            project_root,
+        )
+    }
+
+    fn digest(content: &str) -> TrackedFileDigest {
+        TrackedFileDigest::new(FileDigest::from_bytes_sha1(content.as_bytes()))
+    }
+
+    /// A DepFileState for an action that wrote `foo.o` and a dep file `foo.d` labelled `dep`,
+    /// whose fingerprints have already been computed.
+    fn computed_state(target: &ConfiguredTargetLabel) -> (DepFilesKey, DepFileState) {
+        let owner = BaseDeferredKey::TargetLabel(target.dupe());
+        let key = DepFilesKey::new(
+            owner.dupe(),
+            Category::try_from("cxx_compile").unwrap(),
+            Some("foo.cpp".to_owned()),
+        );
+
+        let dep_file = BuildArtifact::testing_new(
+            target.dupe(),
+            ForwardRelativePathBuf::unchecked_new("foo.d".to_owned()),
+            DeferredId::testing_new(0),
+        );
+        let declared_dep_files = DeclaredDepFiles {
+            tagged: hashmap! {
+                ArtifactTag::new() => DeclaredDepFile {
+                    label: Arc::from("dep"),
+                    output: Artifact::from(dep_file.dupe()),
+                },
+            },
+        };
+
+        let output = |path: &str, content: &str| {
+            (
+                BuckOutPath::new(
+                    owner.dupe(),
+                    ForwardRelativePathBuf::unchecked_new(path.to_owned()),
+                ),
+                ArtifactValue::file(FileMetadata {
+                    digest: digest(content),
+                    is_executable: false,
+                }),
+            )
+        };
+        let result = ActionOutputs::new(IndexMap::from_iter([
+            output("foo.o", "object"),
+            output(dep_file.get_path().path().as_str(), "headers"),
+        ]));
+
+        let state = DepFileState {
+            cli_digest: ExpandedCommandLine {
+                cli: vec!["cc".to_owned()],
+                env: Default::default(),
+            }
+            .fingerprint(),
+            input_signatures: Mutex::new(DepFileStateInputSignatures::Computed(
+                StoredFingerprints::Digests(PartitionedInputs {
+                    untagged: digest("untagged"),
+                    tagged: hashmap! { Arc::from("dep") => digest("tagged") },
+                }),
+            )),
+            declared_dep_files,
+            result,
+        };
+
+        (key, state)
+    }
+
+    fn target() -> ConfiguredTargetLabel {
+        ConfiguredTargetLabel::testing_new(
+            Package::testing_new("cell", "pkg"),
+            TargetName::unchecked_new("foo"),
+            Configuration::testing_new(),
+        )
+    }
+
+    #[test]
+    fn test_persisted_dep_file_state_roundtrip() -> anyhow::Result<()> {
+        let fs = artifact_fs();
+        let (key, state) = computed_state(&target());
+
+        let persisted = serde_json::to_string(&state.to_persisted(&fs)?.unwrap())?;
+        let restored = serde_json::from_str::<PersistedDepFileState>(&persisted)?
+            .into_state(&key, &state.declared_dep_files, &fs)?
+            .unwrap();
+
+        assert_eq!(restored.cli_digest, state.cli_digest);
+        assert_eq!(restored.result, state.result);
+        assert_eq!(
+            serde_json::to_string(&restored.to_persisted(&fs)?.unwrap())?,
+            persisted
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restored_state_does_not_recompute_fingerprints() -> anyhow::Result<()> {
+        let fs = artifact_fs();
+        let (key, state) = computed_state(&target());
+
+        let restored = state
+            .to_persisted(&fs)?
+            .unwrap()
+            .into_state(&key, &state.declared_dep_files, &fs)?
+            .unwrap();
+        assert!(restored.has_signatures());
+
+        // Dep files are only used to compute fingerprints, which we already have, so these are
+        // ignored: had they been used, the fingerprints would have come out differently.
+        let restored_fingerprints = restored.locked_compute_fingerprints(
+            Cow::Owned(ConcreteDepFiles {
+                contents: HashMap::new(),
+            }),
+            false,
+        );
+        match (&*restored_fingerprints, &*state.input_signatures.lock()) {
+            (
+                StoredFingerprints::Digests(restored),
+                DepFileStateInputSignatures::Computed(StoredFingerprints::Digests(original)),
+            ) => assert!(restored == original),
+            _ => panic!("Expected digests"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_state_rejects_stale_state() -> anyhow::Result<()> {
+        let fs = artifact_fs();
+        let target = target();
+        let (key, state) = computed_state(&target);
+
+        // The action now writes its dep file elsewhere.
+        let moved = DeclaredDepFiles {
+            tagged: hashmap! {
+                ArtifactTag::new() => DeclaredDepFile {
+                    label: Arc::from("dep"),
+                    output: Artifact::from(BuildArtifact::testing_new(
+                        target.dupe(),
+                        ForwardRelativePathBuf::unchecked_new("bar.d".to_owned()),
+                        DeferredId::testing_new(0),
+                    )),
+                },
+            },
+        };
+        assert!(
+            state
+                .to_persisted(&fs)?
+                .unwrap()
+                .into_state(&key, &moved, &fs)?
+                .is_none()
+        );
+
+        // Digests that can't be parsed are an error, which the caller treats as a miss.
+        let mut corrupt = state.to_persisted(&fs)?.unwrap();
+        corrupt.outputs[0].digest = "not a digest".to_owned();
+        assert!(
+            corrupt
+                .into_state(&key, &state.declared_dep_files, &fs)
+                .is_err()
+        );
+
+        let mut corrupt = state.to_persisted(&fs)?.unwrap();
+        corrupt.fingerprints.untagged = "0000:1".to_owned();
+        assert!(
+            corrupt
+                .into_state(&key, &state.declared_dep_files, &fs)
+                .is_err()
+        );
+
+        // Until fingerprints are computed, there is nothing to persist.
+        let deferred = DepFileState {
+            input_signatures: Mutex::new(DepFileStateInputSignatures::Deferred(None)),
+            ..state
+        };
+        assert!(deferred.to_persisted(&fs)?.is_none());
+
+        Ok(())
+    }
+
+    /// A materializer that has all the outputs it is asked about, and persists dep file state in
+    /// memory, so that it can outlive `DEP_FILES` like the deferred materializer's sqlite db does.
+    #[derive(Allocative, Default)]
+    struct PersistingMaterializer {
+        #[allocative(skip)]
+        dep_file_states: Mutex<HashMap<String, String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl DepFileStateStore for PersistingMaterializer {
+        async fn get(&self, key: String) -> anyhow::Result<Option<String>> {
+            Ok(self.dep_file_states.lock().get(&key).cloned())
+        }
+
+        async fn insert(&self, key: String, state: String) -> anyhow::Result<()> {
+            self.dep_file_states.lock().insert(key, state);
+            Ok(())
+        }
+
+        async fn delete(&self, key: String) -> anyhow::Result<()> {
+            self.dep_file_states.lock().remove(&key);
+            Ok(())
+        }
+
+        async fn delete_all(&self) -> anyhow::Result<()> {
+            self.dep_file_states.lock().clear();
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Materializer for PersistingMaterializer {
+        async fn declare_existing(
+            &self,
+            _artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+        ) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        async fn declare_copy_impl(
+            &self,
+            _path: ProjectRelativePathBuf,
+            _value: ArtifactValue,
+            _srcs: Vec<CopiedArtifact>,
+        ) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        async fn declare_cas_many_impl<'a, 'b>(
+            &self,
+            _info: Arc<CasDownloadInfo>,
+            _artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+        ) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        async fn declare_http(
+            &self,
+            _path: ProjectRelativePathBuf,
+            _info: HttpDownloadInfo,
+        ) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        async fn declare_match(
+            &self,
+            _artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+        ) -> anyhow::Result<DeclareMatchOutcome> {
+            Ok(DeclareMatchOutcome::Match)
+        }
+
+        async fn declare_write<'a>(
+            &self,
+            _gen: Box<dyn FnOnce() -> anyhow::Result<Vec<WriteRequest>> + Send + 'a>,
+        ) -> anyhow::Result<Vec<ArtifactValue>> {
+            unimplemented!()
+        }
+
+        async fn invalidate_many(&self, _paths: Vec<ProjectRelativePathBuf>) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        async fn materialize_many(
+            &self,
+            artifact_paths: Vec<ProjectRelativePathBuf>,
+        ) -> anyhow::Result<BoxStream<'static, Result<(), MaterializationError>>> {
+            Ok(stream::iter(artifact_paths.into_iter().map(|_| Ok(()))).boxed())
+        }
+
+        async fn try_materialize_final_artifact(
+            &self,
+            _artifact_path: ProjectRelativePathBuf,
+        ) -> anyhow::Result<bool> {
+            unimplemented!()
+        }
+
+        async fn get_materialized_file_paths(
+            &self,
+            _paths: Vec<ProjectRelativePathBuf>,
+        ) -> anyhow::Result<Vec<Result<ProjectRelativePathBuf, ArtifactNotMaterializedReason>>>
+        {
+            unimplemented!()
+        }
+
+        fn dep_file_state_store(&self) -> Option<&dyn DepFileStateStore> {
+            Some(self)
+        }
+    }
+
+    struct TestActionExecutionCtx {
+        fs: ArtifactFs,
+        materializer: PersistingMaterializer,
+        blocking_executor: DummyBlockingExecutor,
+        events: EventDispatcher,
+        inputs: HashMap<ArtifactGroup, ArtifactGroupValues>,
+    }
+
+    #[async_trait::async_trait]
+    impl ActionExecutionCtx for TestActionExecutionCtx {
+        fn target(&self) -> CommandExecutionTarget<'_> {
+            unimplemented!()
+        }
+
+        fn fs(&self) -> &ArtifactFs {
+            &self.fs
+        }
+
+        fn executor_fs(&self) -> ExecutorFs {
+            ExecutorFs::new(&self.fs, PathSeparatorKind::Unix)
+        }
+
+        fn materializer(&self) -> &dyn Materializer {
+            &self.materializer
+        }
+
+        fn events(&self) -> &EventDispatcher {
+            &self.events
+        }
+
+        async fn exec_cmd(
+            &mut self,
+            _request: &CommandExecutionRequest,
+        ) -> anyhow::Result<(
+            IndexMap<CommandExecutionOutput, ArtifactValue>,
+            ActionExecutionMetadata,
+        )> {
+            unimplemented!()
+        }
+
+        async fn cleanup_outputs(&mut self) -> anyhow::Result<()> {
+            unimplemented!()
+        }
+
+        fn artifact_values(&self, input: &ArtifactGroup) -> &ArtifactGroupValues {
+            &self.inputs[input]
+        }
+
+        fn blocking_executor(&self) -> &dyn BlockingExecutor {
+            &self.blocking_executor
+        }
+
+        fn re_client(&self) -> ManagedRemoteExecutionClient {
+            ManagedRemoteExecutionClient::testing_new_dummy()
+        }
+
+        fn run_action_knobs(&self) -> RunActionKnobs {
+            RunActionKnobs::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dep_file_hit_after_restart() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let (_, state) = computed_state(&target());
+        let key = DepFilesKey::new(
+            BaseDeferredKey::TargetLabel(target()),
+            Category::try_from("cxx_compile").unwrap(),
+            Some("restart.cpp".to_owned()),
+        );
+
+        let header = ArtifactGroup::Artifact(Artifact::from(SourceArtifact::new(BuckPath::new(
+            Package::testing_new("cell", "pkg"),
+            PackageRelativePathBuf::unchecked_new("foo.h".to_owned()),
+        ))));
+        let header_value = |content: &str| {
+            ArtifactGroupValues::from_artifact(
+                header.unpack_artifact().unwrap().dupe(),
+                ArtifactValue::file(FileMetadata {
+                    digest: digest(content),
+                    is_executable: false,
+                }),
+            )
+        };
+        let declared_inputs = PartitionedInputs {
+            untagged: Vec::new(),
+            tagged: hashmap! { Arc::from("dep") => vec![header.dupe()] },
+        };
+
+        let mut ctx = TestActionExecutionCtx {
+            fs: artifact_fs_at(temp.path().dupe()),
+            materializer: PersistingMaterializer::default(),
+            blocking_executor: DummyBlockingExecutor {
+                fs: temp.path().dupe(),
+            },
+            events: EventDispatcher::null(),
+            inputs: hashmap! { header.dupe() => header_value("header") },
+        };
+
+        // The action ran, and listed the header in its dep file.
+        for declared_dep_file in state.declared_dep_files.tagged.values() {
+            let path = ctx.fs.resolve(declared_dep_file.output.get_path())?;
+            ctx.fs
+                .fs()
+                .write_file(&path, "cell_path/pkg/foo.h\n", false)?;
+        }
+        populate_dep_files(
+            key.clone(),
+            ExpandedCommandLineDigest::from_hex(&state.cli_digest.to_hex())?,
+            declared_inputs.clone(),
+            state.declared_dep_files.clone(),
+            &state.result,
+            &ctx,
+        )
+        .await?;
+
+        // Restarting the daemon forgets about dep files that aren't persisted.
+        DEP_FILES.remove(&key);
+
+        let outputs = match_or_clear_dep_file(
+            &key,
+            &state.cli_digest,
+            &declared_inputs,
+            &state.declared_dep_files,
+            &ctx,
+        )
+        .await?;
+        assert_eq!(outputs, Some(state.result.dupe()));
+
+        // Changing the header is still a miss, which drops the persisted state.
+        DEP_FILES.remove(&key);
+        ctx.inputs = hashmap! { header.dupe() => header_value("changed header") };
+        let outputs = match_or_clear_dep_file(
+            &key,
+            &state.cli_digest,
+            &declared_inputs,
+            &state.declared_dep_files,
+            &ctx,
+        )
+        .await?;
+        assert_eq!(outputs, None);
+        assert!(ctx.materializer.get(key.to_string()).await?.is_none());
+
+        Ok(())
+    }
 }
@@ -56,6 +56,18 @@ impl ExpandedCommandLine {
     }
 }
 
+impl ExpandedCommandLineDigest {
+    pub(crate) fn to_hex(&self) -> String {
+        hex::encode(self.0.as_bytes())
+    }
+
+    pub(crate) fn from_hex(s: &str) -> anyhow::Result<Self> {
+        let mut bytes = [0; blake3::OUT_LEN];
+        hex::decode_to_slice(s, &mut bytes)?;
+        Ok(Self(blake3::Hash::from(bytes)))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::hash_map::RandomState;
@@ -108,6 +120,21 @@ mod test {
         assert_ne!(cmd2.fingerprint(), cmd3.fingerprint());
     }
 
+    #[test]
+    fn test_digest_hex() {
+        let digest = ExpandedCommandLine {
+            cli: vec!["foo".to_owned()],
+            env: Default::default(),
+        }
+        .fingerprint();
+
+        assert_eq!(
+            ExpandedCommandLineDigest::from_hex(&digest.to_hex()).unwrap(),
+            digest
+        );
+        assert!(ExpandedCommandLineDigest::from_hex("foo").is_err());
+    }
+
     #[test]
     fn test_hash_stability() {
         fn env() -> HashMap<String, String> {
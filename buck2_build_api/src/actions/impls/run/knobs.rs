@@ -13,12 +13,17 @@ use gazebo::prelude::*;
 /// Knobs controlling how RunAction works.
 #[derive(Copy, Clone, Dupe, Default)]
 pub struct RunActionKnobs {
-    /// Process dep files as they are generated.
+    /// Process dep files as they are generated. This is always done when the materializer persists
+    /// dep file state, since that requires the fingerprints computed here.
     pub eager_dep_files: bool,
 
     /// Hash all commands using the same mechanism as dep files. This allows us to skip
     /// re-executing commands if their inputs and outputs haven't changed.
     pub hash_all_commands: bool,
+
+    /// Whether the executors use the remote dep file cache, in which case commands with dep files
+    /// describe them in their request, see `ExecutorGlobalKnobs`.
+    pub remote_dep_file_cache: bool,
 }
 
 pub trait HasRunActionKnobs {
@@ -39,6 +39,7 @@ use crate::actions::artifact::build_artifact::BuildArtifact;
 use crate::actions::execute::action_executor::ActionExecutionKind;
 use crate::actions::execute::action_executor::ActionExecutionMetadata;
 use crate::actions::execute::action_executor::ActionOutputs;
+use crate::actions::impls::run::dep_files::make_remote_dep_files;
 use crate::actions::impls::run::dep_files::match_or_clear_dep_file;
 use crate::actions::impls::run::dep_files::populate_dep_files;
 use crate::actions::impls::run::dep_files::DepFilesCommandLineVisitor;
@@ -47,6 +48,7 @@ use crate::actions::impls::run::dep_files::RunActionDepFiles;
 use crate::actions::impls::run::expanded_command_line::ExpandedCommandLine;
 use crate::actions::impls::run::metadata::metadata_content;
 use crate::actions::Action;
+use crate::actions::ActionCommand;
 use crate::actions::ActionExecutable;
 use crate::actions::ActionExecutionCtx;
 use crate::actions::IncrementalActionExecutable;
@@ -275,6 +277,15 @@ impl Action for RunAction {
             "no_outputs_cleanup".to_owned() => self.inner.no_outputs_cleanup.to_string(),
         }
     }
+
+    fn aquery_command(&self, fs: &ExecutorFs) -> anyhow::Result<Option<ActionCommand>> {
+        let ExpandedCommandLine { cli, env } =
+            self.expand_command_line(fs, &mut SimpleCommandLineArtifactVisitor::new())?;
+        Ok(Some(ActionCommand {
+            cli,
+            env: env.into_iter().sorted().collect(),
+        }))
+    }
 }
 
 #[async_trait]
@@ -399,6 +410,29 @@ impl IncrementalActionExecutable for RunAction {
         .with_local_environment_inheritance(EnvironmentInheritance::local_command_exclusions())
         .with_force_full_hybrid_if_capable(self.inner.force_full_hybrid_if_capable);
 
+        // Results that depend on action metadata can't be shared across different inputs.
+        let req = match &dep_files {
+            Some((_, cli_digest, declared_inputs, declared_dep_files))
+                if ctx.run_action_knobs().remote_dep_file_cache
+                    && !self.inner.dep_files.labels.is_empty()
+                    && self.inner.metadata_param.is_none() =>
+            {
+                let outputs = self
+                    .outputs
+                    .iter()
+                    .map(|b| fs.buck_out_path_resolver().resolve_gen(b.get_path()))
+                    .collect::<Vec<_>>();
+                req.with_remote_dep_files(make_remote_dep_files(
+                    cli_digest,
+                    declared_inputs,
+                    declared_dep_files,
+                    &outputs,
+                    ctx,
+                )?)
+            }
+            _ => req,
+        };
+
         let (outputs, meta) = ctx.exec_cmd(&req).await?;
 
         let outputs = outputs
@@ -139,9 +139,23 @@ pub trait Action: Allocative + Debug + Send + Sync + 'static {
         indexmap! {}
     }
 
+    /// The command this action runs, for actions that run one. Unlike the `cmd` in
+    /// `aquery_attributes`, this keeps the arguments and environment structured.
+    fn aquery_command(&self, _fs: &ExecutorFs) -> anyhow::Result<Option<ActionCommand>> {
+        Ok(None)
+    }
+
     // TODO this probably wants more data for execution, like printing a short_name and the target
 }
 
+/// The command an action runs, with paths resolved as they would be for execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionCommand {
+    pub cli: Vec<String>,
+    /// The environment the action sets, sorted by variable name.
+    pub env: IndexMap<String, String>,
+}
+
 pub enum ActionExecutable<'a> {
     Pristine(&'a dyn PristineActionExecutable),
     Incremental(&'a dyn IncrementalActionExecutable),
@@ -36,6 +36,7 @@ use starlark::values::ValueLike;
 use starlark::values::ValueTyped;
 use thiserror::Error;
 
+use crate::actions::RegisteredAction;
 use crate::analysis::registry::AnalysisRegistry;
 use crate::attrs::resolve::ctx::AnalysisQueryResult;
 use crate::attrs::resolve::ctx::AttrResolutionContext;
@@ -109,6 +110,14 @@ impl AnalysisResult {
     pub fn lookup_deferred(&self, id: DeferredId) -> anyhow::Result<DeferredLookup<'_>> {
         self.deferred.lookup_deferred(id)
     }
+
+    /// The actions registered by the analysis. This doesn't include those registered later by
+    /// dynamic outputs.
+    pub fn iter_actions(&self) -> impl Iterator<Item = &Arc<RegisteredAction>> {
+        self.deferred
+            .iter_trivial()
+            .filter_map(|value| value.downcast::<Arc<RegisteredAction>>().ok())
+    }
 }
 
 // Contains a `module` that things must live on, and various `FrozenProviderCollectionValue`s
@@ -199,16 +199,16 @@ impl<'c> Calculation<'c> for DiceComputations {
         let node = self.get_target_node(target.target()).await?;
 
         let get_platform_configuration = async || -> SharedResult<Configuration> {
-            Ok(match global_target_platform {
-                Some(global_target_platform) => {
-                    self.get_platform_configuration(global_target_platform)
-                        .await?
-                }
+            let platform = match global_target_platform {
+                Some(global_target_platform) => global_target_platform,
                 None => match node.get_default_target_platform() {
-                    Some(target) => self.get_platform_configuration(target.target()).await?,
-                    None => self.get_default_platform(target.target()).await?,
+                    Some(target) => target.target(),
+                    // The default platform has the configuration modifiers applied already.
+                    None => return self.get_default_platform(target.target()).await,
                 },
-            })
+            };
+            self.apply_configuration_modifiers(self.get_platform_configuration(platform).await?)
+                .await
         };
 
         match node.rule_kind() {
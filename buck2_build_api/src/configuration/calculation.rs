@@ -35,14 +35,17 @@ use buck2_node::configuration::toolchain_constraints::ToolchainConstraints;
 use derive_more::Display;
 use dice::DiceComputations;
 use dice::Key;
+use dice::UserComputationData;
 use gazebo::prelude::*;
 use indexmap::IndexSet;
+use itertools::Itertools;
 use starlark::collections::SmallMap;
 use thiserror::Error;
 
 use crate::analysis::calculation::RuleAnalysisCalculation;
 use crate::configuration::ConfigurationCalculation;
 use crate::configuration::ExecutionPlatforms;
+use crate::configuration::HasConfigurationModifiers;
 use crate::configuration::ResolvedConfiguration;
 use crate::interpreter::rule_defs::provider::builtin::configuration_info::FrozenConfigurationInfo;
 use crate::interpreter::rule_defs::provider::builtin::execution_platform_registration_info::ExecutionPlatformRegistrationInfo;
@@ -59,6 +62,10 @@ pub enum ConfigurationError {
         "Expected `{0}` to provide a `ExecutionPlatformRegistrationInfo` as it's configured as the `build.execution_platforms` value."
     )]
     MissingExecutionPlatformRegistrationInfo(TargetLabel),
+    #[error(
+        "Configuration modifier `{0}` sets buckconfig values, but modifiers can only set constraints."
+    )]
+    ModifierSetsBuckconfigs(TargetLabel),
 }
 
 async fn get_target_platform_detector(
@@ -315,10 +322,69 @@ async fn configuration_matches(
     Ok(true)
 }
 
+async fn apply_configuration_modifiers(
+    ctx: &DiceComputations,
+    cfg: &Configuration,
+    modifiers: &[TargetLabel],
+) -> anyhow::Result<Configuration> {
+    let mut modifiers_data = Vec::with_capacity(modifiers.len());
+    for modifier in modifiers {
+        let analysis_result = ctx.get_configuration_analysis_result(modifier).await?;
+        let modifier_data = FrozenConfigurationInfo::from_providers(
+            analysis_result.providers().provider_collection(),
+        )
+        .ok_or_else(|| ConfigurationError::MissingConfigurationInfoProvider(modifier.dupe()))?
+        .to_configuration_data();
+        modifiers_data.push((modifier, modifier_data));
+    }
+    modify_configuration(cfg, modifiers_data)
+}
+
+/// Merges the constraints of the modifiers, in order, on top of those of `cfg`. The resulting
+/// configuration is labelled with the platform and the modifiers, e.g. `//:platform+//:modifier`.
+fn modify_configuration<'a>(
+    cfg: &Configuration,
+    modifiers: impl IntoIterator<Item = (&'a TargetLabel, ConfigurationData)>,
+) -> anyhow::Result<Configuration> {
+    // Targets without a default platform are configured with the unspecified platform, in which
+    // case the modifiers are the only source of constraints.
+    let (mut label, mut data) = if cfg.is_bound() {
+        (cfg.label()?.to_owned(), cfg.data()?.clone())
+    } else {
+        (cfg.to_string(), ConfigurationData::empty())
+    };
+
+    for (modifier, modifier_data) in modifiers {
+        if !modifier_data.buckconfigs.is_empty() {
+            return Err(ConfigurationError::ModifierSetsBuckconfigs(modifier.dupe()).into());
+        }
+
+        data = data.merge(modifier_data);
+        label.push('+');
+        label.push_str(&modifier.to_string());
+    }
+
+    Configuration::from_platform(label, data)
+}
+
 #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
 #[display(fmt = "ExecutionPlatforms")]
 pub struct ExecutionPlatformsKey;
 
+#[derive(Clone, Dupe, Debug)]
+struct ConfigurationModifiers(Arc<Vec<TargetLabel>>);
+
+#[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(
+    fmt = "ModifiedConfiguration({}, {})",
+    cfg,
+    "modifiers.iter().join(\"+\")"
+)]
+struct ModifiedConfigurationKey {
+    cfg: Configuration,
+    modifiers: Arc<Vec<TargetLabel>>,
+}
+
 #[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
 #[display(fmt = "ConfigurationNode({}, {})", cfg_target, target_cfg)]
 struct ConfigurationNodeKey {
@@ -349,11 +415,49 @@ impl ConfigurationCalculation for DiceComputations {
 
     async fn get_default_platform(&self, target: &TargetLabel) -> SharedResult<Configuration> {
         let detector = get_target_platform_detector(self).await?;
-        if let Some(target) = detector.detect(target) {
-            return self.get_platform_configuration(target).await.shared_error();
+        let cfg = match detector.detect(target) {
+            Some(target) => self
+                .get_platform_configuration(target)
+                .await
+                .shared_error()?,
+            // TODO(cjhopman): This needs to implement buck1's approach to determining target platform, it's currently missing the fallback to buckconfig parser.target_platform.
+            None => Configuration::unspecified(),
+        };
+        self.apply_configuration_modifiers(cfg).await
+    }
+
+    async fn apply_configuration_modifiers(
+        &self,
+        cfg: Configuration,
+    ) -> SharedResult<Configuration> {
+        #[async_trait]
+        impl Key for ModifiedConfigurationKey {
+            type Value = SharedResult<Configuration>;
+
+            async fn compute(&self, ctx: &DiceComputations) -> Self::Value {
+                apply_configuration_modifiers(ctx, &self.cfg, &self.modifiers)
+                    .await
+                    .with_context(|| {
+                        format!("when applying configuration modifiers to `{}`", self.cfg)
+                    })
+                    .shared_error()
+            }
+
+            fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+                match (x, y) {
+                    (Ok(x), Ok(y)) => x == y,
+                    _ => false,
+                }
+            }
+        }
+
+        // Most commands don't pass any modifiers, so don't record a node per platform for them.
+        let modifiers = self.per_transaction_data().get_configuration_modifiers();
+        if modifiers.is_empty() {
+            return Ok(cfg);
         }
-        // TODO(cjhopman): This needs to implement buck1's approach to determining target platform, it's currently missing the fallback to buckconfig parser.target_platform.
-        Ok(Configuration::unspecified())
+        self.compute(&ModifiedConfigurationKey { cfg, modifiers })
+            .await?
     }
 
     async fn get_resolved_configuration<'a, T: Iterator<Item = &'a TargetLabel> + Send>(
@@ -517,3 +621,145 @@ impl ConfigurationCalculation for DiceComputations {
         .await
     }
 }
+
+impl HasConfigurationModifiers for UserComputationData {
+    fn set_configuration_modifiers(&mut self, modifiers: Vec<TargetLabel>) {
+        self.data.set(ConfigurationModifiers(Arc::new(modifiers)));
+    }
+
+    fn get_configuration_modifiers(&self) -> Arc<Vec<TargetLabel>> {
+        self.data
+            .get::<ConfigurationModifiers>()
+            .map_or_else(|_| Arc::new(Vec::new()), |m| m.0.dupe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use buck2_core::configuration::constraints::ConstraintKey;
+    use buck2_core::configuration::constraints::ConstraintValue;
+    use buck2_core::configuration::Configuration;
+    use buck2_core::configuration::ConfigurationData;
+    use buck2_core::target::testing::TargetLabelExt;
+    use buck2_core::target::TargetLabel;
+
+    use crate::configuration::calculation::modify_configuration;
+    use crate::configuration::calculation::ConfigurationError;
+
+    fn constraints(constraints: &[(&str, &str)]) -> ConfigurationData {
+        ConfigurationData::new(
+            constraints
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        ConstraintKey(TargetLabel::testing_parse(k)),
+                        ConstraintValue(TargetLabel::testing_parse(v)),
+                    )
+                })
+                .collect(),
+            BTreeMap::new(),
+        )
+    }
+
+    fn platform() -> Configuration {
+        Configuration::from_platform(
+            "root//platforms:linux_x86".to_owned(),
+            constraints(&[
+                ("root//constraints:os", "root//constraints:linux"),
+                ("root//constraints:cpu", "root//constraints:x86_64"),
+            ]),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_modifiers_take_precedence_over_platform() -> anyhow::Result<()> {
+        let arm64 = TargetLabel::testing_parse("root//modifiers:arm64");
+        let cfg = modify_configuration(
+            &platform(),
+            [(
+                &arm64,
+                constraints(&[("root//constraints:cpu", "root//constraints:arm64")]),
+            )],
+        )?;
+
+        assert_eq!(
+            &constraints(&[
+                ("root//constraints:os", "root//constraints:linux"),
+                ("root//constraints:cpu", "root//constraints:arm64"),
+            ]),
+            cfg.data()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_later_modifiers_take_precedence() -> anyhow::Result<()> {
+        let arm64 = TargetLabel::testing_parse("root//modifiers:arm64");
+        let riscv = TargetLabel::testing_parse("root//modifiers:riscv");
+        let cfg = modify_configuration(
+            &platform(),
+            [
+                (
+                    &arm64,
+                    constraints(&[("root//constraints:cpu", "root//constraints:arm64")]),
+                ),
+                (
+                    &riscv,
+                    constraints(&[("root//constraints:cpu", "root//constraints:riscv")]),
+                ),
+            ],
+        )?;
+
+        assert_eq!(
+            &constraints(&[
+                ("root//constraints:os", "root//constraints:linux"),
+                ("root//constraints:cpu", "root//constraints:riscv"),
+            ]),
+            cfg.data()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_configuration_label() -> anyhow::Result<()> {
+        let arm64 = TargetLabel::testing_parse("root//modifiers:arm64");
+        let opt = TargetLabel::testing_parse("root//modifiers:opt");
+        let cfg = modify_configuration(
+            &platform(),
+            [
+                (
+                    &arm64,
+                    constraints(&[("root//constraints:cpu", "root//constraints:arm64")]),
+                ),
+                (
+                    &opt,
+                    constraints(&[("root//constraints:mode", "root//constraints:opt")]),
+                ),
+            ],
+        )?;
+
+        assert_eq!(
+            "root//platforms:linux_x86+root//modifiers:arm64+root//modifiers:opt",
+            cfg.label()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_modifier_setting_buckconfigs_is_rejected() {
+        let modifier = TargetLabel::testing_parse("root//modifiers:buckconfig");
+        let modifier_data = ConfigurationData::new(
+            BTreeMap::new(),
+            BTreeMap::from_iter([("foo.bar".to_owned(), "baz".to_owned())]),
+        );
+        let err = modify_configuration(&platform(), [(&modifier, modifier_data)]).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<ConfigurationError>(),
+            Some(ConfigurationError::ModifierSetsBuckconfigs(label)) if label == &modifier
+        ));
+    }
+}
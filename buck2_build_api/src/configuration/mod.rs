@@ -26,10 +26,29 @@ pub mod calculation;
 
 pub type ExecutionPlatforms = Arc<Vec<ExecutionPlatform>>;
 
+/// Configuration modifiers (`--modifier`) passed to the current command. These are per-command
+/// data rather than DICE state, so that concurrent commands can pass different modifiers.
+pub trait HasConfigurationModifiers {
+    fn set_configuration_modifiers(&mut self, modifiers: Vec<TargetLabel>);
+
+    /// Returns the modifiers of the current command, or none if they were not set.
+    fn get_configuration_modifiers(&self) -> Arc<Vec<TargetLabel>>;
+}
+
 #[async_trait]
 pub trait ConfigurationCalculation {
+    /// Returns the default platform of a target, with the configuration modifiers applied.
     async fn get_default_platform(&self, target: &TargetLabel) -> SharedResult<Configuration>;
 
+    /// Applies the configuration modifiers passed on the command line (`--modifier`) on top of
+    /// the platform a target would otherwise be configured with. The constraints set by the
+    /// modifiers take precedence over those of the platform, and later modifiers take precedence
+    /// over earlier ones.
+    async fn apply_configuration_modifiers(
+        &self,
+        cfg: Configuration,
+    ) -> SharedResult<Configuration>;
+
     async fn get_platform_configuration(
         &self,
         target: &TargetLabel,
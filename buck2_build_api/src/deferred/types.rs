@@ -655,6 +655,14 @@ impl DeferredTable {
             None => Err(anyhow::anyhow!(DeferredErrors::DeferredNotFound(id.id))),
         }
     }
+
+    /// The values of the deferreds that were already computed when they were registered.
+    pub(crate) fn iter_trivial(&self) -> impl Iterator<Item = &Arc<dyn AnyValue>> {
+        self.0.iter().filter_map(|entry| match entry {
+            DeferredTableEntry::Trivial(value) => Some(&value.0),
+            DeferredTableEntry::Complex(..) => None,
+        })
+    }
 }
 
 impl DeferredResult {
@@ -1320,4 +1328,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn iter_trivial_deferred() -> anyhow::Result<()> {
+        let mut registry = DeferredRegistry::new(BaseKey::Base(dummy_base()));
+
+        registry.defer_trivial(123);
+        registry.defer(FakeDeferred {
+            inputs: IndexSet::new(),
+            val: 789,
+        });
+        registry.defer_trivial("foo".to_owned());
+        registry.defer_trivial(456);
+
+        // This is how the actions registered by an analysis are found.
+        let result = DeferredTable::new(registry.take_result()?);
+        assert_eq!(result.iter_trivial().count(), 3);
+        assert_eq!(
+            result
+                .iter_trivial()
+                .filter_map(|value| value.downcast::<i32>().ok())
+                .copied()
+                .collect::<Vec<_>>(),
+            vec![123, 456]
+        );
+
+        Ok(())
+    }
 }
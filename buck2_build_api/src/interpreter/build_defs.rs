@@ -254,6 +254,7 @@ mod tests {
                         "target_compatible_with": [],
                         "tests": [],
                         "visibility": [],
+                        "within_view": null,
                     },
             }),
             targets_to_json(eval_result.targets(), AttrInspectOptions::All)?
@@ -470,6 +470,7 @@ mod tests {
                 "target_compatible_with": [],
                 "tests": [],
                 "visibility": [],
+                "within_view": null,
             },
             "target2": {
                 "name": "target2",
@@ -485,6 +486,7 @@ mod tests {
                 "target_compatible_with": [],
                 "tests": [],
                 "visibility": [],
+                "within_view": null,
             },
         });
         let actual = targets_to_json(&result, AttrInspectOptions::All)?;
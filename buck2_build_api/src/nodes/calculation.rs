@@ -638,6 +638,14 @@ async fn compute_configured_target_node_no_transition(
                         )))
                         .shared_error(),
                     )
+                } else if !target_node.is_within_view(dep.name().unconfigured()) {
+                    ControlFlow::Break(
+                        Err(anyhow::anyhow!(VisibilityError::NotWithinView(
+                            dep.name().unconfigured().dupe(),
+                            target_label.unconfigured().dupe(),
+                        )))
+                        .shared_error(),
+                    )
                 } else {
                     ControlFlow::Continue(dep)
                 }
@@ -837,6 +845,7 @@ mod tests {
     use buck2_core::fs::paths::file_name::FileNameBuf;
     use buck2_core::package::testing::PackageExt;
     use buck2_core::package::Package;
+    use buck2_core::pattern::ParsedPattern;
     use buck2_core::provider::label::ProvidersLabel;
     use buck2_core::provider::label::ProvidersName;
     use buck2_core::target::TargetLabel;
@@ -862,6 +871,9 @@ mod tests {
     use buck2_node::nodes::unconfigured::TargetsMap;
     use buck2_node::rule_type::RuleType;
     use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_node::visibility::VisibilityPattern;
+    use buck2_node::visibility::VisibilitySpecification;
+    use buck2_node::visibility::WithinViewSpecification;
     use dice::testing::DiceBuilder;
     use dice::UserComputationData;
     use gazebo::prelude::*;
@@ -1001,4 +1013,157 @@ mod tests {
 
         Ok(())
     }
+
+    fn deps_attr(deps: &[&TargetLabel]) -> (&'static str, Attribute, CoercedAttr) {
+        (
+            "some_deps",
+            Attribute::testing_new(None, AttrType::list(AttrType::dep(Vec::new()))),
+            CoercedAttr::from_literal(AttrLiteral::List(
+                deps.map(|dep| {
+                    CoercedAttr::from_literal(AttrLiteral::Dep(box DepAttr::new(
+                        DepAttrType::new(Vec::new(), DepAttrTransition::Identity),
+                        ProvidersLabel::new((*dep).dupe(), ProvidersName::Default),
+                    )))
+                })
+                .into_boxed_slice(),
+                AttrType::dep(Vec::new()),
+            )),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_within_view() -> anyhow::Result<()> {
+        let cfg = Configuration::testing_new();
+        let foo = Package::testing_new("cell", "foo");
+        let bar = Package::testing_new("cell", "bar");
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::unchecked_new("cell", "foo", "def.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let node = |pkg: &Package,
+                    name: &str,
+                    deps: &[&TargetLabel],
+                    visibility: VisibilitySpecification,
+                    within_view: WithinViewSpecification| {
+            let label = TargetLabel::new(pkg.dupe(), TargetName::unchecked_new(name));
+            let node = TargetNode::testing_new_with_visibility(
+                label.dupe(),
+                rule_type.dupe(),
+                vec![deps_attr(deps)],
+                visibility,
+                within_view,
+            );
+            (label, node)
+        };
+        let only_view = |pkg: &Package| {
+            WithinViewSpecification::VisibleTo(vec![VisibilityPattern(ParsedPattern::Package(
+                pkg.dupe(),
+            ))])
+        };
+
+        let (public, public_node) = node(
+            &bar,
+            "public",
+            &[],
+            VisibilitySpecification::Public,
+            WithinViewSpecification::Public,
+        );
+        let (private, private_node) = node(
+            &bar,
+            "private",
+            &[],
+            VisibilitySpecification::Default,
+            WithinViewSpecification::Public,
+        );
+        // `bar` is within the view, as is `foo` implicitly.
+        let (allowed, allowed_node) = node(
+            &foo,
+            "allowed",
+            &[&public],
+            VisibilitySpecification::Public,
+            only_view(&bar),
+        );
+        let (sibling, sibling_node) = node(
+            &foo,
+            "sibling",
+            &[&allowed],
+            VisibilitySpecification::Public,
+            only_view(&bar),
+        );
+        // `bar:public` is visible to everyone, but isn't within the view.
+        let (outside_view, outside_view_node) = node(
+            &foo,
+            "outside_view",
+            &[&public],
+            VisibilitySpecification::Public,
+            only_view(&foo),
+        );
+        // `bar:private` is within the view, but isn't visible.
+        let (not_visible, not_visible_node) = node(
+            &foo,
+            "not_visible",
+            &[&private],
+            VisibilitySpecification::Public,
+            only_view(&bar),
+        );
+
+        let eval_result = |pkg: &Package, nodes: Vec<TargetNode>| {
+            Ok(Arc::new(EvaluationResult::new(
+                Arc::new(BuildFilePath::new(
+                    pkg.dupe(),
+                    FileNameBuf::unchecked_new("BUCK"),
+                )),
+                Vec::new(),
+                TargetsMap::from_iter(nodes.into_iter().map(|n| (n.label().name().dupe(), n))),
+            )))
+        };
+
+        let mut data = UserComputationData::new();
+        set_fallback_executor_config(&mut data.data, CommandExecutorConfig::testing_local());
+        let computations = DiceBuilder::new()
+            .mock_and_return(
+                InterpreterResultsKey(foo.dupe()),
+                eval_result(
+                    &foo,
+                    vec![
+                        allowed_node,
+                        sibling_node,
+                        outside_view_node,
+                        not_visible_node,
+                    ],
+                ),
+            )
+            .mock_and_return(
+                InterpreterResultsKey(bar.dupe()),
+                eval_result(&bar, vec![public_node, private_node]),
+            )
+            .mock_and_return(ExecutionPlatformsKey, Ok(None))
+            .build(data)?
+            .commit();
+
+        for target in [&allowed, &sibling] {
+            computations
+                .get_configured_target_node(&target.configure(cfg.dupe()))
+                .await?
+                .require_compatible()?;
+        }
+
+        for (target, expected) in [
+            (&outside_view, "is not within the view of"),
+            (&not_visible, "is not visible to"),
+        ] {
+            match computations
+                .get_configured_target_node(&target.configure(cfg.dupe()))
+                .await
+            {
+                Ok(_) => panic!("Expected `{}` to fail", target),
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    assert!(message.contains(expected), "{}", message);
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -9,12 +9,16 @@
 
 //! Implementation of common cquery/uquery pieces.
 
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_query::query::environment::QueryEnvironment;
 use buck2_query::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use buck2_query::query::syntax::simple::eval::literals::extract_target_literals;
 use buck2_query::query::syntax::simple::eval::multi_query::process_multi_query;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
-use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+use buck2_query::query::syntax::simple::functions::macros::QueryMacros;
+use buck2_query::query::syntax::simple::functions::QueryFunctions;
+use dice::DiceComputations;
 use futures::Future;
 use gazebo::prelude::*;
 use starlark::collections::SmallSet;
@@ -28,12 +32,30 @@ enum EvalQueryError {
     PlaceholderInPattern(String),
 }
 
+/// The query macros defined in the `[query_macros]` section of the root cell's buckconfig,
+/// as `name = query`.
+pub async fn get_query_macros<Env: QueryEnvironment>(
+    ctx: &DiceComputations,
+) -> anyhow::Result<QueryMacros<Env>> {
+    let resolver = ctx.get_cell_resolver().await?;
+    let config = ctx.get_legacy_config_for_cell(resolver.root_cell()).await?;
+    let definitions = match config.get_section("query_macros") {
+        Some(section) => section
+            .iter()
+            .map(|(name, body)| (name.to_owned(), body.as_str().to_owned()))
+            .collect(),
+        None => Vec::new(),
+    };
+    QueryMacros::new(definitions)
+}
+
 pub async fn eval_query<
     Env: QueryEnvironment,
+    F: QueryFunctions<Env = Env>,
     Fut: Future<Output = anyhow::Result<Env>>,
     A: AsRef<str>,
 >(
-    functions: &DefaultQueryFunctionsModule<Env>,
+    functions: &F,
     query: &str,
     query_args: &[A],
     environment: impl FnOnce(Vec<String>) -> Fut,
@@ -13,6 +13,7 @@ use std::collections::VecDeque;
 use std::hash::Hash;
 use std::sync::Arc;
 
+use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
@@ -39,7 +40,9 @@ use ref_cast::RefCast;
 use serde::Serialize;
 
 use crate::actions::key::ActionKey;
+use crate::actions::ActionCommand;
 use crate::actions::RegisteredAction;
+use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::TransitiveSetProjectionKey;
 use crate::query::cquery::environment::CqueryDelegate;
 use crate::query::uquery::environment::QueryLiterals;
@@ -94,29 +97,113 @@ pub enum ActionInput {
     IndirectInputs(SetProjectionInputs),
 }
 
-#[derive(Derivative, Clone, Dupe)]
-#[derivative(Debug)]
+/// The actions producing `inputs`, including those reached through transitive sets.
+pub fn iter_action_inputs<'a>(
+    inputs: &'a [ActionInput],
+) -> impl Iterator<Item = &'a ActionKey> + Send + 'a {
+    struct Iter<'a> {
+        visited: HashSet<&'a SetProjectionInputs>,
+        queue: VecDeque<&'a SetProjectionInputs>,
+    }
+
+    impl<'a> Iter<'a> {
+        fn new<From: Iterator<Item = &'a SetProjectionInputs>>(iter: From) -> Self {
+            let mut visited = HashSet::new();
+            let mut queue = VecDeque::new();
+            for it in iter {
+                if visited.insert(it) {
+                    queue.push_back(it);
+                }
+            }
+            Self { visited, queue }
+        }
+    }
+
+    impl<'a> Iterator for Iter<'a> {
+        type Item = &'a SetProjectionInputs;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.queue.pop_front().map(|node| {
+                for child in &*node.node.children {
+                    if self.visited.insert(child) {
+                        self.queue.push_back(child);
+                    }
+                }
+
+                node
+            })
+        }
+    }
+
+    let direct = inputs.iter().filter_map(|input| match input {
+        ActionInput::ActionKey(action_key) => Some(action_key),
+        ActionInput::IndirectInputs(..) => None,
+    });
+
+    let indirect = Iter::new(inputs.iter().filter_map(|input| match input {
+        ActionInput::ActionKey(..) => None,
+        ActionInput::IndirectInputs(val) => Some(val),
+    }));
+
+    let indirect = Iter::new(indirect);
+
+    direct.chain(indirect.flat_map(|v| v.node.direct.iter()))
+}
+
+#[derive(Derivative, Clone, Dupe, Allocative)]
+#[derivative(Debug, PartialEq, Eq)]
 pub struct ActionQueryNode {
     action: Arc<RegisteredAction>,
+    #[derivative(PartialEq = "ignore")]
+    #[allocative(skip)]
     deps: Arc<Vec<ActionInput>>,
-    #[derivative(Debug = "ignore")]
+    /// The source files the action reads directly, rather than through a transitive set.
+    #[derivative(PartialEq = "ignore")]
+    #[allocative(skip)]
+    source_inputs: Arc<Vec<CellPath>>,
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    #[allocative(skip)]
     fs: Arc<ArtifactFs>,
 }
 
 impl ActionQueryNode {
-    pub fn new(action: Arc<RegisteredAction>, deps: Vec<ActionInput>, fs: Arc<ArtifactFs>) -> Self {
-        Self {
+    pub fn new(
+        action: Arc<RegisteredAction>,
+        deps: Vec<ActionInput>,
+        fs: Arc<ArtifactFs>,
+    ) -> anyhow::Result<Self> {
+        let source_inputs = action
+            .inputs()?
+            .iter()
+            .filter_map(|input| match input {
+                ArtifactGroup::Artifact(artifact) => artifact.get_source(),
+                ArtifactGroup::TransitiveSetProjection(..) => None,
+            })
+            .map(|source| source.get_path().to_cell_path())
+            .collect();
+        Ok(Self {
             action,
             deps: Arc::new(deps),
+            source_inputs: Arc::new(source_inputs),
             fs,
-        }
+        })
+    }
+
+    pub fn action(&self) -> &Arc<RegisteredAction> {
+        &self.action
+    }
+
+    fn executor_fs(&self) -> ExecutorFs {
+        ExecutorFs::new(&self.fs, self.action.execution_config().path_separator)
     }
 
     pub fn attrs(&self) -> IndexMap<String, String> {
-        self.action.action().aquery_attributes(&ExecutorFs::new(
-            &self.fs,
-            self.action.execution_config().path_separator,
-        ))
+        self.action.action().aquery_attributes(&self.executor_fs())
+    }
+
+    /// The command the action runs, if it runs one.
+    pub fn command(&self) -> anyhow::Result<Option<ActionCommand>> {
+        self.action.action().aquery_command(&self.executor_fs())
     }
 }
 
@@ -143,53 +230,7 @@ impl QueryTarget for ActionQueryNode {
 
     // TODO(cjhopman): Use existential traits to remove the Box<> once they are stabilized.
     fn deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
-        struct Iter<'a> {
-            visited: HashSet<&'a SetProjectionInputs>,
-            queue: VecDeque<&'a SetProjectionInputs>,
-        }
-
-        impl<'a> Iter<'a> {
-            fn new<From: Iterator<Item = &'a SetProjectionInputs>>(iter: From) -> Self {
-                let mut visited = HashSet::new();
-                let mut queue = VecDeque::new();
-                for it in iter {
-                    if visited.insert(it) {
-                        queue.push_back(it);
-                    }
-                }
-                Self { visited, queue }
-            }
-        }
-
-        impl<'a> Iterator for Iter<'a> {
-            type Item = &'a SetProjectionInputs;
-
-            fn next(&mut self) -> Option<Self::Item> {
-                self.queue.pop_front().map(|node| {
-                    for child in &*node.node.children {
-                        if self.visited.insert(child) {
-                            self.queue.push_back(child);
-                        }
-                    }
-
-                    node
-                })
-            }
-        }
-
-        let direct = self.deps.iter().filter_map(|input| match input {
-            ActionInput::ActionKey(action_key) => Some(action_key),
-            ActionInput::IndirectInputs(..) => None,
-        });
-
-        let indirect = Iter::new(self.deps.iter().filter_map(|input| match input {
-            ActionInput::ActionKey(..) => None,
-            ActionInput::IndirectInputs(val) => Some(val),
-        }));
-
-        let indirect = Iter::new(indirect);
-
-        box direct.chain(indirect.flat_map(|v| v.node.direct.iter()))
+        box iter_action_inputs(&self.deps)
     }
 
     fn exec_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
@@ -252,10 +293,13 @@ impl QueryTarget for ActionQueryNode {
 
     fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
         &self,
-        mut _func: F,
+        mut func: F,
     ) -> Result<(), E> {
-        // TODO(cjhopman): In addition to implementing this, we should be able to return an anyhow::Error here rather than panicing.
-        unimplemented!("inputs not yet implemented in aquery")
+        // TODO(cjhopman): Include the source files of transitive set inputs.
+        for input in self.source_inputs.iter() {
+            func(input.clone())?;
+        }
+        Ok(())
     }
 
     fn call_stack(&self) -> Option<String> {
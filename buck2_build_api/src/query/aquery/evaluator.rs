@@ -13,11 +13,14 @@ use std::sync::Arc;
 use buck2_core::fs::project::ProjectRelativePath;
 use buck2_core::target::TargetLabel;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::functions::macros::QueryMacros;
+use buck2_query::query::syntax::simple::functions::AugmentedQueryFunctions;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
 use gazebo::prelude::*;
 
 use crate::query::analysis::evaluator::eval_query;
+use crate::query::analysis::evaluator::get_query_macros;
 use crate::query::aquery::environment::ActionQueryNode;
 use crate::query::aquery::environment::AqueryEnvironment;
 use crate::query::dice::aquery::DiceAqueryDelegate;
@@ -27,6 +30,7 @@ use crate::query::uquery::environment::PreresolvedQueryLiterals;
 pub struct AqueryEvaluator<'c> {
     dice_query_delegate: Arc<DiceAqueryDelegate<'c>>,
     functions: DefaultQueryFunctionsModule<AqueryEnvironment<'c>>,
+    macros: QueryMacros<AqueryEnvironment<'c>>,
 }
 
 impl AqueryEvaluator<'_> {
@@ -35,7 +39,8 @@ impl AqueryEvaluator<'_> {
         query: &str,
         query_args: &[String],
     ) -> anyhow::Result<QueryEvaluationResult<ActionQueryNode>> {
-        eval_query(&self.functions, query, query_args, async move |literals| {
+        let functions = AugmentedQueryFunctions::augment(&self.functions, box self.macros.dupe());
+        eval_query(&functions, query, query_args, async move |literals| {
             let resolved_literals =
                 PreresolvedQueryLiterals::pre_resolve(&*self.dice_query_delegate, &literals).await;
             Ok(AqueryEnvironment::new(
@@ -58,8 +63,10 @@ pub async fn get_aquery_evaluator<'a, 'c: 'a>(
         get_dice_query_delegate(ctx, working_dir, global_target_platform).await?;
     let dice_query_delegate = Arc::new(DiceAqueryDelegate::new(dice_query_delegate).await?);
     let functions = DefaultQueryFunctionsModule::new();
+    let macros = get_query_macros(ctx).await?;
     Ok(AqueryEvaluator {
         dice_query_delegate,
         functions,
+        macros,
     })
 }
@@ -0,0 +1,209 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use buck2_common::executor_config::CommandExecutorConfig;
+use buck2_core::buck_path::BuckPath;
+use buck2_core::category::Category;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::cell_root_path::CellRootPathBuf;
+use buck2_core::cells::paths::CellRelativePathBuf;
+use buck2_core::cells::testing::CellResolverExt;
+use buck2_core::cells::CellName;
+use buck2_core::cells::CellResolver;
+use buck2_core::configuration::Configuration;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project::ProjectRelativePathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::package::package_relative_path::PackageRelativePathBuf;
+use buck2_core::package::testing::PackageExt;
+use buck2_core::package::Package;
+use buck2_core::target::testing::ConfiguredTargetLabelExt;
+use buck2_core::target::ConfiguredTargetLabel;
+use buck2_core::target::TargetName;
+use buck2_execute::artifact::fs::ArtifactFs;
+use buck2_execute::artifact::source_artifact::SourceArtifact;
+use buck2_execute::base_deferred_key::BaseDeferredKey;
+use buck2_execute::path::buck_out_path::BuckOutPathResolver;
+use buck2_execute::path::buck_out_path::BuckPathResolver;
+use buck2_query::query::environment::QueryTarget;
+use gazebo::prelude::*;
+use indexmap::indexset;
+
+use crate::actions::artifact::build_artifact::BuildArtifact;
+use crate::actions::artifact::testing::BuildArtifactTestingExt;
+use crate::actions::artifact::Artifact;
+use crate::actions::key::ActionKey;
+use crate::actions::testings::SimpleAction;
+use crate::actions::RegisteredAction;
+use crate::artifact_groups::ArtifactGroup;
+use crate::artifact_groups::TransitiveSetProjectionKey;
+use crate::deferred::types::testing::DeferredDataExt;
+use crate::deferred::types::testing::DeferredIdExt;
+use crate::deferred::types::DeferredData;
+use crate::deferred::types::DeferredId;
+use crate::deferred::types::DeferredKey;
+use crate::query::aquery::environment::iter_action_inputs;
+use crate::query::aquery::environment::ActionInput;
+use crate::query::aquery::environment::ActionQueryNode;
+use crate::query::aquery::environment::SetProjectionInputs;
+
+fn target() -> ConfiguredTargetLabel {
+    ConfiguredTargetLabel::testing_new(
+        Package::testing_new("cell", "pkg"),
+        TargetName::unchecked_new("foo"),
+        Configuration::testing_new(),
+    )
+}
+
+fn deferred_key(id: u32) -> DeferredKey {
+    DeferredKey::Base(
+        BaseDeferredKey::TargetLabel(target()),
+        DeferredId::testing_new(id),
+    )
+}
+
+fn build_artifact(path: &str, id: u32) -> BuildArtifact {
+    BuildArtifact::testing_new(
+        target(),
+        ForwardRelativePathBuf::unchecked_new(path.to_owned()),
+        DeferredId::testing_new(id),
+    )
+}
+
+fn source_artifact(path: &str) -> ArtifactGroup {
+    ArtifactGroup::Artifact(Artifact::from(SourceArtifact::new(BuckPath::new(
+        Package::testing_new("cell", "pkg"),
+        PackageRelativePathBuf::unchecked_new(path.to_owned()),
+    ))))
+}
+
+fn set_projection(
+    id: u32,
+    direct: Vec<ActionKey>,
+    children: Vec<SetProjectionInputs>,
+) -> SetProjectionInputs {
+    SetProjectionInputs::new(
+        TransitiveSetProjectionKey {
+            key: DeferredData::testing_new(deferred_key(id)),
+            projection: 0,
+        },
+        direct,
+        children,
+    )
+}
+
+fn artifact_fs() -> Arc<ArtifactFs> {
+    Arc::new(ArtifactFs::new(
+        BuckPathResolver::new(CellResolver::of_names_and_paths(&[(
+            CellName::unchecked_new("cell".into()),
+            CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+        )])),
+        BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck_out".into())),
+        ProjectRoot::new(AbsNormPathBuf::try_from(std::env::current_dir().unwrap()).unwrap()),
+    ))
+}
+
+fn action_node(inputs: Vec<ArtifactGroup>) -> anyhow::Result<ActionQueryNode> {
+    let output = build_artifact("foo.o", 0);
+    let action = RegisteredAction::new(
+        output.key().dupe(),
+        box SimpleAction::new(
+            inputs.into_iter().collect(),
+            indexset![output],
+            vec!["compile".to_owned()],
+            Category::try_from("compile").unwrap(),
+            Some("foo.cpp".to_owned()),
+        ),
+        CommandExecutorConfig::testing_local(),
+    );
+    ActionQueryNode::new(Arc::new(action), Vec::new(), artifact_fs())
+}
+
+#[test]
+fn test_inputs_are_the_source_inputs() -> anyhow::Result<()> {
+    let node = action_node(vec![
+        source_artifact("foo.cpp"),
+        ArtifactGroup::Artifact(Artifact::from(build_artifact("gen.h", 1))),
+        source_artifact("foo.h"),
+        ArtifactGroup::TransitiveSetProjection(TransitiveSetProjectionKey {
+            key: DeferredData::testing_new(deferred_key(2)),
+            projection: 0,
+        }),
+    ])?;
+
+    let mut inputs = Vec::new();
+    node.inputs_for_each(|input| {
+        inputs.push(input);
+        anyhow::Ok(())
+    })?;
+    // Build artifacts are found through the deps, and transitive sets aren't expanded.
+    assert_eq!(
+        inputs,
+        vec![
+            CellPath::new(
+                CellName::unchecked_new("cell".to_owned()),
+                CellRelativePathBuf::unchecked_new("pkg/foo.cpp".to_owned()),
+            ),
+            CellPath::new(
+                CellName::unchecked_new("cell".to_owned()),
+                CellRelativePathBuf::unchecked_new("pkg/foo.h".to_owned()),
+            ),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_attrs() -> anyhow::Result<()> {
+    let node = action_node(vec![source_artifact("foo.cpp")])?;
+
+    let mut attrs = Vec::new();
+    node.attrs_for_each(|k, v| {
+        attrs.push((k.to_owned(), v.to_string()));
+        anyhow::Ok(())
+    })?;
+    assert_eq!(
+        attrs,
+        vec![
+            ("kind".to_owned(), "notset".to_owned()),
+            ("category".to_owned(), "compile".to_owned()),
+            ("identifier".to_owned(), "foo.cpp".to_owned()),
+            ("inputs".to_owned(), "".to_owned()),
+            ("outputs".to_owned(), "".to_owned()),
+        ]
+    );
+    // Only actions running a command have one.
+    assert!(node.command()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_iter_action_inputs() {
+    let keys: Vec<ActionKey> = (0..4)
+        .map(|id| ActionKey::testing_new(deferred_key(id)))
+        .collect();
+    let leaf = set_projection(10, vec![keys[3].dupe()], Vec::new());
+    let left = set_projection(11, vec![keys[2].dupe()], vec![leaf.dupe()]);
+    let root = set_projection(12, vec![keys[1].dupe()], vec![left, leaf]);
+    let inputs = vec![
+        ActionInput::ActionKey(keys[0].dupe()),
+        ActionInput::IndirectInputs(root),
+    ];
+
+    // The leaf set is reachable twice, but its actions are only visited once.
+    assert_eq!(
+        iter_action_inputs(&inputs).cloned().collect::<Vec<_>>(),
+        keys
+    );
+}
@@ -13,6 +13,7 @@ use anyhow::Context;
 use async_trait::async_trait;
 use buck2_common::result::SharedResult;
 use buck2_core::cells::cell_path::CellPath;
+use buck2_core::package::Package;
 use buck2_core::target::ConfiguredTargetLabel;
 use buck2_core::target::TargetLabel;
 use buck2_events::dispatch::console_message;
@@ -29,6 +30,7 @@ use buck2_query::query::traversal::async_depth_limited_traversal;
 use buck2_query::query::traversal::AsyncNodeLookup;
 use buck2_query::query::traversal::AsyncTraversalDelegate;
 use gazebo::dupe::Dupe;
+use indexmap::IndexSet;
 use tracing::warn;
 
 use crate::query::cquery::universe::CqueryUniverse;
@@ -225,6 +227,39 @@ impl<'c> QueryEnvironment for CqueryEnvironment<'c> {
         return rbuildfiles(universe, argset, self.delegate.uquery_delegate()).await;
     }
 
+    async fn siblings(
+        &self,
+        targets: &TargetSet<Self::Target>,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        let packages: IndexSet<&Package> = targets
+            .iter()
+            .map(|target| target.buildfile_path().package())
+            .collect();
+
+        let mut siblings = TargetSet::new();
+        for package in packages {
+            match &self.universe {
+                Some(universe) => siblings.extend(universe.package_targets(package)),
+                // Without a universe (ex. in BXL), configure the targets the same way as literals.
+                None => {
+                    let nodes = self
+                        .delegate
+                        .uquery_delegate()
+                        .eval_build_file(package)
+                        .await?;
+                    for node in nodes.targets().values() {
+                        if let MaybeCompatible::Compatible(node) =
+                            self.delegate.get_node_for_target(node.label()).await?
+                        {
+                            siblings.insert(node);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(siblings)
+    }
+
     async fn owner(&self, paths: &FileSet) -> anyhow::Result<TargetSet<Self::Target>> {
         let mut result = TargetSet::new();
 
@@ -17,6 +17,8 @@ use buck2_core::target::TargetLabel;
 use buck2_events::dispatch::console_message;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::functions::macros::QueryMacros;
+use buck2_query::query::syntax::simple::functions::AugmentedQueryFunctions;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
 use futures::stream::FuturesUnordered;
@@ -24,6 +26,7 @@ use futures::StreamExt;
 use gazebo::prelude::*;
 
 use crate::query::analysis::evaluator::eval_query;
+use crate::query::analysis::evaluator::get_query_macros;
 use crate::query::cquery::environment::CqueryEnvironment;
 use crate::query::cquery::environment::CqueryOwnerBehavior;
 use crate::query::cquery::universe::CqueryUniverse;
@@ -36,6 +39,7 @@ use crate::query::uquery::environment::UqueryDelegate;
 pub struct CqueryEvaluator<'c> {
     dice_query_delegate: Arc<DiceQueryDelegate<'c>>,
     functions: DefaultQueryFunctionsModule<CqueryEnvironment<'c>>,
+    macros: QueryMacros<CqueryEnvironment<'c>>,
     owner_behavior: CqueryOwnerBehavior,
 }
 
@@ -46,7 +50,8 @@ impl CqueryEvaluator<'_> {
         query_args: &[A],
         target_universe: Option<&[U]>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>> {
-        eval_query(&self.functions, query, query_args, async move |literals| {
+        let functions = AugmentedQueryFunctions::augment(&self.functions, box self.macros.dupe());
+        eval_query(&functions, query, query_args, async move |literals| {
             let (universe, resolved_literals) = match target_universe {
                 None => {
                     if literals.is_empty() {
@@ -110,9 +115,11 @@ pub async fn get_cquery_evaluator<'a, 'c: 'a>(
     let dice_query_delegate =
         Arc::new(get_dice_query_delegate(ctx, working_dir, global_target_platform).await?);
     let functions = DefaultQueryFunctionsModule::new();
+    let macros = get_query_macros(ctx).await?;
     Ok(CqueryEvaluator {
         dice_query_delegate,
         functions,
+        macros,
         owner_behavior,
     })
 }
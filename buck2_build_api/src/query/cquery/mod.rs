@@ -9,4 +9,6 @@
 
 pub mod environment;
 pub mod evaluator;
+#[cfg(test)]
+mod tests;
 pub mod universe;
@@ -0,0 +1,196 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use buck2_core::package::testing::PackageExt;
+use buck2_core::package::Package;
+use buck2_core::pattern::ParsedPattern;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::visibility::VisibilityPattern;
+use buck2_node::visibility::VisibilitySpecification;
+use buck2_query::query::environment::QueryEnvironment;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use buck2_query::query::syntax::simple::functions::helpers::CapturedExpr;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctions;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+use buck2_query_parser::parse_expr;
+use gazebo::dupe::Dupe;
+
+use crate::query::cquery::environment::CqueryEnvironment;
+use crate::query::cquery::environment::CqueryOwnerBehavior;
+use crate::query::cquery::universe::CqueryUniverse;
+use crate::query::testing::TestQueryDelegateBuilder;
+use crate::query::uquery::environment::PreresolvedQueryLiterals;
+
+fn set(nodes: &[&ConfiguredTargetNode]) -> TargetSet<ConfiguredTargetNode> {
+    let mut set = TargetSet::new();
+    for node in nodes {
+        set.insert((*node).dupe());
+    }
+    set
+}
+
+/// A cquery environment without a universe, as used by BXL.
+fn cquery_env(delegate: TestQueryDelegateBuilder) -> CqueryEnvironment<'static> {
+    cquery_env_with_universe(delegate, None)
+}
+
+fn cquery_env_with_universe(
+    delegate: TestQueryDelegateBuilder,
+    universe: Option<CqueryUniverse>,
+) -> CqueryEnvironment<'static> {
+    let delegate = Arc::new(delegate.build());
+    CqueryEnvironment::new(
+        delegate,
+        Arc::new(PreresolvedQueryLiterals::new(HashMap::new())),
+        universe,
+        CqueryOwnerBehavior::Correct,
+    )
+}
+
+#[tokio::test]
+async fn test_siblings() -> anyhow::Result<()> {
+    let mut delegate = TestQueryDelegateBuilder::default();
+    let a1 = delegate.target("a", "1", &[], &[], VisibilitySpecification::Public);
+    let a2 = delegate.target("a", "2", &[&a1], &[], VisibilitySpecification::Public);
+    let b1 = delegate.target("b", "1", &[&a1], &[], VisibilitySpecification::Public);
+    delegate.target("c", "1", &[], &[], VisibilitySpecification::Public);
+    let env = cquery_env(delegate);
+
+    assert_eq!(env.siblings(&set(&[&a1])).await?, set(&[&a1, &a2]));
+    assert_eq!(
+        env.siblings(&set(&[&a2, &b1])).await?,
+        set(&[&a1, &a2, &b1])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_siblings_in_universe() -> anyhow::Result<()> {
+    let mut delegate = TestQueryDelegateBuilder::default();
+    let a1 = delegate.target("a", "1", &[], &[], VisibilitySpecification::Public);
+    let a2 = delegate.target("a", "2", &[&a1], &[], VisibilitySpecification::Public);
+    delegate.target("a", "3", &[], &[], VisibilitySpecification::Public);
+    // Only the targets in the universe are siblings.
+    let universe = CqueryUniverse::build(&set(&[&a2])).await?;
+    let env = cquery_env_with_universe(delegate, Some(universe));
+
+    assert_eq!(env.siblings(&set(&[&a1])).await?, set(&[&a1, &a2]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_same_pkg_direct_rdeps() -> anyhow::Result<()> {
+    let mut delegate = TestQueryDelegateBuilder::default();
+    let a1 = delegate.target("a", "1", &[], &[], VisibilitySpecification::Public);
+    let a2 = delegate.target("a", "2", &[&a1], &[], VisibilitySpecification::Public);
+    let a3 = delegate.target("a", "3", &[&a2], &[], VisibilitySpecification::Public);
+    let b1 = delegate.target("b", "1", &[&a1], &[], VisibilitySpecification::Public);
+    let env = cquery_env(delegate);
+    let functions = DefaultQueryFunctions::<CqueryEnvironment>::new();
+
+    // `a:3` only depends on `a:1` indirectly, and `b:1` is in another package.
+    assert_eq!(
+        functions.same_pkg_direct_rdeps(&env, &set(&[&a1])).await?,
+        set(&[&a2])
+    );
+    assert_eq!(
+        functions
+            .same_pkg_direct_rdeps(&env, &set(&[&a1, &a2]))
+            .await?,
+        set(&[&a2, &a3])
+    );
+    assert_eq!(
+        functions.same_pkg_direct_rdeps(&env, &set(&[&b1])).await?,
+        TargetSet::new()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_visible() -> anyhow::Result<()> {
+    let mut delegate = TestQueryDelegateBuilder::default();
+    let public = delegate.target("a", "public", &[], &[], VisibilitySpecification::Public);
+    let private = delegate.target("a", "private", &[], &[], VisibilitySpecification::Default);
+    let to_b = delegate.target(
+        "a",
+        "to_b",
+        &[],
+        &[],
+        VisibilitySpecification::VisibleTo(vec![VisibilityPattern(ParsedPattern::Package(
+            Package::testing_new("root", "b"),
+        ))]),
+    );
+    let a_user = delegate.target("a", "user", &[], &[], VisibilitySpecification::Public);
+    let b_user = delegate.target("b", "user", &[], &[], VisibilitySpecification::Public);
+    let c_user = delegate.target("c", "user", &[], &[], VisibilitySpecification::Public);
+    let functions = DefaultQueryFunctions::<CqueryEnvironment>::new();
+    let all = set(&[&public, &private, &to_b]);
+
+    // Targets are always visible within their package.
+    assert_eq!(functions.visible(&set(&[&a_user]), &all)?, all.clone());
+    assert_eq!(
+        functions.visible(&set(&[&b_user]), &all)?,
+        set(&[&public, &to_b])
+    );
+    assert_eq!(
+        functions.visible(&set(&[&b_user, &c_user]), &all)?,
+        set(&[&public])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rdeps_with_filter() -> anyhow::Result<()> {
+    let mut delegate = TestQueryDelegateBuilder::default();
+    let tool = delegate.target("a", "tool", &[], &[], VisibilitySpecification::Public);
+    let lib = delegate.target("a", "lib", &[&tool], &[], VisibilitySpecification::Public);
+    let gen = delegate.target("a", "gen", &[], &[&tool], VisibilitySpecification::Public);
+    let bin = delegate.target(
+        "a",
+        "bin",
+        &[&lib, &gen],
+        &[],
+        VisibilitySpecification::Public,
+    );
+    let env = cquery_env(delegate);
+    let functions = DefaultQueryFunctions::<CqueryEnvironment>::new();
+    let module = DefaultQueryFunctionsModule::new();
+    let universe = set(&[&bin]);
+
+    assert_eq!(
+        functions
+            .rdeps(&env, &module, &universe, &set(&[&tool]), None, None)
+            .await?,
+        set(&[&tool, &lib, &gen, &bin])
+    );
+    // Exec deps are not followed when only looking at target deps.
+    let expr = parse_expr("target_deps()")?;
+    assert_eq!(
+        functions
+            .rdeps(
+                &env,
+                &module,
+                &universe,
+                &set(&[&tool]),
+                None,
+                Some(&CapturedExpr { expr: &expr }),
+            )
+            .await?,
+        set(&[&tool, &lib, &bin])
+    );
+
+    Ok(())
+}
@@ -129,6 +129,17 @@ impl CqueryUniverse {
             })
     }
 
+    /// All the configurations of all the targets of a package in the universe.
+    pub(crate) fn package_targets<'a>(
+        &'a self,
+        package: &Package,
+    ) -> impl Iterator<Item = &'a ConfiguredTargetNode> + 'a {
+        self.targets
+            .get(package)
+            .into_iter()
+            .flat_map(|package_universe| package_universe.values().flatten().map(|node| &node.0))
+    }
+
     pub(crate) fn owners(&self, path: &CellPath) -> Vec<ConfiguredTargetNode> {
         let mut nodes = Vec::new();
 
@@ -14,6 +14,8 @@ use anyhow::Context;
 use async_trait::async_trait;
 use buck2_common::result::SharedResult;
 use buck2_core::pattern::ParsedPattern;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::ConfiguredTargetLabel;
 use buck2_execute::artifact::fs::ArtifactFs;
 use buck2_node::compatibility::MaybeCompatible;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
@@ -36,6 +38,7 @@ use crate::actions::key::ActionKey;
 use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::TransitiveSetProjectionKey;
 use crate::calculation::Calculation;
+use crate::query::aquery::environment::iter_action_inputs;
 use crate::query::aquery::environment::ActionInput;
 use crate::query::aquery::environment::ActionQueryNode;
 use crate::query::aquery::environment::AqueryDelegate;
@@ -101,7 +104,7 @@ impl DiceAqueryNodesCache {
     }
 }
 
-pub(crate) struct DiceAqueryDelegate<'c> {
+pub struct DiceAqueryDelegate<'c> {
     base_delegate: DiceQueryDelegate<'c>,
     nodes_cache: DiceAqueryNodesCache,
     artifact_fs: Arc<ArtifactFs>,
@@ -191,7 +194,7 @@ fn compute_action_node(
     async move {
         let action = ctx.get_action(&key).await?;
         let deps = convert_inputs(&ctx, node_cache, action.inputs()?.iter()).await?;
-        Ok(ActionQueryNode::new(action, deps, fs))
+        Ok(ActionQueryNode::new(action, deps, fs)?)
     }
     .boxed()
 }
@@ -212,7 +215,7 @@ async fn get_action_node(
 }
 
 impl<'c> DiceAqueryDelegate<'c> {
-    pub(crate) async fn new<'a>(
+    pub async fn new<'a>(
         base_delegate: DiceQueryDelegate<'a>,
     ) -> anyhow::Result<DiceAqueryDelegate<'a>> {
         let artifact_fs = Arc::new(base_delegate.ctx().get_artifact_fs().await?);
@@ -232,6 +235,62 @@ impl<'c> DiceAqueryDelegate<'c> {
         )
         .await
     }
+
+    /// The actions registered by the analysis of `target`. Incompatible targets have none.
+    pub async fn get_target_actions(
+        &self,
+        target: &ConfiguredTargetLabel,
+    ) -> anyhow::Result<Vec<ActionQueryNode>> {
+        // Not imported, as its `get_providers` would be ambiguous with `Calculation`'s.
+        match crate::analysis::calculation::RuleAnalysisCalculation::get_analysis_result(
+            self.base_delegate.ctx(),
+            target,
+        )
+        .await?
+        {
+            MaybeCompatible::Incompatible(_) => Ok(Vec::new()),
+            MaybeCompatible::Compatible(result) => {
+                let mut nodes = Vec::new();
+                for action in result.iter_actions() {
+                    nodes.push(self.get_action_node(action.key()).await?);
+                }
+                Ok(nodes)
+            }
+        }
+    }
+
+    /// The actions producing the outputs in the `DefaultInfo` of `target`: its default outputs,
+    /// the artifacts associated with them, and its other outputs.
+    pub async fn get_target_output_actions(
+        &self,
+        target: &ConfiguredProvidersLabel,
+    ) -> anyhow::Result<Vec<ActionQueryNode>> {
+        let providers = match self.base_delegate.ctx().get_providers(target).await? {
+            MaybeCompatible::Incompatible(_) => return Ok(Vec::new()),
+            MaybeCompatible::Compatible(providers) => providers,
+        };
+
+        let mut outputs = Vec::new();
+        providers
+            .provider_collection()
+            .default_info()
+            .for_each_output(&mut |output| {
+                outputs.push(output);
+                Ok(())
+            })?;
+
+        let inputs = convert_inputs(
+            self.base_delegate.ctx(),
+            self.nodes_cache.dupe(),
+            outputs.iter(),
+        )
+        .await?;
+        let mut nodes = Vec::new();
+        for key in iter_action_inputs(&inputs).unique() {
+            nodes.push(self.get_action_node(key).await?);
+        }
+        Ok(nodes)
+    }
 }
 
 #[async_trait]
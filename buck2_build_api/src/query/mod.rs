@@ -11,4 +11,5 @@ pub mod analysis;
 pub mod aquery;
 pub mod cquery;
 pub mod dice;
+pub mod testing;
 pub mod uquery;
@@ -0,0 +1,357 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Query delegates over a fixed set of targets and actions, to test query environments without
+//! DICE.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_common::executor_config::CommandExecutorConfig;
+use buck2_common::pattern::resolve::ResolvedPattern;
+use buck2_common::result::SharedResult;
+use buck2_core::build_file_path::BuildFilePath;
+use buck2_core::bzl::ImportPath;
+use buck2_core::category::Category;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::cell_root_path::CellRootPathBuf;
+use buck2_core::cells::testing::CellResolverExt;
+use buck2_core::cells::CellName;
+use buck2_core::cells::CellResolver;
+use buck2_core::collections::ordered_map::OrderedMap;
+use buck2_core::collections::unordered_map::UnorderedMap;
+use buck2_core::configuration::Configuration;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project::ProjectRelativePathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::package::testing::PackageExt;
+use buck2_core::package::Package;
+use buck2_core::target::ConfiguredTargetLabel;
+use buck2_core::target::TargetLabel;
+use buck2_core::target::TargetName;
+use buck2_execute::artifact::fs::ArtifactFs;
+use buck2_execute::path::buck_out_path::BuckOutPathResolver;
+use buck2_execute::path::buck_out_path::BuckPathResolver;
+use buck2_node::compatibility::MaybeCompatible;
+use buck2_node::configuration::execution::ExecutionPlatformResolution;
+use buck2_node::configuration::resolved::ResolvedConfiguration;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::eval_result::EvaluationResult;
+use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_node::nodes::unconfigured::TargetsMap;
+use buck2_node::rule_type::RuleType;
+use buck2_node::rule_type::StarlarkRuleType;
+use buck2_node::visibility::VisibilitySpecification;
+use buck2_node::visibility::WithinViewSpecification;
+use buck2_query::query::environment::LabeledNode;
+use buck2_query::query::syntax::simple::eval::file_set::FileSet;
+use gazebo::dupe::Dupe;
+use gazebo::prelude::IterDuped;
+use indexmap::indexset;
+use indexmap::IndexSet;
+
+use crate::actions::artifact::build_artifact::BuildArtifact;
+use crate::actions::artifact::testing::BuildArtifactTestingExt;
+use crate::actions::execute::action_executor::ActionExecutionMetadata;
+use crate::actions::execute::action_executor::ActionOutputs;
+use crate::actions::key::ActionKey;
+use crate::actions::Action;
+use crate::actions::ActionExecutable;
+use crate::actions::ActionExecutionCtx;
+use crate::actions::PristineActionExecutable;
+use crate::actions::RegisteredAction;
+use crate::artifact_groups::ArtifactGroup;
+use crate::deferred::types::testing::DeferredIdExt;
+use crate::deferred::types::DeferredId;
+use crate::query::aquery::environment::ActionInput;
+use crate::query::aquery::environment::ActionQueryNode;
+use crate::query::aquery::environment::AqueryDelegate;
+use crate::query::cquery::environment::CqueryDelegate;
+use crate::query::uquery::environment::UqueryDelegate;
+
+/// Builds a [`TestQueryDelegate`]. Targets are all in the `root` cell and configured in the
+/// testing configuration.
+#[derive(Default)]
+pub struct TestQueryDelegateBuilder {
+    nodes: Vec<(TargetNode, ConfiguredTargetNode)>,
+    actions: Vec<ActionQueryNode>,
+}
+
+impl TestQueryDelegateBuilder {
+    /// Adds the target `root//<package>:<name>` and returns its configured node, to be used as a
+    /// dependency of targets added later.
+    pub fn target(
+        &mut self,
+        package: &str,
+        name: &str,
+        deps: &[&ConfiguredTargetNode],
+        exec_deps: &[&ConfiguredTargetNode],
+        visibility: VisibilitySpecification,
+    ) -> ConfiguredTargetNode {
+        let label = TargetLabel::new(
+            Package::testing_new("root", package),
+            TargetName::unchecked_new(name),
+        );
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::unchecked_new("root", "rules", "rules.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let node = TargetNode::testing_new_with_visibility(
+            label.dupe(),
+            rule_type,
+            Vec::new(),
+            visibility,
+            WithinViewSpecification::Public,
+        );
+        let configured_label = label.configure(Configuration::testing_new());
+        let configured = ConfiguredTargetNode::new(
+            configured_label.dupe(),
+            node.dupe(),
+            ResolvedConfiguration::new(configured_label.cfg().dupe(), UnorderedMap::new()),
+            OrderedMap::new(),
+            ExecutionPlatformResolution::unspecified(),
+            deps.iter().copied().duped().collect(),
+            exec_deps.iter().copied().duped().collect(),
+            OrderedMap::new(),
+        );
+        self.nodes.push((node, configured.dupe()));
+        configured
+    }
+
+    /// Adds an action registered by `target`, taking the outputs of `deps` as inputs, and returns
+    /// its node. Its only output is named after its `identifier`.
+    pub fn action(
+        &mut self,
+        target: &ConfiguredTargetNode,
+        category: &str,
+        identifier: &str,
+        deps: &[&ActionQueryNode],
+    ) -> ActionQueryNode {
+        let output = BuildArtifact::testing_new(
+            target.name().dupe(),
+            ForwardRelativePathBuf::unchecked_new(identifier.to_owned()),
+            DeferredId::testing_new(self.actions.len().try_into().unwrap()),
+        );
+        let action = RegisteredAction::new(
+            output.key().dupe(),
+            box TestAction {
+                outputs: indexset![output],
+                category: Category::try_from(category).unwrap(),
+                identifier: identifier.to_owned(),
+            },
+            CommandExecutorConfig::testing_local(),
+        );
+        let node = ActionQueryNode::new(
+            Arc::new(action),
+            deps.iter()
+                .map(|dep| ActionInput::ActionKey(dep.node_ref().dupe()))
+                .collect(),
+            Arc::new(artifact_fs()),
+        )
+        .unwrap();
+        self.actions.push(node.dupe());
+        node
+    }
+
+    pub fn build(self) -> TestQueryDelegate {
+        let mut packages: HashMap<Package, TargetsMap> = HashMap::new();
+        let mut configured = HashMap::new();
+        for (node, configured_node) in self.nodes {
+            configured.insert(node.label().dupe(), configured_node);
+            packages
+                .entry(node.label().pkg().dupe())
+                .or_insert_with(TargetsMap::new)
+                .insert(node.label().name().dupe(), node);
+        }
+        let packages = packages
+            .into_iter()
+            .map(|(package, targets)| {
+                let buildfile_path = Arc::new(BuildFilePath::new(
+                    package.dupe(),
+                    FileNameBuf::unchecked_new("BUCK"),
+                ));
+                (
+                    package,
+                    Arc::new(EvaluationResult::new(buildfile_path, Vec::new(), targets)),
+                )
+            })
+            .collect();
+        let actions = self
+            .actions
+            .into_iter()
+            .map(|action| (action.node_ref().dupe(), action))
+            .collect();
+        TestQueryDelegate {
+            packages,
+            configured,
+            actions,
+        }
+    }
+}
+
+fn artifact_fs() -> ArtifactFs {
+    ArtifactFs::new(
+        BuckPathResolver::new(CellResolver::of_names_and_paths(&[(
+            CellName::unchecked_new("root".to_owned()),
+            CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("".to_owned())),
+        )])),
+        BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new(
+            "buck-out/v2".to_owned(),
+        )),
+        ProjectRoot::new(AbsNormPathBuf::try_from(std::env::current_dir().unwrap()).unwrap()),
+    )
+}
+
+/// An action that is only queried, never executed.
+#[derive(Debug, Allocative)]
+struct TestAction {
+    outputs: IndexSet<BuildArtifact>,
+    category: Category,
+    identifier: String,
+}
+
+#[async_trait]
+impl Action for TestAction {
+    fn kind(&self) -> buck2_data::ActionKind {
+        buck2_data::ActionKind::NotSet
+    }
+
+    fn inputs(&self) -> anyhow::Result<Cow<'_, IndexSet<ArtifactGroup>>> {
+        Ok(Cow::Owned(IndexSet::new()))
+    }
+
+    fn outputs(&self) -> anyhow::Result<Cow<'_, IndexSet<BuildArtifact>>> {
+        Ok(Cow::Borrowed(&self.outputs))
+    }
+
+    fn as_executable(&self) -> ActionExecutable<'_> {
+        ActionExecutable::Pristine(self)
+    }
+
+    fn category(&self) -> &Category {
+        &self.category
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(&self.identifier)
+    }
+}
+
+#[async_trait]
+impl PristineActionExecutable for TestAction {
+    async fn execute(
+        &self,
+        _ctx: &mut dyn ActionExecutionCtx,
+    ) -> anyhow::Result<(ActionOutputs, ActionExecutionMetadata)> {
+        unimplemented!()
+    }
+}
+
+/// Serves the build files and configured nodes of the targets it was built with, and the nodes of
+/// its actions. Only what queries over targets and actions need is implemented, file queries
+/// panic.
+pub struct TestQueryDelegate {
+    packages: HashMap<Package, Arc<EvaluationResult>>,
+    configured: HashMap<TargetLabel, ConfiguredTargetNode>,
+    actions: HashMap<ActionKey, ActionQueryNode>,
+}
+
+impl TestQueryDelegate {
+    fn configured_node(&self, target: &TargetLabel) -> anyhow::Result<&ConfiguredTargetNode> {
+        self.configured
+            .get(target)
+            .with_context(|| format!("unknown target `{}`", target))
+    }
+}
+
+#[async_trait]
+impl UqueryDelegate for TestQueryDelegate {
+    async fn eval_build_file(&self, package: &Package) -> SharedResult<Arc<EvaluationResult>> {
+        Ok(self
+            .packages
+            .get(package)
+            .with_context(|| format!("unknown package `{}`", package))?
+            .dupe())
+    }
+
+    async fn eval_module_imports(&self, _path: &ImportPath) -> SharedResult<Vec<ImportPath>> {
+        unimplemented!()
+    }
+
+    fn get_buildfile_names_by_cell(&self) -> anyhow::Result<HashMap<CellName, &[FileNameBuf]>> {
+        unimplemented!()
+    }
+
+    async fn resolve_target_patterns(
+        &self,
+        _pattern: &[&str],
+    ) -> anyhow::Result<ResolvedPattern<TargetName>> {
+        unimplemented!()
+    }
+
+    async fn eval_file_literal(&self, _literal: &str) -> anyhow::Result<FileSet> {
+        unimplemented!()
+    }
+
+    async fn get_enclosing_packages(&self, _path: &CellPath) -> anyhow::Result<Vec<Package>> {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl CqueryDelegate for TestQueryDelegate {
+    fn uquery_delegate(&self) -> &dyn UqueryDelegate {
+        self
+    }
+
+    async fn get_node_for_target(
+        &self,
+        target: &TargetLabel,
+    ) -> SharedResult<MaybeCompatible<ConfiguredTargetNode>> {
+        Ok(MaybeCompatible::Compatible(
+            self.configured_node(target)?.dupe(),
+        ))
+    }
+
+    async fn get_node_for_configured_target(
+        &self,
+        target: &ConfiguredTargetLabel,
+    ) -> SharedResult<ConfiguredTargetNode> {
+        Ok(self.configured_node(target.unconfigured())?.dupe())
+    }
+
+    async fn get_configured_target(
+        &self,
+        target: &TargetLabel,
+    ) -> SharedResult<ConfiguredTargetLabel> {
+        Ok(self.configured_node(target)?.name().dupe())
+    }
+}
+
+#[async_trait]
+impl AqueryDelegate for TestQueryDelegate {
+    fn cquery_delegate(&self) -> &dyn CqueryDelegate {
+        self
+    }
+
+    async fn get_node(&self, key: &ActionKey) -> anyhow::Result<ActionQueryNode> {
+        Ok(self
+            .actions
+            .get(key)
+            .with_context(|| format!("unknown action `{}`", key))?
+            .dupe())
+    }
+}
@@ -233,6 +233,28 @@ impl<'c> QueryEnvironment for UqueryEnvironment<'c> {
         return rbuildfiles(universe, argset, &*self.delegate).await;
     }
 
+    async fn siblings(
+        &self,
+        targets: &TargetSet<Self::Target>,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        let packages: IndexSet<&Package> = targets
+            .iter()
+            .map(|target| target.buildfile_path().package())
+            .collect();
+        let results = futures::future::join_all(
+            packages
+                .into_iter()
+                .map(|package| self.delegate.eval_build_file(package)),
+        )
+        .await;
+
+        let mut siblings = TargetSet::new();
+        for result in results {
+            siblings.extend(result?.targets().values());
+        }
+        Ok(siblings)
+    }
+
     async fn owner(&self, paths: &FileSet) -> anyhow::Result<TargetSet<Self::Target>> {
         let mut result: TargetSet<Self::Target> = TargetSet::new();
         for path in paths.iter() {
@@ -14,11 +14,14 @@ use buck2_core::fs::project::ProjectRelativePath;
 use buck2_core::target::TargetLabel;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::functions::macros::QueryMacros;
+use buck2_query::query::syntax::simple::functions::AugmentedQueryFunctions;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
 use gazebo::prelude::*;
 
 use crate::query::analysis::evaluator::eval_query;
+use crate::query::analysis::evaluator::get_query_macros;
 use crate::query::dice::get_dice_query_delegate;
 use crate::query::dice::DiceQueryDelegate;
 use crate::query::uquery::environment::PreresolvedQueryLiterals;
@@ -27,6 +30,7 @@ use crate::query::uquery::environment::UqueryEnvironment;
 pub struct UqueryEvaluator<'c> {
     dice_query_delegate: Arc<DiceQueryDelegate<'c>>,
     functions: DefaultQueryFunctionsModule<UqueryEnvironment<'c>>,
+    macros: QueryMacros<UqueryEnvironment<'c>>,
 }
 
 impl UqueryEvaluator<'_> {
@@ -35,7 +39,8 @@ impl UqueryEvaluator<'_> {
         query: &str,
         query_args: &[String],
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>> {
-        eval_query(&self.functions, query, query_args, async move |literals| {
+        let functions = AugmentedQueryFunctions::augment(&self.functions, box self.macros.dupe());
+        eval_query(&functions, query, query_args, async move |literals| {
             let resolved_literals =
                 PreresolvedQueryLiterals::pre_resolve(&*self.dice_query_delegate, &literals).await;
             Ok(UqueryEnvironment::new(
@@ -57,9 +62,11 @@ pub async fn get_uquery_evaluator<'a, 'c: 'a>(
     let dice_query_delegate =
         Arc::new(get_dice_query_delegate(ctx, working_dir, global_target_platform).await?);
     let functions = DefaultQueryFunctionsModule::new();
+    let macros = get_query_macros(ctx).await?;
 
     Ok(UqueryEvaluator {
         dice_query_delegate,
         functions,
+        macros,
     })
 }
@@ -9,3 +9,5 @@
 
 pub mod environment;
 pub mod evaluator;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use buck2_core::package::testing::PackageExt;
+use buck2_core::package::Package;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::TargetLabel;
+use buck2_core::target::TargetName;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_node::visibility::VisibilityPattern;
+use buck2_node::visibility::VisibilitySpecification;
+use buck2_query::query::environment::QueryEnvironment;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctions;
+
+use crate::query::testing::TestQueryDelegateBuilder;
+use crate::query::uquery::environment::PreresolvedQueryLiterals;
+use crate::query::uquery::environment::UqueryEnvironment;
+
+/// Looks up `package:name` targets in the `root` cell.
+async fn targets(
+    env: &UqueryEnvironment<'_>,
+    labels: &[&str],
+) -> anyhow::Result<TargetSet<TargetNode>> {
+    let mut targets = TargetSet::new();
+    for label in labels {
+        let (package, name) = label.split_once(':').unwrap();
+        let label = TargetLabel::new(
+            Package::testing_new("root", package),
+            TargetName::unchecked_new(name),
+        );
+        targets.insert(QueryEnvironment::get_node(env, &label).await?);
+    }
+    Ok(targets)
+}
+
+fn uquery_env(delegate: TestQueryDelegateBuilder) -> UqueryEnvironment<'static> {
+    UqueryEnvironment::new(
+        Arc::new(delegate.build()),
+        Arc::new(PreresolvedQueryLiterals::new(HashMap::new())),
+    )
+}
+
+#[tokio::test]
+async fn test_siblings() -> anyhow::Result<()> {
+    let mut delegate = TestQueryDelegateBuilder::default();
+    let a1 = delegate.target("a", "1", &[], &[], VisibilitySpecification::Public);
+    delegate.target("a", "2", &[&a1], &[], VisibilitySpecification::Public);
+    delegate.target("b", "1", &[&a1], &[], VisibilitySpecification::Public);
+    delegate.target("c", "1", &[], &[], VisibilitySpecification::Public);
+    let env = uquery_env(delegate);
+
+    assert_eq!(
+        env.siblings(&targets(&env, &["a:1"]).await?).await?,
+        targets(&env, &["a:1", "a:2"]).await?
+    );
+    assert_eq!(
+        env.siblings(&targets(&env, &["a:2", "b:1"]).await?).await?,
+        targets(&env, &["a:1", "a:2", "b:1"]).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_visible() -> anyhow::Result<()> {
+    let mut delegate = TestQueryDelegateBuilder::default();
+    delegate.target("a", "public", &[], &[], VisibilitySpecification::Public);
+    delegate.target("a", "private", &[], &[], VisibilitySpecification::Default);
+    delegate.target(
+        "a",
+        "to_b",
+        &[],
+        &[],
+        VisibilitySpecification::VisibleTo(vec![VisibilityPattern(ParsedPattern::Package(
+            Package::testing_new("root", "b"),
+        ))]),
+    );
+    delegate.target("a", "user", &[], &[], VisibilitySpecification::Public);
+    delegate.target("b", "user", &[], &[], VisibilitySpecification::Public);
+    delegate.target("c", "user", &[], &[], VisibilitySpecification::Public);
+    let env = uquery_env(delegate);
+    let functions = DefaultQueryFunctions::<UqueryEnvironment>::new();
+    let all = targets(&env, &["a:public", "a:private", "a:to_b"]).await?;
+
+    // Targets are always visible within their package.
+    assert_eq!(
+        functions.visible(&targets(&env, &["a:user"]).await?, &all)?,
+        all.clone()
+    );
+    assert_eq!(
+        functions.visible(&targets(&env, &["b:user"]).await?, &all)?,
+        targets(&env, &["a:public", "a:to_b"]).await?
+    );
+    assert_eq!(
+        functions.visible(&targets(&env, &["b:user", "c:user"]).await?, &all)?,
+        targets(&env, &["a:public"]).await?
+    );
+
+    Ok(())
+}
@@ -71,6 +71,7 @@ mod tests {
                 name: "foo".to_owned(),
             },
             Arc::new(OrderedMap::new()),
+            Arc::new(Vec::new()),
         );
 
         let mut deferred =
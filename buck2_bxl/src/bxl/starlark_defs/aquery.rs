@@ -0,0 +1,416 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_build_api::query::aquery::environment::ActionQueryNode;
+use buck2_build_api::query::aquery::environment::AqueryEnvironment;
+use buck2_build_api::query::aquery::evaluator::get_aquery_evaluator;
+use buck2_build_api::query::cquery::environment::CqueryEnvironment;
+use buck2_build_api::query::dice::aquery::DiceAqueryDelegate;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::provider::label::ProvidersName;
+use buck2_core::target::TargetLabel;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
+use buck2_query::query::syntax::simple::functions::helpers::CapturedExpr;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctions;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+use derivative::Derivative;
+use derive_more::Display;
+use gazebo::any::ProvidesStaticType;
+use gazebo::prelude::*;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
+use starlark::eval::Evaluator;
+use starlark::starlark_module;
+use starlark::starlark_type;
+use starlark::values::dict::Dict;
+use starlark::values::none::NoneOr;
+use starlark::values::type_repr::StarlarkTypeRepr;
+use starlark::values::AllocValue;
+use starlark::values::Heap;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::Trace;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+use starlark::values::ValueLike;
+use starlark::StarlarkDocs;
+
+use crate::bxl::starlark_defs::context::BxlContext;
+use crate::bxl::starlark_defs::cquery::get_cquery_env;
+use crate::bxl::starlark_defs::file_set::StarlarkFileSet;
+use crate::bxl::starlark_defs::target_expr::TargetExpr;
+use crate::bxl::starlark_defs::targetset::StarlarkTargetSet;
+use crate::bxl::value_as_starlark_target_label::ValueAsStarlarkTargetLabel;
+
+/// The context for performing `aquery` operations in bxl. The functions offered on this ctx are
+/// the same behaviour as the query functions available within aquery command.
+///
+/// Where a function takes actions, they can be given as action nodes, a target set of them, or
+/// target literals, which stand for the actions producing the targets' default outputs.
+#[derive(
+    ProvidesStaticType,
+    Derivative,
+    Display,
+    Trace,
+    NoSerialize,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs_attrs(directory = "bxl")]
+#[derivative(Debug)]
+#[display(fmt = "{:?}", self)]
+#[allocative(skip)]
+pub struct StarlarkAQueryCtx<'v> {
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    ctx: &'v BxlContext<'v>,
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    functions: DefaultQueryFunctions<AqueryEnvironment<'v>>,
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    env: AqueryEnvironment<'v>,
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    delegate: Arc<DiceAqueryDelegate<'v>>,
+    /// Used to resolve the targets given to `all_actions` and `all_outputs`.
+    #[trace(unsafe_ignore)]
+    #[derivative(Debug = "ignore")]
+    cquery_env: CqueryEnvironment<'v>,
+    #[derivative(Debug = "ignore")]
+    target_platform: Option<TargetLabel>,
+}
+
+impl<'v> StarlarkValue<'v> for StarlarkAQueryCtx<'v> {
+    starlark_type!("aqueryctx");
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(register_aquery)
+    }
+}
+
+impl<'v> AllocValue<'v> for StarlarkAQueryCtx<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex_no_freeze(self)
+    }
+}
+
+impl<'v> StarlarkTypeRepr for &'v StarlarkAQueryCtx<'v> {
+    fn starlark_type_repr() -> String {
+        StarlarkAQueryCtx::get_type_starlark_repr()
+    }
+}
+
+impl<'v> UnpackValue<'v> for &'v StarlarkAQueryCtx<'v> {
+    fn unpack_value(x: Value<'v>) -> Option<&'v StarlarkAQueryCtx<'v>> {
+        x.downcast_ref()
+    }
+}
+
+impl<'v> StarlarkAQueryCtx<'v> {
+    pub async fn new(
+        ctx: &'v BxlContext<'v>,
+        global_target_platform: Value<'v>,
+    ) -> anyhow::Result<StarlarkAQueryCtx<'v>> {
+        let target_platform =
+            global_target_platform.parse_target_platforms(&ctx.target_alias_resolver, &ctx.cell)?;
+
+        let base_delegate =
+            BxlContext::dice_query_delegate(ctx.async_ctx.0, target_platform.dupe()).await?;
+        let delegate = Arc::new(DiceAqueryDelegate::new(base_delegate).await?);
+        let env = AqueryEnvironment::new(delegate.dupe(), delegate.dupe());
+        let cquery_env = get_cquery_env(ctx.async_ctx.0, target_platform.dupe()).await?;
+        Ok(Self {
+            ctx,
+            functions: DefaultQueryFunctions::new(),
+            env,
+            delegate,
+            cquery_env,
+            target_platform,
+        })
+    }
+
+    async fn configured_targets(
+        &self,
+        targets: Value<'v>,
+        eval: &Evaluator<'v, '_>,
+    ) -> anyhow::Result<TargetSet<ConfiguredTargetNode>> {
+        Ok(TargetExpr::<'v, ConfiguredTargetNode>::unpack(
+            targets,
+            &self.target_platform,
+            self.ctx,
+            eval,
+        )
+        .await?
+        .get(&self.cquery_env)
+        .await?
+        .into_owned())
+    }
+}
+
+fn alloc_query_value<'v>(
+    value: QueryEvaluationValue<ActionQueryNode>,
+    heap: &'v Heap,
+) -> Value<'v> {
+    match value {
+        QueryEvaluationValue::TargetSet(targets) => heap.alloc(StarlarkTargetSet::from(targets)),
+        QueryEvaluationValue::FileSet(files) => heap.alloc(StarlarkFileSet::from(files)),
+    }
+}
+
+#[starlark_module]
+fn register_aquery(builder: &mut MethodsBuilder) {
+    /// The `deps` query, over the graph of actions and the actions they take inputs from.
+    fn deps<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        universe: Value<'v>,
+        #[starlark(default = NoneOr::None)] depth: NoneOr<i32>,
+        #[starlark(default = NoneOr::None)] filter: NoneOr<&'v str>,
+    ) -> anyhow::Result<StarlarkTargetSet<ActionQueryNode>> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                let filter = filter
+                    .into_option()
+                    .try_map(|v| buck2_query_parser::parse_expr(v))?;
+
+                this.functions
+                    .deps(
+                        &this.env,
+                        &DefaultQueryFunctionsModule::new(),
+                        &*TargetExpr::<'v, ActionQueryNode>::unpack(universe, &this.env)
+                            .await?
+                            .get(&this.env)
+                            .await?,
+                        depth.into_option(),
+                        filter
+                            .as_ref()
+                            .map(|span| CapturedExpr { expr: span })
+                            .as_ref(),
+                    )
+                    .await
+            })
+            .map(StarlarkTargetSet::from)
+    }
+
+    /// All the actions registered by the analysis of the given configured targets, whether or not
+    /// anything depends on their outputs. Actions registered by dynamic outputs aren't included.
+    fn all_actions<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        targets: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkTargetSet<ActionQueryNode>> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                let mut actions = TargetSet::new();
+                for target in this.configured_targets(targets, eval).await?.iter() {
+                    actions.extend(this.delegate.get_target_actions(target.name()).await?);
+                }
+                Ok(actions)
+            })
+            .map(StarlarkTargetSet::from)
+    }
+
+    /// The actions producing all the outputs in the `DefaultInfo` of the given configured targets:
+    /// not just their default outputs, but the artifacts associated with them and the other outputs.
+    fn all_outputs<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        targets: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkTargetSet<ActionQueryNode>> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                let mut actions = TargetSet::new();
+                for target in this.configured_targets(targets, eval).await?.iter() {
+                    let label =
+                        ConfiguredProvidersLabel::new(target.name().dupe(), ProvidersName::Default);
+                    actions.extend(this.delegate.get_target_output_actions(&label).await?);
+                }
+                Ok(actions)
+            })
+            .map(StarlarkTargetSet::from)
+    }
+
+    /// The actions with an attribute `attr` equal to `value`. The attributes are those shown by
+    /// `buck2 aquery`, along with `kind`, `category` and `identifier`.
+    fn attrfilter<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        attr: &str,
+        value: &str,
+        targets: Value<'v>,
+    ) -> anyhow::Result<StarlarkTargetSet<ActionQueryNode>> {
+        this.ctx.async_ctx.via(|| async {
+            this.functions
+                .attrfilter(
+                    attr,
+                    value,
+                    &*TargetExpr::<'v, ActionQueryNode>::unpack(targets, &this.env)
+                        .await?
+                        .get(&this.env)
+                        .await?,
+                )
+                .map(StarlarkTargetSet::from)
+        })
+    }
+
+    /// The source files the given actions read directly.
+    fn inputs<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        targets: Value<'v>,
+    ) -> anyhow::Result<StarlarkFileSet> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                this.functions.inputs(
+                    &*TargetExpr::<'v, ActionQueryNode>::unpack(targets, &this.env)
+                        .await?
+                        .get(&this.env)
+                        .await?,
+                )
+            })
+            .map(StarlarkFileSet::from)
+    }
+
+    /// Evaluates some general query string
+    fn eval<'v>(
+        this: &StarlarkAQueryCtx<'v>,
+        query: &'v str,
+        #[starlark(default = Vec::new())] query_args: Vec<String>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Value<'v>> {
+        this.ctx.async_ctx.via_dice(|ctx| async {
+            let evaluator = get_aquery_evaluator(
+                ctx,
+                ctx.get_cell_resolver()
+                    .await?
+                    .get(this.ctx.current_bxl.label().bxl_path.cell())?
+                    .path(),
+                this.target_platform.dupe(),
+            )
+            .await?;
+            Ok(match evaluator.eval_query(query, &query_args).await? {
+                QueryEvaluationResult::Single(result) => alloc_query_value(result, eval.heap()),
+                QueryEvaluationResult::Multiple(multi) => eval.heap().alloc(Dict::new(
+                    multi
+                        .0
+                        .into_iter()
+                        .map(|(q, res)| {
+                            Ok((
+                                eval.heap().alloc(q).get_hashed()?,
+                                alloc_query_value(res?, eval.heap()),
+                            ))
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                )),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use buck2_build_api::query::testing::TestQueryDelegateBuilder;
+    use buck2_build_api::query::uquery::environment::PreresolvedQueryLiterals;
+    use buck2_node::visibility::VisibilitySpecification;
+
+    use super::*;
+
+    fn set(nodes: &[&ActionQueryNode]) -> TargetSet<ActionQueryNode> {
+        let mut set = TargetSet::new();
+        for node in nodes {
+            set.insert((*node).dupe());
+        }
+        set
+    }
+
+    fn env(delegate: TestQueryDelegateBuilder) -> AqueryEnvironment<'static> {
+        let delegate = Arc::new(delegate.build());
+        AqueryEnvironment::new(
+            delegate,
+            Arc::new(PreresolvedQueryLiterals::new(HashMap::new())),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_deps() -> anyhow::Result<()> {
+        let mut delegate = TestQueryDelegateBuilder::default();
+        let target = delegate.target("a", "bin", &[], &[], VisibilitySpecification::Public);
+        let compile = delegate.action(&target, "compile", "bin.o", &[]);
+        let link = delegate.action(&target, "link", "bin", &[&compile]);
+        let strip = delegate.action(&target, "strip", "bin.stripped", &[&link]);
+        let env = env(delegate);
+        let functions = DefaultQueryFunctions::new();
+        let module = DefaultQueryFunctionsModule::new();
+
+        assert_eq!(
+            functions
+                .deps(&env, &module, &set(&[&strip]), None, None)
+                .await?,
+            set(&[&compile, &link, &strip])
+        );
+        assert_eq!(
+            functions
+                .deps(&env, &module, &set(&[&strip]), Some(1), None)
+                .await?,
+            set(&[&link, &strip])
+        );
+        // Parsed the same way as the `filter` argument of `deps`. Actions have no exec deps.
+        let filter = buck2_query_parser::parse_expr("exec_deps()")?;
+        assert_eq!(
+            functions
+                .deps(
+                    &env,
+                    &module,
+                    &set(&[&strip]),
+                    None,
+                    Some(&CapturedExpr { expr: &filter }),
+                )
+                .await?,
+            set(&[&strip])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_attrfilter() -> anyhow::Result<()> {
+        let mut delegate = TestQueryDelegateBuilder::default();
+        let target = delegate.target("a", "bin", &[], &[], VisibilitySpecification::Public);
+        let compile_main = delegate.action(&target, "compile", "main.o", &[]);
+        let compile_lib = delegate.action(&target, "compile", "lib.o", &[]);
+        let link = delegate.action(&target, "link", "bin", &[&compile_main, &compile_lib]);
+        let functions = DefaultQueryFunctions::<AqueryEnvironment>::new();
+        let all = set(&[&compile_main, &compile_lib, &link]);
+
+        assert_eq!(
+            functions.attrfilter("category", "compile", &all)?,
+            set(&[&compile_main, &compile_lib])
+        );
+        assert_eq!(
+            functions.attrfilter("identifier", "bin", &all)?,
+            set(&[&link])
+        );
+        assert_eq!(functions.attrfilter("kind", "notset", &all)?, all.clone());
+
+        Ok(())
+    }
+}
@@ -66,6 +66,7 @@ use starlark::values::ValueTyped;
 use starlark::StarlarkDocs;
 
 use crate::bxl::starlark_defs::alloc_node::AllocNode;
+use crate::bxl::starlark_defs::aquery::StarlarkAQueryCtx;
 use crate::bxl::starlark_defs::context::actions::BxlActionsCtx;
 use crate::bxl::starlark_defs::context::fs::BxlFilesystem;
 use crate::bxl::starlark_defs::context::output::OutputStream;
@@ -376,6 +377,19 @@ fn register_context(builder: &mut MethodsBuilder) {
             .via(|| StarlarkCQueryCtx::new(this, target_platform))
     }
 
+    /// Returns the [`StarlarkAQueryCtx`] that holds all the aquery functions.
+    /// This function takes an optional parameter `target_platform`, which is the target platform
+    /// configuration used to configured any unconfigured target nodes.
+    ///
+    /// The `target_platform` is a target label, or a string that is a target label.
+    fn aquery<'v>(
+        this: &'v BxlContext<'v>,
+        #[starlark(default = NoneType)] target_platform: Value<'v>,
+    ) -> anyhow::Result<StarlarkAQueryCtx<'v>> {
+        this.async_ctx
+            .via(|| StarlarkAQueryCtx::new(this, target_platform))
+    }
+
     /// Returns the action context [`BxlActionsCtx`] for creating and running actions.
     #[starlark(attribute)]
     fn bxl_actions<'v>(this: ValueOf<'v, &'v BxlContext<'v>>) -> anyhow::Result<BxlActionsCtx<'v>> {
@@ -10,20 +10,36 @@
 //! Provides some basic tracked filesystem access for bxl functions so that they can meaningfully
 //! detect simple properties of artifacts, and source directories.
 
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+
 use allocative::Allocative;
+use anyhow::Context;
+use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::file_ops::HasFileOps;
 use buck2_common::file_ops::FileOps;
+use buck2_common::file_ops::RawPathMetadata;
+use buck2_common::find_buildfile::find_buildfile;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRelativePath;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_execute::artifact::fs::ArtifactFs;
+use buck2_interpreter::globspec::GlobSpec;
+use buck2_query::query::syntax::simple::eval::file_set::FileNode;
+use buck2_query::query::syntax::simple::eval::file_set::FileSet;
 use derivative::Derivative;
 use derive_more::Display;
+use dice::DiceComputations;
 use gazebo::any::ProvidesStaticType;
+use gazebo::prelude::*;
+use starlark::collections::SmallMap;
 use starlark::environment::Methods;
 use starlark::environment::MethodsBuilder;
 use starlark::environment::MethodsStatic;
 use starlark::starlark_module;
 use starlark::starlark_type;
+use starlark::values::dict::Dict;
 use starlark::values::type_repr::StarlarkTypeRepr;
 use starlark::values::AllocValue;
 use starlark::values::Heap;
@@ -34,9 +50,11 @@ use starlark::values::UnpackValue;
 use starlark::values::Value;
 use starlark::values::ValueLike;
 use starlark::StarlarkDocs;
+use thiserror::Error;
 
 use crate::bxl::starlark_defs::context::starlark_async::BxlSafeDiceComputations;
 use crate::bxl::starlark_defs::file_expr::FileExpr;
+use crate::bxl::starlark_defs::file_set::StarlarkFileSet;
 use crate::bxl::starlark_defs::file_set::StarlarkReadDirSet;
 
 #[derive(
@@ -159,6 +177,141 @@ fn fs_operations(builder: &mut MethodsBuilder) {
     fn is_file<'v>(this: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result<bool> {
         Ok(std::path::Path::is_file(resolve(this, expr)?.as_ref()))
     }
+
+    /// Returns the contents of the file at the given 'FileExpr' as a string. Errors if the file
+    /// does not exist, is ignored, or is not UTF-8.
+    fn read<'v>(this: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result<String> {
+        let path = expr.get(this.dice)?;
+        this.dice
+            .via_dice(async move |ctx| ctx.file_ops().read_file(&path).await)
+    }
+
+    /// Reads the file at the given 'FileExpr' and parses it as JSON, returning the corresponding
+    /// Starlark value.
+    fn read_json<'v>(
+        this: &BxlFilesystem<'v>,
+        expr: FileExpr<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let path = expr.get(this.dice)?;
+        let contents = this
+            .dice
+            .via_dice(async move |ctx| ctx.file_ops().read_file(&path).await)?;
+        let json = serde_json::from_str(&contents)
+            .with_context(|| format!("Error parsing `{}` as JSON", path))?;
+        json_to_starlark(json, heap)
+    }
+
+    /// Returns the files matching any of the glob `patterns` and none of the `exclude` patterns.
+    /// Patterns are relative to the project root, and are matched the same way as by `glob()` in
+    /// build files. Ignored files are never returned, and the search doesn't descend into
+    /// directories that are packages in their own right, other than the one a pattern starts from.
+    fn glob<'v>(
+        this: &BxlFilesystem<'v>,
+        patterns: Vec<&str>,
+        #[starlark(require = named, default = Vec::new())] exclude: Vec<&str>,
+    ) -> anyhow::Result<StarlarkFileSet> {
+        this.dice
+            .via_dice(async move |ctx| glob(ctx, &patterns, &exclude).await)
+            .map(StarlarkFileSet::from)
+    }
+}
+
+#[derive(Debug, Error)]
+enum BxlFilesystemError {
+    #[error("Number can't be represented in Starlark: `{0}`")]
+    UnrepresentableNumber(String),
+}
+
+fn json_to_starlark<'v>(json: serde_json::Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+    match json {
+        serde_json::Value::Null => Ok(Value::new_none()),
+        serde_json::Value::Bool(x) => Ok(Value::new_bool(x)),
+        serde_json::Value::Number(x) => {
+            if let Some(i) = x.as_i64() {
+                Ok(heap.alloc(i))
+            } else if let Some(f) = x.as_f64() {
+                Ok(heap.alloc(f))
+            } else {
+                Err(BxlFilesystemError::UnrepresentableNumber(x.to_string()).into())
+            }
+        }
+        serde_json::Value::String(x) => Ok(heap.alloc(x)),
+        serde_json::Value::Array(xs) => {
+            Ok(heap.alloc_list_iter(xs.into_try_map(|x| json_to_starlark(x, heap))?))
+        }
+        serde_json::Value::Object(entries) => {
+            let mut dict = SmallMap::with_capacity(entries.len());
+            for (k, v) in entries {
+                dict.insert_hashed(
+                    heap.alloc_str(&k).get_hashed_value(),
+                    json_to_starlark(v, heap)?,
+                );
+            }
+            Ok(heap.alloc(Dict::new(dict)))
+        }
+    }
+}
+
+/// The directory a glob pattern starts from: its leading components without special characters.
+fn glob_base(pattern: &str) -> &str {
+    let literal = match pattern.find(|c| "*?[".contains(c)) {
+        Some(i) => &pattern[..i],
+        None => pattern,
+    };
+    match literal.rfind('/') {
+        Some(i) => &literal[..i],
+        None => "",
+    }
+}
+
+async fn glob(
+    ctx: &DiceComputations,
+    patterns: &[&str],
+    exclude: &[&str],
+) -> anyhow::Result<FileSet> {
+    let cell_resolver = ctx.get_cell_resolver().await?;
+    let file_ops = ctx.file_ops();
+
+    let mut files = BTreeSet::new();
+    // Each pattern is matched on its own walk: the packages one pattern doesn't go into may be
+    // where another starts from.
+    for pattern in patterns {
+        let spec = GlobSpec::new(&[pattern], exclude)?;
+        let base =
+            cell_resolver.get_cell_path(<&ProjectRelativePath>::try_from(glob_base(pattern))?)?;
+        if !matches!(
+            file_ops.read_path_metadata_if_exists(&base).await?,
+            Some(RawPathMetadata::Directory)
+        ) {
+            continue;
+        }
+        let buildfiles = cell_resolver.get(base.cell())?.buildfiles();
+
+        let mut walked = HashSet::new();
+        let mut work = vec![base.clone()];
+        while let Some(dir) = work.pop() {
+            if walked.contains(&dir) {
+                continue;
+            }
+            let entries = file_ops.read_dir(&dir).await?;
+            // The pattern's own directory may well be a package, but we don't go into others.
+            if dir != base && find_buildfile(buildfiles, &entries).is_some() {
+                continue;
+            }
+            for entry in entries.iter() {
+                let path = dir.join(ForwardRelativePath::new(&entry.file_name)?);
+                if entry.file_type.is_dir() {
+                    work.push(path);
+                } else if spec.matches(cell_resolver.resolve_path(&path)?.as_str()) {
+                    files.insert(path);
+                }
+            }
+            walked.insert(dir);
+        }
+    }
+
+    Ok(FileSet::new(files.into_iter().map(FileNode).collect()))
 }
 
 /// Returns the absolute path for a FileExpr.
@@ -167,3 +320,17 @@ fn resolve<'v>(bxl_fs: &BxlFilesystem<'v>, expr: FileExpr<'v>) -> anyhow::Result
     let project_rel_path = bxl_fs.artifact_fs.resolve_cell_path(&cell_path)?;
     Ok(bxl_fs.project_fs.resolve(&project_rel_path))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_base() {
+        assert_eq!(glob_base("foo/bar/**/*.cpp"), "foo/bar");
+        assert_eq!(glob_base("foo/ba*/baz.txt"), "foo");
+        assert_eq!(glob_base("foo/bar.txt"), "foo");
+        assert_eq!(glob_base("**/*.json"), "");
+        assert_eq!(glob_base("OWNERS"), "");
+    }
+}
@@ -10,9 +10,11 @@
 use std::sync::Arc;
 
 use allocative::Allocative;
+use buck2_build_api::query::cquery::environment::CqueryDelegate;
 use buck2_build_api::query::cquery::environment::CqueryEnvironment;
 use buck2_build_api::query::cquery::environment::CqueryOwnerBehavior;
 use buck2_build_api::query::cquery::evaluator::get_cquery_evaluator;
+use buck2_build_api::query::uquery::environment::QueryLiterals;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_core::target::TargetLabel;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
@@ -114,13 +116,20 @@ pub(crate) async fn get_cquery_env<'v>(
 ) -> anyhow::Result<CqueryEnvironment<'v>> {
     let dice_query_delegate = BxlContext::dice_query_delegate(ctx, target_platform).await?;
     let cquery_delegate = Arc::new(dice_query_delegate);
-    Ok(CqueryEnvironment::new(
-        cquery_delegate.dupe(),
-        cquery_delegate,
+    Ok(bxl_cquery_env(cquery_delegate.dupe(), cquery_delegate))
+}
+
+fn bxl_cquery_env<'v>(
+    delegate: Arc<dyn CqueryDelegate + 'v>,
+    literals: Arc<dyn QueryLiterals<ConfiguredTargetNode> + 'v>,
+) -> CqueryEnvironment<'v> {
+    CqueryEnvironment::new(
+        delegate,
+        literals,
         // TODO(nga): add universe.
         None,
         CqueryOwnerBehavior::Deprecated,
-    ))
+    )
 }
 
 impl<'v> StarlarkCQueryCtx<'v> {
@@ -414,19 +423,27 @@ fn register_cquery(builder: &mut MethodsBuilder) {
             .map(StarlarkTargetSet::from)
     }
 
+    /// The `rdeps` query. Like `deps`, the traversal can be restricted by a `filter` expression
+    /// returning the children of each node.
     fn rdeps<'v>(
         this: &StarlarkCQueryCtx<'v>,
         universe: Value<'v>,
         from: Value<'v>,
         depth: Option<i32>,
+        #[starlark(default = NoneOr::None)] filter: NoneOr<&'v str>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<StarlarkTargetSet<ConfiguredTargetNode>> {
         this.ctx
             .async_ctx
             .via(|| async {
+                let filter = filter
+                    .into_option()
+                    .try_map(|v| buck2_query_parser::parse_expr(v))?;
+
                 this.functions
                     .rdeps(
                         &this.env,
+                        &DefaultQueryFunctionsModule::new(),
                         &*TargetExpr::<'v, ConfiguredTargetNode>::unpack(
                             universe,
                             &this.target_platform,
@@ -446,12 +463,106 @@ fn register_cquery(builder: &mut MethodsBuilder) {
                         .get(&this.env)
                         .await?,
                         depth,
+                        filter
+                            .as_ref()
+                            .map(|span| CapturedExpr { expr: span })
+                            .as_ref(),
+                    )
+                    .await
+            })
+            .map(StarlarkTargetSet::from)
+    }
+
+    /// The `siblings` query: all the targets defined in the same packages as `targets`.
+    fn siblings<'v>(
+        this: &StarlarkCQueryCtx<'v>,
+        targets: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkTargetSet<ConfiguredTargetNode>> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                this.functions
+                    .siblings(
+                        &this.env,
+                        &*TargetExpr::<'v, ConfiguredTargetNode>::unpack(
+                            targets,
+                            &this.target_platform,
+                            this.ctx,
+                            eval,
+                        )
+                        .await?
+                        .get(&this.env)
+                        .await?,
                     )
                     .await
             })
             .map(StarlarkTargetSet::from)
     }
 
+    /// The `same_pkg_direct_rdeps` query: the targets in the same packages as `targets` that
+    /// depend directly on one of them.
+    fn same_pkg_direct_rdeps<'v>(
+        this: &StarlarkCQueryCtx<'v>,
+        targets: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkTargetSet<ConfiguredTargetNode>> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                this.functions
+                    .same_pkg_direct_rdeps(
+                        &this.env,
+                        &*TargetExpr::<'v, ConfiguredTargetNode>::unpack(
+                            targets,
+                            &this.target_platform,
+                            this.ctx,
+                            eval,
+                        )
+                        .await?
+                        .get(&this.env)
+                        .await?,
+                    )
+                    .await
+            })
+            .map(StarlarkTargetSet::from)
+    }
+
+    /// The `visible` query: the targets in `targets` that are visible to every target in
+    /// `universe`.
+    fn visible<'v>(
+        this: &StarlarkCQueryCtx<'v>,
+        universe: Value<'v>,
+        targets: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkTargetSet<ConfiguredTargetNode>> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                this.functions.visible(
+                    &*TargetExpr::<'v, ConfiguredTargetNode>::unpack(
+                        universe,
+                        &this.target_platform,
+                        this.ctx,
+                        eval,
+                    )
+                    .await?
+                    .get(&this.env)
+                    .await?,
+                    &*TargetExpr::<'v, ConfiguredTargetNode>::unpack(
+                        targets,
+                        &this.target_platform,
+                        this.ctx,
+                        eval,
+                    )
+                    .await?
+                    .get(&this.env)
+                    .await?,
+                )
+            })
+            .map(StarlarkTargetSet::from)
+    }
+
     /// Evaluates some general query string
     fn eval<'v>(
         this: &StarlarkCQueryCtx<'v>,
@@ -539,3 +650,84 @@ fn register_cquery(builder: &mut MethodsBuilder) {
             .map(StarlarkFileSet::from)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use buck2_build_api::query::testing::TestQueryDelegateBuilder;
+    use buck2_build_api::query::uquery::environment::PreresolvedQueryLiterals;
+    use buck2_node::visibility::VisibilitySpecification;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+
+    use super::*;
+
+    fn set(nodes: &[&ConfiguredTargetNode]) -> TargetSet<ConfiguredTargetNode> {
+        let mut set = TargetSet::new();
+        for node in nodes {
+            set.insert((*node).dupe());
+        }
+        set
+    }
+
+    fn env(delegate: TestQueryDelegateBuilder) -> CqueryEnvironment<'static> {
+        bxl_cquery_env(
+            Arc::new(delegate.build()),
+            Arc::new(PreresolvedQueryLiterals::new(HashMap::new())),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_siblings_and_same_pkg_direct_rdeps() -> anyhow::Result<()> {
+        let mut delegate = TestQueryDelegateBuilder::default();
+        let a1 = delegate.target("a", "1", &[], &[], VisibilitySpecification::Public);
+        let a2 = delegate.target("a", "2", &[&a1], &[], VisibilitySpecification::Public);
+        let b1 = delegate.target("b", "1", &[&a1], &[], VisibilitySpecification::Public);
+        let env = env(delegate);
+        let functions = DefaultQueryFunctions::new();
+
+        // Without a universe, all the targets of the packages are configured.
+        assert_eq!(
+            functions.siblings(&env, &set(&[&a2])).await?,
+            set(&[&a1, &a2])
+        );
+        assert_eq!(
+            functions.same_pkg_direct_rdeps(&env, &set(&[&a1])).await?,
+            set(&[&a2])
+        );
+        assert_eq!(
+            functions.visible(&set(&[&b1]), &set(&[&a1, &a2]))?,
+            set(&[&a1, &a2])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rdeps_filter() -> anyhow::Result<()> {
+        let mut delegate = TestQueryDelegateBuilder::default();
+        let tool = delegate.target("a", "tool", &[], &[], VisibilitySpecification::Public);
+        let lib = delegate.target("a", "lib", &[&tool], &[], VisibilitySpecification::Public);
+        let gen = delegate.target("a", "gen", &[], &[&tool], VisibilitySpecification::Public);
+        let env = env(delegate);
+        let functions = DefaultQueryFunctions::new();
+
+        // Parsed the same way as the `filter` argument of `rdeps`.
+        let filter = buck2_query_parser::parse_expr("target_deps()")?;
+        assert_eq!(
+            functions
+                .rdeps(
+                    &env,
+                    &DefaultQueryFunctionsModule::new(),
+                    &set(&[&lib, &gen]),
+                    &set(&[&tool]),
+                    None,
+                    Some(&CapturedExpr { expr: &filter }),
+                )
+                .await?,
+            set(&[&tool, &lib])
+        );
+
+        Ok(())
+    }
+}
@@ -7,5 +7,6 @@
  * of this source tree.
  */
 
+pub mod action;
 pub mod configured;
 pub mod unconfigured;
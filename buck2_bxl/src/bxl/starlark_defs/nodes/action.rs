@@ -0,0 +1,138 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use allocative::Allocative;
+use buck2_build_api::actions::artifact::Artifact;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::interpreter::rule_defs::artifact::StarlarkArtifact;
+use buck2_build_api::query::aquery::environment::ActionQueryNode;
+use buck2_query::query::environment::QueryTarget;
+use derive_more::Display;
+use gazebo::any::ProvidesStaticType;
+use gazebo::prelude::*;
+use indexmap::IndexMap;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
+use starlark::starlark_module;
+use starlark::starlark_simple_value;
+use starlark::starlark_type;
+use starlark::values::dict::Dict;
+use starlark::values::Heap;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+use starlark::StarlarkDocs;
+
+#[derive(Debug, Display, ProvidesStaticType, StarlarkDocs, Allocative)]
+#[derive(NoSerialize)]
+#[display(fmt = "action_query_node({})", "self.0.action()")]
+#[starlark_docs_attrs(directory = "bxl")]
+pub struct StarlarkActionQueryNode(pub ActionQueryNode);
+
+starlark_simple_value!(StarlarkActionQueryNode);
+
+impl<'v> StarlarkValue<'v> for StarlarkActionQueryNode {
+    starlark_type!("action_query_node");
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(action_query_node_value_methods)
+    }
+}
+
+impl<'a> UnpackValue<'a> for StarlarkActionQueryNode {
+    fn expected() -> String {
+        "action query node".to_owned()
+    }
+
+    fn unpack_value(value: Value<'a>) -> Option<Self> {
+        value
+            .downcast_ref::<Self>()
+            .map(|value| Self(value.0.dupe()))
+    }
+}
+
+fn alloc_string_dict<'v>(
+    heap: &'v Heap,
+    entries: IndexMap<String, String>,
+) -> anyhow::Result<Value<'v>> {
+    Ok(heap.alloc(Dict::new(
+        entries
+            .into_iter()
+            .map(|(k, v)| Ok((heap.alloc(k).get_hashed()?, heap.alloc(v))))
+            .collect::<anyhow::Result<_>>()?,
+    )))
+}
+
+#[starlark_module]
+fn action_query_node_value_methods(builder: &mut MethodsBuilder) {
+    /// The kind of the action, e.g. `run` or `write`.
+    #[starlark(attribute)]
+    fn kind(this: &StarlarkActionQueryNode) -> anyhow::Result<String> {
+        Ok(this.0.rule_type().into_owned())
+    }
+
+    /// The category the action was declared with.
+    #[starlark(attribute)]
+    fn category(this: &StarlarkActionQueryNode) -> anyhow::Result<String> {
+        Ok(this.0.action().category().as_str().to_owned())
+    }
+
+    /// The identifier the action was declared with, or `None` if it has none.
+    #[starlark(attribute)]
+    fn identifier(this: &StarlarkActionQueryNode) -> anyhow::Result<Option<String>> {
+        Ok(this.0.action().identifier().map(|i| i.to_owned()))
+    }
+
+    /// The action's attributes as shown by `buck2 aquery`, as a dict of strings.
+    fn attrs<'v>(this: &StarlarkActionQueryNode, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        alloc_string_dict(heap, this.0.attrs())
+    }
+
+    /// The arguments of the command the action runs, or `None` if it doesn't run one.
+    fn command_line(this: &StarlarkActionQueryNode) -> anyhow::Result<Option<Vec<String>>> {
+        Ok(this.0.command()?.map(|command| command.cli))
+    }
+
+    /// The environment variables the action sets for its command, or `None` if it doesn't run one.
+    fn env<'v>(this: &StarlarkActionQueryNode, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match this.0.command()? {
+            Some(command) => alloc_string_dict(heap, command.env),
+            None => Ok(Value::new_none()),
+        }
+    }
+
+    /// The artifacts the action reads directly. Inputs from transitive sets aren't included, but
+    /// the actions producing them are among the action's deps.
+    fn inputs(this: &StarlarkActionQueryNode) -> anyhow::Result<Vec<StarlarkArtifact>> {
+        Ok(this
+            .0
+            .action()
+            .inputs()?
+            .iter()
+            .filter_map(|input| match input {
+                ArtifactGroup::Artifact(artifact) => Some(StarlarkArtifact::new(artifact.dupe())),
+                ArtifactGroup::TransitiveSetProjection(..) => None,
+            })
+            .collect())
+    }
+
+    /// The artifacts the action produces.
+    fn outputs(this: &StarlarkActionQueryNode) -> anyhow::Result<Vec<StarlarkArtifact>> {
+        Ok(this
+            .0
+            .action()
+            .outputs()?
+            .iter()
+            .map(|output| StarlarkArtifact::new(Artifact::from(output.dupe())))
+            .collect())
+    }
+}
@@ -11,6 +11,8 @@ use std::borrow::Cow;
 
 use buck2_build_api::calculation::load_patterns;
 use buck2_build_api::calculation::Calculation;
+use buck2_build_api::query::aquery::environment::ActionQueryNode;
+use buck2_build_api::query::aquery::environment::AqueryEnvironment;
 use buck2_build_api::query::dice::get_compatible_targets;
 use buck2_core::cells::paths::CellRelativePath;
 use buck2_core::package::Package;
@@ -35,6 +37,7 @@ use starlark::values::ValueLike;
 use thiserror::Error;
 
 use crate::bxl::starlark_defs::context::BxlContext;
+use crate::bxl::starlark_defs::nodes::action::StarlarkActionQueryNode;
 use crate::bxl::starlark_defs::nodes::configured::StarlarkConfiguredTargetNode;
 use crate::bxl::starlark_defs::nodes::unconfigured::StarlarkTargetNode;
 use crate::bxl::starlark_defs::targetset::NodeLike;
@@ -337,3 +340,71 @@ impl<'v> TargetExpr<'v, TargetNode> {
         Ok(Some(Self::Iterable(resolved)))
     }
 }
+
+impl<'v> TargetExpr<'v, ActionQueryNode> {
+    /// Unpacks action nodes, sets of them, or target literals, which are resolved to the actions
+    /// producing the default outputs of the targets.
+    pub async fn unpack<'c>(
+        value: Value<'v>,
+        env: &AqueryEnvironment<'c>,
+    ) -> anyhow::Result<TargetExpr<'v, ActionQueryNode>> {
+        Ok(
+            if let Some(resolved) = Self::unpack_literal(value, env).await? {
+                resolved
+            } else if let Some(resolved) = Self::unpack_iterable(value, env).await? {
+                resolved
+            } else {
+                return Err(anyhow::anyhow!(TargetExprError::NotAListOfTargets(
+                    value.to_repr()
+                )));
+            },
+        )
+    }
+
+    async fn unpack_literal<'c>(
+        value: Value<'v>,
+        env: &AqueryEnvironment<'c>,
+    ) -> anyhow::Result<Option<TargetExpr<'v, ActionQueryNode>>> {
+        if let Some(node) = value.downcast_ref::<StarlarkActionQueryNode>() {
+            Ok(Some(Self::Node(node.0.dupe())))
+        } else if let Some(s) = value.unpack_str() {
+            Ok(Some(Self::TargetSet(Cow::Owned(
+                env.eval_literals(&[s]).await?,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn unpack_iterable<'c>(
+        value: Value<'v>,
+        env: &AqueryEnvironment<'c>,
+    ) -> anyhow::Result<Option<TargetExpr<'v, ActionQueryNode>>> {
+        if let Some(s) = value.downcast_ref::<StarlarkTargetSet<ActionQueryNode>>() {
+            return Ok(Some(Self::TargetSet(Cow::Borrowed(s))));
+        }
+
+        let items = List::from_value(value)
+            .ok_or_else(|| TargetExprError::NotAListOfTargets(value.to_repr()))?;
+
+        let mut resolved = vec![];
+
+        for item in items.iter() {
+            match Self::unpack_literal(item, env).await? {
+                Some(TargetExpr::Node(node)) => resolved.push(Either::Left(node)),
+                Some(TargetExpr::TargetSet(set)) => {
+                    resolved.extend(set.iter().map(|node| Either::Left(node.dupe())))
+                }
+                _ => {
+                    return Err(anyhow::anyhow!(TargetExprError::NotATarget(item.to_repr()))
+                        .context(format!(
+                            "when resolving list `{}`",
+                            truncate(&value.to_repr(), 150)
+                        )));
+                }
+            }
+        }
+
+        Ok(Some(Self::Iterable(resolved)))
+    }
+}
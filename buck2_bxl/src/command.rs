@@ -22,6 +22,7 @@ use buck2_build_api::build::MaterializationContext;
 use buck2_build_api::bxl::build_result::BxlBuildResult;
 use buck2_build_api::bxl::calculation::BxlCalculation;
 use buck2_build_api::calculation::Calculation;
+use buck2_build_api::configuration::HasConfigurationModifiers;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::data::HasIoProvider;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
@@ -162,7 +163,11 @@ pub(crate) async fn get_bxl_key(
     let bxl_args =
         Arc::new(resolve_cli_args(&bxl_label, &cli_ctx, bxl_args, &frozen_callable).await?);
 
-    Ok(BxlKey::new(bxl_label.clone(), bxl_args))
+    Ok(BxlKey::new(
+        bxl_label.clone(),
+        bxl_args,
+        ctx.per_transaction_data().get_configuration_modifiers(),
+    ))
 }
 
 async fn copy_output<W: Write>(
@@ -18,7 +18,9 @@ use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::streaming::StreamingCommand;
 use clap::ArgMatches;
+use cli_proto::allocative_request;
 use cli_proto::AllocativeRequest;
+use gazebo::dupe::Dupe;
 
 #[derive(Debug, clap::Parser)]
 pub struct AllocativeCommand {
@@ -32,6 +34,35 @@ pub struct AllocativeCommand {
         default_value = "allocative-out"
     )]
     output: PathArg,
+
+    /// How to attribute memory shared between several owners (e.g. `Arc`).
+    ///
+    /// `root` puts shared memory under a synthetic root, `first-visitor` attributes it
+    /// to the first path which reaches it, and `proportional` splits it evenly
+    /// between all paths which reach it.
+    #[clap(long, value_enum, default_value = "root")]
+    shared_attribution: SharedAttributionArg,
+}
+
+#[derive(clap::ValueEnum, Dupe, Clone, Debug)]
+enum SharedAttributionArg {
+    Root,
+    FirstVisitor,
+    Proportional,
+}
+
+impl SharedAttributionArg {
+    fn to_proto(&self) -> allocative_request::SharedAttribution {
+        match self {
+            SharedAttributionArg::Root => allocative_request::SharedAttribution::Root,
+            SharedAttributionArg::FirstVisitor => {
+                allocative_request::SharedAttribution::FirstVisitor
+            }
+            SharedAttributionArg::Proportional => {
+                allocative_request::SharedAttribution::Proportional
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -60,6 +91,7 @@ impl StreamingCommand for AllocativeCommand {
                         .to_str()
                         .context("not utf-8")?
                         .to_owned(),
+                    shared_attribution: self.shared_attribution.to_proto() as i32,
                 },
                 ctx.stdin().console_interaction_stream(self.console_opts()),
             )
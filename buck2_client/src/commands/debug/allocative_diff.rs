@@ -0,0 +1,107 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt::Write as _;
+
+use allocative::FlameGraph;
+use allocative::FlameGraphDiff;
+use allocative::PathGrowth;
+use anyhow::Context;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+
+/// Compares two outputs of `buck2 debug allocative`, e.g. taken before and after
+/// a build, and reports which paths retain more memory.
+#[derive(Debug, clap::Parser)]
+pub struct AllocativeDiffCommand {
+    /// Output directory of `buck2 debug allocative` taken first.
+    #[clap(value_name = "BEFORE")]
+    before: PathArg,
+
+    /// Output directory of `buck2 debug allocative` taken second.
+    #[clap(value_name = "AFTER")]
+    after: PathArg,
+
+    /// Output directory path for the differential flamegraph and the growth report.
+    ///
+    /// Directory will be created if it does not exist.
+    #[clap(
+        long,
+        short = 'o',
+        value_name = "PATH",
+        default_value = "allocative-diff-out"
+    )]
+    output: PathArg,
+
+    /// Number of paths with the largest growth to print.
+    #[clap(long, value_name = "N", default_value = "20")]
+    top: usize,
+}
+
+impl AllocativeDiffCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext) -> ExitResult {
+        let before = read_flamegraph(self.before.resolve(&ctx.working_dir))?;
+        let after = read_flamegraph(self.after.resolve(&ctx.working_dir))?;
+        let diff = FlameGraphDiff::new(&before, &after);
+
+        let output = self.output.resolve(&ctx.working_dir);
+        fs_util::create_dir_if_not_exists(&output)?;
+        fs_util::write(output.join("diff.src"), &diff.flamegraph())?;
+        let mut diff_svg = Vec::new();
+        inferno::flamegraph::from_reader(
+            &mut inferno::flamegraph::Options::default(),
+            diff.flamegraph().as_bytes(),
+            &mut diff_svg,
+        )?;
+        fs_util::write(output.join("diff.svg"), &diff_svg)?;
+        fs_util::write(
+            output.join("growth.txt"),
+            format_growth(diff.growth().iter()),
+        )?;
+
+        buck2_client_ctx::println!(
+            "Total retained: {} -> {} ({:+})",
+            before.total_size(),
+            after.total_size(),
+            after.total_size() as isize - before.total_size() as isize
+        )?;
+        buck2_client_ctx::print!("{}", format_growth(diff.growth().iter().take(self.top)))?;
+        buck2_client_ctx::println!("Diff written to `{}`", output.display())?;
+        ExitResult::success()
+    }
+}
+
+fn read_flamegraph(dir: AbsPathBuf) -> anyhow::Result<FlameGraph> {
+    let path = dir.join("flamegraph.json");
+    let json = fs_util::read_to_string(&path)?;
+    serde_json::from_str(&json).with_context(|| {
+        format!(
+            "Parsing `{}`, was it written by `buck2 debug allocative`?",
+            path.display()
+        )
+    })
+}
+
+fn format_growth<'a>(growth: impl Iterator<Item = &'a PathGrowth>) -> String {
+    let mut res = String::new();
+    for g in growth {
+        writeln!(
+            res,
+            "{:+12} self {:+12} retained  {}",
+            g.self_growth(),
+            g.total_growth(),
+            g.path.join(";")
+        )
+        .unwrap();
+    }
+    res
+}
@@ -24,6 +24,7 @@ use materialize::MaterializeCommand;
 use replay::ReplayCommand;
 
 use crate::commands::debug::allocative::AllocativeCommand;
+use crate::commands::debug::allocative_diff::AllocativeDiffCommand;
 use crate::commands::debug::daemon_dir::DaemonDirCommand;
 use crate::commands::debug::exe::ExeCommand;
 use crate::commands::debug::segfault::SegfaultCommand;
@@ -32,6 +33,7 @@ use crate::commands::log::last_log::LastLogCommand;
 use crate::commands::log::what_ran::WhatRanCommand;
 
 mod allocative;
+mod allocative_diff;
 mod allocator_stats;
 mod chrome_trace;
 mod crash;
@@ -86,6 +88,8 @@ pub enum DebugCommand {
     /// Prints buck2 executable (this executable) path.
     Exe(ExeCommand),
     Allocative(AllocativeCommand),
+    /// Compares two outputs of `allocative` and reports memory growth.
+    AllocativeDiff(AllocativeDiffCommand),
 }
 
 /// `cli::exec` function.
@@ -121,6 +125,7 @@ impl DebugCommand {
             DebugCommand::DaemonDir(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Exe(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Allocative(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::AllocativeDiff(cmd) => cmd.exec(matches, ctx),
         }
     }
 }
@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::subscribers::event_log::file_names::retrieve_nth_recent_log;
+use buck2_client_ctx::subscribers::event_log::EventLogPathBuf;
+use tokio::runtime;
+
+/// Rewrites an event log with an index, so that later queries on it are fast. The output can use
+/// another encoding than the input, e.g. to compact a JSON log into a protobuf one.
+#[derive(Debug, clap::Parser)]
+#[clap(group = clap::ArgGroup::with_name("event_log"))]
+pub struct CompactCommand {
+    /// A path to an event-log file to read from. Only works for log files with a single command in them.
+    #[clap(group = "event_log", value_name = "PATH")]
+    path: Option<PathArg>,
+
+    /// Which recent command to read the event log from.
+    #[clap(
+        long,
+        help = "Compact the log of the Nth most recent command (`--recent 0` is the most recent).",
+        group = "event_log",
+        value_name = "NUMBER"
+    )]
+    pub recent: Option<usize>,
+
+    #[clap(
+        long,
+        help = "Where to write the compacted log. Its encoding is inferred from its extension.",
+        value_name = "PATH"
+    )]
+    pub output: PathArg,
+}
+
+impl CompactCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext) -> ExitResult {
+        let Self {
+            path,
+            recent,
+            output,
+        } = self;
+
+        let path = match path {
+            Some(path) => path.resolve(&ctx.working_dir),
+            None => retrieve_nth_recent_log(&ctx, recent.unwrap_or(0))?.into_abs_path_buf(),
+        };
+        let log_path = EventLogPathBuf::infer(path)?;
+        let output = output.resolve(&ctx.working_dir);
+
+        let rt = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        rt.block_on(log_path.write_indexed(output.clone()))?;
+
+        buck2_client_ctx::eprintln!("Wrote {}", output.display())?;
+        ExitResult::success()
+    }
+}
@@ -0,0 +1,107 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use anyhow::Context as _;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::stream_value::StreamValue;
+use buck2_client_ctx::subscribers::event_log::file_names::retrieve_nth_recent_log;
+use buck2_client_ctx::subscribers::event_log::EventLogPathBuf;
+use buck2_events::sink::otel::OtelExporter;
+use buck2_events::sink::otel::SpanConverter;
+use buck2_events::sink::otel::DEFAULT_BATCH_SIZE;
+use buck2_events::BuckEvent;
+use tokio::runtime;
+use tokio_stream::StreamExt;
+
+/// Export the spans of a command to an OpenTelemetry collector
+#[derive(Debug, clap::Parser)]
+#[clap(group = clap::ArgGroup::with_name("event_log"))]
+pub struct ExportOtelCommand {
+    /// A path to an event-log file to read from. Only works for log files with a single command in them.
+    #[clap(group = "event_log", value_name = "PATH")]
+    path: Option<PathArg>,
+
+    /// Which recent command to read the event log from.
+    #[clap(
+        long,
+        help = "Replay the Nth most recent command (`--recent 0` is the most recent).",
+        group = "event_log",
+        value_name = "NUMBER"
+    )]
+    pub recent: Option<usize>,
+
+    #[clap(
+        long,
+        help = "The address of the collector: `grpc://` or `grpcs://` for OTLP/gRPC, `http://` or `https://` for OTLP/HTTP.",
+        value_name = "URL"
+    )]
+    pub endpoint: String,
+
+    #[clap(
+        long = "header",
+        help = "A header to send with every request, e.g. an API key.",
+        value_name = "NAME=VALUE"
+    )]
+    pub headers: Vec<String>,
+}
+
+impl ExportOtelCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext) -> ExitResult {
+        let Self {
+            path,
+            recent,
+            endpoint,
+            headers,
+        } = self;
+
+        let headers = headers
+            .iter()
+            .map(|header| {
+                let (name, value) = header.split_once('=').with_context(|| {
+                    format!("Invalid header `{}`, expected `NAME=VALUE`", header)
+                })?;
+                anyhow::Ok((name.to_owned(), value.to_owned()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let path = match path {
+            Some(path) => path.resolve(&ctx.working_dir),
+            None => retrieve_nth_recent_log(&ctx, recent.unwrap_or(0))?.into_abs_path_buf(),
+        };
+        let log_path = EventLogPathBuf::infer(path)?;
+
+        let rt = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let exported = rt.block_on(async {
+            let exporter = OtelExporter::new(&endpoint, &headers)?;
+            let mut converter = SpanConverter::new();
+            let (_, mut events) = log_path.unpack_stream().await?;
+
+            let mut spans = Vec::new();
+            while let Some(event) = events.try_next().await? {
+                if let StreamValue::Event(event) = event {
+                    spans.extend(converter.convert(&BuckEvent::try_from(event)?));
+                }
+            }
+
+            for batch in spans.chunks(DEFAULT_BATCH_SIZE) {
+                exporter.export(batch.to_vec()).await?;
+            }
+
+            anyhow::Ok(spans.len())
+        })?;
+
+        buck2_client_ctx::eprintln!("Exported {} spans to {}", exported, endpoint)?;
+        ExitResult::success()
+    }
+}
@@ -7,8 +7,11 @@
  * of this source tree.
  */
 
+pub mod compact;
+pub mod export_otel;
 pub mod last_log;
 pub mod show_log;
+pub mod summary;
 pub mod what_ran;
 pub mod what_up;
 
@@ -31,6 +34,16 @@ pub enum LogCommand {
     /// Show all the spans that where open when the log ended
     #[clap(alias = "whatup")]
     WhatUp(what_up::WhatUpCommand),
+
+    /// Exports the spans of a command to an OpenTelemetry collector
+    #[clap(alias = "exportotel")]
+    ExportOtel(export_otel::ExportOtelCommand),
+
+    /// Prints a summary of a command
+    Summary(summary::SummaryCommand),
+
+    /// Rewrites an event log with an index, for fast queries
+    Compact(compact::CompactCommand),
 }
 
 impl LogCommand {
@@ -40,6 +53,9 @@ impl LogCommand {
             Self::Last(cmd) => cmd.exec(matches, ctx),
             Self::Show(cmd) => cmd.exec(matches, ctx),
             Self::WhatUp(cmd) => cmd.exec(matches, ctx),
+            Self::ExportOtel(cmd) => cmd.exec(matches, ctx),
+            Self::Summary(cmd) => cmd.exec(matches, ctx),
+            Self::Compact(cmd) => cmd.exec(matches, ctx),
         }
     }
 }
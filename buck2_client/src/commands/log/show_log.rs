@@ -11,7 +11,9 @@ use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::stdio;
+use buck2_client_ctx::stream_value::StreamValueRef;
 use buck2_client_ctx::subscribers::event_log::file_names::retrieve_nth_recent_log;
+use buck2_client_ctx::subscribers::event_log::index::EventLogQuery;
 use buck2_client_ctx::subscribers::event_log::EventLogPathBuf;
 use buck2_client_ctx::subscribers::event_log::SerializeForLog;
 use tokio::runtime;
@@ -37,11 +39,35 @@ pub struct ShowLogCommand {
         value_name = "NUMBER"
     )]
     pub recent: Option<usize>,
+
+    /// Only show events of this span, or of its children.
+    #[clap(long, value_name = "SPAN_ID")]
+    pub span_id: Option<u64>,
+
+    /// Only show events about this target, e.g. `cell//package:name`.
+    #[clap(long, value_name = "TARGET")]
+    pub target: Option<String>,
+
+    /// Only show events about the action with this key.
+    #[clap(long, value_name = "KEY")]
+    pub action_key: Option<String>,
 }
 
 impl ShowLogCommand {
     pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext) -> ExitResult {
-        let Self { path, recent } = self;
+        let Self {
+            path,
+            recent,
+            span_id,
+            target,
+            action_key,
+        } = self;
+        let query = EventLogQuery {
+            span_id,
+            target,
+            action_key,
+            executions: false,
+        };
 
         let path = match path {
             Some(path) => path.resolve(&ctx.working_dir),
@@ -54,6 +80,19 @@ impl ShowLogCommand {
             .build()?;
 
         rt.block_on(async move {
+            if !query.is_empty() {
+                // Uses the index of the log, if it has one, to only read the parts we need.
+                let mut events = log_path.query(query).await?;
+                let mut buf = Vec::new();
+                while let Some(event) = events.try_next().await? {
+                    buf.clear();
+                    StreamValueRef::Event(&event).serialize_to_json(&mut buf)?;
+                    stdio::print_bytes(&buf)?;
+                    stdio::print_bytes(b"\n")?;
+                }
+                return anyhow::Ok(());
+            }
+
             let (invocation, mut events) = log_path.unpack_stream().await?;
 
             let mut buf = Vec::new();
@@ -0,0 +1,85 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::subscribers::event_log::file_names::retrieve_nth_recent_log;
+use buck2_client_ctx::subscribers::event_log::EventLogPathBuf;
+use tokio::runtime;
+
+/// Prints a summary of a command: what it was, whether it succeeded, how long it took and how much
+/// work it did. This is instant for logs that have an index.
+#[derive(Debug, clap::Parser)]
+#[clap(group = clap::ArgGroup::with_name("event_log"))]
+pub struct SummaryCommand {
+    /// A path to an event-log file to read from. Only works for log files with a single command in them.
+    #[clap(group = "event_log", value_name = "PATH")]
+    path: Option<PathArg>,
+
+    /// Which recent command to read the event log from.
+    #[clap(
+        long,
+        help = "Summarize the Nth most recent command (`--recent 0` is the most recent).",
+        group = "event_log",
+        value_name = "NUMBER"
+    )]
+    pub recent: Option<usize>,
+}
+
+impl SummaryCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext) -> ExitResult {
+        let Self { path, recent } = self;
+
+        let path = match path {
+            Some(path) => path.resolve(&ctx.working_dir),
+            None => retrieve_nth_recent_log(&ctx, recent.unwrap_or(0))?.into_abs_path_buf(),
+        };
+        let log_path = EventLogPathBuf::infer(path)?;
+
+        let rt = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let summary = rt.block_on(log_path.read_summary())?;
+
+        let result = match summary.success {
+            Some(true) => "success",
+            Some(false) => "failure",
+            None => "unfinished",
+        };
+        let duration = match summary.duration() {
+            Some(duration) => format!("{:.3}s", duration.as_secs_f64()),
+            None => "unknown".to_owned(),
+        };
+
+        buck2_client_ctx::println!(
+            "command: {}",
+            summary.command.as_deref().unwrap_or("unknown")
+        )?;
+        buck2_client_ctx::println!(
+            "trace id: {}",
+            summary.trace_id.as_deref().unwrap_or("unknown")
+        )?;
+        buck2_client_ctx::println!("result: {}", result)?;
+        buck2_client_ctx::println!("duration: {}", duration)?;
+        buck2_client_ctx::println!("events: {}", summary.events)?;
+        buck2_client_ctx::println!("spans: {}", summary.spans)?;
+        buck2_client_ctx::println!("loads: {}", summary.loads)?;
+        buck2_client_ctx::println!("analyses: {}", summary.analyses)?;
+        buck2_client_ctx::println!(
+            "actions: {} ({} cached, {} failed)",
+            summary.actions,
+            summary.cached_actions,
+            summary.failed_actions
+        )?;
+
+        ExitResult::success()
+    }
+}
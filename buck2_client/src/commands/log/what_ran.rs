@@ -13,8 +13,8 @@ use std::collections::HashMap;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::path_arg::PathArg;
-use buck2_client_ctx::stream_value::StreamValue;
 use buck2_client_ctx::subscribers::event_log::file_names::retrieve_nth_recent_log;
+use buck2_client_ctx::subscribers::event_log::index::EventLogQuery;
 use buck2_client_ctx::subscribers::event_log::EventLogPathBuf;
 use buck2_client_ctx::what_ran;
 use buck2_client_ctx::what_ran::CommandReproducer;
@@ -120,7 +120,8 @@ impl WhatRanCommand {
 
         rt.block_on(async move {
             let log_path = EventLogPathBuf::infer(log)?;
-            let (invocation, mut events) = log_path.unpack_stream().await?;
+            // The invocation is at the start of the log, we don't read the rest of it here.
+            let (invocation, _) = log_path.unpack_stream().await?;
 
             buck2_client_ctx::eprintln!(
                 "Showing commands from: {}",
@@ -129,11 +130,15 @@ impl WhatRanCommand {
 
             let mut state = WhatRanCommandState::default();
 
+            // If the log has an index, this only reads the parts of it where commands ran.
+            let mut events = log_path
+                .query(EventLogQuery {
+                    executions: true,
+                    ..Default::default()
+                })
+                .await?;
             while let Some(event) = events.try_next().await? {
-                match event {
-                    StreamValue::Event(event) => state.event(event, &mut output, &options)?,
-                    _ => {}
-                }
+                state.event(event, &mut output, &options)?;
             }
 
             anyhow::Ok(())
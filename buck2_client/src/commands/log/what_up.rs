@@ -26,6 +26,7 @@ use buck2_client_ctx::subscribers::superconsole::StatefulSuperConsole;
 use buck2_client_ctx::subscribers::superconsole::CUTOFFS;
 use buck2_client_ctx::verbosity::Verbosity;
 use buck2_events::BuckEvent;
+use futures::Stream;
 use superconsole::components::splitting::SplitKind;
 use superconsole::components::Split;
 use superconsole::Component;
@@ -84,9 +85,6 @@ impl WhatUpCommand {
             .build()?;
 
         rt.block_on(async move {
-            // Get events
-            let (_, mut events) = log_path.unpack_stream().await?;
-
             //Create new superconsole
             let mut console = StatefulSuperConsole::new_with_root_forced(
                 console_root,
@@ -96,34 +94,28 @@ impl WhatUpCommand {
                 Some(Box::new(io::stdout())),
                 Default::default(),
             )?;
-            let mut first_timestamp = None;
-            let mut should_render = true;
-            // Ignore any events that are truncated, hence unreadable
-            while let Ok(Some(event)) = events.try_next().await {
-                match event {
-                    StreamValue::Event(event) => {
-                        let e = BuckEvent::try_from(event)?;
-                        match cutoff_time {
-                            Some(cutoff_time) => {
-                                if should_stop_reading(
-                                    cutoff_time,
-                                    e.timestamp(),
-                                    *first_timestamp.get_or_insert(e.timestamp()),
-                                )? {
-                                    break;
-                                }
-                            }
-                            _ => (),
-                        }
-
-                        console.handle_event(&Arc::new(e)).await.unwrap();
-                    }
-                    StreamValue::Result(result) => {
-                        console.handle_command_result(&result).await.unwrap();
-                        should_render = false;
+            let index = match cutoff_time {
+                Some(_) => None,
+                None => log_path.read_index().await?,
+            };
+            let should_render = match index {
+                // Without a cutoff, the index of the log tells us whether the command finished, and
+                // otherwise which spans were still open when it ended, so we only read those.
+                Some(index) if index.summary.success.is_some() => false,
+                Some(index) => {
+                    let mut events = log_path.open_spans(index);
+                    while let Some(event) = events.try_next().await? {
+                        console
+                            .handle_event(&Arc::new(BuckEvent::try_from(event)?))
+                            .await?;
                     }
+                    true
                 }
-            }
+                None => {
+                    let (_, events) = log_path.unpack_stream().await?;
+                    replay(events, &mut console, cutoff_time).await?
+                }
+            };
             if should_render {
                 console.render_final_normal_console()?;
             } else {
@@ -136,6 +128,43 @@ impl WhatUpCommand {
     }
 }
 
+/// Replays `events` to `console`, up to `cutoff_time` after the first one. Returns whether there
+/// might be open spans to render, that is whether we didn't see the result of the command.
+async fn replay(
+    mut events: impl Stream<Item = anyhow::Result<StreamValue>> + Unpin,
+    console: &mut StatefulSuperConsole,
+    cutoff_time: Option<Duration>,
+) -> anyhow::Result<bool> {
+    let mut first_timestamp = None;
+    // Ignore any events that are truncated, hence unreadable
+    while let Ok(Some(event)) = events.try_next().await {
+        match event {
+            StreamValue::Event(event) => {
+                let e = BuckEvent::try_from(event)?;
+                match cutoff_time {
+                    Some(cutoff_time) => {
+                        if should_stop_reading(
+                            cutoff_time,
+                            e.timestamp(),
+                            *first_timestamp.get_or_insert(e.timestamp()),
+                        )? {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+
+                console.handle_event(&Arc::new(e)).await.unwrap();
+            }
+            StreamValue::Result(result) => {
+                console.handle_command_result(&result).await.unwrap();
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
 fn should_stop_reading(
     after: Duration,
     event: SystemTime,
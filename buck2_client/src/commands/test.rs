@@ -7,6 +7,10 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
 use anyhow::Context;
 use async_trait::async_trait;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
@@ -17,12 +21,14 @@ use buck2_client_ctx::common::CommonDaemonCommandOptions;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::final_console::FinalConsole;
+use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_client_ctx::subscribers::superconsole::test::StylizedCount;
 use buck2_client_ctx::subscribers::superconsole::test::TestHeader;
 use cli_proto::CounterWithExamples;
 use cli_proto::TestRequest;
 use cli_proto::TestSessionOptions;
+use cli_proto::TestSharding;
 use crossterm::style::Color;
 use gazebo::prelude::*;
 
@@ -105,10 +111,13 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long = "deep")]
     deep: bool,
 
-    // ignored. only for e2e tests. compatibility with v1.
-    #[clap(long = "xml")]
-    #[allow(unused)] // for v1 compat
-    xml: Option<String>,
+    /// Write a JUnit XML report of the test results to this file.
+    #[clap(long = "xml", value_name = "PATH")]
+    xml: Option<PathArg>,
+
+    /// Write a JSON report of the test results to this file.
+    #[clap(long, value_name = "PATH")]
+    json_report: Option<PathArg>,
 
     #[clap(
         name = "TEST_EXECUTOR_ARGS",
@@ -126,6 +135,89 @@ If include patterns are present, regardless of whether exclude patterns are pres
     /// relative paths.
     #[clap(long, group = "re_options")]
     unstable_force_tests_on_re: bool,
+
+    /// Always run tests, even those labelled as cacheable whose inputs haven't changed since
+    /// they last passed.
+    #[clap(long)]
+    no_test_cache: bool,
+
+    /// Only run the tests in this shard, numbered from 0. Every test target is assigned to the
+    /// same shard on every machine, so running each index of `--shard-count` runs all the tests
+    /// once. All the test cases of a target run in the same shard.
+    #[clap(long, requires = "shard-count")]
+    shard_index: Option<u32>,
+
+    /// How many shards to split the tests into.
+    #[clap(long, requires = "shard-index")]
+    shard_count: Option<u32>,
+
+    /// A JSON object mapping test labels to how long they are expected to take in seconds, used to
+    /// balance the shards. Tests missing from it are assigned by a hash of their label.
+    #[clap(long, requires = "shard-index", value_name = "PATH")]
+    shard_timings: Option<PathBuf>,
+
+    /// How many times to rerun a failing test. Tests that fail and then pass are reported as
+    /// flaky rather than failed.
+    #[clap(long, default_value = "0", value_name = "N")]
+    retries: u32,
+
+    /// How many times to rerun failing tests with a given label, overriding `--retries`. May be
+    /// passed several times.
+    #[clap(long, value_name = "LABEL=N")]
+    retries_for_label: Vec<String>,
+}
+
+impl TestCommand {
+    fn sharding(&self) -> anyhow::Result<Option<TestSharding>> {
+        let (index, count) = match (self.shard_index, self.shard_count) {
+            (Some(index), Some(count)) => (index, count),
+            _ => return Ok(None),
+        };
+        let timings = match &self.shard_timings {
+            Some(path) => {
+                let data = fs::read(path)
+                    .with_context(|| format!("Error reading `{}`", path.display()))?;
+                serde_json::from_slice::<HashMap<String, f64>>(&data)
+                    .with_context(|| format!("Error parsing `{}`", path.display()))?
+            }
+            None => HashMap::new(),
+        };
+        Ok(Some(TestSharding {
+            index,
+            count,
+            timings,
+        }))
+    }
+
+    fn report_path(
+        &self,
+        path: &Option<PathArg>,
+        ctx: &ClientCommandContext,
+    ) -> anyhow::Result<Option<String>> {
+        path.as_ref()
+            .map(|path| {
+                path.resolve(&ctx.working_dir)
+                    .into_os_string()
+                    .into_string()
+                    .map_err(|_| anyhow::anyhow!("Cannot convert `{}` to UTF-8", path.display()))
+            })
+            .transpose()
+    }
+
+    fn label_retries(&self) -> anyhow::Result<HashMap<String, u32>> {
+        self.retries_for_label
+            .iter()
+            .map(|arg| {
+                let (label, retries) = arg
+                    .split_once('=')
+                    .with_context(|| format!("Expected `LABEL=N`, got `{}`", arg))?;
+                let retries = retries
+                    .parse()
+                    .with_context(|| format!("Invalid number of retries in `{}`", arg))?;
+                Ok((label.to_owned(), retries))
+            })
+            .collect()
+    }
 }
 
 #[async_trait]
@@ -139,6 +231,10 @@ impl StreamingCommand for TestCommand {
         mut ctx: ClientCommandContext,
     ) -> ExitResult {
         let context = ctx.client_context(&self.config_opts, matches, self.sanitized_argv())?;
+        let sharding = self.sharding()?;
+        let label_retries = self.label_retries()?;
+        let junit_xml_path = self.report_path(&self.xml, &ctx)?;
+        let json_report_path = self.report_path(&self.json_report, &ctx)?;
         let response = buckd
             .with_flushing()
             .test(
@@ -160,7 +256,13 @@ impl StreamingCommand for TestCommand {
                             || self.unstable_force_tests_on_re,
                         force_use_project_relative_paths: self.unstable_force_tests_on_re,
                         force_run_from_project_root: self.unstable_force_tests_on_re,
+                        allow_test_cache: !self.no_test_cache,
+                        retries: self.retries,
+                        label_retries,
                     }),
+                    sharding,
+                    junit_xml_path,
+                    json_report_path,
                 },
                 ctx.stdin().console_interaction_stream(&self.console_opts),
             )
@@ -177,6 +279,7 @@ impl StreamingCommand for TestCommand {
         let failed = statuses.failed.context("Missing `failed`")?;
         let fatals = statuses.fatals.context("Missing `fatals`")?;
         let skipped = statuses.skipped.context("Missing `skipped`")?;
+        let flaky = statuses.flaky.context("Missing `flaky`")?;
 
         let console = self.console_opts.final_console();
         print_build_result(&console, &response.error_messages)?;
@@ -197,6 +300,17 @@ impl StreamingCommand for TestCommand {
                 .to_stdio(),
             )?;
         }
+        if flaky.count > 0 {
+            buck2_client_ctx::print!(
+                "{}. ",
+                StylizedCount {
+                    label: "Flaky",
+                    count: flaky.count,
+                    color: Color::Yellow,
+                }
+                .to_stdio(),
+            )?;
+        }
         buck2_client_ctx::println!(
             "{}. {}. {}. {}. {} builds failed",
             StylizedCount {
@@ -229,7 +343,19 @@ impl StreamingCommand for TestCommand {
         print_error_counter(&console, &listing_failed, "LISTINGS FAILED", "⚠")?;
         print_error_counter(&console, &failed, "TESTS FAILED", "✗")?;
         print_error_counter(&console, &fatals, "TESTS FATALS", "⚠")?;
-        if passed.count + failed.count + fatals.count + skipped.count == 0 {
+        if flaky.count > 0 {
+            console.print_warning(&format!("{} TESTS FLAKY", flaky.count))?;
+            for test_name in &flaky.example_tests {
+                console.print_warning(&format!("  ⚑ {}", test_name))?;
+            }
+            if flaky.count > flaky.max {
+                console.print_warning(&format!(
+                    "  ...and {} more not shown...",
+                    flaky.count - flaky.max
+                ))?;
+            }
+        }
+        if passed.count + failed.count + fatals.count + skipped.count + flaky.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         } else if !response.error_messages.is_empty() {
             console.print_error(&format!("{} BUILDS FAILED", response.error_messages.len()))?;
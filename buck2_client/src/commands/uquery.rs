@@ -27,6 +27,8 @@ enum QueryOutputFormatArg {
     Dot,
     Json,
     DotCompact,
+    Ndjson,
+    Proto,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -124,11 +126,13 @@ pub struct CommonQueryArgs {
         ignore_case = true,
         help = "Output format (default: list).",
         long_help = "Output format (default: list). \n
-           dot -  dot graph format. \n
+           dot -  dot graph format, with the attributes from `--output-attribute` in the node labels. \n
            dot_compact - compact alternative to dot format. \n
-           json - JSON format.
+           json - JSON format. \n
+           ndjson - JSON format with one target or file per line, written as the results are processed. \n
+           proto - length-delimited `buck.query_result.QueryResultEntry` protobuf messages, with the schema in `cli_proto/query_result.proto`.
          ",
-        value_name = "dot|dot_compact|json",
+        value_name = "dot|dot_compact|json|ndjson|proto",
         arg_enum
     )]
     output_format: Option<QueryOutputFormatArg>,
@@ -160,6 +164,8 @@ impl CommonQueryArgs {
             Some(QueryOutputFormatArg::Json) => QueryOutputFormat::Json,
             Some(QueryOutputFormatArg::Dot) => QueryOutputFormat::Dot,
             Some(QueryOutputFormatArg::DotCompact) => QueryOutputFormat::DotCompact,
+            Some(QueryOutputFormatArg::Ndjson) => QueryOutputFormat::Ndjson,
+            Some(QueryOutputFormatArg::Proto) => QueryOutputFormat::Proto,
             None => {
                 if self.json {
                     QueryOutputFormat::Json
@@ -32,6 +32,7 @@ use crate::dice::cells::HasCellResolver;
 use crate::dice::data::HasIoProvider;
 use crate::dice::file_ops::keys::FileOpsKey;
 use crate::dice::file_ops::keys::FileOpsValue;
+use crate::external_cells::materialize_external_cell;
 use crate::file_ops::DefaultFileOpsDelegate;
 use crate::file_ops::FileIgnoreResult;
 use crate::file_ops::FileIgnores;
@@ -267,6 +268,49 @@ impl FileChangeTracker {
     }
 }
 
+/// Materializes an external cell. This depends on the cell resolver, which has the pin of the
+/// cell, so changing the pin materializes the cell again.
+#[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "ExternalCell({})", _0)]
+struct ExternalCellKey(CellName);
+
+#[async_trait]
+impl Key for ExternalCellKey {
+    type Value = SharedResult<()>;
+    async fn compute(&self, ctx: &DiceComputations) -> Self::Value {
+        let cells = ctx.get_cell_resolver().await?;
+        if let Some(external) = cells.get(&self.0)?.external() {
+            let io = ctx.global_data().get_io_provider();
+            let path = io.project_root().resolve(external.materialization_path());
+            materialize_external_cell(&path, external.origin()).await?;
+        }
+        Ok(())
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        matches!((x, y), (Ok(()), Ok(())))
+    }
+
+    fn validity(x: &Self::Value) -> bool {
+        x.is_ok()
+    }
+}
+
+/// Makes sure that the sources of `cell` are on disk before we read them, which only needs doing
+/// for external cells.
+async fn ensure_cell_materialized(ctx: &DiceComputations, cell: &CellName) -> SharedResult<()> {
+    if ctx
+        .get_cell_resolver()
+        .await?
+        .get(cell)?
+        .external()
+        .is_some()
+    {
+        ctx.compute(&ExternalCellKey(cell.clone())).await??;
+    }
+    Ok(())
+}
+
 #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
 #[display(fmt = "{}", _0)]
 struct ReadFileKey(Arc<CellPath>);
@@ -291,6 +335,7 @@ struct ReadDirKey(CellPath);
 impl Key for ReadDirKey {
     type Value = SharedResult<ReadDirOutput>;
     async fn compute(&self, ctx: &DiceComputations) -> Self::Value {
+        ensure_cell_materialized(ctx, self.0.cell()).await?;
         get_default_file_ops(ctx)
             .await?
             .read_dir_with_ignores(&self.0)
@@ -317,6 +362,7 @@ struct PathMetadataKey(CellPath);
 impl Key for PathMetadataKey {
     type Value = SharedResult<Option<RawPathMetadata>>;
     async fn compute(&self, ctx: &DiceComputations) -> Self::Value {
+        ensure_cell_materialized(ctx, self.0.cell()).await?;
         let res = get_default_file_ops(ctx)
             .await?
             .read_path_metadata_if_exists(&self.0)
@@ -350,6 +396,7 @@ impl Key for PathMetadataKey {
 #[async_trait]
 impl<'c> FileOps for DiceFileOps<'c> {
     async fn read_file(&self, path: &CellPath) -> anyhow::Result<String> {
+        ensure_cell_materialized(self.0, path.cell()).await?;
         let file_ops = get_default_file_ops(self.0).await?;
 
         self.0
@@ -389,3 +436,98 @@ impl<'c> FileOps for DiceFileOps<'c> {
 pub mod testing {
     pub use super::keys::FileOpsKey;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::project::ProjectRelativePath;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use dice::cycles::DetectCycles;
+    use dice::Dice;
+    use dice::DiceTransaction;
+    use indoc::indoc;
+
+    use crate::dice::cells::HasCellResolver;
+    use crate::dice::data::testing::SetTestingIoProvider;
+    use crate::dice::file_ops::HasFileOps;
+    use crate::external_cells::testing::git_repo_with_commits;
+    use crate::file_ops::FileOps;
+    use crate::legacy_configs::cells::BuckConfigBasedCells;
+    use crate::legacy_configs::dice::HasLegacyConfigs;
+
+    /// Configures the project with the external cell `ext`, pinned to `commit` of `repo`.
+    fn set_external_cell(
+        dice: &Arc<Dice>,
+        fs: &ProjectRootTemp,
+        repo: &str,
+        commit: &str,
+    ) -> anyhow::Result<DiceTransaction> {
+        fs.path().write_file(
+            ProjectRelativePath::new(".buckconfig")?,
+            format!(
+                indoc!(
+                    r#"
+                    [repositories]
+                        root = .
+                    [external_cells]
+                        ext = git
+                    [external_cell_ext]
+                        git_origin = {}
+                        commit_hash = {}
+                    "#
+                ),
+                repo, commit
+            ),
+            false,
+        )?;
+        let cells =
+            BuckConfigBasedCells::parse(fs.path(), ProjectRelativePath::new("buck-out/v2")?)?;
+
+        let ctx = dice.ctx();
+        ctx.set_cell_resolver(cells.cell_resolver)?;
+        ctx.set_legacy_configs(cells.configs_by_name)?;
+        Ok(ctx.commit())
+    }
+
+    #[tokio::test]
+    async fn test_external_cell_materialized_on_read() -> anyhow::Result<()> {
+        if cfg!(windows) {
+            return Ok(());
+        }
+
+        let repo_dir = tempfile::tempdir()?;
+        let commits = git_repo_with_commits(repo_dir.path(), &["# v1", "# v2"])?;
+        let repo = repo_dir.path().display().to_string();
+
+        let fs = ProjectRootTemp::new()?;
+        let mut dice = Dice::builder();
+        dice.set_testing_io_provider(&fs);
+        let dice = dice.build(DetectCycles::Enabled);
+
+        let buck = CellPath::testing_new("ext", "BUCK");
+        let materialization_path = |commit: &str| {
+            fs.path()
+                .root()
+                .as_path()
+                .join("buck-out/v2/external_cells/ext")
+                .join(commit)
+        };
+
+        // The cell is only materialized once it is read from.
+        let ctx = set_external_cell(&dice, &fs, &repo, &commits[0])?;
+        assert!(!fs_util::try_exists(materialization_path(&commits[0]))?);
+        assert_eq!("# v1", ctx.file_ops().read_file(&buck).await?);
+        assert!(fs_util::try_exists(materialization_path(&commits[0]))?);
+        drop(ctx);
+
+        // Changing the pin materializes the cell again, at its new revision.
+        let ctx = set_external_cell(&dice, &fs, &repo, &commits[1])?;
+        assert_eq!("# v2", ctx.file_ops().read_file(&buck).await?);
+        assert!(fs_util::try_exists(materialization_path(&commits[1]))?);
+
+        Ok(())
+    }
+}
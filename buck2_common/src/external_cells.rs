@@ -0,0 +1,336 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Materialization of external cells, see `buck2_core::cells::external`.
+//!
+//! The sources of an external cell are written to a temporary directory next to their final
+//! location, made read-only, and then renamed into place. The final location is named after the
+//! pin of the cell, so once it exists it never needs to be written again, and concurrent writers
+//! agree on its contents.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::Context;
+use buck2_core::cells::external::ExternalCellOrigin;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use sha2::Digest;
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::process::Command;
+
+#[derive(Debug, Error)]
+enum ExternalCellError {
+    #[error("Archive `{0}` has sha256 `{1}`, but `{2}` was expected")]
+    Sha256Mismatch(String, String, String),
+    #[error("Archive `{0}` has no directory `{1}` to use as the root of the cell")]
+    MissingStripPrefix(String, String),
+    #[error("Command `{0}` failed: {1}")]
+    CommandFailed(String, String),
+}
+
+/// Materializes the sources of an external cell with `origin` at `path`, unless they are already
+/// there.
+pub async fn materialize_external_cell(
+    path: &AbsNormPath,
+    origin: &ExternalCellOrigin,
+) -> anyhow::Result<()> {
+    if fs_util::try_exists(path)? {
+        return Ok(());
+    }
+
+    let parent = path
+        .parent()
+        .with_context(|| format!("External cell path `{}` has no parent", path))?;
+    fs_util::create_dir_all(parent)?;
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("External cell path `{}` has no file name", path))?
+        .to_string_lossy();
+    let temp = parent.join(ForwardRelativePath::new(&format!(
+        "{}.{}.tmp",
+        file_name,
+        std::process::id()
+    ))?);
+    fs_util::remove_all(&temp)?;
+
+    let res = async {
+        match origin {
+            ExternalCellOrigin::Archive {
+                url,
+                sha256,
+                strip_prefix,
+            } => {
+                materialize_archive(&temp, url, sha256, strip_prefix.as_deref()).await?;
+            }
+            ExternalCellOrigin::Git { origin, commit } => {
+                materialize_git(&temp, origin, commit).await?;
+            }
+        }
+        make_read_only(&temp)?;
+        anyhow::Ok(())
+    }
+    .await
+    .with_context(|| format!("Error materializing external cell from {}", origin));
+
+    if let Err(e) = res {
+        fs_util::remove_all(&temp).ok();
+        return Err(e);
+    }
+
+    if let Err(e) = fs_util::rename(&temp, path) {
+        fs_util::remove_all(&temp).ok();
+        // Another process might have materialized the cell concurrently, which is fine since
+        // the contents are the same.
+        if !fs_util::try_exists(path)? {
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn materialize_archive(
+    dest: &AbsNormPathBuf,
+    url: &str,
+    sha256: &str,
+    strip_prefix: Option<&str>,
+) -> anyhow::Result<()> {
+    let data = match url.strip_prefix("file://") {
+        Some(path) => tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Error reading archive `{}`", path))?,
+        None => reqwest::get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Error downloading archive `{}`", url))?
+            .bytes()
+            .await
+            .with_context(|| format!("Error downloading archive `{}`", url))?
+            .to_vec(),
+    };
+
+    let actual = hex::encode(Sha256::digest(&data));
+    if actual != sha256 {
+        return Err(
+            ExternalCellError::Sha256Mismatch(url.to_owned(), actual, sha256.to_owned()).into(),
+        );
+    }
+
+    // We leave the choice of archive formats to `tar`, which detects them from their contents.
+    let archive = AbsNormPathBuf::try_from(format!("{}.archive", dest))?;
+    fs_util::write(&archive, &data)?;
+    fs_util::create_dir_all(dest)?;
+    let res = run(Command::new("tar")
+        .arg("-xf")
+        .arg(archive.as_path())
+        .arg("-C")
+        .arg(dest.as_path()))
+    .await;
+    fs_util::remove_file(&archive)?;
+    res?;
+
+    if let Some(prefix) = strip_prefix {
+        if !dest.as_path().join(prefix).is_dir() {
+            return Err(
+                ExternalCellError::MissingStripPrefix(url.to_owned(), prefix.to_owned()).into(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn materialize_git(dest: &AbsNormPath, origin: &str, commit: &str) -> anyhow::Result<()> {
+    run(Command::new("git")
+        .arg("clone")
+        .arg("--quiet")
+        .arg("--no-checkout")
+        .arg(origin)
+        .arg(dest.as_path()))
+    .await?;
+    run(Command::new("git")
+        .arg("-C")
+        .arg(dest.as_path())
+        .arg("checkout")
+        .arg("--quiet")
+        .arg("--detach")
+        .arg(commit))
+    .await?;
+    // The cell is the checkout, not the repository.
+    fs_util::remove_all(dest.as_path().join(".git"))?;
+    Ok(())
+}
+
+async fn run(command: &mut Command) -> anyhow::Result<()> {
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("Error running `{:?}`", command))?;
+    if !output.status.success() {
+        return Err(ExternalCellError::CommandFailed(
+            format!("{:?}", command),
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Makes the files under `path` read-only, so that the sources of external cells are not modified
+/// by accident. Directories are left writable, so that the cell can be deleted.
+fn make_read_only(path: &Path) -> anyhow::Result<()> {
+    let metadata = fs_util::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path)
+            .with_context(|| format!("Error reading directory `{}`", path.display()))?
+        {
+            make_read_only(&entry?.path())?;
+        }
+    } else if metadata.is_file() {
+        let mut permissions = metadata.permissions();
+        permissions.set_readonly(true);
+        fs_util::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::Path;
+    use std::process::Command;
+
+    use buck2_core::fs::fs_util;
+
+    /// Creates a git repository at `dir` with a commit for each of `contents`, which sets the
+    /// contents of the file `BUCK`. Returns the hashes of the commits.
+    pub(crate) fn git_repo_with_commits(
+        dir: &Path,
+        contents: &[&str],
+    ) -> anyhow::Result<Vec<String>> {
+        let git = |args: &[&str]| -> anyhow::Result<String> {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()?;
+            anyhow::ensure!(
+                output.status.success(),
+                "`git {}` failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+        };
+
+        fs_util::create_dir_all(dir)?;
+        git(&["init", "--quiet"])?;
+        let mut commits = Vec::new();
+        for contents in contents {
+            fs_util::write(dir.join("BUCK"), contents)?;
+            git(&["add", "BUCK"])?;
+            git(&["commit", "--quiet", "-m", "Update BUCK"])?;
+            commits.push(git(&["rev-parse", "HEAD"])?);
+        }
+        Ok(commits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_cells::testing::git_repo_with_commits;
+
+    #[tokio::test]
+    async fn test_materialize_archive() -> anyhow::Result<()> {
+        if cfg!(windows) {
+            return Ok(());
+        }
+
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsNormPathBuf::try_from(tempdir.path().to_owned())?;
+
+        // An archive with a single file, `lib-1.0/BUCK`.
+        let sources = root.join(ForwardRelativePath::new("sources")?);
+        fs_util::create_dir_all(sources.as_path().join("lib-1.0"))?;
+        fs_util::write(sources.as_path().join("lib-1.0/BUCK"), "# lib")?;
+        let archive = root.as_path().join("lib.tar");
+        run(Command::new("tar")
+            .arg("-cf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(sources.as_path())
+            .arg("lib-1.0"))
+        .await?;
+        let sha256 = hex::encode(Sha256::digest(&std::fs::read(&archive)?));
+        let url = format!("file://{}", archive.display());
+
+        let cell = root.join(ForwardRelativePath::new("cells/lib")?);
+        let bad = ExternalCellOrigin::Archive {
+            url: url.clone(),
+            sha256: "0".repeat(64),
+            strip_prefix: None,
+        };
+        assert!(materialize_external_cell(&cell, &bad).await.is_err());
+        assert!(!fs_util::try_exists(&cell)?);
+
+        let origin = ExternalCellOrigin::Archive {
+            url,
+            sha256,
+            strip_prefix: Some("lib-1.0".to_owned()),
+        };
+        materialize_external_cell(&cell, &origin).await?;
+        let buck = cell.as_path().join("lib-1.0/BUCK");
+        assert_eq!("# lib", fs_util::read_to_string(&buck)?);
+        assert!(fs_util::metadata(&buck)?.permissions().readonly());
+
+        // Materializing again is a no-op.
+        materialize_external_cell(&cell, &origin).await?;
+
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_materialize_git() -> anyhow::Result<()> {
+        if cfg!(windows) {
+            return Ok(());
+        }
+
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsNormPathBuf::try_from(tempdir.path().to_owned())?;
+        let repo = root.as_path().join("repo");
+        let commits = git_repo_with_commits(&repo, &["# v1", "# v2"])?;
+
+        let cell = root.join(ForwardRelativePath::new("cells/fmt")?);
+        let origin = ExternalCellOrigin::Git {
+            origin: repo.display().to_string(),
+            commit: commits[0].clone(),
+        };
+        materialize_external_cell(&cell, &origin).await?;
+        let buck = cell.as_path().join("BUCK");
+        assert_eq!("# v1", fs_util::read_to_string(&buck)?);
+        assert!(fs_util::metadata(&buck)?.permissions().readonly());
+        // The cell is the checkout, without the repository.
+        assert!(!fs_util::try_exists(cell.as_path().join(".git"))?);
+
+        let missing = root.join(ForwardRelativePath::new("cells/missing")?);
+        let bad = ExternalCellOrigin::Git {
+            origin: repo.display().to_string(),
+            commit: "0".repeat(40),
+        };
+        assert!(materialize_external_cell(&missing, &bad).await.is_err());
+        assert!(!fs_util::try_exists(&missing)?);
+
+        Ok(())
+    }
+}
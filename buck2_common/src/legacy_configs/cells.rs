@@ -11,6 +11,8 @@ use std::collections::HashMap;
 
 use anyhow::Context;
 use buck2_core::cells::cell_root_path::CellRootPathBuf;
+use buck2_core::cells::external::ExternalCell;
+use buck2_core::cells::external::ExternalCellOrigin;
 use buck2_core::cells::CellAlias;
 use buck2_core::cells::CellResolver;
 use buck2_core::cells::CellsAggregator;
@@ -19,9 +21,11 @@ use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::paths::RelativePath;
+use buck2_core::fs::project::ProjectRelativePath;
 use buck2_core::fs::project::ProjectRelativePathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use once_cell::unsync::OnceCell;
+use thiserror::Error;
 
 use crate::legacy_configs::path::BuckConfigFile;
 use crate::legacy_configs::path::DEFAULT_BUCK_CONFIG_FILES;
@@ -35,6 +39,16 @@ use crate::legacy_configs::LegacyBuckConfigs;
 use crate::legacy_configs::LegacyConfigCmdArg;
 use crate::legacy_configs::MainConfigFile;
 
+#[derive(Debug, Error)]
+enum ExternalCellConfigError {
+    #[error("Unknown kind `{1}` for external cell `{0}`, expected `archive` or `git`")]
+    UnknownKind(String, String),
+    #[error("External cell `{0}` is missing `{1}.{2}`")]
+    MissingKey(String, String, String),
+    #[error("Expected `{1}` of external cell `{0}` to be {2} hex digits, got `{3}`")]
+    InvalidPin(String, &'static str, usize, String),
+}
+
 /// Used for creating a CellResolver in a buckv1-compatible way based on values
 /// in .buckconfig in each cell.
 ///
@@ -70,8 +84,12 @@ impl BuckConfigBasedCells {
             // the argfile, so we must have parsed all cells.
             parse_cells: true,
         };
+        // The immediate mapping is only used to resolve paths in argfiles and config includes,
+        // which can't refer to external cells since those aren't materialized yet, so we skip
+        // external cells and don't need a buck-out directory to materialize them in.
         let cells = Self::parse_with_file_ops_and_options(
             project_fs,
+            None,
             file_ops,
             &[],
             project_fs.root(),
@@ -80,9 +98,14 @@ impl BuckConfigBasedCells {
         Ok(cells.cell_resolver)
     }
 
-    pub fn parse(project_fs: &ProjectRoot) -> anyhow::Result<Self> {
+    /// Parses the cells. External cells are materialized in `buck_out_dir`.
+    pub fn parse(
+        project_fs: &ProjectRoot,
+        buck_out_dir: &ProjectRelativePath,
+    ) -> anyhow::Result<Self> {
         Self::parse_with_file_ops(
             project_fs,
+            buck_out_dir,
             &DefaultConfigParserFileOps {},
             &[],
             project_fs.root(),
@@ -91,14 +114,22 @@ impl BuckConfigBasedCells {
 
     pub fn parse_with_config_args(
         project_fs: &ProjectRoot,
+        buck_out_dir: &ProjectRelativePath,
         config_args: &[LegacyConfigCmdArg],
         cwd: &AbsNormPath,
     ) -> anyhow::Result<Self> {
-        Self::parse_with_file_ops(project_fs, &DefaultConfigParserFileOps {}, config_args, cwd)
+        Self::parse_with_file_ops(
+            project_fs,
+            buck_out_dir,
+            &DefaultConfigParserFileOps {},
+            config_args,
+            cwd,
+        )
     }
 
     pub fn parse_with_file_ops(
         project_fs: &ProjectRoot,
+        buck_out_dir: &ProjectRelativePath,
         file_ops: &dyn ConfigParserFileOps,
         config_args: &[LegacyConfigCmdArg],
         cwd: &AbsNormPath,
@@ -107,11 +138,19 @@ impl BuckConfigBasedCells {
             follow_includes: true,
             parse_cells: true,
         };
-        Self::parse_with_file_ops_and_options(project_fs, file_ops, config_args, cwd, opts)
+        Self::parse_with_file_ops_and_options(
+            project_fs,
+            Some(buck_out_dir),
+            file_ops,
+            config_args,
+            cwd,
+            opts,
+        )
     }
 
     fn parse_with_file_ops_and_options(
         project_fs: &ProjectRoot,
+        buck_out_dir: Option<&ProjectRelativePath>,
         file_ops: &dyn ConfigParserFileOps,
         config_args: &[LegacyConfigCmdArg],
         cwd: &AbsNormPath,
@@ -257,6 +296,26 @@ impl BuckConfigBasedCells {
                 }
             }
 
+            // External cells can only be declared by the root cell, like aliases they are
+            // available in all cells.
+            if let (Some(buck_out_dir), "") = (buck_out_dir, path.as_str()) {
+                for (alias, origin) in Self::parse_external_cells(&config)? {
+                    let external = ExternalCell::new(&alias, origin, buck_out_dir)?;
+                    let alias_path = CellRootPathBuf::new(external.cell_root()?);
+                    let alias = CellAlias::new(alias);
+                    root_aliases.insert(alias.clone(), alias_path.clone());
+                    cells_aggregator.add_cell_alias_entry(
+                        path.clone(),
+                        alias,
+                        alias_path.clone(),
+                    )?;
+                    cells_aggregator.set_external(alias_path.clone(), external);
+                    // External cells are only materialized once they are read from, so we can't
+                    // read their buckconfigs: they use the defaults.
+                    buckconfigs.insert(alias_path, LegacyBuckConfig::empty());
+                }
+            }
+
             if let Some(buildfiles) = Self::parse_buildfile_name(&config)? {
                 cells_aggregator.set_buildfiles(path.clone(), buildfiles);
             }
@@ -291,6 +350,81 @@ impl BuckConfigBasedCells {
         })
     }
 
+    /// Deal with the `[external_cells]` section. It maps the name of each external cell to the kind
+    /// of its origin, which is configured in the section `[external_cell_<name>]`:
+    ///
+    /// ```text
+    /// [external_cells]
+    ///     zlib = archive
+    ///     fmt = git
+    /// [external_cell_zlib]
+    ///     url = https://example.com/zlib-1.2.13.tar.gz
+    ///     sha256 = <sha256 of the archive>
+    ///     strip_prefix = zlib-1.2.13
+    /// [external_cell_fmt]
+    ///     git_origin = /path/to/fmt
+    ///     commit_hash = <full commit hash>
+    /// ```
+    fn parse_external_cells(
+        config: &LegacyBuckConfig,
+    ) -> anyhow::Result<Vec<(String, ExternalCellOrigin)>> {
+        let cells = match config.get_section("external_cells") {
+            Some(cells) => cells,
+            None => return Ok(Vec::new()),
+        };
+
+        cells
+            .iter()
+            .map(|(name, kind)| {
+                let section = format!("external_cell_{}", name);
+                let get = |key: &str| {
+                    config
+                        .get(&section, key)
+                        .map(ToOwned::to_owned)
+                        .ok_or_else(|| {
+                            ExternalCellConfigError::MissingKey(
+                                name.to_owned(),
+                                section.clone(),
+                                key.to_owned(),
+                            )
+                        })
+                };
+                let pin = |key: &'static str, len: usize| {
+                    let value = get(key)?;
+                    if value.len() != len || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(ExternalCellConfigError::InvalidPin(
+                            name.to_owned(),
+                            key,
+                            len,
+                            value,
+                        ));
+                    }
+                    Ok(value.to_ascii_lowercase())
+                };
+
+                let origin = match kind.as_str() {
+                    "archive" => ExternalCellOrigin::Archive {
+                        url: get("url")?,
+                        sha256: pin("sha256", 64)?,
+                        strip_prefix: config.get(&section, "strip_prefix").map(ToOwned::to_owned),
+                    },
+                    "git" => ExternalCellOrigin::Git {
+                        origin: get("git_origin")?,
+                        commit: pin("commit_hash", 40)?,
+                    },
+                    kind => {
+                        return Err(ExternalCellConfigError::UnknownKind(
+                            name.to_owned(),
+                            kind.to_owned(),
+                        )
+                        .into());
+                    }
+                };
+                Ok((name.to_owned(), origin))
+            })
+            .collect()
+    }
+
     /// Deal with the `buildfile.name` key (and `name_v2`)
     fn parse_buildfile_name(config: &LegacyBuckConfig) -> anyhow::Result<Option<Vec<FileNameBuf>>> {
         fn parse_list(val: &str) -> impl Iterator<Item = &str> {
@@ -326,9 +460,11 @@ impl BuckConfigBasedCells {
 
 #[cfg(test)]
 mod tests {
+    use buck2_core::cells::external::ExternalCellOrigin;
     use buck2_core::cells::CellAlias;
     use buck2_core::cells::CellName;
     use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::project::ProjectRelativePath;
     use buck2_core::fs::project::ProjectRoot;
     use gazebo::prelude::*;
     use indoc::indoc;
@@ -391,6 +527,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
             &file_ops,
             &[],
             project_fs.root(),
@@ -493,6 +630,7 @@ mod tests {
         let file_arg = "C:/other/cli-conf".to_owned();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
             &file_ops,
             &[LegacyConfigCmdArg::UnresolvedFile(file_arg)],
             project_fs.root(),
@@ -543,6 +681,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
             &file_ops,
             &[],
             project_fs.root(),
@@ -607,6 +746,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
             &file_ops,
             &[
                 LegacyConfigCmdArg::UnresolvedFile("other//app-conf".to_owned()),
@@ -626,6 +766,134 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_external_cells() -> anyhow::Result<()> {
+        let file_ops = TestConfigParserFileOps::new(&[(
+            "/.buckconfig",
+            indoc!(
+                r#"
+                            [repositories]
+                                root = .
+                            [external_cells]
+                                zlib = archive
+                                fmt = git
+                            [external_cell_zlib]
+                                url = https://example.com/zlib-1.2.13.tar.gz
+                                sha256 = ABABABABABABABABABABABABABABABABABABABABABABABABABABABABABABABAB
+                                strip_prefix = zlib-1.2.13
+                            [external_cell_fmt]
+                                git_origin = /repos/fmt
+                                commit_hash = cccccccccccccccccccccccccccccccccccccccc
+                        "#
+            ),
+        )])?;
+
+        let project_fs = create_project_filesystem();
+        let cells = BuckConfigBasedCells::parse_with_file_ops(
+            &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
+            &file_ops,
+            &[],
+            project_fs.root(),
+        )?;
+        let resolver = &cells.cell_resolver;
+
+        let zlib = resolver.get(&CellName::unchecked_new("zlib".to_owned()))?;
+        assert_eq!(
+            format!(
+                "buck-out/v2/external_cells/zlib/{}/zlib-1.2.13",
+                "ab".repeat(32)
+            ),
+            zlib.path().as_str()
+        );
+        assert_eq!(
+            Some(&ExternalCellOrigin::Archive {
+                url: "https://example.com/zlib-1.2.13.tar.gz".to_owned(),
+                sha256: "ab".repeat(32),
+                strip_prefix: Some("zlib-1.2.13".to_owned()),
+            }),
+            zlib.external().map(|external| external.origin())
+        );
+        // External cells see the aliases of the root cell.
+        assert_eq!(
+            "fmt",
+            zlib.cell_alias_resolver()
+                .resolve(&CellAlias::new("fmt".to_owned()))?
+                .as_str()
+        );
+
+        let fmt = resolver.get(&CellName::unchecked_new("fmt".to_owned()))?;
+        assert_eq!(
+            format!("buck-out/v2/external_cells/fmt/{}", "c".repeat(40)),
+            fmt.path().as_str()
+        );
+        assert!(
+            resolver
+                .get(&CellName::unchecked_new("root".to_owned()))?
+                .external()
+                .is_none()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_cells_invalid() -> anyhow::Result<()> {
+        let parse = |config: &str| {
+            let file_ops = TestConfigParserFileOps::new(&[("/.buckconfig", config)])?;
+            let project_fs = create_project_filesystem();
+            BuckConfigBasedCells::parse_with_file_ops(
+                &project_fs,
+                ProjectRelativePath::unchecked_new("buck-out/v2"),
+                &file_ops,
+                &[],
+                project_fs.root(),
+            )
+        };
+
+        assert!(
+            parse(indoc!(
+                r#"
+                    [repositories]
+                        root = .
+                    [external_cells]
+                        fmt = svn
+                "#
+            ))
+            .is_err()
+        );
+        assert!(
+            parse(indoc!(
+                r#"
+                    [repositories]
+                        root = .
+                    [external_cells]
+                        fmt = git
+                    [external_cell_fmt]
+                        git_origin = /repos/fmt
+                        commit_hash = main
+                "#
+            ))
+            .is_err()
+        );
+        assert!(
+            parse(indoc!(
+                r#"
+                    [repositories]
+                        root = .
+                    [external_cells]
+                        fmt = git
+                    [external_cell_fmt]
+                        git_origin = /repos/fmt
+                        commit_hash = cccccccccccccccccccccccccccccccccccccccc
+                "#
+            ))
+            .is_ok()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_local_config_file_overwrite_config_file() -> anyhow::Result<()> {
         let file_ops = TestConfigParserFileOps::new(&[
@@ -658,6 +926,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
             &file_ops,
             &[],
             project_fs.root(),
@@ -737,6 +1006,7 @@ mod tests {
         let project_fs = create_project_filesystem();
         let cells = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
             &file_ops,
             &[],
             project_fs.root(),
@@ -33,6 +33,7 @@ pub mod dice;
 #[cfg(any(fbcode_build, cargo_internal_build))]
 pub mod eden;
 pub mod executor_config;
+pub mod external_cells;
 pub mod external_symlink;
 pub mod file_ops;
 pub mod find_buildfile;
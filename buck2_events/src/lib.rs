@@ -250,6 +250,16 @@ pub trait EventSink: Send + Sync {
     fn send_control(&self, control_event: ControlEvent);
 }
 
+impl<S: EventSink + ?Sized> EventSink for Box<S> {
+    fn send(&self, event: BuckEvent) {
+        (**self).send(event)
+    }
+
+    fn send_control(&self, control_event: ControlEvent) {
+        (**self).send_control(control_event)
+    }
+}
+
 /// A source for events, suitable for reading a stream of events coming out of Buck.
 #[async_trait]
 pub trait EventSource: Send {
@@ -9,7 +9,10 @@
 
 //! Implementations of `[crate::EventSink]` that are useful in different situations. Buck2 primarily uses the `channel`
 //! sink during normal operation.
+pub mod bep;
 pub(crate) mod channel;
 pub(crate) mod null;
+pub mod otel;
+pub(crate) mod remote;
 pub mod scribe;
 pub mod tee;
@@ -0,0 +1,465 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A Sink that publishes events to a build event service, such as BuildBuddy or Buildbarn's
+//! bb-portal, using Bazel's Build Event Protocol (BEP).
+
+mod translate;
+mod upload;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::Context as _;
+use buck2_bep_proto::google_devtools_build_v1::stream_id::BuildComponent;
+use buck2_bep_proto::google_devtools_build_v1::StreamId;
+use futures::FutureExt;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::sink::bep::translate::BepTranslator;
+use crate::sink::bep::upload::Connect;
+use crate::sink::bep::upload::HeadersInterceptor;
+use crate::sink::bep::upload::QueuedEvent;
+use crate::sink::bep::upload::Uploader;
+use crate::sink::remote::grpc_endpoint;
+use crate::sink::remote::grpc_headers;
+use crate::sink::remote::queue;
+use crate::sink::remote::QueueSender;
+use crate::BuckEvent;
+use crate::ControlEvent;
+use crate::EventSink;
+use crate::TraceId;
+
+#[derive(Error, Debug)]
+enum BepSinkError {
+    #[error("Invalid build event service endpoint `{0}`, expected `grpc://` or `grpcs://`")]
+    InvalidEndpoint(String),
+}
+
+/// Configuration for publishing events to a build event service.
+#[derive(Clone, Debug)]
+pub struct BepSinkConfig {
+    /// The address of the service, as `grpc://host:port` or `grpcs://host:port`.
+    pub endpoint: String,
+    /// Sent with every event, for services that require it.
+    pub project_id: String,
+    /// Headers sent when opening a stream, e.g. API keys.
+    pub headers: Vec<(String, String)>,
+    /// How many events can wait to be published. When this is reached, further events are dropped,
+    /// except for the last one.
+    pub buffer_size: usize,
+    /// How many events can be published and not acknowledged by the service yet.
+    pub max_in_flight: usize,
+    /// How many times to retry publishing after an error, without the service acknowledging any
+    /// event in between.
+    pub max_retries: u32,
+    /// Whether to publish all actions, rather than only the failed ones.
+    pub publish_all_actions: bool,
+}
+
+impl BepSinkConfig {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            project_id: String::new(),
+            headers: Vec::new(),
+            buffer_size: 10000,
+            max_in_flight: 1000,
+            max_retries: 4,
+            publish_all_actions: false,
+        }
+    }
+}
+
+/// An EventSink that translates the events of a command into BEP events and publishes them. Events
+/// are published in the background: if the service can't keep up, events are queued up to the
+/// configured buffer size, and then dropped.
+pub struct BepSink {
+    translator: Mutex<BepTranslator>,
+    queue: QueueSender<QueuedEvent>,
+}
+
+impl BepSink {
+    /// Creates a BepSink publishing the events of the command identified by `trace_id`. This must be
+    /// called from within a Tokio runtime, which runs the publishing.
+    pub fn new(config: BepSinkConfig, trace_id: &TraceId) -> anyhow::Result<BepSink> {
+        let connect = connect_to(&config.endpoint)?;
+        Self::with_connect(config, trace_id, connect)
+    }
+
+    fn with_connect(
+        config: BepSinkConfig,
+        trace_id: &TraceId,
+        connect: Connect,
+    ) -> anyhow::Result<BepSink> {
+        let (queue, queue_rx) = queue(config.buffer_size, "Build event service", "build events");
+
+        let uploader = Uploader {
+            connect,
+            headers: HeadersInterceptor {
+                headers: Arc::new(grpc_headers(&config.headers)?),
+            },
+            stream_id: StreamId {
+                build_id: Uuid::new_v4().to_string(),
+                invocation_id: trace_id.to_string(),
+                component: BuildComponent::Tool as i32,
+            },
+            project_id: config.project_id,
+            max_in_flight: config.max_in_flight.max(1),
+            max_retries: config.max_retries,
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = uploader.run(queue_rx).await {
+                tracing::warn!("Error publishing build events: {:#}", e);
+            }
+        });
+
+        Ok(BepSink {
+            translator: Mutex::new(BepTranslator::new(config.publish_all_actions)),
+            queue,
+        })
+    }
+}
+
+impl EventSink for BepSink {
+    fn send(&self, event: BuckEvent) {
+        // Translate and enqueue while holding the lock, so that events are published in the order
+        // they were translated.
+        let mut translator = self.translator.lock().unwrap();
+
+        for bep_event in translator.translate(&event) {
+            let last = bep_event.last_message;
+            let queued = QueuedEvent {
+                time: event.timestamp(),
+                event: bep_event,
+            };
+            // We always keep the last event, so that the service knows the build is over.
+            if last {
+                self.queue.force_send(queued);
+            } else {
+                self.queue.send(queued);
+            }
+        }
+    }
+
+    fn send_control(&self, _control_event: ControlEvent) {}
+}
+
+fn connect_to(endpoint: &str) -> anyhow::Result<Connect> {
+    let endpoint = grpc_endpoint(endpoint)?
+        .ok_or_else(|| BepSinkError::InvalidEndpoint(endpoint.to_owned()))?;
+
+    Ok(Arc::new(move || {
+        let endpoint = endpoint.clone();
+        async move {
+            endpoint
+                .connect()
+                .await
+                .context("Error connecting to the build event service")
+        }
+        .boxed()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::pin::Pin;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use buck2_bep_proto::build_event_stream as bes;
+    use buck2_bep_proto::google_devtools_build_v1::build_event::Event;
+    use buck2_bep_proto::google_devtools_build_v1::publish_build_event_server::PublishBuildEvent;
+    use buck2_bep_proto::google_devtools_build_v1::publish_build_event_server::PublishBuildEventServer;
+    use buck2_bep_proto::google_devtools_build_v1::OrderedBuildEvent;
+    use buck2_bep_proto::google_devtools_build_v1::PublishBuildToolEventStreamRequest;
+    use buck2_bep_proto::google_devtools_build_v1::PublishBuildToolEventStreamResponse;
+    use buck2_grpc::DuplexChannel;
+    use buck2_grpc::ServerHandle;
+    use futures::Stream;
+    use futures::StreamExt;
+    use gazebo::prelude::*;
+    use prost::Message;
+    use tonic::transport::Server;
+    use tonic::Request;
+    use tonic::Response;
+    use tonic::Status;
+    use tonic::Streaming;
+
+    use super::*;
+
+    type ResponseStream =
+        Pin<Box<dyn Stream<Item = Result<PublishBuildToolEventStreamResponse, Status>> + Send>>;
+
+    /// A build event service that records the events it receives, deduplicated by sequence
+    /// number.
+    #[derive(Clone, Default)]
+    struct FakeBuildEventService {
+        events: Arc<Mutex<Vec<OrderedBuildEvent>>>,
+        api_keys: Arc<Mutex<Vec<String>>>,
+        /// Fail the first stream when receiving this sequence number.
+        fail_at: Arc<Mutex<Option<i64>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl PublishBuildEvent for FakeBuildEventService {
+        type PublishBuildToolEventStreamStream = ResponseStream;
+
+        async fn publish_build_tool_event_stream(
+            &self,
+            request: Request<Streaming<PublishBuildToolEventStreamRequest>>,
+        ) -> Result<Response<ResponseStream>, Status> {
+            if let Some(key) = request.metadata().get("x-api-key") {
+                self.api_keys
+                    .lock()
+                    .unwrap()
+                    .push(key.to_str().unwrap().to_owned());
+            }
+
+            let mut requests = request.into_inner();
+            let service = self.clone();
+            let (responses, responses_rx) = futures::channel::mpsc::unbounded();
+
+            tokio::spawn(async move {
+                while let Some(Ok(request)) = requests.next().await {
+                    let event = request.ordered_build_event.unwrap();
+                    let sequence_number = event.sequence_number;
+
+                    {
+                        let mut fail_at = service.fail_at.lock().unwrap();
+                        if *fail_at == Some(sequence_number) {
+                            *fail_at = None;
+                            let _ = responses.unbounded_send(Err(Status::unavailable("Failing")));
+                            return;
+                        }
+                    }
+
+                    {
+                        let mut events = service.events.lock().unwrap();
+                        if events
+                            .last()
+                            .map_or(true, |e| e.sequence_number < sequence_number)
+                        {
+                            events.push(event);
+                        }
+                    }
+
+                    let _ = responses.unbounded_send(Ok(PublishBuildToolEventStreamResponse {
+                        stream_id: None,
+                        sequence_number,
+                    }));
+                }
+            });
+
+            Ok(Response::new(responses_rx.boxed()))
+        }
+    }
+
+    /// Connects to `service` in-process. Every connection gets its own server.
+    fn connect_in_process(
+        service: FakeBuildEventService,
+        servers: Arc<Mutex<Vec<ServerHandle>>>,
+    ) -> Connect {
+        Arc::new(move || {
+            let service = service.clone();
+            let servers = servers.clone();
+            async move {
+                let (client_io, server_io) = tokio::io::duplex(4096);
+                let (read, write) = tokio::io::split(server_io);
+                let router = Server::builder().add_service(PublishBuildEventServer::new(service));
+                servers.lock().unwrap().push(buck2_grpc::spawn_oneshot(
+                    DuplexChannel::new(read, write),
+                    router,
+                ));
+                buck2_grpc::make_channel(client_io, "bes").await
+            }
+            .boxed()
+        })
+    }
+
+    fn command_events(trace_id: &TraceId) -> Vec<BuckEvent> {
+        let event = |data: buck2_data::buck_event::Data| {
+            BuckEvent::new(SystemTime::now(), trace_id.dupe(), None, None, data)
+        };
+        vec![
+            event(
+                buck2_data::SpanStartEvent {
+                    data: Some(
+                        buck2_data::CommandStart {
+                            metadata: HashMap::new(),
+                            data: Some(buck2_data::BuildCommandStart {}.into()),
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            event(
+                buck2_data::InstantEvent {
+                    data: Some(
+                        buck2_data::TestResult {
+                            name: "test".to_owned(),
+                            status: buck2_data::TestStatus::Pass as i32,
+                            ..Default::default()
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            event(
+                buck2_data::SpanEndEvent {
+                    data: Some(
+                        buck2_data::CommandEnd {
+                            metadata: HashMap::new(),
+                            data: Some(buck2_data::BuildCommandEnd::default().into()),
+                            is_success: true,
+                            error_messages: Vec::new(),
+                        }
+                        .into(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        ]
+    }
+
+    /// Waits until `service` received the last event of the stream, and returns the BEP events it
+    /// received.
+    async fn wait_for_stream(service: &FakeBuildEventService) -> Vec<bes::BuildEvent> {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let finished = service.events.lock().unwrap().iter().any(|e| {
+                    matches!(
+                        e.event.as_ref().and_then(|e| e.event.as_ref()),
+                        Some(Event::ComponentStreamFinished(..))
+                    )
+                });
+                if finished {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Timed out waiting for build events");
+
+        let events = service.events.lock().unwrap();
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(event.sequence_number, i as i64 + 1);
+        }
+        events
+            .iter()
+            .filter_map(|e| match e.event.as_ref()?.event.as_ref()? {
+                Event::BazelEvent(any) => {
+                    assert_eq!(any.type_url, bes::BUILD_EVENT_TYPE_URL);
+                    Some(bes::BuildEvent::decode(&*any.value).unwrap())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_publish() -> anyhow::Result<()> {
+        let service = FakeBuildEventService::default();
+        let servers = Arc::new(Mutex::new(Vec::new()));
+        let trace_id = TraceId::new();
+
+        let mut config = BepSinkConfig::new("grpc://unused".to_owned());
+        config.headers = vec![("x-api-key".to_owned(), "secret".to_owned())];
+        let sink = BepSink::with_connect(
+            config,
+            &trace_id,
+            connect_in_process(service.clone(), servers.dupe()),
+        )?;
+        for event in command_events(&trace_id) {
+            sink.send(event);
+        }
+
+        let events = wait_for_stream(&service).await;
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            events[0].payload,
+            Some(bes::build_event::Payload::Started(..))
+        ));
+        assert!(events[2].last_message);
+        assert_eq!(*service.api_keys.lock().unwrap(), vec!["secret".to_owned()]);
+        assert_eq!(
+            service.events.lock().unwrap()[0]
+                .stream_id
+                .as_ref()
+                .unwrap()
+                .invocation_id,
+            trace_id.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry() -> anyhow::Result<()> {
+        let service = FakeBuildEventService::default();
+        *service.fail_at.lock().unwrap() = Some(2);
+        let servers = Arc::new(Mutex::new(Vec::new()));
+        let trace_id = TraceId::new();
+
+        let sink = BepSink::with_connect(
+            BepSinkConfig::new("grpc://unused".to_owned()),
+            &trace_id,
+            connect_in_process(service.clone(), servers.dupe()),
+        )?;
+        for event in command_events(&trace_id) {
+            sink.send(event);
+        }
+
+        // The events are all received once, in order.
+        let events = wait_for_stream(&service).await;
+        assert_eq!(events.len(), 3);
+        assert_eq!(servers.lock().unwrap().len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_buffer_size() -> anyhow::Result<()> {
+        let service = FakeBuildEventService::default();
+        let servers = Arc::new(Mutex::new(Vec::new()));
+        let trace_id = TraceId::new();
+
+        let mut config = BepSinkConfig::new("grpc://unused".to_owned());
+        config.buffer_size = 1;
+        let sink = BepSink::with_connect(
+            config,
+            &trace_id,
+            connect_in_process(service.clone(), servers.dupe()),
+        )?;
+        // Nothing is taken from the queue in between, since we don't yield.
+        for event in command_events(&trace_id) {
+            sink.send(event);
+        }
+
+        // The test result is dropped, but the last event isn't.
+        let events = wait_for_stream(&service).await;
+        assert_eq!(events.len(), 2);
+        assert!(events[1].last_message);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_connect_to() {
+        assert!(connect_to("grpc://localhost:1985").is_ok());
+        assert!(connect_to("localhost:1985").is_err());
+    }
+}
@@ -0,0 +1,687 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Translation of Buck2 events into Build Event Protocol events.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use buck2_bep_proto::build_event_stream as bes;
+use buck2_core::truncate::truncate;
+
+use crate::BuckEvent;
+
+/// Stderr of actions is inlined in the events, so we limit its size.
+const MAX_STDERR_LENGTH: usize = 64 * 1024;
+
+/// A target and its configuration, as reported in the BEP.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct TargetKey {
+    label: String,
+    configuration: String,
+}
+
+impl TargetKey {
+    fn new(target: &buck2_data::ConfiguredTargetLabel) -> Option<Self> {
+        let label = target.label.as_ref()?;
+        Some(Self {
+            label: format!("{}:{}", label.package, label.name),
+            configuration: target
+                .configuration
+                .as_ref()
+                .map_or_else(String::new, |c| c.full_name.clone()),
+        })
+    }
+
+    fn configuration_id(&self) -> bes::build_event_id::ConfigurationId {
+        bes::build_event_id::ConfigurationId {
+            id: self.configuration.clone(),
+        }
+    }
+}
+
+/// What we know about a target that we'll report as complete at the end of the command.
+#[derive(Default)]
+struct TargetState {
+    failed: bool,
+    outputs: Vec<String>,
+}
+
+/// Turns the events of a command into BEP events. Buck2 doesn't report when a requested target is
+/// done, so targets are reported as complete (or failed) when the command ends.
+pub(crate) struct BepTranslator {
+    /// Whether to publish all actions, rather than only the failed ones, like Bazel's
+    /// `--build_event_publish_all_actions`.
+    publish_all_actions: bool,
+    /// The rule of every target that was analyzed.
+    kinds: HashMap<TargetKey, String>,
+    /// The targets we'll report as complete: those whose outputs were requested, and those with
+    /// failed actions.
+    targets: BTreeMap<TargetKey, TargetState>,
+    finished: bool,
+}
+
+impl BepTranslator {
+    pub(crate) fn new(publish_all_actions: bool) -> Self {
+        Self {
+            publish_all_actions,
+            kinds: HashMap::new(),
+            targets: BTreeMap::new(),
+            finished: false,
+        }
+    }
+
+    /// The BEP events corresponding to this event, if any. The last of them is marked as
+    /// `last_message`.
+    pub(crate) fn translate(&mut self, event: &BuckEvent) -> Vec<bes::BuildEvent> {
+        use buck2_data::buck_event::Data;
+
+        if self.finished {
+            return Vec::new();
+        }
+
+        match event.data() {
+            Data::SpanStart(start) => match &start.data {
+                Some(buck2_data::span_start_event::Data::Command(command)) => {
+                    vec![build_started(event, command)]
+                }
+                _ => Vec::new(),
+            },
+            Data::SpanEnd(end) => {
+                use buck2_data::span_end_event::Data;
+
+                match &end.data {
+                    Some(Data::Command(command)) => self.build_finished(event, command),
+                    Some(Data::Analysis(analysis)) => {
+                        if let Some(target) = analysis.target.as_ref().and_then(TargetKey::new) {
+                            self.kinds.insert(target, analysis.rule.clone());
+                        }
+                        Vec::new()
+                    }
+                    Some(Data::ActionExecution(action)) => self
+                        .action_executed(event, end, action)
+                        .into_iter()
+                        .collect(),
+                    Some(Data::FinalMaterialization(materialization)) => {
+                        self.record_output(materialization);
+                        Vec::new()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            Data::Instant(instant) => match &instant.data {
+                Some(buck2_data::instant_event::Data::TestResult(result)) => {
+                    vec![test_result(event, result)]
+                }
+                _ => Vec::new(),
+            },
+            Data::Record(_) => Vec::new(),
+        }
+    }
+
+    fn record_output(&mut self, materialization: &buck2_data::MaterializeRequestedArtifactEnd) {
+        let artifact = match &materialization.artifact {
+            Some(artifact) => artifact,
+            None => return,
+        };
+        if let Some(target) = artifact.key.as_ref().and_then(owner) {
+            self.targets
+                .entry(target)
+                .or_default()
+                .outputs
+                .push(artifact.path.clone());
+        }
+    }
+
+    fn action_executed(
+        &mut self,
+        event: &BuckEvent,
+        end: &buck2_data::SpanEndEvent,
+        action: &buck2_data::ActionExecutionEnd,
+    ) -> Option<bes::BuildEvent> {
+        let target = action.key.as_ref().and_then(owner);
+
+        if action.failed {
+            if let Some(target) = &target {
+                self.targets.entry(target.clone()).or_default().failed = true;
+            }
+        } else if !self.publish_all_actions {
+            return None;
+        }
+
+        // Buck2 doesn't report the paths of the outputs of actions, so we identify them by their
+        // category and identifier instead.
+        let name = action.name.as_ref().map_or_else(String::new, |name| {
+            if name.identifier.is_empty() {
+                name.category.clone()
+            } else {
+                format!("{} {}", name.category, name.identifier)
+            }
+        });
+
+        let details = action
+            .commands
+            .last()
+            .and_then(|command| command.details.as_ref());
+
+        let command_line = match details.and_then(|d| d.command.as_ref()) {
+            Some(buck2_data::command_execution_details::Command::LocalCommand(command)) => {
+                command.argv.clone()
+            }
+            _ => Vec::new(),
+        };
+
+        let stderr = details.filter(|d| !d.stderr.is_empty()).map(|d| bes::File {
+            name: "stderr".to_owned(),
+            file: Some(bes::file::File::Contents(
+                truncate(&d.stderr, MAX_STDERR_LENGTH).into_bytes(),
+            )),
+            ..Default::default()
+        });
+
+        let end_time = event.timestamp();
+        let start_time = end
+            .duration
+            .clone()
+            .and_then(|d| std::time::Duration::try_from(d).ok())
+            .and_then(|d| end_time.checked_sub(d));
+
+        Some(bes::BuildEvent {
+            id: Some(bes::BuildEventId {
+                id: Some(bes::build_event_id::Id::ActionCompleted(
+                    bes::build_event_id::ActionCompletedId {
+                        primary_output: name,
+                        label: target
+                            .as_ref()
+                            .map_or_else(String::new, |t| t.label.clone()),
+                        configuration: target.as_ref().map(|t| t.configuration_id()),
+                    },
+                )),
+            }),
+            payload: Some(bes::build_event::Payload::Action(bes::ActionExecuted {
+                success: !action.failed,
+                r#type: action
+                    .name
+                    .as_ref()
+                    .map_or_else(String::new, |n| n.category.clone()),
+                exit_code: details.map_or(0, |d| d.exit_code as i32),
+                stderr,
+                command_line,
+                start_time: start_time.map(Into::into),
+                end_time: Some(end_time.into()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        })
+    }
+
+    fn build_finished(
+        &mut self,
+        event: &BuckEvent,
+        command: &buck2_data::CommandEnd,
+    ) -> Vec<bes::BuildEvent> {
+        let mut events = Vec::new();
+
+        for (index, (target, state)) in std::mem::take(&mut self.targets).into_iter().enumerate() {
+            events.push(bes::BuildEvent {
+                id: Some(target_configured_id(&target)),
+                children: vec![target_completed_id(&target)],
+                payload: Some(bes::build_event::Payload::Configured(
+                    bes::TargetConfigured {
+                        target_kind: self.kinds.get(&target).cloned().unwrap_or_default(),
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            });
+
+            let mut output_group = Vec::new();
+            if !state.outputs.is_empty() {
+                let named_set = bes::build_event_id::NamedSetOfFilesId {
+                    id: index.to_string(),
+                };
+                events.push(bes::BuildEvent {
+                    id: Some(bes::BuildEventId {
+                        id: Some(bes::build_event_id::Id::NamedSet(named_set.clone())),
+                    }),
+                    payload: Some(bes::build_event::Payload::NamedSetOfFiles(
+                        bes::NamedSetOfFiles {
+                            files: state
+                                .outputs
+                                .into_iter()
+                                .map(|path| bes::File {
+                                    name: path,
+                                    ..Default::default()
+                                })
+                                .collect(),
+                            file_sets: Vec::new(),
+                        },
+                    )),
+                    ..Default::default()
+                });
+                output_group.push(bes::OutputGroup {
+                    name: "default".to_owned(),
+                    file_sets: vec![named_set],
+                    incomplete: false,
+                });
+            }
+
+            events.push(bes::BuildEvent {
+                id: Some(target_completed_id(&target)),
+                payload: Some(bes::build_event::Payload::Completed(bes::TargetComplete {
+                    success: !state.failed,
+                    output_group,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            });
+        }
+
+        let (name, code) = if command.is_success {
+            ("SUCCESS", 0)
+        } else {
+            ("BUILD_FAILURE", 1)
+        };
+
+        events.push(bes::BuildEvent {
+            id: Some(build_finished_id()),
+            last_message: true,
+            payload: Some(bes::build_event::Payload::Finished(bes::BuildFinished {
+                overall_success: command.is_success,
+                exit_code: Some(bes::build_finished::ExitCode {
+                    name: name.to_owned(),
+                    code,
+                }),
+                finish_time: Some(event.timestamp().into()),
+            })),
+            ..Default::default()
+        });
+
+        self.finished = true;
+        events
+    }
+}
+
+fn owner(key: &buck2_data::ActionKey) -> Option<TargetKey> {
+    match key.owner.as_ref()? {
+        buck2_data::action_key::Owner::TargetLabel(target)
+        | buck2_data::action_key::Owner::TestTargetLabel(target) => TargetKey::new(target),
+        _ => None,
+    }
+}
+
+fn build_started(event: &BuckEvent, command: &buck2_data::CommandStart) -> bes::BuildEvent {
+    use buck2_data::command_start::Data;
+
+    let name = match &command.data {
+        Some(Data::Build(..)) => "build",
+        Some(Data::Targets(..)) => "targets",
+        Some(Data::Query(..)) => "uquery",
+        Some(Data::Cquery(..)) => "cquery",
+        Some(Data::Test(..)) => "test",
+        Some(Data::Audit(..)) => "audit",
+        Some(Data::Docs(..)) => "docs",
+        Some(Data::Clean(..)) => "clean",
+        Some(Data::Aquery(..)) => "aquery",
+        Some(Data::Install(..)) => "install",
+        Some(Data::Materialize(..)) => "materialize",
+        Some(Data::Profile(..)) => "profile",
+        Some(Data::Bxl(..)) => "bxl",
+        Some(Data::Lsp(..)) => "lsp",
+        None => "unknown",
+    };
+
+    bes::BuildEvent {
+        id: Some(bes::BuildEventId {
+            id: Some(bes::build_event_id::Id::Started(
+                bes::build_event_id::BuildStartedId {},
+            )),
+        }),
+        children: vec![build_finished_id()],
+        payload: Some(bes::build_event::Payload::Started(bes::BuildStarted {
+            uuid: event.event().trace_id.clone(),
+            start_time: Some(event.timestamp().into()),
+            build_tool_version: "buck2".to_owned(),
+            command: name.to_owned(),
+            server_pid: std::process::id() as i64,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+fn test_result(event: &BuckEvent, result: &buck2_data::TestResult) -> bes::BuildEvent {
+    use buck2_data::TestStatus;
+
+    let status = match TestStatus::from_i32(result.status) {
+        Some(TestStatus::Pass) | Some(TestStatus::ListingSuccess) => bes::TestStatus::Passed,
+        Some(TestStatus::Fail)
+        | Some(TestStatus::Fatal)
+        | Some(TestStatus::Rerun)
+        | Some(TestStatus::ListingFailed) => bes::TestStatus::Failed,
+        Some(TestStatus::Timeout) => bes::TestStatus::Timeout,
+        Some(TestStatus::Flaky) => bes::TestStatus::Flaky,
+        Some(TestStatus::Skip)
+        | Some(TestStatus::Omitted)
+        | Some(TestStatus::Unknown)
+        | Some(TestStatus::NotSetTestStatus)
+        | None => bes::TestStatus::NoStatus,
+    };
+
+    let duration = result.duration.clone();
+    let start = duration
+        .clone()
+        .and_then(|d| std::time::Duration::try_from(d).ok())
+        .and_then(|d| event.timestamp().checked_sub(d))
+        .map(Into::into);
+
+    bes::BuildEvent {
+        id: Some(bes::BuildEventId {
+            id: Some(bes::build_event_id::Id::TestResult(
+                bes::build_event_id::TestResultId {
+                    label: result.name.clone(),
+                    configuration: None,
+                    run: 1,
+                    shard: 1,
+                    attempt: result.attempt.max(1) as i32,
+                },
+            )),
+        }),
+        payload: Some(bes::build_event::Payload::TestResult(bes::TestResult {
+            status: status as i32,
+            status_details: result
+                .msg
+                .as_ref()
+                .map_or_else(String::new, |m| m.msg.clone()),
+            test_attempt_start: start,
+            test_attempt_duration: duration,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+fn build_finished_id() -> bes::BuildEventId {
+    bes::BuildEventId {
+        id: Some(bes::build_event_id::Id::BuildFinished(
+            bes::build_event_id::BuildFinishedId {},
+        )),
+    }
+}
+
+fn target_configured_id(target: &TargetKey) -> bes::BuildEventId {
+    bes::BuildEventId {
+        id: Some(bes::build_event_id::Id::TargetConfigured(
+            bes::build_event_id::TargetConfiguredId {
+                label: target.label.clone(),
+                aspect: String::new(),
+            },
+        )),
+    }
+}
+
+fn target_completed_id(target: &TargetKey) -> bes::BuildEventId {
+    bes::BuildEventId {
+        id: Some(bes::build_event_id::Id::TargetCompleted(
+            bes::build_event_id::TargetCompletedId {
+                label: target.label.clone(),
+                configuration: Some(target.configuration_id()),
+                aspect: String::new(),
+            },
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::TraceId;
+
+    fn event(data: buck2_data::buck_event::Data) -> BuckEvent {
+        BuckEvent::new(SystemTime::now(), TraceId::new(), None, None, data)
+    }
+
+    fn target() -> buck2_data::ConfiguredTargetLabel {
+        buck2_data::ConfiguredTargetLabel {
+            label: Some(buck2_data::TargetLabel {
+                package: "root//foo".to_owned(),
+                name: "bar".to_owned(),
+            }),
+            configuration: Some(buck2_data::Configuration {
+                full_name: "cfg".to_owned(),
+            }),
+            execution_configuration: None,
+        }
+    }
+
+    fn action_key() -> buck2_data::ActionKey {
+        buck2_data::ActionKey {
+            owner: Some(buck2_data::action_key::Owner::TargetLabel(target())),
+            ..Default::default()
+        }
+    }
+
+    fn command_end(is_success: bool) -> BuckEvent {
+        event(
+            buck2_data::SpanEndEvent {
+                data: Some(
+                    buck2_data::CommandEnd {
+                        metadata: HashMap::new(),
+                        data: Some(buck2_data::BuildCommandEnd::default().into()),
+                        is_success,
+                        error_messages: Vec::new(),
+                    }
+                    .into(),
+                ),
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    fn action_end(failed: bool) -> BuckEvent {
+        event(
+            buck2_data::SpanEndEvent {
+                data: Some(
+                    buck2_data::ActionExecutionEnd {
+                        key: Some(action_key()),
+                        name: Some(buck2_data::ActionName {
+                            category: "cxx_compile".to_owned(),
+                            identifier: "bar.cpp".to_owned(),
+                        }),
+                        failed,
+                        ..Default::default()
+                    }
+                    .into(),
+                ),
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    fn payloads(events: &[bes::BuildEvent]) -> Vec<&bes::build_event::Payload> {
+        events.iter().filter_map(|e| e.payload.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_build_started() {
+        let mut translator = BepTranslator::new(false);
+        let start = event(
+            buck2_data::SpanStartEvent {
+                data: Some(
+                    buck2_data::CommandStart {
+                        metadata: HashMap::new(),
+                        data: Some(buck2_data::BuildCommandStart {}.into()),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        );
+
+        let events = translator.translate(&start);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].children, vec![build_finished_id()]);
+        match &events[0].payload {
+            Some(bes::build_event::Payload::Started(started)) => {
+                assert_eq!(started.command, "build");
+                assert_eq!(started.uuid, start.event().trace_id);
+            }
+            p => panic!("Unexpected payload: {:?}", p),
+        }
+    }
+
+    #[test]
+    fn test_only_failed_actions_are_published() {
+        let mut translator = BepTranslator::new(false);
+        assert_eq!(translator.translate(&action_end(false)), Vec::new());
+
+        let events = translator.translate(&action_end(true));
+        match payloads(&events).as_slice() {
+            [bes::build_event::Payload::Action(action)] => {
+                assert!(!action.success);
+                assert_eq!(action.r#type, "cxx_compile");
+            }
+            p => panic!("Unexpected payloads: {:?}", p),
+        }
+
+        let mut translator = BepTranslator::new(true);
+        assert_eq!(translator.translate(&action_end(false)).len(), 1);
+    }
+
+    #[test]
+    fn test_targets_complete_when_command_ends() {
+        let mut translator = BepTranslator::new(false);
+
+        let analysis = event(
+            buck2_data::SpanEndEvent {
+                data: Some(
+                    buck2_data::AnalysisEnd {
+                        target: Some(target()),
+                        rule: "cxx_library".to_owned(),
+                        profile: None,
+                    }
+                    .into(),
+                ),
+                ..Default::default()
+            }
+            .into(),
+        );
+        let materialization = event(
+            buck2_data::SpanEndEvent {
+                data: Some(buck2_data::span_end_event::Data::FinalMaterialization(
+                    buck2_data::MaterializeRequestedArtifactEnd {
+                        artifact: Some(buck2_data::BuildArtifact {
+                            key: Some(action_key()),
+                            path: "buck-out/v2/gen/root/foo/libbar.a".to_owned(),
+                        }),
+                    },
+                )),
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        assert_eq!(translator.translate(&analysis), Vec::new());
+        assert_eq!(translator.translate(&materialization), Vec::new());
+
+        let events = translator.translate(&command_end(true));
+        match payloads(&events).as_slice() {
+            [
+                bes::build_event::Payload::Configured(configured),
+                bes::build_event::Payload::NamedSetOfFiles(files),
+                bes::build_event::Payload::Completed(completed),
+                bes::build_event::Payload::Finished(finished),
+            ] => {
+                assert_eq!(configured.target_kind, "cxx_library");
+                assert_eq!(files.files[0].name, "buck-out/v2/gen/root/foo/libbar.a");
+                assert!(completed.success);
+                assert_eq!(completed.output_group[0].file_sets.len(), 1);
+                assert!(finished.overall_success);
+            }
+            p => panic!("Unexpected payloads: {:?}", p),
+        }
+        assert!(events.last().unwrap().last_message);
+        assert_eq!(
+            events[2].id,
+            Some(bes::BuildEventId {
+                id: Some(bes::build_event_id::Id::TargetCompleted(
+                    bes::build_event_id::TargetCompletedId {
+                        label: "root//foo:bar".to_owned(),
+                        configuration: Some(bes::build_event_id::ConfigurationId {
+                            id: "cfg".to_owned()
+                        }),
+                        aspect: String::new(),
+                    }
+                )),
+            })
+        );
+
+        // Nothing is published after the last message.
+        assert_eq!(translator.translate(&action_end(true)), Vec::new());
+    }
+
+    #[test]
+    fn test_failed_target() {
+        let mut translator = BepTranslator::new(false);
+        translator.translate(&action_end(true));
+
+        let events = translator.translate(&command_end(false));
+        match payloads(&events).as_slice() {
+            [
+                bes::build_event::Payload::Configured(..),
+                bes::build_event::Payload::Completed(completed),
+                bes::build_event::Payload::Finished(finished),
+            ] => {
+                assert!(!completed.success);
+                assert!(!finished.overall_success);
+                assert_eq!(finished.exit_code.as_ref().unwrap().code, 1);
+            }
+            p => panic!("Unexpected payloads: {:?}", p),
+        }
+    }
+
+    #[test]
+    fn test_test_result() {
+        let mut translator = BepTranslator::new(false);
+        let result = event(
+            buck2_data::InstantEvent {
+                data: Some(
+                    buck2_data::TestResult {
+                        name: "foo - test_bar".to_owned(),
+                        status: buck2_data::TestStatus::Fail as i32,
+                        attempt: 2,
+                        ..Default::default()
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        );
+
+        let events = translator.translate(&result);
+        match payloads(&events).as_slice() {
+            [bes::build_event::Payload::TestResult(test_result)] => {
+                assert_eq!(test_result.status, bes::TestStatus::Failed as i32);
+            }
+            p => panic!("Unexpected payloads: {:?}", p),
+        }
+        match &events[0].id.as_ref().unwrap().id {
+            Some(bes::build_event_id::Id::TestResult(id)) => {
+                assert_eq!(id.label, "foo - test_bar");
+                assert_eq!(id.attempt, 2);
+            }
+            id => panic!("Unexpected id: {:?}", id),
+        }
+    }
+}
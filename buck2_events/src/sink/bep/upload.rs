@@ -0,0 +1,246 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Publishing of BEP events over the `PublishBuildToolEventStream` API.
+//!
+//! Events are sent with increasing sequence numbers, and the service acknowledges them in order.
+//! If the stream fails, we open a new one and send again everything that wasn't acknowledged,
+//! which the service is expected to deduplicate by sequence number.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use buck2_bep_proto::build_event_stream as bes;
+use buck2_bep_proto::build_event_stream::BUILD_EVENT_TYPE_URL;
+use buck2_bep_proto::google_devtools_build_v1::build_event::build_component_stream_finished::FinishType;
+use buck2_bep_proto::google_devtools_build_v1::build_event::BuildComponentStreamFinished;
+use buck2_bep_proto::google_devtools_build_v1::build_event::Event;
+use buck2_bep_proto::google_devtools_build_v1::publish_build_event_client::PublishBuildEventClient;
+use buck2_bep_proto::google_devtools_build_v1::BuildEvent;
+use buck2_bep_proto::google_devtools_build_v1::OrderedBuildEvent;
+use buck2_bep_proto::google_devtools_build_v1::PublishBuildToolEventStreamRequest;
+use buck2_bep_proto::google_devtools_build_v1::StreamId;
+use futures::future::BoxFuture;
+use prost::Message;
+use thiserror::Error;
+use tonic::metadata::AsciiMetadataKey;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::service::Interceptor;
+use tonic::transport::Channel;
+use tonic::Request;
+use tonic::Status;
+
+use crate::sink::remote::QueueReceiver;
+
+/// Opens a new connection to the build event service.
+pub(crate) type Connect =
+    Arc<dyn Fn() -> BoxFuture<'static, anyhow::Result<Channel>> + Send + Sync>;
+
+/// A BEP event waiting to be published.
+pub(crate) struct QueuedEvent {
+    pub(crate) time: SystemTime,
+    pub(crate) event: bes::BuildEvent,
+}
+
+#[derive(Error, Debug)]
+enum UploadError {
+    #[error("The build event service closed the stream before acknowledging all events")]
+    StreamClosed,
+    #[error("Gave up publishing build events after {0} attempts")]
+    GaveUp(u32),
+}
+
+#[derive(Clone)]
+pub(crate) struct HeadersInterceptor {
+    pub(crate) headers: Arc<Vec<(AsciiMetadataKey, AsciiMetadataValue)>>,
+}
+
+impl Interceptor for HeadersInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        for (key, value) in self.headers.iter() {
+            request.metadata_mut().insert(key.clone(), value.clone());
+        }
+        Ok(request)
+    }
+}
+
+pub(crate) struct Uploader {
+    pub(crate) connect: Connect,
+    pub(crate) headers: HeadersInterceptor,
+    pub(crate) stream_id: StreamId,
+    pub(crate) project_id: String,
+    /// How many events can be published and not acknowledged yet. We stop taking events from the
+    /// queue when we reach this.
+    pub(crate) max_in_flight: usize,
+    /// How many times we open a new stream after a failure, without any event being acknowledged
+    /// in between.
+    pub(crate) max_retries: u32,
+}
+
+/// The events of the stream that weren't acknowledged yet.
+struct StreamState {
+    unacked: VecDeque<OrderedBuildEvent>,
+    next_sequence_number: i64,
+    /// Whether the last event of the stream was sent.
+    finished: bool,
+}
+
+impl Uploader {
+    /// Publish the events from `queue` until the last one is acknowledged.
+    pub(crate) async fn run(self, mut queue: QueueReceiver<QueuedEvent>) -> anyhow::Result<()> {
+        // Don't connect to the service until there is something to publish.
+        let first = match queue.recv().await {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+
+        let mut state = StreamState {
+            unacked: VecDeque::new(),
+            next_sequence_number: 1,
+            finished: false,
+        };
+        self.push(&mut state, Some(first));
+
+        let mut attempts = 0;
+        loop {
+            let mut progressed = false;
+            match self
+                .publish_stream(&mut state, &mut queue, &mut progressed)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    if progressed {
+                        attempts = 0;
+                    }
+                    attempts += 1;
+                    if attempts > self.max_retries {
+                        return Err(e.context(UploadError::GaveUp(attempts)));
+                    }
+                    tracing::debug!("Retrying to publish build events: {:#}", e);
+                    tokio::time::sleep(backoff(attempts)).await;
+                }
+            }
+        }
+    }
+
+    /// Open a stream and publish events on it, starting with those that weren't acknowledged on
+    /// the previous stream. This returns when all events were acknowledged.
+    async fn publish_stream(
+        &self,
+        state: &mut StreamState,
+        queue: &mut QueueReceiver<QueuedEvent>,
+        progressed: &mut bool,
+    ) -> anyhow::Result<()> {
+        let channel = (self.connect)().await?;
+        let mut client = PublishBuildEventClient::with_interceptor(channel, self.headers.clone());
+
+        let (requests, requests_rx) = futures::channel::mpsc::unbounded();
+        for event in &state.unacked {
+            requests.unbounded_send(self.request(event.clone()))?;
+        }
+
+        let mut responses = client
+            .publish_build_tool_event_stream(requests_rx)
+            .await
+            .context("Error opening build event stream")?
+            .into_inner();
+
+        loop {
+            if state.finished && state.unacked.is_empty() {
+                // Close our side of the stream, and let the service close its side.
+                drop(requests);
+                while responses.message().await?.is_some() {}
+                return Ok(());
+            }
+
+            let can_send = !state.finished && state.unacked.len() < self.max_in_flight;
+
+            tokio::select! {
+                event = queue.recv(), if can_send => {
+                    let sent = state.unacked.len();
+                    self.push(state, event);
+                    for event in state.unacked.iter().skip(sent) {
+                        requests.unbounded_send(self.request(event.clone()))?;
+                    }
+                }
+                response = responses.message() => {
+                    let response = response?.ok_or(UploadError::StreamClosed)?;
+                    while let Some(event) = state.unacked.front() {
+                        if event.sequence_number > response.sequence_number {
+                            break;
+                        }
+                        state.unacked.pop_front();
+                        *progressed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add an event to the stream. If the event is the last one, or there are no more events,
+    /// this also finishes the stream.
+    fn push(&self, state: &mut StreamState, event: Option<QueuedEvent>) {
+        let time = match event {
+            Some(QueuedEvent { time, event }) => {
+                let last = event.last_message;
+                self.push_event(
+                    state,
+                    time,
+                    Event::BazelEvent(prost_types::Any {
+                        type_url: BUILD_EVENT_TYPE_URL.to_owned(),
+                        value: event.encode_to_vec(),
+                    }),
+                );
+                if !last {
+                    return;
+                }
+                time
+            }
+            // The sink went away without sending a last event.
+            None => SystemTime::now(),
+        };
+
+        self.push_event(
+            state,
+            time,
+            Event::ComponentStreamFinished(BuildComponentStreamFinished {
+                r#type: FinishType::Finished as i32,
+            }),
+        );
+        state.finished = true;
+    }
+
+    fn push_event(&self, state: &mut StreamState, time: SystemTime, event: Event) {
+        state.unacked.push_back(OrderedBuildEvent {
+            stream_id: Some(self.stream_id.clone()),
+            sequence_number: state.next_sequence_number,
+            event: Some(BuildEvent {
+                event_time: Some(time.into()),
+                event: Some(event),
+            }),
+        });
+        state.next_sequence_number += 1;
+    }
+
+    fn request(&self, event: OrderedBuildEvent) -> PublishBuildToolEventStreamRequest {
+        PublishBuildToolEventStreamRequest {
+            ordered_build_event: Some(event),
+            project_id: self.project_id.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn backoff(attempts: u32) -> Duration {
+    Duration::from_millis(100 * 2u64.pow(attempts.min(6)))
+}
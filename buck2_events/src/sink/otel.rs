@@ -0,0 +1,356 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A Sink that exports the spans of a command to an OpenTelemetry collector, so that builds can be
+//! looked at in tracing tools such as Jaeger, Tempo or Honeycomb.
+
+mod convert;
+mod export;
+
+use std::sync::Mutex;
+
+use buck2_otel_proto::trace_service::Span;
+
+pub use crate::sink::otel::convert::SpanConverter;
+pub use crate::sink::otel::export::OtelExporter;
+use crate::sink::remote::queue;
+use crate::sink::remote::QueueReceiver;
+use crate::sink::remote::QueueSender;
+use crate::BuckEvent;
+use crate::ControlEvent;
+use crate::EventSink;
+
+/// How many spans are sent to the collector in one request, unless configured otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 512;
+
+/// Configuration for exporting spans to an OpenTelemetry collector.
+#[derive(Clone, Debug)]
+pub struct OtelSinkConfig {
+    /// The address of the collector: `grpc://host:port` or `grpcs://host:port` for OTLP/gRPC,
+    /// `http://host:port` or `https://host:port` for OTLP/HTTP.
+    pub endpoint: String,
+    /// Headers sent with every request, e.g. API keys.
+    pub headers: Vec<(String, String)>,
+    /// The maximum number of spans sent in one request.
+    pub batch_size: usize,
+    /// How many spans can wait to be exported. When this is reached, further spans are dropped.
+    pub buffer_size: usize,
+}
+
+impl OtelSinkConfig {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            headers: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer_size: 10000,
+        }
+    }
+}
+
+/// An EventSink that converts the spans of a command into OpenTelemetry spans and exports them.
+/// Spans are exported in the background, in batches of those that ended while the previous batch
+/// was being sent.
+pub struct OtelSink {
+    converter: Mutex<SpanConverter>,
+    queue: QueueSender<Span>,
+}
+
+impl OtelSink {
+    /// Creates an OtelSink. This must be called from within a Tokio runtime, which runs the
+    /// export.
+    pub fn new(config: OtelSinkConfig) -> anyhow::Result<OtelSink> {
+        let exporter = OtelExporter::new(&config.endpoint, &config.headers)?;
+        Ok(Self::with_exporter(config, exporter))
+    }
+
+    fn with_exporter(config: OtelSinkConfig, exporter: OtelExporter) -> OtelSink {
+        let (queue, queue_rx) = queue(config.buffer_size, "OpenTelemetry collector", "spans");
+
+        tokio::spawn(export_spans(exporter, queue_rx, config.batch_size.max(1)));
+
+        OtelSink {
+            converter: Mutex::new(SpanConverter::new()),
+            queue,
+        }
+    }
+}
+
+impl EventSink for OtelSink {
+    fn send(&self, event: BuckEvent) {
+        if let Some(span) = self.converter.lock().unwrap().convert(&event) {
+            self.queue.send(span);
+        }
+    }
+
+    fn send_control(&self, _control_event: ControlEvent) {}
+}
+
+/// Export spans from `queue` until the sink goes away.
+async fn export_spans(exporter: OtelExporter, mut queue: QueueReceiver<Span>, batch_size: usize) {
+    let mut warned = false;
+
+    while let Some(span) = queue.recv().await {
+        let mut batch = vec![span];
+        while batch.len() < batch_size {
+            match queue.try_recv() {
+                Some(span) => batch.push(span),
+                None => break,
+            }
+        }
+
+        if let Err(e) = exporter.export(batch).await {
+            // The collector is likely to fail the same way for every batch, so only warn once.
+            if !warned {
+                tracing::warn!("Error exporting spans: {:#}", e);
+                warned = true;
+            } else {
+                tracing::debug!("Error exporting spans: {:#}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use buck2_grpc::DuplexChannel;
+    use buck2_otel_proto::trace_service::trace_service_server::TraceService;
+    use buck2_otel_proto::trace_service::trace_service_server::TraceServiceServer;
+    use buck2_otel_proto::trace_service::ExportTraceServiceRequest;
+    use buck2_otel_proto::trace_service::ExportTraceServiceResponse;
+    use gazebo::prelude::*;
+    use prost::Message;
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+    use tonic::transport::Server;
+    use tonic::Request;
+    use tonic::Response;
+    use tonic::Status;
+
+    use super::*;
+    use crate::span::SpanId;
+    use crate::TraceId;
+
+    /// A collector that records the requests it receives.
+    #[derive(Clone, Default)]
+    struct FakeCollector {
+        requests: Arc<Mutex<Vec<ExportTraceServiceRequest>>>,
+        api_keys: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl TraceService for FakeCollector {
+        async fn export(
+            &self,
+            request: Request<ExportTraceServiceRequest>,
+        ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+            if let Some(key) = request.metadata().get("x-api-key") {
+                self.api_keys
+                    .lock()
+                    .unwrap()
+                    .push(key.to_str().unwrap().to_owned());
+            }
+            self.requests.lock().unwrap().push(request.into_inner());
+            Ok(Response::new(ExportTraceServiceResponse::default()))
+        }
+    }
+
+    fn command_events(trace_id: &TraceId) -> Vec<BuckEvent> {
+        let command = SpanId::new();
+        let analysis = SpanId::new();
+        let event = |span_id, parent_id, data: buck2_data::buck_event::Data| {
+            BuckEvent::new(
+                SystemTime::now(),
+                trace_id.dupe(),
+                Some(span_id),
+                parent_id,
+                data,
+            )
+        };
+        vec![
+            event(
+                command,
+                None,
+                buck2_data::SpanStartEvent {
+                    data: Some(
+                        buck2_data::CommandStart {
+                            metadata: HashMap::new(),
+                            data: Some(buck2_data::BuildCommandStart {}.into()),
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            event(
+                analysis,
+                Some(command),
+                buck2_data::SpanStartEvent {
+                    data: Some(
+                        buck2_data::AnalysisStart {
+                            target: None,
+                            rule: "cxx_library".to_owned(),
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            event(
+                analysis,
+                Some(command),
+                buck2_data::SpanEndEvent {
+                    data: Some(buck2_data::AnalysisEnd::default().into()),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            event(
+                command,
+                None,
+                buck2_data::SpanEndEvent {
+                    data: Some(
+                        buck2_data::CommandEnd {
+                            metadata: HashMap::new(),
+                            data: Some(buck2_data::BuildCommandEnd::default().into()),
+                            is_success: true,
+                            error_messages: Vec::new(),
+                        }
+                        .into(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        ]
+    }
+
+    fn spans(requests: &[ExportTraceServiceRequest]) -> Vec<Span> {
+        requests
+            .iter()
+            .flat_map(|r| &r.resource_spans)
+            .flat_map(|r| &r.scope_spans)
+            .flat_map(|s| s.spans.iter().cloned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_export_grpc() -> anyhow::Result<()> {
+        let collector = FakeCollector::default();
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (read, write) = tokio::io::split(server_io);
+        let _server = buck2_grpc::spawn_oneshot(
+            DuplexChannel::new(read, write),
+            Server::builder().add_service(TraceServiceServer::new(collector.clone())),
+        );
+        let channel = buck2_grpc::make_channel(client_io, "collector").await?;
+
+        let mut config = OtelSinkConfig::new("grpc://unused".to_owned());
+        config.headers = vec![("x-api-key".to_owned(), "secret".to_owned())];
+        let exporter = OtelExporter::grpc(channel, &config.headers)?;
+        let sink = OtelSink::with_exporter(config, exporter);
+
+        let trace_id = TraceId::new();
+        for event in command_events(&trace_id) {
+            sink.send(event);
+        }
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while spans(&collector.requests.lock().unwrap()).len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+
+        let spans = spans(&collector.requests.lock().unwrap());
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].name, "analysis");
+        assert_eq!(spans[0].parent_span_id, spans[1].span_id);
+        assert_eq!(spans[1].name, "command");
+        assert_eq!(spans[1].trace_id, trace_id.0.as_bytes().to_vec());
+        assert!(
+            collector
+                .api_keys
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|key| key == "secret")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_http() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+
+        // A minimal OTLP/HTTP collector, which handles a single request.
+        let collector = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut received = Vec::new();
+            let mut buf = [0; 4096];
+            let (head_len, body_len) = loop {
+                let n = stream.read(&mut buf).await?;
+                anyhow::ensure!(n > 0, "Connection closed");
+                received.extend_from_slice(&buf[..n]);
+                if let Some(pos) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                    let head = String::from_utf8_lossy(&received[..pos]).to_lowercase();
+                    let body_len = head
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:"))
+                        .map_or(0, |l| l.trim().parse().unwrap());
+                    break (pos + 4, body_len);
+                }
+            };
+            while received.len() < head_len + body_len {
+                let n = stream.read(&mut buf).await?;
+                anyhow::ensure!(n > 0, "Connection closed");
+                received.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await?;
+
+            let head = String::from_utf8_lossy(&received[..head_len]).into_owned();
+            let request = ExportTraceServiceRequest::decode(&received[head_len..])?;
+            anyhow::Ok((head, request))
+        });
+
+        let exporter = OtelExporter::new(&endpoint, &[])?;
+        let mut converter = SpanConverter::new();
+        let spans_to_export = command_events(&TraceId::new())
+            .iter()
+            .filter_map(|e| converter.convert(e))
+            .collect::<Vec<_>>();
+        exporter.export(spans_to_export).await?;
+
+        let (head, request) = collector.await??;
+        assert!(head.starts_with("POST /v1/traces "));
+        assert!(
+            head.to_lowercase()
+                .contains("content-type: application/x-protobuf")
+        );
+        assert_eq!(spans(&[request]).len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_endpoint() {
+        assert!(OtelExporter::new("grpc://localhost:4317", &[]).is_ok());
+        assert!(OtelExporter::new("localhost:4317", &[]).is_err());
+    }
+}
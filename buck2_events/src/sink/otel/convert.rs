@@ -0,0 +1,545 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Conversion of Buck2 spans into OpenTelemetry spans.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use buck2_otel_proto::trace_service::any_value;
+use buck2_otel_proto::trace_service::span::SpanKind;
+use buck2_otel_proto::trace_service::status::StatusCode;
+use buck2_otel_proto::trace_service::AnyValue;
+use buck2_otel_proto::trace_service::KeyValue;
+use buck2_otel_proto::trace_service::Span;
+use buck2_otel_proto::trace_service::Status;
+use gazebo::variants::VariantName;
+
+use crate::span::SpanId;
+use crate::BuckEvent;
+
+/// A Buck2 span that started and hasn't ended yet.
+struct OpenSpan {
+    /// The closest span, out of this one and its ancestors, that we export.
+    exported_ancestor: Option<SpanId>,
+    /// Set if we export this span.
+    pending: Option<PendingSpan>,
+}
+
+/// What we know about a span we export when it starts.
+struct PendingSpan {
+    name: &'static str,
+    start_time: SystemTime,
+    parent: Option<SpanId>,
+    attributes: Vec<KeyValue>,
+}
+
+/// Turns the spans of commands, analysis, loads, actions and materializations into OpenTelemetry
+/// spans. Other spans aren't exported, so exported spans are parented to their closest exported
+/// ancestor.
+#[derive(Default)]
+pub struct SpanConverter {
+    spans: HashMap<SpanId, OpenSpan>,
+}
+
+impl SpanConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The OpenTelemetry span that this event ends, if any.
+    pub fn convert(&mut self, event: &BuckEvent) -> Option<Span> {
+        use buck2_data::buck_event::Data;
+
+        let span_id = event.span_id()?;
+        match event.data() {
+            Data::SpanStart(start) => {
+                self.start(event, span_id, start);
+                None
+            }
+            Data::SpanEnd(end) => self.end(event, span_id, end),
+            _ => None,
+        }
+    }
+
+    fn start(&mut self, event: &BuckEvent, span_id: SpanId, start: &buck2_data::SpanStartEvent) {
+        let parent = event
+            .parent_id()
+            .and_then(|parent| self.spans.get(&parent))
+            .and_then(|parent| parent.exported_ancestor);
+
+        let pending = start
+            .data
+            .as_ref()
+            .and_then(start_span)
+            .map(|(name, attributes)| PendingSpan {
+                name,
+                start_time: event.timestamp(),
+                parent,
+                attributes,
+            });
+
+        self.spans.insert(
+            span_id,
+            OpenSpan {
+                exported_ancestor: if pending.is_some() {
+                    Some(span_id)
+                } else {
+                    parent
+                },
+                pending,
+            },
+        );
+    }
+
+    fn end(
+        &mut self,
+        event: &BuckEvent,
+        span_id: SpanId,
+        end: &buck2_data::SpanEndEvent,
+    ) -> Option<Span> {
+        use buck2_data::span_end_event::Data;
+
+        let pending = self.spans.remove(&span_id)?.pending?;
+        let trace_id = event.trace_id().ok()?;
+
+        let mut attributes = pending.attributes;
+        let mut error = None;
+        match &end.data {
+            Some(Data::Command(command)) => {
+                if !command.is_success {
+                    error = Some(command.error_messages.join("\n"));
+                }
+            }
+            Some(Data::Load(load)) => error = load.error.clone(),
+            Some(Data::ActionExecution(action)) => {
+                let cache_hit =
+                    action.execution_kind == buck2_data::ActionExecutionKind::ActionCache as i32;
+                attributes.push(string_attribute(
+                    "buck2.execution_kind",
+                    execution_kind(action.execution_kind),
+                ));
+                attributes.push(bool_attribute("buck2.cache_hit", cache_hit));
+                attributes.push(int_attribute(
+                    "buck2.output_size",
+                    action.output_size as i64,
+                ));
+                if action.failed {
+                    error = Some("Action failed".to_owned());
+                }
+            }
+            Some(Data::Materialization(materialization)) => {
+                attributes.push(string_attribute("buck2.path", &materialization.path));
+                attributes.push(int_attribute(
+                    "buck2.file_count",
+                    materialization.file_count as i64,
+                ));
+                attributes.push(int_attribute(
+                    "buck2.total_bytes",
+                    materialization.total_bytes as i64,
+                ));
+                error = materialization.error.clone();
+            }
+            Some(Data::SpanCancelled(..)) => error = Some("Cancelled".to_owned()),
+            _ => {}
+        }
+
+        Some(Span {
+            trace_id: trace_id.0.as_bytes().to_vec(),
+            span_id: span_id_bytes(span_id),
+            parent_span_id: pending.parent.map_or_else(Vec::new, span_id_bytes),
+            name: pending.name.to_owned(),
+            kind: SpanKind::Internal as i32,
+            start_time_unix_nano: unix_nanos(pending.start_time),
+            end_time_unix_nano: unix_nanos(event.timestamp()),
+            attributes,
+            status: error.map(|message| Status {
+                message,
+                code: StatusCode::Error as i32,
+            }),
+        })
+    }
+}
+
+/// The name and attributes of the OpenTelemetry span for a span we export.
+fn start_span(data: &buck2_data::span_start_event::Data) -> Option<(&'static str, Vec<KeyValue>)> {
+    use buck2_data::span_start_event::Data;
+
+    match data {
+        Data::Command(command) => Some((
+            "command",
+            vec![string_attribute(
+                "buck2.command",
+                command
+                    .data
+                    .as_ref()
+                    .map_or("unknown", |data| data.variant_name())
+                    .to_lowercase(),
+            )],
+        )),
+        Data::Analysis(analysis) => {
+            let mut attributes = Vec::new();
+            if let Some(target) = analysis.target.as_ref().and_then(target_label) {
+                attributes.push(string_attribute("buck2.target", target));
+            }
+            attributes.push(string_attribute("buck2.rule", &analysis.rule));
+            Some(("analysis", attributes))
+        }
+        Data::Load(load) => Some((
+            "load",
+            vec![
+                string_attribute("buck2.module", &load.module_id),
+                string_attribute("buck2.cell", &load.cell),
+            ],
+        )),
+        Data::ActionExecution(action) => {
+            let mut attributes = Vec::new();
+            if let Some(target) = action.key.as_ref().and_then(owner) {
+                attributes.push(string_attribute("buck2.target", target));
+            }
+            if let Some(name) = &action.name {
+                attributes.push(string_attribute("buck2.category", &name.category));
+                if !name.identifier.is_empty() {
+                    attributes.push(string_attribute("buck2.identifier", &name.identifier));
+                }
+            }
+            if let Some(kind) = buck2_data::ActionKind::from_i32(action.kind) {
+                attributes.push(string_attribute(
+                    "buck2.action_kind",
+                    format!("{:?}", kind).to_lowercase(),
+                ));
+            }
+            Some(("action", attributes))
+        }
+        Data::Materialization(materialization) => Some((
+            "materialization",
+            materialization
+                .action_digest
+                .iter()
+                .map(|digest| string_attribute("buck2.action_digest", digest))
+                .collect(),
+        )),
+        Data::FinalMaterialization(materialization) => {
+            let mut attributes = Vec::new();
+            if let Some(artifact) = &materialization.artifact {
+                if let Some(target) = artifact.key.as_ref().and_then(owner) {
+                    attributes.push(string_attribute("buck2.target", target));
+                }
+                attributes.push(string_attribute("buck2.path", &artifact.path));
+            }
+            Some(("final_materialization", attributes))
+        }
+        _ => None,
+    }
+}
+
+fn target_label(target: &buck2_data::ConfiguredTargetLabel) -> Option<String> {
+    let label = target.label.as_ref()?;
+    Some(match &target.configuration {
+        Some(configuration) => format!(
+            "{}:{} ({})",
+            label.package, label.name, configuration.full_name
+        ),
+        None => format!("{}:{}", label.package, label.name),
+    })
+}
+
+fn owner(key: &buck2_data::ActionKey) -> Option<String> {
+    match key.owner.as_ref()? {
+        buck2_data::action_key::Owner::TargetLabel(target)
+        | buck2_data::action_key::Owner::TestTargetLabel(target) => target_label(target),
+        _ => None,
+    }
+}
+
+fn execution_kind(kind: i32) -> &'static str {
+    use buck2_data::ActionExecutionKind;
+
+    match ActionExecutionKind::from_i32(kind) {
+        Some(ActionExecutionKind::Local) => "local",
+        Some(ActionExecutionKind::Remote) => "remote",
+        Some(ActionExecutionKind::ActionCache) => "action_cache",
+        Some(ActionExecutionKind::Simple) => "simple",
+        Some(ActionExecutionKind::Skipped) => "skipped",
+        Some(ActionExecutionKind::Deferred) => "deferred",
+        Some(ActionExecutionKind::NotSet) | None => "unknown",
+    }
+}
+
+fn span_id_bytes(span_id: SpanId) -> Vec<u8> {
+    span_id.0.get().to_be_bytes().to_vec()
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+pub(crate) fn string_attribute(key: &str, value: impl Into<String>) -> KeyValue {
+    attribute(key, any_value::Value::StringValue(value.into()))
+}
+
+fn bool_attribute(key: &str, value: bool) -> KeyValue {
+    attribute(key, any_value::Value::BoolValue(value))
+}
+
+fn int_attribute(key: &str, value: i64) -> KeyValue {
+    attribute(key, any_value::Value::IntValue(value))
+}
+
+fn attribute(key: &str, value: any_value::Value) -> KeyValue {
+    KeyValue {
+        key: key.to_owned(),
+        value: Some(AnyValue { value: Some(value) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use gazebo::prelude::*;
+
+    use super::*;
+    use crate::TraceId;
+
+    fn event(
+        trace_id: &TraceId,
+        span_id: SpanId,
+        parent_id: Option<SpanId>,
+        data: buck2_data::buck_event::Data,
+    ) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            trace_id.dupe(),
+            Some(span_id),
+            parent_id,
+            data,
+        )
+    }
+
+    fn target() -> buck2_data::ConfiguredTargetLabel {
+        buck2_data::ConfiguredTargetLabel {
+            label: Some(buck2_data::TargetLabel {
+                package: "root//foo".to_owned(),
+                name: "bar".to_owned(),
+            }),
+            configuration: Some(buck2_data::Configuration {
+                full_name: "cfg".to_owned(),
+            }),
+            execution_configuration: None,
+        }
+    }
+
+    fn attribute_value<'a>(span: &'a Span, key: &str) -> Option<&'a any_value::Value> {
+        span.attributes
+            .iter()
+            .find(|a| a.key == key)?
+            .value
+            .as_ref()?
+            .value
+            .as_ref()
+    }
+
+    #[test]
+    fn test_command_and_action() {
+        let trace_id = TraceId::new();
+        let command = SpanId::new();
+        let dice = SpanId::new();
+        let action = SpanId::new();
+        let mut converter = SpanConverter::new();
+
+        let events = vec![
+            event(
+                &trace_id,
+                command,
+                None,
+                buck2_data::SpanStartEvent {
+                    data: Some(
+                        buck2_data::CommandStart {
+                            metadata: HashMap::new(),
+                            data: Some(buck2_data::BuildCommandStart {}.into()),
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            // Not exported, so the action's parent is the command.
+            event(
+                &trace_id,
+                dice,
+                Some(command),
+                buck2_data::SpanStartEvent {
+                    data: Some(buck2_data::SharedTaskStart {}.into()),
+                }
+                .into(),
+            ),
+            event(
+                &trace_id,
+                action,
+                Some(dice),
+                buck2_data::SpanStartEvent {
+                    data: Some(
+                        buck2_data::ActionExecutionStart {
+                            key: Some(buck2_data::ActionKey {
+                                owner: Some(buck2_data::action_key::Owner::TargetLabel(target())),
+                                ..Default::default()
+                            }),
+                            kind: buck2_data::ActionKind::Run as i32,
+                            name: Some(buck2_data::ActionName {
+                                category: "cxx_compile".to_owned(),
+                                identifier: "bar.cpp".to_owned(),
+                            }),
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            ),
+            event(
+                &trace_id,
+                action,
+                Some(dice),
+                buck2_data::SpanEndEvent {
+                    data: Some(
+                        buck2_data::ActionExecutionEnd {
+                            execution_kind: buck2_data::ActionExecutionKind::ActionCache as i32,
+                            ..Default::default()
+                        }
+                        .into(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            event(
+                &trace_id,
+                dice,
+                Some(command),
+                buck2_data::SpanEndEvent {
+                    data: Some(buck2_data::SharedTaskEnd {}.into()),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            event(
+                &trace_id,
+                command,
+                None,
+                buck2_data::SpanEndEvent {
+                    data: Some(
+                        buck2_data::CommandEnd {
+                            metadata: HashMap::new(),
+                            data: Some(buck2_data::BuildCommandEnd::default().into()),
+                            is_success: false,
+                            error_messages: vec!["Boom".to_owned()],
+                        }
+                        .into(),
+                    ),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        ];
+
+        let spans = events
+            .iter()
+            .filter_map(|e| converter.convert(e))
+            .collect::<Vec<_>>();
+        assert_eq!(spans.len(), 2);
+        assert!(converter.spans.is_empty());
+
+        let (action_span, command_span) = (&spans[0], &spans[1]);
+        assert_eq!(action_span.name, "action");
+        assert_eq!(action_span.trace_id, trace_id.0.as_bytes().to_vec());
+        assert_eq!(action_span.span_id, span_id_bytes(action));
+        assert_eq!(action_span.parent_span_id, span_id_bytes(command));
+        assert_eq!(action_span.status, None);
+        assert_eq!(
+            attribute_value(action_span, "buck2.target"),
+            Some(&any_value::Value::StringValue(
+                "root//foo:bar (cfg)".to_owned()
+            ))
+        );
+        assert_eq!(
+            attribute_value(action_span, "buck2.category"),
+            Some(&any_value::Value::StringValue("cxx_compile".to_owned()))
+        );
+        assert_eq!(
+            attribute_value(action_span, "buck2.action_kind"),
+            Some(&any_value::Value::StringValue("run".to_owned()))
+        );
+        assert_eq!(
+            attribute_value(action_span, "buck2.execution_kind"),
+            Some(&any_value::Value::StringValue("action_cache".to_owned()))
+        );
+        assert_eq!(
+            attribute_value(action_span, "buck2.cache_hit"),
+            Some(&any_value::Value::BoolValue(true))
+        );
+
+        assert_eq!(command_span.name, "command");
+        assert!(command_span.parent_span_id.is_empty());
+        assert!(command_span.start_time_unix_nano <= action_span.start_time_unix_nano);
+        assert_eq!(
+            attribute_value(command_span, "buck2.command"),
+            Some(&any_value::Value::StringValue("build".to_owned()))
+        );
+        assert_eq!(
+            command_span.status,
+            Some(Status {
+                message: "Boom".to_owned(),
+                code: StatusCode::Error as i32,
+            })
+        );
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let trace_id = TraceId::new();
+        let load = SpanId::new();
+        let mut converter = SpanConverter::new();
+
+        assert_eq!(
+            converter.convert(&event(
+                &trace_id,
+                load,
+                None,
+                buck2_data::SpanStartEvent {
+                    data: Some(
+                        buck2_data::LoadBuildFileStart {
+                            module_id: "root//foo:BUCK".to_owned(),
+                            cell: "root".to_owned(),
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            )),
+            None
+        );
+
+        let span = converter
+            .convert(&event(
+                &trace_id,
+                load,
+                None,
+                buck2_data::SpanEndEvent {
+                    data: Some(buck2_data::SpanCancelled {}.into()),
+                    ..Default::default()
+                }
+                .into(),
+            ))
+            .unwrap();
+        assert_eq!(span.name, "load");
+        assert_eq!(span.status.map(|s| s.code), Some(StatusCode::Error as i32));
+    }
+}
@@ -0,0 +1,193 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Export of spans to an OpenTelemetry collector, using OTLP over gRPC or over HTTP.
+
+use anyhow::Context as _;
+use buck2_otel_proto::trace_service::trace_service_client::TraceServiceClient;
+use buck2_otel_proto::trace_service::ExportTraceServiceRequest;
+use buck2_otel_proto::trace_service::ExportTraceServiceResponse;
+use buck2_otel_proto::trace_service::InstrumentationScope;
+use buck2_otel_proto::trace_service::Resource;
+use buck2_otel_proto::trace_service::ResourceSpans;
+use buck2_otel_proto::trace_service::ScopeSpans;
+use buck2_otel_proto::trace_service::Span;
+use prost::Message;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::header::CONTENT_TYPE;
+use thiserror::Error;
+use tonic::metadata::AsciiMetadataKey;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::transport::Channel;
+
+use crate::sink::otel::convert::string_attribute;
+use crate::sink::remote::grpc_endpoint;
+use crate::sink::remote::grpc_headers;
+
+/// The path that OTLP/HTTP collectors receive traces on.
+const HTTP_TRACES_PATH: &str = "/v1/traces";
+
+#[derive(Error, Debug)]
+enum OtelExportError {
+    #[error(
+        "Invalid OpenTelemetry collector endpoint `{0}`, expected `grpc://`, `grpcs://`, `http://` or `https://`"
+    )]
+    InvalidEndpoint(String),
+    #[error("Invalid OpenTelemetry collector header `{0}`")]
+    InvalidHeader(String),
+    #[error("The OpenTelemetry collector rejected {0} spans: {1}")]
+    Rejected(i64, String),
+}
+
+enum Transport {
+    Grpc {
+        client: TraceServiceClient<Channel>,
+        headers: Vec<(AsciiMetadataKey, AsciiMetadataValue)>,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+    },
+}
+
+/// Sends spans to an OpenTelemetry collector. The protocol is picked from the scheme of the
+/// endpoint: `grpc://` and `grpcs://` for OTLP/gRPC, `http://` and `https://` for OTLP/HTTP with
+/// protobuf payloads.
+pub struct OtelExporter {
+    transport: Transport,
+    resource: Resource,
+}
+
+impl OtelExporter {
+    /// Creates an exporter sending spans to `endpoint`, with `headers` on every request, e.g. API
+    /// keys. No connection is made until spans are exported. This must be called from within a
+    /// Tokio runtime.
+    pub fn new(endpoint: &str, headers: &[(String, String)]) -> anyhow::Result<OtelExporter> {
+        if let Some(grpc_endpoint) = grpc_endpoint(endpoint)? {
+            Self::grpc(grpc_endpoint.connect_lazy(), headers)
+        } else if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+            Self::http(endpoint, headers)
+        } else {
+            Err(OtelExportError::InvalidEndpoint(endpoint.to_owned()).into())
+        }
+    }
+
+    pub(crate) fn grpc(
+        channel: Channel,
+        headers: &[(String, String)],
+    ) -> anyhow::Result<OtelExporter> {
+        Ok(OtelExporter {
+            transport: Transport::Grpc {
+                client: TraceServiceClient::new(channel),
+                headers: grpc_headers(headers)?,
+            },
+            resource: resource(),
+        })
+    }
+
+    fn http(endpoint: &str, headers: &[(String, String)]) -> anyhow::Result<OtelExporter> {
+        let mut header_map = HeaderMap::new();
+        for (key, value) in headers {
+            header_map.insert(
+                HeaderName::from_bytes(key.as_bytes())
+                    .map_err(|_| OtelExportError::InvalidHeader(key.clone()))?,
+                HeaderValue::from_str(value)
+                    .map_err(|_| OtelExportError::InvalidHeader(key.clone()))?,
+            );
+        }
+
+        // Like OpenTelemetry SDKs, we take the endpoint to be the base URL of the collector, unless
+        // it already points at the traces path.
+        let url = if endpoint.ends_with(HTTP_TRACES_PATH) {
+            endpoint.to_owned()
+        } else {
+            format!("{}{}", endpoint.trim_end_matches('/'), HTTP_TRACES_PATH)
+        };
+
+        Ok(OtelExporter {
+            transport: Transport::Http {
+                client: reqwest::Client::builder()
+                    .default_headers(header_map)
+                    .build()
+                    .context("Error creating HTTP client")?,
+                url,
+            },
+            resource: resource(),
+        })
+    }
+
+    /// Sends `spans` to the collector, in a single request.
+    pub async fn export(&self, spans: Vec<Span>) -> anyhow::Result<()> {
+        if spans.is_empty() {
+            return Ok(());
+        }
+
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(self.resource.clone()),
+                scope_spans: vec![ScopeSpans {
+                    scope: Some(InstrumentationScope {
+                        name: "buck2".to_owned(),
+                        version: String::new(),
+                    }),
+                    spans,
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+
+        let response = match &self.transport {
+            Transport::Grpc { client, headers } => {
+                let mut request = tonic::Request::new(request);
+                for (key, value) in headers {
+                    request.metadata_mut().insert(key.clone(), value.clone());
+                }
+                client
+                    .clone()
+                    .export(request)
+                    .await
+                    .context("Error exporting spans to the OpenTelemetry collector")?
+                    .into_inner()
+            }
+            Transport::Http { client, url } => {
+                let body = client
+                    .post(url)
+                    .header(CONTENT_TYPE, "application/x-protobuf")
+                    .body(request.encode_to_vec())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .context("Error exporting spans to the OpenTelemetry collector")?
+                    .bytes()
+                    .await
+                    .context("Error reading the response of the OpenTelemetry collector")?;
+                ExportTraceServiceResponse::decode(body)
+                    .context("Invalid response from the OpenTelemetry collector")?
+            }
+        };
+
+        match response.partial_success {
+            Some(partial) if partial.rejected_spans > 0 => {
+                Err(OtelExportError::Rejected(partial.rejected_spans, partial.error_message).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn resource() -> Resource {
+    let mut attributes = vec![string_attribute("service.name", "buck2")];
+    if let Ok(host) = hostname::get() {
+        attributes.push(string_attribute("host.name", host.to_string_lossy()));
+    }
+    Resource { attributes }
+}
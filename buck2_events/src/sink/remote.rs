@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! What the sinks sending events to remote services have in common: the queue that events wait
+//! in until they are sent in the background, and how gRPC endpoints and headers are configured.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use gazebo::prelude::*;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tonic::metadata::AsciiMetadataKey;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::transport::ClientTlsConfig;
+use tonic::transport::Endpoint;
+
+#[derive(Error, Debug)]
+enum RemoteSinkError {
+    #[error("Invalid endpoint `{0}`")]
+    InvalidEndpoint(String),
+    #[error("Invalid header `{0}`")]
+    InvalidHeader(String),
+}
+
+/// Creates a queue for the items sent to `service`. Once `buffer_size` items are waiting, further
+/// items are dropped, and we warn that `service` is not keeping up with `items`.
+pub(crate) fn queue<T>(
+    buffer_size: usize,
+    service: &'static str,
+    items: &'static str,
+) -> (QueueSender<T>, QueueReceiver<T>) {
+    let queued = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (
+        QueueSender {
+            sender,
+            queued: queued.dupe(),
+            buffer_size,
+            warned_about_drops: AtomicBool::new(false),
+            service,
+            items,
+        },
+        QueueReceiver { receiver, queued },
+    )
+}
+
+pub(crate) struct QueueSender<T> {
+    sender: mpsc::UnboundedSender<T>,
+    /// The number of items in the queue, shared with the receiver.
+    queued: Arc<AtomicUsize>,
+    buffer_size: usize,
+    warned_about_drops: AtomicBool,
+    service: &'static str,
+    items: &'static str,
+}
+
+impl<T> QueueSender<T> {
+    /// Adds `item` to the queue, unless the queue is full.
+    pub(crate) fn send(&self, item: T) {
+        if self.queued.load(Ordering::Relaxed) >= self.buffer_size {
+            if !self.warned_about_drops.swap(true, Ordering::Relaxed) {
+                tracing::warn!(
+                    "{} is not keeping up, some {} will be dropped",
+                    self.service,
+                    self.items
+                );
+            }
+            return;
+        }
+        self.force_send(item);
+    }
+
+    /// Adds `item` to the queue, even if it is full.
+    pub(crate) fn force_send(&self, item: T) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        // If this fails, sending gave up, and it already reported why.
+        let _ignored = self.sender.send(item);
+    }
+}
+
+pub(crate) struct QueueReceiver<T> {
+    receiver: mpsc::UnboundedReceiver<T>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl<T> QueueReceiver<T> {
+    /// Waits for the next item. Returns `None` once the sender is gone and the queue is empty.
+    /// This is cancel safe.
+    pub(crate) async fn recv(&mut self) -> Option<T> {
+        let item = self.receiver.recv().await?;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        Some(item)
+    }
+
+    /// Takes the next item, if there is one already.
+    pub(crate) fn try_recv(&mut self) -> Option<T> {
+        let item = self.receiver.try_recv().ok()?;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        Some(item)
+    }
+}
+
+/// The gRPC endpoint for `address`, given as `grpc://host:port`, or `grpcs://host:port` to use
+/// TLS. Returns `None` if `address` has another scheme.
+pub(crate) fn grpc_endpoint(address: &str) -> anyhow::Result<Option<Endpoint>> {
+    let (uri, tls) = if let Some(host) = address.strip_prefix("grpc://") {
+        (format!("http://{}", host), false)
+    } else if let Some(host) = address.strip_prefix("grpcs://") {
+        (format!("https://{}", host), true)
+    } else {
+        return Ok(None);
+    };
+
+    let mut endpoint = Endpoint::from_shared(uri)
+        .with_context(|| RemoteSinkError::InvalidEndpoint(address.to_owned()))?;
+    if tls {
+        endpoint = endpoint
+            .tls_config(ClientTlsConfig::new())
+            .context("Error configuring TLS")?;
+    }
+    Ok(Some(endpoint))
+}
+
+/// Converts `headers` to gRPC metadata.
+pub(crate) fn grpc_headers(
+    headers: &[(String, String)],
+) -> anyhow::Result<Vec<(AsciiMetadataKey, AsciiMetadataValue)>> {
+    headers
+        .iter()
+        .map(|(key, value)| {
+            anyhow::Ok((
+                AsciiMetadataKey::from_bytes(key.as_bytes())
+                    .map_err(|_| RemoteSinkError::InvalidHeader(key.clone()))?,
+                AsciiMetadataValue::try_from(value.as_str())
+                    .map_err(|_| RemoteSinkError::InvalidHeader(key.clone()))?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue_drops_when_full() {
+        let (sender, mut receiver) = queue(2, "Service", "items");
+        sender.send(1);
+        sender.send(2);
+        sender.send(3);
+        sender.force_send(4);
+
+        assert_eq!(receiver.recv().await, Some(1));
+        assert_eq!(receiver.try_recv(), Some(2));
+        // There is room again.
+        sender.send(5);
+        assert_eq!(receiver.try_recv(), Some(4));
+        assert_eq!(receiver.try_recv(), Some(5));
+        assert_eq!(receiver.try_recv(), None);
+        drop(sender);
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[test]
+    fn test_grpc_endpoint() -> anyhow::Result<()> {
+        let endpoint = grpc_endpoint("grpc://localhost:1985")?.unwrap();
+        assert_eq!(endpoint.uri().scheme_str(), Some("http"));
+        assert_eq!(endpoint.uri().host(), Some("localhost"));
+        assert!(grpc_endpoint("http://localhost:1985")?.is_none());
+        assert!(grpc_endpoint("grpc://local host").is_err());
+        Ok(())
+    }
+}
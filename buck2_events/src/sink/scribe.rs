@@ -274,6 +274,7 @@ mod fbcode {
 
                 match i.data {
                     Some(Data::RawOutput(..)) => false,
+                    Some(Data::CommandStderr(..)) => false,
                     Some(Data::Snapshot(..)) => false,
                     Some(Data::DiceStateSnapshot(..)) => false,
                     Some(Data::LspResult(..)) => false,
@@ -48,8 +48,16 @@ pub enum CliArgValue {
 pub struct BxlKey(Arc<BxlKeyData>);
 
 impl BxlKey {
-    pub fn new(spec: BxlFunctionLabel, bxl_args: Arc<OrderedMap<String, CliArgValue>>) -> Self {
-        Self(Arc::new(BxlKeyData { spec, bxl_args }))
+    pub fn new(
+        spec: BxlFunctionLabel,
+        bxl_args: Arc<OrderedMap<String, CliArgValue>>,
+        modifiers: Arc<Vec<TargetLabel>>,
+    ) -> Self {
+        Self(Arc::new(BxlKeyData {
+            spec,
+            bxl_args,
+            modifiers,
+        }))
     }
 
     pub fn label(&self) -> &BxlFunctionLabel {
@@ -59,6 +67,10 @@ impl BxlKey {
     pub fn cli_args(&self) -> &Arc<OrderedMap<String, CliArgValue>> {
         &self.0.bxl_args
     }
+
+    pub fn modifiers(&self) -> &Arc<Vec<TargetLabel>> {
+        &self.0.modifiers
+    }
 }
 
 #[derive(
@@ -68,6 +80,9 @@ impl BxlKey {
 struct BxlKeyData {
     spec: BxlFunctionLabel,
     bxl_args: Arc<OrderedMap<String, CliArgValue>>,
+    /// The configuration modifiers of the command, which apply to the targets the function
+    /// configures, so a function evaluated with different modifiers is a different key.
+    modifiers: Arc<Vec<TargetLabel>>,
 }
 
 fn print_like_args(args: &Arc<OrderedMap<String, CliArgValue>>) -> String {
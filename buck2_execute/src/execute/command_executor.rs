@@ -30,6 +30,7 @@ use crate::artifact::fs::ExecutorFs;
 use crate::digest::CasDigestToReExt;
 use crate::directory::insert_entry;
 use crate::directory::ActionDirectoryMember;
+use crate::execute::action_digest::ActionDigest;
 use crate::execute::blobs::ActionBlobs;
 use crate::execute::inputs_directory::inputs_directory;
 use crate::execute::manager::CommandExecutionManager;
@@ -123,6 +124,14 @@ impl CommandExecutor {
             .await
     }
 
+    /// The digest of the action a command would be executed as. This is what results are cached
+    /// against in the action cache, so it is stable as long as the command, its environment and
+    /// its inputs are.
+    pub fn action_digest(&self, request: &CommandExecutionRequest) -> anyhow::Result<ActionDigest> {
+        let (_, prepared_action) = self.prepare_action(request)?;
+        Ok(prepared_action.action)
+    }
+
     async fn prepare(
         &self,
         mut manager: CommandExecutionManager,
@@ -130,42 +139,7 @@ impl CommandExecutor {
     ) -> ControlFlow<CommandExecutionResult, (CommandExecutionManager, ActionPaths, PreparedAction)>
     {
         let (action_paths, action) = match manager.stage(buck2_data::PrepareAction {}, || {
-            let action_paths = self.preamble(request.inputs(), request.outputs())?;
-            let input_digest = action_paths.inputs.fingerprint();
-
-            let mut output_files = Vec::new();
-            let mut output_dirs = Vec::new();
-            for (output, output_type) in &action_paths.outputs {
-                match output_type {
-                    OutputType::FileOrDirectory => {
-                        output_files.push(output.as_str().to_owned());
-                        output_dirs.push(output.as_str().to_owned());
-                    }
-                    OutputType::File => output_files.push(output.as_str().to_owned()),
-                    OutputType::Directory => output_dirs.push(output.as_str().to_owned()),
-                }
-            }
-
-            let action_metadata_blobs = request.inputs().iter().filter_map(|x| match x {
-                CommandExecutionInput::Artifact(_) => None,
-                CommandExecutionInput::ActionMetadata(metadata) => {
-                    Some((metadata.data.clone(), metadata.digest.dupe()))
-                }
-            });
-            let action = re_create_action(
-                request.args().to_vec(),
-                output_files,
-                output_dirs,
-                request.working_directory().map(|p| p.as_str().to_owned()),
-                request.env(),
-                input_digest,
-                action_metadata_blobs,
-                None,
-                self.0.inner.re_platform().cloned(),
-                false,
-            );
-
-            anyhow::Ok((action_paths, action))
+            self.prepare_action(request)
         }) {
             Ok(v) => v,
             Err(e) => return ControlFlow::Break(manager.error("prepare", e)),
@@ -174,6 +148,48 @@ impl CommandExecutor {
         ControlFlow::Continue((manager, action_paths, action))
     }
 
+    fn prepare_action(
+        &self,
+        request: &CommandExecutionRequest,
+    ) -> anyhow::Result<(ActionPaths, PreparedAction)> {
+        let action_paths = self.preamble(request.inputs(), request.outputs())?;
+        let input_digest = action_paths.inputs.fingerprint();
+
+        let mut output_files = Vec::new();
+        let mut output_dirs = Vec::new();
+        for (output, output_type) in &action_paths.outputs {
+            match output_type {
+                OutputType::FileOrDirectory => {
+                    output_files.push(output.as_str().to_owned());
+                    output_dirs.push(output.as_str().to_owned());
+                }
+                OutputType::File => output_files.push(output.as_str().to_owned()),
+                OutputType::Directory => output_dirs.push(output.as_str().to_owned()),
+            }
+        }
+
+        let action_metadata_blobs = request.inputs().iter().filter_map(|x| match x {
+            CommandExecutionInput::Artifact(_) => None,
+            CommandExecutionInput::ActionMetadata(metadata) => {
+                Some((metadata.data.clone(), metadata.digest.dupe()))
+            }
+        });
+        let action = re_create_action(
+            request.args().to_vec(),
+            output_files,
+            output_dirs,
+            request.working_directory().map(|p| p.as_str().to_owned()),
+            request.env(),
+            input_digest,
+            action_metadata_blobs,
+            None,
+            self.0.inner.re_platform().cloned(),
+            false,
+        );
+
+        Ok((action_paths, action))
+    }
+
     /// Return the inputs (in the form of a ActionImmutableDirectory) and the outputs for this
     /// action.
     fn preamble<'a>(
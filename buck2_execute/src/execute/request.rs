@@ -9,6 +9,7 @@
 
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::sync::Arc;
 use std::time::Duration;
 
 use allocative::Allocative;
@@ -24,6 +25,7 @@ use thiserror::Error;
 
 use crate::artifact::fs::ArtifactFs;
 use crate::artifact::group::artifact_group_values_dyn::ArtifactGroupValuesDyn;
+use crate::directory::ActionSharedDirectory;
 use crate::execute::environment_inheritance::EnvironmentInheritance;
 use crate::path::buck_out_path::BuckOutPath;
 use crate::path::buck_out_path::BuckOutTestPath;
@@ -35,6 +37,18 @@ pub struct ActionMetadataBlob {
     pub path: BuckOutPath,
 }
 
+/// The dep files of a command. This lets the action cache serve the result of a command that only
+/// differs from this one in inputs that the dep files say weren't used.
+#[derive(Clone)]
+pub struct RemoteDepFiles {
+    /// Identifies the command, except for the inputs covered by dep files.
+    pub key: String,
+    /// The inputs covered by dep files, by dep file label.
+    pub tagged_inputs: HashMap<Arc<str>, ActionSharedDirectory>,
+    /// The path of each dep file produced by the command, by label.
+    pub dep_files: HashMap<Arc<str>, ProjectRelativePathBuf>,
+}
+
 pub enum CommandExecutionInput {
     Artifact(Box<dyn ArtifactGroupValuesDyn>),
     ActionMetadata(ActionMetadataBlob),
@@ -140,9 +154,14 @@ pub struct CommandExecutionRequest {
     local_environment_inheritance: Option<EnvironmentInheritance>,
     /// Whether this command should be uploaded to cache when successful.
     allow_cache_upload: bool,
+    /// Whether a result for this command may be looked up in the action cache instead of running
+    /// it.
+    allow_cache_lookup: bool,
     /// Whether this command should override the fallback-only behavior on an hybrid executor and
     /// thus always run as if the executor was full-hybrid, assuming it is capable.
     force_full_hybrid_if_capable: bool,
+    /// The dep files of this command, if it has any.
+    remote_dep_files: Option<RemoteDepFiles>,
 }
 
 impl CommandExecutionRequest {
@@ -167,7 +186,9 @@ impl CommandExecutionRequest {
             outputs_cleanup: true,
             local_environment_inheritance: None,
             allow_cache_upload: false,
+            allow_cache_lookup: true,
             force_full_hybrid_if_capable: false,
+            remote_dep_files: None,
         }
     }
 
@@ -294,6 +315,15 @@ impl CommandExecutionRequest {
         self.allow_cache_upload
     }
 
+    pub fn with_allow_cache_lookup(mut self, allow_cache_lookup: bool) -> Self {
+        self.allow_cache_lookup = allow_cache_lookup;
+        self
+    }
+
+    pub fn allow_cache_lookup(&self) -> bool {
+        self.allow_cache_lookup
+    }
+
     pub fn with_force_full_hybrid_if_capable(mut self, force_full_hybrid_if_capable: bool) -> Self {
         self.force_full_hybrid_if_capable = force_full_hybrid_if_capable;
         self
@@ -302,6 +332,15 @@ impl CommandExecutionRequest {
     pub fn force_full_hybrid_if_capable(&self) -> bool {
         self.force_full_hybrid_if_capable
     }
+
+    pub fn with_remote_dep_files(mut self, remote_dep_files: RemoteDepFiles) -> Self {
+        self.remote_dep_files = Some(remote_dep_files);
+        self
+    }
+
+    pub fn remote_dep_files(&self) -> Option<&RemoteDepFiles> {
+        self.remote_dep_files.as_ref()
+    }
 }
 
 /// Is an output a file or a directory
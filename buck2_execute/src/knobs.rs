@@ -11,4 +11,8 @@ use gazebo::dupe::Dupe;
 
 /// Daemon-level config that can tweak how the executors work.
 #[derive(Clone, Dupe, Default)]
-pub struct ExecutorGlobalKnobs {}
+pub struct ExecutorGlobalKnobs {
+    /// Whether to look up and record results of commands with dep files in the action cache by
+    /// the inputs they used, rather than all their inputs.
+    pub remote_dep_file_cache: bool,
+}
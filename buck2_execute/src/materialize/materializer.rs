@@ -249,6 +249,12 @@ pub trait Materializer: Allocative + Send + Sync + 'static {
     fn as_deferred_materializer_extension(&self) -> Option<&dyn DeferredMaterializerExtensions> {
         None
     }
+
+    /// Expose the store used to persist dep file state alongside the materializer state.
+    /// Return None if the materializer doesn't persist its state across restarts.
+    fn dep_file_state_store(&self) -> Option<&dyn DepFileStateStore> {
+        None
+    }
 }
 
 #[derive(Copy, Clone, Dupe, Debug)]
@@ -568,3 +574,18 @@ pub trait DeferredMaterializerExtensions: Send + Sync {
         dry_run: bool,
     ) -> anyhow::Result<String>;
 }
+
+/// Storage for the dep file state of actions, keyed by a string identifying the action. This lives
+/// alongside the materializer state since the outputs recorded in a dep file state are only usable
+/// if the materializer still knows about them.
+#[async_trait]
+pub trait DepFileStateStore: Send + Sync {
+    async fn get(&self, key: String) -> anyhow::Result<Option<String>>;
+
+    /// Insert the state for `key`, replacing any existing state.
+    async fn insert(&self, key: String, state: String) -> anyhow::Result<()>;
+
+    async fn delete(&self, key: String) -> anyhow::Result<()>;
+
+    async fn delete_all(&self) -> anyhow::Result<()>;
+}
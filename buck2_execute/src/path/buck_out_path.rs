@@ -100,6 +100,10 @@ impl BuckOutPath {
         &self.0.path
     }
 
+    pub fn hidden_components_count(&self) -> usize {
+        self.0.hidden_components_count
+    }
+
     // The suffix of `path` that is usually relevant to user rules.
     pub fn short_path(&self) -> &ForwardRelativePath {
         self.0.path
@@ -359,6 +363,10 @@ impl BaseDeferredKey {
                 let output_hash = {
                     let mut hasher = DefaultHasher::new();
                     key.cli_args().hash(&mut hasher);
+                    // Only hashed when set, so that outputs without modifiers keep their paths.
+                    if !key.modifiers().is_empty() {
+                        key.modifiers().hash(&mut hasher);
+                    }
                     let output_hash = hasher.finish();
                     format!("{:x}", output_hash)
                 };
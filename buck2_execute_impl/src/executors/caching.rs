@@ -55,6 +55,9 @@ use remote_execution::TStatus;
 use remote_execution::TTimestamp;
 use tracing::info;
 
+use crate::re::dep_files::index_digest;
+use crate::re::dep_files::DepFileIndex;
+use crate::re::dep_files::DepFileIndexEntry;
 use crate::re::download::download_action_results;
 
 // Whether to throw errors when cache uploads fail (primarily for tests).
@@ -163,6 +166,147 @@ impl CachingExecutor {
         )
     }
 
+    /// Look for the result of a command that only differs from this one in inputs that aren't used
+    /// according to its dep files. See `crate::re::dep_files`.
+    async fn try_remote_dep_file_cache_fetch(
+        &self,
+        mut manager: CommandExecutionManager,
+        request: &CommandExecutionRequest,
+        action_paths: &ActionPaths,
+    ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
+        let dep_files = match request.remote_dep_files() {
+            Some(dep_files) if self.knobs.remote_dep_file_cache => dep_files,
+            _ => return ControlFlow::Continue(manager),
+        };
+
+        let index_digest = index_digest(dep_files, self.re_platform());
+
+        let lookup = manager
+            .stage_async(
+                buck2_data::CacheQuery {
+                    action_digest: index_digest.to_string(),
+                },
+                async {
+                    let index =
+                        DepFileIndex::read(&self.re_client, self.re_use_case(), &index_digest)
+                            .await?;
+                    let action_digest = match index.find_match(dep_files)? {
+                        Some(action_digest) => action_digest,
+                        None => return anyhow::Ok(None),
+                    };
+                    let response = self
+                        .re_client
+                        .action_cache(action_digest.dupe(), self.re_use_case())
+                        .await?;
+                    Ok(response.map(|response| (action_digest, response)))
+                },
+            )
+            .await;
+
+        let (action_digest, response) = match lookup {
+            Ok(Some(found)) => found,
+            Ok(None) => return ControlFlow::Continue(manager),
+            Err(e) => {
+                // This is only a cache, so we don't fail the command if it doesn't work.
+                tracing::warn!(
+                    "Error looking up dep file index `{}`: {:#}",
+                    index_digest,
+                    e
+                );
+                return ControlFlow::Continue(manager);
+            }
+        };
+
+        info!(
+            "Action result is cached for the inputs used according to dep files, skipping execution of:\n```\n$ {}\n```\n for action `{}`",
+            request.args().join(" "),
+            action_digest,
+        );
+
+        ControlFlow::Break(
+            download_action_results(
+                request,
+                &*self.materializer,
+                &self.re_client,
+                self.re_use_case(),
+                manager,
+                buck2_data::CacheHit {
+                    action_digest: action_digest.to_string(),
+                }
+                .into(),
+                action_paths,
+                request.outputs(),
+                &action_digest,
+                &response,
+            )
+            .await,
+        )
+    }
+
+    /// Record in the dep file index that the result of this command is in the action cache, so
+    /// that commands that only differ from it in inputs it didn't use can reuse it.
+    ///
+    /// The dep files are read before this returns, since later actions may overwrite them. Only
+    /// reading and writing back the index is done in the background, so as not to delay the
+    /// completion of the action.
+    async fn maybe_record_remote_dep_files(
+        &self,
+        request: &CommandExecutionRequest,
+        digest: &ActionDigest,
+        result: &CommandExecutionResult,
+    ) {
+        let dep_files = match request.remote_dep_files() {
+            Some(dep_files) if self.knobs.remote_dep_file_cache => dep_files,
+            _ => return,
+        };
+
+        // Writing the index is a cache upload, so it's subject to the same settings.
+        if !request.allow_cache_upload()
+            || matches!(self.cache_upload_behavior, CacheUploadBehavior::Disabled)
+        {
+            return;
+        }
+
+        // We can only point at results that are in the action cache.
+        let cached_digest = match &result.report.status {
+            CommandExecutionStatus::Success {
+                execution_kind: CommandExecutionKind::Local { .. },
+            } if result.did_cache_upload => digest,
+            CommandExecutionStatus::Success {
+                execution_kind: CommandExecutionKind::Remote { digest },
+            } => digest,
+            _ => return,
+        };
+
+        let entry =
+            match DepFileIndexEntry::new(dep_files, cached_digest, &self.fs, &*self.materializer)
+                .await
+            {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::warn!("Reading dep files for `{}` failed: {:#}", cached_digest, e);
+                    return;
+                }
+            };
+
+        let index_digest = index_digest(dep_files, self.re_platform());
+        let action_digest = cached_digest.dupe();
+        let re_client = self.re_client.dupe();
+        let re_use_case = self.re_use_case();
+
+        tokio::spawn(async move {
+            if let Err(e) =
+                record_remote_dep_files(entry, &index_digest, &re_client, re_use_case).await
+            {
+                tracing::warn!(
+                    "Recording dep files for `{}` failed: {:#}",
+                    action_digest,
+                    e
+                );
+            }
+        });
+    }
+
     /// Upload an action result to the RE action cache, assuming conditions for the upload are met:
     /// the action must have been successful and must have run locally (not much point in caching
     /// something that ran on RE and is already cached), and cache uploads must be enabled, both
@@ -432,15 +576,21 @@ impl PreparedCommandExecutor for CachingExecutor {
             Err(e) => return manager.error("cache_upload", e),
         };
 
-        let manager = self
-            .try_action_cache_fetch(
-                manager,
-                command.request,
-                &command.action_paths,
-                &command.prepared_action.action,
-                &command.prepared_action.blobs,
-            )
-            .await?;
+        let manager = if command.request.allow_cache_lookup() {
+            let manager = self
+                .try_action_cache_fetch(
+                    manager,
+                    command.request,
+                    &command.action_paths,
+                    &command.prepared_action.action,
+                    &command.prepared_action.blobs,
+                )
+                .await?;
+            self.try_remote_dep_file_cache_fetch(manager, command.request, &command.action_paths)
+                .await?
+        } else {
+            manager
+        };
 
         let mut res = self.inner.exec_cmd(command, manager).await;
 
@@ -490,6 +640,9 @@ impl PreparedCommandExecutor for CachingExecutor {
             }
         };
 
+        self.maybe_record_remote_dep_files(command.request, &command.prepared_action.action, &res)
+            .await;
+
         res
     }
 
@@ -502,6 +655,17 @@ impl PreparedCommandExecutor for CachingExecutor {
     }
 }
 
+async fn record_remote_dep_files(
+    entry: DepFileIndexEntry,
+    index_digest: &ActionDigest,
+    re_client: &ManagedRemoteExecutionClient,
+    re_use_case: RemoteExecutorUseCase,
+) -> anyhow::Result<()> {
+    let mut index = DepFileIndex::read(re_client, re_use_case, index_digest).await?;
+    index.insert(entry);
+    index.write(re_client, re_use_case, index_digest).await
+}
+
 /// Whether we completed a cache upload.
 #[derive(Copy, Clone, Dupe, Debug)]
 enum CacheUploadOutcome {
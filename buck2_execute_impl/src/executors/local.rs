@@ -29,6 +29,7 @@ use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_core::fs::project::ProjectRelativePath;
 use buck2_core::process::background_command;
+use buck2_events::dispatch::get_dispatcher;
 use buck2_execute::artifact::fs::ArtifactFs;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::directory::extract_artifact_value;
@@ -58,7 +59,7 @@ use buck2_execute::execute::target::CommandExecutionTarget;
 use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_forkserver::client::ForkserverClient;
-use buck2_forkserver::run::gather_output;
+use buck2_forkserver::run::gather_output_observing_stderr;
 use buck2_forkserver::run::timeout_into_cancellation;
 use buck2_forkserver::run::GatherOutputStatus;
 use derive_more::From;
@@ -128,6 +129,7 @@ impl LocalExecutor {
         timeout: Option<Duration>,
         env_inheritance: Option<&'a EnvironmentInheritance>,
         liveliness_manager: Arc<dyn LivelinessManager>,
+        on_stderr: impl FnMut(&[u8]) + Send + 'a,
     ) -> impl futures::future::Future<Output = anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>> + 'a
     {
         async move {
@@ -151,13 +153,14 @@ impl LocalExecutor {
                             timeout,
                             env_inheritance,
                             liveliness_manager,
+                            on_stderr,
                         )
                         .await
                     }
 
                     #[cfg(not(unix))]
                     {
-                        let _unused = forkserver;
+                        let _unused = (forkserver, on_stderr);
                         Err(anyhow::anyhow!("Forkserver is not supported off-UNIX"))
                     }
                 }
@@ -178,7 +181,7 @@ impl LocalExecutor {
                         .map(|()| anyhow::Ok(GatherOutputStatus::Cancelled));
                     let cancellation =
                         select(timeout.boxed(), alive.boxed()).map(|r| r.factor_first().0);
-                    gather_output(cmd, cancellation).await
+                    gather_output_observing_stderr(cmd, cancellation, on_stderr).await
                 }
                 .with_context(|| format!("Failed to gather output from command: {}", exe)),
             }
@@ -325,6 +328,15 @@ impl LocalExecutor {
                     let execution_start = Instant::now();
                     let start_time = SystemTime::now();
 
+                    // Stream stderr as it is produced so that the console can show what a running
+                    // command is doing. These events belong to this stage's span.
+                    let events = get_dispatcher();
+                    let on_stderr = move |stderr: &[u8]| {
+                        events.instant_event(buck2_data::CommandStderr {
+                            stderr: String::from_utf8_lossy(stderr).into_owned(),
+                        })
+                    };
+
                     let env = iter_env().map(|(k, v)| (k, v.into_os_str()));
                     let r = self
                         .exec(
@@ -335,6 +347,7 @@ impl LocalExecutor {
                             request.timeout(),
                             request.local_environment_inheritance(),
                             liveliness_manager,
+                            on_stderr,
                         )
                         .await;
 
@@ -395,8 +408,7 @@ impl LocalExecutor {
         for output in request.outputs() {
             let path = output.resolve(&self.artifact_fs).into_path();
             let abspath = self.root.join(&path);
-            let entry = self
-                .build_entry_from_disk(abspath)
+            let entry = build_entry_from_disk(abspath)
                 .with_context(|| format!("collecting output {:?}", path))?;
             if let Some(entry) = entry {
                 insert_entry(&mut builder, path.as_ref(), entry)?;
@@ -430,74 +442,74 @@ impl LocalExecutor {
 
         Ok(mapped_outputs)
     }
+}
 
-    fn build_entry_from_disk(
-        &self,
-        mut path: AbsNormPathBuf,
-    ) -> anyhow::Result<Option<ActionDirectoryEntry<ActionDirectoryBuilder>>> {
-        fn build_dir_from_disk(
-            disk_path: &mut AbsNormPathBuf,
-        ) -> anyhow::Result<ActionDirectoryBuilder> {
-            let mut builder = ActionDirectoryBuilder::empty();
-
-            for file in fs_util::read_dir(&disk_path)? {
-                let file = file?;
-                let filetype = file.file_type()?;
-                let filename = file.file_name();
-
-                let filename = filename
-                    .to_str()
-                    .context("Filename is not UTF-8")
-                    .and_then(|f| FileNameBuf::try_from(f.to_owned()))
-                    .with_context(|| format!("Invalid filename: {}", disk_path.display()))?;
-
-                disk_path.push(&filename);
-
-                if filetype.is_dir() {
-                    let dir = build_dir_from_disk(disk_path)?;
-                    builder.insert(filename, DirectoryEntry::Dir(dir))?;
-                } else if filetype.is_symlink() {
-                    builder.insert(
-                        filename,
-                        DirectoryEntry::Leaf(new_symlink(fs_util::read_link(&disk_path)?)?),
-                    )?;
-                } else if filetype.is_file() {
-                    let metadata = FileMetadata {
-                        digest: TrackedFileDigest::new(FileDigest::from_file(&disk_path)?),
-                        is_executable: file.path().executable(),
-                    };
-                    builder.insert(
-                        filename,
-                        DirectoryEntry::Leaf(ActionDirectoryMember::File(metadata)),
-                    )?;
-                }
-                disk_path.pop();
+/// Build the directory entry for what is at `path` on disk, or `None` if there is nothing there.
+pub fn build_entry_from_disk(
+    mut path: AbsNormPathBuf,
+) -> anyhow::Result<Option<ActionDirectoryEntry<ActionDirectoryBuilder>>> {
+    fn build_dir_from_disk(
+        disk_path: &mut AbsNormPathBuf,
+    ) -> anyhow::Result<ActionDirectoryBuilder> {
+        let mut builder = ActionDirectoryBuilder::empty();
+
+        for file in fs_util::read_dir(&disk_path)? {
+            let file = file?;
+            let filetype = file.file_type()?;
+            let filename = file.file_name();
+
+            let filename = filename
+                .to_str()
+                .context("Filename is not UTF-8")
+                .and_then(|f| FileNameBuf::try_from(f.to_owned()))
+                .with_context(|| format!("Invalid filename: {}", disk_path.display()))?;
+
+            disk_path.push(&filename);
+
+            if filetype.is_dir() {
+                let dir = build_dir_from_disk(disk_path)?;
+                builder.insert(filename, DirectoryEntry::Dir(dir))?;
+            } else if filetype.is_symlink() {
+                builder.insert(
+                    filename,
+                    DirectoryEntry::Leaf(new_symlink(fs_util::read_link(&disk_path)?)?),
+                )?;
+            } else if filetype.is_file() {
+                let metadata = FileMetadata {
+                    digest: TrackedFileDigest::new(FileDigest::from_file(&disk_path)?),
+                    is_executable: file.path().executable(),
+                };
+                builder.insert(
+                    filename,
+                    DirectoryEntry::Leaf(ActionDirectoryMember::File(metadata)),
+                )?;
             }
-
-            Ok(builder)
+            disk_path.pop();
         }
 
-        // Get file metadata. If the file is missing, ignore it.
-        let m = match std::fs::symlink_metadata(&path) {
-            Ok(m) => m,
-            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
-            Err(err) => return Err(err.into()),
-        };
-
-        let value = if m.file_type().is_symlink() {
-            DirectoryEntry::Leaf(new_symlink(fs_util::read_link(&path)?)?)
-        } else if m.is_file() {
-            DirectoryEntry::Leaf(ActionDirectoryMember::File(FileMetadata {
-                digest: TrackedFileDigest::new(FileDigest::from_file(&path)?),
-                is_executable: path.executable(),
-            }))
-        } else if m.is_dir() {
-            DirectoryEntry::Dir(build_dir_from_disk(&mut path)?)
-        } else {
-            unimplemented!("Path {:?} is of an unknown file type.", path)
-        };
-        Ok(Some(value))
+        Ok(builder)
     }
+
+    // Get file metadata. If the file is missing, ignore it.
+    let m = match std::fs::symlink_metadata(&path) {
+        Ok(m) => m,
+        Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let value = if m.file_type().is_symlink() {
+        DirectoryEntry::Leaf(new_symlink(fs_util::read_link(&path)?)?)
+    } else if m.is_file() {
+        DirectoryEntry::Leaf(ActionDirectoryMember::File(FileMetadata {
+            digest: TrackedFileDigest::new(FileDigest::from_file(&path)?),
+            is_executable: path.executable(),
+        }))
+    } else if m.is_dir() {
+        DirectoryEntry::Dir(build_dir_from_disk(&mut path)?)
+    } else {
+        unimplemented!("Path {:?} is of an unknown file type.", path)
+    };
+    Ok(Some(value))
 }
 
 #[async_trait]
@@ -753,6 +765,7 @@ mod unix {
         comand_timeout: Option<Duration>,
         env_inheritance: Option<&EnvironmentInheritance>,
         liveliness_manager: Arc<dyn LivelinessManager>,
+        on_stderr: impl FnMut(&[u8]) + Send,
     ) -> anyhow::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)> {
         let exe = exe.as_ref();
 
@@ -770,7 +783,7 @@ mod unix {
         };
         apply_local_execution_environment(&mut req, working_directory, env, env_inheritance);
         forkserver
-            .execute(req, liveliness_manager.while_alive_owned())
+            .execute_observing_stderr(req, liveliness_manager.while_alive_owned(), on_stderr)
             .await
     }
 
@@ -837,6 +850,7 @@ mod tests {
     use buck2_execute::materialize::nodisk::NoDiskMaterializer;
     use buck2_execute::path::buck_out_path::BuckOutPathResolver;
     use buck2_execute::path::buck_out_path::BuckPathResolver;
+    use buck2_forkserver::run::gather_output;
     use host_sharing::HostSharingStrategy;
 
     use super::*;
@@ -969,6 +983,7 @@ mod tests {
                 None,
                 None,
                 NoopLivelinessManager::create(),
+                |_| {},
             )
             .await?;
         assert!(matches!(status, GatherOutputStatus::Finished(s) if s.code() == Some(0)));
@@ -1004,6 +1019,7 @@ mod tests {
                 None,
                 Some(&EnvironmentInheritance::empty()),
                 NoopLivelinessManager::create(),
+                |_| {},
             )
             .await?;
         assert!(matches!(status, GatherOutputStatus::Finished(s) if s.code() == Some(0)));
@@ -124,15 +124,35 @@ impl ReExecutor {
     async fn upload(
         &self,
         mut manager: CommandExecutionManager,
+        request: &CommandExecutionRequest,
+        action_digest: &ActionDigest,
         blobs: &ActionBlobs,
         action_paths: &ActionPaths,
     ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
         let re_client = &self.re_client;
 
+        let command = buck2_data::LocalCommand {
+            action_digest: action_digest.to_string(),
+            argv: request.args().to_vec(),
+            env: request
+                .env()
+                .iter()
+                .map(|(k, v)| buck2_data::local_command::EnvironmentEntry {
+                    key: k.clone(),
+                    value: v.clone(),
+                })
+                .collect(),
+        };
+
         let upload_response = manager
             .stage_async(
                 buck2_data::ReStage {
-                    stage: Some(buck2_data::ReUpload {}.into()),
+                    stage: Some(
+                        buck2_data::ReUpload {
+                            command: Some(command),
+                        }
+                        .into(),
+                    ),
                 },
                 re_client.upload(
                     &self.materializer,
@@ -176,7 +196,7 @@ impl ReExecutor {
                 self.re_use_case,
                 &identity,
                 &mut manager,
-                self.skip_cache_lookup,
+                self.skip_cache_lookup || !request.allow_cache_lookup(),
             )
             .await;
 
@@ -237,7 +257,9 @@ impl PreparedCommandExecutor for ReExecutor {
             return ControlFlow::Break(manager.error("remote_prepare", error))?;
         }
 
-        let manager = self.upload(manager, blobs, action_paths).await?;
+        let manager = self
+            .upload(manager, request, action_digest, blobs, action_paths)
+            .await?;
 
         let (manager, response) = self
             .re_execute(manager, target, request, action_digest, action_paths)
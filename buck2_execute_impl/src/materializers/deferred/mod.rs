@@ -54,6 +54,7 @@ use buck2_execute::materialize::materializer::CasDownloadInfo;
 use buck2_execute::materialize::materializer::CopiedArtifact;
 use buck2_execute::materialize::materializer::DeclareMatchOutcome;
 use buck2_execute::materialize::materializer::DeferredMaterializerExtensions;
+use buck2_execute::materialize::materializer::DepFileStateStore;
 use buck2_execute::materialize::materializer::HttpDownloadInfo;
 use buck2_execute::materialize::materializer::MaterializationError;
 use buck2_execute::materialize::materializer::Materializer;
@@ -136,6 +137,10 @@ pub struct DeferredMaterializer {
     /// To be removed, used to implement write for now.
     fs: ProjectRoot,
     io_executor: Arc<dyn BlockingExecutor>,
+
+    /// Shared with the command processor. Also holds the persisted dep file state.
+    #[allocative(skip)]
+    sqlite_db: Option<Arc<MaterializerStateSqliteDb>>,
 }
 
 impl Drop for DeferredMaterializer {
@@ -632,6 +637,12 @@ impl Materializer for DeferredMaterializer {
     fn as_deferred_materializer_extension(&self) -> Option<&dyn DeferredMaterializerExtensions> {
         Some(self as _)
     }
+
+    fn dep_file_state_store(&self) -> Option<&dyn DepFileStateStore> {
+        self.sqlite_db
+            .as_ref()
+            .map(|db| db.dep_file_state_table() as &dyn DepFileStateStore)
+    }
 }
 
 impl DeferredMaterializer {
@@ -647,13 +658,14 @@ impl DeferredMaterializer {
         sqlite_state: Option<MaterializerState>,
     ) -> Self {
         let (command_sender, command_recv) = mpsc::unbounded_channel();
+        let sqlite_db = sqlite_db.map(Arc::new);
 
         let command_processor = Arc::new(DeferredMaterializerCommandProcessor {
             fs: fs.dupe(),
             re_client_manager,
             io_executor: io_executor.dupe(),
             command_sender: command_sender.clone(),
-            sqlite_db: sqlite_db.map(Arc::new),
+            sqlite_db: sqlite_db.dupe(),
             ttl_refresh_frequency: configs.ttl_refresh_frequency,
             ttl_refresh_min_ttl: configs.ttl_refresh_min_ttl,
             ttl_refresh_enabled: configs.ttl_refresh_enabled,
@@ -688,6 +700,7 @@ impl DeferredMaterializer {
             defer_write_actions: configs.defer_write_actions,
             fs,
             io_executor,
+            sqlite_db,
         }
     }
 }
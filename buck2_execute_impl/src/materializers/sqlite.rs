@@ -13,6 +13,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::Context;
+use async_trait::async_trait;
 use buck2_common::external_symlink::ExternalSymlink;
 use buck2_common::file_ops::FileDigest;
 use buck2_common::file_ops::FileMetadata;
@@ -27,6 +28,7 @@ use buck2_core::fs::project::ProjectRelativePathBuf;
 use buck2_execute::directory::ActionDirectoryMember;
 use buck2_execute::directory::Symlink;
 use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_execute::materialize::materializer::DepFileStateStore;
 use chrono::DateTime;
 use chrono::TimeZone;
 use chrono::Utc;
@@ -41,7 +43,7 @@ use crate::materializers::deferred::ArtifactMetadata;
 /// materializer state sqlite db schema! If you forget to bump this version,
 /// then you can fix forward by bumping the `buck2.sqlite_materializer_state_version`
 /// buckconfig in the project root's .buckconfig.
-pub const DB_SCHEMA_VERSION: u64 = 1;
+pub const DB_SCHEMA_VERSION: u64 = 2;
 
 pub type MaterializerState = Vec<(ProjectRelativePathBuf, (ArtifactMetadata, DateTime<Utc>))>;
 
@@ -402,6 +404,90 @@ impl MaterializerStateSqliteTable {
     }
 }
 
+/// Table storing the serialized dep file state of actions, so that dep files can still be used to
+/// skip actions after a restart. The state is opaque to the materializer.
+#[derive(Clone)]
+pub(crate) struct DepFileStateSqliteTable {
+    connection: Arc<tokio_rusqlite::Connection>,
+}
+
+impl DepFileStateSqliteTable {
+    const TABLE_NAME: &'static str = "dep_file_state";
+
+    pub fn new(connection: Arc<tokio_rusqlite::Connection>) -> Self {
+        Self { connection }
+    }
+
+    pub(crate) async fn create_table(&self) -> anyhow::Result<()> {
+        let sql = format!(
+            "CREATE TABLE {} (
+                key     TEXT NOT NULL PRIMARY KEY,
+                state   TEXT NOT NULL
+            )",
+            Self::TABLE_NAME,
+        );
+        tracing::trace!(sql = %sql, "creating table");
+        self.connection
+            .call(move |connection| connection.execute(&sql, []))
+            .await
+            .with_context(|| format!("creating sqlite table {}", Self::TABLE_NAME))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DepFileStateStore for DepFileStateSqliteTable {
+    async fn get(&self, key: String) -> anyhow::Result<Option<String>> {
+        let sql = format!("SELECT state FROM {} WHERE key = (?1)", Self::TABLE_NAME);
+        tracing::trace!(sql = %sql, key = %key, "reading from table");
+        let state = self
+            .connection
+            .call(move |connection| {
+                let mut stmt = connection.prepare(&sql)?;
+                let mut rows =
+                    stmt.query_map(rusqlite::params![key], |row| row.get::<_, String>(0))?;
+                let state = rows.next().transpose();
+                state
+            })
+            .await
+            .with_context(|| format!("reading from sqlite table {}", Self::TABLE_NAME))?;
+        Ok(state)
+    }
+
+    async fn insert(&self, key: String, state: String) -> anyhow::Result<()> {
+        let sql = format!(
+            "INSERT OR REPLACE INTO {} (key, state) VALUES (?1, ?2)",
+            Self::TABLE_NAME
+        );
+        tracing::trace!(sql = %sql, key = %key, "inserting into table");
+        self.connection
+            .call(move |connection| connection.execute(&sql, rusqlite::params![key, state]))
+            .await
+            .with_context(|| format!("inserting into sqlite table {}", Self::TABLE_NAME))?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: String) -> anyhow::Result<()> {
+        let sql = format!("DELETE FROM {} WHERE key = (?1)", Self::TABLE_NAME);
+        tracing::trace!(sql = %sql, key = %key, "deleting from table");
+        self.connection
+            .call(move |connection| connection.execute(&sql, rusqlite::params![key]))
+            .await
+            .with_context(|| format!("deleting from sqlite table {}", Self::TABLE_NAME))?;
+        Ok(())
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let sql = format!("DELETE FROM {}", Self::TABLE_NAME);
+        tracing::trace!(sql = %sql, "deleting all from table");
+        self.connection
+            .call(move |connection| connection.execute(&sql, []))
+            .await
+            .with_context(|| format!("deleting from sqlite table {}", Self::TABLE_NAME))?;
+        Ok(())
+    }
+}
+
 #[derive(Error, Debug, PartialEq, Eq)]
 enum MaterializerStateSqliteDbError {
     #[error("Path {} does not exist", .0)]
@@ -420,6 +506,9 @@ enum MaterializerStateSqliteDbError {
 pub struct MaterializerStateSqliteDb {
     /// Table storing actual materializer state
     materializer_state_table: MaterializerStateSqliteTable,
+    /// Table storing the dep file state of actions, whose outputs are validated against
+    /// `materializer_state_table` when the state is used.
+    dep_file_state_table: DepFileStateSqliteTable,
     /// Table for holding any metadata used to check version match. When loading
     /// from an existing db, we check if the versions from this table match the
     /// versions this buck2 binary expects. If the versions don't match, we throw
@@ -436,10 +525,12 @@ impl MaterializerStateSqliteDb {
     pub async fn open(path: &AbsNormPath) -> anyhow::Result<Self> {
         let connection = Arc::new(tokio_rusqlite::Connection::open(path).await?);
         let materializer_state_table = MaterializerStateSqliteTable::new(connection.dupe());
+        let dep_file_state_table = DepFileStateSqliteTable::new(connection.dupe());
         let versions_table = KeyValueSqliteTable::new("versions".to_owned(), connection.dupe());
         let metadata_table = KeyValueSqliteTable::new("metadata".to_owned(), connection);
         Ok(Self {
             materializer_state_table,
+            dep_file_state_table,
             versions_table,
             metadata_table,
         })
@@ -522,10 +613,15 @@ impl MaterializerStateSqliteDb {
         &self.materializer_state_table
     }
 
+    pub(crate) fn dep_file_state_table(&self) -> &DepFileStateSqliteTable {
+        &self.dep_file_state_table
+    }
+
     pub(crate) async fn create_all_tables(&self) -> anyhow::Result<()> {
         // We can do these awaits in serial because writes through the same `Connection`
         // get serialized anyways.
         self.materializer_state_table.create_table().await?;
+        self.dep_file_state_table.create_table().await?;
         self.versions_table.create_table().await?;
         self.metadata_table.create_table().await?;
         Ok(())
@@ -690,6 +786,50 @@ mod tests {
         assert_eq!(artifacts, state.into_iter().collect::<HashMap<_, _>>());
     }
 
+    #[tokio::test]
+    async fn test_dep_file_state_sqlite_table() {
+        let fs = ProjectRootTemp::new().unwrap();
+        let connection = tokio_rusqlite::Connection::open(
+            fs.path()
+                .resolve(ProjectRelativePath::unchecked_new("test.db")),
+        )
+        .await
+        .unwrap();
+        let table = DepFileStateSqliteTable::new(Arc::new(connection));
+
+        table.create_table().await.unwrap();
+
+        assert_eq!(table.get("foo".to_owned()).await.unwrap(), None);
+
+        table
+            .insert("foo".to_owned(), "1".to_owned())
+            .await
+            .unwrap();
+        table
+            .insert("bar".to_owned(), "2".to_owned())
+            .await
+            .unwrap();
+        // Inserting again replaces the existing state.
+        table
+            .insert("foo".to_owned(), "3".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            table.get("foo".to_owned()).await.unwrap(),
+            Some("3".to_owned())
+        );
+
+        table.delete("foo".to_owned()).await.unwrap();
+        assert_eq!(table.get("foo".to_owned()).await.unwrap(), None);
+        assert_eq!(
+            table.get("bar".to_owned()).await.unwrap(),
+            Some("2".to_owned())
+        );
+
+        table.delete_all().await.unwrap();
+        assert_eq!(table.get("bar".to_owned()).await.unwrap(), None);
+    }
+
     async fn testing_materializer_state_sqlite_db(
         fs: &ProjectRoot,
         versions: HashMap<String, Option<String>>,
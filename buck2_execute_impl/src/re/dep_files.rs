@@ -0,0 +1,396 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! An index, stored in the action cache, of the results of commands with dep files. The index is
+//! keyed by the command without the inputs covered by its dep files. Each entry records the inputs
+//! a previous execution used according to its dep files, their fingerprints, and the action whose
+//! result is in the action cache. A command can reuse that result if the inputs it has at those
+//! paths have the same fingerprints, which is what `DepFileState` does locally.
+//!
+//! The index is the only output file of an action result written under a digest derived from the
+//! key. Concurrent updates to the same index may drop each other's entries, which is fine since
+//! this is only a cache.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use buck2_common::executor_config::RemoteExecutorUseCase;
+use buck2_core::directory::DirectorySelector;
+use buck2_core::directory::FingerprintedDirectory;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::project::ProjectRelativePath;
+use buck2_execute::artifact::fs::ArtifactFs;
+use buck2_execute::digest::CasDigestToReExt;
+use buck2_execute::directory::expand_selector_for_dependencies;
+use buck2_execute::directory::ActionDirectoryBuilder;
+use buck2_execute::directory::ActionSharedDirectory;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::request::RemoteDepFiles;
+use buck2_execute::materialize::materializer::Materializer;
+use buck2_execute::re::manager::ManagedRemoteExecutionClient;
+use gazebo::prelude::*;
+use remote_execution as RE;
+use remote_execution::DigestWithStatus;
+use remote_execution::TActionResult2;
+use remote_execution::TCode;
+use remote_execution::TFile;
+use remote_execution::TStatus;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The name of the output file holding the index.
+const INDEX_OUTPUT_NAME: &str = "buck2_dep_file_index.json";
+
+/// How many entries we keep in an index. New entries replace the oldest ones.
+const MAX_INDEX_ENTRIES: usize = 16;
+
+/// The digest under which we store the index for the command described by `dep_files`, when
+/// executed on `platform`.
+pub(crate) fn index_digest(
+    dep_files: &RemoteDepFiles,
+    platform: Option<&RE::Platform>,
+) -> ActionDigest {
+    let mut key = dep_files.key.clone();
+    for property in platform.into_iter().flat_map(|p| p.properties.iter()) {
+        key.push('\n');
+        key.push_str(&property.name);
+        key.push('=');
+        key.push_str(&property.value);
+    }
+    ActionDigest::from_bytes_sha1(key.as_bytes())
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct DepFileIndex {
+    entries: Vec<DepFileIndexEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DepFileIndexEntry {
+    /// The action whose result is in the action cache.
+    action_digest: String,
+    /// The inputs that the action used, by dep file label.
+    used_inputs: BTreeMap<String, Vec<String>>,
+    /// The fingerprint of the inputs that the action used, by dep file label.
+    fingerprints: BTreeMap<String, String>,
+}
+
+impl DepFileIndex {
+    /// Read the index stored at `digest`. This is empty if there is no index there yet.
+    pub(crate) async fn read(
+        re_client: &ManagedRemoteExecutionClient,
+        re_use_case: RemoteExecutorUseCase,
+        digest: &ActionDigest,
+    ) -> anyhow::Result<Self> {
+        let response = match re_client.action_cache(digest.dupe(), re_use_case).await? {
+            Some(response) => response,
+            None => return Ok(Self::default()),
+        };
+
+        let file = match response
+            .action_result
+            .output_files
+            .iter()
+            .find(|f| f.name == INDEX_OUTPUT_NAME)
+        {
+            Some(file) => file,
+            None => return Ok(Self::default()),
+        };
+
+        let blob = re_client
+            .download_blob(&file.digest.digest, re_use_case)
+            .await?;
+        serde_json::from_slice(&blob).context("Invalid dep file index")
+    }
+
+    pub(crate) async fn write(
+        &self,
+        re_client: &ManagedRemoteExecutionClient,
+        re_use_case: RemoteExecutorUseCase,
+        digest: &ActionDigest,
+    ) -> anyhow::Result<()> {
+        let blob_digest = re_client
+            .upload_blob(serde_json::to_vec(self)?, re_use_case)
+            .await?;
+
+        let result = TActionResult2 {
+            output_files: vec![TFile {
+                digest: DigestWithStatus {
+                    digest: blob_digest,
+                    status: TStatus {
+                        code: TCode::OK,
+                        message: String::new(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                name: INDEX_OUTPUT_NAME.to_owned(),
+                executable: false,
+                ..Default::default()
+            }],
+            exit_code: 0,
+            ..Default::default()
+        };
+
+        re_client
+            .write_action_result(digest.to_re(), result, re_use_case)
+            .await
+    }
+
+    /// Find an action whose result can be reused by the command described by `dep_files`.
+    pub(crate) fn find_match(
+        &self,
+        dep_files: &RemoteDepFiles,
+    ) -> anyhow::Result<Option<ActionDigest>> {
+        for entry in &self.entries {
+            if entry.matches(dep_files)? {
+                return Ok(Some(ActionDigest::parse_digest_sha1(&entry.action_digest)?));
+            }
+        }
+        Ok(None)
+    }
+
+    pub(crate) fn insert(&mut self, entry: DepFileIndexEntry) {
+        self.entries
+            .retain(|e| e.action_digest != entry.action_digest);
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_INDEX_ENTRIES);
+    }
+}
+
+impl DepFileIndexEntry {
+    /// Create the entry for a command that ran as `action_digest`, reading the dep files it
+    /// produced.
+    pub(crate) async fn new(
+        dep_files: &RemoteDepFiles,
+        action_digest: &ActionDigest,
+        fs: &ArtifactFs,
+        materializer: &dyn Materializer,
+    ) -> anyhow::Result<Self> {
+        materializer
+            .ensure_materialized(dep_files.dep_files.values().cloned().collect())
+            .await
+            .context("Error materializing dep files")?;
+
+        let mut used_inputs = BTreeMap::new();
+        let mut fingerprints = BTreeMap::new();
+
+        for (label, path) in &dep_files.dep_files {
+            let contents = fs_util::read_to_string(&fs.fs().resolve(path))
+                .with_context(|| format!("Error reading `{}` dep file at `{}`", label, path))?;
+            let used = contents
+                .split('\n')
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_owned())
+                .collect::<Vec<_>>();
+
+            fingerprints.insert(
+                label.to_string(),
+                fingerprint_used_inputs(dep_files.tagged_inputs.get(label), &used)?,
+            );
+            used_inputs.insert(label.to_string(), used);
+        }
+
+        Ok(Self {
+            action_digest: action_digest.to_string(),
+            used_inputs,
+            fingerprints,
+        })
+    }
+
+    fn matches(&self, dep_files: &RemoteDepFiles) -> anyhow::Result<bool> {
+        if self.used_inputs.len() != dep_files.dep_files.len() {
+            return Ok(false);
+        }
+
+        for label in dep_files.dep_files.keys() {
+            let (used, fingerprint) = match (
+                self.used_inputs.get(&**label),
+                self.fingerprints.get(&**label),
+            ) {
+                (Some(used), Some(fingerprint)) => (used, fingerprint),
+                _ => return Ok(false),
+            };
+
+            if fingerprint_used_inputs(dep_files.tagged_inputs.get(label), used)? != *fingerprint {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Fingerprint the subset of `inputs` listed in a dep file.
+fn fingerprint_used_inputs(
+    inputs: Option<&ActionSharedDirectory>,
+    used: &[String],
+) -> anyhow::Result<String> {
+    let mut builder = match inputs {
+        Some(inputs) => inputs.dupe().into_builder(),
+        None => ActionDirectoryBuilder::empty(),
+    };
+
+    let mut selector = DirectorySelector::empty();
+    for path in used {
+        selector.select(ProjectRelativePath::new(path).context("Invalid line in dep file")?);
+    }
+
+    expand_selector_for_dependencies(&builder, &mut selector);
+
+    // NOTE: Like for local dep files, we ignore the filtering if it produces an invalid directory,
+    // since this will just result in a mismatch.
+    let _ignored = selector.filter(&mut builder);
+
+    Ok(builder.fingerprint().fingerprint().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use buck2_common::file_ops::FileDigest;
+    use buck2_common::file_ops::FileMetadata;
+    use buck2_common::file_ops::TrackedFileDigest;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+    use buck2_core::fs::project::ProjectRelativePathBuf;
+    use buck2_execute::directory::insert_file;
+    use buck2_execute::directory::INTERNER;
+
+    use super::*;
+
+    fn inputs(files: &[(&str, &str)]) -> ActionSharedDirectory {
+        let mut builder = ActionDirectoryBuilder::empty();
+        for (path, content) in files {
+            insert_file(
+                &mut builder,
+                ForwardRelativePath::new(path).unwrap(),
+                FileMetadata {
+                    digest: TrackedFileDigest::new(FileDigest::from_bytes_sha1(content.as_bytes())),
+                    is_executable: false,
+                },
+            )
+            .unwrap();
+        }
+        builder.fingerprint().shared(&*INTERNER)
+    }
+
+    fn dep_files(headers: ActionSharedDirectory) -> RemoteDepFiles {
+        RemoteDepFiles {
+            key: "key".to_owned(),
+            tagged_inputs: HashMap::from([(Arc::from("headers"), headers)]),
+            dep_files: HashMap::from([(
+                Arc::from("headers"),
+                ProjectRelativePathBuf::unchecked_new("buck-out/dep_file".to_owned()),
+            )]),
+        }
+    }
+
+    /// The entry recorded by an execution with `headers` as inputs that used `used`.
+    fn recorded_entry(
+        action_digest: &ActionDigest,
+        headers: &ActionSharedDirectory,
+        used: &[&str],
+    ) -> DepFileIndexEntry {
+        let used = used.map(|p| (*p).to_owned());
+        DepFileIndexEntry {
+            action_digest: action_digest.to_string(),
+            fingerprints: BTreeMap::from([(
+                "headers".to_owned(),
+                fingerprint_used_inputs(Some(headers), &used).unwrap(),
+            )]),
+            used_inputs: BTreeMap::from([("headers".to_owned(), used)]),
+        }
+    }
+
+    #[test]
+    fn test_match_when_unused_input_changes() -> anyhow::Result<()> {
+        let digest = ActionDigest::from_bytes_sha1(b"action");
+        let before = inputs(&[("src/a.h", "a"), ("src/b.h", "b")]);
+        let after = inputs(&[("src/a.h", "a"), ("src/b.h", "b2"), ("src/c.h", "c")]);
+
+        let mut index = DepFileIndex::default();
+        index.insert(recorded_entry(&digest, &before, &["src/a.h"]));
+
+        assert_eq!(index.find_match(&dep_files(after))?, Some(digest));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_match_when_used_input_changes() -> anyhow::Result<()> {
+        let digest = ActionDigest::from_bytes_sha1(b"action");
+        let before = inputs(&[("src/a.h", "a"), ("src/b.h", "b")]);
+        let after = inputs(&[("src/a.h", "a2"), ("src/b.h", "b")]);
+
+        let mut index = DepFileIndex::default();
+        index.insert(recorded_entry(&digest, &before, &["src/a.h"]));
+
+        assert_eq!(index.find_match(&dep_files(after))?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_match_on_stale_or_absent_entry() -> anyhow::Result<()> {
+        let digest = ActionDigest::from_bytes_sha1(b"action");
+        let headers = inputs(&[("src/a.h", "a")]);
+
+        // No entry at all.
+        assert_eq!(
+            DepFileIndex::default().find_match(&dep_files(headers.dupe()))?,
+            None
+        );
+
+        // An entry recorded for dep files which the command no longer produces.
+        let mut stale = recorded_entry(&digest, &headers, &["src/a.h"]);
+        let used = stale.used_inputs.remove("headers").unwrap();
+        let fingerprint = stale.fingerprints.remove("headers").unwrap();
+        stale.used_inputs.insert("srcs".to_owned(), used);
+        stale.fingerprints.insert("srcs".to_owned(), fingerprint);
+        assert!(!stale.matches(&dep_files(headers.dupe()))?);
+
+        // An entry missing the fingerprint of a dep file.
+        let mut stale = recorded_entry(&digest, &headers, &["src/a.h"]);
+        stale.fingerprints.clear();
+        assert!(!stale.matches(&dep_files(headers))?);
+
+        Ok(())
+    }
+
+    fn entry(action_digest: &str) -> DepFileIndexEntry {
+        DepFileIndexEntry {
+            action_digest: action_digest.to_owned(),
+            used_inputs: BTreeMap::new(),
+            fingerprints: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut index = DepFileIndex::default();
+        for i in 0..MAX_INDEX_ENTRIES + 1 {
+            index.insert(entry(&i.to_string()));
+        }
+        index.insert(entry("3"));
+
+        assert_eq!(index.entries.len(), MAX_INDEX_ENTRIES);
+        assert_eq!(index.entries[0].action_digest, "3");
+        assert_eq!(
+            index
+                .entries
+                .iter()
+                .filter(|e| e.action_digest == "3")
+                .count(),
+            1
+        );
+        // The oldest entry was dropped.
+        assert!(index.entries.iter().all(|e| e.action_digest != "0"));
+    }
+}
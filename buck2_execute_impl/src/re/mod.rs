@@ -7,4 +7,5 @@
  * of this source tree.
  */
 
+pub mod dep_files;
 pub mod download;
@@ -225,6 +225,7 @@ pub fn configure_base_globals(
         LibraryExtension::Print,
         LibraryExtension::RecordType,
         LibraryExtension::ExperimentalRegex,
+        LibraryExtension::SetType,
         LibraryExtension::StructType,
     ];
     let mut global_env = GlobalsBuilder::extended_by(&starlark_extensions)
@@ -739,6 +740,7 @@ mod tests {
     use buck2_core::cells::CellName;
     use buck2_core::collections::ordered_map::OrderedMap;
     use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::project::ProjectRelativePath;
     use buck2_core::fs::project::ProjectRoot;
     use indoc::indoc;
     use serde_json::json;
@@ -784,6 +786,7 @@ mod tests {
             configs_by_name,
         } = BuckConfigBasedCells::parse_with_file_ops(
             &project_fs,
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
             &TestConfigParserFileOps::new(&[
                 (
                     "/.buckconfig",
@@ -41,6 +41,7 @@ pub const LEGACY_TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD: &str = "compatible_with
 pub const EXEC_COMPATIBLE_WITH_ATTRIBUTE_FIELD: &str = "exec_compatible_with";
 
 pub const VISIBILITY_ATTRIBUTE_FIELD: &str = "visibility";
+pub const WITHIN_VIEW_ATTRIBUTE_FIELD: &str = "within_view";
 
 pub const TESTS_ATTRIBUTE_FIELD: &str = "tests";
 
@@ -101,6 +102,16 @@ fn visibility_attribute() -> Attribute {
     )
 }
 
+fn within_view_attribute() -> Attribute {
+    // This is optional, since rules used to declare it as `attrs.option(attrs.list(attrs.string()))`
+    // and existing targets set it to `None`.
+    Attribute::new_internal(
+        Some(Arc::new(CoercedAttr::Literal(AttrLiteral::None))),
+        "a list of visibility patterns restricting what targets this one can depend on".to_owned(),
+        AttrType::option(AttrType::list(AttrType::string())),
+    )
+}
+
 fn tests_attribute() -> Attribute {
     let entry_type = AttrType::label();
     Attribute::new_internal(
@@ -131,6 +142,7 @@ pub fn internal_attrs() -> &'static OrderedMap<&'static str, Attribute> {
                 exec_compatible_with_attribute(),
             ),
             (VISIBILITY_ATTRIBUTE_FIELD, visibility_attribute()),
+            (WITHIN_VIEW_ATTRIBUTE_FIELD, within_view_attribute()),
             (TESTS_ATTRIBUTE_FIELD, tests_attribute()),
         ])
     });
@@ -144,6 +156,7 @@ pub fn attr_is_configurable(name: &str) -> AttrIsConfigurable {
         || name == DEFAULT_TARGET_PLATFORM_ATTRIBUTE_FIELD
         // visibility attributes aren't configurable so that we can cache them on targetnodes.
         || name == VISIBILITY_ATTRIBUTE_FIELD
+        || name == WITHIN_VIEW_ATTRIBUTE_FIELD
     {
         AttrIsConfigurable::No
     } else {
@@ -90,6 +90,10 @@ impl QueryTarget for ConfiguredGraphNodeRef {
         self.0.buildfile_path()
     }
 
+    fn is_visible_to(&self, target: &Self) -> bool {
+        self.0.is_visible_to(target.0.name().unconfigured())
+    }
+
     // TODO(cjhopman): Use existential traits to remove the Box<> once they are stabilized.
     fn deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
         box self.0.deps().map(ConfiguredGraphNodeRef::ref_cast)
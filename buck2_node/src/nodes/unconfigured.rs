@@ -39,6 +39,7 @@ use crate::nodes::attributes::PACKAGE;
 use crate::nodes::attributes::TYPE;
 use crate::rule_type::RuleType;
 use crate::visibility::VisibilitySpecification;
+use crate::visibility::WithinViewSpecification;
 
 /// Map of target -> details of those targets within a build file.
 pub type TargetsMap = OrderedMap<TargetName, TargetNode>;
@@ -96,6 +97,9 @@ pub struct TargetNodeData {
     /// Visibility specification restricts what targets can depend on this one.
     visibility: VisibilitySpecification,
 
+    /// Within view specification restricts what targets this one can depend on.
+    within_view: WithinViewSpecification,
+
     /// Call stack for the target.
     call_stack: Option<StarlarkCallStack>,
 
@@ -114,6 +118,7 @@ impl TargetNode {
         attributes: AttrValues,
         deps_cache: CoercedDepsCollector,
         visibility: VisibilitySpecification,
+        within_view: WithinViewSpecification,
         call_stack: Option<StarlarkCallStack>,
         oncall: Option<Arc<String>>,
     ) -> TargetNode {
@@ -127,6 +132,7 @@ impl TargetNode {
             attributes,
             deps_cache,
             visibility,
+            within_view,
             call_stack,
             oncall,
         }))
@@ -248,6 +254,13 @@ impl TargetNode {
         self.0.visibility.is_visible_to(target)
     }
 
+    pub fn is_within_view(&self, dep: &TargetLabel) -> bool {
+        if self.label().pkg() == dep.pkg() {
+            return true;
+        }
+        self.0.within_view.is_within_view(dep)
+    }
+
     pub fn attrs(&self, opts: AttrInspectOptions) -> impl Iterator<Item = (&str, &CoercedAttr)> {
         self.0.attr_spec.attrs(&self.0.attributes, opts)
     }
@@ -433,6 +446,7 @@ pub mod testing {
     use crate::nodes::unconfigured::TargetsMap;
     use crate::rule_type::RuleType;
     use crate::visibility::VisibilitySpecification;
+    use crate::visibility::WithinViewSpecification;
 
     pub trait TargetNodeExt {
         fn testing_new(
@@ -440,6 +454,14 @@ pub mod testing {
             rule_type: RuleType,
             attrs: Vec<(&str, Attribute, CoercedAttr)>,
         ) -> Self;
+
+        fn testing_new_with_visibility(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            visibility: VisibilitySpecification,
+            within_view: WithinViewSpecification,
+        ) -> Self;
     }
 
     impl TargetNodeExt for TargetNode {
@@ -447,6 +469,22 @@ pub mod testing {
             label: TargetLabel,
             rule_type: RuleType,
             attrs: Vec<(&str, Attribute, CoercedAttr)>,
+        ) -> TargetNode {
+            TargetNode::testing_new_with_visibility(
+                label,
+                rule_type,
+                attrs,
+                VisibilitySpecification::Public,
+                WithinViewSpecification::Public,
+            )
+        }
+
+        fn testing_new_with_visibility(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            visibility: VisibilitySpecification,
+            within_view: WithinViewSpecification,
         ) -> TargetNode {
             let mut indices = OrderedMap::with_capacity(attrs.len());
             let mut instances = Vec::with_capacity(attrs.len());
@@ -477,7 +515,8 @@ pub mod testing {
                 Arc::new(AttributeSpec::testing_new(indices, instances)),
                 attributes,
                 deps_cache,
-                VisibilitySpecification::Public,
+                visibility,
+                within_view,
                 None,
                 None,
             )
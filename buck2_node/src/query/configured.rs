@@ -57,6 +57,10 @@ impl QueryTarget for ConfiguredTargetNode {
         Some(box self.tests().map(|t| t.target().dupe()))
     }
 
+    fn is_visible_to(&self, target: &Self) -> bool {
+        ConfiguredTargetNode::is_visible_to(self, target.name().unconfigured())
+    }
+
     fn special_attrs_for_each<E, F: FnMut(&str, &Self::Attr) -> Result<(), E>>(
         &self,
         mut func: F,
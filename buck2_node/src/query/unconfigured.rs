@@ -56,6 +56,10 @@ impl QueryTarget for TargetNode {
         Some(box self.tests().map(|t| t.target().dupe()))
     }
 
+    fn is_visible_to(&self, target: &Self) -> bool {
+        TargetNode::is_visible_to(self, target.label())
+    }
+
     fn attr_any_matches(
         attr: &Self::Attr,
         filter: &dyn Fn(&str) -> anyhow::Result<bool>,
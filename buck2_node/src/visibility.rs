@@ -19,6 +19,10 @@ pub enum VisibilityError {
         "`{0}` is not visible to `{1}` (run `buck2 uquery --output-attribute visibility {0}` to check the visibility)"
     )]
     NotVisibleTo(TargetLabel, TargetLabel),
+    #[error(
+        "`{0}` is not within the view of `{1}` (run `buck2 uquery --output-attribute within_view {1}` to check the view)"
+    )]
+    NotWithinView(TargetLabel, TargetLabel),
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Allocative)]
@@ -50,3 +54,23 @@ impl VisibilitySpecification {
         }
     }
 }
+
+/// Represents the `within_view` spec of a target, which restricts what it can depend on. Targets in
+/// the same package are always within each other's view.
+#[derive(Debug, Eq, PartialEq, Hash, Allocative)]
+pub enum WithinViewSpecification {
+    // Public is used when a target doesn't restrict its deps.
+    Public,
+    VisibleTo(Vec<VisibilityPattern>),
+}
+
+impl WithinViewSpecification {
+    pub fn is_within_view(&self, dep: &TargetLabel) -> bool {
+        match self {
+            WithinViewSpecification::Public => true,
+            WithinViewSpecification::VisibleTo(patterns) => {
+                patterns.iter().any(|pattern| pattern.0.matches(dep))
+            }
+        }
+    }
+}
@@ -105,6 +105,12 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
         None
     }
 
+    /// Whether `target` may depend on this node. Nodes without a visibility are visible to
+    /// everything.
+    fn is_visible_to(&self, _target: &Self) -> bool {
+        true
+    }
+
     fn attr_any_matches(
         attr: &Self::Attr,
         filter: &dyn Fn(&str) -> anyhow::Result<bool>,
@@ -168,7 +174,7 @@ pub trait QueryEnvironment: Send + Sync {
         from: &TargetSet<Self::Target>,
         to: &TargetSet<Self::Target>,
     ) -> anyhow::Result<TargetSet<Self::Target>> {
-        self.rdeps(from, to, None).await
+        self.rdeps(from, to, None, None).await
     }
 
     async fn somepath(
@@ -244,20 +250,36 @@ pub trait QueryEnvironment: Send + Sync {
         )))
     }
 
+    /// Finds all the targets defined in the same packages as `targets`.
+    async fn siblings(
+        &self,
+        _targets: &TargetSet<Self::Target>,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        Err(anyhow::anyhow!(QueryError::FunctionUnimplemented(
+            "siblings() is implemented only for uquery and cquery."
+        )))
+    }
+
+    /// Finds the targets in `universe` that depend on `from`, within `depth` steps. If there's a
+    /// `filter`, only the edges it returns are followed.
     async fn rdeps(
         &self,
         universe: &TargetSet<Self::Target>,
         from: &TargetSet<Self::Target>,
         depth: Option<i32>,
+        filter: Option<&dyn TraversalFilter<Self::Target>>,
     ) -> anyhow::Result<TargetSet<Self::Target>> {
         let mut deps = TargetSet::new();
 
         struct Delegate<'a, Q: QueryTarget> {
             from: &'a TargetSet<Q>,
             max_distance: Option<usize>,
+            filter: Option<&'a dyn TraversalFilter<Q>>,
 
             result: &'a mut TargetSet<Q>,
             distance: HashMap<Q::NodeRef, Option<usize>>,
+            /// The children found by the filter, which `visit` can't compute as it isn't async.
+            filtered_children: HashMap<Q::NodeRef, Vec<Q::NodeRef>>,
         }
 
         #[async_trait]
@@ -268,7 +290,12 @@ pub trait QueryEnvironment: Send + Sync {
                     Some(0)
                 } else {
                     let mut distance = None;
-                    for dep in target.deps() {
+                    let children: Box<dyn Iterator<Item = &Q::NodeRef> + Send + '_> =
+                        match self.filtered_children.get(node_ref) {
+                            Some(children) => box children.iter(),
+                            None => target.deps(),
+                        };
+                    for dep in children {
                         let dep_distance = *self.distance.get(dep).ok_or_else(|| {
                             QueryEnvironmentError::DependencyCycle(
                                 dep.to_string(),
@@ -299,8 +326,22 @@ pub trait QueryEnvironment: Send + Sync {
                 func: &mut dyn ChildVisitor<Q>,
             ) -> anyhow::Result<()> {
                 let res: anyhow::Result<_> = try {
-                    for dep in target.deps() {
-                        func.visit(dep.clone())?;
+                    match self.filter {
+                        Some(filter) => {
+                            let children = filter.get_children(target).await?;
+                            let children: Vec<_> =
+                                children.iter().map(|dep| dep.node_ref().clone()).collect();
+                            for dep in &children {
+                                func.visit(dep.clone())?;
+                            }
+                            self.filtered_children
+                                .insert(target.node_ref().clone(), children);
+                        }
+                        None => {
+                            for dep in target.deps() {
+                                func.visit(dep.clone())?;
+                            }
+                        }
                     }
                 };
                 res.with_context(|| format!("When traversing children of `{}`", target.node_ref()))
@@ -313,7 +354,9 @@ pub trait QueryEnvironment: Send + Sync {
                 result: &mut deps,
                 from,
                 max_distance: depth.map(|v| v as usize),
+                filter,
                 distance: HashMap::new(),
+                filtered_children: HashMap::new(),
             },
         )
         .await?;
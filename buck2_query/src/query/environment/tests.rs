@@ -10,6 +10,7 @@
 #![cfg(test)]
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt;
 use std::sync::Arc;
 
@@ -20,11 +21,19 @@ use derive_more::From;
 use indexmap::IndexSet;
 
 use super::*;
+use crate::query::syntax::simple::functions::DefaultQueryFunctions;
 use crate::query::traversal::AsyncNodeLookup;
 
 #[derive(Debug, Copy, Clone, Dupe, Eq, PartialEq, Hash, Display, From)]
 struct TestTargetId(u64);
 
+impl TestTargetId {
+    /// Targets are in the same package if their ids only differ by their last digit.
+    fn package(&self) -> u64 {
+        self.0 / 10
+    }
+}
+
 impl NodeLabel for TestTargetId {}
 
 #[derive(Debug, Copy, Clone, Dupe, Eq, PartialEq, Hash, Display, Serialize)]
@@ -34,6 +43,8 @@ struct TestTargetAttr;
 struct TestTarget {
     id: TestTargetId,
     deps: Arc<IndexSet<TestTargetId>>,
+    /// Private targets are only visible to their package.
+    private: bool,
 }
 
 /// Custom debug to make the test output more readable
@@ -66,6 +77,10 @@ impl QueryTarget for TestTarget {
         unimplemented!()
     }
 
+    fn is_visible_to(&self, target: &Self) -> bool {
+        !self.private || self.id.package() == target.id.package()
+    }
+
     fn deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
         box self.deps.iter()
     }
@@ -174,6 +189,20 @@ impl QueryEnvironment for TestEnv {
     async fn owner(&self, _paths: &FileSet) -> anyhow::Result<TargetSet<Self::Target>> {
         unimplemented!()
     }
+
+    async fn siblings(
+        &self,
+        targets: &TargetSet<Self::Target>,
+    ) -> anyhow::Result<TargetSet<Self::Target>> {
+        let packages: HashSet<u64> = targets.iter().map(|t| t.id.package()).collect();
+        let mut siblings = TargetSet::new();
+        for target in self.graph.values() {
+            if packages.contains(&target.id.package()) {
+                siblings.insert(target.dupe());
+            }
+        }
+        Ok(siblings)
+    }
 }
 
 impl TestEnv {
@@ -191,6 +220,7 @@ impl TestEnv {
 #[derive(Default)]
 pub struct TestEnvBuilder {
     graph: HashMap<u64, IndexSet<u64>>,
+    private: HashSet<u64>,
 }
 
 impl TestEnvBuilder {
@@ -199,6 +229,10 @@ impl TestEnvBuilder {
         self.graph.entry(to).or_default();
     }
 
+    fn private(&mut self, id: u64) {
+        self.private.insert(id);
+    }
+
     fn build(&self) -> TestEnv {
         TestEnv {
             graph: self
@@ -207,7 +241,8 @@ impl TestEnvBuilder {
                 .map(|(id, vs)| {
                     let id = TestTargetId(*id);
                     let deps = Arc::new(vs.iter().map(|v| TestTargetId(*v)).collect());
-                    (id, TestTarget { id, deps })
+                    let private = self.private.contains(&id.0);
+                    (id, TestTarget { id, deps, private })
                 })
                 .collect(),
         }
@@ -317,3 +352,127 @@ async fn test_nested_paths() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_rdeps_with_filter() -> anyhow::Result<()> {
+    /// Follows all the edges except those to `skip`.
+    struct SkipChild<'a> {
+        env: &'a TestEnv,
+        skip: TestTargetId,
+    }
+
+    #[async_trait]
+    impl TraversalFilter<TestTarget> for SkipChild<'_> {
+        async fn get_children(&self, target: &TestTarget) -> anyhow::Result<TargetSet<TestTarget>> {
+            let mut children = TargetSet::new();
+            for dep in target.deps() {
+                if *dep != self.skip {
+                    children.insert(<TestEnv as NodeLookup<TestTarget>>::get(self.env, dep)?);
+                }
+            }
+            Ok(children)
+        }
+    }
+
+    let mut env = TestEnvBuilder::default();
+    env.edge(1, 2);
+    env.edge(2, 3);
+    env.edge(1, 10);
+    env.edge(10, 3);
+    let env = env.build();
+
+    let rdeps = env
+        .rdeps(&env.set("1")?, &env.set("3")?, None, None)
+        .await?;
+    assert_eq!(rdeps, env.set("3,2,10,1")?);
+
+    let filter = SkipChild {
+        env: &env,
+        skip: TestTargetId(2),
+    };
+    let rdeps = env
+        .rdeps(&env.set("1")?, &env.set("3")?, None, Some(&filter))
+        .await?;
+    assert_eq!(rdeps, env.set("3,10,1")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_siblings() -> anyhow::Result<()> {
+    let mut env = TestEnvBuilder::default();
+    env.edge(11, 12);
+    env.edge(12, 21);
+    env.edge(13, 21);
+    env.edge(22, 12);
+    let env = env.build();
+
+    assert_eq!(env.siblings(&env.set("12")?).await?, env.set("11,12,13")?);
+    assert_eq!(
+        env.siblings(&env.set("12,21")?).await?,
+        env.set("11,12,13,21,22")?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_same_pkg_direct_rdeps() -> anyhow::Result<()> {
+    let mut env = TestEnvBuilder::default();
+    env.edge(11, 12);
+    env.edge(12, 13);
+    env.edge(13, 21);
+    env.edge(22, 12);
+    env.edge(23, 22);
+    let env = env.build();
+    let functions = DefaultQueryFunctions::<TestEnv>::new();
+
+    // 11 only depends on 13 indirectly.
+    assert_eq!(
+        functions
+            .same_pkg_direct_rdeps(&env, &env.set("13")?)
+            .await?,
+        env.set("12")?
+    );
+    // 22 depends on 12, but from another package.
+    assert_eq!(
+        functions
+            .same_pkg_direct_rdeps(&env, &env.set("12,22")?)
+            .await?,
+        env.set("11,23")?
+    );
+    assert_eq!(
+        functions
+            .same_pkg_direct_rdeps(&env, &env.set("11")?)
+            .await?,
+        TargetSet::new()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_visible() -> anyhow::Result<()> {
+    let mut env = TestEnvBuilder::default();
+    env.edge(11, 12);
+    env.edge(21, 22);
+    env.private(12);
+    let env = env.build();
+    let functions = DefaultQueryFunctions::<TestEnv>::new();
+
+    assert_eq!(
+        functions.visible(&env.set("11")?, &env.set("12,22")?)?,
+        env.set("12,22")?
+    );
+    // A target is only in the result if it's visible to the whole universe.
+    assert_eq!(
+        functions.visible(&env.set("11,21")?, &env.set("12,22")?)?,
+        env.set("22")?
+    );
+    assert_eq!(
+        functions.visible(&env.set("21")?, &env.set("12")?)?,
+        TargetSet::new()
+    );
+
+    Ok(())
+}
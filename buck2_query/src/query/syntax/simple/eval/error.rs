@@ -50,6 +50,8 @@ pub enum QueryError {
     FileLiteralNotInProject(ProjectRoot, String),
     #[error("query function {0} not available in this context")]
     NotAvailableInContext(&'static str),
+    #[error("`${0}` is not bound by an enclosing `let` or query macro")]
+    UnboundVariable(String),
     #[error(
         "Operation + requires either two set types, or one set and one string, got `{0}` and `{1}`"
     )]
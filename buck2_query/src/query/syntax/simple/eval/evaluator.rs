@@ -18,6 +18,7 @@ use gazebo::variants::VariantName;
 
 use crate::__derive_refs::indexmap::IndexSet;
 use crate::query::environment::QueryEnvironment;
+use crate::query::environment::QueryTarget;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::file_set::FileNode;
 use crate::query::syntax::simple::eval::file_set::FileSet;
@@ -26,14 +27,36 @@ use crate::query::syntax::simple::eval::values::QueryEvaluationValue;
 use crate::query::syntax::simple::eval::values::QueryResult;
 use crate::query::syntax::simple::eval::values::QueryValue;
 use crate::query::syntax::simple::functions::QueryFunctions;
+
+/// The values bound by a `let` (or the arguments of a query macro), along with the scope they
+/// were bound in.
+struct QueryScope<'a, T: QueryTarget> {
+    bindings: Vec<(String, QueryValue<T>)>,
+    parent: Option<&'a QueryScope<'a, T>>,
+}
+
+impl<'a, T: QueryTarget> QueryScope<'a, T> {
+    fn lookup(&self, name: &str) -> Option<&QueryValue<T>> {
+        match self.bindings.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => Some(value),
+            None => self.parent?.lookup(name),
+        }
+    }
+}
+
 pub struct QueryEvaluator<'e, Env: QueryEnvironment> {
     env: &'e Env,
     functions: &'e dyn QueryFunctions<Env = Env>,
+    scope: Option<&'e QueryScope<'e, Env::Target>>,
 }
 
 impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
     pub fn new(env: &'e Env, functions: &'e dyn QueryFunctions<Env = Env>) -> Self {
-        Self { env, functions }
+        Self {
+            env,
+            functions,
+            scope: None,
+        }
     }
 
     pub fn env(&self) -> &Env {
@@ -98,9 +121,48 @@ impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
 
                 Ok(files.into())
             }
+            Expr::Let { name, value, body } => {
+                // The bound value is evaluated once, here, rather than each time it's referenced.
+                let value = self.eval(value).await?.value;
+                let scope = QueryScope {
+                    bindings: vec![((*name.fragment()).to_owned(), value)],
+                    parent: self.scope,
+                };
+                Ok(self.scoped(&scope).eval(body).await?.value)
+            }
+            Expr::Variable(name) => match self.scope.and_then(|s| s.lookup(name.fragment())) {
+                Some(value) => Ok(value.clone()),
+                None => Err(QueryError::UnboundVariable((*name.fragment()).to_owned())),
+            },
+        }
+    }
+
+    fn scoped<'a>(&'a self, scope: &'a QueryScope<'a, Env::Target>) -> QueryEvaluator<'a, Env> {
+        QueryEvaluator {
+            env: self.env,
+            functions: self.functions,
+            scope: Some(scope),
         }
     }
 
+    /// Evaluates the body of a query macro, with the arguments bound as `$1`, `$2`, etc. The
+    /// caller's `let` bindings aren't visible within the body.
+    pub async fn eval_with_args(
+        &self,
+        expr: &Spanned<Expr<'_>>,
+        args: Vec<QueryValue<Env::Target>>,
+    ) -> QueryResult<QueryValue<Env::Target>> {
+        let scope = QueryScope {
+            bindings: args
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| ((i + 1).to_string(), value))
+                .collect(),
+            parent: None,
+        };
+        self.scoped(&scope).eval(expr).await
+    }
+
     pub fn eval<'a>(
         &'a self,
         expr: &'a Spanned<Expr<'a>>,
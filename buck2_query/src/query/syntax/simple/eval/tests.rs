@@ -29,6 +29,9 @@ use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use crate::query::syntax::simple::eval::file_set::FileSet;
 use crate::query::syntax::simple::eval::set::TargetSet;
+use crate::query::syntax::simple::eval::values::QueryValue;
+use crate::query::syntax::simple::functions::macros::QueryMacros;
+use crate::query::syntax::simple::functions::AugmentedQueryFunctions;
 use crate::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use crate::query::traversal::AsyncTraversalDelegate;
 
@@ -166,3 +169,63 @@ pub async fn test_missing_arg() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+async fn eval_with_macros(
+    input: &str,
+    macros: &[(&str, &str)],
+) -> anyhow::Result<QueryValue<Target>> {
+    let parsed = parse_expr(input)?;
+    let defaults = DefaultQueryFunctionsModule::new();
+    let functions = AugmentedQueryFunctions::augment(
+        &defaults,
+        box QueryMacros::new(
+            macros
+                .iter()
+                .map(|(name, body)| ((*name).to_owned(), (*body).to_owned())),
+        )?,
+    );
+    match QueryEvaluator::new(&Env, &functions).eval(&parsed).await {
+        Ok(v) => Ok(v.value),
+        Err(e) => Err(QueryError::convert_error(e, input)),
+    }
+}
+
+#[tokio::test]
+pub async fn test_let() -> anyhow::Result<()> {
+    assert_eq!(
+        QueryValue::String("a".to_owned()),
+        eval_with_macros("let x = a in let y = $x in $y", &[]).await?
+    );
+    assert_eq!(
+        QueryValue::Integer(2),
+        eval_with_macros("let x = 1 in let x = 2 in $x", &[]).await?
+    );
+
+    let err = eval_with_macros("let x = a in $y", &[]).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("`$y` is not bound"));
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_macros() -> anyhow::Result<()> {
+    let macros = [("outer", "inner($2)"), ("inner", "$1")];
+    assert_eq!(
+        QueryValue::String("b".to_owned()),
+        eval_with_macros("outer(a, b)", &macros).await?
+    );
+
+    // The caller's bindings aren't visible within the macro.
+    let err = eval_with_macros("let x = a in leak()", &[("leak", "$x")])
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("`$x` is not bound"));
+
+    let err = QueryMacros::<Env>::new([
+        ("a".to_owned(), "b()".to_owned()),
+        ("b".to_owned(), "deps(a())".to_owned()),
+    ])
+    .err()
+    .unwrap();
+    assert_eq!("Query macro `a` calls itself: a -> b -> a", err.to_string());
+    Ok(())
+}
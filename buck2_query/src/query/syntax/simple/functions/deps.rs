@@ -56,6 +56,36 @@ impl<'a, Env: QueryEnvironment> DepsContextFunctions<'a, Env> {
     }
 }
 
+/// Finds the children of a node during a traversal by evaluating a captured expression, within
+/// which `first_order_deps()` and friends refer to that node.
+struct ExprFilter<'a, Env: QueryEnvironment> {
+    env: &'a Env,
+    functions: &'a dyn QueryFunctions<Env = Env>,
+    expr: &'a CapturedExpr<'a>,
+}
+
+#[async_trait]
+impl<'a, T: QueryTarget, Env: QueryEnvironment<Target = T>> TraversalFilter<T>
+    for ExprFilter<'a, Env>
+{
+    async fn get_children(&self, target: &T) -> anyhow::Result<TargetSet<T>> {
+        let augmented_functions =
+            AugmentedQueryFunctions::augment(self.functions, box DepsContextFunctions { target });
+        let evaluator = QueryEvaluator::new(self.env, &augmented_functions);
+        match evaluator.eval_parsed_query(self.expr.expr).await {
+            Ok(v) => match v.value {
+                QueryEvaluationValue::TargetSet(v) => Ok(v),
+                v => Err(QueryError::InvalidType {
+                    expected: "targets",
+                    actual: v.variant_name(),
+                }
+                .into()),
+            },
+            Err(e) => Err(QueryError::drop_spans(e)),
+        }
+    }
+}
+
 pub(crate) struct DepsFunction<Env: QueryEnvironment> {
     pub(crate) _marker: PhantomData<Env>,
 }
@@ -69,49 +99,36 @@ impl<Env: QueryEnvironment> DepsFunction<Env> {
         depth: Option<i32>,
         captured_expr: Option<&CapturedExpr<'_>>,
     ) -> anyhow::Result<TargetSet<Env::Target>> {
-        let filter = match captured_expr {
-            Some(expr) => {
-                struct Filter<'a, Env: QueryEnvironment> {
-                    inner_env: &'a Env,
-                    functions: &'a dyn QueryFunctions<Env = Env>,
-                    expr: &'a CapturedExpr<'a>,
-                }
-
-                #[async_trait]
-                impl<'a, T: QueryTarget, Env: QueryEnvironment<Target = T>> TraversalFilter<T> for Filter<'a, Env> {
-                    async fn get_children(&self, target: &T) -> anyhow::Result<TargetSet<T>> {
-                        let augmented_functions = AugmentedQueryFunctions::augment(
-                            self.functions,
-                            box DepsContextFunctions { target },
-                        );
-                        let evaluator = QueryEvaluator::new(self.inner_env, &augmented_functions);
-                        match evaluator.eval_parsed_query(self.expr.expr).await {
-                            Ok(v) => match v.value {
-                                QueryEvaluationValue::TargetSet(v) => Ok(v),
-                                v => Err(QueryError::InvalidType {
-                                    expected: "targets",
-                                    actual: v.variant_name(),
-                                }
-                                .into()),
-                            },
-                            Err(e) => Err(QueryError::drop_spans(e)),
-                        }
-                    }
-                }
+        let filter = captured_expr.map(|expr| ExprFilter {
+            env,
+            functions,
+            expr,
+        });
+        let filter_ref = filter
+            .as_ref()
+            .map(|v| v as &dyn TraversalFilter<Env::Target>);
 
-                Some(Filter {
-                    inner_env: env,
-                    functions,
-                    expr,
-                })
-            }
-            None => None,
-        };
+        env.deps(targets, depth, filter_ref).await
+    }
 
+    pub(crate) async fn invoke_rdeps(
+        &self,
+        env: &Env,
+        functions: &dyn QueryFunctions<Env = Env>,
+        universe: &TargetSet<Env::Target>,
+        targets: &TargetSet<Env::Target>,
+        depth: Option<i32>,
+        captured_expr: Option<&CapturedExpr<'_>>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        let filter = captured_expr.map(|expr| ExprFilter {
+            env,
+            functions,
+            expr,
+        });
         let filter_ref = filter
             .as_ref()
             .map(|v| v as &dyn TraversalFilter<Env::Target>);
 
-        env.deps(targets, depth, filter_ref).await
+        env.rdeps(universe, targets, depth, filter_ref).await
     }
 }
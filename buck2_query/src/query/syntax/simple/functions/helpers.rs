@@ -111,7 +111,7 @@ impl QueryArgType {
 
 #[async_trait]
 pub trait QueryFunction<Env: QueryEnvironment>: Send + Sync {
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     async fn invoke(
         &self,
@@ -120,6 +120,12 @@ pub trait QueryFunction<Env: QueryEnvironment>: Send + Sync {
     ) -> Result<QueryValue<Env::Target>, QueryError>;
 
     fn arg_type(&self, idx: usize) -> Result<QueryArgType, QueryError>;
+
+    /// For functions defined by a query macro, the query they expand to. Used to find the
+    /// literals the macro refers to.
+    fn macro_body(&self) -> Option<&str> {
+        None
+    }
 }
 
 #[async_trait]
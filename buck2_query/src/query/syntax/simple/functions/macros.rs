@@ -0,0 +1,223 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Query macros are named queries defined outside of the query being evaluated (ex. in the
+//! `[query_macros]` buckconfig section). They are called like functions, with the arguments
+//! available within the macro's query as `$1`, `$2`, etc.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_query_parser::parse_expr;
+use buck2_query_parser::BinaryOp;
+use buck2_query_parser::Expr;
+use buck2_query_parser::SpannedExpr;
+use gazebo::prelude::*;
+use thiserror::Error;
+
+use crate::query::environment::QueryEnvironment;
+use crate::query::syntax::simple::eval::error::QueryError;
+use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
+use crate::query::syntax::simple::eval::values::QueryValue;
+use crate::query::syntax::simple::functions::helpers::QueryArgType;
+use crate::query::syntax::simple::functions::helpers::QueryBinaryOp;
+use crate::query::syntax::simple::functions::helpers::QueryFunction;
+use crate::query::syntax::simple::functions::QueryFunctions;
+
+#[derive(Debug, Error)]
+enum QueryMacroError {
+    #[error("Query macro name `{0}` is not a valid function name")]
+    InvalidName(String),
+    #[error("Query macro `{0}` calls itself: {}", .1.join(" -> "))]
+    Cycle(String, Vec<String>),
+}
+
+struct QueryMacro<Env: QueryEnvironment> {
+    name: String,
+    body: String,
+    _marker: PhantomData<Env>,
+}
+
+/// A set of query macros, to be added to the available functions with
+/// `AugmentedQueryFunctions::augment`.
+pub struct QueryMacros<Env: QueryEnvironment> {
+    macros: Arc<HashMap<String, QueryMacro<Env>>>,
+}
+
+impl<Env: QueryEnvironment> Clone for QueryMacros<Env> {
+    fn clone(&self) -> Self {
+        Self {
+            macros: self.macros.dupe(),
+        }
+    }
+}
+
+impl<Env: QueryEnvironment> Dupe for QueryMacros<Env> {}
+
+impl<Env: QueryEnvironment> QueryMacros<Env> {
+    /// Creates the macros from `(name, query)` pairs, checking that each query parses and that
+    /// no macro ends up calling itself.
+    pub fn new(definitions: impl IntoIterator<Item = (String, String)>) -> anyhow::Result<Self> {
+        let mut macros = HashMap::new();
+        let mut calls = HashMap::new();
+        for (name, body) in definitions {
+            if !is_function_name(&name) {
+                return Err(QueryMacroError::InvalidName(name).into());
+            }
+            let parsed = parse_expr(&body)
+                .with_context(|| format!("Error parsing query macro `{}`", name))?;
+            let mut called = Vec::new();
+            called_functions(&parsed.value, &mut called);
+            calls.insert(name.clone(), called.map(|f| (*f).to_owned()));
+            macros.insert(
+                name.clone(),
+                QueryMacro {
+                    name,
+                    body,
+                    _marker: PhantomData,
+                },
+            );
+        }
+        check_no_cycles(&calls)?;
+        Ok(Self {
+            macros: Arc::new(macros),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.macros.is_empty()
+    }
+}
+
+fn is_function_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn called_functions<'a>(expr: &Expr<'a>, result: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Function {
+            function_name,
+            args,
+        } => {
+            result.push(*function_name.fragment());
+            for arg in args {
+                called_functions(&arg.value, result);
+            }
+        }
+        Expr::BinaryOpSequence(left, exprs) => {
+            called_functions(&left.value, result);
+            for (_, right) in exprs {
+                called_functions(&right.value, result);
+            }
+        }
+        Expr::Let { value, body, .. } => {
+            called_functions(&value.value, result);
+            called_functions(&body.value, result);
+        }
+        Expr::String(..)
+        | Expr::Integer(..)
+        | Expr::Set(..)
+        | Expr::FileSet(..)
+        | Expr::Variable(..) => {}
+    }
+}
+
+fn check_no_cycles(calls: &HashMap<String, Vec<String>>) -> anyhow::Result<()> {
+    fn visit<'a>(
+        name: &'a str,
+        calls: &'a HashMap<String, Vec<String>>,
+        stack: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> anyhow::Result<()> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if let Some(start) = stack.iter().position(|n| *n == name) {
+            let mut cycle = stack[start..].map(|n| (*n).to_owned());
+            cycle.push(name.to_owned());
+            return Err(QueryMacroError::Cycle(name.to_owned(), cycle).into());
+        }
+        stack.push(name);
+        for callee in &calls[name] {
+            if calls.contains_key(callee) {
+                visit(callee, calls, stack, done)?;
+            }
+        }
+        stack.pop();
+        done.insert(name);
+        Ok(())
+    }
+
+    // Visit in a fixed order so that the reported cycle doesn't depend on hashing.
+    let mut names: Vec<&String> = calls.keys().collect();
+    names.sort();
+    let mut done = HashSet::new();
+    for name in names {
+        visit(name, calls, &mut Vec::new(), &mut done)?;
+    }
+    Ok(())
+}
+
+impl<Env: QueryEnvironment> QueryFunctions for QueryMacros<Env> {
+    type Env = Env;
+
+    fn get(&self, name: &str) -> Option<&dyn QueryFunction<Env>> {
+        self.macros.get(name).map(|m| m as &dyn QueryFunction<Env>)
+    }
+
+    fn get_op(&self, _op: BinaryOp) -> Option<&dyn QueryBinaryOp<Env>> {
+        None
+    }
+}
+
+#[async_trait]
+impl<Env: QueryEnvironment> QueryFunction<Env> for QueryMacro<Env> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn invoke(
+        &self,
+        evaluator: &QueryEvaluator<Env>,
+        args: &[SpannedExpr<'_>],
+    ) -> Result<QueryValue<Env::Target>, QueryError> {
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(evaluator.eval(arg).await?.value);
+        }
+        let body = parse_expr(&self.body)?;
+        // Spans within the body refer to the macro's query rather than the one being evaluated,
+        // so resolve them here.
+        match evaluator.eval_with_args(&body, values).await {
+            Ok(v) => Ok(v.value),
+            Err(e) => Err(QueryError::Anyhow(
+                QueryError::convert_error(e, &self.body)
+                    .context(format!("Error evaluating query macro `{}`", self.name)),
+            )),
+        }
+    }
+
+    fn arg_type(&self, _idx: usize) -> Result<QueryArgType, QueryError> {
+        Ok(QueryArgType::Value)
+    }
+
+    fn macro_body(&self) -> Option<&str> {
+        Some(&self.body)
+    }
+}
@@ -12,11 +12,14 @@ use std::marker::PhantomData;
 use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_query_derive::query_module;
+use buck2_query_parser::parse_expr;
 use buck2_query_parser::spanned::Spanned;
 use buck2_query_parser::BinaryOp;
 use buck2_query_parser::Expr;
+use gazebo::dupe::Dupe;
 use gazebo::variants::VariantName;
 
+use crate::query::environment::LabeledNode;
 use crate::query::environment::QueryEnvironment;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
@@ -36,6 +39,7 @@ use crate::query::syntax::simple::functions::helpers::QueryFunction;
 pub mod deps;
 pub mod docs;
 pub mod helpers;
+pub mod macros;
 
 pub trait QueryLiteralVisitor {
     fn target_pattern(&mut self, pattern: &str) -> anyhow::Result<()>;
@@ -92,6 +96,12 @@ impl<F: QueryFunctions> QueryFunctionsVisitLiterals for F {
                                 ),
                             )?;
                         }
+                        if let Some(body) = func.macro_body() {
+                            let parsed = parse_expr(body)?;
+                            visit_literals_item(this, visitor, &parsed, true).map_err(|e| {
+                                QueryError::Anyhow(QueryError::convert_error(e, body))
+                            })?;
+                        }
                         Ok(())
                     }
                     None => Err(QueryError::UnknownFunction(
@@ -113,6 +123,15 @@ impl<F: QueryFunctions> QueryFunctionsVisitLiterals for F {
                     Ok(())
                 }
                 Expr::FileSet(_args) => Ok(()),
+                Expr::Let { value, body, .. } => {
+                    // We don't know how the bound value will be used, so treat a literal there
+                    // like an argument of type `Value`.
+                    visit_literals_item(this, visitor, value, true)?;
+                    visit_literals_item(this, visitor, body, true)?;
+                    Ok(())
+                }
+                // Whatever was bound to the variable was visited where it was bound.
+                Expr::Variable(..) => Ok(()),
                 Expr::String(..) | Expr::Integer(..) => {
                     panic!(
                         "This shouldn't be called with literals, they should be handled in the caller"
@@ -330,24 +349,66 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
         Ok(self.implementation.owner(env, &files).await?.into())
     }
 
+    /// The targets in `universe` that depend on `targets`, optionally within `depth` steps.
+    ///
+    /// Like `deps()`, the traversal can be restricted by an expression evaluated for each node of
+    /// the universe, which returns the children to follow. Within it, `first_order_deps()`,
+    /// `target_deps()` and `exec_deps()` refer to the deps of that node. For example,
+    /// `rdeps(//..., //foo:bar, -1, target_deps())` ignores exec deps.
     async fn rdeps(
         &self,
-        env: &Env,
+        evaluator: &QueryEvaluator<'_, Env>,
         universe: TargetSet<Env::Target>,
         targets: TargetSet<Env::Target>,
         depth: Option<u64>,
+        captured_expr: Option<CapturedExpr<'_>>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .rdeps(
+                evaluator.env(),
+                evaluator.functions(),
+                &universe,
+                &targets,
+                depth.map(|v| v as i32),
+                captured_expr.as_ref(),
+            )
+            .await?
+            .into())
+    }
+
+    /// The targets defined in the same packages as `targets` that depend directly on one of them.
+    async fn same_pkg_direct_rdeps(
+        &self,
+        env: &Env,
+        targets: TargetSet<Env::Target>,
     ) -> QueryFuncResult<Env> {
         Ok(self
             .implementation
-            .rdeps(env, &universe, &targets, depth.map(|v| v as i32))
+            .same_pkg_direct_rdeps(env, &targets)
             .await?
             .into())
     }
 
+    /// All the targets defined in the same packages as `targets`, including `targets` themselves.
+    async fn siblings(&self, env: &Env, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
+        Ok(self.implementation.siblings(env, &targets).await?.into())
+    }
+
     async fn testsof(&self, env: &Env, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
         Ok(self.implementation.testsof(env, &targets).await?.into())
     }
 
+    /// The targets in `targets` that are visible to every target in `universe`, according to
+    /// their `visibility` attributes.
+    async fn visible(
+        &self,
+        universe: TargetSet<Env::Target>,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self.implementation.visible(&universe, &targets)?.into())
+    }
+
     // These three functions are intentionally implemented as errors. They are only available within the context
     // of a deps functions 3rd parameter expr. When used in that context, the QueryFunctions will be augmented to
     // have non-erroring implementations.
@@ -531,11 +592,54 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
     pub async fn rdeps(
         &self,
         env: &Env,
+        functions: &dyn QueryFunctions<Env = Env>,
         universe: &TargetSet<Env::Target>,
         targets: &TargetSet<Env::Target>,
         depth: Option<i32>,
+        captured_expr: Option<&CapturedExpr<'_>>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        DepsFunction::<Env> {
+            _marker: PhantomData,
+        }
+        .invoke_rdeps(env, functions, universe, targets, depth, captured_expr)
+        .await
+    }
+
+    pub async fn same_pkg_direct_rdeps(
+        &self,
+        env: &Env,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        // Look at the siblings of each target on its own, since targets depending on a target in
+        // another package are not same package rdeps.
+        let mut rdeps = TargetSet::new();
+        for target in targets.iter() {
+            let mut target_set = TargetSet::new();
+            target_set.insert(target.dupe());
+            rdeps.extend(
+                env.siblings(&target_set)
+                    .await?
+                    .filter(|node| Ok(node.deps().any(|dep| dep == target.node_ref())))?
+                    .into_iter(),
+            );
+        }
+        Ok(rdeps)
+    }
+
+    pub async fn siblings(
+        &self,
+        env: &Env,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        env.siblings(targets).await
+    }
+
+    pub fn visible(
+        &self,
+        universe: &TargetSet<Env::Target>,
+        targets: &TargetSet<Env::Target>,
     ) -> anyhow::Result<TargetSet<Env::Target>> {
-        env.rdeps(universe, targets, depth).await
+        targets.filter(|node| Ok(universe.iter().all(|dep| node.is_visible_to(dep))))
     }
 
     pub async fn testsof(
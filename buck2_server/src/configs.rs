@@ -13,6 +13,7 @@ use buck2_common::legacy_configs::LegacyBuckConfigs;
 use buck2_common::legacy_configs::LegacyConfigCmdArg;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::project::ProjectRelativePath;
 use buck2_core::fs::project::ProjectRoot;
 use cli_proto::config_override::ConfigType;
 use cli_proto::ConfigOverride;
@@ -48,6 +49,7 @@ pub fn parse_legacy_cells<'a, Iter: Iterator<Item = &'a ConfigOverride>>(
     config_overrides: Iter,
     cwd: &AbsNormPath,
     fs: &ProjectRoot,
+    buck_out_dir: &ProjectRelativePath,
 ) -> anyhow::Result<(CellResolver, LegacyBuckConfigs)> {
     let config_values = get_legacy_config_args(config_overrides)?;
     // TODO: We do not need to reparse _all_ configs, instead we just need to
@@ -55,6 +57,6 @@ pub fn parse_legacy_cells<'a, Iter: Iterator<Item = &'a ConfigOverride>>(
     // the base configs derived from the config files. This requires us to
     // store the base configs + overlaid ones separately, so we can cheaply
     // recompose.
-    let res = BuckConfigBasedCells::parse_with_config_args(fs, &config_values, cwd)?;
+    let res = BuckConfigBasedCells::parse_with_config_args(fs, buck_out_dir, &config_values, cwd)?;
     Ok((res.cell_resolver, res.configs_by_name))
 }
@@ -21,6 +21,7 @@ use buck2_build_api::actions::build_listener::SetBuildSignals;
 use buck2_build_api::actions::impls::run::knobs::HasRunActionKnobs;
 use buck2_build_api::actions::impls::run::knobs::RunActionKnobs;
 use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
+use buck2_build_api::configuration::HasConfigurationModifiers;
 use buck2_build_api::context::SetBuildContextData;
 use buck2_build_api::interpreter::context::configure_build_file_globals;
 use buck2_build_api::interpreter::context::configure_extension_file_globals;
@@ -49,6 +50,7 @@ use buck2_core::fs::project::ProjectRelativePathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::pattern::ParsedPattern;
 use buck2_core::pattern::ProvidersPattern;
+use buck2_core::pattern::TargetPattern;
 use buck2_core::truncate::truncate_container;
 use buck2_events::dispatch::EventDispatcher;
 use buck2_events::metadata;
@@ -167,6 +169,9 @@ pub struct ServerCommandContext {
     host_platform_override: HostPlatformOverride,
     host_arch_override: HostArchOverride,
 
+    /// Configuration modifiers (`--modifier`) to apply on top of each target's platform.
+    modifiers: Vec<String>,
+
     // This ensures that there's only one RE connection during the lifetime of this context. It's possible
     // that we give out other handles, but we don't depend on the lifetimes of those for this guarantee. We
     // also use this to send a RemoteExecutionSessionCreated if the connection is made.
@@ -267,6 +272,7 @@ impl ServerCommandContext {
         let cell_configs_loader = Arc::new(CellConfigLoader {
             project_root: base_context.project_root.clone(),
             working_dir: project_path.to_buf().into(),
+            buck_out_dir: buck_out_dir.clone(),
             reuse_current_config: client_context.reuse_current_config,
             config_overrides: client_context.config_overrides.clone(),
             loaded_cell_configs: AsyncOnceCell::new(),
@@ -277,6 +283,7 @@ impl ServerCommandContext {
             working_dir: project_path.to_buf().into(),
             host_platform_override: client_context.host_platform(),
             host_arch_override: client_context.host_arch(),
+            modifiers: client_context.modifiers.clone(),
             oncall,
             _re_connection_handle: re_connection_handle,
             build_signals,
@@ -344,6 +351,7 @@ impl ServerCommandContext {
             events: self.events().dupe(),
             execution_strategy,
             run_action_knobs,
+            modifiers: self.modifiers.clone(),
             concurrency,
             executor_config,
             blocking_executor,
@@ -384,6 +392,8 @@ impl ServerCommandContext {
 struct CellConfigLoader {
     project_root: ProjectRoot,
     working_dir: ProjectRelativePathBuf,
+    /// Where external cells are materialized.
+    buck_out_dir: ProjectRelativePathBuf,
     /// Reuses build config from the previous invocation if there is one
     reuse_current_config: bool,
     config_overrides: Vec<ConfigOverride>,
@@ -421,7 +431,12 @@ impl CellConfigLoader {
                         );
                     }
                 }
-                parse_legacy_cells(self.config_overrides.iter(), &fs.resolve(cwd), fs)
+                parse_legacy_cells(
+                    self.config_overrides.iter(),
+                    &fs.resolve(cwd),
+                    fs,
+                    &self.buck_out_dir,
+                )
                     .shared_error()
             })
             .await
@@ -442,6 +457,7 @@ struct DiceCommandDataProvider {
     forkserver: Option<ForkserverClient>,
     upload_all_actions: bool,
     run_action_knobs: RunActionKnobs,
+    modifiers: Vec<String>,
     no_remote_cache: bool,
     create_unhashed_symlink_lock: Arc<Mutex<()>>,
 }
@@ -468,7 +484,15 @@ impl DiceDataProvider for DiceCommandDataProvider {
             .concurrency
             .unwrap_or_else(|| parse_concurrency(config_threads))?;
 
-        let executor_global_knobs = ExecutorGlobalKnobs {};
+        let executor_global_knobs = ExecutorGlobalKnobs {
+            remote_dep_file_cache: root_config
+                .parse("buck2", "remote_dep_file_cache")?
+                .unwrap_or(false),
+        };
+        let run_action_knobs = RunActionKnobs {
+            remote_dep_file_cache: executor_global_knobs.remote_dep_file_cache,
+            ..self.run_action_knobs
+        };
 
         let host_sharing_broker =
             HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, concurrency);
@@ -480,6 +504,20 @@ impl DiceDataProvider for DiceCommandDataProvider {
         // would expect to start losing out to RE in terms of perf.
         let low_pass_filter = LowPassFilter::new(concurrency);
 
+        // Modifiers are resolved relative to the client's working directory, like the target
+        // platform is.
+        let modifiers = {
+            let cwd = cell_resolver.get_cell_path(&self.cell_configs_loader.working_dir)?;
+            let cwd_alias_resolver = cell_resolver.get(cwd.cell())?.cell_alias_resolver();
+            self.modifiers
+                .iter()
+                .map(|modifier| {
+                    ParsedPattern::<TargetPattern>::parse_precise(cwd_alias_resolver, modifier)?
+                        .as_target_label(modifier)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+
         let mut data = DiceData::new();
         data.set(self.events.dupe());
 
@@ -520,7 +558,8 @@ impl DiceDataProvider for DiceCommandDataProvider {
         data.set_blocking_executor(self.blocking_executor);
         data.set_materializer(self.materializer);
         data.set_build_signals(self.build_signals);
-        data.set_run_action_knobs(self.run_action_knobs);
+        data.set_run_action_knobs(run_action_knobs);
+        data.set_configuration_modifiers(modifiers);
         data.set_create_unhashed_symlink_lock(self.create_unhashed_symlink_lock);
         data.spawner = Arc::new(BuckSpawner::default());
         Ok(data)
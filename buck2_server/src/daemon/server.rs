@@ -24,6 +24,7 @@ use std::time::Instant;
 use std::time::SystemTime;
 
 use allocative::Allocative;
+use allocative::SharedAttribution;
 use anyhow::Context as _;
 use async_trait::async_trait;
 use buck2_build_api::actions::build_listener;
@@ -720,9 +721,15 @@ impl DaemonApi for BuckdServer {
         &self,
         req: Request<FlushDepFilesRequest>,
     ) -> Result<Response<CommandResult>, Status> {
+        let daemon_state = self.0.daemon_state.dupe();
+
         self.oneshot(req, DefaultCommandOptions, move |req| async move {
             let FlushDepFilesRequest {} = req;
-            buck2_build_api::actions::impls::run::dep_files::flush_dep_files();
+            let data = daemon_state.data().await?;
+            buck2_build_api::actions::impls::run::dep_files::flush_persisted_dep_files(
+                &*data.materializer,
+            )
+            .await?;
             Ok(GenericResponse {})
         })
         .await
@@ -938,9 +945,26 @@ impl DaemonApi for BuckdServer {
         Ok(
             streaming(req, event_source, dispatcher.dupe(), |req| async move {
                 let result = try {
+                    let shared_attribution =
+                        match cli_proto::allocative_request::SharedAttribution::from_i32(
+                            req.shared_attribution,
+                        )
+                        .context("Invalid shared attribution")?
+                        {
+                            cli_proto::allocative_request::SharedAttribution::Root => {
+                                SharedAttribution::Root
+                            }
+                            cli_proto::allocative_request::SharedAttribution::FirstVisitor => {
+                                SharedAttribution::FirstVisitor
+                            }
+                            cli_proto::allocative_request::SharedAttribution::Proportional => {
+                                SharedAttribution::Proportional
+                            }
+                        };
                     spawn_allocative(
                         this,
                         AbsPathBuf::try_from(req.output_path)?,
+                        shared_attribution,
                         dispatcher.dupe(),
                     )
                     .await?;
@@ -10,6 +10,7 @@
 use std::sync::Arc;
 
 use allocative::FlameGraphBuilder;
+use allocative::SharedAttribution;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_path::AbsPathBuf;
 use buck2_events::dispatch::EventDispatcher;
@@ -19,10 +20,11 @@ use crate::daemon::server::BuckdServerData;
 pub(crate) async fn spawn_allocative(
     buckd_server_data: Arc<BuckdServerData>,
     path: AbsPathBuf,
+    shared_attribution: SharedAttribution,
     dispatcher: EventDispatcher,
 ) -> anyhow::Result<()> {
     tokio::task::spawn_blocking(move || {
-        let mut graph = FlameGraphBuilder::default();
+        let mut graph = FlameGraphBuilder::with_shared_attribution(shared_attribution);
         dispatcher.console_message(
             "Starting allocative profiling. It may take a while to finish...".to_owned(),
         );
@@ -42,6 +44,11 @@ pub(crate) async fn spawn_allocative(
             &mut fg_svg,
         )?;
         fs_util::write(path.join("flamegraph.svg"), &fg_svg)?;
+        // Consumed by `buck2 debug allocative-diff`.
+        fs_util::write(
+            path.join("flamegraph.json"),
+            &serde_json::to_vec(fg.tree())?,
+        )?;
 
         fs_util::write(path.join("warnings.txt"), fg.warnings())?;
 
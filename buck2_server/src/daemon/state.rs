@@ -30,9 +30,14 @@ use buck2_core::fs::project::ProjectRelativePathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::rollout_percentage::RolloutPercentage;
 use buck2_events::dispatch::EventDispatcher;
+use buck2_events::sink::bep::BepSink;
+use buck2_events::sink::bep::BepSinkConfig;
+use buck2_events::sink::otel::OtelSink;
+use buck2_events::sink::otel::OtelSinkConfig;
 use buck2_events::sink::scribe;
 use buck2_events::sink::tee::TeeSink;
 use buck2_events::trace::TraceId;
+use buck2_events::EventSink;
 use buck2_events::EventSource;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::BuckBlockingExecutor;
@@ -109,7 +114,7 @@ pub struct DaemonStateData {
     /// needs access to the `ReConnectionManager` to download from RE. It must
     /// live for the entire lifetime of the daemon, in order to allow deferred
     /// materializations to work properly between distinct build commands.
-    materializer: Arc<dyn Materializer>,
+    pub(crate) materializer: Arc<dyn Materializer>,
 
     forkserver: Option<ForkserverClient>,
 
@@ -150,6 +155,105 @@ impl DaemonStatePanicDiceDump for DaemonStateData {
 pub struct EventLoggingData {
     /// The size of the queue for in-flight messages.
     buffer_size: usize,
+    /// Where to publish build events using the Build Event Protocol, if anywhere.
+    #[allocative(skip)]
+    bep: Option<BepSinkConfig>,
+    /// Where to export spans using OpenTelemetry, if anywhere.
+    #[allocative(skip)]
+    otel: Option<OtelSinkConfig>,
+}
+
+impl EventLoggingData {
+    fn new(root_config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        let buffer_size = root_config
+            .parse("buck2", "event_log_buffer_size")?
+            .unwrap_or(10000);
+
+        Ok(Self {
+            buffer_size,
+            bep: parse_bep_config(root_config)?,
+            otel: parse_otel_config(root_config)?,
+        })
+    }
+}
+
+/// A comma-separated list of `name=value`, from `section.headers`.
+fn parse_headers(
+    root_config: &LegacyBuckConfig,
+    section: &str,
+) -> anyhow::Result<Option<Vec<(String, String)>>> {
+    let headers = match root_config.get(section, "headers") {
+        Some(headers) => headers,
+        None => return Ok(None),
+    };
+    headers
+        .split(',')
+        .map(|header| header.trim())
+        .filter(|header| !header.is_empty())
+        .map(|header| {
+            let (name, value) = header.split_once('=').with_context(|| {
+                format!(
+                    "Invalid header `{}` in `{}.headers`, expected `name=value`",
+                    header, section
+                )
+            })?;
+            Ok((name.trim().to_owned(), value.trim().to_owned()))
+        })
+        .collect::<anyhow::Result<_>>()
+        .map(Some)
+}
+
+/// Publishing to a build event service is enabled by setting `bep.endpoint`.
+fn parse_bep_config(root_config: &LegacyBuckConfig) -> anyhow::Result<Option<BepSinkConfig>> {
+    const SECTION: &str = "bep";
+
+    let mut config = match root_config.get(SECTION, "endpoint") {
+        Some(endpoint) => BepSinkConfig::new(endpoint.to_owned()),
+        None => return Ok(None),
+    };
+
+    if let Some(project_id) = root_config.get(SECTION, "project_id") {
+        config.project_id = project_id.to_owned();
+    }
+    if let Some(headers) = parse_headers(root_config, SECTION)? {
+        config.headers = headers;
+    }
+    if let Some(buffer_size) = root_config.parse(SECTION, "buffer_size")? {
+        config.buffer_size = buffer_size;
+    }
+    if let Some(max_in_flight) = root_config.parse(SECTION, "max_in_flight")? {
+        config.max_in_flight = max_in_flight;
+    }
+    if let Some(max_retries) = root_config.parse(SECTION, "max_retries")? {
+        config.max_retries = max_retries;
+    }
+    if let Some(publish_all_actions) = root_config.parse(SECTION, "publish_all_actions")? {
+        config.publish_all_actions = publish_all_actions;
+    }
+
+    Ok(Some(config))
+}
+
+/// Exporting spans to an OpenTelemetry collector is enabled by setting `otel.endpoint`.
+fn parse_otel_config(root_config: &LegacyBuckConfig) -> anyhow::Result<Option<OtelSinkConfig>> {
+    const SECTION: &str = "otel";
+
+    let mut config = match root_config.get(SECTION, "endpoint") {
+        Some(endpoint) => OtelSinkConfig::new(endpoint.to_owned()),
+        None => return Ok(None),
+    };
+
+    if let Some(headers) = parse_headers(root_config, SECTION)? {
+        config.headers = headers;
+    }
+    if let Some(batch_size) = root_config.parse(SECTION, "batch_size")? {
+        config.batch_size = batch_size;
+    }
+    if let Some(buffer_size) = root_config.parse(SECTION, "buffer_size")? {
+        config.buffer_size = buffer_size;
+    }
+
+    Ok(Some(config))
 }
 
 pub trait DaemonStateDiceConstructor: Allocative + Send + Sync + 'static {
@@ -183,7 +287,7 @@ impl DaemonState {
     ) -> anyhow::Result<Arc<DaemonStateData>> {
         let fs = paths.project_root().clone();
 
-        let legacy_cells = BuckConfigBasedCells::parse(&fs)?;
+        let legacy_cells = BuckConfigBasedCells::parse(&fs, &paths.buck_out_dir())?;
         let (legacy_configs, cells) = (legacy_cells.configs_by_name, legacy_cells.cell_resolver);
 
         let root_config = legacy_configs
@@ -257,10 +361,7 @@ impl DaemonState {
             materializer_state,
         )?;
 
-        let buffer_size = root_config
-            .parse("buck2", "event_log_buffer_size")?
-            .unwrap_or(10000);
-        let event_logging_data = Arc::new(EventLoggingData { buffer_size });
+        let event_logging_data = Arc::new(EventLoggingData::new(root_config)?);
 
         let dice = dice_constructor.construct_dice(io.dupe(), root_config)?;
 
@@ -416,7 +517,8 @@ impl DaemonState {
     }
 
     /// Prepares an event stream for a request by bootstrapping an event source and EventDispatcher pair. The given
-    /// EventDispatcher will log to the returned EventSource and (optionally) to Scribe if enabled via buckconfig.
+    /// EventDispatcher will log to the returned EventSource and (optionally) to Scribe, to a build event service and to
+    /// an OpenTelemetry collector if enabled via buckconfig.
     pub async fn prepare_events(
         &self,
         trace_id: TraceId,
@@ -425,17 +527,22 @@ impl DaemonState {
         facebook_only();
         let (events, sink) = buck2_events::create_source_sink_pair();
         let data = self.data().await?;
-        let dispatcher = if let Some(scribe_sink) =
+        let mut sink: Box<dyn EventSink> = Box::new(sink);
+        if let Some(config) = &data.event_logging_data.otel {
+            sink = Box::new(TeeSink::new(OtelSink::new(config.clone())?, sink));
+        }
+        if let Some(config) = &data.event_logging_data.bep {
+            sink = Box::new(TeeSink::new(BepSink::new(config.clone(), &trace_id)?, sink));
+        }
+        // Writing to Scribe via the HTTP gateway (what we do for a Cargo build) is many times slower than the fbcode
+        // Scribe client, so we don't do it. It's really, really bad for build performance - turning it on regresses
+        // build performance by 10x.
+        if let Some(scribe_sink) =
             scribe::new_thrift_scribe_sink_if_enabled(self.fb, data.event_logging_data.buffer_size)?
         {
-            EventDispatcher::new(trace_id, TeeSink::new(scribe_sink, sink))
-        } else {
-            // Writing to Scribe via the HTTP gateway (what we do for a Cargo build) is many times slower than the fbcode
-            // Scribe client, so we don't do it. It's really, really bad for build performance - turning it on regresses
-            // build performance by 10x.
-            EventDispatcher::new(trace_id, sink)
-        };
-        Ok((events, dispatcher))
+            sink = Box::new(TeeSink::new(scribe_sink, sink));
+        }
+        Ok((events, EventDispatcher::new(trace_id, sink)))
     }
 
     /// Prepares a ServerCommandContext for processing a complex command (that accesses the dice computation graph, for example).
@@ -9,6 +9,7 @@
 
 #![allow(clippy::drop_non_drop)] // FIXME?
 
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
@@ -22,10 +23,14 @@ use buck2_query::query::syntax::simple::eval::file_set::FileSet;
 use buck2_query::query::syntax::simple::eval::multi_query::MultiQueryResult;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
+use cli_proto::query_result::query_result_entry;
+use cli_proto::query_result::QueryResultEntry;
+use cli_proto::query_result::QueryResultTarget;
 use cli_proto::QueryOutputFormat;
 use gazebo::prelude::*;
 use gazebo::variants::UnpackVariants;
 use indent_write::fmt::IndentWriter;
+use prost::Message;
 use regex::RegexSet;
 use serde::ser::SerializeMap;
 use serde::ser::SerializeSeq;
@@ -190,6 +195,54 @@ impl<'a> Serialize for FileSetJsonPrinter<'a> {
     }
 }
 
+fn proto_target<T: QueryTarget>(
+    target: &T,
+    attributes: &Option<RegexSet>,
+) -> anyhow::Result<QueryResultTarget> {
+    let mut attrs = HashMap::new();
+    if let Some(attr_regex) = attributes {
+        QueryTargets::for_all_attrs::<anyhow::Error, _, _>(target, |attr_name, attr_value| {
+            if attr_regex.is_match(attr_name) {
+                attrs.insert(attr_name.to_owned(), serde_json::to_string(attr_value)?);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(QueryResultTarget {
+        label: target.node_ref().to_string(),
+        attributes: attrs,
+        deps: target.deps().map(|dep| dep.to_string()).collect(),
+    })
+}
+
+fn write_proto_entry(
+    output: &mut impl std::io::Write,
+    entry: query_result_entry::Entry,
+) -> anyhow::Result<()> {
+    let entry = QueryResultEntry { entry: Some(entry) };
+    output.write_all(&entry.encode_length_delimited_to_vec())?;
+    Ok(())
+}
+
+/// Writes a single line of the NDJSON output for a target: the entry the target has in the
+/// JSON output.
+fn write_ndjson_target<T: QueryTarget>(
+    output: &mut impl std::io::Write,
+    target: &PrintableQueryTarget<T>,
+    is_complex: bool,
+) -> anyhow::Result<()> {
+    let mut ser = serde_json::Serializer::new(&mut *output);
+    if is_complex {
+        let mut map = ser.serialize_map(Some(1))?;
+        map.serialize_entry(&target.label(), target)?;
+        SerializeMap::end(map)?;
+    } else {
+        target.label().serialize(&mut ser)?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
 impl<'a> QueryResultPrinter<'a> {
     /// Utility for creating from the options in their protobuf form.
     pub fn from_request_options(
@@ -344,6 +397,30 @@ impl<'a> QueryResultPrinter<'a> {
                         &mut output,
                     )?;
                 }
+                QueryOutputFormat::Proto => {
+                    for target in targets.iter() {
+                        write_proto_entry(
+                            &mut output,
+                            query_result_entry::Entry::Target(proto_target(
+                                target,
+                                &self.attributes,
+                            )?),
+                        )?;
+                    }
+                }
+                QueryOutputFormat::Ndjson => {
+                    let is_complex = self.attributes.is_some()
+                        || call_stack
+                        || print_providers.unpack_yes().is_some();
+                    // Targets are looked up and written one at a time so that the whole output is
+                    // never held in memory.
+                    for target in targets.iter() {
+                        let target =
+                            printable_target(target, print_providers, &self.attributes, call_stack)
+                                .await?;
+                        write_ndjson_target(&mut output, &target, is_complex)?;
+                    }
+                }
             },
             QueryEvaluationValue::FileSet(files) => {
                 if self.attributes.is_some() {
@@ -372,6 +449,25 @@ impl<'a> QueryResultPrinter<'a> {
                     QueryOutputFormat::DotCompact => {
                         unimplemented!("dot_compact output for files not implemented yet")
                     }
+                    QueryOutputFormat::Proto => {
+                        for file in files.iter() {
+                            write_proto_entry(
+                                &mut output,
+                                query_result_entry::Entry::File(
+                                    self.resolver.resolve_path(file)?.to_string(),
+                                ),
+                            )?;
+                        }
+                    }
+                    QueryOutputFormat::Ndjson => {
+                        for file in files.iter() {
+                            serde_json::to_writer(
+                                &mut output,
+                                &self.resolver.resolve_path(file)?.to_string(),
+                            )?;
+                            writeln!(&mut output)?;
+                        }
+                    }
                 }
             }
         }
@@ -380,29 +476,302 @@ impl<'a> QueryResultPrinter<'a> {
     }
 }
 
+async fn printable_target<'a, T: QueryTarget>(
+    target: &'a T,
+    print_providers: ShouldPrintProviders<'_, T>,
+    attributes: &'a Option<RegexSet>,
+    target_call_stacks: bool,
+) -> anyhow::Result<PrintableQueryTarget<'a, T>> {
+    Ok(PrintableQueryTarget {
+        value: target,
+        attributes,
+        target_call_stacks,
+        providers: match print_providers {
+            ShouldPrintProviders::No => None,
+            ShouldPrintProviders::Yes(lookup) => {
+                Some(lookup.lookup(target).await?.require_compatible()?)
+            }
+        },
+    })
+}
+
 async fn printable_targets<'a, T: QueryTarget>(
     targets: &'a TargetSet<T>,
     print_providers: ShouldPrintProviders<'a, T>,
     attributes: &'a Option<RegexSet>,
     target_call_stacks: bool,
 ) -> anyhow::Result<Vec<PrintableQueryTarget<'a, T>>> {
-    futures::future::join_all(targets.iter().map(|t| {
-        let print_providers = &print_providers;
-        async move {
-            Ok(PrintableQueryTarget {
-                value: t,
-                attributes,
-                target_call_stacks,
-                providers: match print_providers {
-                    ShouldPrintProviders::No => None,
-                    ShouldPrintProviders::Yes(lookup) => {
-                        Some(lookup.lookup(t).await?.require_compatible()?)
-                    }
-                },
-            })
-        }
-    }))
+    futures::future::join_all(
+        targets
+            .iter()
+            .map(|t| printable_target(t, print_providers, attributes, target_call_stacks)),
+    )
     .await
     .into_iter()
     .collect::<anyhow::Result<_>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::fmt;
+    use std::sync::Arc;
+
+    use buck2_core::build_file_path::BuildFilePath;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::testing::CellResolverExt;
+    use buck2_core::cells::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::project::ProjectRelativePathBuf;
+    use buck2_query::query::environment::LabeledNode;
+    use buck2_query::query::environment::NodeLabel;
+    use buck2_query::query::environment::QueryTarget;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+    use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
+    use cli_proto::query_result::query_result_entry;
+    use cli_proto::query_result::QueryResultEntry;
+    use cli_proto::query_result::QueryResultTarget;
+    use cli_proto::QueryOutputFormat;
+    use gazebo::prelude::*;
+    use prost::Message;
+
+    use crate::commands::query::printer::QueryResultPrinter;
+    use crate::commands::query::printer::ShouldPrintProviders;
+
+    #[derive(Debug, Clone, Dupe, Eq, PartialEq, Hash)]
+    struct TestTargetId(&'static str);
+
+    impl fmt::Display for TestTargetId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl NodeLabel for TestTargetId {}
+
+    #[derive(Clone, Dupe)]
+    struct TestTarget {
+        id: TestTargetId,
+        deps: Arc<Vec<TestTargetId>>,
+        attrs: Arc<Vec<(&'static str, &'static str)>>,
+    }
+
+    impl LabeledNode for TestTarget {
+        type NodeRef = TestTargetId;
+
+        fn node_ref(&self) -> &Self::NodeRef {
+            &self.id
+        }
+    }
+
+    impl QueryTarget for TestTarget {
+        type Attr = str;
+
+        fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
+            &self,
+            _func: F,
+        ) -> Result<(), E> {
+            unimplemented!()
+        }
+
+        fn rule_type(&self) -> Cow<str> {
+            unimplemented!()
+        }
+
+        fn buildfile_path(&self) -> &BuildFilePath {
+            unimplemented!()
+        }
+
+        fn deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+            box self.deps.iter()
+        }
+
+        fn exec_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+            box std::iter::empty()
+        }
+
+        fn target_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+            box std::iter::empty()
+        }
+
+        fn attr_any_matches(
+            _attr: &Self::Attr,
+            _filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+        ) -> anyhow::Result<bool> {
+            unimplemented!()
+        }
+
+        fn special_attrs_for_each<E, F: FnMut(&str, &Self::Attr) -> Result<(), E>>(
+            &self,
+            _func: F,
+        ) -> Result<(), E> {
+            Ok(())
+        }
+
+        fn attrs_for_each<E, F: FnMut(&str, &Self::Attr) -> Result<(), E>>(
+            &self,
+            mut func: F,
+        ) -> Result<(), E> {
+            for (name, value) in self.attrs.iter() {
+                func(name, value)?;
+            }
+            Ok(())
+        }
+
+        fn map_attr<R, F: FnMut(Option<&Self::Attr>) -> R>(&self, _key: &str, _func: F) -> R {
+            unimplemented!()
+        }
+
+        fn call_stack(&self) -> Option<String> {
+            None
+        }
+    }
+
+    fn target(
+        id: &'static str,
+        deps: &[&'static str],
+        attrs: &[(&'static str, &'static str)],
+    ) -> TestTarget {
+        TestTarget {
+            id: TestTargetId(id),
+            deps: Arc::new(deps.map(|d| TestTargetId(*d))),
+            attrs: Arc::new(attrs.to_vec()),
+        }
+    }
+
+    fn cell_resolver() -> CellResolver {
+        CellResolver::of_names_and_paths(&[(
+            CellName::unchecked_new("root".to_owned()),
+            CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("".to_owned())),
+        )])
+    }
+
+    async fn print(
+        targets: &[TestTarget],
+        attributes: &[&str],
+        output_format: QueryOutputFormat,
+    ) -> anyhow::Result<Vec<u8>> {
+        let resolver = cell_resolver();
+        let printer = QueryResultPrinter::from_options(
+            &resolver,
+            &attributes.map(|a| (*a).to_owned()),
+            output_format,
+        )?;
+        let mut set = TargetSet::new();
+        for t in targets {
+            set.insert(t.dupe());
+        }
+        let mut output = Vec::new();
+        printer
+            .print_single_output(
+                &mut output,
+                QueryEvaluationValue::TargetSet(set),
+                false,
+                ShouldPrintProviders::No,
+            )
+            .await?;
+        Ok(output)
+    }
+
+    fn targets() -> Vec<TestTarget> {
+        vec![
+            target(
+                "root//:a",
+                &["root//:b", "root//:c"],
+                &[("cmd", "echo a"), ("name", "a")],
+            ),
+            target("root//:b", &[], &[("cmd", "true"), ("name", "b")]),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_proto_output() -> anyhow::Result<()> {
+        let output = print(&targets(), &["cmd"], QueryOutputFormat::Proto).await?;
+
+        let mut buf = output.as_slice();
+        let mut entries = Vec::new();
+        while !buf.is_empty() {
+            entries.push(QueryResultEntry::decode_length_delimited(&mut buf)?);
+        }
+
+        let expected = vec![
+            QueryResultEntry {
+                entry: Some(query_result_entry::Entry::Target(QueryResultTarget {
+                    label: "root//:a".to_owned(),
+                    attributes: [("cmd".to_owned(), "\"echo a\"".to_owned())]
+                        .into_iter()
+                        .collect(),
+                    // Deps outside of the result are included too.
+                    deps: vec!["root//:b".to_owned(), "root//:c".to_owned()],
+                })),
+            },
+            QueryResultEntry {
+                entry: Some(query_result_entry::Entry::Target(QueryResultTarget {
+                    label: "root//:b".to_owned(),
+                    attributes: [("cmd".to_owned(), "\"true\"".to_owned())]
+                        .into_iter()
+                        .collect(),
+                    deps: vec![],
+                })),
+            },
+        ];
+        assert_eq!(expected, entries);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_output() -> anyhow::Result<()> {
+        let output = print(&targets(), &[], QueryOutputFormat::Ndjson).await?;
+        assert_eq!("\"root//:a\"\n\"root//:b\"\n", String::from_utf8(output)?);
+
+        let output = print(&targets(), &["cmd"], QueryOutputFormat::Ndjson).await?;
+        assert_eq!(
+            concat!(
+                "{\"root//:a\":{\"cmd\":\"echo a\"}}\n",
+                "{\"root//:b\":{\"cmd\":\"true\"}}\n",
+            ),
+            String::from_utf8(output)?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dot_label_escaping() -> anyhow::Result<()> {
+        let targets = [
+            target(
+                "root//:a",
+                &["root//:b"],
+                &[("cmd", "printf \"a\\tb\"\ndone"), ("name", "a")],
+            ),
+            target("root//:b", &[], &[("cmd", "true"), ("name", "b")]),
+        ];
+
+        // In the label, backslashes are escaped, newlines are written as `\n` and quotes are
+        // escaped when the label is quoted.
+        let output = print(&targets, &["cmd"], QueryOutputFormat::Dot).await?;
+        assert_eq!(
+            r##"digraph result_graph {
+  "root//:a" [style=filled,color="#DFECDF",label="root//:a\ncmd=printf \"a\\tb\"\ndone",buck_cmd="printf \"a\tb\"
+done"];
+  "root//:a" -> "root//:b";
+  "root//:b" [style=filled,color="#DFECDF",label="root//:b\ncmd=true",buck_cmd=true];
+}
+"##,
+            String::from_utf8(output)?
+        );
+
+        let output = print(&targets, &["cmd"], QueryOutputFormat::DotCompact).await?;
+        assert_eq!(
+            r##"digraph result_graph {
+  1 [style=filled,color="#DFECDF",label="root//:a\ncmd=printf \"a\\tb\"\ndone",buck_cmd="printf \"a\tb\"
+done"];
+  1 -> 2;
+  2 [style=filled,color="#DFECDF",label="root//:b\ncmd=true",buck_cmd=true];
+}
+"##,
+            String::from_utf8(output)?
+        );
+        Ok(())
+    }
+}
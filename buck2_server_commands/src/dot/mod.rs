@@ -151,15 +151,13 @@ impl DotCompact {
         };
 
         graph.for_each_node(|node| {
-            let attrs = node.attrs()?;
+            let mut attrs = node.attrs()?;
             let node_name = &escape_id(&node.id());
-            writeln!(
-                w,
-                "  {} [{},label={}];",
-                name_to_number(node_name),
-                attrs,
-                escape_id(&node.id())
-            )?;
+            // Nodes are identified by numbers here, so they need a label to show the id.
+            if attrs.label.is_none() {
+                attrs.label = Some(node.id());
+            }
+            writeln!(w, "  {} [{}];", name_to_number(node_name), attrs)?;
             graph.for_each_edge(node, |edge| {
                 writeln!(
                     w,
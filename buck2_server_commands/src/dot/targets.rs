@@ -64,28 +64,36 @@ impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
 
 impl<'a, T: QueryTarget> DotNode for DotTargetGraphNode<'a, T> {
     fn attrs(&self) -> anyhow::Result<DotNodeAttrs> {
-        let extra = match &self.1.attributes {
+        let (label, extra) = match &self.1.attributes {
             Some(attr_regex) => {
                 let mut extra = IndexMap::new();
+                // The requested attributes are shown below the target in the node label, one per
+                // line (`\n` is a newline in dot strings).
+                let mut label = self.id();
                 QueryTargets::for_all_attrs::<anyhow::Error, _, _>(
                     self.0,
                     |attr_name, attr_value| {
                         if attr_regex.is_match(attr_name) {
-                            extra
-                                .insert(format!("buck_{}", attr_name), format!("{:#}", attr_value));
+                            let value = format!("{:#}", attr_value);
+                            label.push_str(&format!(
+                                "\\n{}={}",
+                                attr_name,
+                                value.replace('\\', "\\\\").replace('\n', "\\n")
+                            ));
+                            extra.insert(format!("buck_{}", attr_name), value);
                         }
                         Ok(())
                     },
                 )?;
-                extra
+                (Some(label), extra)
             }
-            None => indexmap![],
+            None => (None, indexmap![]),
         };
         Ok(DotNodeAttrs {
             style: Some("filled".to_owned()),
             color: Some("#DFECDF".to_owned()),
+            label,
             extra,
-            ..DotNodeAttrs::default()
         })
     }
 
@@ -0,0 +1,71 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+
+#[cfg(unix)]
+type RawFd = std::os::unix::io::RawFd;
+
+#[cfg(windows)]
+type RawFd = String;
+
+#[derive(Debug, clap::Parser)]
+#[clap(about = "run the built-in test executor, used by `buck2 test`")]
+pub(crate) struct InternalTestRunnerCommand {
+    #[clap(long, requires = "orchestrator-fd", conflicts_with = "executor-addr")]
+    executor_fd: Option<RawFd>,
+    #[clap(long)]
+    orchestrator_fd: Option<RawFd>,
+    #[clap(long, requires = "orchestrator-addr")]
+    executor_addr: Option<String>,
+    #[clap(long)]
+    orchestrator_addr: Option<String>,
+    /// Arguments passed to the test executor by `buck2 test`.
+    #[clap(last = true)]
+    test_executor_args: Vec<String>,
+}
+
+impl InternalTestRunnerCommand {
+    pub(crate) fn exec(
+        self,
+        _matches: &clap::ArgMatches,
+        _ctx: ClientCommandContext,
+    ) -> anyhow::Result<()> {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+
+        match self {
+            #[cfg(unix)]
+            Self {
+                executor_fd: Some(executor_fd),
+                orchestrator_fd: Some(orchestrator_fd),
+                test_executor_args,
+                ..
+            } => rt.block_on(buck2_test_runner::run_from_fds(
+                executor_fd,
+                orchestrator_fd,
+                test_executor_args,
+            )),
+            Self {
+                executor_addr: Some(executor_addr),
+                orchestrator_addr: Some(orchestrator_addr),
+                test_executor_args,
+                ..
+            } => rt.block_on(buck2_test_runner::run_from_addrs(
+                &executor_addr,
+                &orchestrator_addr,
+                test_executor_args,
+            )),
+            _ => Err(anyhow::anyhow!(
+                "Either `--executor-fd` and `--orchestrator-fd`, or `--executor-addr` and `--orchestrator-addr` must be set"
+            )),
+        }
+    }
+}
@@ -11,3 +11,4 @@ pub mod daemon;
 pub(crate) mod daemonize;
 pub mod docs;
 pub mod forkserver;
+pub mod internal_test_runner;
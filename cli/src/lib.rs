@@ -68,6 +68,7 @@ use crate::check_user_allowed::check_user_allowed;
 use crate::commands::daemon::DaemonCommand;
 use crate::commands::docs::DocsCommand;
 use crate::commands::forkserver::ForkserverCommand;
+use crate::commands::internal_test_runner::InternalTestRunnerCommand;
 
 #[macro_use]
 pub mod panic;
@@ -173,7 +174,10 @@ pub fn exec(
     let opt: Opt = Opt::from_clap(&matches);
 
     match &opt.cmd {
-        CommandKind::Clean(..) | CommandKind::Daemon(..) | CommandKind::Forkserver(..) => {}
+        CommandKind::Clean(..)
+        | CommandKind::Daemon(..)
+        | CommandKind::Forkserver(..)
+        | CommandKind::InternalTestRunner(..) => {}
         _ => {
             check_user_allowed()?;
         }
@@ -188,6 +192,8 @@ pub(crate) enum CommandKind {
     Daemon(DaemonCommand),
     #[clap(setting(AppSettings::Hidden))]
     Forkserver(ForkserverCommand),
+    #[clap(setting(AppSettings::Hidden))]
+    InternalTestRunner(InternalTestRunnerCommand),
     #[clap(subcommand)]
     Audit(AuditCommand),
     Aquery(AqueryCommand),
@@ -314,6 +320,7 @@ impl CommandKind {
         match self {
             CommandKind::Daemon(..) => unreachable!("Checked earlier"),
             CommandKind::Forkserver(cmd) => cmd.exec(matches, command_ctx).into(),
+            CommandKind::InternalTestRunner(cmd) => cmd.exec(matches, command_ctx).into(),
             CommandKind::Aquery(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Build(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Bxl(cmd) => cmd.exec(matches, command_ctx),
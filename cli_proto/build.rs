@@ -12,7 +12,7 @@ use std::io;
 use std::path::PathBuf;
 
 fn main() -> io::Result<()> {
-    let proto_files = &["daemon.proto"];
+    let proto_files = &["daemon.proto", "query_result.proto"];
     let events_include = if let Ok(value) = env::var("BUCK_HACK_PROTOC_INCLUDE") {
         let path = PathBuf::from(value);
         path.parent().unwrap().to_str().unwrap().to_owned()
@@ -16,6 +16,10 @@ use crate::BuckDaemonProtoError::MissingClientContext;
 
 tonic::include_proto!("buck.daemon");
 
+pub mod query_result {
+    tonic::include_proto!("buck.query_result");
+}
+
 #[derive(Debug, Error)]
 enum BuckDaemonProtoError {
     #[error("daemon request was missing client context")]
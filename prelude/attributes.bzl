@@ -118,7 +118,6 @@ attributes = {
         "default_host_platform": attrs.option(attrs.configuration_label(), default = None),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_aar": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -174,7 +173,6 @@ attributes = {
         "srcs": attrs.list(attrs.source(), default = []),
         "target": attrs.option(attrs.string(), default = None),
         "use_jvm_abi_gen": attrs.option(attrs.bool(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_app_modularity": {
         "application_module_blacklist": attrs.option(attrs.list(attrs.query()), default = None),
@@ -188,7 +186,6 @@ attributes = {
         "no_dx": attrs.list(attrs.dep(), default = []),
         "should_include_classes": attrs.bool(default = True),
         "should_include_libraries": attrs.bool(default = False),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_binary": {
         "aapt2_keep_raw_values": attrs.bool(),
@@ -267,7 +264,6 @@ attributes = {
         "skip_proguard": attrs.bool(),
         "trim_resource_ids": attrs.bool(),
         "use_split_dex": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "xz_compression_level": attrs.int(default = 4),
     },
     "android_build_config": {
@@ -278,7 +274,6 @@ attributes = {
         "package": attrs.string(default = ""),
         "values": attrs.list(attrs.string(), default = []),
         "values_file": attrs.option(attrs.source(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_bundle": {
         "aapt2_keep_raw_values": attrs.bool(),
@@ -358,7 +353,6 @@ attributes = {
         "skip_proguard": attrs.bool(),
         "trim_resource_ids": attrs.bool(),
         "use_split_dex": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "xz_compression_level": attrs.int(default = 4),
     },
     "android_instrumentation_apk": {
@@ -373,7 +367,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "manifest": attrs.option(attrs.source(), default = None),
         "manifest_skeleton": attrs.option(attrs.source(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_instrumentation_test": {
         "apk": attrs.dep(),
@@ -383,7 +376,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "test_rule_timeout_ms": attrs.option(attrs.int(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_library": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -427,7 +419,6 @@ attributes = {
         "srcs": attrs.list(attrs.source(), default = []),
         "target": attrs.option(attrs.string(), default = None),
         "use_jvm_abi_gen": attrs.option(attrs.bool(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_manifest": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -436,12 +427,10 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "skeleton": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_platform": {
         "base_platform": attrs.configuration_label(),
         "native_platforms": attrs.dict(key = attrs.enum(TargetCpuType), value = attrs.configuration_label(), sorted = False, default = {}),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_prebuilt_aar": {
         "aar": attrs.source(),
@@ -455,7 +444,6 @@ attributes = {
         "required_for_source_only_abi": attrs.bool(),
         "source_jar": attrs.option(attrs.source(), default = None),
         "use_system_library_loader": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "android_resource": {
         "allowlisted_locales": attrs.option(attrs.set(attrs.string(), sorted = False), default = None),
@@ -472,7 +460,6 @@ attributes = {
         "project_res": attrs.option(attrs.source(), default = None),
         "res": attrs.option(attrs.one_of(attrs.source(), attrs.dict(key = attrs.string(), value = attrs.source(), sorted = True)), default = None),
         "resource_union": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "apk_genrule": {
         "aab": attrs.option(attrs.dep(), default = None),
@@ -492,7 +479,6 @@ attributes = {
         "out": attrs.option(attrs.string(), default = None),
         "remote": attrs.option(attrs.bool(), default = None),
         "srcs": attrs.named_set(attrs.source(), sorted = False, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "apple_asset_catalog": {
         "app_icon": attrs.option(attrs.string(), default = None),
@@ -502,7 +488,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "launch_image": attrs.option(attrs.string(), default = None),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "apple_binary": {
         "bridging_header": attrs.option(attrs.source(), default = None),
@@ -593,7 +578,6 @@ attributes = {
         "uses_cxx_explicit_modules": attrs.bool(),
         "uses_explicit_modules": attrs.bool(),
         "uses_modules": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "xcode_private_headers_symlinks": attrs.option(attrs.bool(), default = None),
         "xcode_public_headers_symlinks": attrs.option(attrs.bool(), default = None),
     },
@@ -620,7 +604,6 @@ attributes = {
         "resource_group_map": attrs.option(attrs.list(attrs.tuple(attrs.string(), attrs.list(attrs.tuple(attrs.dep(), attrs.enum(Traversal), attrs.option(attrs.string()))))), default = None),
         "skip_copying_swift_stdlib": attrs.option(attrs.bool(), default = None),
         "try_skip_code_signing": attrs.option(attrs.bool(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "xcode_product_type": attrs.option(attrs.string(), default = None),
     },
     "apple_library": {
@@ -711,7 +694,6 @@ attributes = {
         "uses_cxx_explicit_modules": attrs.bool(),
         "uses_explicit_modules": attrs.bool(),
         "uses_modules": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "xcode_private_headers_symlinks": attrs.option(attrs.bool(), default = None),
         "xcode_public_headers_symlinks": attrs.option(attrs.bool(), default = None),
     },
@@ -723,7 +705,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "need_android_tools": attrs.bool(default = False),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "apple_resource": {
         "codesign_on_copy": attrs.bool(),
@@ -738,7 +719,6 @@ attributes = {
         "named_variants": attrs.dict(key = attrs.string(), value = attrs.set(attrs.source(), sorted = False), sorted = False, default = {}),
         "resources_from_deps": attrs.list(attrs.dep(), default = []),
         "variants": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "apple_test": {
         "asset_catalogs_compilation_options": attrs.dict(key = attrs.string(), value = attrs.any(), default = {}),
@@ -845,7 +825,6 @@ attributes = {
         "uses_cxx_explicit_modules": attrs.bool(),
         "uses_explicit_modules": attrs.bool(),
         "uses_modules": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "xcode_private_headers_symlinks": attrs.option(attrs.bool(), default = None),
         "xcode_product_type": attrs.option(attrs.string(), default = None),
         "xcode_public_headers_symlinks": attrs.option(attrs.bool(), default = None),
@@ -877,7 +856,6 @@ attributes = {
         "swift_toolchain": attrs.option(attrs.dep(), default = None),
         "version": attrs.string(default = ""),
         "watch_kit_stub_binary": attrs.option(attrs.source(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "work_around_dsymutil_lto_stack_overflow_bug": attrs.option(attrs.bool(), default = None),
         "xcode_build_version": attrs.string(default = ""),
         "xcode_version": attrs.string(default = ""),
@@ -889,7 +867,6 @@ attributes = {
         "default_host_platform": attrs.option(attrs.configuration_label(), default = None),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cgo_library": {
         "cgo_compiler_flags": attrs.list(attrs.string(), default = []),
@@ -945,7 +922,6 @@ attributes = {
         "thin_lto": attrs.bool(),
         "version_universe": attrs.option(attrs.string(), default = None),
         "weak_framework_names": attrs.list(attrs.string(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "command_alias": {
         "args": attrs.list(attrs.arg(), default = []),
@@ -957,12 +933,10 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "platform_exe": attrs.dict(key = attrs.enum(Platform), value = attrs.dep(), sorted = False, default = {}),
         "resources": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "config_setting": {
         "constraint_values": attrs.list(attrs.configuration_label(), default = []),
         "values": attrs.dict(key = attrs.string(), value = attrs.string(), sorted = False, default = {}),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "configured_alias": {
         "actual": attrs.configuration_label(),
@@ -971,14 +945,11 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "platform": attrs.configuration_label(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "constraint_setting": {
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "constraint_value": {
         "constraint_setting": attrs.configuration_label(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "core_data_model": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -986,7 +957,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "path": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "csharp_library": {
         "compiler_flags": attrs.list(attrs.string(), default = []),
@@ -999,7 +969,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "resources": attrs.dict(key = attrs.string(), value = attrs.source(), sorted = False, default = {}),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cxx_binary": {
         "compiler_flags": attrs.list(attrs.arg(), default = []),
@@ -1050,7 +1019,6 @@ attributes = {
         "thin_lto": attrs.bool(),
         "version_universe": attrs.option(attrs.string(), default = None),
         "weak_framework_names": attrs.list(attrs.string(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cxx_genrule": {
         "bash": attrs.option(attrs.arg(), default = None),
@@ -1070,7 +1038,6 @@ attributes = {
         "remote": attrs.option(attrs.bool(), default = None),
         "srcs": attrs.named_set(attrs.source(), sorted = False, default = []),
         "type": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cxx_library": {
         "bridging_header": attrs.option(attrs.source(), default = None),
@@ -1153,7 +1120,6 @@ attributes = {
         "uses_explicit_modules": attrs.bool(),
         "version_universe": attrs.option(attrs.string(), default = None),
         "weak_framework_names": attrs.list(attrs.string(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "xcode_private_headers_symlinks": attrs.option(attrs.bool(), default = None),
         "xcode_public_headers_symlinks": attrs.option(attrs.bool(), default = None),
     },
@@ -1195,7 +1161,6 @@ attributes = {
         "raw_headers": attrs.set(attrs.source(), sorted = True, default = []),
         "srcs": attrs.list(attrs.one_of(attrs.source(), attrs.tuple(attrs.source(), attrs.list(attrs.arg()))), default = []),
         "version_universe": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cxx_precompiled_header": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1205,7 +1170,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "src": attrs.source(),
         "version_universe": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cxx_python_extension": {
         "base_module": attrs.option(attrs.string(), default = None),
@@ -1248,7 +1212,6 @@ attributes = {
         "srcs": attrs.list(attrs.one_of(attrs.source(), attrs.tuple(attrs.source(), attrs.list(attrs.arg()))), default = []),
         "type_stub": attrs.option(attrs.source(), default = None),
         "version_universe": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cxx_test": {
         "additional_coverage_targets": attrs.list(attrs.source(), default = []),
@@ -1306,7 +1269,6 @@ attributes = {
         "use_default_test_main": attrs.option(attrs.bool(), default = None),
         "version_universe": attrs.option(attrs.string(), default = None),
         "weak_framework_names": attrs.list(attrs.string(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "cxx_toolchain": {
         "archive_contents": attrs.enum(ArchiveContents, default = "normal"),
@@ -1382,7 +1344,6 @@ attributes = {
         "strip_non_global_flags": attrs.option(attrs.list(attrs.arg()), default = None),
         "use_arg_file": attrs.bool(),
         "use_header_map": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "d_binary": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1392,7 +1353,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "linker_flags": attrs.list(attrs.string(), default = []),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "d_library": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1402,7 +1362,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "linker_flags": attrs.list(attrs.string(), default = []),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "d_test": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1413,7 +1372,6 @@ attributes = {
         "linker_flags": attrs.list(attrs.string(), default = []),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
         "test_rule_timeout_ms": attrs.option(attrs.int(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "export_file": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1423,7 +1381,6 @@ attributes = {
         "mode": attrs.option(attrs.enum(ExportFileDescriptionMode), default = None),
         "out": attrs.option(attrs.string(), default = None),
         "src": attrs.option(attrs.source(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "external_test_runner": {
         "binary": attrs.dep(),
@@ -1431,7 +1388,6 @@ attributes = {
         "default_host_platform": attrs.option(attrs.configuration_label(), default = None),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "filegroup": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1439,7 +1395,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "srcs": attrs.option(attrs.named_set(attrs.source(), sorted = False), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "gen_aidl": {
         "aidl": attrs.source(),
@@ -1451,7 +1406,6 @@ attributes = {
         "import_paths": attrs.list(attrs.string(), default = []),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "genrule": {
         "bash": attrs.option(attrs.arg(), default = None),
@@ -1472,7 +1426,6 @@ attributes = {
         "remote": attrs.option(attrs.bool(), default = None),
         "srcs": attrs.named_set(attrs.source(), sorted = False, default = []),
         "type": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "go_binary": {
         "assembler_flags": attrs.list(attrs.string(), default = []),
@@ -1490,7 +1443,6 @@ attributes = {
         "platform_external_linker_flags": attrs.list(attrs.tuple(attrs.regex(), attrs.list(attrs.arg())), default = []),
         "resources": attrs.list(attrs.source(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "go_exported_library": {
         "assembler_flags": attrs.list(attrs.string(), default = []),
@@ -1509,7 +1461,6 @@ attributes = {
         "platform_external_linker_flags": attrs.list(attrs.tuple(attrs.regex(), attrs.list(attrs.arg())), default = []),
         "resources": attrs.list(attrs.source(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "go_library": {
         "assembler_flags": attrs.list(attrs.string(), default = []),
@@ -1522,7 +1473,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "package_name": attrs.option(attrs.string(), default = None),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "go_test": {
         "assembler_flags": attrs.list(attrs.string(), default = []),
@@ -1547,7 +1497,6 @@ attributes = {
         "specs": attrs.option(attrs.arg(json = True), default = None),
         "srcs": attrs.list(attrs.source(), default = []),
         "test_rule_timeout_ms": attrs.option(attrs.int(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "go_test_runner": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1555,7 +1504,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "test_runner_generator": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "groovy_library": {
         "annotation_processor_deps": attrs.list(attrs.dep(), default = []),
@@ -1589,7 +1537,6 @@ attributes = {
         "source_only_abi_deps": attrs.list(attrs.dep(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
         "target": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "groovy_test": {
         "annotation_processor_deps": attrs.list(attrs.dep(), default = []),
@@ -1637,7 +1584,6 @@ attributes = {
         "use_cxx_libraries": attrs.option(attrs.bool(), default = None),
         "use_dependency_order_classpath": attrs.option(attrs.bool(), default = None),
         "vm_args": attrs.list(attrs.arg(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "gwt_binary": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1654,7 +1600,6 @@ attributes = {
         "strict": attrs.option(attrs.bool(), default = None),
         "style": attrs.option(attrs.enum(Style), default = None),
         "vm_args": attrs.list(attrs.string(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "halide_library": {
         "compiler_deps": attrs.list(attrs.dep(), default = []),
@@ -1709,7 +1654,6 @@ attributes = {
         "thin_lto": attrs.bool(),
         "version_universe": attrs.option(attrs.string(), default = None),
         "weak_framework_names": attrs.list(attrs.string(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "haskell_binary": {
         "compiler_flags": attrs.list(attrs.string(), default = []),
@@ -1730,7 +1674,6 @@ attributes = {
         "platform_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
         "platform_linker_flags": attrs.list(attrs.tuple(attrs.regex(), attrs.list(attrs.arg())), default = []),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "haskell_ghci": {
         "compiler_flags": attrs.list(attrs.string(), default = []),
@@ -1750,7 +1693,6 @@ attributes = {
         "platform_preload_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
         "preload_deps": attrs.set(attrs.dep(), sorted = True, default = []),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "haskell_haddock": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1761,7 +1703,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "platform": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "haskell_ide": {
         "compiler_flags": attrs.list(attrs.string(), default = []),
@@ -1777,7 +1718,6 @@ attributes = {
         "platform": attrs.option(attrs.string(), default = None),
         "platform_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "haskell_library": {
         "compiler_flags": attrs.list(attrs.string(), default = []),
@@ -1797,7 +1737,6 @@ attributes = {
         "platform_linker_flags": attrs.list(attrs.tuple(attrs.regex(), attrs.list(attrs.arg())), default = []),
         "preferred_linkage": attrs.enum(Linkage),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "haskell_prebuilt_library": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1818,7 +1757,6 @@ attributes = {
         "shared_libs": attrs.dict(key = attrs.string(), value = attrs.source(), sorted = False, default = {}),
         "static_libs": attrs.list(attrs.source(), default = []),
         "version": attrs.string(default = ""),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "http_archive": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1832,7 +1770,6 @@ attributes = {
         "strip_prefix": attrs.option(attrs.string(), default = None),
         "type": attrs.option(attrs.string(), default = None),
         "urls": attrs.list(attrs.string(validate = _uri), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "http_file": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1844,7 +1781,6 @@ attributes = {
         "sha1": attrs.option(attrs.string(), default = None),
         "sha256": attrs.string(default = ""),
         "urls": attrs.list(attrs.string(validate = _uri), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "jar_genrule": {
         "bash": attrs.option(attrs.arg(), default = None),
@@ -1861,7 +1797,6 @@ attributes = {
         "remote": attrs.option(attrs.bool(), default = None),
         "srcs": attrs.named_set(attrs.source(), sorted = False, default = []),
         "type": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "java_annotation_processor": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1873,7 +1808,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "processor_class": attrs.string(default = ""),
         "supports_abi_generation_from_source": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "java_binary": {
         "blacklist": attrs.list(attrs.regex(), default = []),
@@ -1887,7 +1821,6 @@ attributes = {
         "main_class": attrs.option(attrs.string(), default = None),
         "manifest_file": attrs.option(attrs.source(), default = None),
         "meta_inf_directory": attrs.option(attrs.source(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "java_library": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -1921,7 +1854,6 @@ attributes = {
         "source_only_abi_deps": attrs.list(attrs.dep(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
         "target": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "java_plugin": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -1933,7 +1865,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "plugin_name": attrs.string(default = ""),
         "supports_abi_generation_from_source": attrs.bool(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "java_test": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -1983,7 +1914,6 @@ attributes = {
         "use_cxx_libraries": attrs.option(attrs.bool(), default = None),
         "use_dependency_order_classpath": attrs.option(attrs.bool(), default = None),
         "vm_args": attrs.list(attrs.arg(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "java_test_runner": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -2018,7 +1948,6 @@ attributes = {
         "source_only_abi_deps": attrs.list(attrs.dep(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
         "target": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "js_bundle": {
         "android_package": attrs.option(attrs.string(), default = None),
@@ -2032,7 +1961,6 @@ attributes = {
         "fallback_transform_profile": attrs.option(attrs.string(), default = None),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "worker": attrs.dep(),
     },
     "js_bundle_genrule": {
@@ -2056,7 +1984,6 @@ attributes = {
         "rewrite_sourcemap": attrs.bool(),
         "skip_resources": attrs.bool(),
         "srcs": attrs.named_set(attrs.source(), sorted = False, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "js_library": {
         "asset_extensions": attrs.option(attrs.set(attrs.string(), sorted = False), default = None),
@@ -2070,7 +1997,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "srcs": attrs.list(attrs.one_of(attrs.source(), attrs.tuple(attrs.source(), attrs.string())), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "worker": attrs.dep(),
     },
     "keystore": {
@@ -2081,7 +2007,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "properties": attrs.source(),
         "store": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "kotlin_library": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -2120,7 +2045,6 @@ attributes = {
         "srcs": attrs.list(attrs.source(), default = []),
         "target": attrs.option(attrs.string(), default = None),
         "use_jvm_abi_gen": attrs.option(attrs.bool(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "kotlin_test": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -2173,7 +2097,6 @@ attributes = {
         "use_dependency_order_classpath": attrs.option(attrs.bool(), default = None),
         "use_jvm_abi_gen": attrs.option(attrs.bool(), default = None),
         "vm_args": attrs.list(attrs.arg(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "legacy_toolchain": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2181,7 +2104,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "toolchain_name": attrs.string(default = ""),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "lua_binary": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2195,7 +2117,6 @@ attributes = {
         "platform": attrs.option(attrs.string(), default = None),
         "platform_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
         "python_platform": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "lua_library": {
         "base_module": attrs.option(attrs.string(), default = None),
@@ -2206,7 +2127,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "platform_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
         "srcs": attrs.named_set(attrs.source(), sorted = True, default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "ndk_library": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2217,7 +2137,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "ndk_toolchain": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2229,7 +2148,6 @@ attributes = {
         "objdump": attrs.source(),
         "shared_runtime_path": attrs.option(attrs.source(), default = None),
         "strip_apk_libs_flags": attrs.option(attrs.list(attrs.arg()), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "ocaml_binary": {
         "bytecode_only": attrs.option(attrs.bool(), default = None),
@@ -2247,7 +2165,6 @@ attributes = {
         "platform_linker_flags": attrs.list(attrs.tuple(attrs.regex(), attrs.list(attrs.string())), default = []),
         "srcs": attrs.option(attrs.named_set(attrs.source(), sorted = False), default = None),
         "warnings_flags": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "ocaml_library": {
         "bytecode_only": attrs.bool(),
@@ -2264,12 +2181,10 @@ attributes = {
         "platform_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
         "srcs": attrs.option(attrs.named_set(attrs.source(), sorted = False), default = None),
         "warnings_flags": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "platform": {
         "constraint_values": attrs.list(attrs.configuration_label(), default = []),
         "deps": attrs.list(attrs.configuration_label(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_apple_framework": {
         "code_sign_on_copy": attrs.option(attrs.bool(), default = None),
@@ -2285,7 +2200,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "preferred_linkage": attrs.enum(Linkage),
         "supported_platforms_regex": attrs.option(attrs.regex(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_cxx_library": {
         "can_be_asset": attrs.bool(),
@@ -2340,7 +2254,6 @@ attributes = {
         "versioned_soname": attrs.option(attrs.versioned(attrs.string()), default = None),
         "versioned_static_lib": attrs.option(attrs.versioned(attrs.source()), default = None),
         "versioned_static_pic_lib": attrs.option(attrs.versioned(attrs.source()), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_cxx_library_group": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2362,7 +2275,6 @@ attributes = {
         "static_pic_libs": attrs.list(attrs.source(), default = []),
         "static_pic_link": attrs.list(attrs.string(), default = []),
         "supported_platforms_regex": attrs.option(attrs.regex(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_dotnet_library": {
         "assembly": attrs.source(),
@@ -2370,7 +2282,6 @@ attributes = {
         "default_host_platform": attrs.option(attrs.configuration_label(), default = None),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_go_library": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2381,7 +2292,6 @@ attributes = {
         "library": attrs.source(),
         "licenses": attrs.list(attrs.source(), default = []),
         "package_name": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_jar": {
         "binary_jar": attrs.source(),
@@ -2396,7 +2306,6 @@ attributes = {
         "never_mark_as_unused_dependency": attrs.bool(),
         "required_for_source_only_abi": attrs.bool(),
         "source_jar": attrs.option(attrs.source(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_native_library": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2407,7 +2316,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "native_libs": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_ocaml_library": {
         "bytecode_c_libs": attrs.list(attrs.string(), default = []),
@@ -2425,7 +2333,6 @@ attributes = {
         "native_c_libs": attrs.list(attrs.string(), default = []),
         "native_lib": attrs.string(default = ""),
         "platform_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_python_library": {
         "binary_src": attrs.source(),
@@ -2437,7 +2344,6 @@ attributes = {
         "ignore_compile_errors": attrs.bool(),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "prebuilt_rust_library": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2450,7 +2356,6 @@ attributes = {
         "platform_deps": attrs.list(attrs.tuple(attrs.regex(), attrs.set(attrs.dep(), sorted = True)), default = []),
         "proc_macro": attrs.bool(),
         "rlib": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "python_binary": {
         "base_module": attrs.option(attrs.string(), default = None),
@@ -2477,7 +2382,6 @@ attributes = {
         "prefer_stripped_native_objects": attrs.bool(),
         "preload_deps": attrs.list(attrs.dep(), default = []),
         "version_universe": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "zip_safe": attrs.option(attrs.bool(), default = None),
     },
     "python_library": {
@@ -2500,7 +2404,6 @@ attributes = {
         "version_universe": attrs.option(attrs.string(), default = None),
         "versioned_resources": attrs.option(attrs.versioned(attrs.named_set(attrs.source(), sorted = True)), default = None),
         "versioned_srcs": attrs.option(attrs.versioned(attrs.named_set(attrs.source(), sorted = True)), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "zip_safe": attrs.option(attrs.bool(), default = None),
     },
     "python_test": {
@@ -2540,7 +2443,6 @@ attributes = {
         "version_universe": attrs.option(attrs.string(), default = None),
         "versioned_resources": attrs.option(attrs.versioned(attrs.named_set(attrs.source(), sorted = True)), default = None),
         "versioned_srcs": attrs.option(attrs.versioned(attrs.named_set(attrs.source(), sorted = True)), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "zip_safe": attrs.option(attrs.bool(), default = None),
     },
     "python_test_runner": {
@@ -2550,7 +2452,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "main_module": attrs.string(default = ""),
         "src": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "remote_file": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2562,7 +2463,6 @@ attributes = {
         "sha256": attrs.option(attrs.string(), default = None),
         "type": attrs.option(attrs.enum(RemoteFileType), default = None),
         "url": attrs.string(validate = _uri),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "robolectric_test": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -2623,7 +2523,6 @@ attributes = {
         "use_dependency_order_classpath": attrs.option(attrs.bool(), default = None),
         "use_jvm_abi_gen": attrs.option(attrs.bool(), default = None),
         "vm_args": attrs.list(attrs.arg(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "rust_binary": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2652,7 +2551,6 @@ attributes = {
         "rustdoc_flags": attrs.list(attrs.arg(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
         "version_universe": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "rust_library": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2678,7 +2576,6 @@ attributes = {
         "rustdoc_flags": attrs.list(attrs.arg(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
         "version_universe": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "rust_test": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2707,7 +2604,6 @@ attributes = {
         "rustdoc_flags": attrs.list(attrs.arg(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
         "version_universe": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "scala_library": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -2741,7 +2637,6 @@ attributes = {
         "source_only_abi_deps": attrs.list(attrs.dep(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
         "target": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "scala_test": {
         "abi_generation_mode": attrs.option(attrs.enum(AbiGenerationMode), default = None),
@@ -2789,7 +2684,6 @@ attributes = {
         "use_cxx_libraries": attrs.option(attrs.bool(), default = None),
         "use_dependency_order_classpath": attrs.option(attrs.bool(), default = None),
         "vm_args": attrs.list(attrs.arg(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "scene_kit_assets": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2797,7 +2691,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "path": attrs.source(),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "sh_binary": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2807,7 +2700,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "main": attrs.source(),
         "resources": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "sh_test": {
         "args": attrs.list(attrs.arg(), default = []),
@@ -2826,7 +2718,6 @@ attributes = {
         "test": attrs.option(attrs.source(), default = None),
         "test_rule_timeout_ms": attrs.option(attrs.int(), default = None),
         "type": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "supermodule_target_graph": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2837,7 +2728,6 @@ attributes = {
         "licenses": attrs.list(attrs.source(), default = []),
         "on_duplicate_entry": attrs.enum(OnDuplicateEntry, default = "overwrite"),
         "out": attrs.string(default = ""),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "swift_library": {
         "bridging_header": attrs.option(attrs.source(), default = None),
@@ -2860,7 +2750,6 @@ attributes = {
         "target_sdk_version": attrs.option(attrs.string(), default = None),
         "uses_explicit_modules": attrs.bool(),
         "version": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "swift_toolchain": {
         "can_toolchain_emit_obj_c_header_textually": attrs.bool(),
@@ -2882,14 +2771,12 @@ attributes = {
         "swift_stdlib_tool_flags": attrs.list(attrs.arg(), default = []),
         "swiftc": attrs.source(),
         "swiftc_flags": attrs.list(attrs.arg(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "test_suite": {
         "contacts": attrs.list(attrs.string(), default = []),
         "default_host_platform": attrs.option(attrs.configuration_label(), default = None),
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "versioned_alias": {
         "contacts": attrs.list(attrs.string(), default = []),
@@ -2897,7 +2784,6 @@ attributes = {
         "labels": attrs.list(attrs.string(), default = []),
         "licenses": attrs.list(attrs.source(), default = []),
         "versions": attrs.dict(key = attrs.string(), value = attrs.dep(), sorted = False, default = {}),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "worker_tool": {
         "args": attrs.one_of(attrs.arg(), attrs.list(attrs.arg())),
@@ -2910,7 +2796,6 @@ attributes = {
         "max_workers": attrs.option(attrs.int(), default = None),
         "max_workers_per_thread_percent": attrs.option(attrs.int(), default = None),
         "persistent": attrs.option(attrs.bool(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "xcode_postbuild_script": {
         "cmd": attrs.string(default = ""),
@@ -2923,7 +2808,6 @@ attributes = {
         "output_file_lists": attrs.list(attrs.string(), default = []),
         "outputs": attrs.list(attrs.string(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "xcode_prebuild_script": {
         "cmd": attrs.string(default = ""),
@@ -2936,7 +2820,6 @@ attributes = {
         "output_file_lists": attrs.list(attrs.string(), default = []),
         "outputs": attrs.list(attrs.string(), default = []),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
     },
     "xcode_workspace_config": {
         "action_config_names": attrs.dict(key = attrs.enum(SchemeActionType), value = attrs.string(), sorted = False, default = {}),
@@ -2957,7 +2840,6 @@ attributes = {
         "src_target": attrs.option(attrs.dep(), default = None),
         "was_created_for_app_extension": attrs.option(attrs.bool(), default = None),
         "watch_interface": attrs.option(attrs.enum(WatchInterface), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "workspace_name": attrs.option(attrs.string(), default = None),
     },
     "zip_file": {
@@ -2969,7 +2851,6 @@ attributes = {
         "on_duplicate_entry": attrs.enum(OnDuplicateEntry, default = "overwrite"),
         "out": attrs.string(default = ""),
         "srcs": attrs.list(attrs.source(), default = []),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "zip_srcs": attrs.list(attrs.source(), default = []),
     },
 }
@@ -487,7 +487,6 @@ inlined_extra_attributes = {
         "platform_linker_flags": attrs.list(attrs.tuple(attrs.regex(), attrs.list(attrs.string())), default = []),
         "srcs": attrs.option(attrs.named_set(attrs.source(), sorted = False), default = None),
         "warnings_flags": attrs.option(attrs.string(), default = None),
-        "within_view": attrs.option(attrs.list(attrs.string())),
         "_cxx_toolchain": _cxx_toolchain(),
         "_ocaml_toolchain": _ocaml_toolchain(),
     },
@@ -16,7 +16,6 @@
  */
 
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
@@ -35,6 +34,13 @@ use serde_json::Map;
 use serde_json::Value;
 use starlark::codemap::FileSpan;
 use starlark::codemap::FileSpanRef;
+use starlark::collections::SmallMap;
+use starlark::debug::inspect_children;
+use starlark::debug::resolve_path;
+use starlark::debug::BreakpointCondition;
+use starlark::debug::PathSegment;
+use starlark::debug::StepKind;
+use starlark::debug::Stepping;
 use starlark::environment::Module;
 use starlark::eval::Evaluator;
 use starlark::syntax::AstModule;
@@ -52,9 +58,14 @@ struct Backend {
 
     // These breakpoints must all match statements as per before_stmt.
     // Those values for which we abort the execution.
-    breakpoints: Arc<Mutex<HashMap<String, HashSet<FileSpan>>>>,
+    breakpoints: Arc<Mutex<HashMap<String, HashMap<FileSpan, BreakpointCondition>>>>,
     // Set while we are doing evaluate calls (>= 1 means disable)
     disable_breakpoints: Arc<AtomicUsize>,
+    // The step in progress, if any, cleared whenever we pause.
+    stepping: Arc<Mutex<Option<Stepping>>>,
+    // What each variables_reference handed out while paused refers to,
+    // where reference `i` is stored at index `i - 1`. Cleared when we resume.
+    references: Arc<Mutex<Vec<(VariableScope, Vec<PathSegment>)>>>,
 
     sender: Sender<Box<dyn Fn(FileSpanRef, &mut Evaluator) -> Next + Send>>,
     receiver: Arc<Mutex<Receiver<Box<dyn Fn(FileSpanRef, &mut Evaluator) -> Next + Send>>>>,
@@ -65,6 +76,35 @@ enum Next {
     RemainPaused,
 }
 
+#[derive(Debug, Clone, Copy)]
+enum VariableScope {
+    Locals,
+    Module,
+}
+
+impl VariableScope {
+    fn variables<'v>(
+        self,
+        eval: &Evaluator<'v, '_>,
+    ) -> SmallMap<String, starlark::values::Value<'v>> {
+        match self {
+            VariableScope::Locals => eval.local_variables(),
+            VariableScope::Module => eval.top_level_variables(),
+        }
+    }
+}
+
+/// Allocate a `variables_reference` for `scope` and `path`, starting from 1 since 0 means none.
+fn add_reference(
+    references: &Mutex<Vec<(VariableScope, Vec<PathSegment>)>>,
+    scope: VariableScope,
+    path: Vec<PathSegment>,
+) -> i64 {
+    let mut references = references.lock().unwrap();
+    references.push((scope, path));
+    references.len() as i64
+}
+
 impl Backend {
     fn inject<T: 'static + Send>(
         &self,
@@ -82,9 +122,19 @@ impl Backend {
     }
 
     fn inject_continue(&self) {
+        self.references.lock().unwrap().clear();
         self.inject(Box::new(|_, _| (Next::Continue, ())))
     }
 
+    fn inject_step(&self, kind: StepKind) {
+        self.references.lock().unwrap().clear();
+        let stepping = self.stepping.dupe();
+        self.inject(Box::new(move |_, eval| {
+            *stepping.lock().unwrap() = Some(Stepping::new(kind, eval));
+            (Next::Continue, ())
+        }))
+    }
+
     fn with_ctx<T: 'static + Send>(
         &self,
         f: Box<dyn Fn(FileSpanRef, &mut Evaluator) -> T + Send>,
@@ -100,6 +150,7 @@ impl Backend {
         let path = PathBuf::from(path);
         let breakpoints = self.breakpoints.dupe();
         let disable_breakpoints = self.disable_breakpoints.dupe();
+        let stepping = self.stepping.dupe();
         let receiver = self.receiver.dupe();
 
         let go = move || -> anyhow::Result<String> {
@@ -109,20 +160,45 @@ impl Backend {
             let globals = globals();
             let mut eval = Evaluator::new(&module);
             let fun = |span_loc: FileSpanRef, eval: &mut Evaluator| {
-                let stop = if disable_breakpoints.load(Ordering::SeqCst) > 0 {
-                    false
+                if disable_breakpoints.load(Ordering::SeqCst) > 0 {
+                    return;
+                }
+                let stepped = stepping
+                    .lock()
+                    .unwrap()
+                    .map_or(false, |stepping| stepping.should_pause(eval));
+                let stop = if stepped {
+                    Some(("step", None))
                 } else {
-                    let breaks = breakpoints.lock().unwrap();
-                    breaks
-                        .get(span_loc.filename())
-                        .map(|set| set.contains(&span_loc.to_file_span()))
-                        .unwrap_or_default()
+                    let mut breaks = breakpoints.lock().unwrap();
+                    match breaks
+                        .get_mut(span_loc.filename())
+                        .and_then(|conds| conds.get_mut(&span_loc.to_file_span()))
+                    {
+                        None => None,
+                        Some(cond) => {
+                            // Conditions are evaluated in the program, which must not hit breakpoints
+                            disable_breakpoints.fetch_add(1, Ordering::SeqCst);
+                            let res = cond.should_pause(eval);
+                            disable_breakpoints.fetch_sub(1, Ordering::SeqCst);
+                            match res {
+                                Ok(false) => None,
+                                Ok(true) => Some(("breakpoint", None)),
+                                // Pause on broken conditions so the user finds out about them
+                                Err(e) => Some((
+                                    "breakpoint",
+                                    Some(format!("Error evaluating condition: {:#}", e)),
+                                )),
+                            }
+                        }
+                    }
                 };
-                if stop {
+                if let Some((reason, description)) = stop {
+                    *stepping.lock().unwrap() = None;
                     client.event_stopped(StoppedEventBody {
-                        reason: "breakpoint".to_owned(),
+                        reason: reason.to_owned(),
                         thread_id: Some(0),
-                        description: Some("Hello".to_owned()),
+                        description,
                         all_threads_stopped: Some(true),
                         preserve_focus_hint: None,
                         text: None,
@@ -185,8 +261,10 @@ impl DebugServer for Backend {
     fn initialize(&self, _: InitializeRequestArguments) -> anyhow::Result<Option<Capabilities>> {
         self.client.event_initialized(None);
         Ok(Some(Capabilities {
+            supports_conditional_breakpoints: Some(true),
             supports_configuration_done_request: Some(true),
             supports_evaluate_for_hovers: Some(true),
+            supports_hit_conditional_breakpoints: Some(true),
             supports_set_variable: Some(true),
             supports_step_in_targets_request: Some(true),
             ..Capabilities::default()
@@ -219,14 +297,26 @@ impl DebugServer for Backend {
                         .iter()
                         .map(|span| (span.resolve_span().begin_line, span.dupe()))
                         .collect();
-                    let list = breakpoints.map(|x| poss.get(&(x.line as usize - 1)));
-                    self.breakpoints
-                        .lock()
-                        .unwrap()
-                        .insert(source, list.iter().filter_map(|x| x.duped()).collect());
-                    Ok(SetBreakpointsResponseBody {
-                        breakpoints: list.map(|x| breakpoint(x.is_some())),
-                    })
+                    let mut conds = HashMap::new();
+                    let mut res = Vec::with_capacity(breakpoints.len());
+                    for x in breakpoints {
+                        let span = poss.get(&(x.line as usize - 1));
+                        let cond =
+                            BreakpointCondition::new(x.condition, x.hit_condition.as_deref());
+                        match (span, cond) {
+                            (Some(span), Ok(cond)) => {
+                                conds.insert(span.dupe(), cond);
+                                res.push(breakpoint(true));
+                            }
+                            (_, Err(e)) => res.push(Breakpoint {
+                                message: Some(format!("{:#}", e)),
+                                ..breakpoint(false)
+                            }),
+                            (None, _) => res.push(breakpoint(false)),
+                        }
+                    }
+                    self.breakpoints.lock().unwrap().insert(source, conds);
+                    Ok(SetBreakpointsResponseBody { breakpoints: res })
                 }
             }
         }
@@ -314,40 +404,79 @@ impl DebugServer for Backend {
     }
 
     fn scopes(&self, _: ScopesArguments) -> anyhow::Result<ScopesResponseBody> {
-        self.with_ctx(Box::new(|_, eval| {
-            let vars = eval.local_variables();
+        let references = self.references.dupe();
+        self.with_ctx(Box::new(move |_, eval| {
+            let scope = |name: &str, scope: VariableScope| Scope {
+                name: name.to_owned(),
+                named_variables: Some(scope.variables(eval).len() as i64),
+                variables_reference: add_reference(&references, scope, Vec::new()),
+                expensive: false,
+                column: None,
+                end_column: None,
+                end_line: None,
+                indexed_variables: None,
+                line: None,
+                source: None,
+            };
             Ok(ScopesResponseBody {
-                scopes: vec![Scope {
-                    name: "Locals".to_owned(),
-                    named_variables: Some(vars.len() as i64),
-                    variables_reference: 2000,
-                    expensive: false,
-                    column: None,
-                    end_column: None,
-                    end_line: None,
-                    indexed_variables: None,
-                    line: None,
-                    source: None,
-                }],
+                scopes: vec![
+                    scope("Locals", VariableScope::Locals),
+                    scope("Module", VariableScope::Module),
+                ],
             })
         }))
     }
 
-    fn variables(&self, _: VariablesArguments) -> anyhow::Result<VariablesResponseBody> {
-        self.with_ctx(Box::new(|_, eval| {
-            let vars = eval.local_variables();
+    fn variables(&self, x: VariablesArguments) -> anyhow::Result<VariablesResponseBody> {
+        let references = self.references.dupe();
+        self.with_ctx(Box::new(move |_, eval| {
+            let (scope, path) = references
+                .lock()
+                .unwrap()
+                .get((x.variables_reference as usize).wrapping_sub(1))
+                .cloned()
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Unknown variables reference {}", x.variables_reference)
+                })?;
+            let heap = eval.heap();
+            let children: Vec<_> = match path.split_first() {
+                None => scope
+                    .variables(eval)
+                    .into_iter()
+                    .map(|(name, value)| (PathSegment::Attr(name.clone()), name, value))
+                    .collect(),
+                Some((PathSegment::Attr(name), rest)) => {
+                    let root =
+                        scope.variables(eval).get(name).copied().ok_or_else(|| {
+                            anyhow::anyhow!("Variable `{}` no longer exists", name)
+                        })?;
+                    inspect_children(resolve_path(root, rest, heap)?, heap)
+                }
+                Some((segment, _)) => {
+                    return Err(anyhow::anyhow!("Invalid variable path at `{}`", segment));
+                }
+            };
             Ok(VariablesResponseBody {
-                variables: vars
+                variables: children
                     .into_iter()
-                    .map(|(name, value)| Variable {
-                        name,
-                        value: value.to_string(),
-                        type_: Some(value.get_type().to_owned()),
-                        evaluate_name: None,
-                        indexed_variables: None,
-                        named_variables: None,
-                        presentation_hint: None,
-                        variables_reference: 0,
+                    .map(|(segment, name, value)| {
+                        let expandable = !inspect_children(value, heap).is_empty();
+                        Variable {
+                            name,
+                            value: value.to_string(),
+                            type_: Some(value.get_type().to_owned()),
+                            evaluate_name: None,
+                            indexed_variables: None,
+                            named_variables: None,
+                            presentation_hint: None,
+                            variables_reference: if expandable {
+                                let mut path = path.clone();
+                                path.push(segment);
+                                add_reference(&references, scope, path)
+                            } else {
+                                0
+                            },
+                        }
                     })
                     .collect(),
             })
@@ -359,6 +488,21 @@ impl DebugServer for Backend {
         Ok(ContinueResponseBody::default())
     }
 
+    fn next(&self, _: NextArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Over);
+        Ok(())
+    }
+
+    fn step_in(&self, _: StepInArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Into);
+        Ok(())
+    }
+
+    fn step_out(&self, _: StepOutArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Out);
+        Ok(())
+    }
+
     fn evaluate(&self, x: EvaluateArguments) -> anyhow::Result<EvaluateResponseBody> {
         let disable_breakpoints = self.disable_breakpoints.dupe();
         self.with_ctx(Box::new(move |_, eval| {
@@ -389,6 +533,8 @@ pub(crate) fn server() {
         client,
         breakpoints: Default::default(),
         disable_breakpoints: Default::default(),
+        stepping: Default::default(),
+        references: Default::default(),
         file: Default::default(),
         sender,
         receiver: Arc::new(Mutex::new(receiver)),
@@ -66,6 +66,8 @@ pub(crate) struct Context {
     pub(crate) module: Option<Module>,
     pub(crate) builtin_docs: HashMap<LspUrl, String>,
     pub(crate) builtin_symbols: HashMap<String, LspUrl>,
+    /// The documentation of the globals and prelude, used to type check calls to them.
+    pub(crate) builtin_types: HashMap<String, Option<DocItem>>,
 }
 
 /// The outcome of evaluating (checking, parsing or running) given starlark code.
@@ -106,6 +108,13 @@ impl Context {
             env.freeze()
         })?;
 
+        let mut builtin_types = globals.member_documentation();
+        for p in &prelude {
+            for name in p.names() {
+                builtin_types.insert(name.as_str().to_owned(), None);
+            }
+        }
+
         let module = if module {
             Some(Self::new_module(&prelude))
         } else {
@@ -130,6 +139,7 @@ impl Context {
             module,
             builtin_docs,
             builtin_symbols,
+            builtin_types,
         })
     }
 
@@ -257,7 +267,11 @@ impl Context {
             Some(globals.as_slice())
         };
 
-        module.lint(globals).into_iter().map(EvalMessage::from)
+        module
+            .lint(globals)
+            .into_iter()
+            .chain(module.typecheck(&self.builtin_types))
+            .map(EvalMessage::from)
     }
 }
 
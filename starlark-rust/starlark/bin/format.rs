@@ -0,0 +1,44 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs;
+use std::path::Path;
+
+use starlark::syntax::AstModule;
+use starlark::syntax::FormatOptions;
+
+use crate::eval::dialect;
+
+/// Format a file in place, or with `check`, only report whether it is formatted.
+///
+/// Returns `true` if the file was already formatted.
+pub(crate) fn format_file(
+    file: &Path,
+    options: &FormatOptions,
+    check: bool,
+) -> anyhow::Result<bool> {
+    let source = fs::read_to_string(file)?;
+    let module = AstModule::parse(&file.to_string_lossy(), source.clone(), &dialect())?;
+    let formatted = module.format(options);
+    if formatted == source {
+        return Ok(true);
+    }
+    if !check {
+        fs::write(file, formatted)?;
+    }
+    Ok(false)
+}
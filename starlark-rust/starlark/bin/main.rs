@@ -37,6 +37,7 @@ use starlark::errors::EvalMessage;
 use starlark::errors::EvalSeverity;
 use starlark::lsp;
 use starlark::read_line::ReadLine;
+use starlark::syntax::FormatOptions;
 use walkdir::WalkDir;
 
 use crate::eval::ContextMode;
@@ -44,6 +45,7 @@ use crate::types::LintMessage;
 
 mod dap;
 mod eval;
+mod format;
 mod types;
 
 #[derive(Debug, Parser)]
@@ -55,6 +57,7 @@ struct Args {
         conflicts_with_all = &[
             "dap",
             "check",
+            "format",
             "json",
             "evaluate",
             "files",
@@ -69,6 +72,7 @@ struct Args {
         conflicts_with_all = &[
             "lsp",
             "check",
+            "format",
             "json",
             "extension",
             "prelude",
@@ -85,6 +89,21 @@ struct Args {
     )]
     check: bool,
 
+    #[arg(
+        long = "format",
+        help = "Format files in place, or with --check, only report unformatted files.",
+        conflicts_with_all = &["lsp", "dap", "json", "evaluate"],
+        requires = "files",
+    )]
+    format: bool,
+
+    #[arg(
+        long = "opinionated",
+        help = "When formatting, also sort load symbols and target attributes.",
+        requires = "format"
+    )]
+    opinionated: bool,
+
     #[arg(
         long = "json",
         help = "Show output as JSON lines.",
@@ -206,6 +225,29 @@ fn interactive(ctx: &Context) -> anyhow::Result<()> {
     }
 }
 
+fn format(ext: &str, files: Vec<PathBuf>, check: bool, opinionated: bool) -> anyhow::Result<()> {
+    let options = if opinionated {
+        FormatOptions::opinionated()
+    } else {
+        FormatOptions::default()
+    };
+    let mut unformatted = 0;
+    for file in expand_dirs(ext, files) {
+        if !format::format_file(&file, &options, check)? {
+            unformatted += 1;
+            if check {
+                println!("{}: not formatted", file.display());
+            } else {
+                println!("{}: formatted", file.display());
+            }
+        }
+    }
+    if check && unformatted > 0 {
+        return Err(anyhow::anyhow!("{} files are not formatted", unformatted));
+    }
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
     gazebo::terminate_on_panic();
 
@@ -213,6 +255,12 @@ fn main() -> anyhow::Result<()> {
     let args: Args = Args::parse_from(args);
     if args.dap {
         dap::server();
+    } else if args.format {
+        let ext = args
+            .extension
+            .as_ref()
+            .map_or("bzl", |x| x.strip_prefix('.').unwrap_or(x.as_str()));
+        format(ext, args.files, args.check, args.opinionated)?;
     } else {
         let is_interactive = args.evaluate.is_empty() && args.files.is_empty();
 
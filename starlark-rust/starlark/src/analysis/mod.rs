@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+
 #[cfg(all(test, not(windows)))]
 pub(crate) use definition::helpers::FixtureWithRanges;
 pub(crate) use definition::Definition;
@@ -27,6 +29,7 @@ pub use types::Lint;
 
 use crate::analysis::types::LintT;
 use crate::syntax::AstModule;
+use crate::values::docs::DocItem;
 
 mod bind;
 mod definition;
@@ -36,6 +39,7 @@ mod flow;
 mod incompatible;
 mod names;
 mod performance;
+mod typecheck;
 mod types;
 
 impl AstModule {
@@ -59,4 +63,17 @@ impl AstModule {
         res.extend(performance::performance(self).into_iter().map(LintT::erase));
         res
     }
+
+    /// Run a static type checker over the module, reporting calls, attributes and return values
+    /// which can't match their type annotations. Calls to globals are checked against their
+    /// documented signatures, as returned by
+    /// [`member_documentation`](crate::environment::Globals::member_documentation), where a
+    /// global without documentation accepts anything. Like [`lint`](AstModule::lint), the precise
+    /// checks are not considered stable between versions.
+    pub fn typecheck(&self, globals: &HashMap<String, Option<DocItem>>) -> Vec<Lint> {
+        typecheck::typecheck(self, globals)
+            .into_iter()
+            .map(LintT::erase)
+            .collect()
+    }
 }
@@ -0,0 +1,1092 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A static check of the type annotations in a module.
+//!
+//! Types are inferred from literals, from the annotations on parameters, assignments and return
+//! values, and from the documented signatures of the globals. Anything the checker can't follow
+//! (e.g. a variable assigned in several places) has the type `""`, which matches everything, so
+//! only calls, attributes and returns that can never succeed are reported.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use gazebo::variants::VariantName;
+use thiserror::Error;
+
+use crate::analysis::types::LintT;
+use crate::analysis::types::LintWarning;
+use crate::codemap::CodeMap;
+use crate::codemap::Span;
+use crate::environment::Methods;
+use crate::syntax::ast::Argument;
+use crate::syntax::ast::Assign;
+use crate::syntax::ast::AstArgument;
+use crate::syntax::ast::AstAssign;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstParameter;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::Clause;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::ForClause;
+use crate::syntax::ast::Parameter;
+use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::values::docs;
+use crate::values::docs::DocItem;
+use crate::values::types::dict::dict_methods;
+use crate::values::types::list::list_methods;
+use crate::values::types::string::str_methods;
+
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum TypeCheck {
+    #[error("Argument `{1}` of `{0}` expects type `{2}`, but got `{3}`")]
+    ArgumentTypeMismatch(String, String, Ty, Ty),
+    #[error("Too many positional arguments to `{0}`, expected at most {1}")]
+    TooManyPositionalArguments(String, usize),
+    #[error("Missing argument `{1}` in call to `{0}`")]
+    MissingArgument(String, String),
+    #[error("Unexpected named argument `{1}` in call to `{0}`")]
+    UnexpectedNamedArgument(String, String),
+    #[error("Value of type `{0}` has no attribute `{1}`")]
+    UnknownAttribute(Ty, String),
+    #[error("Function `{0}` is declared to return `{1}`, but returns `{2}`")]
+    ReturnTypeMismatch(String, Ty, Ty),
+}
+
+impl LintWarning for TypeCheck {
+    fn is_serious(&self) -> bool {
+        true
+    }
+}
+
+/// The static type of an expression, following the runtime meaning of type annotations
+/// (see `values/typing.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Ty {
+    /// Could be any value.
+    Any,
+    None,
+    Bool,
+    Int,
+    Float,
+    String,
+    List(Box<Ty>),
+    Dict(Box<(Ty, Ty)>),
+    /// A tuple, with the types of its elements if its length is known.
+    Tuple(Option<Vec<Ty>>),
+    /// A function, with its signature if it is known.
+    Function(Option<Rc<Signature>>),
+    /// A global object with documented members.
+    Object(Rc<HashMap<String, Ty>>),
+    /// A value whose type is matched by name, e.g. `"struct"` or a record.
+    Name(String),
+    Union(Vec<Ty>),
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Signature {
+    name: String,
+    /// The parameters, if known. Native functions which take their arguments unparsed document
+    /// no parameters, so we don't check calls to functions without parameters.
+    params: Option<Vec<Param>>,
+    ret: Ty,
+}
+
+#[derive(Debug, PartialEq)]
+enum Param {
+    Normal {
+        name: String,
+        ty: Ty,
+        required: bool,
+    },
+    NoArgs,
+    Args,
+    Kwargs(Ty),
+}
+
+impl Ty {
+    fn union(xs: Vec<Ty>) -> Ty {
+        let mut res: Vec<Ty> = Vec::new();
+        for x in xs {
+            let xs = match x {
+                Ty::Any => return Ty::Any,
+                Ty::Union(xs) => xs,
+                x => vec![x],
+            };
+            for x in xs {
+                if !res.contains(&x) {
+                    res.push(x);
+                }
+            }
+        }
+        match res.len() {
+            0 => Ty::Any,
+            1 => res.pop().unwrap(),
+            _ => Ty::Union(res),
+        }
+    }
+
+    fn list(elems: Vec<Ty>) -> Ty {
+        Ty::List(Box::new(Ty::union(elems)))
+    }
+
+    fn dict(key: Ty, value: Ty) -> Ty {
+        Ty::Dict(Box::new((key, value)))
+    }
+
+    /// The type named by a string, e.g. `"string"` or `""`.
+    fn from_name(name: &str) -> Ty {
+        match name {
+            "" => Ty::Any,
+            _ if name.starts_with('_') => Ty::Any,
+            "NoneType" => Ty::None,
+            "bool" => Ty::Bool,
+            "int" => Ty::Int,
+            "float" => Ty::Float,
+            "string" => Ty::String,
+            "list" => Ty::list(Vec::new()),
+            "dict" => Ty::dict(Ty::Any, Ty::Any),
+            "tuple" => Ty::Tuple(None),
+            "function" => Ty::Function(None),
+            _ => Ty::Name(name.to_owned()),
+        }
+    }
+
+    /// The type described by a type annotation. Annotations we don't understand, e.g. the
+    /// `.type` of a user defined record, are `Any`.
+    fn from_annotation(x: &AstExpr) -> Ty {
+        match &**x {
+            Expr::Identifier(name, _) if name.node == "None" => Ty::None,
+            Expr::Literal(AstLiteral::String(name)) => Ty::from_name(&name.node),
+            Expr::Dot(x, attr) if attr.node == "type" => match &***x {
+                Expr::Identifier(name, _) => match name.node.as_str() {
+                    "str" => Ty::String,
+                    "bool" | "int" | "float" | "list" | "dict" | "tuple" => {
+                        Ty::from_name(&name.node)
+                    }
+                    _ => Ty::Any,
+                },
+                _ => Ty::Any,
+            },
+            Expr::List(xs) => match xs.as_slice() {
+                [x] => Ty::list(vec![Ty::from_annotation(x)]),
+                xs => Ty::union(xs.iter().map(Ty::from_annotation).collect()),
+            },
+            Expr::Tuple(xs) => Ty::Tuple(Some(xs.iter().map(Ty::from_annotation).collect())),
+            Expr::Dict(xs) => match xs.as_slice() {
+                [(k, v)] => Ty::dict(Ty::from_annotation(k), Ty::from_annotation(v)),
+                _ => Ty::Any,
+            },
+            _ => Ty::Any,
+        }
+    }
+
+    /// The type described by the documentation of a native value, which is written like an
+    /// annotation, e.g. `[None, str.type]`.
+    fn from_doc(x: Option<&docs::Type>) -> Ty {
+        let x = match x {
+            Some(x) => x,
+            None => return Ty::Any,
+        };
+        match AstModule::parse("type", x.raw_type.clone(), &Dialect::Extended) {
+            Ok(module) => match &module.statement.node {
+                Stmt::Expression(x) => Ty::from_annotation(x),
+                Stmt::Statements(xs) => match xs.as_slice() {
+                    [x] => match &x.node {
+                        Stmt::Expression(x) => Ty::from_annotation(x),
+                        _ => Ty::Any,
+                    },
+                    _ => Ty::Any,
+                },
+                _ => Ty::Any,
+            },
+            Err(_) => Ty::Any,
+        }
+    }
+
+    fn from_doc_item(name: &str, x: &DocItem) -> Ty {
+        match x {
+            DocItem::Function(x) => Ty::Function(Some(Rc::new(Signature::from_doc(name, x)))),
+            DocItem::Object(x) => Ty::Object(Rc::new(
+                x.members
+                    .iter()
+                    .map(|(name, member)| (name.clone(), Ty::from_doc_member(name, member)))
+                    .collect(),
+            )),
+            DocItem::Module(_) => Ty::Any,
+        }
+    }
+
+    fn from_doc_member(name: &str, x: &docs::Member) -> Ty {
+        match x {
+            docs::Member::Property(x) => Ty::from_doc(x.typ.as_ref()),
+            docs::Member::Function(x) => Ty::Function(Some(Rc::new(Signature::from_doc(name, x)))),
+        }
+    }
+
+    /// Could a value of this type also have the other type? Ints are accepted as floats, since
+    /// many native functions unpack floats from either.
+    fn intersects(&self, other: &Ty) -> bool {
+        match (self, other) {
+            (Ty::Any, _) | (_, Ty::Any) => true,
+            (Ty::Object(_), _) | (_, Ty::Object(_)) => true,
+            (Ty::Union(xs), y) => xs.iter().any(|x| x.intersects(y)),
+            (x, Ty::Union(ys)) => ys.iter().any(|y| x.intersects(y)),
+            (Ty::None, Ty::None)
+            | (Ty::Bool, Ty::Bool)
+            | (Ty::Int, Ty::Int)
+            | (Ty::Float, Ty::Float)
+            | (Ty::Int, Ty::Float)
+            | (Ty::Float, Ty::Int)
+            | (Ty::String, Ty::String)
+            | (Ty::Function(_), Ty::Function(_)) => true,
+            (Ty::List(x), Ty::List(y)) => x.intersects(y),
+            (Ty::Dict(x), Ty::Dict(y)) => x.0.intersects(&y.0) && x.1.intersects(&y.1),
+            (Ty::Tuple(Some(xs)), Ty::Tuple(Some(ys))) => {
+                xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| x.intersects(y))
+            }
+            (Ty::Tuple(_), Ty::Tuple(_)) => true,
+            // Records and enums match both their own name and a generic one.
+            (Ty::Name(_), Ty::Name(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter<'_>, xs: &[Ty]) -> fmt::Result {
+            for (i, x) in xs.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", x)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Ty::Any | Ty::Object(_) => write!(f, "\"\""),
+            Ty::None => write!(f, "None"),
+            Ty::Bool => write!(f, "bool.type"),
+            Ty::Int => write!(f, "int.type"),
+            Ty::Float => write!(f, "float.type"),
+            Ty::String => write!(f, "str.type"),
+            Ty::List(x) if **x == Ty::Any => write!(f, "\"list\""),
+            Ty::List(x) => write!(f, "[{}]", x),
+            Ty::Dict(x) if x.0 == Ty::Any && x.1 == Ty::Any => write!(f, "\"dict\""),
+            Ty::Dict(x) => write!(f, "{{{}: {}}}", x.0, x.1),
+            Ty::Tuple(None) => write!(f, "\"tuple\""),
+            Ty::Tuple(Some(xs)) if xs.len() == 1 => write!(f, "({},)", xs[0]),
+            Ty::Tuple(Some(xs)) => {
+                write!(f, "(")?;
+                list(f, xs)?;
+                write!(f, ")")
+            }
+            Ty::Function(_) => write!(f, "\"function\""),
+            Ty::Name(name) => write!(f, "\"{}\"", name),
+            Ty::Union(xs) => {
+                write!(f, "[")?;
+                list(f, xs)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl Signature {
+    fn from_doc(name: &str, x: &docs::Function) -> Signature {
+        let params = x
+            .params
+            .iter()
+            .map(|x| match x {
+                docs::Param::Arg {
+                    name,
+                    typ,
+                    default_value,
+                    ..
+                } => Param::Normal {
+                    name: name.clone(),
+                    ty: Ty::from_doc(typ.as_ref()),
+                    required: default_value.is_none(),
+                },
+                docs::Param::NoArgs => Param::NoArgs,
+                docs::Param::Args { .. } => Param::Args,
+                docs::Param::Kwargs { typ, .. } => Param::Kwargs(Ty::from_doc(typ.as_ref())),
+            })
+            .collect::<Vec<_>>();
+        Signature {
+            name: name.to_owned(),
+            params: if params.is_empty() {
+                None
+            } else {
+                Some(params)
+            },
+            ret: Ty::from_doc(x.ret.typ.as_ref()),
+        }
+    }
+
+    fn from_ast(name: &str, params: &[AstParameter], ret: Option<&AstExpr>) -> Signature {
+        let ty = |x: &Option<Box<AstExpr>>| x.as_deref().map_or(Ty::Any, Ty::from_annotation);
+        Signature {
+            name: name.to_owned(),
+            params: Some(
+                params
+                    .iter()
+                    .map(|x| match &x.node {
+                        Parameter::Normal(name, t) => Param::Normal {
+                            name: name.0.clone(),
+                            ty: ty(t),
+                            required: true,
+                        },
+                        Parameter::WithDefaultValue(name, t, _) => Param::Normal {
+                            name: name.0.clone(),
+                            ty: ty(t),
+                            required: false,
+                        },
+                        Parameter::NoArgs => Param::NoArgs,
+                        Parameter::Args(..) => Param::Args,
+                        Parameter::KwArgs(_, t) => Param::Kwargs(ty(t)),
+                    })
+                    .collect(),
+            ),
+            ret: ret.map_or(Ty::Any, Ty::from_annotation),
+        }
+    }
+}
+
+/// The variables of a function body, a comprehension or the module.
+struct Scope {
+    types: HashMap<String, Ty>,
+    /// Variables bound more than once, whose type we don't follow.
+    rebound: HashSet<String>,
+}
+
+impl Scope {
+    fn new(counts: HashMap<String, usize>) -> Scope {
+        let rebound = counts
+            .iter()
+            .filter(|(_, n)| **n > 1)
+            .map(|(name, _)| name.clone())
+            .collect();
+        Scope {
+            types: counts.into_keys().map(|name| (name, Ty::Any)).collect(),
+            rebound,
+        }
+    }
+}
+
+/// Count how many times each variable is bound by the statements of a scope, not including
+/// nested functions.
+fn count_bindings(x: &AstStmt, res: &mut HashMap<String, usize>) {
+    let mut bind = |name: &str| *res.entry(name.to_owned()).or_default() += 1;
+    match &**x {
+        Stmt::Assign(lhs, _) | Stmt::AssignModify(lhs, _, _) | Stmt::For(lhs, _) => {
+            lhs.visit_lvalue(|x| bind(&x.0))
+        }
+        Stmt::Def(name, ..) => bind(&name.0),
+        Stmt::Load(load) => load.args.iter().for_each(|(name, _)| bind(&name.0)),
+        _ => {}
+    }
+    match &**x {
+        Stmt::Def(..) => {}
+        _ => x.visit_stmt(|x| count_bindings(x, res)),
+    }
+}
+
+struct TypeChecker<'a> {
+    codemap: &'a CodeMap,
+    globals: HashMap<String, Ty>,
+    /// The members of the builtin types which have methods.
+    string_members: HashMap<String, Ty>,
+    list_members: HashMap<String, Ty>,
+    dict_members: HashMap<String, Ty>,
+    scopes: Vec<Scope>,
+    /// The name and declared return type of the enclosing functions.
+    returns: Vec<Option<(String, Ty)>>,
+    res: Vec<LintT<TypeCheck>>,
+}
+
+fn members(methods: Option<&'static Methods>) -> HashMap<String, Ty> {
+    match methods.map(Methods::documentation) {
+        Some(DocItem::Object(x)) => x
+            .members
+            .iter()
+            .map(|(name, member)| (name.clone(), Ty::from_doc_member(name, member)))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+impl<'a> TypeChecker<'a> {
+    fn new(codemap: &'a CodeMap, globals: &HashMap<String, Option<DocItem>>) -> Self {
+        Self {
+            codemap,
+            globals: globals
+                .iter()
+                .map(|(name, doc)| {
+                    let ty = doc
+                        .as_ref()
+                        .map_or(Ty::Any, |doc| Ty::from_doc_item(name, doc));
+                    (name.clone(), ty)
+                })
+                .collect(),
+            string_members: members(str_methods()),
+            list_members: members(list_methods()),
+            dict_members: members(dict_methods()),
+            scopes: Vec::new(),
+            returns: Vec::new(),
+            res: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, span: Span, problem: TypeCheck) {
+        self.res.push(LintT::new(self.codemap, span, problem))
+    }
+
+    fn lookup(&self, name: &str) -> Ty {
+        for scope in self.scopes.iter().rev() {
+            if let Some(ty) = scope.types.get(name) {
+                return ty.clone();
+            }
+        }
+        match name {
+            "None" => Ty::None,
+            "True" | "False" => Ty::Bool,
+            _ => self.globals.get(name).cloned().unwrap_or(Ty::Any),
+        }
+    }
+
+    /// Record the type of a variable on its assignment, unless it is bound more than once.
+    fn assign(&mut self, name: &str, ty: Ty) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.rebound.contains(name) {
+                if let Some(x) = scope.types.get_mut(name) {
+                    *x = ty;
+                }
+            }
+        }
+    }
+
+    /// Enter the scope of a function or of the module. Functions defined directly in it get
+    /// their signatures up front, so they can be called before their definition.
+    fn enter_scope(&mut self, params: &[AstParameter], body: &AstStmt) {
+        let mut counts = HashMap::new();
+        for p in params {
+            if let Some(name) = p.split().0 {
+                *counts.entry(name.0.clone()).or_default() += 1;
+            }
+        }
+        count_bindings(body, &mut counts);
+        self.scopes.push(Scope::new(counts));
+
+        for p in params {
+            if let (Some(name), ty, _) = p.split() {
+                let ty = match &p.node {
+                    Parameter::Args(..) => Ty::Tuple(None),
+                    Parameter::KwArgs(..) => Ty::dict(Ty::String, Ty::Any),
+                    _ => ty.map_or(Ty::Any, Ty::from_annotation),
+                };
+                self.assign(&name.0, ty);
+            }
+        }
+        fn defs<'b>(x: &'b AstStmt, res: &mut Vec<&'b AstStmt>) {
+            match &**x {
+                Stmt::Def(..) => res.push(x),
+                _ => x.visit_stmt(|x| defs(x, res)),
+            }
+        }
+        let mut res = Vec::new();
+        defs(body, &mut res);
+        for x in res {
+            if let Stmt::Def(name, params, ret, ..) = &**x {
+                let sig = Signature::from_ast(&name.0, params, ret.as_deref());
+                self.assign(&name.0, Ty::Function(Some(Rc::new(sig))));
+            }
+        }
+    }
+
+    fn module(&mut self, module: &AstModule) {
+        self.enter_scope(&[], &module.statement);
+        self.returns.push(None);
+        self.stmt(&module.statement);
+    }
+
+    fn stmt(&mut self, x: &AstStmt) {
+        match &**x {
+            Stmt::Break | Stmt::Continue | Stmt::Pass | Stmt::Load(_) => {}
+            Stmt::Return(ret) => {
+                let ty = match ret {
+                    Some(ret) => self.expr(ret),
+                    None => Ty::None,
+                };
+                if let Some(Some((name, expected))) = self.returns.last() {
+                    if !expected.intersects(&ty) {
+                        let problem =
+                            TypeCheck::ReturnTypeMismatch(name.clone(), expected.clone(), ty);
+                        self.report(x.span, problem);
+                    }
+                }
+            }
+            Stmt::Expression(x) => {
+                self.expr(x);
+            }
+            Stmt::Assign(lhs, rhs) => {
+                let (annotation, rhs) = &**rhs;
+                let ty = self.expr(rhs);
+                let ty = annotation.as_ref().map_or(ty, Ty::from_annotation);
+                self.assign_target(lhs, ty);
+            }
+            Stmt::AssignModify(lhs, _, rhs) => {
+                self.expr(rhs);
+                self.assign_target(lhs, Ty::Any);
+            }
+            Stmt::Statements(xs) => xs.iter().for_each(|x| self.stmt(x)),
+            Stmt::If(cond, then) => {
+                self.expr(cond);
+                self.stmt(then);
+            }
+            Stmt::IfElse(cond, branches) => {
+                self.expr(cond);
+                self.stmt(&branches.0);
+                self.stmt(&branches.1);
+            }
+            Stmt::For(var, over_body) => {
+                let (over, body) = &**over_body;
+                let ty = self.expr(over);
+                self.assign_target(var, element(&ty));
+                self.stmt(body);
+            }
+            Stmt::Def(name, params, ret, body, _) => {
+                self.defaults(params);
+                let ret = ret
+                    .as_ref()
+                    .map(|x| (name.0.clone(), Ty::from_annotation(x)));
+                self.enter_scope(params, body);
+                self.returns.push(ret);
+                self.stmt(body);
+                self.returns.pop();
+                self.scopes.pop();
+            }
+        }
+    }
+
+    fn assign_target(&mut self, x: &AstAssign, ty: Ty) {
+        let mut exprs = Vec::new();
+        x.visit_expr(|x| exprs.push(x));
+        for x in exprs {
+            self.expr(x);
+        }
+        if let Assign::Identifier(name) = &**x {
+            self.assign(&name.0, ty);
+        }
+    }
+
+    fn defaults(&mut self, params: &[AstParameter]) {
+        for p in params {
+            if let (_, _, Some(default)) = p.split() {
+                self.expr(default);
+            }
+        }
+    }
+
+    fn expr(&mut self, x: &AstExpr) -> Ty {
+        match &**x {
+            Expr::Tuple(xs) => Ty::Tuple(Some(xs.iter().map(|x| self.expr(x)).collect())),
+            Expr::Dot(obj, attr) => {
+                let ty = self.expr(obj);
+                match self.attribute(&ty, &attr.node) {
+                    Some(res) => res,
+                    None => {
+                        self.report(x.span, TypeCheck::UnknownAttribute(ty, attr.node.clone()));
+                        Ty::Any
+                    }
+                }
+            }
+            Expr::Call(fun, args) => {
+                let ty = self.expr(fun);
+                let arg_tys = args.iter().map(|x| self.expr(x.expr())).collect::<Vec<_>>();
+                match ty {
+                    Ty::Function(Some(sig)) => {
+                        self.call(x.span, &sig, args, &arg_tys);
+                        sig.ret.clone()
+                    }
+                    _ => Ty::Any,
+                }
+            }
+            Expr::ArrayIndirection(array_index) => {
+                let (array, index) = &**array_index;
+                let ty = self.expr(array);
+                self.expr(index);
+                match ty {
+                    Ty::List(x) => *x,
+                    Ty::Dict(x) => x.1,
+                    Ty::String => Ty::String,
+                    Ty::Tuple(Some(xs)) => Ty::union(xs),
+                    _ => Ty::Any,
+                }
+            }
+            Expr::Slice(x, a, b, c) => {
+                let ty = self.expr(x);
+                for x in [a, b, c].into_iter().flatten() {
+                    self.expr(x);
+                }
+                match ty {
+                    Ty::List(_) | Ty::String => ty,
+                    Ty::Tuple(_) => Ty::Tuple(None),
+                    _ => Ty::Any,
+                }
+            }
+            Expr::Identifier(name, _) => self.lookup(&name.node),
+            Expr::Lambda(params, body, _) => {
+                self.defaults(params);
+                let sig = Signature::from_ast("lambda", params, None);
+                // The body of a lambda is an expression, so has no bindings of its own.
+                let mut counts = HashMap::new();
+                for p in params {
+                    if let Some(name) = p.split().0 {
+                        *counts.entry(name.0.clone()).or_default() += 1;
+                    }
+                }
+                self.scopes.push(Scope::new(counts));
+                for p in params {
+                    if let (Some(name), Some(ty), _) = p.split() {
+                        if let Parameter::Normal(..) | Parameter::WithDefaultValue(..) = &p.node {
+                            self.assign(&name.0, Ty::from_annotation(ty));
+                        }
+                    }
+                }
+                self.returns.push(None);
+                self.expr(body);
+                self.returns.pop();
+                self.scopes.pop();
+                Ty::Function(Some(Rc::new(sig)))
+            }
+            Expr::Literal(x) => match x {
+                AstLiteral::Int(_) => Ty::Int,
+                AstLiteral::Float(_) => Ty::Float,
+                AstLiteral::String(_) => Ty::String,
+            },
+            Expr::Not(x) => {
+                self.expr(x);
+                Ty::Bool
+            }
+            Expr::Minus(x) | Expr::Plus(x) => match self.expr(x) {
+                ty @ (Ty::Int | Ty::Float) => ty,
+                _ => Ty::Any,
+            },
+            Expr::BitNot(x) => match self.expr(x) {
+                Ty::Int => Ty::Int,
+                _ => Ty::Any,
+            },
+            Expr::Op(lhs, op, rhs) => {
+                let lhs = self.expr(lhs);
+                let rhs = self.expr(rhs);
+                binary_op(lhs, *op, rhs)
+            }
+            Expr::If(cond_then_else) => {
+                let (cond, then, els) = &**cond_then_else;
+                self.expr(cond);
+                let then = self.expr(then);
+                let els = self.expr(els);
+                Ty::union(vec![then, els])
+            }
+            Expr::List(xs) => Ty::list(xs.iter().map(|x| self.expr(x)).collect()),
+            Expr::Dict(xs) => {
+                if xs.is_empty() {
+                    return Ty::dict(Ty::Any, Ty::Any);
+                }
+                let (keys, values) = xs.iter().map(|(k, v)| (self.expr(k), self.expr(v))).unzip();
+                Ty::dict(Ty::union(keys), Ty::union(values))
+            }
+            Expr::ListComprehension(x, for_, clauses) => {
+                self.comprehension(for_, clauses);
+                let ty = self.expr(x);
+                self.scopes.pop();
+                Ty::list(vec![ty])
+            }
+            Expr::DictComprehension(kv, for_, clauses) => {
+                self.comprehension(for_, clauses);
+                let key = self.expr(&kv.0);
+                let value = self.expr(&kv.1);
+                self.scopes.pop();
+                Ty::dict(key, value)
+            }
+        }
+    }
+
+    /// Enter the scope of a comprehension, which the caller must pop.
+    fn comprehension(&mut self, for_: &ForClause, clauses: &[Clause]) {
+        // The first iterable is evaluated outside of the comprehension.
+        let first = self.expr(&for_.over);
+        let mut names = HashSet::new();
+        for_.var.visit_lvalue(|x| {
+            names.insert(x.0.clone());
+        });
+        for clause in clauses {
+            if let Clause::For(x) = clause {
+                x.var.visit_lvalue(|x| {
+                    names.insert(x.0.clone());
+                });
+            }
+        }
+        let counts = names.into_iter().map(|name| (name, 1)).collect();
+        self.scopes.push(Scope::new(counts));
+        self.assign_target(&for_.var, element(&first));
+        for clause in clauses {
+            match clause {
+                Clause::For(x) => {
+                    let ty = self.expr(&x.over);
+                    self.assign_target(&x.var, element(&ty));
+                }
+                Clause::If(x) => {
+                    self.expr(x);
+                }
+            }
+        }
+    }
+
+    /// The type of an attribute, or `None` if values of this type definitely don't have it.
+    fn attribute(&self, ty: &Ty, attr: &str) -> Option<Ty> {
+        let members = match ty {
+            Ty::String => &self.string_members,
+            Ty::List(_) => &self.list_members,
+            Ty::Dict(_) => &self.dict_members,
+            Ty::None | Ty::Bool | Ty::Int | Ty::Float | Ty::Tuple(_) => return None,
+            // Objects aren't documented with their constant members.
+            Ty::Object(members) => return Some(members.get(attr).cloned().unwrap_or(Ty::Any)),
+            Ty::Union(xs) => {
+                let found = xs
+                    .iter()
+                    .filter_map(|x| self.attribute(x, attr))
+                    .collect::<Vec<_>>();
+                return if found.is_empty() {
+                    None
+                } else {
+                    Some(Ty::union(found))
+                };
+            }
+            Ty::Any | Ty::Function(_) | Ty::Name(_) => return Some(Ty::Any),
+        };
+        members.get(attr).cloned()
+    }
+
+    fn call(&mut self, span: Span, sig: &Signature, args: &[AstArgument], arg_tys: &[Ty]) {
+        let params = match &sig.params {
+            Some(params) => params,
+            None => return,
+        };
+        // With `*args` or `**kwargs` we can't tell which parameters the arguments go to.
+        let star_args = args
+            .iter()
+            .position(|x| matches!(&x.node, Argument::Args(_) | Argument::KwArgs(_)));
+
+        let positional_params = params
+            .iter()
+            .take_while(|x| matches!(x, Param::Normal { .. }))
+            .collect::<Vec<_>>();
+        let has_args = params.iter().any(|x| matches!(x, Param::Args));
+        let kwargs = params.iter().find_map(|x| match x {
+            Param::Kwargs(ty) => Some(ty),
+            _ => None,
+        });
+
+        let mut given = HashSet::new();
+        let mut positional = 0;
+        for (i, (arg, ty)) in args.iter().zip(arg_tys).enumerate() {
+            match &arg.node {
+                Argument::Positional(_) => {
+                    if star_args.map_or(false, |star| star < i) {
+                        continue;
+                    }
+                    if let Some(Param::Normal {
+                        name, ty: expected, ..
+                    }) = positional_params.get(positional)
+                    {
+                        given.insert(name.as_str());
+                        self.check_arg(arg.span, sig, name, expected, ty);
+                    }
+                    positional += 1;
+                }
+                Argument::Named(name, _) => {
+                    let param = params.iter().find_map(|x| match x {
+                        Param::Normal { name: n, ty, .. } if *n == name.node => Some(ty),
+                        _ => None,
+                    });
+                    match param.or(kwargs) {
+                        Some(expected) => {
+                            given.insert(name.node.as_str());
+                            self.check_arg(arg.span, sig, &name.node, expected, ty);
+                        }
+                        None => self.report(
+                            arg.span,
+                            TypeCheck::UnexpectedNamedArgument(sig.name.clone(), name.node.clone()),
+                        ),
+                    }
+                }
+                Argument::Args(_) | Argument::KwArgs(_) => {}
+            }
+        }
+
+        if star_args.is_some() {
+            return;
+        }
+        if positional > positional_params.len() && !has_args {
+            self.report(
+                span,
+                TypeCheck::TooManyPositionalArguments(sig.name.clone(), positional_params.len()),
+            );
+        }
+        for param in params {
+            if let Param::Normal {
+                name,
+                required: true,
+                ..
+            } = param
+            {
+                if !given.contains(name.as_str()) {
+                    self.report(
+                        span,
+                        TypeCheck::MissingArgument(sig.name.clone(), name.clone()),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_arg(&mut self, span: Span, sig: &Signature, name: &str, expected: &Ty, ty: &Ty) {
+        if !expected.intersects(ty) {
+            self.report(
+                span,
+                TypeCheck::ArgumentTypeMismatch(
+                    sig.name.clone(),
+                    name.to_owned(),
+                    expected.clone(),
+                    ty.clone(),
+                ),
+            );
+        }
+    }
+}
+
+/// The type of the elements produced by iterating over a value of the given type.
+fn element(ty: &Ty) -> Ty {
+    match ty {
+        Ty::List(x) => (**x).clone(),
+        Ty::Dict(x) => x.0.clone(),
+        Ty::Tuple(Some(xs)) => Ty::union(xs.clone()),
+        _ => Ty::Any,
+    }
+}
+
+fn binary_op(lhs: Ty, op: BinOp, rhs: Ty) -> Ty {
+    let numeric = |lhs: &Ty, rhs: &Ty| match (lhs, rhs) {
+        (Ty::Int, Ty::Int) => Ty::Int,
+        (Ty::Int | Ty::Float, Ty::Int | Ty::Float) => Ty::Float,
+        _ => Ty::Any,
+    };
+    match op {
+        BinOp::Equal
+        | BinOp::NotEqual
+        | BinOp::Less
+        | BinOp::Greater
+        | BinOp::LessOrEqual
+        | BinOp::GreaterOrEqual
+        | BinOp::In
+        | BinOp::NotIn => Ty::Bool,
+        BinOp::And | BinOp::Or => Ty::union(vec![lhs, rhs]),
+        BinOp::Add => match (lhs, rhs) {
+            (Ty::String, Ty::String) => Ty::String,
+            (Ty::List(x), Ty::List(y)) => Ty::list(vec![*x, *y]),
+            (Ty::Tuple(_), Ty::Tuple(_)) => Ty::Tuple(None),
+            (lhs, rhs) => numeric(&lhs, &rhs),
+        },
+        BinOp::Multiply => match (lhs, rhs) {
+            (Ty::String, Ty::Int) | (Ty::Int, Ty::String) => Ty::String,
+            (ty @ Ty::List(_), Ty::Int) | (Ty::Int, ty @ Ty::List(_)) => ty,
+            (lhs, rhs) => numeric(&lhs, &rhs),
+        },
+        BinOp::Percent => match lhs {
+            Ty::String => Ty::String,
+            lhs => numeric(&lhs, &rhs),
+        },
+        BinOp::Divide => match numeric(&lhs, &rhs) {
+            Ty::Any => Ty::Any,
+            _ => Ty::Float,
+        },
+        BinOp::Subtract | BinOp::FloorDivide => numeric(&lhs, &rhs),
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::LeftShift | BinOp::RightShift => {
+            match (lhs, rhs) {
+                (Ty::Int, Ty::Int) => Ty::Int,
+                _ => Ty::Any,
+            }
+        }
+    }
+}
+
+pub(crate) fn typecheck(
+    module: &AstModule,
+    globals: &HashMap<String, Option<DocItem>>,
+) -> Vec<LintT<TypeCheck>> {
+    let mut checker = TypeChecker::new(&module.codemap, globals);
+    checker.module(module);
+    checker.res
+}
+
+#[cfg(test)]
+mod tests {
+    use gazebo::prelude::*;
+
+    use super::*;
+    use crate::environment::Globals;
+
+    fn check(x: &str) -> Vec<String> {
+        let module = AstModule::parse("bad.bzl", x.to_owned(), &Dialect::Extended).unwrap();
+        typecheck(&module, &Globals::standard().member_documentation())
+            .map(|x| x.problem.to_string())
+    }
+
+    #[test]
+    fn test_typecheck_call_arguments() {
+        assert_eq!(
+            check(
+                r#"
+def f(x: int.type, y: [str.type] = [], *, z: bool.type = False):
+    pass
+f("a")
+f(1, ["b"], z = True)
+f(1, [2], z = None)
+f(1, 2, 3)
+f(y = [])
+f(1, w = 2)
+f(*[1, 2])
+"#
+            ),
+            &[
+                "Argument `x` of `f` expects type `int.type`, but got `str.type`",
+                "Argument `y` of `f` expects type `[str.type]`, but got `[int.type]`",
+                "Argument `z` of `f` expects type `bool.type`, but got `None`",
+                "Argument `y` of `f` expects type `[str.type]`, but got `int.type`",
+                "Too many positional arguments to `f`, expected at most 2",
+                "Missing argument `x` in call to `f`",
+                "Unexpected named argument `w` in call to `f`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typecheck_builtins() {
+        assert_eq!(
+            check(
+                r#"
+len([1, 2])
+len()
+"a,b".split(",")
+"a,b".split(1)
+"#
+            ),
+            &[
+                "Missing argument `a` in call to `len`",
+                "Argument `sep` of `split` expects type `[None, str.type]`, but got `int.type`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typecheck_attributes() {
+        assert_eq!(
+            check(
+                r#"
+def f(x: str.type, y: ["string", None], z):
+    x.upper()
+    x.append(1)
+    y.upper()
+    y.foo
+    z.foo
+    [].append(1)
+    {}.nope()
+"#
+            ),
+            &[
+                "Value of type `str.type` has no attribute `append`",
+                "Value of type `[str.type, None]` has no attribute `foo`",
+                "Value of type `\"dict\"` has no attribute `nope`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typecheck_return() {
+        assert_eq!(
+            check(
+                r#"
+def f(x) -> int.type:
+    if x:
+        return "a"
+    return 1
+def g() -> [str.type, None]:
+    if True:
+        return
+    return "a"
+def h() -> "string":
+    return len("a")
+def i() -> "":
+    return 1
+"#
+            ),
+            &[
+                "Function `f` is declared to return `int.type`, but returns `str.type`",
+                "Function `h` is declared to return `str.type`, but returns `int.type`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typecheck_follows_variables() {
+        assert_eq!(
+            check(
+                r#"
+def f(x: int.type) -> str.type:
+    return g(x)
+def g(x: int.type) -> str.type:
+    return str(x)
+x = 1
+f(x)
+y = "a"
+f(y)
+z = 1
+z = "a"
+f(z)
+f(f(1))
+[f(a) for a in ["a"]]
+"#
+            ),
+            &[
+                "Argument `x` of `f` expects type `int.type`, but got `str.type`",
+                "Argument `x` of `f` expects type `int.type`, but got `str.type`",
+                "Argument `x` of `f` expects type `int.type`, but got `str.type`",
+            ]
+        );
+    }
+}
@@ -46,6 +46,11 @@ impl Pos {
     pub const fn new(x: u32) -> Self {
         Self(x)
     }
+
+    /// Get the value.
+    pub const fn get(self) -> u32 {
+        self.0
+    }
 }
 
 impl Add<u32> for Pos {
@@ -0,0 +1,310 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! State a debug adapter needs between `before_stmt` callbacks: stepping, breakpoint
+//! conditions and the structure of values being inspected.
+
+use std::fmt;
+use std::fmt::Display;
+
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::values::dict::Dict;
+use crate::values::list::ListRef;
+use crate::values::set::Set;
+use crate::values::tuple::Tuple;
+use crate::values::Heap;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum AdapterError {
+    #[error("Invalid hit condition `{0}`, expected `N`, `==N`, `>N`, `>=N` or `%N`")]
+    InvalidHitCondition(String),
+    #[error("No child `{0}` in value of type `{1}`")]
+    NoSuchChild(PathSegment, String),
+}
+
+/// The kind of step requested by the debugger client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    /// Pause at the next statement, wherever it is.
+    Into,
+    /// Pause at the next statement in the current function, or a caller of it.
+    Over,
+    /// Pause at the next statement in a caller of the current function.
+    Out,
+}
+
+/// An in-progress step, created when the client asks to step and consulted
+/// on every subsequent statement until it says to pause.
+#[derive(Debug, Clone, Copy)]
+pub struct Stepping {
+    kind: StepKind,
+    depth: usize,
+}
+
+impl Stepping {
+    /// Start a step of the given kind from the statement the evaluator is paused at.
+    pub fn new(kind: StepKind, eval: &Evaluator) -> Self {
+        Self {
+            kind,
+            depth: eval.call_stack_count(),
+        }
+    }
+
+    /// Should execution pause at the statement the evaluator is about to run.
+    pub fn should_pause(&self, eval: &Evaluator) -> bool {
+        let depth = eval.call_stack_count();
+        match self.kind {
+            StepKind::Into => true,
+            StepKind::Over => depth <= self.depth,
+            StepKind::Out => depth < self.depth,
+        }
+    }
+}
+
+/// A DAP hit condition, deciding on which hits a breakpoint pauses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitCondition {
+    /// Pause on exactly the `N`th hit, written `N` or `==N`.
+    Equal(usize),
+    /// Pause on every hit after the `N`th, written `>N`.
+    Greater(usize),
+    /// Pause on the `N`th hit and every one after, written `>=N`.
+    GreaterEqual(usize),
+    /// Pause on every `N`th hit, written `%N`.
+    Multiple(usize),
+}
+
+impl HitCondition {
+    /// Parse a hit condition as typed by the user.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let err = || AdapterError::InvalidHitCondition(s.to_owned());
+        let (make, n): (fn(usize) -> Self, &str) = if let Some(n) = s.strip_prefix(">=") {
+            (Self::GreaterEqual, n)
+        } else if let Some(n) = s.strip_prefix('>') {
+            (Self::Greater, n)
+        } else if let Some(n) = s.strip_prefix("==") {
+            (Self::Equal, n)
+        } else if let Some(n) = s.strip_prefix('%') {
+            (Self::Multiple, n)
+        } else {
+            (Self::Equal, s)
+        };
+        let n: usize = n.trim().parse().map_err(|_| err())?;
+        if matches!(make(n), Self::Multiple(0)) {
+            return Err(err().into());
+        }
+        Ok(make(n))
+    }
+
+    /// Does the `hits`th hit (counting from 1) satisfy the condition.
+    pub fn matches(self, hits: usize) -> bool {
+        match self {
+            Self::Equal(n) => hits == n,
+            Self::Greater(n) => hits > n,
+            Self::GreaterEqual(n) => hits >= n,
+            Self::Multiple(n) => hits % n == 0,
+        }
+    }
+}
+
+/// The condition attached to a single breakpoint, along with how often it has been hit.
+#[derive(Debug, Clone, Default)]
+pub struct BreakpointCondition {
+    condition: Option<String>,
+    hit_condition: Option<HitCondition>,
+    hits: usize,
+}
+
+impl BreakpointCondition {
+    /// Create from the optional `condition` and `hitCondition` of a DAP source breakpoint.
+    pub fn new(condition: Option<String>, hit_condition: Option<&str>) -> anyhow::Result<Self> {
+        Ok(Self {
+            condition: condition.filter(|x| !x.trim().is_empty()),
+            hit_condition: hit_condition
+                .filter(|x| !x.trim().is_empty())
+                .map(HitCondition::parse)
+                .transpose()?,
+            hits: 0,
+        })
+    }
+
+    /// Called when execution reaches the breakpoint, returning whether to pause.
+    /// Only hits where the condition holds are counted towards the hit condition.
+    ///
+    /// The condition is evaluated with [`Evaluator::eval_statements`], so the caller must
+    /// make sure its own `before_stmt` callback does not recurse while this runs.
+    pub fn should_pause(&mut self, eval: &mut Evaluator) -> anyhow::Result<bool> {
+        if let Some(condition) = &self.condition {
+            let ast = AstModule::parse("condition", condition.clone(), &Dialect::Extended)?;
+            if !eval.eval_statements(ast)?.to_bool() {
+                return Ok(false);
+            }
+        }
+        self.hits += 1;
+        Ok(self
+            .hit_condition
+            .map_or(true, |hit_condition| hit_condition.matches(self.hits)))
+    }
+}
+
+/// One step from a value to one of the children shown by [`inspect_children`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// An element of a list, tuple or set, or an entry of a dict.
+    Index(usize),
+    /// An attribute, e.g. a field of a struct or provider.
+    Attr(String),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(i) => write!(f, "[{}]", i),
+            Self::Attr(x) => write!(f, ".{}", x),
+        }
+    }
+}
+
+/// The children of a value a debugger should let the user expand, as
+/// `(segment, display name, child)`.
+///
+/// Lists, tuples and sets give their elements, dicts give their values named by the
+/// key, and anything else (structs, providers, records) gives its attributes.
+/// Attributes which are methods are omitted, since they carry no state.
+pub fn inspect_children<'v>(
+    value: Value<'v>,
+    heap: &'v Heap,
+) -> Vec<(PathSegment, String, Value<'v>)> {
+    fn indexed<'v>(xs: impl Iterator<Item = Value<'v>>) -> Vec<(PathSegment, String, Value<'v>)> {
+        xs.enumerate()
+            .map(|(i, x)| (PathSegment::Index(i), i.to_string(), x))
+            .collect()
+    }
+
+    if let Some(xs) = ListRef::from_value(value) {
+        indexed(xs.iter())
+    } else if let Some(xs) = Tuple::from_value(value) {
+        indexed(xs.iter())
+    } else if let Some(xs) = Set::from_value(value) {
+        indexed(xs.iter())
+    } else if let Some(xs) = Dict::from_value(value) {
+        xs.iter()
+            .enumerate()
+            .map(|(i, (k, v))| (PathSegment::Index(i), k.to_repr(), v))
+            .collect()
+    } else {
+        value
+            .dir_attr()
+            .into_iter()
+            .filter_map(|name| {
+                let child = value.get_attr(&name, heap).ok()??;
+                if child.get_type() == "function" {
+                    return None;
+                }
+                Some((PathSegment::Attr(name.clone()), name, child))
+            })
+            .collect()
+    }
+}
+
+/// Follow a path of segments, as produced by [`inspect_children`], down from `value`.
+pub fn resolve_path<'v>(
+    value: Value<'v>,
+    path: &[PathSegment],
+    heap: &'v Heap,
+) -> anyhow::Result<Value<'v>> {
+    let mut value = value;
+    for segment in path {
+        value = inspect_children(value, heap)
+            .into_iter()
+            .find(|(s, _, _)| s == segment)
+            .map(|(_, _, x)| x)
+            .ok_or_else(|| {
+                AdapterError::NoSuchChild(segment.clone(), value.get_type().to_owned())
+            })?;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+
+    #[test]
+    fn test_hit_condition() {
+        assert_eq!(HitCondition::parse("3").unwrap(), HitCondition::Equal(3));
+        assert_eq!(HitCondition::parse("== 3").unwrap(), HitCondition::Equal(3));
+        assert_eq!(HitCondition::parse(">3").unwrap(), HitCondition::Greater(3));
+        assert_eq!(
+            HitCondition::parse(">=3").unwrap(),
+            HitCondition::GreaterEqual(3)
+        );
+        assert_eq!(
+            HitCondition::parse("%2").unwrap(),
+            HitCondition::Multiple(2)
+        );
+        assert!(HitCondition::parse("%0").is_err());
+        assert!(HitCondition::parse("x").is_err());
+
+        let hits = |c: HitCondition| (1..=6).filter(|i| c.matches(*i)).collect::<Vec<_>>();
+        assert_eq!(hits(HitCondition::Equal(3)), vec![3]);
+        assert_eq!(hits(HitCondition::Greater(4)), vec![5, 6]);
+        assert_eq!(hits(HitCondition::GreaterEqual(4)), vec![4, 5, 6]);
+        assert_eq!(hits(HitCondition::Multiple(2)), vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn test_inspect_children() {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse(
+            "x.star",
+            "struct(a = [1, (2, 3)], b = {'k': 4})".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let heap = module.heap();
+        let value = eval.eval_module(ast, &Globals::extended()).unwrap();
+
+        let names = |x: Value| {
+            inspect_children(x, heap)
+                .into_iter()
+                .map(|(_, name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(value), vec!["a", "b"]);
+
+        let path = [
+            PathSegment::Attr("a".to_owned()),
+            PathSegment::Index(1),
+            PathSegment::Index(0),
+        ];
+        assert_eq!(
+            resolve_path(value, &path, heap).unwrap().unpack_int(),
+            Some(2)
+        );
+        let b = resolve_path(value, &[PathSegment::Attr("b".to_owned())], heap).unwrap();
+        assert_eq!(names(b), vec!["\"k\""]);
+        assert!(resolve_path(value, &[PathSegment::Index(0)], heap).is_err());
+    }
+}
@@ -40,6 +40,12 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     pub fn local_variables(&self) -> SmallMap<String, Value<'v>> {
         inspect_local_variables(self).unwrap_or_else(|| inspect_module_variables(self))
     }
+
+    /// Obtain the variables defined at the top-level of the [`Module`](crate::environment::Module)
+    /// being evaluated. As with [`local_variables`](Evaluator::local_variables), only for debugging.
+    pub fn top_level_variables(&self) -> SmallMap<String, Value<'v>> {
+        inspect_module_variables(self)
+    }
 }
 
 fn inspect_local_variables<'v>(eval: &Evaluator<'v, '_>) -> Option<SmallMap<String, Value<'v>>> {
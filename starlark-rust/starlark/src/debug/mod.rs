@@ -15,6 +15,17 @@
  * limitations under the License.
  */
 
+//! Building blocks for debuggers, such as the DAP server, to pause and inspect an [`Evaluator`](crate::eval::Evaluator).
+
+mod adapter;
 mod breakpoint;
 mod evaluate;
 mod inspect;
+
+pub use adapter::inspect_children;
+pub use adapter::resolve_path;
+pub use adapter::BreakpointCondition;
+pub use adapter::HitCondition;
+pub use adapter::PathSegment;
+pub use adapter::StepKind;
+pub use adapter::Stepping;
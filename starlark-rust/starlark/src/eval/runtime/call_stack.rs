@@ -224,6 +224,11 @@ impl<'v> CheapCallStack<'v> {
         Ok(())
     }
 
+    /// Number of frames on the stack.
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+
     /// Remove the top element from the stack. Called after `push`.
     pub(crate) fn pop(&mut self) {
         debug_assert!(self.count >= 1);
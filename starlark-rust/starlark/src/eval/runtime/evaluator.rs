@@ -372,6 +372,11 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             .to_diagnostic_frames(InlinedFrames::default())
     }
 
+    /// Number of entries on the call-stack, including native functions.
+    pub fn call_stack_count(&self) -> usize {
+        self.call_stack.count()
+    }
+
     /// Obtain the top location on the call-stack. May be [`None`] if the
     /// call happened via native functions.
     pub fn call_stack_top_location(&self) -> Option<FileSpan> {
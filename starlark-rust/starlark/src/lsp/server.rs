@@ -18,6 +18,7 @@
 //! Based on the reference lsp-server example at <https://github.com/rust-analyzer/lsp-server/blob/master/examples/goto_def.rs>.
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::path::Path;
 use std::path::PathBuf;
@@ -39,12 +40,16 @@ use lsp_types::notification::DidCloseTextDocument;
 use lsp_types::notification::DidOpenTextDocument;
 use lsp_types::notification::LogMessage;
 use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::Formatting;
 use lsp_types::request::GotoDefinition;
+use lsp_types::request::RangeFormatting;
 use lsp_types::DefinitionOptions;
 use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DocumentFormattingParams;
+use lsp_types::DocumentRangeFormattingParams;
 use lsp_types::GotoDefinitionParams;
 use lsp_types::GotoDefinitionResponse;
 use lsp_types::InitializeParams;
@@ -57,6 +62,7 @@ use lsp_types::Range;
 use lsp_types::ServerCapabilities;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
+use lsp_types::TextEdit;
 use lsp_types::Url;
 use lsp_types::WorkDoneProgressOptions;
 use serde::de::DeserializeOwned;
@@ -72,6 +78,7 @@ use crate::analysis::LspModule;
 use crate::codemap::ResolvedSpan;
 use crate::lsp::server::LoadContentsError::WrongScheme;
 use crate::syntax::AstModule;
+use crate::syntax::FormatOptions;
 
 /// The request to get the file contents for a starlark: URI
 struct StarlarkFileContentsRequest {}
@@ -234,15 +241,22 @@ pub struct LspEvalResult {
 /// Settings that the LspContext can provide to change what capabilities the server enables
 /// or disables.
 #[derive(Dupe, Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct LspServerSettings {
     /// Whether goto definition should work.
     pub enable_goto_definition: bool,
+    /// Whether document and range formatting should work.
+    pub enable_formatting: bool,
+    /// Whether formatting should also sort load symbols and target attributes.
+    pub opinionated_formatting: bool,
 }
 
 impl Default for LspServerSettings {
     fn default() -> Self {
         Self {
             enable_goto_definition: true,
+            enable_formatting: true,
+            opinionated_formatting: false,
         }
     }
 }
@@ -313,9 +327,13 @@ pub(crate) enum LoadContentsError {
 struct Backend<T: LspContext> {
     connection: Connection,
     context: T,
+    settings: LspServerSettings,
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// Files whose current contents failed to parse, so `last_valid_parse` is out of date.
+    /// Entries are evicted when the file is closed.
+    failed_parse: RwLock<HashSet<LspUrl>>,
 }
 
 /// The logic implementations of stuff
@@ -331,6 +349,10 @@ impl<T: LspContext> Backend<T> {
         ServerCapabilities {
             text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
             definition_provider,
+            document_formatting_provider: settings.enable_formatting.then_some(OneOf::Left(true)),
+            document_range_formatting_provider: settings
+                .enable_formatting
+                .then_some(OneOf::Left(true)),
             ..ServerCapabilities::default()
         }
     }
@@ -354,11 +376,16 @@ impl<T: LspContext> Backend<T> {
     fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
         let uri = uri.try_into()?;
         let eval_result = self.context.parse_file_with_contents(&uri, text);
+        let mut failed_parse = self.failed_parse.write().unwrap();
         if let Some(ast) = eval_result.ast {
             let module = Arc::new(LspModule::new(ast));
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
             last_valid_parse.insert(uri.clone(), module);
+            failed_parse.remove(&uri);
+        } else {
+            failed_parse.insert(uri.clone());
         }
+        drop(failed_parse);
         self.publish_diagnostics(uri.try_into()?, eval_result.diagnostics, version);
         Ok(())
     }
@@ -383,8 +410,10 @@ impl<T: LspContext> Backend<T> {
 
     fn did_close(&self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
         {
+            let uri = params.text_document.uri.clone().try_into()?;
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
-            last_valid_parse.remove(&params.text_document.uri.clone().try_into()?);
+            last_valid_parse.remove(&uri);
+            self.failed_parse.write().unwrap().remove(&uri);
         }
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None);
         Ok(())
@@ -400,6 +429,67 @@ impl<T: LspContext> Backend<T> {
         self.send_response(new_response(id, self.find_definition(params)));
     }
 
+    /// Format the whole of a file.
+    fn formatting(&self, id: RequestId, params: DocumentFormattingParams) {
+        self.send_response(new_response(
+            id,
+            self.format_file(params.text_document.uri, None),
+        ));
+    }
+
+    /// Format the top-level statements which overlap the given range.
+    fn range_formatting(&self, id: RequestId, params: DocumentRangeFormattingParams) {
+        self.send_response(new_response(
+            id,
+            self.format_file(params.text_document.uri, Some(params.range)),
+        ));
+    }
+
+    /// Get the edits to format a file, or to format only those statements within `range`.
+    ///
+    /// Returns `None` if the current contents of the file do not parse, as formatting the
+    /// last valid parse would overwrite the user's changes.
+    fn format_file(&self, uri: Url, range: Option<Range>) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let uri = uri.try_into()?;
+        if self.failed_parse.read().unwrap().contains(&uri) {
+            return Ok(None);
+        }
+        let module = match self.get_ast(&uri) {
+            Some(module) => module,
+            None => return Ok(None),
+        };
+        let ast = &module.ast;
+        let options = if self.settings.opinionated_formatting {
+            FormatOptions::opinionated()
+        } else {
+            FormatOptions::default()
+        };
+        let edits = match range {
+            None => {
+                let span = ast.codemap.full_span();
+                let text = ast.format(&options);
+                if text == ast.codemap.source_span(span) {
+                    Vec::new()
+                } else {
+                    vec![TextEdit::new(ast.codemap.resolve_span(span).into(), text)]
+                }
+            }
+            Some(range) => {
+                // A selection ending at the start of a line doesn't include that line.
+                let end_line = if range.end.character == 0 && range.end.line > range.start.line {
+                    range.end.line - 1
+                } else {
+                    range.end.line
+                };
+                ast.format_lines(&options, range.start.line as usize, end_line as usize)
+                    .into_iter()
+                    .map(|(span, text)| TextEdit::new(span.into(), text))
+                    .collect()
+            }
+        };
+        Ok(Some(edits))
+    }
+
     /// Get the file contents of a starlark: URI.
     fn get_starlark_file_contents(&self, id: RequestId, params: StarlarkFileContentsParams) {
         let response: anyhow::Result<_> = match params.uri {
@@ -623,6 +713,10 @@ impl<T: LspContext> Backend<T> {
                     //            be handled client side.
                     if let Some(params) = as_request::<GotoDefinition>(&req) {
                         self.goto_definition(req.id, params);
+                    } else if let Some(params) = as_request::<Formatting>(&req) {
+                        self.formatting(req.id, params);
+                    } else if let Some(params) = as_request::<RangeFormatting>(&req) {
+                        self.range_formatting(req.id, params);
                     } else if let Some(params) = as_request::<StarlarkFileContentsRequest>(&req) {
                         self.get_starlark_file_contents(req.id, params);
                     } else if self.connection.handle_shutdown(&req)? {
@@ -671,12 +765,12 @@ pub fn server_with_connection<T: LspContext>(
     let (init_request_id, init_value) = connection.initialize_start()?;
 
     let initialization_params: InitializeParams = serde_json::from_value(init_value)?;
-    let server_settings = initialization_params
+    let server_settings: LspServerSettings = initialization_params
         .initialization_options
         .as_ref()
         .and_then(|opts| serde_json::from_value(opts.clone()).ok())
         .unwrap_or_default();
-    let capabilities_payload = Backend::<T>::server_capabilities(server_settings);
+    let capabilities_payload = Backend::<T>::server_capabilities(server_settings.dupe());
     let server_capabilities = serde_json::to_value(&capabilities_payload).unwrap();
 
     let initialize_data = serde_json::json!({
@@ -687,7 +781,9 @@ pub fn server_with_connection<T: LspContext>(
     Backend {
         connection,
         context,
+        settings: server_settings,
         last_valid_parse: RwLock::default(),
+        failed_parse: RwLock::default(),
     }
     .main_loop(initialization_params)?;
 
@@ -769,7 +865,12 @@ mod test {
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::request::Formatting;
     use lsp_types::request::GotoDefinition;
+    use lsp_types::request::RangeFormatting;
+    use lsp_types::DocumentFormattingParams;
+    use lsp_types::DocumentRangeFormattingParams;
+    use lsp_types::FormattingOptions;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
     use lsp_types::LocationLink;
@@ -777,6 +878,7 @@ mod test {
     use lsp_types::Range;
     use lsp_types::TextDocumentIdentifier;
     use lsp_types::TextDocumentPositionParams;
+    use lsp_types::TextEdit;
     use lsp_types::Url;
     use textwrap::dedent;
 
@@ -1565,6 +1667,7 @@ mod test {
     fn disables_goto_definition() -> anyhow::Result<()> {
         let server = TestServer::new_with_settings(Some(LspServerSettings {
             enable_goto_definition: false,
+            ..LspServerSettings::default()
         }))?;
 
         let goto_definition_disabled = server
@@ -1578,6 +1681,7 @@ mod test {
 
         let server = TestServer::new_with_settings(Some(LspServerSettings {
             enable_goto_definition: true,
+            ..LspServerSettings::default()
         }))?;
 
         let goto_definition_enabled = server
@@ -1591,6 +1695,81 @@ mod test {
         Ok(())
     }
 
+    fn formatting_request(server: &mut TestServer, uri: Url) -> Request {
+        server.new_request::<Formatting>(DocumentFormattingParams {
+            text_document: TextDocumentIdentifier { uri },
+            options: FormattingOptions::default(),
+            work_done_progress_params: Default::default(),
+        })
+    }
+
+    #[test]
+    fn formats_document() -> anyhow::Result<()> {
+        let uri = temp_file_uri("foo.star");
+        let mut server = TestServer::new()?;
+        server.open_file(
+            uri.clone(),
+            "x=[1,2]\ndef f(a,b):\n  return a+b\n".to_owned(),
+        )?;
+
+        let req = formatting_request(&mut server, uri.clone());
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        let expected = TextEdit::new(
+            Range::new(Position::new(0, 0), Position::new(3, 0)),
+            "x = [1, 2]\n\ndef f(a, b):\n    return a + b\n".to_owned(),
+        );
+        assert_eq!(Some(vec![expected]), response);
+
+        server.change_file(uri.clone(), "x = [1, 2]\n".to_owned())?;
+        let req = formatting_request(&mut server, uri.clone());
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        assert_eq!(Some(vec![]), response);
+
+        // The last valid parse must not be used once the file no longer parses.
+        server.change_file(uri.clone(), "x = [1,\n".to_owned())?;
+        let req = formatting_request(&mut server, uri);
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        assert_eq!(None, response);
+        Ok(())
+    }
+
+    #[test]
+    fn formats_range() -> anyhow::Result<()> {
+        let uri = temp_file_uri("foo.star");
+        let mut server = TestServer::new()?;
+        server.open_file(uri.clone(), "x=1\ny=[1,\n2]\nz=3\n".to_owned())?;
+
+        let req = server.new_request::<RangeFormatting>(DocumentRangeFormattingParams {
+            text_document: TextDocumentIdentifier { uri },
+            range: Range::new(Position::new(2, 0), Position::new(3, 0)),
+            options: FormattingOptions::default(),
+            work_done_progress_params: Default::default(),
+        });
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        let expected = TextEdit::new(
+            Range::new(Position::new(1, 0), Position::new(2, 2)),
+            "y = [\n    1,\n    2,\n]".to_owned(),
+        );
+        assert_eq!(Some(vec![expected]), response);
+        Ok(())
+    }
+
+    #[test]
+    fn disables_formatting() -> anyhow::Result<()> {
+        let server = TestServer::new_with_settings(Some(LspServerSettings {
+            enable_formatting: false,
+            ..LspServerSettings::default()
+        }))?;
+        let capabilities = server.initialization_result().unwrap().capabilities;
+        assert!(capabilities.document_formatting_provider.is_none());
+        assert!(capabilities.document_range_formatting_provider.is_none());
+        Ok(())
+    }
+
     #[test]
     fn returns_starlark_file_contents() -> anyhow::Result<()> {
         let mut server = TestServer::new()?;
@@ -0,0 +1,339 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set()` constructor and methods for the `set` type.
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
+use crate::values::none::NoneType;
+use crate::values::set::Set;
+use crate::values::set::SetConstructor;
+use crate::values::set::SetRef;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::ValueError;
+
+#[starlark_module]
+pub fn global(builder: &mut GlobalsBuilder) {
+    /// `set([iterable])` creates a set of the unique elements of `iterable`, in the order
+    /// they first appear. `set[t]` is a type annotation for a set whose elements are of type `t`.
+    const set: SetConstructor = SetConstructor;
+}
+
+/// Fold each of the iterables in `others` into `this` using `f`.
+fn fold<'v>(
+    this: &Set<'v>,
+    others: &[Value<'v>],
+    heap: &'v Heap,
+    f: impl Fn(&Set<'v>, &Set<'v>) -> Set<'v>,
+) -> anyhow::Result<Set<'v>> {
+    let mut res = this.clone();
+    for other in others {
+        res = f(&res, &Set::from_iterable(*other, heap)?);
+    }
+    Ok(res)
+}
+
+#[starlark_module]
+pub(crate) fn set_methods(builder: &mut MethodsBuilder) {
+    /// `S.add(x)` adds `x` to the set S, and returns `None`.
+    ///
+    /// `add` fails if `x` is unhashable, or if the set is frozen or has active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1])
+    /// x.add(2)
+    /// x.add(1)
+    /// x == set([1, 2])
+    /// # "#);
+    /// ```
+    fn add<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        Set::from_value_mut(this)?.insert_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// `S.clear()` removes all the elements of the set S and returns `None`.
+    /// It fails if the set is frozen or if there are active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.clear()
+    /// x == set()
+    /// # "#);
+    /// ```
+    fn clear(this: Value) -> anyhow::Result<NoneType> {
+        Set::from_value_mut(this)?.clear();
+        Ok(NoneType)
+    }
+
+    /// `S.difference(*others)` returns a new set with the elements of S which are not
+    /// in any of the iterables `others`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 3]).difference([1], set([3])) == set([2])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn difference<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        fold(&this, &others, heap, Set::difference)
+    }
+
+    /// `S.discard(x)` removes `x` from the set S if present, and returns `None`.
+    ///
+    /// `discard` fails if `x` is unhashable, or if the set is frozen or has active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.discard(2)
+    /// x.discard(3)
+    /// x == set([1])
+    /// # "#);
+    /// ```
+    fn discard<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        Set::from_value_mut(this)?.remove_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// `S.intersection(*others)` returns a new set with the elements of S which are
+    /// also in all of the iterables `others`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 3]).intersection([1, 2], set([2, 3])) == set([2])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn intersection<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        fold(&this, &others, heap, Set::intersection)
+    }
+
+    /// `S.isdisjoint(other)` returns `True` if S has no elements in common with the
+    /// iterable `other`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set([1, 2]).isdisjoint([3])
+    /// not set([1, 2]).isdisjoint(set([2]))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn isdisjoint<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        let other = Set::from_iterable(other, heap)?;
+        Ok(this.iter_hashed().all(|x| !other.contains_hashed(x)))
+    }
+
+    /// `S.issubset(other)` returns `True` if every element of S is in the iterable `other`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set([1, 2]).issubset([1, 2, 3])
+    /// not set([1, 4]).issubset(set([1, 2, 3]))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issubset<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        Ok(this.is_subset(&Set::from_iterable(other, heap)?))
+    }
+
+    /// `S.issuperset(other)` returns `True` if every element of the iterable `other` is in S.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set([1, 2, 3]).issuperset([1, 2])
+    /// not set([1, 2, 3]).issuperset(set([1, 4]))
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issuperset<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        Ok(Set::from_iterable(other, heap)?.is_subset(&this))
+    }
+
+    /// `S.remove(x)` removes `x` from the set S, and returns `None`.
+    ///
+    /// `remove` fails if `x` is not in the set, is unhashable, or if the set is frozen
+    /// or has active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.remove(2)
+    /// x == set([1])
+    /// # "#);
+    /// ```
+    ///
+    /// ```
+    /// # starlark::assert::fail(r#"
+    /// x = set([1, 2])
+    /// x.remove(3)   # error: not found
+    /// # "#, "not found");
+    /// ```
+    fn remove<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let hashed = value.get_hashed()?;
+        if Set::from_value_mut(this)?.remove_hashed(hashed) {
+            Ok(NoneType)
+        } else {
+            Err(ValueError::KeyNotFound(value.to_repr()).into())
+        }
+    }
+
+    /// `S.symmetric_difference(other)` returns a new set with the elements which are in
+    /// exactly one of S and the iterable `other`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).symmetric_difference([2, 3]) == set([1, 3])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn symmetric_difference<'v>(
+        this: SetRef<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        Ok(this.symmetric_difference(&Set::from_iterable(other, heap)?))
+    }
+
+    /// `S.union(*others)` returns a new set with the elements of S followed by
+    /// the elements of the iterables `others` which are not already present.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// list(set([1, 2]).union([3, 1], set([4]))) == [1, 2, 3, 4]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn union<'v>(
+        this: SetRef<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        fold(&this, &others, heap, Set::union)
+    }
+
+    /// `S.update(*others)` adds the elements of the iterables `others` to the set S,
+    /// and returns `None`.
+    ///
+    /// `update` fails if any element is unhashable, or if the set is frozen or has
+    /// active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1])
+    /// x.update([2], set([3]))
+    /// x == set([1, 2, 3])
+    /// # "#);
+    /// ```
+    fn update<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<NoneType> {
+        // Collect everything before borrowing mutably, as `others` may include `this`.
+        let others = others
+            .into_iter()
+            .map(|x| Set::from_iterable(x, heap))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut this = Set::from_value_mut(this)?;
+        for other in others {
+            for x in other.iter_hashed() {
+                this.insert_hashed(x);
+            }
+        }
+        Ok(NoneType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_set_methods() {
+        assert::is_true(
+            r#"
+x = set([1, 2])
+x.update(x, [3])
+x.remove(1)
+x.discard(4)
+x == set([2, 3]) and x.union() == x
+"#,
+        );
+        assert::fail("set([1]).remove(2)", "not found");
+        assert::fail("set([]).add([])", "not hashable");
+        assert::fail("set(x = [1])", "extra named");
+    }
+}
@@ -0,0 +1,1258 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A source formatter for Starlark, working over the parsed [`AstModule`].
+//!
+//! Layout is derived from the AST, while literals are copied verbatim from the source
+//! and comments (which the lexer drops) are recovered from the source text and
+//! reattached by position. A bracketed construct is laid out one item per line if it
+//! was split over multiple lines in the source, contains comments, or does not fit
+//! on a single line.
+
+use std::cmp::Ordering;
+
+use crate::codemap::CodeMap;
+use crate::codemap::Pos;
+use crate::codemap::ResolvedSpan;
+use crate::codemap::Span;
+use crate::syntax::ast::Argument;
+use crate::syntax::ast::Assign;
+use crate::syntax::ast::AstArgument;
+use crate::syntax::ast::AstAssign;
+use crate::syntax::ast::AstAssignIdent;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLoad;
+use crate::syntax::ast::AstParameter;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::AstString;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::Clause;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::ForClause;
+use crate::syntax::ast::Parameter;
+use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
+
+const INDENT: &str = "    ";
+
+/// Lines longer than this are split, if they consist of something that can be split.
+const MAX_LINE_LENGTH: usize = 100;
+
+/// Options controlling [`AstModule::format`].
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Sort the symbols imported by each `load()` statement by their local name.
+    pub sort_load_symbols: bool,
+    /// Reorder the named arguments of top-level calls consisting only of named arguments,
+    /// i.e. target definitions in `BUCK` files, so that `name` comes first and
+    /// `deps`/`visibility` come last, with other attributes in between.
+    pub sort_target_attributes: bool,
+}
+
+impl FormatOptions {
+    /// The opinionated formatting options, enabling all the reordering rewrites.
+    pub fn opinionated() -> Self {
+        Self {
+            sort_load_symbols: true,
+            sort_target_attributes: true,
+        }
+    }
+}
+
+impl AstModule {
+    /// Format the module in a canonical style, preserving comments.
+    ///
+    /// Formatting is idempotent, and the result parses to an equivalent module.
+    pub fn format(&self, options: &FormatOptions) -> String {
+        let mut f = Formatter::new(&self.codemap, options);
+        let end = self.codemap.full_span().end();
+        f.block(&self.statement, 0, Pos::new(0), end);
+        f.out
+    }
+
+    /// Format the top-level statements which overlap the given (0-based, inclusive) lines.
+    ///
+    /// Returns the span of the source lines each statement occupies together with its
+    /// formatted replacement. Comments on their own lines between statements are left as is.
+    pub fn format_lines(
+        &self,
+        options: &FormatOptions,
+        begin_line: usize,
+        end_line: usize,
+    ) -> Vec<(ResolvedSpan, String)> {
+        let top = match &self.statement.node {
+            Stmt::Statements(xs) => xs.iter().collect(),
+            _ => vec![&self.statement],
+        };
+        let mut f = Formatter::new(&self.codemap, options);
+        let mut res = Vec::new();
+        for (i, x) in top.iter().enumerate() {
+            let first = self.codemap.find_line(x.span.begin());
+            let last = self.codemap.find_line(Formatter::stmt_end(x));
+            if last < begin_line || first > end_line {
+                continue;
+            }
+            let next = top
+                .get(i + 1)
+                .map_or(self.codemap.full_span().end(), |x| x.span.begin());
+            f.out.clear();
+            f.stmt(x, 0, next);
+            let last_line = self.codemap.line_span(last);
+            let source = self.codemap.source_span(last_line);
+            let line_end = last_line.end().get() - (source.len() - source.trim_end().len()) as u32;
+            let span = Span::new(self.codemap.line_span(first).begin(), Pos::new(line_end));
+            let text = f.out.trim_end().to_owned();
+            res.push((self.codemap.resolve_span(span), text));
+        }
+        res
+    }
+}
+
+/// Find the spans of all the comments in a source file, skipping over string literals.
+fn find_comments(source: &str) -> Vec<Span> {
+    let bytes = source.as_bytes();
+    let span = |begin: usize, end: usize| Span::new(Pos::new(begin as u32), Pos::new(end as u32));
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => {
+                let end = memchr::memchr(b'\n', &bytes[i..]).map_or(bytes.len(), |n| i + n);
+                let text = &source[i..end];
+                res.push(span(i, i + text.trim_end().len()));
+                i = end;
+            }
+            q @ (b'"' | b'\'') => {
+                let triple = bytes[i..].starts_with(&[q, q, q]);
+                i += if triple { 3 } else { 1 };
+                while i < bytes.len() {
+                    if bytes[i] == b'\\' {
+                        i += 2;
+                    } else if triple && bytes[i..].starts_with(&[q, q, q]) {
+                        i += 3;
+                        break;
+                    } else if !triple && (bytes[i] == q || bytes[i] == b'\n') {
+                        i += 1;
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    res
+}
+
+// Precedence levels, from loosest to tightest binding. An expression printed in a position
+// requiring a tighter precedence than its own is parenthesized.
+const PREC_TEST: u8 = 0;
+const PREC_IF: u8 = 1;
+const PREC_OR: u8 = 2;
+const PREC_AND: u8 = 3;
+const PREC_NOT: u8 = 4;
+const PREC_COMPARE: u8 = 5;
+const PREC_BIT_OR: u8 = 6;
+const PREC_BIT_XOR: u8 = 7;
+const PREC_BIT_AND: u8 = 8;
+const PREC_SHIFT: u8 = 9;
+const PREC_ARITH: u8 = 10;
+const PREC_PRODUCT: u8 = 11;
+const PREC_UNARY: u8 = 12;
+const PREC_PRIMARY: u8 = 13;
+
+fn bin_op_prec(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => PREC_OR,
+        BinOp::And => PREC_AND,
+        BinOp::Equal
+        | BinOp::NotEqual
+        | BinOp::Less
+        | BinOp::Greater
+        | BinOp::LessOrEqual
+        | BinOp::GreaterOrEqual
+        | BinOp::In
+        | BinOp::NotIn => PREC_COMPARE,
+        BinOp::BitOr => PREC_BIT_OR,
+        BinOp::BitXor => PREC_BIT_XOR,
+        BinOp::BitAnd => PREC_BIT_AND,
+        BinOp::LeftShift | BinOp::RightShift => PREC_SHIFT,
+        BinOp::Add | BinOp::Subtract => PREC_ARITH,
+        BinOp::Multiply | BinOp::Percent | BinOp::Divide | BinOp::FloorDivide => PREC_PRODUCT,
+    }
+}
+
+fn expr_prec(x: &Expr) -> u8 {
+    match x {
+        Expr::Lambda(..) => PREC_TEST,
+        Expr::If(..) => PREC_IF,
+        Expr::Not(..) => PREC_NOT,
+        Expr::Op(_, op, _) => bin_op_prec(*op),
+        Expr::Minus(..) | Expr::Plus(..) | Expr::BitNot(..) => PREC_UNARY,
+        _ => PREC_PRIMARY,
+    }
+}
+
+/// Sort key for target attributes, lower first, following the conventions of `buildifier`.
+fn attribute_priority(name: &str) -> i32 {
+    match name {
+        "name" => -99,
+        "size" => -95,
+        "timeout" => -94,
+        "testonly" => -93,
+        "src" => -92,
+        "srcdir" => -91,
+        "srcs" => -90,
+        "out" => -89,
+        "outs" => -88,
+        "hdrs" => -87,
+        "headers" => -86,
+        "exported_headers" => -85,
+        "exports" => 2,
+        "runtime_deps" => 3,
+        "exported_deps" => 4,
+        "deps" => 5,
+        "visibility" => 10,
+        _ => 0,
+    }
+}
+
+/// Something which is laid out as a comma-separated sequence between brackets.
+enum Item<'a> {
+    Expr(&'a AstExpr),
+    Argument(&'a AstArgument),
+    Parameter(&'a AstParameter),
+    DictEntry(&'a AstExpr, &'a AstExpr),
+    LoadModule(&'a AstString),
+    LoadSymbol(&'a AstAssignIdent, &'a AstString),
+}
+
+impl<'a> Item<'a> {
+    fn span(&self) -> Span {
+        match self {
+            Item::Expr(x) => x.span,
+            Item::Argument(x) => x.span,
+            Item::Parameter(x) => x.span,
+            Item::DictEntry(k, v) => k.span.merge(v.span),
+            Item::LoadModule(x) => x.span,
+            Item::LoadSymbol(local, name) => local.span.merge(name.span),
+        }
+    }
+}
+
+/// A bracketed sequence of items, see [`Formatter::sequence`].
+struct Sequence<'a, 'b> {
+    open: &'static str,
+    close: &'static str,
+    items: &'b [Item<'a>],
+    /// The order to write the items in, as indices into `items`.
+    order: Vec<usize>,
+    /// Positions of the brackets in the source.
+    open_pos: Pos,
+    close_pos: Pos,
+    /// Whether the sequence spans multiple lines in the source.
+    multiline: bool,
+    /// Whether a single item needs a trailing comma.
+    is_tuple: bool,
+}
+
+impl<'a, 'b> Sequence<'a, 'b> {
+    fn new(
+        open: &'static str,
+        close: &'static str,
+        items: &'b [Item<'a>],
+        open_pos: Pos,
+        close_pos: Pos,
+    ) -> Self {
+        Self {
+            open,
+            close,
+            items,
+            order: (0..items.len()).collect(),
+            open_pos,
+            close_pos,
+            multiline: false,
+            is_tuple: false,
+        }
+    }
+}
+
+struct Formatter<'a> {
+    codemap: &'a CodeMap,
+    options: &'a FormatOptions,
+    /// All the comments in the file, with a flag for whether they have been written out.
+    comments: Vec<(Span, bool)>,
+    /// When set, everything is written on a single line, and comments are not emitted.
+    /// Used to measure whether something fits on a line.
+    flat: bool,
+    out: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(codemap: &'a CodeMap, options: &'a FormatOptions) -> Self {
+        Self {
+            codemap,
+            options,
+            comments: find_comments(codemap.source())
+                .into_iter()
+                .map(|x| (x, false))
+                .collect(),
+            flat: false,
+            out: String::new(),
+        }
+    }
+
+    fn line(&self, pos: Pos) -> usize {
+        self.codemap.find_line(pos)
+    }
+
+    fn column(&self, pos: Pos) -> u32 {
+        pos.get() - self.codemap.line_span(self.line(pos)).begin().get()
+    }
+
+    fn is_multiline(&self, begin: Pos, end: Pos) -> bool {
+        self.line(begin) != self.line(end)
+    }
+
+    /// The column the output is currently at.
+    fn out_column(&self) -> usize {
+        match self.out.rfind('\n') {
+            Some(i) => self.out.len() - i - 1,
+            None => self.out.len(),
+        }
+    }
+
+    fn write(&mut self, x: &str) {
+        self.out.push_str(x);
+    }
+
+    fn newline(&mut self, level: usize) {
+        self.out.push('\n');
+        for _ in 0..level {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn indent(&mut self, level: usize) {
+        for _ in 0..level {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn comment_text(&self, x: Span) -> &'a str {
+        self.codemap.source_span(x)
+    }
+
+    fn has_comments(&self, begin: Pos, end: Pos) -> bool {
+        self.comments
+            .iter()
+            .any(|(x, used)| !used && x.begin() >= begin && x.begin() < end)
+    }
+
+    /// Take all the comments starting in the given range which have not been written yet.
+    fn take_comments(&mut self, begin: Pos, end: Pos) -> Vec<Span> {
+        let mut res = Vec::new();
+        for (x, used) in &mut self.comments {
+            if !*used && x.begin() >= begin && x.begin() < end {
+                *used = true;
+                res.push(*x);
+            }
+        }
+        res
+    }
+
+    /// Take the comment after `after` (and before `limit`) which is on the same line as `after`.
+    fn take_trailing_comment(&mut self, after: Pos, limit: Pos) -> Option<Span> {
+        let line = self.line(after);
+        let codemap = self.codemap;
+        let (x, used) = self.comments.iter_mut().find(|(x, used)| {
+            !*used
+                && x.begin() >= after
+                && x.begin() < limit
+                && codemap.find_line(x.begin()) == line
+        })?;
+        *used = true;
+        Some(*x)
+    }
+
+    fn write_trailing_comment(&mut self, after: Pos, limit: Pos) {
+        if let Some(x) = self.take_trailing_comment(after, limit) {
+            self.write("  ");
+            self.write(self.comment_text(x));
+        }
+    }
+
+    /// Position of the first token at or after `pos`, skipping whitespace and comments.
+    fn next_token(&self, mut pos: Pos) -> Pos {
+        let source = self.codemap.source().as_bytes();
+        while (pos.get() as usize) < source.len() {
+            match source[pos.get() as usize] {
+                b' ' | b'\t' | b'\r' | b'\n' | b'\\' => pos = Pos::new(pos.get() + 1),
+                b'#' => match self.comments.iter().find(|(x, _)| x.begin() == pos) {
+                    Some((x, _)) => pos = x.end(),
+                    None => break,
+                },
+                _ => break,
+            }
+        }
+        pos
+    }
+
+    /// Position of the closing bracket of a sequence whose last item ends at `pos`.
+    fn closing_bracket(&self, pos: Pos) -> Pos {
+        let pos = self.next_token(pos);
+        if self.codemap.source().as_bytes().get(pos.get() as usize) == Some(&b',') {
+            self.next_token(Pos::new(pos.get() + 1))
+        } else {
+            pos
+        }
+    }
+
+    /// Write lines of comments, separated by a blank line from what came before if
+    /// they were in the source.
+    fn write_comment_lines(
+        &mut self,
+        comments: Vec<Span>,
+        level: usize,
+        last_line: &mut Option<usize>,
+    ) {
+        for x in comments {
+            self.blank_line_if_gap(last_line, self.line(x.begin()));
+            self.indent(level);
+            self.write(self.comment_text(x));
+            self.write("\n");
+            *last_line = Some(self.line(x.begin()));
+        }
+    }
+
+    fn blank_line_if_gap(&mut self, last_line: &mut Option<usize>, line: usize) {
+        if let Some(last) = *last_line {
+            if line > last + 1 {
+                self.write("\n");
+            }
+        }
+    }
+
+    /// The end of the last token of a statement. The span of a compound statement
+    /// extends to the dedent that closes it, so may cover comments which follow it.
+    fn stmt_end(x: &AstStmt) -> Pos {
+        match &x.node {
+            Stmt::Statements(xs) => xs.last().map_or(x.span.end(), Self::stmt_end),
+            Stmt::If(_, body) | Stmt::Def(_, _, _, body, _) => Self::stmt_end(body),
+            Stmt::For(_, over_body) => Self::stmt_end(&over_body.1),
+            Stmt::IfElse(_, then_else) => Self::stmt_end(&then_else.1),
+            _ => x.span.end(),
+        }
+    }
+
+    fn flatten<'b>(x: &'b AstStmt, res: &mut Vec<&'b AstStmt>) {
+        match &x.node {
+            Stmt::Statements(xs) => xs.iter().for_each(|x| Self::flatten(x, res)),
+            _ => res.push(x),
+        }
+    }
+
+    /// Write a block of statements at the given indentation level. Comments in the block
+    /// are those from `begin` onwards, while comments after the last statement and before
+    /// `end` belong to the block only if they are indented at least as much as its statements.
+    fn block(&mut self, x: &AstStmt, level: usize, begin: Pos, end: Pos) {
+        let mut stmts = Vec::new();
+        Self::flatten(x, &mut stmts);
+        let mut last_line = None;
+        let mut prev_end = begin;
+        for (i, x) in stmts.iter().enumerate() {
+            let comments = self.take_comments(prev_end, x.span.begin());
+            self.write_comment_lines(comments, level, &mut last_line);
+            self.blank_line_if_gap(&mut last_line, self.line(x.span.begin()));
+            let next = stmts.get(i + 1).map_or(end, |x| x.span.begin());
+            self.stmt(x, level, next);
+            prev_end = Self::stmt_end(x);
+            last_line = Some(self.line(prev_end));
+        }
+
+        let min_column = stmts.first().map(|x| self.column(x.span.begin()));
+        let mut comments = Vec::new();
+        for (x, used) in &mut self.comments {
+            if !*used && x.begin() >= prev_end && x.begin() < end {
+                let column = x.begin().get()
+                    - self
+                        .codemap
+                        .line_span(self.codemap.find_line(x.begin()))
+                        .begin()
+                        .get();
+                if level > 0 && min_column.map_or(false, |min| column < min) {
+                    break;
+                }
+                *used = true;
+                comments.push(*x);
+            }
+        }
+        self.write_comment_lines(comments, level, &mut last_line);
+    }
+
+    /// Write a statement on its own line(s), followed by a newline.
+    /// `next` is the start of whatever follows the statement.
+    fn stmt(&mut self, x: &AstStmt, level: usize, next: Pos) {
+        let start = self.out.len();
+        self.indent(level);
+        match &x.node {
+            Stmt::Break => self.write("break"),
+            Stmt::Continue => self.write("continue"),
+            Stmt::Pass => self.write("pass"),
+            Stmt::Return(None) => self.write("return"),
+            Stmt::Return(Some(e)) => {
+                self.write("return ");
+                self.expr_top(e, level);
+            }
+            Stmt::Expression(e) => match &e.node {
+                Expr::Call(f, args) if level == 0 && self.options.sort_target_attributes => {
+                    self.call(e, f, args, level, true)
+                }
+                _ => self.expr(e, level, PREC_TEST),
+            },
+            Stmt::Assign(lhs, ty_rhs) => {
+                let (ty, rhs) = &**ty_rhs;
+                self.assign_target(lhs, level, true);
+                if let Some(ty) = ty {
+                    self.write(": ");
+                    self.expr(ty, level, PREC_TEST);
+                }
+                self.write(" = ");
+                self.expr_top(rhs, level);
+            }
+            Stmt::AssignModify(lhs, op, rhs) => {
+                self.assign_target(lhs, level, true);
+                self.write(&op.to_string());
+                self.expr(rhs, level, PREC_TEST);
+            }
+            Stmt::Load(load) => self.load(load, level),
+            Stmt::Statements(_) => {
+                // Only reachable for `a; b` at the top of `format_lines`.
+                self.out.truncate(start);
+                self.block(x, level, x.span.begin(), next);
+                return;
+            }
+            Stmt::If(cond, then) => {
+                self.if_chain("if", cond, then, None, level, next);
+                self.write_leftover_comments(x, start, level);
+                return;
+            }
+            Stmt::IfElse(cond, then_else) => {
+                let (then, els) = &**then_else;
+                self.if_chain("if", cond, then, Some(els), level, next);
+                self.write_leftover_comments(x, start, level);
+                return;
+            }
+            Stmt::For(var, over_body) => {
+                let (over, body) = &**over_body;
+                self.write("for ");
+                self.assign_target(var, level, true);
+                self.write(" in ");
+                self.expr(over, level, PREC_TEST);
+                self.write(":");
+                self.suite(over.span.end(), body, level, next);
+                self.write_leftover_comments(x, start, level);
+                return;
+            }
+            Stmt::Def(name, params, ret, body, _) => {
+                self.write("def ");
+                self.write(&name.node.0);
+                let close =
+                    self.closing_bracket(params.last().map_or(name.span.end(), |p| p.span.end()));
+                let items: Vec<_> = params.iter().map(Item::Parameter).collect();
+                let mut seq = Sequence::new("(", ")", &items, name.span.end(), close);
+                seq.multiline = self.is_multiline(name.span.end(), close);
+                self.sequence(&seq, level);
+                let mut header_end = close;
+                if let Some(ret) = ret {
+                    self.write(" -> ");
+                    self.expr(ret, level, PREC_TEST);
+                    header_end = ret.span.end();
+                }
+                self.write(":");
+                self.suite(header_end, body, level, next);
+                self.write_leftover_comments(x, start, level);
+                return;
+            }
+        }
+        self.write_trailing_comment(x.span.end(), next);
+        self.write("\n");
+        self.write_leftover_comments(x, start, level);
+    }
+
+    /// Any comments inside a statement that could not be placed are written before it,
+    /// so that no comment is ever lost.
+    fn write_leftover_comments(&mut self, x: &AstStmt, start: usize, level: usize) {
+        let comments = self.take_comments(x.span.begin(), Self::stmt_end(x));
+        if !comments.is_empty() {
+            let mut lines = String::new();
+            for x in comments {
+                for _ in 0..level {
+                    lines.push_str(INDENT);
+                }
+                lines.push_str(self.comment_text(x));
+                lines.push('\n');
+            }
+            self.out.insert_str(start, &lines);
+        }
+    }
+
+    /// Write the body of a compound statement, whose header (up to the `:`) ends at `header_end`.
+    fn suite(&mut self, header_end: Pos, body: &AstStmt, level: usize, next: Pos) {
+        self.write_trailing_comment(header_end, body.span.begin());
+        self.write("\n");
+        self.block(body, level + 1, header_end, next);
+    }
+
+    fn if_chain(
+        &mut self,
+        keyword: &str,
+        cond: &AstExpr,
+        then: &AstStmt,
+        els: Option<&AstStmt>,
+        level: usize,
+        next: Pos,
+    ) {
+        self.write(keyword);
+        self.write(" ");
+        self.expr(cond, level, PREC_TEST);
+        self.write(":");
+        let els = match els {
+            None => {
+                self.suite(cond.span.end(), then, level, next);
+                return;
+            }
+            Some(els) => els,
+        };
+        // The position of the `elif` or `else` keyword.
+        let keyword = self.next_token(then.span.end());
+        self.suite(cond.span.end(), then, level, keyword);
+        let comments = self.take_comments(then.span.end(), keyword);
+        self.write_comment_lines(comments, level, &mut None);
+        self.indent(level);
+        match &els.node {
+            Stmt::If(cond, then) => self.if_chain("elif", cond, then, None, level, next),
+            Stmt::IfElse(cond, then_else) => {
+                let (then, els) = &**then_else;
+                self.if_chain("elif", cond, then, Some(els), level, next)
+            }
+            _ => {
+                self.write("else:");
+                self.suite(keyword, els, level, next);
+            }
+        }
+    }
+
+    fn load(&mut self, load: &AstLoad, level: usize) {
+        self.write("load");
+        let mut items = vec![Item::LoadModule(&load.node.module)];
+        items.extend(
+            load.node
+                .args
+                .iter()
+                .map(|(local, name)| Item::LoadSymbol(local, name)),
+        );
+        let close = Pos::new(load.span.end().get() - 1);
+        let mut seq = Sequence::new("(", ")", &items, load.span.begin(), close);
+        seq.multiline = self.is_multiline(load.span.begin(), load.span.end());
+        if self.options.sort_load_symbols {
+            seq.order[1..].sort_by(|a, b| match (&items[*a], &items[*b]) {
+                (Item::LoadSymbol(a, _), Item::LoadSymbol(b, _)) => a.node.0.cmp(&b.node.0),
+                _ => Ordering::Equal,
+            });
+        }
+        self.sequence(&seq, level);
+    }
+
+    fn assign_target(&mut self, x: &AstAssign, level: usize, top: bool) {
+        match &x.node {
+            Assign::Tuple(xs) => {
+                if !top {
+                    self.write("(");
+                }
+                for (i, x) in xs.iter().enumerate() {
+                    if i != 0 {
+                        self.write(", ");
+                    }
+                    self.assign_target(x, level, false);
+                }
+                if xs.len() == 1 {
+                    self.write(",");
+                }
+                if !top {
+                    self.write(")");
+                }
+            }
+            Assign::ArrayIndirection(array_index) => {
+                let (array, index) = &**array_index;
+                self.expr(array, level, PREC_PRIMARY);
+                self.write("[");
+                self.expr_top(index, level);
+                self.write("]");
+            }
+            Assign::Dot(x, field) => {
+                self.expr(x, level, PREC_PRIMARY);
+                self.write(".");
+                self.write(&field.node);
+            }
+            Assign::Identifier(x) => self.write(&x.node.0),
+        }
+    }
+
+    /// An expression in a position where a tuple does not need brackets.
+    fn expr_top(&mut self, x: &AstExpr, level: usize) {
+        match &x.node {
+            Expr::Tuple(xs)
+                if xs.len() > 1
+                    && !self.is_multiline(xs[0].span.begin(), xs[xs.len() - 1].span.end()) =>
+            {
+                for (i, x) in xs.iter().enumerate() {
+                    if i != 0 {
+                        self.write(", ");
+                    }
+                    self.expr(x, level, PREC_TEST);
+                }
+            }
+            _ => self.expr(x, level, PREC_TEST),
+        }
+    }
+
+    fn expr(&mut self, x: &AstExpr, level: usize, prec: u8) {
+        if expr_prec(&x.node) < prec {
+            self.write("(");
+            self.expr_inner(x, level);
+            self.write(")");
+        } else {
+            self.expr_inner(x, level);
+        }
+    }
+
+    fn expr_inner(&mut self, x: &AstExpr, level: usize) {
+        match &x.node {
+            Expr::Tuple(xs) => {
+                let items: Vec<_> = xs.iter().map(Item::Expr).collect();
+                let mut seq = Sequence::new("(", ")", &items, x.span.begin(), x.span.end());
+                if let (Some(first), Some(last)) = (xs.first(), xs.last()) {
+                    seq.multiline = self.is_multiline(first.span.begin(), last.span.end());
+                    seq.close_pos = self.closing_bracket(last.span.end());
+                }
+                seq.is_tuple = true;
+                self.sequence(&seq, level);
+            }
+            Expr::Dot(x, field) => {
+                self.expr(x, level, PREC_PRIMARY);
+                self.write(".");
+                self.write(&field.node);
+            }
+            Expr::Call(f, args) => self.call(x, f, args, level, false),
+            Expr::ArrayIndirection(array_index) => {
+                let (array, index) = &**array_index;
+                self.expr(array, level, PREC_PRIMARY);
+                self.write("[");
+                self.expr_top(index, level);
+                self.write("]");
+            }
+            Expr::Slice(x, start, stop, stride) => {
+                self.expr(x, level, PREC_PRIMARY);
+                self.write("[");
+                if let Some(start) = start {
+                    self.expr(start, level, PREC_TEST);
+                }
+                self.write(":");
+                if let Some(stop) = stop {
+                    self.expr(stop, level, PREC_TEST);
+                }
+                if let Some(stride) = stride {
+                    self.write(":");
+                    self.expr(stride, level, PREC_TEST);
+                }
+                self.write("]");
+            }
+            Expr::Identifier(x, _) => self.write(&x.node),
+            Expr::Lambda(params, body, _) => {
+                self.write("lambda");
+                for (i, p) in params.iter().enumerate() {
+                    self.write(if i == 0 { " " } else { ", " });
+                    self.parameter(p, level);
+                }
+                self.write(": ");
+                self.expr(body, level, PREC_TEST);
+            }
+            Expr::Literal(_) => self.write(self.codemap.source_span(x.span)),
+            Expr::Not(x) => {
+                self.write("not ");
+                self.expr(x, level, PREC_NOT);
+            }
+            Expr::Minus(x) => {
+                self.write("-");
+                self.expr(x, level, PREC_UNARY);
+            }
+            Expr::Plus(x) => {
+                self.write("+");
+                self.expr(x, level, PREC_UNARY);
+            }
+            Expr::BitNot(x) => {
+                self.write("~");
+                self.expr(x, level, PREC_UNARY);
+            }
+            Expr::Op(lhs, op, rhs) => {
+                let prec = bin_op_prec(*op);
+                let lhs_prec = if prec == PREC_COMPARE { prec + 1 } else { prec };
+                self.expr(lhs, level, lhs_prec);
+                self.write(&op.to_string());
+                self.expr(rhs, level, prec + 1);
+            }
+            Expr::If(cond_then_else) => {
+                let (cond, then, els) = &**cond_then_else;
+                self.expr(then, level, PREC_OR);
+                self.write(" if ");
+                self.expr(cond, level, PREC_OR);
+                self.write(" else ");
+                self.expr(els, level, PREC_TEST);
+            }
+            Expr::List(xs) => {
+                let items: Vec<_> = xs.iter().map(Item::Expr).collect();
+                self.bracketed("[", "]", x.span, &items, level);
+            }
+            Expr::Dict(xs) => {
+                let items: Vec<_> = xs.iter().map(|(k, v)| Item::DictEntry(k, v)).collect();
+                self.bracketed("{", "}", x.span, &items, level);
+            }
+            Expr::ListComprehension(x, for_, clauses) => {
+                self.write("[");
+                self.expr(x, level, PREC_TEST);
+                self.clauses(for_, clauses, level);
+                self.write("]");
+            }
+            Expr::DictComprehension(k_v, for_, clauses) => {
+                let (k, v) = &**k_v;
+                self.write("{");
+                self.expr(k, level, PREC_TEST);
+                self.write(": ");
+                self.expr(v, level, PREC_TEST);
+                self.clauses(for_, clauses, level);
+                self.write("}");
+            }
+        }
+    }
+
+    fn for_clause(&mut self, x: &ForClause, level: usize) {
+        self.write(" for ");
+        self.assign_target(&x.var, level, true);
+        self.write(" in ");
+        self.expr(&x.over, level, PREC_OR);
+    }
+
+    fn clauses(&mut self, for_: &ForClause, clauses: &[Clause], level: usize) {
+        self.for_clause(for_, level);
+        for x in clauses {
+            match x {
+                Clause::For(x) => self.for_clause(x, level),
+                Clause::If(x) => {
+                    self.write(" if ");
+                    self.expr(x, level, PREC_OR);
+                }
+            }
+        }
+    }
+
+    /// A list or dict literal, whose span includes the brackets.
+    fn bracketed(
+        &mut self,
+        open: &'static str,
+        close: &'static str,
+        span: Span,
+        items: &[Item],
+        level: usize,
+    ) {
+        let close_pos = Pos::new(span.end().get() - 1);
+        let mut seq = Sequence::new(open, close, items, span.begin(), close_pos);
+        seq.multiline = self.is_multiline(span.begin(), span.end());
+        self.sequence(&seq, level);
+    }
+
+    fn call(
+        &mut self,
+        x: &AstExpr,
+        f: &AstExpr,
+        args: &[AstArgument],
+        level: usize,
+        sort_attributes: bool,
+    ) {
+        self.expr(f, level, PREC_PRIMARY);
+        let close = Pos::new(x.span.end().get() - 1);
+
+        // A single list or dict argument hugs the brackets, e.g. `glob([...])`.
+        if let [arg] = args {
+            if let Argument::Positional(e) = &arg.node {
+                if matches!(
+                    e.node,
+                    Expr::List(_)
+                        | Expr::Dict(_)
+                        | Expr::ListComprehension(..)
+                        | Expr::DictComprehension(..)
+                ) && (self.flat
+                    || (!self.has_comments(f.span.end(), e.span.begin())
+                        && !self.has_comments(e.span.end(), close)))
+                {
+                    self.write("(");
+                    self.expr(e, level, PREC_TEST);
+                    self.write(")");
+                    return;
+                }
+            }
+        }
+
+        let items: Vec<_> = args.iter().map(Item::Argument).collect();
+        let mut seq = Sequence::new("(", ")", &items, f.span.end(), close);
+        seq.multiline = self.is_multiline(f.span.end(), x.span.end());
+        if sort_attributes && args.iter().all(|x| matches!(x.node, Argument::Named(..))) {
+            let name = |i: usize| match &args[i].node {
+                Argument::Named(name, _) => name.node.as_str(),
+                _ => "",
+            };
+            seq.order.sort_by_key(|i| attribute_priority(name(*i)));
+        }
+        self.sequence(&seq, level);
+    }
+
+    /// Write a comma-separated sequence of items in brackets, either on a single line,
+    /// or with one item per line and a trailing comma.
+    fn sequence(&mut self, seq: &Sequence, level: usize) {
+        let Sequence {
+            open,
+            close,
+            items,
+            order,
+            open_pos,
+            close_pos,
+            ..
+        } = seq;
+        let multiline = !self.flat
+            && !items.is_empty()
+            && (seq.multiline
+                || self.has_comments(*open_pos, *close_pos)
+                || self.too_long(seq, level));
+        if !multiline {
+            self.write(open);
+            for (i, x) in order.iter().enumerate() {
+                if i != 0 {
+                    self.write(", ");
+                }
+                self.item(&items[*x], level);
+            }
+            if seq.is_tuple && items.len() == 1 {
+                self.write(",");
+            }
+            self.write(close);
+            return;
+        }
+
+        // Gather the comments for each item in source order, before writing them in
+        // the requested order.
+        let mut leading = Vec::with_capacity(items.len());
+        let mut trailing = Vec::with_capacity(items.len());
+        let mut prev_end = *open_pos;
+        for (i, x) in items.iter().enumerate() {
+            let span = x.span();
+            leading.push(self.take_comments(prev_end, span.begin()));
+            let next = items.get(i + 1).map_or(*close_pos, |x| x.span().begin());
+            trailing.push(self.take_trailing_comment(span.end(), next));
+            prev_end = span.end();
+        }
+        let final_comments = self.take_comments(prev_end, *close_pos);
+        let sorted = order.iter().enumerate().any(|(i, x)| i != *x);
+
+        self.write(open);
+        let mut last_line = None;
+        for x in order {
+            let item = &items[*x];
+            if sorted {
+                last_line = None;
+            }
+            for c in &leading[*x] {
+                self.out.push('\n');
+                self.blank_line_if_gap(&mut last_line, self.line(c.begin()));
+                self.indent(level + 1);
+                self.write(self.comment_text(*c));
+                last_line = Some(self.line(c.begin()));
+            }
+            self.out.push('\n');
+            self.blank_line_if_gap(&mut last_line, self.line(item.span().begin()));
+            self.indent(level + 1);
+            self.item(item, level + 1);
+            self.write(",");
+            if let Some(c) = trailing[*x] {
+                self.write("  ");
+                self.write(self.comment_text(c));
+            }
+            last_line = Some(self.line(item.span().end()));
+        }
+        for c in final_comments {
+            self.out.push('\n');
+            self.blank_line_if_gap(&mut last_line, self.line(c.begin()));
+            self.indent(level + 1);
+            self.write(self.comment_text(c));
+            last_line = Some(self.line(c.begin()));
+        }
+        self.newline(level);
+        self.write(close);
+    }
+
+    /// Would the sequence overflow the line if written on a single line.
+    fn too_long(&mut self, seq: &Sequence, level: usize) -> bool {
+        let start = self.out.len();
+        let column = self.out_column();
+        self.flat = true;
+        self.sequence(seq, level);
+        self.flat = false;
+        let width = self.out[start..].lines().next().map_or(0, |x| x.len());
+        self.out.truncate(start);
+        column + width > MAX_LINE_LENGTH
+    }
+
+    fn item(&mut self, x: &Item, level: usize) {
+        match x {
+            Item::Expr(x) => self.expr(x, level, PREC_TEST),
+            Item::Argument(x) => match &x.node {
+                Argument::Positional(x) => self.expr(x, level, PREC_TEST),
+                Argument::Named(name, x) => {
+                    self.write(&name.node);
+                    self.write(" = ");
+                    self.expr(x, level, PREC_TEST);
+                }
+                Argument::Args(x) => {
+                    self.write("*");
+                    self.expr(x, level, PREC_TEST);
+                }
+                Argument::KwArgs(x) => {
+                    self.write("**");
+                    self.expr(x, level, PREC_TEST);
+                }
+            },
+            Item::Parameter(x) => self.parameter(x, level),
+            Item::DictEntry(k, v) => {
+                self.expr(k, level, PREC_TEST);
+                self.write(": ");
+                self.expr(v, level, PREC_TEST);
+            }
+            Item::LoadModule(x) => self.write(self.codemap.source_span(x.span)),
+            Item::LoadSymbol(local, name) => {
+                if local.span != name.span {
+                    self.write(&local.node.0);
+                    self.write(" = ");
+                }
+                self.write(self.codemap.source_span(name.span));
+            }
+        }
+    }
+
+    fn parameter(&mut self, x: &AstParameter, level: usize) {
+        let (prefix, name, ty, default) = match &x.node {
+            Parameter::Normal(name, ty) => ("", name, ty, None),
+            Parameter::WithDefaultValue(name, ty, default) => ("", name, ty, Some(default)),
+            Parameter::NoArgs => {
+                self.write("*");
+                return;
+            }
+            Parameter::Args(name, ty) => ("*", name, ty, None),
+            Parameter::KwArgs(name, ty) => ("**", name, ty, None),
+        };
+        self.write(prefix);
+        self.write(&name.node.0);
+        if let Some(ty) = ty {
+            self.write(": ");
+            self.expr(ty, level, PREC_TEST);
+        }
+        if let Some(default) = default {
+            self.write(" = ");
+            self.expr(default, level, PREC_TEST);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn format_with(program: &str, options: &FormatOptions) -> String {
+        let module = AstModule::parse(
+            "test.bzl",
+            dedent(program).trim_start().to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let res = module.format(options);
+        let again = AstModule::parse("test.bzl", res.clone(), &Dialect::Extended)
+            .unwrap()
+            .format(options);
+        assert_eq!(res, again, "Formatting should be idempotent");
+        res
+    }
+
+    fn format(program: &str) -> String {
+        format_with(program, &FormatOptions::default())
+    }
+
+    fn expected(program: &str) -> String {
+        dedent(program).trim_start().to_owned()
+    }
+
+    #[test]
+    fn test_format_simple() {
+        assert_eq!(
+            format("x=1+2*3\ny =(1+2)*3\nz=  not (a and b)  or c\n"),
+            "x = 1 + 2 * 3\ny = (1 + 2) * 3\nz = not (a and b) or c\n"
+        );
+        assert_eq!(
+            format("a,b=1,2\nc = 1,\nd = x[1:2]\n"),
+            "a, b = 1, 2\nc = (1,)\nd = x[1:2]\n"
+        );
+        assert_eq!(
+            format("x = 'single' + r\"raw\\n\"\n"),
+            "x = 'single' + r\"raw\\n\"\n"
+        );
+        assert_eq!(format("x -= 1\n"), "x -= 1\n");
+    }
+
+    #[test]
+    fn test_format_def() {
+        assert_eq!(
+            format(
+                r#"
+                def  f(a,b:int=1,*args,**kwargs)->str:
+                  if a: return b
+                  elif b:
+                      pass
+                  else:
+                    for x,y in a: print(x)
+                  return lambda q: q+1
+                "#
+            ),
+            expected(
+                r#"
+                def f(a, b: int = 1, *args, **kwargs) -> str:
+                    if a:
+                        return b
+                    elif b:
+                        pass
+                    else:
+                        for x, y in a:
+                            print(x)
+                    return lambda q: q + 1
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let program = r#"
+            # Header comment.
+
+            load("//a:b.bzl", "c")  # trailing
+
+            def f():
+                # Leading.
+                x = [
+                    1,  # one
+                    # two
+                    2,
+                ]
+                # At the end of the body.
+
+            # Before y.
+            y = {"a": 1}
+            "#;
+        assert_eq!(format(program), expected(program));
+    }
+
+    #[test]
+    fn test_format_multiline() {
+        assert_eq!(
+            format(
+                r#"
+                cxx_library(name = "foo", srcs = glob(["*.cpp",
+                  "*.c"]), deps = ["//bar:baz"])
+                "#
+            ),
+            expected(
+                r#"
+                cxx_library(
+                    name = "foo",
+                    srcs = glob([
+                        "*.cpp",
+                        "*.c",
+                    ]),
+                    deps = ["//bar:baz"],
+                )
+                "#
+            )
+        );
+        let long = format!("x = [{}]\n", vec!["\"abcdefghij\""; 10].join(", "));
+        assert_eq!(
+            format(&long),
+            format!("x = [\n{}]\n", "    \"abcdefghij\",\n".repeat(10))
+        );
+    }
+
+    #[test]
+    fn test_format_opinionated() {
+        let program = r#"
+            load(":defs.bzl", "z", "a", b = "c")
+            cxx_library(
+                deps = [],
+                visibility = ["PUBLIC"],
+                srcs = [],
+                name = "foo",  # the name
+            )
+            "#;
+        assert_eq!(
+            format_with(program, &FormatOptions::opinionated()),
+            expected(
+                r#"
+                load(":defs.bzl", "a", b = "c", "z")
+                cxx_library(
+                    name = "foo",  # the name
+                    srcs = [],
+                    deps = [],
+                    visibility = ["PUBLIC"],
+                )
+                "#
+            )
+        );
+        assert_eq!(format(program), expected(program));
+    }
+
+    #[test]
+    fn test_format_lines() {
+        let module = AstModule::parse(
+            "test.bzl",
+            "x=1\n# comment\ny=[1,\n2]  # y\nz=3\n".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let edits = module.format_lines(&FormatOptions::default(), 2, 2);
+        assert_eq!(1, edits.len());
+        assert_eq!("3:1-4:8", edits[0].0.to_string());
+        assert_eq!("y = [\n    1,\n    2,\n]  # y", edits[0].1);
+    }
+}
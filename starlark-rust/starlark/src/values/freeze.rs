@@ -21,6 +21,7 @@ use std::marker::PhantomData;
 
 use gazebo::prelude::*;
 use starlark_map::small_map::SmallMap;
+use starlark_map::small_set::SmallSet;
 use starlark_map::Hashed;
 
 use crate::values::Freezer;
@@ -200,6 +201,24 @@ where
     }
 }
 
+impl<T> Freeze for SmallSet<T>
+where
+    T: Freeze,
+    T::Frozen: Eq,
+{
+    type Frozen = SmallSet<T::Frozen>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<SmallSet<T::Frozen>> {
+        let mut new = SmallSet::with_capacity(self.len());
+        for x in self.into_iter_hashed() {
+            let hash = x.hash();
+            let x = x.into_key().freeze(freezer)?;
+            new.insert_hashed_unique_unchecked(Hashed::new_unchecked(hash, x));
+        }
+        Ok(new)
+    }
+}
+
 impl<'v> Freeze for Value<'v> {
     type Frozen = FrozenValue;
 
@@ -35,6 +35,7 @@ use std::sync::Mutex;
 use hashbrown::raw::RawTable;
 
 use crate::collections::SmallMap;
+use crate::collections::SmallSet;
 use crate::values::FrozenValue;
 use crate::values::Tracer;
 use crate::values::Value;
@@ -96,6 +97,17 @@ unsafe impl<'v, K: Trace<'v>, V: Trace<'v>> Trace<'v> for SmallMap<K, V> {
     }
 }
 
+unsafe impl<'v, T: Trace<'v>> Trace<'v> for SmallSet<T> {
+    fn trace(&mut self, tracer: &Tracer<'v>) {
+        self.iter().for_each(|x| {
+            // As for `SmallMap` keys, we promise the traced value is morally the same.
+            #[allow(clippy::cast_ref_to_mut)]
+            let x_mut = unsafe { &mut *(x as *const T as *mut T) };
+            x_mut.trace(tracer);
+        })
+    }
+}
+
 unsafe impl<'v, T: Trace<'v>> Trace<'v> for Option<T> {
     fn trace(&mut self, tracer: &Tracer<'v>) {
         if let Some(x) = self {
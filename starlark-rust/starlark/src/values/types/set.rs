@@ -0,0 +1,740 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The set type, a mutable collection of unique hashable values, which iterates in insertion order.
+//!
+//! Created in Starlark using the `set()` function, which optionally accepts an iterable.
+//! The same `set` value is also used in type annotations, where `set[t]` matches a set
+//! whose elements all match `t`.
+//!
+//! ```
+//! # starlark::assert::is_true(r#"
+//! s = set([1, 2])
+//! s.add(3)
+//! def f(x: set[int.type]) -> int.type:
+//!     return len(x)
+//! f(s) == 3 and s | set([4]) == set([1, 2, 3, 4])
+//! # "#);
+//! ```
+
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::hash::Hasher;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+use allocative::Allocative;
+use gazebo::any::ProvidesStaticType;
+use gazebo::cell::ARef;
+use gazebo::coerce::coerce;
+use gazebo::coerce::Coerce;
+use gazebo::display::display_container;
+use serde::Serialize;
+
+use crate as starlark;
+use crate::collections::Hashed;
+use crate::collections::SmallSet;
+use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::values::error::ControlError;
+use crate::values::error::ValueError;
+use crate::values::function::FUNCTION_TYPE;
+use crate::values::iter::ARefIterator;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::AllocFrozenValue;
+use crate::values::AllocValue;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenStringValue;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(
+    Clone,
+    Default,
+    Trace,
+    Debug,
+    ProvidesStaticType,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs_attrs(builtin = "standard")]
+struct SetGen<T>(T);
+
+impl FrozenSet {
+    // The doc macros assume that FrozenSet is an alias for SetGen, which isn't true,
+    // so do some internal reexposing so everything works.
+    #[doc(hidden)]
+    pub fn __generated_documentation() -> Option<starlark::values::docs::Doc> {
+        SetGen::<FrozenSet>::__generated_documentation()
+    }
+}
+
+impl<'v, T: SetLike<'v>> Display for SetGen<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display_container(f, "set([", "])", self.0.content().iter())
+    }
+}
+
+impl<'v> Display for Set<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display_container(f, "set([", "])", self.iter())
+    }
+}
+
+/// Define the set type. See [`Set`] and [`FrozenSet`] as the two possible representations.
+#[derive(Clone, Default, Trace, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub struct Set<'v> {
+    /// The data stored by the set. The values must all be hashable.
+    content: SmallSet<Value<'v>>,
+}
+
+impl<'v> StarlarkTypeRepr for Set<'v> {
+    fn starlark_type_repr() -> String {
+        format!("set[{}]", Value::<'v>::starlark_type_repr())
+    }
+}
+
+/// Define the set type. See [`Set`] and [`FrozenSet`] as the two possible representations.
+#[derive(Clone, Default, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub struct FrozenSet {
+    /// The data stored by the set. The values must all be hashable.
+    content: SmallSet<FrozenValue>,
+}
+
+unsafe impl<'v> Coerce<Set<'v>> for FrozenSet {}
+
+impl<'v> AllocValue<'v> for Set<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex(SetGen(RefCell::new(self)))
+    }
+}
+
+impl AllocFrozenValue for FrozenSet {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc_simple(SetGen(self))
+    }
+}
+
+/// Borrowed `Set`.
+pub struct SetRef<'v> {
+    aref: ARef<'v, Set<'v>>,
+}
+
+/// Mutably borrowed `Set`.
+pub struct SetMut<'v> {
+    aref: RefMut<'v, Set<'v>>,
+}
+
+impl<'v> Deref for SetRef<'v> {
+    type Target = Set<'v>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.aref
+    }
+}
+
+impl<'v> Deref for SetMut<'v> {
+    type Target = Set<'v>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.aref
+    }
+}
+
+impl<'v> DerefMut for SetMut<'v> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.aref
+    }
+}
+
+impl<'v> Set<'v> {
+    /// The result of calling `type()` on sets.
+    pub const TYPE: &'static str = "set";
+
+    /// Set type string as Starlark frozen string value.
+    pub fn get_type_value_static() -> FrozenStringValue {
+        SetGen::<FrozenSet>::get_type_value_static()
+    }
+
+    /// Create a new [`Set`].
+    pub fn new(content: SmallSet<Value<'v>>) -> Self {
+        Self { content }
+    }
+
+    /// Downcast the value to a set.
+    pub fn from_value(x: Value<'v>) -> Option<SetRef<'v>> {
+        if x.unpack_frozen().is_some() {
+            x.downcast_ref::<SetGen<FrozenSet>>().map(|x| SetRef {
+                aref: ARef::new_ptr(coerce(&x.0)),
+            })
+        } else {
+            let ptr = x.downcast_ref::<SetGen<RefCell<Set<'v>>>>()?;
+            Some(SetRef {
+                aref: ARef::new_ref(ptr.0.borrow()),
+            })
+        }
+    }
+
+    /// Downcast the value to a mutable set reference.
+    #[inline]
+    pub fn from_value_mut(x: Value<'v>) -> anyhow::Result<SetMut> {
+        #[derive(thiserror::Error, Debug)]
+        #[error("Value is not set, value type: `{0}`")]
+        struct NotSetError(&'static str);
+
+        #[cold]
+        #[inline(never)]
+        fn error<'v>(x: Value<'v>) -> anyhow::Error {
+            if x.downcast_ref::<SetGen<FrozenSet>>().is_some() {
+                ValueError::CannotMutateImmutableValue.into()
+            } else {
+                NotSetError(x.get_type()).into()
+            }
+        }
+
+        let ptr = x.downcast_ref::<SetGen<RefCell<Set<'v>>>>();
+        match ptr {
+            None => Err(error(x)),
+            Some(ptr) => match ptr.0.try_borrow_mut() {
+                Ok(x) => Ok(SetMut { aref: x }),
+                Err(_) => Err(ValueError::MutationDuringIteration.into()),
+            },
+        }
+    }
+
+    /// Collect the elements of an iterable into a new set.
+    pub(crate) fn from_iterable(xs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Set<'v>> {
+        if let Some(xs) = Set::from_value(xs) {
+            return Ok(xs.clone());
+        }
+        xs.with_iterator(heap, |it| -> anyhow::Result<_> {
+            let mut content = SmallSet::with_capacity(it.size_hint().0);
+            for x in it {
+                content.insert_hashed(x.get_hashed()?);
+            }
+            Ok(Set::new(content))
+        })?
+    }
+
+    /// Number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Iterate through the values in the set.
+    pub fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = Value<'v>> + 'a {
+        self.content.iter().copied()
+    }
+
+    /// Iterate through the values in the set, but retaining their hashes.
+    pub fn iter_hashed<'a>(&'a self) -> impl ExactSizeIterator<Item = Hashed<Value<'v>>> + 'a
+    where
+        'v: 'a,
+    {
+        self.content.iter_hashed().map(|x| x.copied())
+    }
+
+    /// Does the set contain the value? Will be [`Err`] if the value is not hashable.
+    pub fn contains(&self, value: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.contains_hashed(value.get_hashed()?))
+    }
+
+    /// Does the set contain the given prehashed value?
+    pub fn contains_hashed(&self, value: Hashed<Value<'v>>) -> bool {
+        self.content.contains_hashed_by_value(value)
+    }
+
+    /// Reserve capacity to insert `additional` elements without reallocating.
+    pub fn reserve(&mut self, additional: usize) {
+        self.content.reserve(additional);
+    }
+
+    /// Insert a value into the set.
+    ///
+    /// Returns `true` iff the value was not already present.
+    pub fn insert_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.insert_hashed(value)
+    }
+
+    /// Remove a value from the set.
+    ///
+    /// Returns `true` iff the value was present.
+    pub fn remove_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.remove_hashed(value.borrow())
+    }
+
+    /// Remove all elements from the set.
+    pub fn clear(&mut self) {
+        self.content.clear();
+    }
+
+    /// Is every element of this set also in `other`?
+    pub fn is_subset(&self, other: &Set<'v>) -> bool {
+        self.len() <= other.len() && self.iter_hashed().all(|x| other.contains_hashed(x))
+    }
+
+    /// Elements in either this set or `other`, this set's elements first.
+    pub fn union(&self, other: &Set<'v>) -> Set<'v> {
+        let mut content = self.content.clone();
+        for x in other.iter_hashed() {
+            content.insert_hashed(x);
+        }
+        Set::new(content)
+    }
+
+    /// Elements in both this set and `other`, in the order of this set.
+    pub fn intersection(&self, other: &Set<'v>) -> Set<'v> {
+        Set::new(
+            self.iter_hashed()
+                .filter(|x| other.contains_hashed(*x))
+                .collect_set(),
+        )
+    }
+
+    /// Elements in this set but not in `other`, in the order of this set.
+    pub fn difference(&self, other: &Set<'v>) -> Set<'v> {
+        Set::new(
+            self.iter_hashed()
+                .filter(|x| !other.contains_hashed(*x))
+                .collect_set(),
+        )
+    }
+
+    /// Elements in exactly one of this set and `other`, this set's elements first.
+    pub fn symmetric_difference(&self, other: &Set<'v>) -> Set<'v> {
+        let mut res = self.difference(other);
+        for x in other.iter_hashed() {
+            if !self.contains_hashed(x) {
+                res.insert_hashed(x);
+            }
+        }
+        res
+    }
+}
+
+/// Collect prehashed values, known to be unique, into a [`SmallSet`].
+trait CollectSet<'v> {
+    fn collect_set(self) -> SmallSet<Value<'v>>;
+}
+
+impl<'v, I: Iterator<Item = Hashed<Value<'v>>>> CollectSet<'v> for I {
+    fn collect_set(self) -> SmallSet<Value<'v>> {
+        let mut res = SmallSet::with_capacity(self.size_hint().0);
+        for x in self {
+            res.insert_hashed_unique_unchecked(x);
+        }
+        res
+    }
+}
+
+impl<'v> StarlarkTypeRepr for SetRef<'v> {
+    fn starlark_type_repr() -> String {
+        Set::<'v>::starlark_type_repr()
+    }
+}
+
+impl<'v> UnpackValue<'v> for SetRef<'v> {
+    fn expected() -> String {
+        "set".to_owned()
+    }
+
+    fn unpack_value(value: Value<'v>) -> Option<SetRef<'v>> {
+        Set::from_value(value)
+    }
+}
+
+impl FrozenSet {
+    /// Obtain the [`FrozenSet`] pointed at by a [`FrozenValue`].
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    // We need a lifetime because FrozenValue doesn't contain the right lifetime
+    pub fn from_frozen_value(x: &FrozenValue) -> Option<&FrozenSet> {
+        x.downcast_ref::<SetGen<FrozenSet>>().map(|x| &x.0)
+    }
+
+    /// Iterate through the values in the set.
+    pub fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = FrozenValue> + 'a {
+        self.content.iter().copied()
+    }
+
+    /// Does the set contain the value? Will be [`Err`] if the value is not hashable.
+    pub fn contains<'v>(&self, value: Value<'v>) -> anyhow::Result<bool> {
+        let content: &SmallSet<Value<'v>> = coerce(&self.content);
+        Ok(content.contains_hashed_by_value(value.get_hashed()?))
+    }
+}
+
+impl<'v> Freeze for SetGen<RefCell<Set<'v>>> {
+    type Frozen = SetGen<FrozenSet>;
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let content = self.0.into_inner().content.freeze(freezer)?;
+        Ok(SetGen(FrozenSet { content }))
+    }
+}
+
+trait SetLike<'v>: Debug + Allocative {
+    /// Sets are only hashable once frozen, as the hash must not change.
+    const FROZEN: bool;
+
+    fn content(&self) -> ARef<SmallSet<Value<'v>>>;
+}
+
+impl<'v> SetLike<'v> for RefCell<Set<'v>> {
+    const FROZEN: bool = false;
+
+    fn content(&self) -> ARef<SmallSet<Value<'v>>> {
+        ARef::new_ref(Ref::map(self.borrow(), |x| &x.content))
+    }
+}
+
+impl<'v> SetLike<'v> for FrozenSet {
+    const FROZEN: bool = true;
+
+    fn content(&self) -> ARef<SmallSet<Value<'v>>> {
+        ARef::new_ptr(coerce(&self.content))
+    }
+}
+
+pub(crate) fn set_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::set::set_methods)
+}
+
+impl<'v, T: SetLike<'v> + 'v> SetGen<T> {
+    /// Apply a binary set operation, requiring the right hand side to be a set.
+    fn binop(
+        &self,
+        op: &str,
+        rhs: Value<'v>,
+        heap: &'v Heap,
+        f: impl FnOnce(&Set<'v>, &Set<'v>) -> Set<'v>,
+    ) -> anyhow::Result<Value<'v>> {
+        let rhs =
+            Set::from_value(rhs).map_or_else(|| ValueError::unsupported_with(self, op, rhs), Ok)?;
+        let lhs = Set::new(self.0.content().clone());
+        Ok(heap.alloc(f(&lhs, &*rhs)))
+    }
+}
+
+impl<'v, T: SetLike<'v> + 'v> StarlarkValue<'v> for SetGen<T>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!(Set::TYPE);
+
+    fn get_methods() -> Option<&'static Methods> {
+        set_methods()
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        r.push_str("set([");
+        for (i, x) in self.0.content().iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            x.collect_repr(r);
+        }
+        r.push_str("])");
+    }
+
+    fn collect_repr_cycle(&self, collector: &mut String) {
+        collector.push_str("set([...])");
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.content().is_empty()
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        if !T::FROZEN {
+            return Err(ControlError::NotHashableValue(Set::TYPE.to_owned()).into());
+        }
+        // Equality ignores order, so the hash must too.
+        let mut hash = 0u32;
+        for x in self.0.content().iter_hashed() {
+            hash = hash.wrapping_add(x.hash().get());
+        }
+        hasher.write_u32(hash);
+        Ok(())
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match Set::from_value(other) {
+            None => Ok(false),
+            Some(other) => {
+                let content = self.0.content();
+                Ok(content.len() == other.len()
+                    && content
+                        .iter_hashed()
+                        .all(|x| other.contains_hashed(x.copied())))
+            }
+        }
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.content().len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self
+            .0
+            .content()
+            .contains_hashed_by_value(other.get_hashed()?))
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        _heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        Ok(Box::new(ARefIterator::new(self.0.content(), |x| {
+            x.iter().copied()
+        })))
+    }
+
+    fn with_iterator(
+        &self,
+        _heap: &'v Heap,
+        f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        f(&mut self.0.content().iter().copied())
+    }
+
+    fn bit_or(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("|", rhs, heap, Set::union)
+    }
+
+    fn bit_and(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("&", rhs, heap, Set::intersection)
+    }
+
+    fn bit_xor(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("^", rhs, heap, Set::symmetric_difference)
+    }
+
+    fn sub(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("-", rhs, heap, Set::difference)
+    }
+}
+
+impl<'v, T: SetLike<'v>> Serialize for SetGen<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.content().iter())
+    }
+}
+
+/// The global `set` value. Calling it constructs a [`Set`], and indexing it as `set[t]`
+/// produces a type annotation matching sets whose elements all match `t`.
+#[derive(Debug, ProvidesStaticType, NoSerialize, Allocative)]
+pub(crate) struct SetConstructor;
+
+starlark_simple_value!(SetConstructor);
+
+impl Display for SetConstructor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "set")
+    }
+}
+
+impl<'v> StarlarkValue<'v> for SetConstructor {
+    starlark_type!(FUNCTION_TYPE);
+
+    fn invoke(
+        &self,
+        _me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Value<'v>> {
+        args.no_named_args()?;
+        let heap = eval.heap();
+        let set = match args.optional1(heap)? {
+            None => Set::default(),
+            Some(xs) => Set::from_iterable(xs, heap)?,
+        };
+        Ok(heap.alloc(set))
+    }
+
+    fn at(&self, index: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        // Allow `set[str]` as well as `set[str.type]`.
+        let element = if index.get_type() == FUNCTION_TYPE {
+            index.get_attr("type", heap)?.unwrap_or(index)
+        } else {
+            index
+        };
+        Ok(heap.alloc(SetOfType { element }))
+    }
+
+    fn get_attr(&self, attribute: &str, _heap: &'v Heap) -> Option<Value<'v>> {
+        if attribute == "type" {
+            Some(Set::get_type_value_static().to_value())
+        } else {
+            None
+        }
+    }
+
+    fn has_attr(&self, attribute: &str, _heap: &'v Heap) -> bool {
+        attribute == "type"
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        vec!["type".to_owned()]
+    }
+}
+
+/// The result of `set[t]`, a type annotation for sets whose elements all match `t`.
+#[derive(
+    Clone,
+    Debug,
+    Trace,
+    Freeze,
+    Coerce,
+    NoSerialize,
+    ProvidesStaticType,
+    Allocative
+)]
+#[repr(C)]
+pub(crate) struct SetOfTypeGen<V> {
+    pub(crate) element: V,
+}
+
+starlark_complex_value!(pub(crate) SetOfType);
+
+impl<'v, V: ValueLike<'v>> Display for SetOfTypeGen<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "set[{}]", self.element.to_value().to_repr())
+    }
+}
+
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for SetOfTypeGen<V>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!("set_type");
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        self.element.write_hash(hasher)
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match SetOfType::from_value(other) {
+            None => Ok(false),
+            Some(other) => self.element.to_value().equals(other.element),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_set() {
+        assert::all_true(
+            r#"
+set() == set([])
+set([1, 2, 1]) == set([2, 1])
+len(set([1, 2, 1])) == 2
+list(set([3, 1, 3, 2])) == [3, 1, 2]
+2 in set([1, 2])
+3 not in set([1, 2])
+not set()
+repr(set(["a", 1])) == 'set(["a", 1])'
+type(set()) == "set"
+set.type == "set"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_set_operators() {
+        assert::all_true(
+            r#"
+set([1, 2]) | set([2, 3]) == set([1, 2, 3])
+set([1, 2]) & set([2, 3]) == set([2])
+set([1, 2]) - set([2, 3]) == set([1])
+set([1, 2]) ^ set([2, 3]) == set([1, 3])
+list(set([3, 2]) | set([1, 2])) == [3, 2, 1]
+"#,
+        );
+        assert::fail("set([1]) | [2]", "not supported");
+        assert::fail("set([[1]])", "not hashable");
+    }
+
+    #[test]
+    fn test_set_hash() {
+        assert::fail("{set([1]): 1}", "not hashable");
+        let mut a = assert::Assert::new();
+        a.module("s.star", "s = set([1, 2])\nt = set([2, 1])");
+        a.is_true(
+            r#"
+load("s.star", "s", "t")
+{s: 1}[t] == 1
+"#,
+        );
+        a.fail(
+            r#"
+load("s.star", "s")
+s.add(3)
+"#,
+            "Immutable",
+        );
+    }
+
+    #[test]
+    fn test_set_types() {
+        assert::all_true(
+            r#"
+is_type(set([1, 2]), set.type)
+is_type(set([1, 2]), set[int.type])
+is_type(set([1, 2]), set[int])
+is_type(set(), set[str])
+not is_type(set([1, "a"]), set[int])
+not is_type([1], set[int])
+repr(set[str]) == 'set["string"]'
+"#,
+        );
+        assert::fail(
+            "def f(x: set[str]):\n pass\nf(set([1]))",
+            "does not match the type annotation",
+        );
+    }
+}
@@ -27,6 +27,8 @@ use crate::values::dict::Dict;
 use crate::values::dict::DictRef;
 use crate::values::list::List;
 use crate::values::list::ListRef;
+use crate::values::set::Set;
+use crate::values::set::SetOfType;
 use crate::values::tuple::Tuple;
 use crate::values::tuple::TupleGen;
 use crate::values::Heap;
@@ -184,6 +186,22 @@ impl TypeCompiled {
         TypeCompiled(Box::new(IsListOf(t)))
     }
 
+    fn type_set_of(t: TypeCompiled) -> TypeCompiled {
+        #[derive(Allocative)]
+        struct IsSetOf(TypeCompiled);
+
+        impl TypeCompiledImpl for IsSetOf {
+            fn matches(&self, value: Value) -> bool {
+                match Set::from_value(value) {
+                    None => false,
+                    Some(set) => set.iter().all(|v| self.0.matches(v)),
+                }
+            }
+        }
+
+        TypeCompiled(Box::new(IsSetOf(t)))
+    }
+
     fn type_any_of_two(t1: TypeCompiled, t2: TypeCompiled) -> TypeCompiled {
         #[derive(Allocative)]
         struct IsAnyOfTwo(TypeCompiled, TypeCompiled);
@@ -350,6 +368,10 @@ impl TypeCompiled {
             TypeCompiled::from_list(t, heap)
         } else if let Some(t) = Dict::from_value(ty) {
             TypeCompiled::from_dict(t, heap)
+        } else if let Some(t) = SetOfType::from_value(ty) {
+            Ok(TypeCompiled::type_set_of(TypeCompiled::new(
+                t.element, heap,
+            )?))
         } else {
             Err(invalid_type_annotation(ty, heap).into())
         }
@@ -23,6 +23,8 @@ use std::hash::Hash;
 use std::hash::Hasher;
 
 use allocative::Allocative;
+use gazebo::coerce::Coerce;
+use gazebo::coerce::CoerceKey;
 use gazebo::prelude::*;
 
 use crate::equivalent::Equivalent;
@@ -32,9 +34,12 @@ pub use crate::small_set::iter::IntoIter;
 pub use crate::small_set::iter::Iter;
 
 /// An memory-efficient set with determinstic order, based on [`SmallMap`].
+#[repr(transparent)]
 #[derive(Clone, Default_, Allocative)]
 pub struct SmallSet<T>(SmallMap<T, ()>);
 
+unsafe impl<FromT, ToT> Coerce<SmallSet<ToT>> for SmallSet<FromT> where FromT: CoerceKey<ToT> {}
+
 impl<T: Debug> Debug for SmallSet<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_set().entries(self.iter()).finish()
@@ -91,6 +96,18 @@ impl<T> SmallSet<T> {
         self.into_iter()
     }
 
+    /// Iterate the element references with their hashes.
+    #[inline]
+    pub fn iter_hashed(&self) -> impl ExactSizeIterator<Item = Hashed<&T>> {
+        self.0.iter_hashed().map(|(k, ())| k)
+    }
+
+    /// Iterate the elements with their hashes.
+    #[inline]
+    pub fn into_iter_hashed(self) -> impl ExactSizeIterator<Item = Hashed<T>> {
+        self.0.into_iter_hashed().map(|(k, ())| k)
+    }
+
     /// Insert the element into the set.
     ///
     /// Return `true` iff the element was inserted.
@@ -190,6 +207,18 @@ impl<T> SmallSet<T> {
         self.0.remove(key).is_some()
     }
 
+    /// Remove the element with the given prehashed key from the set if it is present.
+    ///
+    /// Return `true` iff the element was removed.
+    #[inline]
+    pub fn remove_hashed<Q>(&mut self, key: Hashed<&Q>) -> bool
+    where
+        Q: ?Sized + Equivalent<T>,
+        T: Eq,
+    {
+        self.0.remove_hashed(key).is_some()
+    }
+
     /// Insert entry if it doesn't exist.
     ///
     /// Return the resulting entry in the map.
@@ -267,6 +296,35 @@ impl<T> SmallSet<T> {
         self.0.contains_key(key)
     }
 
+    /// Check if the set contains an element, given a prehashed key.
+    #[inline]
+    pub fn contains_hashed<Q>(&self, key: Hashed<&Q>) -> bool
+    where
+        Q: Equivalent<T> + ?Sized,
+        T: Eq,
+    {
+        self.0.contains_key_hashed(key)
+    }
+
+    /// Check if the set contains an element, given a prehashed key by value.
+    #[inline]
+    pub fn contains_hashed_by_value<Q>(&self, key: Hashed<Q>) -> bool
+    where
+        Q: Equivalent<T>,
+        T: Eq,
+    {
+        self.0.contains_key_hashed_by_value(key)
+    }
+
+    /// Reserve capacity for at least `additional` more elements to be inserted.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize)
+    where
+        T: Eq,
+    {
+        self.0.reserve(additional)
+    }
+
     /// Remove all elements from the set.
     ///
     /// Retain the capacity.
@@ -581,6 +639,20 @@ mod tests {
         assert!(!s.remove(&17));
     }
 
+    #[test]
+    fn test_hashed() {
+        let mut s: SmallSet<u32> = SmallSet::from_iter([17, 19]);
+        assert!(s.contains_hashed(Hashed::new(&17)));
+        assert!(s.contains_hashed_by_value(Hashed::new(19)));
+        assert_eq!(
+            vec![Hashed::new(&17), Hashed::new(&19)],
+            Vec::from_iter(s.iter_hashed())
+        );
+        assert!(s.remove_hashed(Hashed::new(&17)));
+        assert!(!s.remove_hashed(Hashed::new(&17)));
+        assert_eq!(vec![Hashed::new(19)], Vec::from_iter(s.into_iter_hashed()));
+    }
+
     #[test]
     fn test_difference() {
         let a = SmallSet::from_iter([1, 2, 3]);
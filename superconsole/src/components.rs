@@ -20,7 +20,10 @@ pub(crate) use canvas::Canvas;
 pub use echo::Echo;
 pub use expanding::Expanding;
 pub use padding::Padded;
+pub use progress_bar::ProgressBar;
+pub use sparkline::Sparkline;
 pub use splitting::Split;
+pub use table::Table;
 
 use crate::content::LinesExt;
 use crate::Dimensions;
@@ -35,7 +38,10 @@ mod canvas;
 mod echo;
 mod expanding;
 pub mod padding;
+pub mod progress_bar;
+pub mod sparkline;
 pub mod splitting;
+pub mod table;
 
 /// Used to mark whether a draw is final.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -0,0 +1,229 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A [`ProgressBar`](ProgressBar) shows how far along some work is, on a single line.
+//! When the total amount of work is known, it draws a bar with an estimate of the time remaining,
+//! otherwise it draws a spinner which advances each time the caller bumps the tick.
+
+use std::time::Duration;
+
+use crate::Component;
+use crate::Dimensions;
+use crate::DrawMode;
+use crate::Line;
+use crate::Lines;
+use crate::Span;
+use crate::State;
+
+const SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// Bars narrower than this convey nothing, so are left out in favour of the counts.
+const MIN_BAR_WIDTH: usize = 5;
+
+/// How far along the work tracked by a [`ProgressBar`](ProgressBar) is.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Progress {
+    /// `done` out of `total` units of work have finished.
+    Determinate { done: u64, total: u64 },
+    /// The amount of work is unknown. The spinner shows a different frame for each `tick`.
+    Indeterminate { tick: u64 },
+}
+
+/// Component that draws a labelled progress bar or spinner.
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    label: Line,
+    progress: Progress,
+    elapsed: Option<Duration>,
+}
+
+impl ProgressBar {
+    pub fn new(label: Line, progress: Progress) -> Self {
+        Self {
+            label,
+            progress,
+            elapsed: None,
+        }
+    }
+
+    /// How long the work has been running. This is shown next to a spinner, and is used
+    /// to estimate the time remaining for a bar.
+    pub fn elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed = Some(elapsed);
+        self
+    }
+
+    /// The time remaining, assuming the remaining work proceeds at the same rate as so far.
+    pub fn eta(&self) -> Option<Duration> {
+        match self.progress {
+            Progress::Determinate { done, total } if done > 0 && done < total => {
+                let elapsed = self.elapsed?;
+                Some(elapsed.mul_f64((total - done) as f64 / done as f64))
+            }
+            _ => None,
+        }
+    }
+
+    fn suffix(&self) -> String {
+        match self.progress {
+            Progress::Determinate { done, total } => {
+                let percent = if total == 0 {
+                    100
+                } else {
+                    done.min(total) * 100 / total
+                };
+                let mut suffix = format!("{}/{} {}%", done, total, percent);
+                if let Some(eta) = self.eta() {
+                    suffix.push_str(&format!(" ETA {}", format_duration(eta)));
+                }
+                suffix
+            }
+            Progress::Indeterminate { tick } => {
+                let spinner = SPINNER[(tick % SPINNER.len() as u64) as usize];
+                match self.elapsed {
+                    Some(elapsed) => format!("{} {}", spinner, format_duration(elapsed)),
+                    None => spinner.to_owned(),
+                }
+            }
+        }
+    }
+}
+
+/// Format a duration compactly, e.g. `12s`, `3m05s` or `1h02m`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Draw a `[===>   ]` bar `width` wide, including the brackets.
+fn bar(done: u64, total: u64, width: usize) -> String {
+    let inner = width - 2;
+    let filled = if total == 0 {
+        inner
+    } else {
+        (inner as u64 * done.min(total) / total) as usize
+    };
+    let head = if filled < inner { ">" } else { "" };
+    format!(
+        "[{}{}{}]",
+        "=".repeat(filled),
+        head,
+        " ".repeat(inner - filled - head.len())
+    )
+}
+
+impl Component for ProgressBar {
+    fn draw_unchecked(
+        &self,
+        _state: &State,
+        dimensions: Dimensions,
+        _mode: DrawMode,
+    ) -> anyhow::Result<Lines> {
+        let suffix = self.suffix();
+        let mut line = self.label.clone();
+        if !line.is_empty() {
+            line.pad_right(1);
+        }
+        if let Progress::Determinate { done, total } = self.progress {
+            let bar_width = dimensions
+                .width
+                .saturating_sub(line.len() + 1 + suffix.len());
+            if bar_width >= MIN_BAR_WIDTH {
+                line.0
+                    .push(Span::new_unstyled(bar(done, total, bar_width))?);
+                line.pad_right(1);
+            }
+        }
+        line.0.push(Span::new_unstyled(suffix)?);
+        Ok(vec![line])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+    use crate::testing::SuperConsoleTestingExt;
+
+    fn draw(progress_bar: &ProgressBar, width: usize) -> anyhow::Result<String> {
+        let lines = progress_bar.draw(
+            &crate::state![],
+            Dimensions::new(width, 1),
+            DrawMode::Normal,
+        )?;
+        Ok(lines[0].to_unstyled())
+    }
+
+    #[test]
+    fn test_determinate() -> anyhow::Result<()> {
+        let progress_bar = ProgressBar::new(
+            Line::unstyled("Actions")?,
+            Progress::Determinate {
+                done: 25,
+                total: 100,
+            },
+        )
+        .elapsed(Duration::from_secs(10));
+        assert_eq!(progress_bar.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(
+            draw(&progress_bar, 40)?,
+            "Actions [==>        ] 25/100 25% ETA 30s"
+        );
+        // Too narrow for a bar, so just the counts are shown.
+        assert_eq!(draw(&progress_bar, 30)?, "Actions 25/100 25% ETA 30s");
+        Ok(())
+    }
+
+    #[test]
+    fn test_complete() -> anyhow::Result<()> {
+        let progress_bar =
+            ProgressBar::new(Line::default(), Progress::Determinate { done: 4, total: 4 })
+                .elapsed(Duration::from_secs(10));
+        assert_eq!(progress_bar.eta(), None);
+        assert_eq!(draw(&progress_bar, 16)?, "[=====] 4/4 100%");
+        Ok(())
+    }
+
+    #[test]
+    fn test_spinner() -> anyhow::Result<()> {
+        let spinner = |tick| ProgressBar::new(Line::default(), Progress::Indeterminate { tick });
+        assert_eq!(draw(&spinner(0), 20)?, "|");
+        assert_eq!(draw(&spinner(1), 20)?, "/");
+        assert_eq!(
+            draw(&spinner(4).elapsed(Duration::from_secs(125)), 20)?,
+            "| 2m05s"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_console() -> anyhow::Result<()> {
+        let progress_bar = ProgressBar::new(
+            Line::unstyled("Downloading")?,
+            Progress::Determinate { done: 1, total: 2 },
+        );
+        let mut console = test_console(Box::new(progress_bar));
+        console.render(&crate::state![])?;
+        let frame = console
+            .test_output_mut()?
+            .frames
+            .pop()
+            .expect("a frame was rendered");
+        assert!(frame_contains(&frame, "Downloading"));
+        assert!(frame_contains(&frame, "1/2 50%"));
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use crate::Component;
+use crate::Dimensions;
+use crate::DrawMode;
+use crate::Line;
+use crate::Lines;
+use crate::Span;
+use crate::State;
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Component that draws a series of values as a single line of bars, e.g. `▁▂▅▇█▃`,
+/// which is handy for showing recent history such as throughput.
+/// When there are more values than fit, the most recent (last) values are shown.
+#[derive(Debug, Clone)]
+pub struct Sparkline {
+    label: Option<Line>,
+    values: Vec<f64>,
+    max: Option<f64>,
+}
+
+impl Sparkline {
+    pub fn new(values: Vec<f64>) -> Self {
+        Self {
+            label: None,
+            values,
+            max: None,
+        }
+    }
+
+    /// Text shown before the bars.
+    pub fn label(mut self, label: Line) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// The value drawn as a full bar. Defaults to the largest value shown, but a fixed
+    /// maximum keeps the scale from jumping around as values come and go.
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    fn bars(&self, width: usize) -> String {
+        let shown = &self.values[self.values.len().saturating_sub(width)..];
+        let max = self
+            .max
+            .unwrap_or_else(|| shown.iter().copied().fold(0.0, f64::max));
+        shown
+            .iter()
+            .map(|v| {
+                if max <= 0.0 || !v.is_finite() {
+                    return BLOCKS[0];
+                }
+                let level = (v / max * (BLOCKS.len() - 1) as f64).round();
+                BLOCKS[level.clamp(0.0, (BLOCKS.len() - 1) as f64) as usize]
+            })
+            .collect()
+    }
+}
+
+impl Component for Sparkline {
+    fn draw_unchecked(
+        &self,
+        _state: &State,
+        dimensions: Dimensions,
+        _mode: DrawMode,
+    ) -> anyhow::Result<Lines> {
+        let mut line = self.label.clone().unwrap_or_default();
+        if !line.is_empty() {
+            line.pad_right(1);
+        }
+        let width = dimensions.width.saturating_sub(line.len());
+        line.0.push(Span::new_unstyled(self.bars(width))?);
+        Ok(vec![line])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+    use crate::testing::SuperConsoleTestingExt;
+
+    fn draw(sparkline: &Sparkline, width: usize) -> anyhow::Result<String> {
+        let lines = sparkline.draw(
+            &crate::state![],
+            Dimensions::new(width, 1),
+            DrawMode::Normal,
+        )?;
+        Ok(lines[0].to_unstyled())
+    }
+
+    #[test]
+    fn test_scaling() -> anyhow::Result<()> {
+        let sparkline = Sparkline::new(vec![0.0, 1.0, 2.0, 7.0]);
+        assert_eq!(draw(&sparkline, 10)?, "▁▂▃█");
+        assert_eq!(draw(&sparkline.clone().max(14.0), 10)?, "▁▂▂▅");
+        // Nothing to scale against.
+        assert_eq!(draw(&Sparkline::new(vec![0.0, 0.0]), 10)?, "▁▁");
+        Ok(())
+    }
+
+    #[test]
+    fn test_most_recent() -> anyhow::Result<()> {
+        let sparkline = Sparkline::new(vec![7.0, 7.0, 0.0, 7.0]).label(Line::unstyled("RE")?);
+        assert_eq!(draw(&sparkline, 5)?, "RE ▁█");
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_console() -> anyhow::Result<()> {
+        let mut console = test_console(Box::new(Sparkline::new(vec![1.0, 2.0])));
+        console.render(&crate::state![])?;
+        let frame = console
+            .test_output_mut()?
+            .frames
+            .pop()
+            .expect("a frame was rendered");
+        assert!(frame_contains(&frame, "▅█"));
+        Ok(())
+    }
+}
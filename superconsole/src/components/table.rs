@@ -0,0 +1,317 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A [`Table`](Table) lays out rows of cells in columns.
+//! Each column is as wide as its widest cell, unless the table doesn't fit, in which case
+//! the widest columns are shrunk first and their cells truncated with an ellipsis.
+//!
+//! Like most content, a table is typically built by a parent component's `draw` from the current state.
+
+use crate::Component;
+use crate::Dimensions;
+use crate::DrawMode;
+use crate::Line;
+use crate::Lines;
+use crate::Span;
+use crate::State;
+
+const ELLIPSIS: &str = "...";
+
+/// Which side of a column its cells are aligned to.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ColumnAlignment {
+    Left,
+    Right,
+}
+
+/// Describes one column of a [`Table`](Table).
+#[derive(Debug, Clone)]
+pub struct Column {
+    header: Option<Line>,
+    alignment: ColumnAlignment,
+    min_width: usize,
+    max_width: Option<usize>,
+}
+
+impl Default for Column {
+    fn default() -> Self {
+        Self {
+            header: None,
+            alignment: ColumnAlignment::Left,
+            min_width: 0,
+            max_width: None,
+        }
+    }
+}
+
+impl Column {
+    /// A left aligned column without a header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The header shown above the column. If no column has a header, no header row is drawn.
+    pub fn header(mut self, header: Line) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    pub fn alignment(mut self, alignment: ColumnAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// The column is never made narrower than this, even if the table then overflows.
+    pub fn min_width(mut self, min_width: usize) -> Self {
+        self.min_width = min_width;
+        self
+    }
+
+    /// The column is never made wider than this, even if there is space available.
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+}
+
+/// Component that draws rows of cells aligned in columns.
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<Line>>,
+    spacing: usize,
+}
+
+impl Table {
+    /// Create an empty table with the given columns, separated by a single space.
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+            spacing: 1,
+        }
+    }
+
+    /// The number of spaces between adjacent columns.
+    pub fn spacing(mut self, spacing: usize) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Add a row. Missing cells are left blank, and cells beyond the last column are ignored.
+    pub fn push_row(&mut self, row: Vec<Line>) {
+        self.rows.push(row);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn has_header(&self) -> bool {
+        self.columns.iter().any(|c| c.header.is_some())
+    }
+
+    /// The width each column is given when the table is drawn `width` wide.
+    pub fn column_widths(&self, width: usize) -> Vec<usize> {
+        let natural: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let widest = column
+                    .header
+                    .iter()
+                    .chain(self.rows.iter().filter_map(|row| row.get(i)))
+                    .map(Line::len)
+                    .max()
+                    .unwrap_or(0);
+                let widest = widest.max(column.min_width);
+                match column.max_width {
+                    Some(max_width) => widest.min(max_width.max(column.min_width)),
+                    None => widest,
+                }
+            })
+            .collect();
+
+        let available = width.saturating_sub(self.spacing * self.columns.len().saturating_sub(1));
+        if natural.iter().sum::<usize>() <= available {
+            return natural;
+        }
+
+        // Give every column an equal share, with columns narrower than their share
+        // handing what they don't need back to the others.
+        let mut widths = vec![0; natural.len()];
+        let mut pending: Vec<usize> = (0..natural.len()).collect();
+        pending.sort_by_key(|i| natural[*i]);
+        let mut remaining = available;
+        while let Some(&narrowest) = pending.first() {
+            let share = remaining / pending.len();
+            if natural[narrowest] <= share {
+                widths[narrowest] = natural[narrowest];
+                remaining -= natural[narrowest];
+                pending.remove(0);
+            } else {
+                let extra = remaining % pending.len();
+                pending.sort_unstable();
+                for (k, i) in pending.into_iter().enumerate() {
+                    widths[i] = share + usize::from(k < extra);
+                }
+                break;
+            }
+        }
+
+        for (width, column) in widths.iter_mut().zip(&self.columns) {
+            *width = (*width).max(column.min_width);
+        }
+        widths
+    }
+
+    fn draw_row(&self, cells: &[Line], widths: &[usize]) -> Line {
+        let mut line = Line::default();
+        for (i, (column, width)) in self.columns.iter().zip(widths).enumerate() {
+            if i > 0 {
+                line.pad_right(self.spacing);
+            }
+            let cell = cells.get(i).cloned().unwrap_or_default();
+            line.0.extend(fit(cell, *width, column.alignment).0);
+        }
+        line
+    }
+}
+
+/// Truncate or pad `cell` to be exactly `width` wide.
+fn fit(mut cell: Line, width: usize, alignment: ColumnAlignment) -> Line {
+    let len = cell.len();
+    if len > width {
+        if width > ELLIPSIS.len() {
+            // Keep the style of the text we are cutting off.
+            let style = cell.0.last().map(|span| span.stylization);
+            cell.truncate_line(width - ELLIPSIS.len());
+            let mut ellipsis = Span::new_unstyled_lossy(ELLIPSIS);
+            if let Some(style) = style {
+                ellipsis.stylization = style;
+            }
+            cell.0.push(ellipsis);
+        } else {
+            cell.truncate_line(width);
+        }
+        return cell;
+    }
+    match alignment {
+        ColumnAlignment::Left => cell.pad_right(width - len),
+        ColumnAlignment::Right => cell.pad_left(width - len),
+    }
+    cell
+}
+
+impl Component for Table {
+    fn draw_unchecked(
+        &self,
+        _state: &State,
+        dimensions: Dimensions,
+        _mode: DrawMode,
+    ) -> anyhow::Result<Lines> {
+        let widths = self.column_widths(dimensions.width);
+        let header = if self.has_header() {
+            let cells: Vec<Line> = self
+                .columns
+                .iter()
+                .map(|c| c.header.clone().unwrap_or_default())
+                .collect();
+            Some(self.draw_row(&cells, &widths))
+        } else {
+            None
+        };
+        Ok(header
+            .into_iter()
+            .chain(self.rows.iter().map(|row| self.draw_row(row, &widths)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+    use crate::testing::SuperConsoleTestingExt;
+
+    fn table() -> anyhow::Result<Table> {
+        let mut table = Table::new(vec![
+            Column::new().header(Line::unstyled("name")?),
+            Column::new()
+                .header(Line::unstyled("time")?)
+                .alignment(ColumnAlignment::Right),
+        ]);
+        table.push_row(vec![
+            Line::unstyled("compile foo.c")?,
+            Line::unstyled("1.5s")?,
+        ]);
+        table.push_row(vec![Line::unstyled("link")?, Line::unstyled("12.0s")?]);
+        Ok(table)
+    }
+
+    #[test]
+    fn test_natural_widths() -> anyhow::Result<()> {
+        let table = table()?;
+        assert_eq!(table.column_widths(80), vec![13, 5]);
+        let output = table.draw(&crate::state![], Dimensions::new(80, 10), DrawMode::Normal)?;
+        let expected: Lines = vec![
+            vec!["name         ", " ", " time"].try_into()?,
+            vec!["compile foo.c", " ", " 1.5s"].try_into()?,
+            vec!["link         ", " ", "12.0s"].try_into()?,
+        ];
+        assert_eq!(output, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncation() -> anyhow::Result<()> {
+        let table = table()?;
+        // The narrow time column keeps its width, the name column is shrunk.
+        assert_eq!(table.column_widths(14), vec![8, 5]);
+        let output = table.draw(&crate::state![], Dimensions::new(14, 10), DrawMode::Normal)?;
+        assert_eq!(output[1], vec!["compi...", " ", " 1.5s"].try_into()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_width_limits() -> anyhow::Result<()> {
+        let mut table =
+            Table::new(vec![Column::new().max_width(4), Column::new().min_width(6)]).spacing(2);
+        table.push_row(vec![Line::unstyled("abcdefgh")?]);
+        assert_eq!(table.column_widths(80), vec![4, 6]);
+        // Columns are shared evenly when both need more than their share.
+        let mut table = Table::new(vec![Column::new(), Column::new()]);
+        table.push_row(vec![
+            Line::unstyled(&"a".repeat(20))?,
+            Line::unstyled(&"b".repeat(20))?,
+        ]);
+        assert_eq!(table.column_widths(12), vec![6, 5]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_console() -> anyhow::Result<()> {
+        let mut console = test_console(Box::new(table()?));
+        console.render(&crate::state![])?;
+        let frame = console
+            .test_output_mut()?
+            .frames
+            .pop()
+            .expect("a frame was rendered");
+        assert!(frame_contains(&frame, "compile foo.c"));
+        assert!(frame_contains(&frame, "12.0s"));
+        Ok(())
+    }
+}